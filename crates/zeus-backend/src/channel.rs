@@ -0,0 +1,303 @@
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use crate::types::{Request, RequestKey, Response};
+
+/// Create the channel the backend sends its [Response]s on
+///
+/// Every response goes either on the priority or the bulk channel depending on [Response::is_priority],
+/// so a flood of balances never delays a response the UI is waiting on
+pub fn response_channel() -> (ResponseSender, ResponseReceiver) {
+    let (priority_sender, priority) = unbounded();
+    let (bulk_sender, bulk) = unbounded();
+
+    let sender = ResponseSender {
+        priority: priority_sender,
+        bulk: bulk_sender,
+    };
+    (sender, ResponseReceiver { priority, bulk })
+}
+
+/// The backend end of [response_channel]
+#[derive(Clone)]
+pub struct ResponseSender {
+    priority: Sender<Response>,
+    bulk: Sender<Response>,
+}
+
+impl ResponseSender {
+    /// Send `response` on the channel matching its priority
+    pub fn send(&self, response: Response) -> Result<(), anyhow::Error> {
+        if response.is_priority() {
+            self.priority.send(response)?;
+        } else {
+            self.bulk.send(response)?;
+        }
+        Ok(())
+    }
+}
+
+/// How many responses a frame handles at most, see [ResponseReceiver::drain_within]
+pub const MAX_RESPONSES_PER_FRAME: usize = 200;
+
+/// How long a frame spends handling responses at most
+pub const MAX_RESPONSE_TIME_PER_FRAME: Duration = Duration::from_millis(2);
+
+/// The frontend end of [response_channel]
+#[derive(Clone)]
+pub struct ResponseReceiver {
+    priority: Receiver<Response>,
+    bulk: Receiver<Response>,
+}
+
+impl ResponseReceiver {
+    /// Pass the queued responses to `handle`, priority first, until `max_responses` were handled or
+    /// `max_time` has passed
+    ///
+    /// Returns how many were handled, whatever is left waits for the next call
+    pub fn drain_within(&self, max_responses: usize, max_time: Duration, mut handle: impl FnMut(Response)) -> usize {
+        let start = Instant::now();
+        let mut handled = 0;
+
+        while handled < max_responses && start.elapsed() < max_time {
+            let Ok(response) = self.priority.try_recv().or_else(|_| self.bulk.try_recv()) else {
+                break;
+            };
+            handle(response);
+            handled += 1;
+        }
+        handled
+    }
+
+    /// Are there no responses waiting
+    pub fn is_empty(&self) -> bool {
+        self.priority.is_empty() && self.bulk.is_empty()
+    }
+}
+
+/// The polled requests that were sent and not answered yet, see [Request::coalesce_keys]
+#[derive(Default)]
+pub struct PendingRequests {
+    keys: HashSet<RequestKey>,
+}
+
+impl PendingRequests {
+    /// Mark `request` as pending, `false` if it should be dropped since everything it asks for already is
+    ///
+    /// Requests that are not polled are always sent
+    pub fn admit(&mut self, request: &Request) -> bool {
+        let keys = request.coalesce_keys();
+        if keys.is_empty() {
+            return true;
+        }
+        // every key is inserted, some of a batch may be new
+        let mut admitted = false;
+        for key in keys {
+            admitted |= self.keys.insert(key);
+        }
+        admitted
+    }
+
+    /// Forget the requests `response` answers
+    ///
+    /// An eth balance does not say which request it answers, only one is pending at a time
+    pub fn complete(&mut self, response: &Response) {
+        match response {
+            Response::EthBalance(_) => self.keys.retain(|key| !matches!(key, RequestKey::EthBalance(..))),
+            Response::ERC20Balance(res) => self.keys.retain(|key| {
+                !matches!(key, RequestKey::ERC20Balance(chain_id, owner, token, _)
+                    if *chain_id == res.chain_id && *owner == res.owner && *token == res.token)
+            }),
+            Response::BalancesBatch { chain_id, owner, balances } => self.keys.retain(|key| {
+                !matches!(key, RequestKey::ERC20Balance(key_chain, key_owner, token, _)
+                    if key_chain == chain_id && key_owner == owner && balances.iter().any(|(address, _)| address == token))
+            }),
+            _ => {}
+        }
+    }
+
+    /// Forget the requests of other chains or of blocks before `block`, they failed or a newer one replaced them
+    pub fn prune(&mut self, chain_id: u64, block: u64) {
+        self.keys.retain(|key| key.chain_id() == chain_id && key.block() >= block);
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ERC20BalanceRes;
+    use std::sync::Arc;
+    use zeus_chain::{alloy::primitives::{Address, U256}, test_node::TestNode, ChainId, ERC20Token, WsClient};
+
+    const OWNER: Address = Address::repeat_byte(1);
+    const TOKEN: Address = Address::repeat_byte(2);
+    const OTHER_TOKEN: Address = Address::repeat_byte(3);
+
+    async fn client() -> Arc<WsClient> {
+        Arc::new(TestNode::empty_chain().client().await)
+    }
+
+    fn token(address: Address) -> ERC20Token {
+        ERC20Token { chain_id: 1, address, ..Default::default() }
+    }
+
+    /// Send the polled requests of a frame through [PendingRequests::admit] like the app does, returns how many were sent
+    fn send_frame(pending: &mut PendingRequests, requests: &[Request]) -> usize {
+        requests.iter().filter(|request| pending.admit(request)).count()
+    }
+
+    #[tokio::test]
+    async fn identical_requests_in_a_frame_are_sent_once() {
+        let client = client().await;
+        let mut pending = PendingRequests::default();
+        let balance = || Request::eth_balance(OWNER, 1, 100, false, client.clone());
+
+        assert_eq!(send_frame(&mut pending, &[balance(), balance()]), 1);
+        // still pending on the next frame
+        assert_eq!(send_frame(&mut pending, &[balance()]), 0);
+        // a forced refresh is always sent
+        assert_eq!(send_frame(&mut pending, &[Request::eth_balance(OWNER, 1, 100, true, client.clone())]), 1);
+
+        pending.complete(&Response::eth_balance(U256::from(1)));
+        assert_eq!(send_frame(&mut pending, &[balance()]), 1);
+    }
+
+    #[tokio::test]
+    async fn a_polled_batch_of_balances_is_sent_once() {
+        let client = client().await;
+        let mut pending = PendingRequests::default();
+        let batch = || Request::erc20_balances(vec![token(TOKEN), token(OTHER_TOKEN)], OWNER, 1, 100, client.clone());
+
+        assert!(pending.admit(&batch()));
+        assert!(!pending.admit(&batch()));
+        // a single token of the batch is pending as well
+        assert!(!pending.admit(&Request::erc20_balance(token(TOKEN), OWNER, 1, 100, false, client.clone())));
+
+        pending.complete(&Response::balances_batch(1, OWNER, vec![(TOKEN, U256::from(1)), (OTHER_TOKEN, U256::from(2))]));
+        assert!(pending.is_empty());
+        assert!(pending.admit(&batch()));
+    }
+
+    #[tokio::test]
+    async fn a_batch_is_sent_if_one_of_its_tokens_is_not_pending() {
+        let client = client().await;
+        let mut pending = PendingRequests::default();
+
+        assert!(pending.admit(&Request::erc20_balance(token(TOKEN), OWNER, 1, 100, false, client.clone())));
+        assert!(pending.admit(&Request::erc20_balances(vec![token(TOKEN), token(OTHER_TOKEN)], OWNER, 1, 100, client.clone())));
+        assert_eq!(pending.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_balance_response_only_completes_its_token() {
+        let client = client().await;
+        let mut pending = PendingRequests::default();
+        let balance = |token_address| Request::erc20_balance(token(token_address), OWNER, 1, 100, false, client.clone());
+        send_frame(&mut pending, &[balance(TOKEN), balance(OTHER_TOKEN)]);
+
+        pending.complete(&Response::ERC20Balance(ERC20BalanceRes {
+            owner: OWNER,
+            token: TOKEN,
+            balance: U256::from(5),
+            chain_id: 1,
+        }));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(send_frame(&mut pending, &[balance(TOKEN), balance(OTHER_TOKEN)]), 1);
+    }
+
+    #[tokio::test]
+    async fn requests_of_older_blocks_and_other_chains_are_pruned() {
+        let client = client().await;
+        let mut pending = PendingRequests::default();
+        let balance = |chain_id, block| Request::eth_balance(OWNER, chain_id, block, false, client.clone());
+        send_frame(&mut pending, &[balance(1, 100), balance(1, 101), balance(8453, 500)]);
+
+        pending.prune(1, 101);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(send_frame(&mut pending, &[balance(1, 100), balance(1, 101)]), 1);
+    }
+
+    /// Handle the queued responses frame by frame with the budgets of the app
+    fn handle_frames(receiver: &ResponseReceiver) -> Vec<Response> {
+        let mut responses = Vec::new();
+        while !receiver.is_empty() {
+            receiver.drain_within(MAX_RESPONSES_PER_FRAME, MAX_RESPONSE_TIME_PER_FRAME, |res| responses.push(res));
+        }
+        responses
+    }
+
+    #[test]
+    fn a_chain_switch_is_handled_ahead_of_queued_balances() {
+        let (sender, receiver) = response_channel();
+
+        for balance in 0..100 {
+            sender.send(Response::eth_balance(U256::from(balance))).unwrap();
+        }
+        sender.send(Response::client(Err("timeout".to_string()), ChainId::from_id(8453).unwrap())).unwrap();
+
+        let responses = handle_frames(&receiver);
+        assert_eq!(responses.len(), 101);
+        assert!(matches!(responses[0], Response::Client(..)));
+        assert!(responses[1..].iter().all(|res| matches!(res, Response::EthBalance(_))));
+    }
+
+    #[test]
+    fn a_burst_is_handled_within_a_few_frames() {
+        let (sender, receiver) = response_channel();
+        for balance in 0..500 {
+            sender.send(Response::eth_balance(U256::from(balance))).unwrap();
+        }
+
+        let mut frames = 0;
+        let mut handled = 0;
+        while !receiver.is_empty() {
+            handled += receiver.drain_within(MAX_RESPONSES_PER_FRAME, Duration::from_secs(1), |_| {});
+            frames += 1;
+        }
+        assert_eq!(handled, 500);
+        assert_eq!(frames, 3);
+    }
+
+    #[test]
+    fn a_slow_handler_stops_at_the_time_budget() {
+        let (sender, receiver) = response_channel();
+        for balance in 0..10 {
+            sender.send(Response::eth_balance(U256::from(balance))).unwrap();
+        }
+
+        let handled = receiver.drain_within(MAX_RESPONSES_PER_FRAME, MAX_RESPONSE_TIME_PER_FRAME, |_| {
+            std::thread::sleep(Duration::from_millis(5));
+        });
+        assert_eq!(handled, 1);
+        assert!(!receiver.is_empty());
+    }
+
+    #[test]
+    fn bulk_responses_keep_their_order() {
+        let (sender, receiver) = response_channel();
+
+        for balance in 0..3 {
+            sender.send(Response::eth_balance(U256::from(balance))).unwrap();
+        }
+
+        let balances: Vec<_> = handle_frames(&receiver)
+            .into_iter()
+            .map(|res| match res {
+                Response::EthBalance(balance) => balance.to::<u64>(),
+                _ => panic!("unexpected response"),
+            })
+            .collect();
+        assert_eq!(balances, vec![0, 1, 2]);
+    }
+}
@@ -2,29 +2,102 @@ use r2d2::{Pool as connPool, PooledConnection};
 use r2d2_sqlite::{rusqlite::params, SqliteConnectionManager};
 
 use anyhow::anyhow;
-use std::{collections::HashMap, path::PathBuf, str::FromStr};
+use std::{collections::{HashMap, HashSet}, path::PathBuf, str::FromStr};
 use tracing::{error, info, trace};
 use zeus_chain::{
     alloy::primitives::{Address, U256},
     Currency, ERC20Token, Pool, PoolVariant,
 };
 
+use crate::types::{PriceWatch, WatchCondition};
+
+/// Set in the `Meta` table once the default tokens were inserted, see [ZeusDB::insert_default_once]
+const DEFAULT_TOKENS_KEY: &str = "default_tokens_inserted";
+
+/// How many tokens a page of [ZeusDB::load_currency_page] has at most
+pub const CURRENCY_PAGE_SIZE: usize = 100;
+
+/// How many eth balance points we keep per wallet and chain
+pub const BALANCE_HISTORY_POINTS: usize = 500;
+
+/// The latest points are all kept, older ones are downsampled, see [balance_blocks_to_remove]
+pub const RECENT_BALANCE_POINTS: usize = 100;
+
+/// Which of the sampled `blocks` (sorted ascending) to remove to stay within [BALANCE_HISTORY_POINTS]
+///
+/// The last [RECENT_BALANCE_POINTS] are kept as is. Before them we keep one point per `N` blocks,
+/// with `N` picked so the older points fit in what is left
+pub fn balance_blocks_to_remove(blocks: &[u64]) -> Vec<u64> {
+    if blocks.len() <= BALANCE_HISTORY_POINTS {
+        return Vec::new();
+    }
+
+    let (older, recent) = blocks.split_at(blocks.len() - RECENT_BALANCE_POINTS);
+    let budget = (BALANCE_HISTORY_POINTS - RECENT_BALANCE_POINTS) as u64;
+    let first = older[0];
+    let span = recent[0] - first;
+    let step = span.div_ceil(budget).max(1);
+
+    // keep the first point of every `step` blocks
+    let mut last_bucket = None;
+    older
+        .iter()
+        .filter(|block| {
+            let bucket = (**block - first) / step;
+            let keep = last_bucket != Some(bucket);
+            last_bucket = Some(bucket);
+            !keep
+        })
+        .copied()
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct ZeusDB {
     pub erc20_tokens: connPool<SqliteConnectionManager>,
     pub pools: connPool<SqliteConnectionManager>,
     pub erc20_balance: connPool<SqliteConnectionManager>,
     pub eth_balance: connPool<SqliteConnectionManager>,
+    pub watches: connPool<SqliteConnectionManager>,
+    pub contracts: connPool<SqliteConnectionManager>,
 }
 
 impl ZeusDB {
     pub fn new() -> Result<Self, anyhow::Error> {
-        let db_path = PathBuf::from("db");
+        Self::open(PathBuf::from("db"))
+    }
 
+    /// Open the databases in `db_path`, creating the directory and the tables if needed
+    pub fn open(db_path: PathBuf) -> Result<Self, anyhow::Error> {
         std::fs::create_dir_all(&db_path)?;
+        Self::create(Some(db_path))
+    }
 
-        let erc20_manager = SqliteConnectionManager::file(db_path.join("erc20.db"));
-        let erc20_conn = connPool::builder().build(erc20_manager)?;
+    /// Databases that only live in memory, used when the data directory is read-only
+    ///
+    /// They start with the default tokens so the token list is not empty
+    pub fn in_memory() -> Result<Self, anyhow::Error> {
+        let db = Self::create(None)?;
+        db.insert_default()?;
+        Ok(db)
+    }
+
+    /// A connection pool to `file` in `db_path`, or to an in-memory database if `db_path` is `None`
+    fn pool(db_path: &Option<PathBuf>, file: &str) -> Result<connPool<SqliteConnectionManager>, anyhow::Error> {
+        match db_path {
+            Some(db_path) => Ok(connPool::builder().build(SqliteConnectionManager::file(db_path.join(file)))?),
+            // every connection to `:memory:` is a new database, so we keep a single one open for good
+            None => Ok(connPool::builder()
+                .max_size(1)
+                .max_lifetime(None)
+                .idle_timeout(None)
+                .build(SqliteConnectionManager::memory())?),
+        }
+    }
+
+    /// Create the tables that don't exist yet
+    fn create(db_path: Option<PathBuf>) -> Result<Self, anyhow::Error> {
+        let erc20_conn = Self::pool(&db_path, "erc20.db")?;
 
         {
             let conn = erc20_conn.get()?;
@@ -37,14 +110,37 @@ impl ZeusDB {
                           name         TEXT NOT NULL,
                           decimals         INTEGER NOT NULL,
                           total_supply         TEXT NOT NULL,
+                          pinned             INTEGER NOT NULL DEFAULT 0,
+                          last_refreshed     INTEGER NOT NULL DEFAULT 0,
                           UNIQUE(chain_id, address)
                           )",
                 [],
             )?;
+            // tables created before tokens could be pinned
+            let has_pinned = conn
+                .prepare("SELECT 1 FROM pragma_table_info('ERC20Token') WHERE name = 'pinned'")?
+                .exists([])?;
+            if !has_pinned {
+                conn.execute("ALTER TABLE ERC20Token ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0", [])?;
+            }
+            // the tokens stored before we kept track are refreshed first, see [ZeusDB::stale_erc20]
+            let has_last_refreshed = conn
+                .prepare("SELECT 1 FROM pragma_table_info('ERC20Token') WHERE name = 'last_refreshed'")?
+                .exists([])?;
+            if !has_last_refreshed {
+                conn.execute("ALTER TABLE ERC20Token ADD COLUMN last_refreshed INTEGER NOT NULL DEFAULT 0", [])?;
+            }
+            // one-off bookkeeping, eg. which startup steps already ran
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS Meta (
+                          key             TEXT PRIMARY KEY,
+                          value           TEXT NOT NULL
+                          )",
+                [],
+            )?;
         }
 
-        let pools_manager = SqliteConnectionManager::file(db_path.join("pools.db"));
-        let pools_conn = connPool::builder().build(pools_manager)?;
+        let pools_conn = Self::pool(&db_path, "pools.db")?;
 
         {
             let conn = pools_conn.get()?;
@@ -63,8 +159,7 @@ impl ZeusDB {
             )?;
         }
 
-        let erc20_balance_manager = SqliteConnectionManager::file(db_path.join("erc20_balance.db"));
-        let erc20_balance_conn = connPool::builder().build(erc20_balance_manager)?;
+        let erc20_balance_conn = Self::pool(&db_path, "erc20_balance.db")?;
 
         {
             let conn = erc20_balance_conn.get()?;
@@ -82,8 +177,7 @@ impl ZeusDB {
             )?;
         }
 
-        let eth_balance_manager = SqliteConnectionManager::file(db_path.join("eth_balance.db"));
-        let eth_balance_conn = connPool::builder().build(eth_balance_manager)?;
+        let eth_balance_conn = Self::pool(&db_path, "eth_balance.db")?;
 
         {
             let conn = eth_balance_conn.get()?;
@@ -100,11 +194,60 @@ impl ZeusDB {
             )?;
         }
 
+        let watches_conn = Self::pool(&db_path, "watches.db")?;
+
+        {
+            let conn = watches_conn.get()?;
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS PriceWatch (
+                          id              INTEGER PRIMARY KEY,
+                          chain_id         INTEGER NOT NULL,
+                          token_in            TEXT NOT NULL,
+                          token_in_symbol            TEXT NOT NULL,
+                          token_in_decimals         INTEGER NOT NULL,
+                          token_out            TEXT NOT NULL,
+                          token_out_symbol            TEXT NOT NULL,
+                          token_out_decimals         INTEGER NOT NULL,
+                          condition         INTEGER NOT NULL,
+                          target             REAL NOT NULL
+                          )",
+                [],
+            )?;
+        }
+
+        let contracts_conn = Self::pool(&db_path, "contracts.db")?;
+
+        {
+            let conn = contracts_conn.get()?;
+            // only addresses that have code, an EOA can still get code later
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS Contract (
+                          id              INTEGER PRIMARY KEY,
+                          chain_id         INTEGER NOT NULL,
+                          address            TEXT NOT NULL,
+                          UNIQUE(chain_id, address)
+                          )",
+                [],
+            )?;
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS AddressLabel (
+                          id              INTEGER PRIMARY KEY,
+                          chain_id         INTEGER NOT NULL,
+                          address            TEXT NOT NULL,
+                          label             TEXT NOT NULL,
+                          UNIQUE(chain_id, address)
+                          )",
+                [],
+            )?;
+        }
+
         Ok(Self {
             erc20_tokens: erc20_conn,
             pools: pools_conn,
             erc20_balance: erc20_balance_conn,
             eth_balance: eth_balance_conn,
+            watches: watches_conn,
+            contracts: contracts_conn,
         })
     }
 
@@ -148,6 +291,8 @@ impl ZeusDB {
     }
 
     /// Insert the eth balance of a given address at a given block for a given chain
+    ///
+    /// A balance already stored for the block is replaced, eg. by a forced refresh
     pub fn insert_eth_balance(
         &self,
         address: Address,
@@ -157,34 +302,113 @@ impl ZeusDB {
     ) -> Result<(), anyhow::Error> {
         let conn = self.eth_balance.get()?;
         conn.execute(
-            "INSERT INTO ETHBalance (chain_id, block_number, address, balance) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT OR REPLACE INTO ETHBalance (chain_id, block_number, address, balance) VALUES (?1, ?2, ?3, ?4)",
             params![chain_id, block, address.to_string(), balance.to_string()],
         )?;
+        // an in-memory database has a single connection
+        drop(conn);
 
-        // remove any old balances < block
-        if let Err(e) = self.remove_eth_balance(block, chain_id) {
-            error!("Error removing old eth balances: {:?}", e);
+        // keep a bounded history instead of only the latest balance
+        if let Err(e) = self.prune_eth_balance_history(address, chain_id) {
+            error!("Error pruning the eth balance history: {:?}", e);
         }
 
         Ok(())
     }
 
-    /// Remove old eth balances from a given block for a given chain
-    pub fn remove_eth_balance(&self, block: u64, chain_id: u64) -> Result<(), anyhow::Error> {
+    /// Get the eth balance of an address at exactly `block`, `None` if we never fetched it
+    pub fn get_eth_balance_at_block(
+        &self,
+        address: Address,
+        chain_id: u64,
+        block: u64,
+    ) -> Result<Option<U256>, anyhow::Error> {
+        let conn = self.eth_balance.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT balance FROM ETHBalance WHERE address = ?1 AND chain_id = ?2 AND block_number = ?3",
+        )?;
+        let mut rows = stmt.query(params![address.to_string(), chain_id, block])?;
+
+        if let Some(row) = rows.next()? {
+            let balance: String = row.get(0)?;
+            Ok(Some(U256::from_str(&balance)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Cache a historical eth balance
+    ///
+    /// Unlike [ZeusDB::insert_eth_balance] this does not remove the older balances
+    pub fn insert_historical_eth_balance(
+        &self,
+        address: Address,
+        balance: U256,
+        chain_id: u64,
+        block: u64,
+    ) -> Result<(), anyhow::Error> {
         let conn = self.eth_balance.get()?;
         conn.execute(
-            "DELETE FROM ETHBalance WHERE block_number < ?1 AND chain_id = ?2",
-            params![block, chain_id],
+            "INSERT OR IGNORE INTO ETHBalance (chain_id, block_number, address, balance) VALUES (?1, ?2, ?3, ?4)",
+            params![chain_id, block, address.to_string(), balance.to_string()],
         )?;
         Ok(())
     }
 
+    /// Downsample the eth balance history of a wallet, see [balance_blocks_to_remove]
+    pub fn prune_eth_balance_history(&self, address: Address, chain_id: u64) -> Result<(), anyhow::Error> {
+        let mut conn = self.eth_balance.get()?;
+        let blocks = {
+            let mut stmt = conn.prepare(
+                "SELECT block_number FROM ETHBalance WHERE address = ?1 AND chain_id = ?2 ORDER BY block_number",
+            )?;
+            let blocks = stmt
+                .query_map(params![address.to_string(), chain_id], |row| row.get::<_, u64>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            blocks
+        };
+
+        let remove = balance_blocks_to_remove(&blocks);
+        if remove.is_empty() {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        for block in &remove {
+            tx.execute(
+                "DELETE FROM ETHBalance WHERE address = ?1 AND chain_id = ?2 AND block_number = ?3",
+                params![address.to_string(), chain_id, block],
+            )?;
+        }
+        tx.commit()?;
+        trace!("Pruned {} eth balance points of {}", remove.len(), address);
+        Ok(())
+    }
+
+    /// The sampled eth balances of a wallet, oldest first
+    pub fn load_eth_balance_history(&self, address: Address, chain_id: u64) -> Result<Vec<(u64, U256)>, anyhow::Error> {
+        let conn = self.eth_balance.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT block_number, balance FROM ETHBalance WHERE address = ?1 AND chain_id = ?2 ORDER BY block_number",
+        )?;
+        let mut rows = stmt.query(params![address.to_string(), chain_id])?;
+
+        let mut history = Vec::new();
+        while let Some(row) = rows.next()? {
+            let block: u64 = row.get(0)?;
+            let balance: String = row.get(1)?;
+            history.push((block, U256::from_str(&balance)?));
+        }
+        Ok(history)
+    }
+
     /// Insert a new [ERC20Token] into the database
     pub fn insert_erc20(&self, token: ERC20Token, chain_id: u64) -> Result<(), anyhow::Error> {
         let time = std::time::Instant::now();
         let conn = self.get_erc20_conn()?;
         conn.execute(
-            "INSERT INTO ERC20Token (chain_id, address, symbol, name, decimals, total_supply) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO ERC20Token (chain_id, address, symbol, name, decimals, total_supply, last_refreshed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, unixepoch())",
             params![
                 chain_id,
                 token.address.to_string(),
@@ -218,7 +442,7 @@ impl ZeusDB {
     /// Get the [ERC20Token] from the given address and chain_id
     pub fn get_erc20(&self, address: Address, chain_id: u64) -> Result<ERC20Token, anyhow::Error> {
         let conn = self.get_erc20_conn()?;
-        let mut stmt = conn.prepare("SELECT * FROM ERC20Token WHERE address = ?1, ?2")?;
+        let mut stmt = conn.prepare("SELECT * FROM ERC20Token WHERE address = ?1 AND chain_id = ?2")?;
         let mut rows = stmt.query(params![address.to_string(), chain_id])?;
 
         if let Some(row) = rows.next()? {
@@ -245,7 +469,51 @@ impl ZeusDB {
         }
     }
 
-    /// Get the [Pool] from the given token0, token1, pool variant, chain_id and fee
+    /// Fix the decimals of a stored [ERC20Token], eg. after a proxy upgrade changed them
+    pub fn update_erc20_decimals(&self, address: Address, chain_id: u64, decimals: u8) -> Result<(), anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        conn.execute(
+            "UPDATE ERC20Token SET decimals = ?1 WHERE chain_id = ?2 AND address = ?3",
+            params![decimals, chain_id, address.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Insert an [ERC20Token] or overwrite the metadata of the stored one, the pinned flag is kept
+    ///
+    /// The token counts as refreshed now
+    pub fn upsert_erc20(&self, token: &ERC20Token, chain_id: u64) -> Result<(), anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        conn.execute(
+            "INSERT INTO ERC20Token (chain_id, address, symbol, name, decimals, total_supply, last_refreshed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, unixepoch())
+             ON CONFLICT(chain_id, address) DO UPDATE SET
+                symbol = excluded.symbol, name = excluded.name, decimals = excluded.decimals, total_supply = excluded.total_supply,
+                last_refreshed = excluded.last_refreshed",
+            params![
+                chain_id,
+                token.address.to_string(),
+                token.symbol,
+                token.name,
+                token.decimals.to_string(),
+                token.total_supply.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Check if an [ERC20Token] with the given address and chain_id is already stored
+    pub fn erc20_exists(&self, address: Address, chain_id: u64) -> Result<bool, anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        let mut stmt =
+            conn.prepare("SELECT 1 FROM ERC20Token WHERE chain_id = ?1 AND address = ?2 LIMIT 1")?;
+        let exists = stmt.exists(params![chain_id, address.to_string()])?;
+        Ok(exists)
+    }
+
+    /// Get the [Pool] from the given tokens, pool variant, chain_id and fee
+    ///
+    /// The tokens can be in any order, the pool comes back with them in the order of the pool contract
     pub fn get_pool(
         &self,
         token0: ERC20Token,
@@ -264,7 +532,7 @@ impl ZeusDB {
 
         let time = std::time::Instant::now();
         let conn = self.get_pools_conn()?;
-        let mut stmt = conn.prepare("SELECT * FROM Pool WHERE chain_id = ?1 AND token0 = ?2 AND token1 = ?3 AND variant = ?4 AND fee = ?5")?;
+        let mut stmt = conn.prepare("SELECT * FROM Pool WHERE chain_id = ?1 AND ((token0 = ?2 AND token1 = ?3) OR (token0 = ?3 AND token1 = ?2)) AND variant = ?4 AND fee = ?5")?;
         let mut rows = stmt.query(params![
             chain_id,
             token0_addr,
@@ -275,9 +543,16 @@ impl ZeusDB {
 
         if let Some(row) = rows.next()? {
             let address: String = row.get(2)?;
+            let stored_token0: String = row.get(3)?;
             let variant: String = row.get(5)?;
             let pool_fee: u32 = row.get(6)?;
 
+            let (token0, token1) = if stored_token0 == token0_addr {
+                (token0, token1)
+            } else {
+                (token1, token0)
+            };
+
             let pool = Pool {
                 chain_id,
                 address: address.parse().unwrap(),
@@ -296,11 +571,25 @@ impl ZeusDB {
         }
     }
 
-    /// Get all [ERC20Token] from the given chain_id
+    /// Get all [ERC20Token] from the given chain_id, pinned tokens first then by symbol
     pub fn get_all_erc20(&self, chain_id: u64) -> Result<Vec<ERC20Token>, anyhow::Error> {
+        // a negative limit is no limit for sqlite
+        self.get_erc20_page(chain_id, "", 0, -1)
+    }
+
+    /// The [ERC20Token] of `chain_id` whose symbol, name or address contain `query`, skipping the first `offset`
+    ///
+    /// Same order as [ZeusDB::get_all_erc20], the address breaks the ties so the pages never overlap
+    pub fn get_erc20_page(&self, chain_id: u64, query: &str, offset: usize, limit: i64) -> Result<Vec<ERC20Token>, anyhow::Error> {
         let conn = self.get_erc20_conn()?;
-        let mut stmt = conn.prepare("SELECT * FROM ERC20Token WHERE chain_id = ?1")?;
-        let mut rows = stmt.query(params![chain_id])?;
+        let mut stmt = conn.prepare(
+            "SELECT * FROM ERC20Token WHERE chain_id = ?1
+             AND (symbol LIKE ?2 ESCAPE '\\' OR name LIKE ?2 ESCAPE '\\' OR address LIKE ?2 ESCAPE '\\')
+             ORDER BY pinned DESC, symbol COLLATE NOCASE, address
+             LIMIT ?3 OFFSET ?4",
+        )?;
+        let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let mut rows = stmt.query(params![chain_id, pattern, limit, offset as i64])?;
         let mut tokens = Vec::new();
 
         while let Some(row) = rows.next()? {
@@ -327,7 +616,78 @@ impl ZeusDB {
         Ok(tokens)
     }
 
+    /// The [ERC20Token] of `chain_id` whose metadata was last read before `before` (unix seconds), oldest first
+    pub fn stale_erc20(&self, chain_id: u64, before: u64) -> Result<Vec<ERC20Token>, anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT chain_id, address, symbol, name, decimals, total_supply FROM ERC20Token
+             WHERE chain_id = ?1 AND last_refreshed < ?2
+             ORDER BY last_refreshed, address",
+        )?;
+        let mut rows = stmt.query(params![chain_id, before as i64])?;
+        let mut tokens = Vec::new();
+
+        while let Some(row) = rows.next()? {
+            let chain_id: i32 = row.get(0)?;
+            let address: String = row.get(1)?;
+            let total_supply: String = row.get(5)?;
+            let decimals: i32 = row.get(4)?;
+
+            tokens.push(ERC20Token {
+                chain_id: chain_id as u64,
+                address: address.parse()?,
+                symbol: row.get(2)?,
+                name: row.get(3)?,
+                decimals: decimals as u8,
+                total_supply: total_supply.parse()?,
+                icon: None,
+            });
+        }
+
+        Ok(tokens)
+    }
+
+    /// Record when the metadata of a stored [ERC20Token] was last read, `at` is in unix seconds
+    pub fn set_erc20_refreshed(&self, address: Address, chain_id: u64, at: u64) -> Result<(), anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        conn.execute(
+            "UPDATE ERC20Token SET last_refreshed = ?1 WHERE chain_id = ?2 AND address = ?3",
+            params![at as i64, chain_id, address.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Pin or unpin a stored [ERC20Token], pinned tokens are listed first
+    pub fn set_pinned(&self, address: Address, chain_id: u64, pinned: bool) -> Result<(), anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        let updated = conn.execute(
+            "UPDATE ERC20Token SET pinned = ?1 WHERE chain_id = ?2 AND address = ?3",
+            params![pinned, chain_id, address.to_string()],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Token {} is not stored on chain {}", address, chain_id));
+        }
+        Ok(())
+    }
+
+    /// The pinned tokens of every chain as (chain_id, address)
+    pub fn load_pinned_tokens(&self) -> Result<HashSet<(u64, Address)>, anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        let mut stmt = conn.prepare("SELECT chain_id, address FROM ERC20Token WHERE pinned = 1")?;
+        let mut rows = stmt.query([])?;
+
+        let mut pinned = HashSet::new();
+        while let Some(row) = rows.next()? {
+            let chain_id: u64 = row.get(0)?;
+            let address: String = row.get(1)?;
+            pinned.insert((chain_id, address.parse()?));
+        }
+        Ok(pinned)
+    }
+
     /// Insert the balance of a token at a given block for a given chain
+    ///
+    /// A balance already stored for the block is replaced, eg. by a forced refresh
     pub fn insert_erc20_balance(
         &self,
         owner: Address,
@@ -338,9 +698,10 @@ impl ZeusDB {
     ) -> Result<(), anyhow::Error> {
         let conn = self.get_erc20_balance_conn()?;
         conn.execute(
-            "INSERT INTO ERC20Balance (chain_id, block_number, owner, token, balance) VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT OR REPLACE INTO ERC20Balance (chain_id, block_number, owner, token, balance) VALUES (?1, ?2, ?3, ?4, ?5)",
             params![chain_id, block, owner.to_string(), token.to_string(), balance.to_string()],
         )?;
+        drop(conn);
 
         // remove any old balances < block
         if let Err(e) = self.remove_erc20_balance(owner, token, block, chain_id) {
@@ -373,6 +734,47 @@ impl ZeusDB {
     }
 
 
+    /// Get the balance of a token at exactly `block`, `None` if we never fetched it
+    pub fn get_erc20_balance_at_block(
+        &self,
+        owner: Address,
+        token: Address,
+        chain_id: u64,
+        block: u64,
+    ) -> Result<Option<U256>, anyhow::Error> {
+        let conn = self.get_erc20_balance_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT balance FROM ERC20Balance WHERE owner = ?1 AND token = ?2 AND chain_id = ?3 AND block_number = ?4",
+        )?;
+        let mut rows = stmt.query(params![owner.to_string(), token.to_string(), chain_id, block])?;
+
+        if let Some(row) = rows.next()? {
+            let balance: String = row.get(0)?;
+            Ok(Some(U256::from_str(&balance)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Cache a historical erc20 balance
+    ///
+    /// Unlike [ZeusDB::insert_erc20_balance] this does not remove the older balances
+    pub fn insert_historical_erc20_balance(
+        &self,
+        owner: Address,
+        token: Address,
+        balance: U256,
+        chain_id: u64,
+        block: u64,
+    ) -> Result<(), anyhow::Error> {
+        let conn = self.get_erc20_balance_conn()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO ERC20Balance (chain_id, block_number, owner, token, balance) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![chain_id, block, owner.to_string(), token.to_string(), balance.to_string()],
+        )?;
+        Ok(())
+    }
+
     /// Remove old erc20 balances from a given block for a given chain
     pub fn remove_erc20_balance(
         &self,
@@ -395,7 +797,7 @@ impl ZeusDB {
         let mut balances_map = HashMap::new();
         for chain_id in chain_ids {
             let conn = self.get_erc20_balance_conn()?;
-            let mut stmt = conn.prepare("SELECT * FROM ERC20Balance WHERE chain_id = ?1")?;
+            let mut stmt = conn.prepare("SELECT * FROM ERC20Balance WHERE chain_id = ?1 ORDER BY block_number")?;
             let mut rows = stmt.query(params![chain_id])?;
 
             while let Some(row) = rows.next()? {
@@ -419,7 +821,7 @@ impl ZeusDB {
         let mut balances_map = HashMap::new();
         for chain_id in chain_ids {
             let conn = self.eth_balance.get()?;
-            let mut stmt = conn.prepare("SELECT * FROM ETHBalance WHERE chain_id = ?1")?;
+            let mut stmt = conn.prepare("SELECT * FROM ETHBalance WHERE chain_id = ?1 ORDER BY block_number")?;
             let mut rows = stmt.query(params![chain_id])?;
 
             while let Some(row) = rows.next()? {
@@ -436,32 +838,175 @@ impl ZeusDB {
     }
 
 
-    /// Load all tokens to a hashmap
-    pub fn load_currencies(
-        &self,
-        id: Vec<u64>,
-    ) -> Result<HashMap<u64, Vec<Currency>>, anyhow::Error> {
-        let mut currencies_map = HashMap::new();
-        for chain_id in id {
-            let mut currencies = Vec::new();
-
-            let erc20_tokens = self.get_all_erc20(chain_id)?;
-            let native_currency = Currency::new_native(chain_id);
-            currencies.push(native_currency);
-
-            for token in erc20_tokens {
-                let erc20_currency = Currency::new_erc20(token);
-                currencies.push(erc20_currency);
-            }
+    /// Save a new [PriceWatch], returns the id it was given
+    pub fn insert_watch(&self, watch: &PriceWatch) -> Result<i64, anyhow::Error> {
+        let conn = self.watches.get()?;
+        conn.execute(
+            "INSERT INTO PriceWatch (chain_id, token_in, token_in_symbol, token_in_decimals, token_out, token_out_symbol, token_out_decimals, condition, target) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                watch.chain_id,
+                watch.token_in.address.to_string(),
+                watch.token_in.symbol,
+                watch.token_in.decimals,
+                watch.token_out.address.to_string(),
+                watch.token_out.symbol,
+                watch.token_out.decimals,
+                watch.condition.to_i64(),
+                watch.target
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Remove a [PriceWatch] by its id
+    pub fn remove_watch(&self, id: i64) -> Result<(), anyhow::Error> {
+        let conn = self.watches.get()?;
+        conn.execute("DELETE FROM PriceWatch WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Load all the saved [PriceWatch]es
+    pub fn load_watches(&self) -> Result<Vec<PriceWatch>, anyhow::Error> {
+        let conn = self.watches.get()?;
+        let mut stmt = conn.prepare("SELECT * FROM PriceWatch ORDER BY id")?;
+        let mut rows = stmt.query([])?;
 
-            currencies_map.insert(chain_id, currencies.clone());
+        let mut watches = Vec::new();
+        while let Some(row) = rows.next()? {
+            let chain_id: u64 = row.get(1)?;
+            let token = |address: usize, symbol: usize, decimals: usize| -> Result<ERC20Token, anyhow::Error> {
+                let address: String = row.get(address)?;
+                let symbol: String = row.get(symbol)?;
+                Ok(ERC20Token {
+                    chain_id,
+                    name: symbol.clone(),
+                    address: address.parse()?,
+                    decimals: row.get(decimals)?,
+                    symbol,
+                    total_supply: U256::ZERO,
+                    icon: None,
+                })
+            };
+
+            watches.push(PriceWatch {
+                id: row.get(0)?,
+                chain_id,
+                token_in: token(2, 3, 4)?,
+                token_out: token(5, 6, 7)?,
+                condition: WatchCondition::from_i64(row.get(8)?),
+                target: row.get(9)?,
+            });
+        }
+
+        Ok(watches)
+    }
+
+    /// A page of the token list of `chain_id` matching `query`, see [ZeusDB::get_erc20_page]
+    ///
+    /// The native currency leads the first page, `offset` only counts the tokens.
+    /// Returns the currencies and whether there are more pages
+    pub fn load_currency_page(&self, chain_id: u64, query: &str, offset: usize) -> Result<(Vec<Currency>, bool), anyhow::Error> {
+        let mut tokens = self.get_erc20_page(chain_id, query, offset, CURRENCY_PAGE_SIZE as i64 + 1)?;
+        let more = tokens.len() > CURRENCY_PAGE_SIZE;
+        tokens.truncate(CURRENCY_PAGE_SIZE);
+
+        let mut currencies = Vec::new();
+        let native = Currency::new_native(chain_id);
+        if offset == 0 && native.symbol().to_lowercase().contains(&query.to_lowercase()) {
+            currencies.push(native);
         }
+        currencies.extend(tokens.into_iter().map(Currency::new_erc20));
 
-        Ok(currencies_map)
+        Ok((currencies, more))
     }
 
-    /// insert some default tokens
-    pub fn insert_default(&self) -> Result<(), anyhow::Error> {
+    /// Remember that `address` has code on `chain_id`
+    pub fn insert_contract(&self, address: Address, chain_id: u64) -> Result<(), anyhow::Error> {
+        let conn = self.contracts.get()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO Contract (chain_id, address) VALUES (?1, ?2)",
+            params![chain_id, address.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Did we already see code at `address` on `chain_id`
+    pub fn is_known_contract(&self, address: Address, chain_id: u64) -> Result<bool, anyhow::Error> {
+        let conn = self.contracts.get()?;
+        let mut stmt = conn.prepare("SELECT 1 FROM Contract WHERE chain_id = ?1 AND address = ?2")?;
+        Ok(stmt.exists(params![chain_id, address.to_string()])?)
+    }
+
+    /// Set the user label of an address, an empty label removes it
+    pub fn set_address_label(&self, address: Address, chain_id: u64, label: &str) -> Result<(), anyhow::Error> {
+        let conn = self.contracts.get()?;
+        if label.is_empty() {
+            conn.execute(
+                "DELETE FROM AddressLabel WHERE chain_id = ?1 AND address = ?2",
+                params![chain_id, address.to_string()],
+            )?;
+        } else {
+            conn.execute(
+                "INSERT OR REPLACE INTO AddressLabel (chain_id, address, label) VALUES (?1, ?2, ?3)",
+                params![chain_id, address.to_string(), label],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// All the user labels
+    ///
+    /// `Key:` (chain_id, address) -> `Value:` label
+    pub fn load_address_labels(&self) -> Result<HashMap<(u64, Address), String>, anyhow::Error> {
+        let conn = self.contracts.get()?;
+        let mut stmt = conn.prepare("SELECT chain_id, address, label FROM AddressLabel")?;
+        let mut rows = stmt.query([])?;
+
+        let mut labels = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let chain_id: u64 = row.get(0)?;
+            let address: String = row.get(1)?;
+            labels.insert((chain_id, address.parse()?), row.get(2)?);
+        }
+        Ok(labels)
+    }
+
+    /// Read a value from the `Meta` table
+    fn get_meta(&self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        let mut stmt = conn.prepare("SELECT value FROM Meta WHERE key = ?1")?;
+        let mut rows = stmt.query(params![key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a value to the `Meta` table, replacing the previous one
+    fn set_meta(&self, key: &str, value: &str) -> Result<(), anyhow::Error> {
+        let conn = self.get_erc20_conn()?;
+        conn.execute("INSERT OR REPLACE INTO Meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+        Ok(())
+    }
+
+    /// Insert the default tokens on the first startup only
+    ///
+    /// Tokens the user removed afterwards don't come back, they can be restored with [ZeusDB::insert_default]
+    ///
+    /// Returns `true` if the tokens were inserted
+    pub fn insert_default_once(&self) -> Result<bool, anyhow::Error> {
+        if self.get_meta(DEFAULT_TOKENS_KEY)?.is_some() {
+            return Ok(false);
+        }
+        self.insert_default()?;
+        self.set_meta(DEFAULT_TOKENS_KEY, "1")?;
+        Ok(true)
+    }
+
+    /// Insert some default tokens
+    ///
+    /// Tokens that already exist are skipped, returns how many were inserted
+    pub fn insert_default(&self) -> Result<usize, anyhow::Error> {
         let eth_token1 = ERC20Token::eth_default_input();
         let eth_token2 = ERC20Token::eth_default_output();
         let bsc_token1 = ERC20Token::bsc_default_input();
@@ -482,9 +1027,23 @@ impl ZeusDB {
             arbitrum_token2,
         ];
 
+        let mut conn = self.get_erc20_conn()?;
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
         for token in &tokens {
-            self.insert_erc20(token.clone(), token.chain_id.clone())?;
+            inserted += tx.execute(
+                "INSERT OR IGNORE INTO ERC20Token (chain_id, address, symbol, name, decimals, total_supply) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    token.chain_id,
+                    token.address.to_string(),
+                    token.symbol.to_string(),
+                    token.name.to_string(),
+                    token.decimals.to_string(),
+                    token.total_supply.to_string()
+                ],
+            )?;
         }
-        Ok(())
+        tx.commit()?;
+        Ok(inserted)
     }
 }
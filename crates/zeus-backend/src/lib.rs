@@ -1,28 +1,126 @@
 use anyhow::Context;
+use bigdecimal::BigDecimal;
 use crossbeam::channel::{unbounded, Receiver, Sender};
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::OpenOptions,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tokio::runtime::Runtime;
-use tracing::{error, info, trace};
+use tracing::{error, info, info_span, trace, warn};
 
 use zeus_chain::{
     alloy::{
-        primitives::{Address, U256},
+        network::TransactionBuilder,
+        primitives::{utils::parse_ether, Address, Bytes, U256},
         providers::{Provider, ProviderBuilder},
-        rpc::types::eth::{BlockId, BlockNumberOrTag},
+        rpc::types::eth::{Block, BlockId, BlockNumberOrTag, TransactionRequest},
         transports::ws::WsConnect,
     },
-    defi_types::currency::{erc20::ERC20Token, Currency},
-    start_block_oracle, BlockOracle, ChainId, OracleAction, Rpc, WsClient, BLOCK_ORACLE,
+    defi_types::{
+        currency::{erc20::ERC20Token, Currency},
+        pool::{dex_name, get_reference_v3_quote, get_v2_pool, get_v2_pool_fee, get_v3_pool, Pool, PoolVariant, V3_FEES},
+    },
+    evm_types::simulation::{simulate_v2_swap, FundedFork, SwapSimulation},
+    format_units, gas_limit_for,
+    tx::{broadcast_raw_tx, TxAmount, TxData, TxOutcome, TxOverrides},
+    start_block_oracle, utils::{arbitrum::is_arbitrum, explorer::erc20_from_explorer, looks_like_erc20, record_rpc}, BlockOracle, ChainId, OracleAction, Rpc,
+    RpcOrigin, TokenMetadataSource, WsClient, BLOCK_ORACLE,
 };
 
-use zeus_core::Profile;
-use zeus_shared_types::{ErrorMsg, SelectedCurrency, SHARED_UI_STATE, SWAP_UI_STATE};
+use zeus_core::{is_writable, zeroize::Zeroize, Profile, Wallet};
+use zeus_shared_types::{cache::SHARED_CACHE, AmountInput, PoolQuote, QuoteResult, QuoteRoute, RouteHop, SelectedCurrency, percent_to_bps, share_to_bps, notify_error, notify_error_text, Language, UiText};
 use anyhow::anyhow;
-use crate::{db::ZeusDB, types::*};
+use crate::{
+    channel::ResponseSender,
+    db::ZeusDB,
+    provider::NodeProvider,
+    metadata_refresh::{decimals_changed_msg, MetadataRefresh},
+    quote_log::{log_quote, MAX_QUOTE_LOG_BYTES, QUOTE_LOG_FILE},
+    types::*,
+};
 
+pub mod channel;
 pub mod db;
+pub mod metadata_refresh;
+mod provider;
+pub mod quote_log;
 pub mod types;
 
+/// How long we wait for the node to return the token metadata before trying the block explorer
+const NODE_METADATA_TIMEOUT: u64 = 10;
+
+/// symbol, name, decimals and totalSupply
+const ERC20_METADATA_CALLS: u64 = 4;
+
+/// A transaction the wallet signs and sends, see [Backend::execute_call]
+struct ContractCall {
+    wallet: Wallet,
+    client: Arc<WsClient>,
+    to: Address,
+    value: U256,
+    call_data: Bytes,
+    chain_id: u64,
+    next_base_fee: U256,
+    priority_fee: U256,
+    mev_protect: bool,
+    gas_multiplier: f64,
+    dry_run: bool,
+    overrides: TxOverrides,
+
+    /// What the call moves, shown in the dry-run and signed transaction windows
+    amount: TxAmount,
+}
+
+impl From<SendCryptoParams> for ContractCall {
+    fn from(params: SendCryptoParams) -> Self {
+        let (to, value, call_data) = params.currency.transfer_call(params.recipient, params.amount);
+
+        Self {
+            amount: TxAmount::new(params.currency, params.amount),
+            wallet: params.wallet,
+            client: params.client,
+            to,
+            value,
+            call_data,
+            chain_id: params.chain_id,
+            next_base_fee: params.next_base_fee,
+            priority_fee: params.priority_fee,
+            mev_protect: params.mev_protect,
+            gas_multiplier: params.gas_multiplier,
+            dry_run: params.dry_run,
+            overrides: params.overrides,
+        }
+    }
+}
+
+impl From<ApproveParams> for ContractCall {
+    fn from(params: ApproveParams) -> Self {
+        let amount = params.amount();
+        Self {
+            to: params.token.address,
+            value: U256::ZERO,
+            call_data: Bytes::from(params.token.encode_approve(params.spender, amount)),
+            wallet: params.wallet,
+            client: params.client,
+            chain_id: params.chain_id,
+            next_base_fee: params.next_base_fee,
+            priority_fee: params.priority_fee,
+            mev_protect: params.mev_protect,
+            gas_multiplier: params.gas_multiplier,
+            dry_run: params.dry_run,
+            overrides: TxOverrides::default(),
+            amount: TxAmount::native(params.chain_id, U256::ZERO),
+        }
+    }
+}
+
 /// A simple backend to handle async/expensive tasks without blocking the gui
 ///
 /// All the API calls that the UI can make to the backend are defined here
@@ -30,7 +128,7 @@ pub mod types;
 /// Still in WIP
 pub struct Backend {
     /// Send Data back to the frontend
-    pub back_sender: Sender<Response>,
+    pub back_sender: ResponseSender,
 
     /// Receive Data from the frontend
     pub front_receiver: Receiver<Request>,
@@ -39,18 +137,56 @@ pub struct Backend {
     pub db: ZeusDB,
 
     pub oracle_sender: Option<Sender<OracleAction>>,
+
+    /// Another instance of Zeus is running or the data directory is read-only, do not write anything to disk
+    pub read_only: bool,
+
+    /// Bumped on every [Request::SaveProfile], a queued save is skipped if a newer one follows it
+    profile_saves: Arc<AtomicU64>,
+
+    /// Only one profile save writes the file at a time
+    profile_save_lock: Arc<Mutex<()>>,
+
+    /// The feature behind the request we are currently handling, see [Request::origin]
+    origin: Option<RpcOrigin>,
+
+    /// Set to stop the running metadata refresh, see [Request::RefreshStaleMetadata]
+    metadata_refresh: Option<Arc<AtomicBool>>,
 }
 
 impl Backend {
-    pub fn new(back_sender: Sender<Response>, front_receiver: Receiver<Request>) -> Self {
+    pub fn new(back_sender: ResponseSender, front_receiver: Receiver<Request>) -> Self {
+        let time = Instant::now();
+        let db = {
+            let _span = info_span!("startup", phase = "db_init").entered();
+            if is_writable(".") {
+                ZeusDB::new().unwrap()
+            } else {
+                warn!("The data directory is read-only, the database is kept in memory");
+                ZeusDB::in_memory().unwrap()
+            }
+        };
+        info!("Database initialized in {}ms", time.elapsed().as_millis());
+
         Self {
             back_sender,
             front_receiver,
-            db: ZeusDB::new().unwrap(),
+            db,
             oracle_sender: None,
+            read_only: false,
+            profile_saves: Arc::new(AtomicU64::new(0)),
+            profile_save_lock: Arc::new(Mutex::new(())),
+            origin: None,
+            metadata_refresh: None,
         }
     }
 
+    /// Run the backend in read-only mode
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
     /// Start the backend
     pub fn init(&mut self) {
         let rt = Runtime::new().unwrap();
@@ -61,101 +197,266 @@ impl Backend {
         rt.block_on(async {
             loop {
                 match self.front_receiver.recv() {
-                    Ok(request) => match request {
-                        Request::OnStartup(chain_id, rpcs) => {
-                            println!("On Startup");
-                            match self.get_client(chain_id.clone(), rpcs.clone()).await {
+                    Ok(request) => {
+                        self.origin = request.origin();
+                        match request {
+                            Request::OnStartup(chain_id, rpcs) => {
+                                println!("On Startup");
+                                if let Err(e) = self.get_client(chain_id.clone(), rpcs.clone()).await {
+                                    self.send_response(Response::client(Err(e.to_string()), chain_id));
+                                }
+                            }
+
+                            Request::LoadCache(chain_ids) => {
+                                self.load_cache(chain_ids);
+                            }
+
+                            Request::DataDirChanged(chain_ids) => match self.reopen_db(chain_ids) {
                                 Ok(_) => {}
                                 Err(e) => {
-                                    let res = Response::client(None, chain_id);
-                                    self.send_response(res);
-                                    let mut state = SHARED_UI_STATE.write().unwrap();
-                                    state.err_msg.show(e);
+                                    notify_error(e);
                                 }
-                            }
-                        }
+                            },
 
-                        Request::InitOracles(client, chain_id) => {
-                            match self.init_oracles(client, chain_id).await {
+                            Request::RestoreDefaultTokens(chain_ids) => match self.restore_default_tokens(chain_ids) {
                                 Ok(_) => {}
                                 Err(e) => {
-                                    let mut state = SHARED_UI_STATE.write().unwrap();
-                                    state.err_msg.show(e);
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::InitOracles(client, chain_id) => {
+                                match self.init_oracles(client, chain_id).await {
+                                    Ok(_) => {}
+                                    Err(e) => {
+                                        notify_error(e);
+                                    }
                                 }
                             }
-                        }
 
-                        Request::ERC20Balance(params) => {
-                            match self
-                                .get_erc20_balance(params.token, params.owner, params.chain_id, params.block, params.client)
+                            Request::ERC20Balance(params) => {
+                                match self
+                                    .get_erc20_balance(params.token, params.owner, params.chain_id, params.block, params.force, &params.client)
+                                    .await
+                                {
+                                    Ok(_) => {}
+                                    Err(e) => {
+                                        notify_error(e);
+                                    }
+                                }
+                            }
+
+                            Request::ERC20Balances(params) => {
+                                if let Err(e) = self
+                                    .get_erc20_balances(params.tokens, params.owner, params.chain_id, params.block, &params.client)
+                                    .await
+                                {
+                                    notify_error(e);
+                                }
+                            }
+
+                            Request::EthBalance(params) => match self
+                                .get_eth_balance(
+                                    params.owner,
+                                    params.chain_id,
+                                    params.block,
+                                    params.force,
+                                    &params.client,
+                                )
                                 .await
                             {
                                 Ok(_) => {}
                                 Err(e) => {
-                                    let mut state = SHARED_UI_STATE.write().unwrap();
-                                    state.err_msg.show(e);
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::SaveProfile(profile) => match self.save_profile(profile) {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
                                 }
+                            },
+
+                            Request::ExportKeystore(params) => self.export_keystore(params),
+
+                            Request::ImportKeystore(params) => self.import_keystore(params),
+
+                            Request::Client(chain_id, rpcs) => {
+                                info!("Received Request to get client: {}", chain_id.name());
+                                
+                                    if let Err(e) = self.get_client(chain_id.clone(), rpcs).await {
+                                        self.send_response(Response::client(Err(e.to_string()), chain_id));
+                                    }
+                                
+                            }
+
+                            Request::ERC20Token(params) => {
+                                match self
+                                    .get_erc20_token(params.currency_id, params.owner, params.token, &params.client, params.chain_id, params.source, params.force)
+                                    .await
+                                {
+                                    Ok(_) => {}
+                                    Err(e) => {
+                                        notify_error(e);
+                                    }
+                                }
+                            }
+
+                            Request::SendCrypto(params) => match self.send_crypto(params).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::BatchSend(params) => match self.batch_send(params).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::Approve(params) => match self.approve(params).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::SignRawTx(params) => match self.sign_raw_tx(params).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::SignTransaction(tx) => match self.sign_transaction(tx).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::BroadcastRawTx(params) => match self.broadcast_raw_tx(params).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::HistoricalBalance(params) => match self.get_historical_balance(params).await {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::LoadWatches => match self.send_watches() {
+                                Ok(_) => {}
+                                Err(e) => error!("Error Loading Price Watches: {}", e),
+                            },
+
+                            Request::AddWatch(watch) => match self.add_watch(watch) {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::CancelWatch(id) => match self.cancel_watch(id) {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    notify_error(e);
+                                }
+                            },
+
+                            Request::CheckWatches(chain_id, client) => {
+                                if let Err(e) = self.check_watches(chain_id, client).await {
+                                    error!("Error Checking Price Watches: {}", e);
+                                }
+                            }
+
+                            Request::RefreshStaleMetadata(chain_id, client) => {
+                                self.refresh_stale_metadata(chain_id, client);
                             }
-                        }
 
-                        Request::EthBalance(params) => match self
-                            .get_eth_balance(
-                                params.owner,
-                                params.chain_id,
-                                params.block,
-                                params.client,
-                            )
-                            .await
-                        {
-                            Ok(_) => {}
-                            Err(e) => {
-                                let mut state = SHARED_UI_STATE.write().unwrap();
-                                state.err_msg.show(e);
+                            Request::CancelMetadataRefresh => {
+                                self.cancel_metadata_refresh();
                             }
-                        },
 
-                        Request::SaveProfile(profile) => match self.save_profile(profile) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                let mut state = SHARED_UI_STATE.write().unwrap();
-                                state.err_msg.show(e);
+                            Request::EstimateTx(params) => {
+                                let estimate = self.estimate_tx(&params).await.map_err(|e| e.to_string());
+                                self.send_response(Response::tx_estimate(params.call, estimate));
                             }
-                        },
 
-                        Request::Client(chain_id, rpcs) => {
-                            info!("Received Request to get client: {}", chain_id.name());
-                            
-                                match self.get_client(chain_id.clone(), rpcs).await {
+                            Request::CheckContract(params) => {
+                                if let Err(e) = self.check_contract(params.address, params.chain_id, &params.client).await {
+                                    warn!("Error Checking Contract: {}", e);
+                                }
+                            }
+
+                            Request::LoadAddressLabels => match self.send_address_labels() {
+                                Ok(_) => {}
+                                Err(e) => error!("Error Loading Address Labels: {}", e),
+                            },
+
+                            Request::SetAddressLabel(chain_id, address, label) => {
+                                match self.set_address_label(chain_id, address, label) {
                                     Ok(_) => {}
                                     Err(e) => {
-                                        let res = Response::client(None, chain_id);
-                                        self.send_response(res);
-                                        let mut state = SHARED_UI_STATE.write().unwrap();
-                                        state.err_msg.show(e);
+                                        notify_error(e);
                                     }
                                 }
-                            
-                        }
+                            }
 
-                        Request::ERC20Token(params) => {
-                            match self
-                                .get_erc20_token(params.currency_id, params.owner, params.token, params.client, params.chain_id)
-                                .await
-                            {
+                            Request::CurrencyPage(params) => match self.send_currency_page(params) {
                                 Ok(_) => {}
-                                Err(e) => {
-                                    let mut state = SHARED_UI_STATE.write().unwrap();
-                                    state.err_msg.show(e);
+                                Err(e) => error!("Error Loading Currencies: {}", e),
+                            },
+
+                            Request::SetPinned(chain_id, address, pinned) => {
+                                match self.set_pinned(chain_id, address, pinned) {
+                                    Ok(_) => {}
+                                    Err(e) => {
+                                        notify_error(e);
+                                    }
+                                }
+                            }
+
+                            Request::BalanceHistory(chain_id, owner) => {
+                                match self.db.load_eth_balance_history(owner, chain_id) {
+                                    Ok(history) => self.send_response(Response::BalanceHistory(chain_id, owner, history)),
+                                    Err(e) => error!("Error Loading Balance History: {}", e),
+                                }
+                            }
+
+                            Request::SpotPrice(params) => {
+                                if let Err(e) = self.get_spot_price(params).await {
+                                    warn!("Error Getting Spot Price: {}", e);
+                                }
+                            }
+
+                            Request::Quote(params) => {
+                                if let Err(e) = self.get_quote(*params).await {
+                                    notify_error_text(UiText::key("error.quote").with("error", e));
                                 }
                             }
                         }
-                    },
+                    }
                     Err(_e) => {}
                 }
             }
         })
     }
 
+    /// Count `calls` rpc calls against the origin of the current request
+    fn record_rpc(&self, calls: u64) {
+        if let Some(origin) = self.origin {
+            record_rpc(origin, calls);
+        }
+    }
+
     fn send_response(&self, response: Response) {
         match self.back_sender.send(response)  {
             Ok(_) => {}
@@ -163,6 +464,100 @@ impl Backend {
         }     
     }
 
+    /// Load the currencies and balances from the database and send them to the frontend
+    ///
+    /// This runs in the background right after the window is created so the UI does not have to wait for it
+    fn load_cache(&self, chain_ids: Vec<u64>) {
+        let time = Instant::now();
+
+        if !self.read_only {
+            let _span = info_span!("startup", phase = "insert_default").entered();
+            if let Err(e) = self.db.insert_default_once() {
+                error!("Error Inserting Default Tokens: {}", e);
+            }
+        }
+
+        let erc20_balance = {
+            let _span = info_span!("startup", phase = "load_erc20_balances").entered();
+            self.db.load_all_erc20_balances(chain_ids.clone()).unwrap_or_else(|e| {
+                error!("Error Loading ERC20 Balances: {}", e);
+                HashMap::new()
+            })
+        };
+
+        let eth_balance = {
+            let _span = info_span!("startup", phase = "load_eth_balances").entered();
+            self.db.load_all_eth_balances(chain_ids).unwrap_or_else(|e| {
+                error!("Error Loading ETH Balances: {}", e);
+                HashMap::new()
+            })
+        };
+
+        let pinned = self.db.load_pinned_tokens().unwrap_or_else(|e| {
+            error!("Error Loading Pinned Tokens: {}", e);
+            HashSet::new()
+        });
+
+        trace!("ERC20 Balances Loaded: {:?}", erc20_balance);
+        trace!("ETH Balances Loaded: {:?}", eth_balance);
+        info!("Cache loaded from database in {}ms", time.elapsed().as_millis());
+
+        let res = Response::cache_loaded(CacheRes {
+            erc20_balance,
+            eth_balance,
+            pinned,
+        });
+        self.send_response(res);
+    }
+
+    /// Open the database of the new data directory, leave read-only mode and reload the cache
+    fn reopen_db(&mut self, chain_ids: Vec<u64>) -> Result<(), anyhow::Error> {
+        // it would keep writing to the old database
+        self.cancel_metadata_refresh();
+        self.db = ZeusDB::new()?;
+        self.read_only = false;
+        self.load_cache(chain_ids);
+        Ok(())
+    }
+
+    /// Start refreshing the stale token metadata of `chain_id` in the background, see [MetadataRefresh]
+    ///
+    /// Nothing is refreshed in read-only mode since the rows can't be updated
+    fn refresh_stale_metadata(&mut self, chain_id: u64, client: Arc<WsClient>) {
+        self.cancel_metadata_refresh();
+        if self.read_only {
+            return;
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.metadata_refresh = Some(cancel.clone());
+        let mut refresh = MetadataRefresh::new(self.db.clone(), self.back_sender.clone(), cancel);
+
+        tokio::spawn(async move {
+            if let Err(e) = refresh.run(&client, chain_id).await {
+                error!("Error Refreshing Token Metadata: {}", e);
+            }
+        });
+    }
+
+    /// Stop the running metadata refresh if any, it stops before its next rpc call
+    fn cancel_metadata_refresh(&mut self) {
+        if let Some(cancel) = self.metadata_refresh.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Insert the default tokens again and reload the cache, the token lists are loaded again afterwards
+    fn restore_default_tokens(&self, chain_ids: Vec<u64>) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow!("Zeus is running in read-only mode, the default tokens were not restored"));
+        }
+        let restored = self.db.insert_default()?;
+        self.load_cache(chain_ids);
+        self.send_response(Response::DefaultTokensRestored(restored));
+        Ok(())
+    }
+
     async fn init_oracles(
         &mut self,
         client: Arc<WsClient>,
@@ -201,82 +596,907 @@ impl Backend {
 
     /// Get the eth balance of an address
     ///
-    /// If the balance is not found in the database, we make an rpc call
+    /// If the balance at `block` is not found in the database, or `force` is set, we make an rpc call
     async fn get_eth_balance(
         &mut self,
         owner: Address,
         chain_id: u64,
         block: u64,
-        client: Arc<WsClient>,
+        force: bool,
+        client: &impl NodeProvider,
     ) -> Result<(), anyhow::Error> {
-        let balance = if let Ok(balance) = self.db.get_eth_balance(owner, chain_id, block) {
+        let cached = if force { None } else { self.db.get_eth_balance_at_block(owner, chain_id, block).ok().flatten() };
+        let balance = if let Some(balance) = cached {
             balance
         } else {
-            let balance = client.get_balance(owner).await?;
-            if let Err(e) = self.db.insert_eth_balance(owner, balance, chain_id, block) {
-                error!("Failed to insert Eth balance into db: {}", e);
+            let time = Instant::now();
+            let balance = client.eth_balance(owner).await?;
+            self.record_rpc(1);
+            self.send_response(Response::latency(time.elapsed()));
+
+            if !self.read_only {
+                if let Err(e) = self.db.insert_eth_balance(owner, balance, chain_id, block) {
+                    error!("Failed to insert Eth balance into db: {}", e);
+                }
             }
             balance
         };
-        self.back_sender.send(Response::EthBalance(balance))?;
+        self.back_sender.send(Response::eth_balance(balance))?;
+        Ok(())
+    }
+
+    /// Get the balance of a currency at a past block
+    ///
+    /// The balance is cached in the block-keyed balance tables, so asking again for the same block is free
+    async fn get_historical_balance(&self, params: HistoricalBalanceParams) -> Result<(), anyhow::Error> {
+        let HistoricalBalanceParams { currency, owner, chain_id, block, client } = params;
+
+        let latest = BLOCK_ORACLE.read().unwrap().latest_block().number;
+        if latest != 0 && block > latest {
+            return Err(anyhow!("Block {} is in the future, the latest block is {}", block, latest));
+        }
+
+        let cached = match &currency {
+            Currency::Native(_) => self.db.get_eth_balance_at_block(owner, chain_id, block),
+            Currency::ERC20(token) => self.db.get_erc20_balance_at_block(owner, token.address, chain_id, block),
+        };
+
+        let balance = match cached {
+            Ok(Some(balance)) => balance,
+            _ => {
+                let time = Instant::now();
+                let balance = match &currency {
+                    Currency::Native(_) => client
+                        .get_balance(owner)
+                        .block_id(BlockId::number(block))
+                        .await
+                        .map_err(anyhow::Error::from),
+                    Currency::ERC20(token) => token.balance_at(owner, block, client.clone()).await,
+                };
+                self.record_rpc(1);
+
+                let balance = balance.map_err(|e| {
+                    anyhow!(
+                        "Failed to get the balance at block {}, the node may not have the state of that block (an archive node is needed for old blocks): {}",
+                        block,
+                        e
+                    )
+                })?;
+                self.send_response(Response::latency(time.elapsed()));
+
+                if !self.read_only {
+                    let inserted = match &currency {
+                        Currency::Native(_) => self.db.insert_historical_eth_balance(owner, balance, chain_id, block),
+                        Currency::ERC20(token) => {
+                            self.db.insert_historical_erc20_balance(owner, token.address, balance, chain_id, block)
+                        }
+                    };
+                    if let Err(e) = inserted {
+                        error!("Failed to cache historical balance: {}", e);
+                    }
+                }
+                balance
+            }
+        };
+
+        self.send_response(Response::historical_balance(currency, owner, chain_id, block, balance));
+        Ok(())
+    }
+
+    /// Send all the saved price watches to the frontend
+    /// Find out if `address` has code and if it is a token, and tell the frontend
+    ///
+    /// Contracts are remembered in the database, EOAs are checked every time since they can get code later.
+    /// A contract is a token if it is in our token list, or if its code looks like an ERC20 and it answers like one
+    async fn check_contract(&self, address: Address, chain_id: u64, client: &impl NodeProvider) -> Result<(), anyhow::Error> {
+        let (is_contract, looks_like_token) = if self.db.is_known_contract(address, chain_id)? {
+            (true, false)
+        } else {
+            let code = client.code(address).await?;
+            self.record_rpc(1);
+            if !code.is_empty() && !self.read_only {
+                self.db.insert_contract(address, chain_id)?;
+            }
+            (!code.is_empty(), looks_like_erc20(&code))
+        };
+
+        let token = if self.db.erc20_exists(address, chain_id)? {
+            Some(self.db.get_erc20(address, chain_id)?.symbol)
+        } else if looks_like_token {
+            self.record_rpc(ERC20_METADATA_CALLS);
+            match client.erc20_metadata(address, chain_id).await {
+                Ok(token) => {
+                    if !self.read_only && token.check_decimals().is_ok() {
+                        self.db.insert_erc20(token.clone(), chain_id)?;
+                    }
+                    Some(token.symbol)
+                }
+                Err(e) => {
+                    warn!("{} looks like a token but its metadata could not be read: {}", address, e);
+                    Some("ERC20".to_string())
+                }
+            }
+        } else {
+            None
+        };
+
+        self.send_response(Response::ContractCheck(chain_id, address, is_contract, token));
+        Ok(())
+    }
+
+    fn send_address_labels(&self) -> Result<(), anyhow::Error> {
+        let labels = self.db.load_address_labels()?;
+        self.send_response(Response::AddressLabels(labels));
+        Ok(())
+    }
+
+    fn set_address_label(&self, chain_id: u64, address: Address, label: String) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow!("Zeus is running in read-only mode, the label was not saved"));
+        }
+        self.db.set_address_label(address, chain_id, label.trim())?;
+        self.send_address_labels()
+    }
+
+    fn send_currency_page(&self, params: CurrencyPageParams) -> Result<(), anyhow::Error> {
+        let (currencies, more) = self.db.load_currency_page(params.chain_id, &params.query, params.offset)?;
+        self.send_response(Response::CurrencyPage(Box::new(CurrencyPageRes {
+            chain_id: params.chain_id,
+            query: params.query,
+            offset: params.offset,
+            currencies,
+            more,
+        })));
+        Ok(())
+    }
+
+    fn set_pinned(&self, chain_id: u64, address: Address, pinned: bool) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow!("Zeus is running in read-only mode, the token was not pinned"));
+        }
+        self.db.set_pinned(address, chain_id, pinned)?;
+        self.send_response(Response::PinnedTokens(self.db.load_pinned_tokens()?));
+        Ok(())
+    }
+
+    fn send_watches(&self) -> Result<(), anyhow::Error> {
+        let watches = self.db.load_watches()?;
+        self.send_response(Response::watches(watches));
+        Ok(())
+    }
+
+    fn add_watch(&self, watch: PriceWatch) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow!("Zeus is running in read-only mode, the price watch was not saved"));
+        }
+        self.db.insert_watch(&watch)?;
+        self.send_watches()
+    }
+
+    fn cancel_watch(&self, id: i64) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow!("Zeus is running in read-only mode, the price watch was not removed"));
+        }
+        self.db.remove_watch(id)?;
+        self.send_watches()
+    }
+
+    /// Sample the spot price of every watched pair on `chain_id` and notify the frontend of the ones that triggered
+    ///
+    /// Triggered watches are removed, so they fire only once
+    ///
+    /// In read-only mode we can't remove them, so the watches are paused
+    async fn check_watches(&self, chain_id: u64, client: Arc<WsClient>) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        let watches = self.db.load_watches()?;
+        let mut triggered = false;
+
+        for watch in watches.into_iter().filter(|w| w.chain_id == chain_id) {
+            let one = U256::from(10).pow(U256::from(watch.token_in.decimals));
+            let amount = get_reference_v3_quote(
+                watch.token_in.clone(),
+                watch.token_out.clone(),
+                one,
+                chain_id,
+                client.clone(),
+                RpcOrigin::Quotes,
+            )
+            .await;
+
+            let price = match amount {
+                Ok(amount) => format_units(amount, watch.token_out.decimals).parse::<f64>()?,
+                Err(e) => {
+                    warn!("Failed to get the price for watch {}: {}", watch.describe(), e);
+                    continue;
+                }
+            };
+
+            triggered |= self.on_watch_price(&watch, price)?;
+        }
+
+        if triggered {
+            self.send_watches()?;
+        }
+        Ok(())
+    }
+
+    /// Notify the frontend if `watch` reached its target at `price`, returns if it triggered
+    ///
+    /// A triggered watch is removed, only the notification is sent, a watch never makes a transaction
+    fn on_watch_price(&self, watch: &PriceWatch, price: f64) -> Result<bool, anyhow::Error> {
+        if !watch.condition.is_met(price, watch.target) {
+            return Ok(false);
+        }
+
+        info!("Price watch triggered: {} at {}", watch.describe(), price);
+        self.db.remove_watch(watch.id)?;
+        self.send_response(Response::watch_triggered(watch.clone(), price));
+        Ok(true)
+    }
+
+    /// Get a pool of the pair from the database or the node, the tokens can be in any order
+    async fn find_pool(
+        &self,
+        token0: &ERC20Token,
+        token1: &ERC20Token,
+        variant: PoolVariant,
+        fee: u32,
+        chain_id: u64,
+        client: Arc<WsClient>,
+    ) -> Result<Pool, anyhow::Error> {
+        if let Ok(pool) = self.db.get_pool(token0.clone(), token1.clone(), chain_id, variant.clone(), fee) {
+            return Ok(pool);
+        }
+
+        // getPair/getPool + token0
+        self.record_rpc(2);
+        let pool = match variant {
+            PoolVariant::UniswapV2 => get_v2_pool(token0.clone(), token1.clone(), chain_id, client).await?,
+            PoolVariant::UniswapV3 => get_v3_pool(token0.clone(), token1.clone(), fee, chain_id, client).await?,
+        };
+
+        if !self.read_only {
+            if let Err(e) = self.db.insert_pool(pool.clone(), chain_id) {
+                error!("Failed to insert pool into db {}", e);
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Read the spot price of a pair from every V2 and V3 pool we can find and send the best one
+    ///
+    /// The best pool is the one that gives the most `token_out`, like a swap would pick
+    async fn get_spot_price(&self, params: SpotPriceParams) -> Result<(), anyhow::Error> {
+        let SpotPriceParams { token_in, token_out, chain_id, client } = params;
+
+        let candidates = std::iter::once((PoolVariant::UniswapV2, get_v2_pool_fee(chain_id)))
+            .chain(V3_FEES.into_iter().map(|fee| (PoolVariant::UniswapV3, fee)));
+
+        let mut best: Option<(Pool, U256)> = None;
+        for (variant, fee) in candidates {
+            let Ok(pool) = self.find_pool(&token_in, &token_out, variant, fee, chain_id, client.clone()).await else {
+                continue;
+            };
+
+            self.record_rpc(1);
+            let price = match pool.fetch_state(client.clone()).await {
+                Ok(state) => pool.spot_price(&state, &token_in),
+                Err(e) => Err(e),
+            };
+
+            match price {
+                Ok(price) if best.as_ref().is_none_or(|(_, best)| price > *best) => best = Some((pool, price)),
+                Ok(_) => {}
+                Err(e) => trace!("Skipping pool {}: {}", pool.address, e),
+            }
+        }
+
+        let (pool, price) = best.ok_or(anyhow!("No pool found for {}/{}", token_in.symbol, token_out.symbol))?;
+        self.send_response(Response::spot_price(token_in, token_out, pool, price));
+        Ok(())
+    }
+
+    /// The allowance of `router` over the input of a swap, `None` for the native currency
+    ///
+    /// Also `None` if it could not be read, the swap is not blocked on an allowance we don't know
+    async fn router_allowance(
+        &self,
+        currency_in: &Currency,
+        owner: Address,
+        router: Address,
+        client: &impl NodeProvider,
+    ) -> Option<U256> {
+        let token = currency_in.erc20()?;
+        self.record_rpc(1);
+        match client.erc20_allowance(token, owner, router).await {
+            Ok(allowance) => Some(allowance),
+            Err(e) => {
+                trace!("Could not read the allowance of {} over {}: {}", router, token.symbol, e);
+                None
+            }
+        }
+    }
+
+    /// The output of a V2 pool and the swap it was simulated with
+    ///
+    /// If the swap could not be simulated (see [Self::get_quote]) this falls back to `getAmountOut`
+    /// over the reserves, no transfer tax is known then
+    async fn v2_quote(
+        &self,
+        pool: &Pool,
+        token_in: &ERC20Token,
+        amount_in: U256,
+        simulated: Result<SwapSimulation, anyhow::Error>,
+        client: &impl NodeProvider,
+    ) -> Result<(U256, Option<SwapSimulation>), anyhow::Error> {
+        let e = match simulated {
+            Ok(sim) => return Ok((sim.amount_out, Some(sim))),
+            Err(e) => e,
+        };
+
+        trace!("Could not simulate the swap on {}, using the reserves: {}", pool.address, e);
+        self.record_rpc(1);
+        let state = client.pool_state(pool).await?;
+        Ok((pool.amount_out(&state, token_in, amount_in)?, None))
+    }
+
+    /// Quote a swap on every V2 and V3 pool of the pair and send the one with the highest output
+    ///
+    /// V2 pools are quoted by swapping on a fork of `params.block`, with the input balance written
+    /// straight to the storage of `token_in` so the user does not need to hold it.
+    /// This also catches transfer taxes. If the swap can't be simulated (eg. we could not find the balance slot)
+    /// we fall back to `getAmountOut` over the reserves.
+    ///
+    /// V3 pools are quoted at their spot price minus the pool fee
+    async fn get_quote(&self, params: QuoteParams) -> Result<(), anyhow::Error> {
+        let token_in = params.currency_in.wrapped();
+        let token_out = params.currency_out.wrapped();
+        let chain_id = params.chain_id;
+        let client = params.client.clone();
+
+        if token_in.address == token_out.address {
+            return Err(anyhow!("Cannot swap {} for itself", token_in.symbol));
+        }
+
+        let block_number = params.block.header.number.ok_or(anyhow!("Block number is missing"))?;
+        let router = params.router.validate(chain_id)?;
+
+        let candidates = std::iter::once((PoolVariant::UniswapV2, get_v2_pool_fee(chain_id)))
+            .chain(V3_FEES.into_iter().map(|fee| (PoolVariant::UniswapV3, fee)));
+
+        let mut quotes: Vec<(Pool, U256, Option<SwapSimulation>)> = Vec::new();
+        for (variant, fee) in candidates {
+            let Ok(pool) = self.find_pool(&token_in, &token_out, variant, fee, chain_id, client.clone()).await else {
+                continue;
+            };
+
+            let quote = match pool.variant {
+                PoolVariant::UniswapV2 => {
+                    let simulated = simulate_quote(&pool, &token_in, params.amount_in, &params.block, block_number, client.clone());
+                    self.v2_quote(&pool, &token_in, params.amount_in, simulated, &client).await
+                }
+                PoolVariant::UniswapV3 => {
+                    self.record_rpc(1);
+                    match pool.fetch_state(client.clone()).await {
+                        Ok(state) => pool.quote(&state, &token_in, params.amount_in).map(|amount| {
+                            (amount * U256::from(1_000_000 - pool.fee) / U256::from(1_000_000), None)
+                        }),
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+
+            match quote {
+                Ok((amount, sim)) => quotes.push((pool, amount, sim)),
+                Err(e) => trace!("Skipping pool {}: {}", pool.address, e),
+            }
+        }
+
+        // best first, the first pool wins a tie like before
+        quotes.sort_by_key(|(_, amount, _)| std::cmp::Reverse(*amount));
+        let pool_quotes = quotes
+            .iter()
+            .map(|(pool, amount_out, _)| PoolQuote {
+                address: pool.address,
+                variant: pool.variant.clone(),
+                fee: pool.fee,
+                amount_out: *amount_out,
+            })
+            .collect();
+
+        let (pool, amount_out, sim) = quotes
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("No pool found for {}/{}", token_in.symbol, token_out.symbol))?;
+
+        let slippage_bps = percent_to_bps(params.slippage);
+        let minimum_received = amount_out * U256::from(10_000 - slippage_bps) / U256::from(10_000);
+
+        let token_tax_bps = match &sim {
+            Some(sim) if sim.tax() > 0.0 => Some(share_to_bps(sim.tax())),
+            _ => None,
+        };
+
+        let allowance = self.router_allowance(&params.currency_in, params.owner, router, &client).await;
+
+        let (balance_in, balance_out) = {
+            let cache = SHARED_CACHE.read().unwrap();
+            (
+                cache.balance_of(chain_id, params.owner, &params.currency_in),
+                cache.balance_of(chain_id, params.owner, &params.currency_out),
+            )
+        };
+
+        let result = QuoteResult {
+            block_number,
+            input_token: SelectedCurrency {
+                currency: params.currency_in,
+                balance: balance_in,
+                amount: AmountInput::from_amount(params.amount_in, token_in.decimals),
+            },
+            output_token: SelectedCurrency {
+                currency: params.currency_out,
+                balance: balance_out,
+                amount: AmountInput::from_amount(amount_out, token_out.decimals),
+            },
+            slippage_bps,
+            amount_out,
+            minimum_received,
+            token_tax_bps,
+            gas_used: sim.as_ref().map_or(0, |sim| sim.gas_used),
+            route: QuoteRoute {
+                dex: dex_name(chain_id).to_string(),
+                hops: vec![RouteHop { pool: pool.address, variant: pool.variant.clone(), fee: pool.fee }],
+            },
+            pool_quotes,
+            router,
+            allowance,
+            ..Default::default()
+        };
+
+        if params.log_quotes && !self.read_only {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+            if let Err(e) = log_quote(Path::new(QUOTE_LOG_FILE), &result, now, MAX_QUOTE_LOG_BYTES) {
+                warn!("Could not log the quote to {}: {}", QUOTE_LOG_FILE, e);
+            }
+        }
+
+        self.send_response(Response::quote(result));
+        Ok(())
+    }
+
+    /// Transactions are only sent by the instance that owns the data, a second one would reuse its nonces
+    fn check_can_send(&self) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow!("Zeus is running in read-only mode, transactions are disabled"));
+        }
+        Ok(())
+    }
+
+    /// Build, sign, simulate and (unless in dry-run mode) broadcast a transfer
+    async fn send_crypto(&self, params: SendCryptoParams) -> Result<(), anyhow::Error> {
+        self.check_can_send()?;
+        let nonce = self.next_nonce(&params.client, params.wallet.key.address(), &params.overrides).await?;
+        let outcome = self.transfer(params, nonce).await?;
+        self.send_response(Response::transaction(outcome));
+        Ok(())
+    }
+
+    /// Send the rows of a batch one after the other, reporting the status of each row as it changes
+    ///
+    /// The nonce is fetched once and incremented locally, it is only fetched again after a row failed
+    async fn batch_send(&self, params: BatchSendParams) -> Result<(), anyhow::Error> {
+        self.check_can_send()?;
+        let from = params.wallet.key.address();
+        let mut nonce = params.client.get_transaction_count(from).await?;
+        self.record_rpc(1);
+
+        let mut failed = false;
+        for (index, (recipient, amount)) in params.rows.iter().enumerate() {
+            if failed && params.abort_on_failure {
+                self.send_response(Response::batch_send(index, BatchRowStatus::Skipped));
+                continue;
+            }
+            self.send_response(Response::batch_send(index, BatchRowStatus::Pending));
+
+            let status = match self.transfer(params.transfer(*recipient, *amount), nonce).await {
+                Ok(TxOutcome::DryRun(_)) => {
+                    nonce += 1;
+                    BatchRowStatus::Simulated
+                }
+                Ok(TxOutcome::Sent(receipt)) => {
+                    nonce += 1;
+                    if receipt.status() {
+                        BatchRowStatus::Confirmed(receipt.transaction_hash)
+                    } else {
+                        failed = true;
+                        BatchRowStatus::Failed("Transaction reverted".to_string())
+                    }
+                }
+                Err(e) => {
+                    failed = true;
+                    // we don't know if the transaction made it out before the error
+                    if !params.dry_run {
+                        nonce = params.client.get_transaction_count(from).await?;
+                        self.record_rpc(1);
+                    }
+                    BatchRowStatus::Failed(e.to_string())
+                }
+            };
+            self.send_response(Response::batch_send(index, status));
+        }
+        Ok(())
+    }
+
+    /// Approve the spender for the amount of [ApproveParams::amount]
+    async fn approve(&self, params: ApproveParams) -> Result<(), anyhow::Error> {
+        self.check_can_send()?;
+        let nonce = params.client.get_transaction_count(params.wallet.key.address()).await?;
+        self.record_rpc(1);
+
+        let outcome = self.execute_call(ContractCall::from(params), nonce).await?;
+        self.send_response(Response::transaction(outcome));
+        Ok(())
+    }
+
+    /// Build and execute a transfer with the given nonce
+    async fn transfer(&self, params: SendCryptoParams, nonce: u64) -> Result<TxOutcome, anyhow::Error> {
+        self.execute_call(ContractCall::from(params), nonce).await
+    }
+
+    /// Build, simulate and sign a transfer or an approval like [Self::send_crypto] and [Self::approve],
+    /// the signed transaction is sent back instead of being broadcasted
+    async fn sign_transaction(&self, tx: TxRequest) -> Result<(), anyhow::Error> {
+        let nonce = self.next_nonce(tx.client(), tx.wallet().key.address(), &tx.overrides()).await?;
+
+        let call = match tx {
+            TxRequest::Transfer(params) => ContractCall::from(params),
+            TxRequest::Approve(params) => ContractCall::from(params),
+        };
+        let chain_id = call.chain_id;
+        let tx = self.build_call(call, nonce).await?;
+        let signed = tx.sign_only().await;
+
+        // call + estimate_gas for the simulation (+ the L1 gas on Arbitrum)
+        self.record_rpc(if is_arbitrum(chain_id) { 3 } else { 2 });
+        self.send_response(Response::signed_transaction(signed?));
+        Ok(())
+    }
+
+    /// The nonce the user set, otherwise the next one of `from`
+    async fn next_nonce(&self, client: &WsClient, from: Address, overrides: &TxOverrides) -> Result<u64, anyhow::Error> {
+        if let Some(nonce) = overrides.nonce {
+            return Ok(nonce);
+        }
+        self.record_rpc(1);
+        Ok(client.get_transaction_count(from).await?)
+    }
+
+    /// Estimate the gas of the call and build the transaction with the given nonce
+    ///
+    /// The [TxOverrides] are applied last, a gas limit set by the user is used if the estimate fails
+    async fn build_call(&self, call: ContractCall, nonce: u64) -> Result<TxData, anyhow::Error> {
+        call.overrides.check()?;
+        let from = call.wallet.key.address();
+
+        let estimate = TransactionRequest::default()
+            .with_from(from)
+            .with_to(call.to)
+            .with_value(call.value)
+            .with_input(call.call_data.clone());
+        self.record_rpc(1);
+        let gas_used = estimated_or_override(call.client.estimate_gas(&estimate).await, &call.overrides)?;
+
+        let tx = TxData::new(
+            call.wallet.key,
+            (*call.client).clone(),
+            call.next_base_fee,
+            call.call_data,
+            call.to,
+            call.value,
+            nonce,
+            call.priority_fee,
+            gas_used,
+            call.chain_id,
+            call.mev_protect,
+        )
+        .with_gas_multiplier(call.gas_multiplier)
+        .with_overrides(&call.overrides)
+        .with_amount(call.amount);
+        Ok(tx)
+    }
+
+    /// Estimate the gas of a call and create its access list if asked to
+    ///
+    /// With the access list the gas is the one `eth_createAccessList` used, it includes the list
+    async fn estimate_tx(&self, params: &EstimateTxParams) -> Result<TxEstimate, anyhow::Error> {
+        let request = params.call.request();
+        self.record_rpc(1);
+
+        let (gas_used, access_list) = if params.access_list {
+            let created = params.client.create_access_list(&request).await?;
+            (created.gas_used.saturating_to::<u128>(), Some(created.access_list))
+        } else {
+            (params.client.estimate_gas(&request).await?, None)
+        };
+
+        Ok(TxEstimate {
+            gas_limit: gas_limit_for(gas_used, params.gas_multiplier, params.call.data.is_empty()),
+            access_list,
+        })
+    }
+
+    /// Estimate the gas of the call, then sign, simulate and broadcast it with the given nonce
+    async fn execute_call(&self, call: ContractCall, nonce: u64) -> Result<TxOutcome, anyhow::Error> {
+        let (chain_id, dry_run) = (call.chain_id, call.dry_run);
+        let tx = self.build_call(call, nonce).await?;
+        let outcome = tx.execute(dry_run).await;
+
+        // call + estimate_gas for the simulation (+ the L1 gas on Arbitrum), plus the broadcast
+        let simulation_calls = if is_arbitrum(chain_id) { 3 } else { 2 };
+        self.record_rpc(if dry_run { simulation_calls } else { simulation_calls + 1 });
+        outcome
+    }
+
+    /// Fill the missing nonce, gas and fees of a raw transaction, then sign, simulate and broadcast it
+    /// like [Self::send_crypto]
+    async fn sign_raw_tx(&self, params: SignRawTxParams) -> Result<(), anyhow::Error> {
+        self.check_can_send()?;
+        let SignRawTxParams { tx: raw, wallet, next_base_fee, priority_fee, mev_protect, gas_multiplier, dry_run, overrides, client } = params;
+        overrides.check()?;
+        let from = wallet.key.address();
+
+        let nonce = match overrides.nonce.or(raw.nonce) {
+            Some(nonce) => nonce,
+            None => {
+                self.record_rpc(1);
+                client.get_transaction_count(from).await?
+            }
+        };
+
+        // still needed for the dry-run fee even if the gas limit is given
+        let estimate = TransactionRequest::default()
+            .with_from(from)
+            .with_to(raw.to)
+            .with_value(raw.value)
+            .with_input(raw.data.clone());
+        self.record_rpc(1);
+        let gas_limit = overrides.gas_limit.or(raw.gas_limit.map(|gas_limit| gas_limit as u128));
+        let gas_used = estimated_or_override(
+            client.estimate_gas(&estimate).await,
+            &TxOverrides { gas_limit, ..TxOverrides::default() },
+        )?;
+
+        let priority_fee = raw.max_priority_fee_per_gas.map(U256::from).unwrap_or(priority_fee);
+
+        let mut tx = TxData::new(
+            wallet.key,
+            (*client).clone(),
+            next_base_fee,
+            raw.data,
+            raw.to,
+            raw.value,
+            nonce,
+            priority_fee,
+            gas_used,
+            raw.chain_id,
+            mev_protect,
+        )
+        .with_gas_multiplier(gas_multiplier);
+        if let Some(gas_limit) = raw.gas_limit {
+            tx = tx.with_gas_limit(gas_limit as u128);
+        }
+        if let Some(max_fee) = raw.max_fee_per_gas {
+            tx = tx.with_max_fee(max_fee);
+        }
+        let tx = tx.with_overrides(&overrides);
+
+        let outcome = tx.execute(dry_run).await;
+
+        let simulation_calls = if is_arbitrum(raw.chain_id) { 3 } else { 2 };
+        self.record_rpc(if dry_run { simulation_calls } else { simulation_calls + 1 });
+        let outcome = outcome?;
+        self.send_response(Response::transaction(outcome));
+        Ok(())
+    }
+
+    /// Broadcast a transaction signed by another tool and wait for its receipt
+    async fn broadcast_raw_tx(&self, params: BroadcastRawTxParams) -> Result<(), anyhow::Error> {
+        self.check_can_send()?;
+        let BroadcastRawTxParams { tx, chain_id, mev_protect, client } = params;
+        tx.check_chain(chain_id)?;
+
+        info!("Broadcasting raw transaction {} from {}", tx.hash, tx.from);
+        self.record_rpc(1);
+        let receipt = broadcast_raw_tx(&client, chain_id, mev_protect, &tx.raw).await?;
+        self.send_response(Response::transaction(TxOutcome::Sent(Box::new(receipt))));
+        Ok(())
+    }
+
+    /// Get the [ERC20Token] from the given address
+    ///
+    /// If the token is not found in the database, we make an rpc call
+    ///
+    /// ### Arguments:
+    ///
+    /// `id:` Which token to update in the Swap UI ("token_in" or "token_out")
+    ///
+    /// `address:` The address of the token
+    ///
+    /// `client:` The websocket client
+    ///
+    /// `chain_id:` The chain id
+    ///
+    /// `source:` Where to read the token metadata from if its not in the database
+    ///
+    /// `force:` Read the metadata again even if the token is known, the stored token is replaced
+    #[allow(clippy::too_many_arguments)]
+    async fn get_erc20_token(
+        &self,
+        currency_id: String,
+        owner: Address,
+        token_address: Address,
+        client: &impl NodeProvider,
+        chain_id: u64,
+        source: TokenMetadataSource,
+        force: bool,
+    ) -> Result<(), anyhow::Error> {
+        let (token, added) = match self.known_currency(chain_id, token_address) {
+            _ if force => (self.refetch_erc20(token_address, client, chain_id, source).await?, false),
+            Some(Currency::ERC20(token)) => (self.verify_decimals(token, client).await?, false),
+            _ => match self.resolve_currency(chain_id, token_address, client, source).await? {
+                Currency::ERC20(token) => (token, true),
+                Currency::Native(_) => return Err(anyhow!("{} is not an ERC20 token", token_address)),
+            },
+        };
+
+        let balance = client.erc20_balance(&token, owner).await?;
+        self.record_rpc(1);
+        let res = Response::erc20_token(currency_id, owner, token, balance, chain_id, added);
+
+        self.back_sender.send(res)?;
+
         Ok(())
     }
 
-    /// Get the [ERC20Token] from the given address
-    ///
-    /// If the token is not found in the database, we make an rpc call
-    ///
-    /// ### Arguments:
-    ///
-    /// `id:` Which token to update in the Swap UI ("token_in" or "token_out")
-    ///
-    /// `address:` The address of the token
-    ///
-    /// `client:` The websocket client
+    /// Read the metadata of a token from the node even if we know it and replace the stored one
+    async fn refetch_erc20(
+        &self,
+        token_address: Address,
+        client: &impl NodeProvider,
+        chain_id: u64,
+        source: TokenMetadataSource,
+    ) -> Result<ERC20Token, anyhow::Error> {
+        let token = self.fetch_erc20(token_address, client, chain_id, source).await?;
+        token.check_decimals()?;
+
+        let stored = self.db.get_erc20(token_address, chain_id).ok();
+        if let Some(msg) = stored.and_then(|stored| decimals_changed_msg(&stored, &token)) {
+            warn!("{}", msg.translate_to(Language::English));
+            notify_error_text(msg);
+        }
+
+        if !self.read_only {
+            self.db.upsert_erc20(&token, chain_id)?;
+        }
+        Ok(token)
+    }
+
+    /// Re-read the decimals of a token we already know, a proxy upgrade may have changed them
+    ///
+    /// On a mismatch the database row is updated and the user is warned.
+    /// If the node can't answer the cached token is kept
+    async fn verify_decimals(&self, token: ERC20Token, client: &impl NodeProvider) -> Result<ERC20Token, anyhow::Error> {
+        let decimals = match client.erc20_decimals(token.address).await {
+            Ok(decimals) => decimals,
+            Err(e) => {
+                warn!("Could not verify the decimals of {}: {}", token.symbol, e);
+                return Ok(token);
+            }
+        };
+        self.record_rpc(1);
+
+        if decimals == token.decimals {
+            return Ok(token);
+        }
+
+        let updated = ERC20Token { decimals, ..token.clone() };
+        updated.check_decimals()?;
+        warn!("{} decimals changed from {} to {}", token.symbol, token.decimals, decimals);
+
+        if !self.read_only {
+            self.db.update_erc20_decimals(token.address, token.chain_id, decimals)?;
+        }
+
+        if let Some(msg) = decimals_changed_msg(&token, &updated) {
+            notify_error_text(msg);
+        }
+        Ok(updated)
+    }
+
+    /// The [Currency] of `address` from the [SHARED_CACHE] or the database, without any rpc call
+    fn known_currency(&self, chain_id: u64, address: Address) -> Option<Currency> {
+        if let Some(currency) = SHARED_CACHE.read().unwrap().get_currency(chain_id, address) {
+            return Some(currency);
+        }
+
+        self.db.get_erc20(address, chain_id).ok().map(Currency::new_erc20)
+    }
+
+    /// Turn a token address into a [Currency]
+    ///
+    /// Looks in the [SHARED_CACHE] first, then in the database and finally asks the node, see [Backend::fetch_erc20].
+    /// Tokens fetched from the node are saved to the database
+    async fn resolve_currency(
+        &self,
+        chain_id: u64,
+        address: Address,
+        client: &impl NodeProvider,
+        source: TokenMetadataSource,
+    ) -> Result<Currency, anyhow::Error> {
+        if let Some(currency) = self.known_currency(chain_id, address) {
+            return Ok(currency);
+        }
+
+        let token = self.fetch_erc20(address, client, chain_id, source).await?;
+        token.check_decimals()?;
+        if !self.read_only {
+            self.db.insert_erc20(token.clone(), chain_id)?;
+        }
+        Ok(Currency::new_erc20(token))
+    }
+
+    /// Read the metadata of an [ERC20Token] from the node
     ///
-    /// `chain_id:` The chain id
-    async fn get_erc20_token(
+    /// If the node fails or is too slow and the `source` allows it, we fall back to a block explorer API
+    async fn fetch_erc20(
         &self,
-        currency_id: String,
-        owner: Address,
         token_address: Address,
-        client: Arc<WsClient>,
+        client: &impl NodeProvider,
         chain_id: u64,
-    ) -> Result<(), anyhow::Error> {
-        let token = if let Ok(token) = self.db.get_erc20(token_address, chain_id) {
-            token
-        } else {
-            let token = ERC20Token::new(token_address, client.clone(), chain_id, None).await?;
-            self.db.insert_erc20(token.clone(), chain_id)?;
-            token
-        };
+        source: TokenMetadataSource,
+    ) -> Result<ERC20Token, anyhow::Error> {
+        self.record_rpc(ERC20_METADATA_CALLS);
 
-        let balance = token.balance_of(owner, client).await?;
-        let res = Response::erc20_token(currency_id, owner, token, balance, chain_id);
+        if !source.uses_explorer() {
+            return client.erc20_metadata(token_address, chain_id).await;
+        }
 
-        self.back_sender.send(res)?;
+        let from_node = tokio::time::timeout(
+            Duration::from_secs(NODE_METADATA_TIMEOUT),
+            client.erc20_metadata(token_address, chain_id),
+        )
+        .await;
 
-        Ok(())
+        match from_node {
+            Ok(Ok(token)) => Ok(token),
+            Ok(Err(e)) => {
+                warn!("Failed to get token metadata from node: {}, trying block explorer", e);
+                erc20_from_explorer(token_address, chain_id).await
+            }
+            Err(_) => {
+                warn!("Node timed out getting token metadata, trying block explorer");
+                erc20_from_explorer(token_address, chain_id).await
+            }
+        }
     }
 
     /// Get the balance of an erc20 token
     ///
-    /// We first check if the balance is in the database, if not we make an rpc call
+    /// We first check if the balance at `block` is in the database, if not (or `force` is set) we make an rpc call
     async fn get_erc20_balance(
         &self,
         token: ERC20Token,
         owner: Address,
         chain_id: u64,
         block: u64,
-        client: Arc<WsClient>,
+        force: bool,
+        client: &impl NodeProvider,
     ) -> Result<(), anyhow::Error> {
-        let balance = token.balance_of(owner, client.clone()).await?;
-        if let Err(e) = self
-            .db
-            .insert_erc20_balance(owner, token.address, balance, chain_id, block)
-        {
-            error!("Failed to insert balance into db: {}", e);
-        }
+        let balance = self.erc20_balance_at(&token, owner, chain_id, block, force, client).await?;
 
         trace!("Got Balance {} For Token: {}", balance, token.address);
         let res = Response::erc20_balance(owner, token.address, balance, chain_id);
@@ -286,12 +1506,133 @@ impl Backend {
         Ok(())
     }
 
+    /// Get the balances of several erc20 tokens like [Backend::get_erc20_balance], sent together in one response
+    ///
+    /// A balance that can't be read is left out of the batch, the others are still sent
+    async fn get_erc20_balances(
+        &self,
+        tokens: Vec<ERC20Token>,
+        owner: Address,
+        chain_id: u64,
+        block: u64,
+        client: &impl NodeProvider,
+    ) -> Result<(), anyhow::Error> {
+        let mut balances = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            match self.erc20_balance_at(token, owner, chain_id, block, false, client).await {
+                Ok(balance) => balances.push((token.address, balance)),
+                Err(e) => warn!("Failed to get the balance of {}: {}", token.symbol, e),
+            }
+        }
+
+        self.back_sender.send(Response::balances_batch(chain_id, owner, balances))?;
+        Ok(())
+    }
+
+    /// The balance of `token` at `block`, from the database unless it is missing or `force` is set
+    async fn erc20_balance_at(
+        &self,
+        token: &ERC20Token,
+        owner: Address,
+        chain_id: u64,
+        block: u64,
+        force: bool,
+        client: &impl NodeProvider,
+    ) -> Result<U256, anyhow::Error> {
+        let cached = if force {
+            None
+        } else {
+            self.db.get_erc20_balance_at_block(owner, token.address, chain_id, block).ok().flatten()
+        };
+        let balance = if let Some(balance) = cached {
+            balance
+        } else {
+            let time = Instant::now();
+            let balance = client.erc20_balance(token, owner).await?;
+            self.record_rpc(1);
+            self.send_response(Response::latency(time.elapsed()));
+
+            if !self.read_only {
+                if let Err(e) = self
+                    .db
+                    .insert_erc20_balance(owner, token.address, balance, chain_id, block)
+                {
+                    error!("Failed to insert balance into db: {}", e);
+                }
+            }
+            balance
+        };
+
+        Ok(balance)
+    }
+
+    /// Encrypt and save the profile on a blocking thread so Argon2 does not stall the backend loop
+    ///
+    /// Answers with [Response::ProfileSaved]
     fn save_profile(&self, profile: Profile) -> Result<(), anyhow::Error> {
-        profile.encrypt_and_save()?;
-        trace!("Profile Saved");
+        if self.read_only {
+            let reason = "Zeus is running in read-only mode, the profile was not saved".to_string();
+            self.send_response(Response::ProfileSaved(Err(reason)));
+            return Ok(());
+        }
+
+        let save = self.profile_saves.fetch_add(1, Ordering::SeqCst) + 1;
+        let saves = self.profile_saves.clone();
+        let lock = self.profile_save_lock.clone();
+        let sender = self.back_sender.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _guard = lock.lock().unwrap();
+            // a newer save is queued behind this one and has the latest wallets
+            if saves.load(Ordering::SeqCst) != save {
+                return;
+            }
+
+            let res = profile.encrypt_and_save().map_err(|e| e.to_string());
+            if res.is_ok() {
+                trace!("Profile Saved");
+            }
+            if let Err(e) = sender.send(Response::ProfileSaved(res)) {
+                error!("Error sending response to front: {}", e);
+            }
+        });
         Ok(())
     }
 
+    /// Encrypt a wallet as a keystore file on a blocking thread, the KDF would stall the backend loop
+    ///
+    /// Answers with [Response::KeystoreExported]
+    fn export_keystore(&self, mut params: ExportKeystoreParams) {
+        let sender = self.back_sender.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let res = write_keystore(&params).map(|_| params.path.clone()).map_err(|e| e.to_string());
+            params.password.zeroize();
+            if let Err(e) = sender.send(Response::KeystoreExported(res)) {
+                error!("Error sending response to front: {}", e);
+            }
+        });
+    }
+
+    /// Decrypt the wallet of a keystore file on a blocking thread
+    ///
+    /// Answers with [Response::KeystoreImported]
+    fn import_keystore(&self, mut params: ImportKeystoreParams) {
+        let sender = self.back_sender.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let path = params.path.trim();
+            let res = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Could not read {}: {}", path, e))
+                .and_then(|json| Wallet::from_keystore(params.name.clone(), &json, &params.password))
+                .map_err(|e| e.to_string());
+            params.password.zeroize();
+            if let Err(e) = sender.send(Response::KeystoreImported(res)) {
+                error!("Error sending response to front: {}", e);
+            }
+        });
+    }
+
     async fn get_client(&mut self, chain_id: ChainId, rpcs: Vec<Rpc>) -> Result<(), anyhow::Error> {
         let url = rpcs
             .iter()
@@ -303,17 +1644,66 @@ impl Backend {
         let client = ProviderBuilder::new().on_ws(WsConnect::new(url)).await?;
         let client = Arc::new(client);
 
+        let time = Instant::now();
         let client_chain_id = client.get_chain_id().await?;
+        self.record_rpc(1);
+        let latency = time.elapsed();
+
         if client_chain_id != chain_id.id() {
             return Err(anyhow!("Chain ID Mismatch, Expected: {}, Got: {}", chain_id.id(), client_chain_id));
         }
 
-        let res = Response::client(Some(client), chain_id);
+        let res = Response::client(Ok(client), chain_id);
         self.back_sender.send(res)?;
+        self.send_response(Response::latency(latency));
         Ok(())
     }
 }
 
+/// The gas used by the `estimate`, or the gas limit of the [TxOverrides] if the estimate failed
+///
+/// Some calls can't be estimated, eg. they revert unless they get more gas than the node tries
+/// Encrypt the wallet of `params` and write it to a new file at its path
+fn write_keystore(params: &ExportKeystoreParams) -> Result<(), anyhow::Error> {
+    let json = params.profile.export_keystore(&params.wallet, &params.credentials, &params.password)?;
+
+    let path = params.path.trim();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| anyhow!("Could not create {}: {}", path, e))?;
+    file.write_all(json.as_bytes())?;
+    Ok(())
+}
+
+fn estimated_or_override<E: Into<anyhow::Error>>(estimate: Result<u128, E>, overrides: &TxOverrides) -> Result<u128, anyhow::Error> {
+    match (estimate, overrides.gas_limit) {
+        (Ok(gas_used), _) => Ok(gas_used),
+        (Err(e), Some(gas_limit)) => {
+            warn!("Could not estimate the gas, using the gas limit {}: {}", gas_limit, e.into());
+            Ok(gas_limit)
+        }
+        (Err(e), None) => Err(e.into()),
+    }
+}
+
+/// Swap `amount_in` of `token_in` on a V2 `pool` on a fork of `block`, from a caller we fund with the input
+fn simulate_quote(
+    pool: &Pool,
+    token_in: &ERC20Token,
+    amount_in: U256,
+    block: &Block,
+    block_number: u64,
+    client: Arc<WsClient>,
+) -> Result<SwapSimulation, anyhow::Error> {
+    let block_id = BlockId::Number(BlockNumberOrTag::Number(block_number));
+    let mut fork = FundedFork::new(client, Some(block_id), pool.chain_id, parse_ether("1000")?)?;
+    fork.fund_erc20(token_in.address, amount_in)?;
+
+    simulate_v2_swap(fork.fork_db(), block, pool.chain_id, pool, fork.caller.address, token_in, amount_in)
+}
+
 /*
 
 /// Dummy implementation
@@ -376,7 +1766,7 @@ async fn swap(
     fork_db: ForkDB
 ) -> Result<QuoteResult, anyhow::Error> {
     let slippage: f32 = params.slippage.parse().unwrap_or(1.0);
-    let amount_in = parse_wei(&params.amount_in, params.token_in.token.decimals)?;
+    let amount_in = parse_units(&params.amount_in, params.token_in.token.decimals)?;
 
     let pools = self.collect_pools(params.clone()).await?;
 
@@ -570,7 +1960,7 @@ fn sim_swap(
     params: SwapParams,
     evm: &mut Evm<'static, (), ForkDB>
 ) -> Result<U256, anyhow::Error> {
-    let amount_in = parse_wei(&params.amount_in, params.token_in.token.decimals)?;
+    let amount_in = parse_units(&params.amount_in, params.token_in.token.decimals)?;
 
     // approve the contract to spend token_in
     evm.tx_mut().caller = caller.address;
@@ -606,52 +1996,750 @@ fn sim_swap(
 }
     */
 
-/*
 /// Calculate token out price in usd
 ///
 /// This only works if `base_token` is WETH and `quote_token` is anything except of a stable coin
 ///
 /// ## Arguments
 ///
-/// `base_token` - ([ERC20Token], amount_in, price_in_usd)
+/// `base_token` - ([Currency], amount_in, price_in_usd)
 ///
-/// `quote_token` - ([ERC20Token], amount_out)
+/// `quote_token` - ([Currency], amount_out)
+///
+/// `amount_in` bought `amount_out`, so one quote token is worth `amount_in / amount_out` base tokens
 pub fn calc_quote_token_price(
-    base_token: (ERC20Token, U256, BigDecimal),
-    quote_token: (ERC20Token, U256),
-) -> BigDecimal {
+    base_token: (Currency, U256, BigDecimal),
+    quote_token: (Currency, U256),
+) -> Result<BigDecimal, anyhow::Error> {
+    let (base_currency, base_token_amount, base_token_usd_price) = base_token;
+    let (quote_currency, quote_token_amount) = quote_token;
+
+    if quote_token_amount.is_zero() {
+        return Err(anyhow!("Cannot price {}, the swap gives none of it", quote_currency.symbol()));
+    }
+
+    let amount_in = base_currency.to_big_decimal(base_token_amount);
+    let amount_out = quote_currency.to_big_decimal(quote_token_amount);
+
+    // Quote price in base token
+    let quote_price_in_base = amount_in / amount_out;
 
-    let base_token_erc20 = base_token.0;
-    let base_token_amount = base_token.1;
-    let base_token_usd_price = base_token.2;
+    Ok(quote_price_in_base * base_token_usd_price)
+}
 
-    let quote_token_erc20 = quote_token.0;
-    let quote_token_amount = quote_token.1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{response_channel, ResponseReceiver};
+    use std::{
+        cell::Cell,
+        path::PathBuf,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+    use zeus_chain::{alloy::primitives::address, get_amount_out, PoolState};
+
+    const CHAIN_ID: u64 = 1;
+    const BLOCK: u64 = 100;
+    const OWNER: Address = address!("1111111111111111111111111111111111111111");
+    const TOKEN: Address = address!("2222222222222222222222222222222222222222");
+    const CACHED_TOKEN: Address = address!("3333333333333333333333333333333333333333");
+
+    /// Every test gets its own database directory
+    static NEXT_DB: AtomicU64 = AtomicU64::new(0);
+
+    /// Answers with canned values and counts how many calls reached the "node"
+    #[derive(Default)]
+    struct MockProvider {
+        balance: U256,
+        token: Option<ERC20Token>,
+        code: Bytes,
+        reserves: (U256, U256),
+        allowance: U256,
+        balance_calls: Cell<u64>,
+        metadata_calls: Cell<u64>,
+        decimals_calls: Cell<u64>,
+    }
 
-    // convert amount_in to BigDecimal
-    let amount_in_str = base_token_erc20.big_dec(base_token_amount.to_string());
-    info!("Amount in str: {}", amount_in_str);
+    impl NodeProvider for MockProvider {
+        async fn eth_balance(&self, _owner: Address) -> Result<U256, anyhow::Error> {
+            self.balance_calls.set(self.balance_calls.get() + 1);
+            Ok(self.balance)
+        }
 
-    // Convert amount_out to BigDecimal
-    let amount_out_str = quote_token_erc20.big_dec(quote_token_amount.to_string());
-    info!("Amount out str: {}", amount_out_str);
+        async fn erc20_balance(&self, _token: &ERC20Token, _owner: Address) -> Result<U256, anyhow::Error> {
+            self.balance_calls.set(self.balance_calls.get() + 1);
+            Ok(self.balance)
+        }
 
+        async fn erc20_metadata(&self, _token: Address, _chain_id: u64) -> Result<ERC20Token, anyhow::Error> {
+            self.metadata_calls.set(self.metadata_calls.get() + 1);
+            self.token.clone().ok_or(anyhow!("No canned token"))
+        }
 
-    // Quote price in base token
-    let quote_price_in_base = amount_in_str.clone() / amount_out_str.clone();
-    info!("Quote price in base: {}", quote_price_in_base);
+        async fn erc20_decimals(&self, _token: Address) -> Result<u8, anyhow::Error> {
+            self.decimals_calls.set(self.decimals_calls.get() + 1);
+            self.token.as_ref().map(|token| token.decimals).ok_or(anyhow!("No canned token"))
+        }
+
+        async fn code(&self, _address: Address) -> Result<Bytes, anyhow::Error> {
+            Ok(self.code.clone())
+        }
+
+        async fn pool_state(&self, _pool: &Pool) -> Result<PoolState, anyhow::Error> {
+            let (reserve0, reserve1) = self.reserves;
+            Ok(PoolState::UniswapV2 { reserve0, reserve1 })
+        }
+
+        async fn erc20_allowance(&self, _token: &ERC20Token, _owner: Address, _spender: Address) -> Result<U256, anyhow::Error> {
+            Ok(self.allowance)
+        }
+    }
+
+    /// A backend on a fresh database, the directory is removed on drop
+    struct TestBackend {
+        backend: Backend,
+        responses: ResponseReceiver,
+        dir: PathBuf,
+    }
+
+    impl TestBackend {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "zeus-backend-test-{}-{}",
+                std::process::id(),
+                NEXT_DB.fetch_add(1, Ordering::Relaxed)
+            ));
+            let (back_sender, responses) = response_channel();
+            let (_, front_receiver) = unbounded();
+
+            let backend = Backend {
+                back_sender,
+                front_receiver,
+                db: ZeusDB::open(dir.clone()).unwrap(),
+                oracle_sender: None,
+                read_only: false,
+                profile_saves: Arc::new(AtomicU64::new(0)),
+                profile_save_lock: Arc::new(Mutex::new(())),
+                origin: None,
+                metadata_refresh: None,
+            };
+
+            Self { backend, responses, dir }
+        }
+
+        /// Drain the responses sent so far, ignoring the latency reports
+        fn responses(&self) -> Vec<Response> {
+            let mut responses = Vec::new();
+            self.responses.drain_within(usize::MAX, Duration::MAX, |res| {
+                if !matches!(res, Response::Latency(_)) {
+                    responses.push(res);
+                }
+            });
+            responses
+        }
+    }
+
+    impl Drop for TestBackend {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn token() -> ERC20Token {
+        ERC20Token {
+            chain_id: CHAIN_ID,
+            address: TOKEN,
+            symbol: "TKN".to_string(),
+            name: "Token".to_string(),
+            decimals: 18,
+            total_supply: U256::from(1_000_000),
+            icon: None,
+        }
+    }
+
+    /// A token with a unique address built from `index`
+    fn numbered_token(index: u64, symbol: &str) -> ERC20Token {
+        ERC20Token {
+            address: Address::left_padding_from(&(index + 1).to_be_bytes()),
+            symbol: symbol.to_string(),
+            ..token()
+        }
+    }
+
+    #[test]
+    fn erc20_tokens_are_ordered_by_symbol() {
+        let test = TestBackend::new();
+        for (index, symbol) in ["weth", "USDC", "Dai", "usdc", "AAVE"].iter().enumerate() {
+            test.backend.db.insert_erc20(numbered_token(index as u64, symbol), CHAIN_ID).unwrap();
+        }
+
+        let all = test.backend.db.get_all_erc20(CHAIN_ID).unwrap();
+        let symbols: Vec<&str> = all.iter().map(|token| token.symbol.as_str()).collect();
+        assert_eq!(symbols, ["AAVE", "Dai", "USDC", "usdc", "weth"]);
+
+        // pages of 2 add up to the same list, the same symbol is ordered by address
+        let paged: Vec<ERC20Token> = (0..3)
+            .flat_map(|page| test.backend.db.get_erc20_page(CHAIN_ID, "", page * 2, 2).unwrap())
+            .collect();
+        assert_eq!(paged, all);
+        // and the order does not change between calls
+        assert_eq!(test.backend.db.get_all_erc20(CHAIN_ID).unwrap(), all);
+    }
+
+    #[test]
+    fn currency_pages_stop_at_the_last_token() {
+        let test = TestBackend::new();
+        let count = db::CURRENCY_PAGE_SIZE + 1;
+        for index in 0..count {
+            test.backend.db.insert_erc20(numbered_token(index as u64, &format!("T{:03}", index)), CHAIN_ID).unwrap();
+        }
+
+        let (first, more) = test.backend.db.load_currency_page(CHAIN_ID, "", 0).unwrap();
+        assert!(more);
+        // the native currency leads the first page and is not counted in the offset
+        assert!(first[0].is_native());
+        assert_eq!(first.len(), db::CURRENCY_PAGE_SIZE + 1);
+
+        let (last, more) = test.backend.db.load_currency_page(CHAIN_ID, "", db::CURRENCY_PAGE_SIZE).unwrap();
+        assert!(!more);
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].symbol(), format!("T{:03}", count - 1));
+
+        let (past_the_end, more) = test.backend.db.load_currency_page(CHAIN_ID, "", count).unwrap();
+        assert!(past_the_end.is_empty() && !more);
+    }
+
+    #[test]
+    fn currency_pages_filter_by_query() {
+        let test = TestBackend::new();
+        for (index, symbol) in ["USDC", "USDT", "WETH", "100%"].iter().enumerate() {
+            test.backend.db.insert_erc20(numbered_token(index as u64, symbol), CHAIN_ID).unwrap();
+        }
+
+        let (found, more) = test.backend.db.load_currency_page(CHAIN_ID, "usd", 0).unwrap();
+        let symbols: Vec<String> = found.iter().map(|currency| currency.symbol()).collect();
+        assert_eq!(symbols, ["USDC", "USDT"]);
+        assert!(!more);
+
+        // `%` is matched literally instead of matching everything
+        let (found, _) = test.backend.db.load_currency_page(CHAIN_ID, "%", 0).unwrap();
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn balance_history_is_kept_until_the_limit() {
+        let blocks: Vec<u64> = (0..db::BALANCE_HISTORY_POINTS as u64).collect();
+        assert!(db::balance_blocks_to_remove(&blocks).is_empty());
+    }
+
+    #[test]
+    fn balance_history_keeps_the_recent_points_and_downsamples_the_rest() {
+        let blocks: Vec<u64> = (0..2_000u64).map(|i| i * 3).collect();
+        let remove = db::balance_blocks_to_remove(&blocks);
+        let kept: Vec<u64> = blocks.iter().copied().filter(|b| !remove.contains(b)).collect();
+
+        assert!(kept.len() <= db::BALANCE_HISTORY_POINTS);
+        // the recent window is untouched and the oldest point survives
+        assert!(blocks[blocks.len() - db::RECENT_BALANCE_POINTS..].iter().all(|b| kept.contains(b)));
+        assert_eq!(kept[0], 0);
+
+        // the older points are spread evenly
+        let older = &kept[..kept.len() - db::RECENT_BALANCE_POINTS];
+        let gaps: Vec<u64> = older.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps.iter().max().unwrap() - gaps.iter().min().unwrap() <= 3);
+    }
+
+    #[tokio::test]
+    async fn eth_balance_history_is_pruned_per_wallet() {
+        let test = TestBackend::new();
+        let other = address!("4444444444444444444444444444444444444444");
+        test.backend.db.insert_eth_balance(other, U256::from(1), CHAIN_ID, 1).unwrap();
+
+        let points = db::BALANCE_HISTORY_POINTS as u64 + 50;
+        for block in 1..=points {
+            test.backend.db.insert_eth_balance(OWNER, U256::from(block), CHAIN_ID, block).unwrap();
+        }
+
+        let history = test.backend.db.load_eth_balance_history(OWNER, CHAIN_ID).unwrap();
+        assert!(history.len() <= db::BALANCE_HISTORY_POINTS);
+        assert_eq!(history.last(), Some(&(points, U256::from(points))));
+        // other wallets on the same chain keep their balance
+        assert_eq!(test.backend.db.load_eth_balance_history(other, CHAIN_ID).unwrap(), vec![(1, U256::from(1))]);
+    }
+
+    #[tokio::test]
+    async fn eth_balance_miss_calls_the_node_and_caches() {
+        let mut test = TestBackend::new();
+        let client = MockProvider { balance: U256::from(5), ..Default::default() };
+
+        test.backend.get_eth_balance(OWNER, CHAIN_ID, BLOCK, false, &client).await.unwrap();
+
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(test.responses()[..], [Response::EthBalance(b)] if b == U256::from(5)));
+        assert_eq!(test.backend.db.get_eth_balance_at_block(OWNER, CHAIN_ID, BLOCK).unwrap(), Some(U256::from(5)));
+    }
+
+    #[tokio::test]
+    async fn eth_balance_hit_skips_the_node() {
+        let mut test = TestBackend::new();
+        test.backend.db.insert_eth_balance(OWNER, U256::from(7), CHAIN_ID, BLOCK).unwrap();
+        let client = MockProvider { balance: U256::from(5), ..Default::default() };
+
+        test.backend.get_eth_balance(OWNER, CHAIN_ID, BLOCK, false, &client).await.unwrap();
+
+        assert_eq!(client.balance_calls.get(), 0);
+        assert!(matches!(test.responses()[..], [Response::EthBalance(b)] if b == U256::from(7)));
+    }
+
+    #[tokio::test]
+    async fn forced_eth_balance_replaces_the_cached_one() {
+        let mut test = TestBackend::new();
+        test.backend.db.insert_eth_balance(OWNER, U256::from(7), CHAIN_ID, BLOCK).unwrap();
+        let client = MockProvider { balance: U256::from(5), ..Default::default() };
+
+        test.backend.get_eth_balance(OWNER, CHAIN_ID, BLOCK, true, &client).await.unwrap();
+
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(test.responses()[..], [Response::EthBalance(b)] if b == U256::from(5)));
+        assert_eq!(test.backend.db.get_eth_balance_at_block(OWNER, CHAIN_ID, BLOCK).unwrap(), Some(U256::from(5)));
+    }
+
+    #[tokio::test]
+    async fn erc20_balance_miss_calls_the_node_and_caches() {
+        let test = TestBackend::new();
+        let client = MockProvider { balance: U256::from(42), ..Default::default() };
+
+        test.backend.get_erc20_balance(token(), OWNER, CHAIN_ID, BLOCK, false, &client).await.unwrap();
+
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Balance(res)] if res.balance == U256::from(42)));
+        assert_eq!(
+            test.backend.db.get_erc20_balance_at_block(OWNER, TOKEN, CHAIN_ID, BLOCK).unwrap(),
+            Some(U256::from(42))
+        );
+    }
+
+    #[tokio::test]
+    async fn erc20_balance_hit_skips_the_node() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20_balance(OWNER, TOKEN, U256::from(9), CHAIN_ID, BLOCK).unwrap();
+        let client = MockProvider { balance: U256::from(42), ..Default::default() };
+
+        test.backend.get_erc20_balance(token(), OWNER, CHAIN_ID, BLOCK, false, &client).await.unwrap();
+
+        assert_eq!(client.balance_calls.get(), 0);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Balance(res)] if res.balance == U256::from(9)));
+    }
+
+    #[tokio::test]
+    async fn erc20_balances_are_sent_in_one_batch() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20_balance(OWNER, CACHED_TOKEN, U256::from(9), CHAIN_ID, BLOCK).unwrap();
+        let client = MockProvider { balance: U256::from(42), ..Default::default() };
+        let cached = ERC20Token { address: CACHED_TOKEN, ..token() };
+
+        test.backend.get_erc20_balances(vec![token(), cached], OWNER, CHAIN_ID, BLOCK, &client).await.unwrap();
+
+        // only the missing balance reached the node
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(
+            &test.responses()[..],
+            [Response::BalancesBatch { chain_id: CHAIN_ID, owner: OWNER, balances }]
+                if balances == &[(TOKEN, U256::from(42)), (CACHED_TOKEN, U256::from(9))]
+        ));
+    }
+
+    #[tokio::test]
+    async fn forced_erc20_balance_replaces_the_cached_one() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20_balance(OWNER, TOKEN, U256::from(9), CHAIN_ID, BLOCK).unwrap();
+        let client = MockProvider { balance: U256::from(42), ..Default::default() };
+
+        test.backend.get_erc20_balance(token(), OWNER, CHAIN_ID, BLOCK, true, &client).await.unwrap();
+
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Balance(res)] if res.balance == U256::from(42)));
+        assert_eq!(
+            test.backend.db.get_erc20_balance_at_block(OWNER, TOKEN, CHAIN_ID, BLOCK).unwrap(),
+            Some(U256::from(42))
+        );
+    }
+
+    #[tokio::test]
+    async fn erc20_token_miss_fetches_the_metadata_and_caches() {
+        let test = TestBackend::new();
+        let client = MockProvider { balance: U256::from(3), token: Some(token()), ..Default::default() };
+
+        test.backend
+            .get_erc20_token("id".to_string(), OWNER, TOKEN, &client, CHAIN_ID, TokenMetadataSource::Node, false)
+            .await
+            .unwrap();
+
+        assert_eq!(client.metadata_calls.get(), 1);
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Token(res)] if res.token == token() && res.balance == U256::from(3) && res.added));
+        assert_eq!(test.backend.db.get_erc20(TOKEN, CHAIN_ID).unwrap(), token());
+    }
+
+    #[tokio::test]
+    async fn erc20_token_hit_skips_the_metadata_calls() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        let client = MockProvider { balance: U256::from(3), ..Default::default() };
+
+        test.backend
+            .get_erc20_token("id".to_string(), OWNER, TOKEN, &client, CHAIN_ID, TokenMetadataSource::Node, false)
+            .await
+            .unwrap();
+
+        // the balance is always fresh, only the metadata is cached
+        assert_eq!(client.metadata_calls.get(), 0);
+        assert_eq!(client.balance_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Token(res)] if res.token == token() && !res.added));
+    }
+
+    #[tokio::test]
+    async fn forced_erc20_token_rereads_the_metadata() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        let renamed = ERC20Token { symbol: "NEW".to_string(), ..token() };
+        let client = MockProvider { balance: U256::from(3), token: Some(renamed.clone()), ..Default::default() };
+
+        test.backend
+            .get_erc20_token("id".to_string(), OWNER, TOKEN, &client, CHAIN_ID, TokenMetadataSource::Node, true)
+            .await
+            .unwrap();
+
+        assert_eq!(client.metadata_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Token(res)] if res.token == renamed));
+        assert_eq!(test.backend.db.get_erc20(TOKEN, CHAIN_ID).unwrap().symbol, "NEW");
+    }
+
+    #[tokio::test]
+    async fn erc20_token_decimals_mismatch_updates_the_row() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        let upgraded = ERC20Token { decimals: 6, ..token() };
+        let client = MockProvider { token: Some(upgraded.clone()), ..Default::default() };
+
+        test.backend
+            .get_erc20_token("id".to_string(), OWNER, TOKEN, &client, CHAIN_ID, TokenMetadataSource::Node, false)
+            .await
+            .unwrap();
+
+        // only the decimals are read again
+        assert_eq!(client.decimals_calls.get(), 1);
+        assert_eq!(client.metadata_calls.get(), 0);
+        assert!(matches!(&test.responses()[..], [Response::ERC20Token(res)] if res.token == upgraded));
+        assert_eq!(test.backend.db.get_erc20(TOKEN, CHAIN_ID).unwrap().decimals, 6);
+    }
 
-    // Calculate the price of the quote token in USD
-    let quote_price_usd = quote_price_in_base * base_token_usd_price.clone();
+    #[tokio::test]
+    async fn erc20_token_with_too_many_decimals_is_rejected() {
+        let test = TestBackend::new();
+        let broken = ERC20Token { decimals: 77, ..token() };
+        let client = MockProvider { token: Some(broken), ..Default::default() };
 
-    // quote total worth in usd
-    let quote_total_worth_usd = quote_price_usd.clone() * amount_out_str.clone();
-    info!("Quote total worth usd: {}", quote_total_worth_usd);
+        let res = test
+            .backend
+            .get_erc20_token("id".to_string(), OWNER, TOKEN, &client, CHAIN_ID, TokenMetadataSource::Node, false)
+            .await;
 
-    let base_total_worth = base_token_usd_price * amount_in_str;
-    info!("Base total worth: {}", base_total_worth);
+        assert!(res.is_err());
+        assert!(test.responses().is_empty());
+        assert!(test.backend.db.get_erc20(TOKEN, CHAIN_ID).is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_currency_prefers_the_shared_cache() {
+        // the shared cache is global, use a token no other test resolves
+        let cached = ERC20Token { address: CACHED_TOKEN, ..token() };
+        SHARED_CACHE.write().unwrap().add_currency(CHAIN_ID, Currency::new_erc20(cached.clone()));
+        let test = TestBackend::new();
+        let client = MockProvider { token: Some(token()), ..Default::default() };
+
+        let currency = test
+            .backend
+            .resolve_currency(CHAIN_ID, CACHED_TOKEN, &client, TokenMetadataSource::Node)
+            .await
+            .unwrap();
+
+        assert_eq!(currency, Currency::new_erc20(cached));
+        assert_eq!(client.metadata_calls.get(), 0);
+        assert!(test.backend.db.get_erc20(CACHED_TOKEN, CHAIN_ID).is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_currency_falls_back_to_the_database() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        let client = MockProvider::default();
+
+        let currency = test
+            .backend
+            .resolve_currency(CHAIN_ID, TOKEN, &client, TokenMetadataSource::Node)
+            .await
+            .unwrap();
+
+        assert_eq!(currency, Currency::new_erc20(token()));
+        assert_eq!(client.metadata_calls.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn resolve_currency_fetches_from_the_node_and_saves() {
+        let test = TestBackend::new();
+        let client = MockProvider { token: Some(token()), ..Default::default() };
+
+        let currency = test
+            .backend
+            .resolve_currency(CHAIN_ID, TOKEN, &client, TokenMetadataSource::Node)
+            .await
+            .unwrap();
+
+        assert_eq!(currency, Currency::new_erc20(token()));
+        assert_eq!(client.metadata_calls.get(), 1);
+        assert_eq!(test.backend.db.get_erc20(TOKEN, CHAIN_ID).unwrap(), token());
+    }
+
+    /// A refresh of the test database that never has to wait for the rpc budget
+    fn metadata_refresh(test: &TestBackend, cancelled: bool) -> MetadataRefresh {
+        MetadataRefresh::new(test.backend.db.clone(), test.backend.back_sender.clone(), Arc::new(AtomicBool::new(cancelled)))
+    }
+
+    #[tokio::test]
+    async fn stale_metadata_is_refreshed_and_reported() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        test.backend.db.insert_erc20(numbered_token(10, "FRESH"), CHAIN_ID).unwrap();
+        test.backend.db.set_erc20_refreshed(TOKEN, CHAIN_ID, 0).unwrap();
+
+        let renamed = ERC20Token { symbol: "TKN2".to_string(), decimals: 6, ..token() };
+        let client = MockProvider { token: Some(renamed.clone()), ..Default::default() };
+        metadata_refresh(&test, false).run(&client, CHAIN_ID).await.unwrap();
+
+        // only the stale token was read again
+        assert_eq!(client.metadata_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::TokenRefreshed(token)] if *token == renamed));
+        assert_eq!(test.backend.db.get_erc20(TOKEN, CHAIN_ID).unwrap(), renamed);
+        assert_eq!(test.backend.db.stale_erc20(CHAIN_ID, u64::MAX / 2).unwrap().len(), 2);
+        assert!(test.backend.db.stale_erc20(CHAIN_ID, 1).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unchanged_metadata_is_only_marked_as_refreshed() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        test.backend.db.set_erc20_refreshed(TOKEN, CHAIN_ID, 0).unwrap();
+
+        let client = MockProvider { token: Some(token()), ..Default::default() };
+        metadata_refresh(&test, false).run(&client, CHAIN_ID).await.unwrap();
+
+        assert_eq!(client.metadata_calls.get(), 1);
+        assert!(test.responses().is_empty());
+        assert!(test.backend.db.stale_erc20(CHAIN_ID, 1).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_metadata_refresh_makes_no_calls() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        test.backend.db.set_erc20_refreshed(TOKEN, CHAIN_ID, 0).unwrap();
+
+        let client = MockProvider { token: Some(token()), ..Default::default() };
+        metadata_refresh(&test, true).run(&client, CHAIN_ID).await.unwrap();
+
+        assert_eq!(client.metadata_calls.get(), 0);
+        assert_eq!(test.backend.db.stale_erc20(CHAIN_ID, 1).unwrap().len(), 1);
+    }
+
+    /// `whole` units of a currency with `decimals` in its smallest unit
+    fn units(whole: u64, decimals: u8) -> U256 {
+        U256::from(whole) * U256::from(10).pow(U256::from(decimals))
+    }
+
+    fn assert_close(actual: BigDecimal, expected: &str) {
+        let expected: BigDecimal = expected.parse().unwrap();
+        let tolerance: BigDecimal = "0.000000001".parse().unwrap();
+        assert!((actual.clone() - &expected).abs() < tolerance, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn quote_token_price_with_fewer_decimals_than_weth() {
+        let weth = Currency::new_native(CHAIN_ID).wrapped();
+        let quote = ERC20Token { decimals: 6, ..token() };
+
+        // 1 WETH at $3000 bought 1500 tokens, one token is worth $2
+        let price = calc_quote_token_price(
+            (Currency::new_erc20(weth), units(1, 18), BigDecimal::from(3000)),
+            (Currency::new_erc20(quote), units(1500, 6)),
+        );
+        assert_close(price.unwrap(), "2");
+    }
+
+    #[test]
+    fn quote_token_price_with_18_decimals() {
+        let weth = Currency::new_native(CHAIN_ID).wrapped();
 
+        // 0.5 WETH at $3000 bought 250,000 tokens
+        let price = calc_quote_token_price(
+            (Currency::new_erc20(weth), units(5, 17), BigDecimal::from(3000)),
+            (Currency::new_erc20(token()), units(250_000, 18)),
+        );
+        assert_close(price.unwrap(), "0.006");
+    }
+
+    #[test]
+    fn a_quote_without_output_cannot_be_priced() {
+        let weth = Currency::new_native(CHAIN_ID).wrapped();
+        let price = calc_quote_token_price(
+            (Currency::new_erc20(weth), units(1, 18), BigDecimal::from(3000)),
+            (Currency::new_erc20(token()), U256::ZERO),
+        );
+        assert!(price.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_v2_quote_falls_back_to_the_reserves_if_the_swap_cant_be_simulated() {
+        let test = TestBackend::new();
+        let weth = Currency::new_native(CHAIN_ID).wrapped();
+        let pool = Pool::new(address!("4444444444444444444444444444444444444444"), token(), weth, PoolVariant::UniswapV2, 3000, CHAIN_ID);
+        let client = MockProvider { reserves: (units(1_000, 18), units(10, 18)), ..Default::default() };
+        let amount_in = units(1, 18);
+
+        // eg. the balance slot of the token was not found on the fork
+        let not_funded = Err(anyhow!("Could not find the balance slot of {}", TOKEN));
+        let (amount_out, sim) = test.backend.v2_quote(&pool, &token(), amount_in, not_funded, &client).await.unwrap();
+        assert_eq!(amount_out, get_amount_out(amount_in, units(1_000, 18), units(10, 18), 3000));
+        assert!(sim.is_none());
+
+        // a simulated swap is used as is, with its tax
+        let simulated = SwapSimulation { amount_out: U256::from(90), expected_out: U256::from(100), gas_used: 120_000 };
+        let (amount_out, sim) = test.backend.v2_quote(&pool, &token(), amount_in, Ok(simulated), &client).await.unwrap();
+        assert_eq!(amount_out, U256::from(90));
+        assert_eq!(sim.unwrap().tax(), 0.1);
+    }
+
+    #[tokio::test]
+    async fn the_router_allowance_is_only_read_for_a_token() {
+        let test = TestBackend::new();
+        let router = address!("5555555555555555555555555555555555555555");
+        let client = MockProvider { allowance: U256::from(7), ..Default::default() };
+
+        let allowance = test.backend.router_allowance(&Currency::new_erc20(token()), OWNER, router, &client).await;
+        assert_eq!(allowance, Some(U256::from(7)));
+
+        // the router takes the native currency with the call
+        let allowance = test.backend.router_allowance(&Currency::new_native(CHAIN_ID), OWNER, router, &client).await;
+        assert_eq!(allowance, None);
+    }
+
+    #[test]
+    fn a_failed_estimate_falls_back_to_the_gas_limit_override() {
+        let reverted = || Err::<u128, _>(anyhow!("execution reverted"));
+        let manual = TxOverrides { gas_limit: Some(300_000), ..TxOverrides::default() };
+
+        assert_eq!(estimated_or_override(reverted(), &manual).unwrap(), 300_000);
+        assert!(estimated_or_override(reverted(), &TxOverrides::default()).is_err());
+        // the estimate is kept for the simulation, the override still sets the limit of the transaction
+        assert_eq!(estimated_or_override(Ok::<_, anyhow::Error>(50_000), &manual).unwrap(), 50_000);
+    }
+
+    fn watch(condition: WatchCondition, target: f64) -> PriceWatch {
+        let token = |address: Address, symbol: &str, decimals: u8| ERC20Token {
+            chain_id: CHAIN_ID,
+            address,
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals,
+            ..Default::default()
+        };
+        PriceWatch { id: 0, chain_id: CHAIN_ID, token_in: token(TOKEN, "TKN", 18), token_out: token(CACHED_TOKEN, "USD", 6), condition, target }
+    }
+
+    #[test]
+    fn watches_survive_a_restart() {
+        let test = TestBackend::new();
+        test.backend.add_watch(watch(WatchCondition::Above, 2.5)).unwrap();
+        test.backend.add_watch(watch(WatchCondition::Below, 1.5)).unwrap();
+        assert!(matches!(&test.responses()[..], [Response::Watches(w1), Response::Watches(w2)] if w1.len() == 1 && w2.len() == 2));
+
+        let saved = ZeusDB::open(test.dir.clone()).unwrap().load_watches().unwrap();
+        let expected: Vec<PriceWatch> = [(1, WatchCondition::Above, 2.5), (2, WatchCondition::Below, 1.5)]
+            .into_iter()
+            .map(|(id, condition, target)| PriceWatch { id, ..watch(condition, target) })
+            .collect();
+        assert_eq!(saved, expected);
+
+        test.backend.cancel_watch(1).unwrap();
+        let saved = ZeusDB::open(test.dir.clone()).unwrap().load_watches().unwrap();
+        assert_eq!(saved, expected[1..]);
+    }
+
+    #[test]
+    fn a_triggered_watch_is_removed_and_never_sends_a_transaction() {
+        let test = TestBackend::new();
+        test.backend.add_watch(watch(WatchCondition::Above, 2.5)).unwrap();
+        test.responses();
+        let saved = test.backend.db.load_watches().unwrap().remove(0);
+
+        assert!(!test.backend.on_watch_price(&saved, 2.49).unwrap());
+        assert!(test.responses().is_empty());
+        assert_eq!(test.backend.db.load_watches().unwrap(), std::slice::from_ref(&saved));
+
+        assert!(test.backend.on_watch_price(&saved, 2.5).unwrap());
+        // only the notification, no transaction is built or sent
+        assert!(matches!(&test.responses()[..], [Response::WatchTriggered(w, price)] if *w == saved && *price == 2.5));
+        assert!(test.backend.db.load_watches().unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_read_only_backend_sends_no_transactions() {
+        let mut test = TestBackend::new();
+        assert!(test.backend.check_can_send().is_ok());
+
+        test.backend.read_only = true;
+        let err = test.backend.check_can_send().unwrap_err();
+        assert!(err.to_string().contains("read-only"));
+    }
 
-    quote_price_usd
+    /// `PUSH4 <selector>` for `transfer`, `balanceOf` and `totalSupply`
+    fn erc20_code() -> Bytes {
+        Bytes::from_static(&[0x63, 0xa9, 0x05, 0x9c, 0xbb, 0x63, 0x70, 0xa0, 0x82, 0x31, 0x63, 0x18, 0x16, 0x0d, 0xdd])
+    }
+
+    #[tokio::test]
+    async fn a_known_token_contract_is_reported_without_the_node() {
+        let test = TestBackend::new();
+        test.backend.db.insert_erc20(token(), CHAIN_ID).unwrap();
+        test.backend.db.insert_contract(TOKEN, CHAIN_ID).unwrap();
+        let client = MockProvider::default();
+
+        test.backend.check_contract(TOKEN, CHAIN_ID, &client).await.unwrap();
+
+        assert_eq!(client.metadata_calls.get(), 0);
+        assert!(matches!(&test.responses()[..], [Response::ContractCheck(CHAIN_ID, TOKEN, true, Some(symbol))] if symbol == "TKN"));
+    }
+
+    #[tokio::test]
+    async fn erc20_code_is_checked_and_the_token_is_stored() {
+        let test = TestBackend::new();
+        let client = MockProvider { token: Some(token()), code: erc20_code(), ..Default::default() };
+
+        test.backend.check_contract(TOKEN, CHAIN_ID, &client).await.unwrap();
+
+        assert_eq!(client.metadata_calls.get(), 1);
+        assert!(matches!(&test.responses()[..], [Response::ContractCheck(CHAIN_ID, TOKEN, true, Some(symbol))] if symbol == "TKN"));
+        assert!(test.backend.db.erc20_exists(TOKEN, CHAIN_ID).unwrap());
+        assert!(test.backend.db.is_known_contract(TOKEN, CHAIN_ID).unwrap());
+
+        // any other contract or an EOA is not a token
+        let router = MockProvider { code: Bytes::from_static(&[0x60, 0x80]), ..Default::default() };
+        test.backend.check_contract(CACHED_TOKEN, CHAIN_ID, &router).await.unwrap();
+        test.backend.check_contract(OWNER, CHAIN_ID, &MockProvider::default()).await.unwrap();
+        assert!(matches!(
+            &test.responses()[..],
+            [Response::ContractCheck(_, CACHED_TOKEN, true, None), Response::ContractCheck(_, OWNER, false, None)]
+        ));
+        assert_eq!(router.metadata_calls.get(), 0);
+    }
 }
-    */
@@ -0,0 +1,128 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tracing::{info, warn};
+use zeus_chain::{defi_types::currency::erc20::ERC20Token, utils::record_rpc, RpcBudget, RpcOrigin};
+use zeus_shared_types::{notify_error_text, Language, UiText};
+
+use crate::{channel::ResponseSender, db::ZeusDB, provider::NodeProvider, types::Response, ERC20_METADATA_CALLS};
+
+/// The metadata of a stored token is read again once it is older than this
+pub const METADATA_MAX_AGE_DAYS: u64 = 30;
+
+/// The refresh runs in the background, it never makes more rpc calls than this per minute
+pub const METADATA_REFRESH_CALLS_PER_MIN: u64 = 60;
+
+/// How long a paced refresh sleeps at most before it checks if it was cancelled
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The warning for a token whose decimals changed, amounts parsed with the old ones are off
+pub fn decimals_changed_msg(stored: &ERC20Token, fetched: &ERC20Token) -> Option<UiText> {
+    (stored.decimals != fetched.decimals).then(|| {
+        UiText::key("error.decimals_changed")
+            .with("symbol", &stored.symbol)
+            .with("decimals", fetched.decimals)
+            .with("previous", stored.decimals)
+    })
+}
+
+/// Reads the metadata of the stored tokens of a chain again, see [METADATA_MAX_AGE_DAYS]
+///
+/// The rows that changed are updated and sent to the frontend with [Response::TokenRefreshed]
+pub(crate) struct MetadataRefresh {
+    db: ZeusDB,
+    back_sender: ResponseSender,
+    cancel: Arc<AtomicBool>,
+    budget: RpcBudget,
+}
+
+impl MetadataRefresh {
+    pub fn new(db: ZeusDB, back_sender: ResponseSender, cancel: Arc<AtomicBool>) -> Self {
+        Self {
+            db,
+            back_sender,
+            cancel,
+            budget: RpcBudget::new(METADATA_REFRESH_CALLS_PER_MIN),
+        }
+    }
+
+    /// Refresh the stale tokens of `chain_id` one at a time until they are done or the refresh is cancelled
+    pub async fn run(&mut self, client: &impl NodeProvider, chain_id: u64) -> Result<(), anyhow::Error> {
+        let before = now().saturating_sub(METADATA_MAX_AGE_DAYS * 24 * 60 * 60);
+        let stale = self.db.stale_erc20(chain_id, before)?;
+        if stale.is_empty() {
+            return Ok(());
+        }
+        info!("Refreshing the metadata of {} tokens on chain {}", stale.len(), chain_id);
+
+        for stored in stale {
+            if !self.wait_for_budget(ERC20_METADATA_CALLS).await {
+                info!("Metadata refresh cancelled");
+                return Ok(());
+            }
+
+            record_rpc(RpcOrigin::Discovery, ERC20_METADATA_CALLS);
+            // a token the node can't read is tried again on the next run
+            let fetched = match client.erc20_metadata(stored.address, chain_id).await {
+                Ok(token) => token,
+                Err(e) => {
+                    warn!("Could not refresh the metadata of {}: {}", stored.symbol, e);
+                    continue;
+                }
+            };
+
+            if let Some(updated) = self.apply(&stored, fetched)? {
+                self.back_sender.send(Response::token_refreshed(updated))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the row if the metadata changed, returns the updated token
+    fn apply(&self, stored: &ERC20Token, fetched: ERC20Token) -> Result<Option<ERC20Token>, anyhow::Error> {
+        let changed = fetched.symbol != stored.symbol
+            || fetched.name != stored.name
+            || fetched.decimals != stored.decimals
+            || fetched.total_supply != stored.total_supply;
+
+        // keep what we have rather than a token that looks broken
+        if !changed || fetched.check_decimals().is_err() {
+            self.db.set_erc20_refreshed(stored.address, stored.chain_id, now())?;
+            return Ok(None);
+        }
+
+        if let Some(msg) = decimals_changed_msg(stored, &fetched) {
+            warn!("{}", msg.translate_to(Language::English));
+            notify_error_text(msg);
+        }
+        if fetched.symbol != stored.symbol {
+            info!("{} is now called {}", stored.symbol, fetched.symbol);
+        }
+
+        self.db.upsert_erc20(&fetched, stored.chain_id)?;
+        Ok(Some(fetched))
+    }
+
+    /// Wait until the budget allows `calls` more calls, `false` if the refresh was cancelled meanwhile
+    async fn wait_for_budget(&mut self, calls: u64) -> bool {
+        loop {
+            if self.cancel.load(Ordering::Relaxed) {
+                return false;
+            }
+            match self.budget.take(calls, Instant::now()) {
+                None => return true,
+                Some(wait) => tokio::time::sleep(wait.min(CANCEL_CHECK_INTERVAL)).await,
+            }
+        }
+    }
+}
+
+/// Unix seconds
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs())
+}
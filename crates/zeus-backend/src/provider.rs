@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use zeus_chain::{
+    alloy::{
+        primitives::{Address, Bytes, U256},
+        providers::Provider,
+    },
+    ERC20Token, Pool, PoolState, WsClient,
+};
+
+/// The node calls the backend handlers make
+///
+/// The handlers are generic over this so they can be tested against canned responses instead of a live [WsClient]
+pub(crate) trait NodeProvider {
+    /// The native balance of `owner` at the latest block
+    async fn eth_balance(&self, owner: Address) -> Result<U256, anyhow::Error>;
+
+    /// The balance of `owner` in `token` at the latest block
+    async fn erc20_balance(&self, token: &ERC20Token, owner: Address) -> Result<U256, anyhow::Error>;
+
+    /// Read the symbol, name, decimals and total supply of `token`
+    async fn erc20_metadata(&self, token: Address, chain_id: u64) -> Result<ERC20Token, anyhow::Error>;
+
+    /// Read only the decimals of `token`
+    async fn erc20_decimals(&self, token: Address) -> Result<u8, anyhow::Error>;
+
+    /// The runtime code at `address`, empty for an EOA
+    async fn code(&self, address: Address) -> Result<Bytes, anyhow::Error>;
+
+    /// The reserves (V2) or the sqrt price (V3) of `pool` at the latest block
+    async fn pool_state(&self, pool: &Pool) -> Result<PoolState, anyhow::Error>;
+
+    /// How much of `token` `spender` can move from `owner`
+    async fn erc20_allowance(&self, token: &ERC20Token, owner: Address, spender: Address) -> Result<U256, anyhow::Error>;
+}
+
+impl NodeProvider for Arc<WsClient> {
+    async fn eth_balance(&self, owner: Address) -> Result<U256, anyhow::Error> {
+        Ok(self.get_balance(owner).await?)
+    }
+
+    async fn erc20_balance(&self, token: &ERC20Token, owner: Address) -> Result<U256, anyhow::Error> {
+        token.balance_of(owner, self.clone()).await
+    }
+
+    async fn erc20_metadata(&self, token: Address, chain_id: u64) -> Result<ERC20Token, anyhow::Error> {
+        ERC20Token::new(token, self.clone(), chain_id, None).await
+    }
+
+    async fn erc20_decimals(&self, token: Address) -> Result<u8, anyhow::Error> {
+        ERC20Token::decimals(token, self.clone()).await
+    }
+
+    async fn code(&self, address: Address) -> Result<Bytes, anyhow::Error> {
+        Ok(self.get_code_at(address).await?)
+    }
+
+    async fn pool_state(&self, pool: &Pool) -> Result<PoolState, anyhow::Error> {
+        pool.fetch_state(self.clone()).await
+    }
+
+    async fn erc20_allowance(&self, token: &ERC20Token, owner: Address, spender: Address) -> Result<U256, anyhow::Error> {
+        token.allowance(owner, spender, self.clone()).await
+    }
+}
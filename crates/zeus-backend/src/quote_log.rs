@@ -0,0 +1,162 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use zeus_chain::{defi_types::currency::Currency, format_units, serde_json};
+use zeus_shared_types::QuoteResult;
+
+/// The file quotes are appended to when [zeus_shared_types::Settings::log_quotes] is on
+pub const QUOTE_LOG_FILE: &str = "quotes.csv";
+
+/// Past this size the log is moved to `quotes.csv.1`, replacing the previous one, and a new file is started
+pub const MAX_QUOTE_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+const HEADER: &str =
+    "timestamp,chain_id,pair,token_in,token_out,amount_in,amount_out,minimum_received,price_impact,gas_used,block,route";
+
+/// Append `quote` to the log at `path`, `timestamp` is in unix seconds
+///
+/// A log larger than `max_bytes` is rotated first, a new log starts with the header
+pub fn log_quote(path: &Path, quote: &QuoteResult, timestamp: u64, max_bytes: u64) -> Result<(), anyhow::Error> {
+    if fs::metadata(path).is_ok_and(|meta| meta.len() >= max_bytes) {
+        fs::rename(path, rotated_path(path))?;
+    }
+
+    let new_file = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if new_file {
+        writeln!(file, "{}", HEADER)?;
+    }
+    writeln!(file, "{}", row(quote, timestamp)?)?;
+    Ok(())
+}
+
+/// eg. `quotes.csv.1`
+pub fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    PathBuf::from(rotated)
+}
+
+/// One line of the log, the route is kept as JSON
+fn row(quote: &QuoteResult, timestamp: u64) -> Result<String, anyhow::Error> {
+    let input = &quote.input_token;
+    let output = &quote.output_token;
+    let pair = format!("{}/{}", input.currency.symbol(), output.currency.symbol());
+    let price_impact = quote.price_impact.map(|impact| format!("{:.6}", impact)).unwrap_or_default();
+
+    let fields = [
+        timestamp.to_string(),
+        input.currency.chain_id().to_string(),
+        pair,
+        token_column(&input.currency),
+        token_column(&output.currency),
+        format_units(input.amount.amount_or_zero(), input.decimals()),
+        format_units(quote.amount_out, output.decimals()),
+        format_units(quote.minimum_received, output.decimals()),
+        price_impact,
+        quote.gas_used.to_string(),
+        quote.block_number.to_string(),
+        serde_json::to_string(&quote.route)?,
+    ];
+    Ok(fields.iter().map(|field| escape(field)).collect::<Vec<_>>().join(","))
+}
+
+/// The token address, `native` for the native currency
+fn token_column(currency: &Currency) -> String {
+    currency.erc20().map_or("native".to_string(), |token| token.address.to_string())
+}
+
+/// Quote a field that has a separator, a quote or a line break, quotes inside are doubled
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeus_chain::{alloy::primitives::U256, defi_types::pool::PoolVariant};
+    use zeus_shared_types::{AmountInput, QuoteRoute, RouteHop, SelectedCurrency};
+
+    fn quote() -> QuoteResult {
+        let mut input = SelectedCurrency::default_input(1);
+        input.amount = AmountInput::new("1.5", 18);
+        let output = SelectedCurrency::default_output(1);
+        let decimals = output.decimals();
+
+        QuoteResult {
+            block_number: 20_000_000,
+            amount_out: U256::from(3_000) * U256::from(10).pow(U256::from(decimals)),
+            gas_used: 120_000,
+            route: QuoteRoute {
+                dex: "Uniswap".to_string(),
+                hops: vec![RouteHop { pool: Default::default(), variant: PoolVariant::UniswapV3, fee: 500 }],
+            },
+            input_token: input,
+            output_token: output,
+            ..Default::default()
+        }
+    }
+
+    /// A fresh path in the temp directory, removed with its rotated file on drop
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zeus-quote-log-{}-{}.csv", std::process::id(), name));
+            let _ = fs::remove_file(&path);
+            let _ = fs::remove_file(rotated_path(&path));
+            Self(path)
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+            let _ = fs::remove_file(rotated_path(&self.0));
+        }
+    }
+
+    #[test]
+    fn quotes_are_appended_under_one_header() {
+        let log = TempLog::new("append");
+        log_quote(&log.0, &quote(), 1, MAX_QUOTE_LOG_BYTES).unwrap();
+        log_quote(&log.0, &quote(), 2, MAX_QUOTE_LOG_BYTES).unwrap();
+
+        let text = fs::read_to_string(&log.0).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], HEADER);
+        assert!(lines[1].starts_with("1,1,ETH/"));
+        assert!(lines[1].contains(",native,"));
+        assert!(lines[1].contains(",1.5,3000,"));
+        assert!(lines[2].starts_with("2,"));
+    }
+
+    #[test]
+    fn the_route_is_quoted_json() {
+        let line = row(&quote(), 1).unwrap();
+        let route = line.split_once(",\"").unwrap().1;
+        assert!(route.starts_with("{\"\"dex\"\":\"\"Uniswap\"\""));
+        assert!(route.ends_with('"'));
+    }
+
+    #[test]
+    fn a_full_log_is_rotated() {
+        let log = TempLog::new("rotate");
+        log_quote(&log.0, &quote(), 1, 1).unwrap();
+        log_quote(&log.0, &quote(), 2, 1).unwrap();
+
+        let rotated = fs::read_to_string(rotated_path(&log.0)).unwrap();
+        let current = fs::read_to_string(&log.0).unwrap();
+        assert!(rotated.lines().nth(1).unwrap().starts_with("1,"));
+        assert_eq!(current.lines().next(), Some(HEADER));
+        assert!(current.lines().nth(1).unwrap().starts_with("2,"));
+    }
+}
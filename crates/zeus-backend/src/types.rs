@@ -1,22 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
 use zeus_chain::{
     alloy::{
-        primitives::{Address, U256},
+        eips::eip2930::AccessList,
+        primitives::{Address, TxHash, U256},
         providers::RootProvider,
         pubsub::PubSubFrontend,
         rpc::types::eth::Block,
     },
-    defi_types::currency::erc20::ERC20Token,
-    ChainId, Rpc, WsClient,
+    defi_types::{currency::{erc20::{ApprovalAmount, ERC20Token}, Currency}, pool::Pool},
+    raw_tx::{RawTx, SignedRawTx},
+    tx::{SignedTx, TxCall, TxOutcome, TxOverrides},
+    ChainId, Rpc, RpcOrigin, SwapRouter, TokenMetadataSource, WsClient,
 };
-use zeus_core::Profile;
+use zeus_core::{Profile, UnlockCredentials, Wallet};
+use zeus_shared_types::QuoteResult;
 
 pub struct EthBalanceParams {
     pub owner: Address,
     pub chain_id: u64,
     pub block: u64,
+    /// Skip the database and read the balance from the node, the stored one is replaced
+    pub force: bool,
     pub client: Arc<WsClient>
 }
 
@@ -25,16 +32,386 @@ pub struct ERC20BalanceParams {
     pub owner: Address,
     pub chain_id: u64,
     pub block: u64,
+    /// Skip the database and read the balance from the node, the stored one is replaced
+    pub force: bool,
     pub client: Arc<WsClient>
 }
+
+/// The balances of several tokens of one owner, answered with one [Response::BalancesBatch]
+pub struct ERC20BalancesParams {
+    pub tokens: Vec<ERC20Token>,
+    pub owner: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub client: Arc<WsClient>
+}
+
 pub struct ERC20TokenParams {
     pub currency_id: String,
     pub owner: Address,
     pub token: Address,
     pub chain_id: u64,
+    pub client: Arc<WsClient>,
+    pub source: TokenMetadataSource,
+    /// Skip the cache and the database and read the metadata again, the stored token is replaced
+    pub force: bool
+}
+
+/// Read the balance of a currency at a past block
+pub struct HistoricalBalanceParams {
+    pub currency: Currency,
+    pub owner: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub client: Arc<WsClient>
+}
+
+/// Find out if an address we are about to send to or approve is a contract
+pub struct CheckContractParams {
+    pub address: Address,
+    pub chain_id: u64,
+    pub client: Arc<WsClient>
+}
+
+/// Estimate the gas of a call before it is confirmed, see [Response::TxEstimate]
+pub struct EstimateTxParams {
+    pub call: TxCall,
+
+    /// The estimate is multiplied by this like for the transaction itself
+    pub gas_multiplier: f64,
+
+    /// Create an access list with `eth_createAccessList`, the estimate then includes it
+    pub access_list: bool,
+    pub client: Arc<WsClient>
+}
+
+/// The gas limit and access list of a call, see [Request::EstimateTx]
+#[derive(Clone, Debug)]
+pub struct TxEstimate {
+    /// The estimate with the gas multiplier, see [zeus_chain::gas_limit_for]
+    pub gas_limit: u128,
+
+    /// Only when it was asked for
+    pub access_list: Option<AccessList>,
+}
+
+/// Load a page of the token list, see [crate::db::ZeusDB::load_currency_page]
+pub struct CurrencyPageParams {
+    pub chain_id: u64,
+    pub query: String,
+    pub offset: usize,
+}
+
+/// Find the spot price of a pair from its best pool
+pub struct SpotPriceParams {
+    pub token_in: ERC20Token,
+    pub token_out: ERC20Token,
+    pub chain_id: u64,
+    pub client: Arc<WsClient>
+}
+
+/// Quote a swap of `amount_in` on the best pool of the pair
+pub struct QuoteParams {
+    pub currency_in: Currency,
+    pub currency_out: Currency,
+    pub amount_in: U256,
+
+    /// The wallet that swaps, its balances are kept with the quote
+    pub owner: Address,
+
+    /// Slippage in %
+    pub slippage: f32,
+    pub chain_id: u64,
+
+    /// The router the swap is sent to
+    pub router: SwapRouter,
+
+    /// The block we fork from to simulate the swap
+    pub block: Block,
+
+    /// Append the quote to the quotes log, see [crate::quote_log]
+    pub log_quotes: bool,
+    pub client: Arc<WsClient>
+}
+
+pub struct SendCryptoParams {
+    pub currency: Currency,
+    pub amount: U256,
+    pub recipient: Address,
+    pub wallet: Wallet,
+    pub chain_id: u64,
+    pub next_base_fee: U256,
+    pub priority_fee: U256,
+    pub mev_protect: bool,
+
+    /// The estimated gas is multiplied by this, see [zeus_chain::gas_limit_for]
+    pub gas_multiplier: f64,
+    /// Stop before broadcasting the transaction
+    pub dry_run: bool,
+
+    /// Set by the user in the confirmation
+    pub overrides: TxOverrides,
     pub client: Arc<WsClient>
 }
 
+/// Let `spender` spend `token` of the wallet, see [ApprovalAmount]
+pub struct ApproveParams {
+    pub token: ERC20Token,
+    pub spender: Address,
+
+    /// The amount of the swap the approval is for
+    pub amount_in: U256,
+    pub approval: ApprovalAmount,
+    pub wallet: Wallet,
+    pub chain_id: u64,
+    pub next_base_fee: U256,
+    pub priority_fee: U256,
+    pub mev_protect: bool,
+
+    /// The estimated gas is multiplied by this, see [zeus_chain::gas_limit_for]
+    pub gas_multiplier: f64,
+    /// Stop before broadcasting the transaction
+    pub dry_run: bool,
+    pub client: Arc<WsClient>
+}
+
+impl ApproveParams {
+    /// The amount `spender` is allowed to spend
+    pub fn amount(&self) -> U256 {
+        self.approval.amount(self.amount_in)
+    }
+}
+
+/// Send the same currency to several recipients, one transaction after the other
+pub struct BatchSendParams {
+    pub currency: Currency,
+
+    /// (recipient, amount) in the order they are sent
+    pub rows: Vec<(Address, U256)>,
+    pub wallet: Wallet,
+    pub chain_id: u64,
+    pub next_base_fee: U256,
+    pub priority_fee: U256,
+    pub mev_protect: bool,
+
+    /// The estimated gas is multiplied by this, see [zeus_chain::gas_limit_for]
+    pub gas_multiplier: f64,
+    /// Stop before broadcasting the transactions
+    pub dry_run: bool,
+
+    /// Skip the remaining rows once one fails
+    pub abort_on_failure: bool,
+    pub client: Arc<WsClient>
+}
+
+impl BatchSendParams {
+    /// The transfer of a single row
+    pub fn transfer(&self, recipient: Address, amount: U256) -> SendCryptoParams {
+        SendCryptoParams {
+            currency: self.currency.clone(),
+            amount,
+            recipient,
+            wallet: self.wallet.clone(),
+            chain_id: self.chain_id,
+            next_base_fee: self.next_base_fee,
+            priority_fee: self.priority_fee,
+            mev_protect: self.mev_protect,
+            gas_multiplier: self.gas_multiplier,
+            dry_run: self.dry_run,
+            // the nonces of a batch are counted by the backend
+            overrides: TxOverrides::default(),
+            client: self.client.clone(),
+        }
+    }
+}
+
+/// The state of a row of a batch send, see [Response::BatchSend]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchRowStatus {
+    /// Waiting for its turn or for the receipt
+    Pending,
+
+    /// Included in a block
+    Confirmed(TxHash),
+
+    /// Simulated and signed in dry-run mode
+    Simulated,
+
+    Failed(String),
+
+    /// Not sent because an earlier row failed
+    Skipped,
+}
+
+impl BatchRowStatus {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Pending => "Pending".to_string(),
+            Self::Confirmed(_) => "Confirmed".to_string(),
+            Self::Simulated => "Simulated".to_string(),
+            Self::Failed(_) => "Failed".to_string(),
+            Self::Skipped => "Skipped".to_string(),
+        }
+    }
+
+    /// Is the row done, either way
+    pub fn is_final(&self) -> bool {
+        !matches!(self, Self::Pending)
+    }
+}
+
+/// Sign and broadcast a transaction built by another tool
+pub struct SignRawTxParams {
+    pub tx: RawTx,
+    pub wallet: Wallet,
+    pub next_base_fee: U256,
+
+    /// Used if the transaction has no `maxPriorityFeePerGas`
+    pub priority_fee: U256,
+    pub mev_protect: bool,
+
+    /// Used if the transaction has no gas limit, see [zeus_chain::gas_limit_for]
+    pub gas_multiplier: f64,
+
+    /// Stop before broadcasting the transaction
+    pub dry_run: bool,
+
+    /// Set by the user in the confirmation, they win over the fields of the transaction
+    pub overrides: TxOverrides,
+    pub client: Arc<WsClient>
+}
+
+/// Broadcast a transaction that was signed by another tool
+pub struct BroadcastRawTxParams {
+    pub tx: SignedRawTx,
+
+    /// The chain we are connected to, the transaction must be signed for it
+    pub chain_id: u64,
+    pub mev_protect: bool,
+    pub client: Arc<WsClient>
+}
+
+/// Export a wallet as a keystore file, see [Response::KeystoreExported]
+pub struct ExportKeystoreParams {
+    /// The credentials are checked against its key
+    pub profile: Profile,
+    pub wallet: Wallet,
+    pub credentials: UnlockCredentials,
+
+    /// The keystore is encrypted with it
+    pub password: String,
+
+    /// An existing file is never overwritten
+    pub path: String,
+}
+
+/// Import a wallet from a keystore file, see [Response::KeystoreImported]
+pub struct ImportKeystoreParams {
+    pub name: String,
+    pub path: String,
+    pub password: String,
+}
+
+/// A transaction to sign without broadcasting it, see [Request::SignTransaction]
+///
+/// Built the same way as when it is sent, the `dry_run` flag of the params is not used
+pub enum TxRequest {
+    Transfer(SendCryptoParams),
+    Approve(ApproveParams),
+}
+
+impl TxRequest {
+    /// The wallet that signs
+    pub fn wallet(&self) -> &Wallet {
+        match self {
+            TxRequest::Transfer(params) => &params.wallet,
+            TxRequest::Approve(params) => &params.wallet,
+        }
+    }
+
+    pub fn client(&self) -> &Arc<WsClient> {
+        match self {
+            TxRequest::Transfer(params) => &params.client,
+            TxRequest::Approve(params) => &params.client,
+        }
+    }
+
+    /// What the user set in the confirmation, approvals are confirmed without them
+    pub fn overrides(&self) -> TxOverrides {
+        match self {
+            TxRequest::Transfer(params) => params.overrides.clone(),
+            TxRequest::Approve(_) => TxOverrides::default(),
+        }
+    }
+}
+
+/// When a [PriceWatch] should trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchCondition {
+    /// The price is at or above the target
+    Above,
+
+    /// The price is at or below the target
+    Below,
+}
+
+impl WatchCondition {
+    pub fn is_met(&self, price: f64, target: f64) -> bool {
+        match self {
+            WatchCondition::Above => price >= target,
+            WatchCondition::Below => price <= target,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatchCondition::Above => "Above",
+            WatchCondition::Below => "Below",
+        }
+    }
+
+    pub fn to_i64(&self) -> i64 {
+        match self {
+            WatchCondition::Above => 0,
+            WatchCondition::Below => 1,
+        }
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            0 => WatchCondition::Above,
+            _ => WatchCondition::Below,
+        }
+    }
+}
+
+/// Notify the user when the price of `token_in` in `token_out` reaches `target`
+///
+/// This is not an on-chain limit order, nothing is ever sent automatically
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceWatch {
+    /// Assigned by the database
+    pub id: i64,
+    pub chain_id: u64,
+    pub token_in: ERC20Token,
+    pub token_out: ERC20Token,
+    pub condition: WatchCondition,
+    pub target: f64,
+}
+
+impl PriceWatch {
+    /// eg. `1 WETH Above 3500 USDC`
+    pub fn describe(&self) -> String {
+        format!(
+            "1 {} {} {} {}",
+            self.token_in.symbol,
+            self.condition.name(),
+            self.target,
+            self.token_out.symbol
+        )
+    }
+}
+
 pub struct ERC20BalanceRes {
     pub owner: Address,
     pub token: Address,
@@ -42,12 +419,49 @@ pub struct ERC20BalanceRes {
     pub chain_id: u64
 }
 
+pub struct HistoricalBalanceRes {
+    pub currency: Currency,
+    pub owner: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub balance: U256
+}
+
+pub struct SpotPriceRes {
+    pub token_in: ERC20Token,
+    pub token_out: ERC20Token,
+    /// The pool the price was read from
+    pub pool: Pool,
+    /// How much `token_out` one whole `token_in` is worth
+    pub price: U256
+}
+
+/// Balances loaded from the database on startup, the currencies are loaded per chain with [Request::CurrencyPage]
+pub struct CacheRes {
+    pub erc20_balance: HashMap<(u64, Address, Address), U256>,
+    pub eth_balance: HashMap<(u64, Address), (u64, U256)>,
+    /// (chain_id, address) of the pinned tokens
+    pub pinned: HashSet<(u64, Address)>,
+}
+
+/// A page of the token list, `more` is `false` on the last page
+pub struct CurrencyPageRes {
+    pub chain_id: u64,
+    pub query: String,
+    pub offset: usize,
+    pub currencies: Vec<Currency>,
+    pub more: bool,
+}
+
 pub struct ERC20TokenRes {
     pub currency_id: String,
     pub owner: Address,
     pub token: ERC20Token,
     pub balance: U256,
-    pub chain_id: u64
+    pub chain_id: u64,
+
+    /// The token was unknown and its metadata was just read from the node
+    pub added: bool,
 }
 
 
@@ -58,6 +472,12 @@ pub enum Request {
     /// For now we just connect on the default chain and initialize the oracles
     OnStartup(ChainId, Vec<Rpc>),
 
+    /// Load the currencies and balances from the database for the given chains
+    LoadCache(Vec<u64>),
+
+    /// Insert the default tokens that are missing, then reload the cache of the given chains
+    RestoreDefaultTokens(Vec<u64>),
+
     /// Initialize the Oracles
     InitOracles(Arc<WsClient>, ChainId),
 
@@ -67,16 +487,180 @@ pub enum Request {
     /// Get the ERC20 Balance
     ERC20Balance(ERC20BalanceParams),
 
+    /// Get the balances of several ERC20 tokens, see [Response::BalancesBatch]
+    ERC20Balances(ERC20BalancesParams),
+
     /// Encrypt and save the profile
     SaveProfile(Profile),
 
+    /// Encrypt a wallet as a keystore file
+    ExportKeystore(ExportKeystoreParams),
+
+    /// Decrypt the wallet of a keystore file, the GUI adds it to the profile
+    ImportKeystore(ImportKeystoreParams),
+
     Client(ChainId, Vec<Rpc>),
 
-    ERC20Token(ERC20TokenParams)
+    ERC20Token(ERC20TokenParams),
+
+    /// Transfer a native currency or an ERC20 token
+    SendCrypto(SendCryptoParams),
+
+    /// Transfer a currency to several recipients, see [Response::BatchSend]
+    BatchSend(BatchSendParams),
+
+    /// Approve a router to spend an ERC20 token
+    Approve(ApproveParams),
+
+    /// Sign and broadcast a raw transaction
+    SignRawTx(SignRawTxParams),
+
+    /// Broadcast an already signed transaction
+    BroadcastRawTx(BroadcastRawTxParams),
+
+    /// Sign a transaction without broadcasting it, see [Response::SignedTransaction]
+    SignTransaction(TxRequest),
+
+    /// Get the balance of a currency at a specific block
+    HistoricalBalance(HistoricalBalanceParams),
+
+    /// Load the sampled eth balances of a wallet on a chain, see [Response::BalanceHistory]
+    BalanceHistory(u64, Address),
+
+    /// Load the saved price watches
+    LoadWatches,
+
+    /// Save a new price watch
+    AddWatch(PriceWatch),
+
+    /// Remove a price watch by its id
+    CancelWatch(i64),
+
+    /// Check the price watches of a chain against the current spot prices
+    CheckWatches(u64, Arc<WsClient>),
+
+    /// Read the metadata of the stale tokens of a chain again in the background, see [crate::metadata_refresh]
+    ///
+    /// A refresh that is still running is cancelled
+    RefreshStaleMetadata(u64, Arc<WsClient>),
+
+    /// Stop the background metadata refresh
+    CancelMetadataRefresh,
+
+    /// Check if an address has code, see [Response::ContractCheck]
+    CheckContract(CheckContractParams),
+
+    /// Estimate the gas of a call and create its access list, see [Response::TxEstimate]
+    EstimateTx(EstimateTxParams),
+
+    /// Load the user labels of the addresses
+    LoadAddressLabels,
+
+    /// Label an address on a chain, an empty label removes it
+    SetAddressLabel(u64, Address, String),
+
+    /// The data directory was moved to a writable one, open its database and reload the cache
+    DataDirChanged(Vec<u64>),
 
+    /// Load a page of the token list of a chain, see [Response::CurrencyPage]
+    CurrencyPage(CurrencyPageParams),
+
+    /// Pin or unpin a token on a chain, see [Response::PinnedTokens]
+    SetPinned(u64, Address, bool),
+
+    /// Get the spot price of a pair
+    SpotPrice(SpotPriceParams),
+
+    /// Quote a swap
+    Quote(Box<QuoteParams>)
+
+}
+
+/// What makes two requests identical, see [crate::channel::PendingRequests]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestKey {
+    /// `chain_id`, `owner`, `block`
+    EthBalance(u64, Address, u64),
+
+    /// `chain_id`, `owner`, `token`, `block`
+    ERC20Balance(u64, Address, Address, u64),
+}
+
+impl RequestKey {
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            RequestKey::EthBalance(chain_id, ..) | RequestKey::ERC20Balance(chain_id, ..) => *chain_id,
+        }
+    }
+
+    /// The block the request is for
+    pub fn block(&self) -> u64 {
+        match self {
+            RequestKey::EthBalance(.., block) | RequestKey::ERC20Balance(.., block) => *block,
+        }
+    }
 }
 
 impl Request {
+    /// The keys of the requests that are polled every frame, empty for the ones that are always sent
+    ///
+    /// A batch of balances has a key per token. Forced refreshes are always sent, the user asked for them
+    pub fn coalesce_keys(&self) -> Vec<RequestKey> {
+        match self {
+            Request::EthBalance(params) if params.force => Vec::new(),
+            Request::ERC20Balance(params) if params.force => Vec::new(),
+            Request::EthBalance(params) => vec![RequestKey::EthBalance(params.chain_id, params.owner, params.block)],
+            Request::ERC20Balance(params) => vec![RequestKey::ERC20Balance(
+                params.chain_id,
+                params.owner,
+                params.token.address,
+                params.block,
+            )],
+            Request::ERC20Balances(params) => params
+                .tokens
+                .iter()
+                .map(|token| RequestKey::ERC20Balance(params.chain_id, params.owner, token.address, params.block))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The feature that makes rpc calls for this request, used to tag the [zeus_chain::RPC_USAGE]
+    ///
+    /// `None` if the request never touches the node
+    pub fn origin(&self) -> Option<RpcOrigin> {
+        match self {
+            Request::OnStartup(..) | Request::Client(..) => Some(RpcOrigin::Connection),
+            Request::InitOracles(..) => Some(RpcOrigin::Oracle),
+            Request::EthBalance(_) | Request::ERC20Balance(_) | Request::ERC20Balances(_) | Request::HistoricalBalance(_) => {
+                Some(RpcOrigin::Balances)
+            }
+            Request::ERC20Token(_) | Request::RefreshStaleMetadata(..) => Some(RpcOrigin::Discovery),
+            Request::SendCrypto(_)
+            | Request::BatchSend(_)
+            | Request::Approve(_)
+            | Request::SignRawTx(_)
+            | Request::BroadcastRawTx(_)
+            | Request::SignTransaction(_) => Some(RpcOrigin::Transactions),
+            Request::CheckWatches(..) | Request::SpotPrice(_) | Request::Quote(_) => Some(RpcOrigin::Quotes),
+            Request::CheckContract(_) | Request::EstimateTx(_) => Some(RpcOrigin::Transactions),
+            Request::LoadCache(_)
+            | Request::DataDirChanged(_)
+            | Request::CurrencyPage(_)
+            | Request::RestoreDefaultTokens(_)
+            | Request::SaveProfile(_)
+            | Request::ExportKeystore(_)
+            | Request::ImportKeystore(_)
+            | Request::LoadWatches
+            | Request::AddWatch(_)
+            | Request::CancelWatch(_)
+            | Request::LoadAddressLabels
+            | Request::SetAddressLabel(..)
+            | Request::SetPinned(..)
+            | Request::CancelMetadataRefresh
+            | Request::BalanceHistory(..) => None,
+        }
+    }
 
     pub fn client(chain_id: ChainId, rpcs: Vec<Rpc>) -> Self {
         Request::Client(chain_id, rpcs)
@@ -86,35 +670,181 @@ impl Request {
         Request::OnStartup(chain_id, rpcs)
     }
 
+    pub fn load_cache(chain_ids: Vec<u64>) -> Self {
+        Request::LoadCache(chain_ids)
+    }
+
+    pub fn restore_default_tokens(chain_ids: Vec<u64>) -> Self {
+        Request::RestoreDefaultTokens(chain_ids)
+    }
+
     pub fn init_oracles(client: Arc<WsClient>, chain_id: ChainId) -> Self {
         Request::InitOracles(client, chain_id)
     }
 
-    pub fn erc20_token(currency_id: String, owner: Address, token: Address, chain_id: u64, client: Arc<WsClient>) -> Self {
+    pub fn save_profile(profile: Profile) -> Self {
+        Request::SaveProfile(profile)
+    }
+
+    pub fn export_keystore(params: ExportKeystoreParams) -> Self {
+        Request::ExportKeystore(params)
+    }
+
+    pub fn import_keystore(name: String, path: String, password: String) -> Self {
+        Request::ImportKeystore(ImportKeystoreParams { name, path, password })
+    }
+
+    pub fn erc20_token(currency_id: String, owner: Address, token: Address, chain_id: u64, client: Arc<WsClient>, source: TokenMetadataSource, force: bool) -> Self {
         Request::ERC20Token(ERC20TokenParams {
             currency_id,
             owner,
             token,
             chain_id,
+            client,
+            source,
+            force
+        })
+    }
+
+    pub fn send_crypto(params: SendCryptoParams) -> Self {
+        Request::SendCrypto(params)
+    }
+
+    pub fn batch_send(params: BatchSendParams) -> Self {
+        Request::BatchSend(params)
+    }
+
+    pub fn approve(params: ApproveParams) -> Self {
+        Request::Approve(params)
+    }
+
+    pub fn sign_raw_tx(params: SignRawTxParams) -> Self {
+        Request::SignRawTx(params)
+    }
+
+    pub fn broadcast_raw_tx(params: BroadcastRawTxParams) -> Self {
+        Request::BroadcastRawTx(params)
+    }
+
+    pub fn sign_transaction(tx: TxRequest) -> Self {
+        Request::SignTransaction(tx)
+    }
+
+    pub fn load_watches() -> Self {
+        Request::LoadWatches
+    }
+
+    pub fn add_watch(watch: PriceWatch) -> Self {
+        Request::AddWatch(watch)
+    }
+
+    pub fn cancel_watch(id: i64) -> Self {
+        Request::CancelWatch(id)
+    }
+
+    pub fn check_watches(chain_id: u64, client: Arc<WsClient>) -> Self {
+        Request::CheckWatches(chain_id, client)
+    }
+
+    pub fn refresh_stale_metadata(chain_id: u64, client: Arc<WsClient>) -> Self {
+        Request::RefreshStaleMetadata(chain_id, client)
+    }
+
+    pub fn cancel_metadata_refresh() -> Self {
+        Request::CancelMetadataRefresh
+    }
+
+    pub fn spot_price(token_in: ERC20Token, token_out: ERC20Token, chain_id: u64, client: Arc<WsClient>) -> Self {
+        Request::SpotPrice(SpotPriceParams {
+            token_in,
+            token_out,
+            chain_id,
             client
         })
     }
 
-    pub fn eth_balance(owner: Address, chain_id: u64, block: u64, client: Arc<WsClient>) -> Self {
+    pub fn quote(params: QuoteParams) -> Self {
+        Request::Quote(Box::new(params))
+    }
+
+    pub fn check_contract(address: Address, chain_id: u64, client: Arc<WsClient>) -> Self {
+        Request::CheckContract(CheckContractParams {
+            address,
+            chain_id,
+            client
+        })
+    }
+
+    pub fn estimate_tx(call: TxCall, gas_multiplier: f64, access_list: bool, client: Arc<WsClient>) -> Self {
+        Request::EstimateTx(EstimateTxParams {
+            call,
+            gas_multiplier,
+            access_list,
+            client
+        })
+    }
+
+    pub fn load_address_labels() -> Self {
+        Request::LoadAddressLabels
+    }
+
+    pub fn balance_history(chain_id: u64, owner: Address) -> Self {
+        Request::BalanceHistory(chain_id, owner)
+    }
+
+    pub fn set_address_label(chain_id: u64, address: Address, label: String) -> Self {
+        Request::SetAddressLabel(chain_id, address, label)
+    }
+
+    pub fn currency_page(chain_id: u64, query: String, offset: usize) -> Self {
+        Request::CurrencyPage(CurrencyPageParams { chain_id, query, offset })
+    }
+
+    pub fn data_dir_changed(chain_ids: Vec<u64>) -> Self {
+        Request::DataDirChanged(chain_ids)
+    }
+
+    pub fn set_pinned(chain_id: u64, address: Address, pinned: bool) -> Self {
+        Request::SetPinned(chain_id, address, pinned)
+    }
+
+    pub fn historical_balance(currency: Currency, owner: Address, chain_id: u64, block: u64, client: Arc<WsClient>) -> Self {
+        Request::HistoricalBalance(HistoricalBalanceParams {
+            currency,
+            owner,
+            chain_id,
+            block,
+            client
+        })
+    }
+
+    pub fn eth_balance(owner: Address, chain_id: u64, block: u64, force: bool, client: Arc<WsClient>) -> Self {
         Request::EthBalance(EthBalanceParams {
             owner,
             chain_id,
             block,
+            force,
             client
         })
     }
 
-    pub fn erc20_balance(token: ERC20Token, owner: Address, chain_id: u64, block: u64, client: Arc<WsClient>) -> Self {
+    pub fn erc20_balance(token: ERC20Token, owner: Address, chain_id: u64, block: u64, force: bool, client: Arc<WsClient>) -> Self {
         Request::ERC20Balance(ERC20BalanceParams {
             token,
             owner,
             chain_id,
             block,
+            force,
+            client
+        })
+    }
+
+    pub fn erc20_balances(tokens: Vec<ERC20Token>, owner: Address, chain_id: u64, block: u64, client: Arc<WsClient>) -> Self {
+        Request::ERC20Balances(ERC20BalancesParams {
+            tokens,
+            owner,
+            chain_id,
+            block,
             client
         })
     }
@@ -124,30 +854,200 @@ impl Request {
 pub enum Response {
     EthBalance(U256),
 
-    Client(Option<Arc<WsClient>>, ChainId),
+    /// The client of a chain, or why we could not connect to it
+    Client(Result<Arc<WsClient>, String>, ChainId),
 
     ERC20Token(ERC20TokenRes),
 
-    ERC20Balance(ERC20BalanceRes)
+    ERC20Balance(ERC20BalanceRes),
+
+    /// The balances of several tokens of one owner, so the cache is updated once for all of them
+    BalancesBatch {
+        chain_id: u64,
+        owner: Address,
+        balances: Vec<(Address, U256)>,
+    },
+
+    /// The cached data from the database are ready
+    CacheLoaded(CacheRes),
+
+    /// How many default tokens were restored, the cache was reloaded before this
+    DefaultTokensRestored(usize),
+
+    /// The profile was written to disk, or why it failed
+    ProfileSaved(Result<(), String>),
+
+    /// The path the keystore was saved to, or why it failed
+    KeystoreExported(Result<String, String>),
+
+    /// The wallet decrypted from a keystore, or why it failed
+    KeystoreImported(Result<Wallet, String>),
+
+    /// Round-trip time of an rpc request
+    Latency(Duration),
+
+    /// A transaction went through the pipeline, see [TxOutcome]
+    Transaction(TxOutcome),
+
+    /// A transaction was signed for the user to broadcast, see [Request::SignTransaction]
+    SignedTransaction(Box<SignedTx>),
+
+    /// A row of a batch send changed state
+    ///
+    /// `row index`, `status`
+    BatchSend(usize, BatchRowStatus),
+
+    /// The balance of a currency at a past block
+    HistoricalBalance(HistoricalBalanceRes),
+
+    /// The sampled eth balances of a wallet, oldest first
+    ///
+    /// `chain_id`, `owner`, (`block`, `balance`)
+    BalanceHistory(u64, Address, Vec<(u64, U256)>),
+
+    /// All the active price watches
+    Watches(Vec<PriceWatch>),
+
+    /// A price watch reached its target at the given price, it is removed afterwards
+    WatchTriggered(PriceWatch, f64),
+
+    /// Does the address have code on the chain, and the symbol of the token if it is a token contract
+    ///
+    /// `chain_id`, `address`, `is_contract`, `token`
+    ContractCheck(u64, Address, bool, Option<String>),
+
+    /// The estimate of a call, or why the node could not estimate it
+    TxEstimate(TxCall, Result<TxEstimate, String>),
+
+    /// All the user labels, `Key:` (chain_id, address) -> `Value:` label
+    AddressLabels(HashMap<(u64, Address), String>),
+
+    /// A page of the token list of a chain
+    CurrencyPage(Box<CurrencyPageRes>),
+
+    /// The background refresh found new metadata for a stored token
+    TokenRefreshed(ERC20Token),
+
+    /// All the pinned tokens as (chain_id, address)
+    PinnedTokens(HashSet<(u64, Address)>),
+
+    /// The spot price of a pair
+    SpotPrice(Box<SpotPriceRes>),
+
+    /// A swap quote
+    Quote(Box<QuoteResult>)
 }
 
 impl Response {
+    /// Results the user is waiting on, they go ahead of the periodic balances and prices
+    ///
+    /// See [crate::channel::response_channel]
+    pub fn is_priority(&self) -> bool {
+        match self {
+            Response::Client(..)
+            | Response::ERC20Token(_)
+            | Response::DefaultTokensRestored(_)
+            | Response::ProfileSaved(_)
+            | Response::KeystoreExported(_)
+            | Response::KeystoreImported(_)
+            | Response::Transaction(_)
+            | Response::SignedTransaction(_)
+            | Response::BatchSend(..)
+            | Response::HistoricalBalance(_)
+            | Response::WatchTriggered(..)
+            | Response::ContractCheck(..)
+            | Response::TxEstimate(..)
+            | Response::Quote(_) => true,
+            Response::EthBalance(_)
+            | Response::ERC20Balance(_)
+            | Response::BalancesBatch { .. }
+            | Response::CacheLoaded(_)
+            | Response::Latency(_)
+            | Response::BalanceHistory(..)
+            | Response::Watches(_)
+            | Response::AddressLabels(_)
+            | Response::CurrencyPage(_)
+            | Response::PinnedTokens(_)
+            | Response::TokenRefreshed(_)
+            | Response::SpotPrice(_) => false,
+        }
+    }
 
     pub fn eth_balance(balance: U256) -> Self {
         Response::EthBalance(balance)
     }
 
-    pub fn client(client: Option<Arc<WsClient>>, chain_id: ChainId) -> Self {
+    pub fn client(client: Result<Arc<WsClient>, String>, chain_id: ChainId) -> Self {
         Response::Client(client, chain_id)
     }
 
-    pub fn erc20_token(currency_id: String, owner: Address, token: ERC20Token, balance: U256, chain_id: u64) -> Self {
+    pub fn erc20_token(currency_id: String, owner: Address, token: ERC20Token, balance: U256, chain_id: u64, added: bool) -> Self {
         Response::ERC20Token(ERC20TokenRes {
             currency_id,
             owner,
             token,
             balance,
-            chain_id
+            chain_id,
+            added,
+        })
+    }
+
+    pub fn latency(latency: Duration) -> Self {
+        Response::Latency(latency)
+    }
+
+    pub fn transaction(outcome: TxOutcome) -> Self {
+        Response::Transaction(outcome)
+    }
+
+    pub fn tx_estimate(call: TxCall, estimate: Result<TxEstimate, String>) -> Self {
+        Response::TxEstimate(call, estimate)
+    }
+
+    pub fn signed_transaction(tx: SignedTx) -> Self {
+        Response::SignedTransaction(Box::new(tx))
+    }
+
+    pub fn batch_send(index: usize, status: BatchRowStatus) -> Self {
+        Response::BatchSend(index, status)
+    }
+
+    pub fn cache_loaded(cache: CacheRes) -> Self {
+        Response::CacheLoaded(cache)
+    }
+
+    pub fn watches(watches: Vec<PriceWatch>) -> Self {
+        Response::Watches(watches)
+    }
+
+    pub fn watch_triggered(watch: PriceWatch, price: f64) -> Self {
+        Response::WatchTriggered(watch, price)
+    }
+
+    pub fn spot_price(token_in: ERC20Token, token_out: ERC20Token, pool: Pool, price: U256) -> Self {
+        Response::SpotPrice(Box::new(SpotPriceRes {
+            token_in,
+            token_out,
+            pool,
+            price
+        }))
+    }
+
+    pub fn token_refreshed(token: ERC20Token) -> Self {
+        Response::TokenRefreshed(token)
+    }
+
+    pub fn quote(result: QuoteResult) -> Self {
+        Response::Quote(Box::new(result))
+    }
+
+    pub fn historical_balance(currency: Currency, owner: Address, chain_id: u64, block: u64, balance: U256) -> Self {
+        Response::HistoricalBalance(HistoricalBalanceRes {
+            currency,
+            owner,
+            chain_id,
+            block,
+            balance
         })
     }
 
@@ -159,4 +1059,311 @@ impl Response {
             chain_id
         })
     }
-}
\ No newline at end of file
+
+    pub fn balances_batch(chain_id: u64, owner: Address, balances: Vec<(Address, U256)>) -> Self {
+        Response::BalancesBatch { chain_id, owner, balances }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeus_chain::{
+        alloy::primitives::{address, Bytes, B256},
+        defi_types::pool::PoolVariant,
+        test_node::TestNode,
+        tx::{DryRunResult, SimulationResult, TxAmount},
+    };
+
+    const CHAIN_ID: u64 = 1;
+    const BLOCK: u64 = 100;
+    const OWNER: Address = address!("1111111111111111111111111111111111111111");
+    const TOKEN: Address = address!("2222222222222222222222222222222222222222");
+
+    fn token() -> ERC20Token {
+        ERC20Token { chain_id: CHAIN_ID, address: TOKEN, symbol: "TKN".to_string(), decimals: 18, ..Default::default() }
+    }
+
+    async fn client() -> Arc<WsClient> {
+        Arc::new(TestNode::empty_chain().client().await)
+    }
+
+    fn watch() -> PriceWatch {
+        PriceWatch {
+            id: 7,
+            chain_id: CHAIN_ID,
+            token_in: token(),
+            token_out: ERC20Token::eth_default_output(),
+            condition: WatchCondition::Above,
+            target: 2.5,
+        }
+    }
+
+    fn simulation() -> SimulationResult {
+        SimulationResult { success: true, output: String::new(), gas_used: 21_000, l1_gas_used: None }
+    }
+
+    fn send_params(client: Arc<WsClient>) -> SendCryptoParams {
+        SendCryptoParams {
+            currency: Currency::new_erc20(token()),
+            amount: U256::from(5),
+            recipient: OWNER,
+            wallet: Wallet::new_rng(String::new()),
+            chain_id: CHAIN_ID,
+            next_base_fee: U256::ZERO,
+            priority_fee: U256::ZERO,
+            mev_protect: false,
+            gas_multiplier: 1.0,
+            dry_run: true,
+            overrides: TxOverrides::default(),
+            client,
+        }
+    }
+
+    fn approve_params(client: Arc<WsClient>) -> ApproveParams {
+        ApproveParams {
+            token: token(),
+            spender: OWNER,
+            amount_in: U256::from(5),
+            approval: ApprovalAmount::Exact,
+            wallet: Wallet::new_rng(String::new()),
+            chain_id: CHAIN_ID,
+            next_base_fee: U256::ZERO,
+            priority_fee: U256::ZERO,
+            mev_protect: false,
+            gas_multiplier: 1.0,
+            dry_run: true,
+            client,
+        }
+    }
+
+    #[test]
+    fn a_watch_condition_is_met_at_its_target() {
+        assert!(WatchCondition::Above.is_met(2.5, 2.5));
+        assert!(WatchCondition::Above.is_met(2.6, 2.5));
+        assert!(!WatchCondition::Above.is_met(2.4, 2.5));
+
+        assert!(WatchCondition::Below.is_met(2.5, 2.5));
+        assert!(WatchCondition::Below.is_met(2.4, 2.5));
+        assert!(!WatchCondition::Below.is_met(2.6, 2.5));
+
+        // stored as an integer in the database
+        for condition in [WatchCondition::Above, WatchCondition::Below] {
+            assert_eq!(WatchCondition::from_i64(condition.to_i64()), condition);
+        }
+    }
+
+    #[test]
+    fn the_request_constructors_without_a_client_build_their_variant() {
+        let rpc = || vec![Rpc::new("wss://eth.example".to_string(), CHAIN_ID)];
+
+        assert!(matches!(Request::on_startup(ChainId::Ethereum(1), rpc()), Request::OnStartup(ChainId::Ethereum(1), rpcs) if rpcs.len() == 1));
+        assert!(matches!(Request::client(ChainId::Base(8453), rpc()), Request::Client(ChainId::Base(8453), rpcs) if rpcs.len() == 1));
+        assert!(matches!(Request::load_cache(vec![1, 56]), Request::LoadCache(ids) if ids == [1, 56]));
+        assert!(matches!(Request::restore_default_tokens(vec![1]), Request::RestoreDefaultTokens(ids) if ids == [1]));
+        assert!(matches!(Request::data_dir_changed(vec![8453]), Request::DataDirChanged(ids) if ids == [8453]));
+        assert!(matches!(Request::save_profile(Profile::default()), Request::SaveProfile(profile) if profile == Profile::default()));
+        assert!(matches!(Request::load_watches(), Request::LoadWatches));
+        assert!(matches!(Request::add_watch(watch()), Request::AddWatch(added) if added == watch()));
+        assert!(matches!(Request::cancel_watch(7), Request::CancelWatch(7)));
+        assert!(matches!(Request::cancel_metadata_refresh(), Request::CancelMetadataRefresh));
+        assert!(matches!(Request::load_address_labels(), Request::LoadAddressLabels));
+        assert!(matches!(Request::balance_history(CHAIN_ID, OWNER), Request::BalanceHistory(CHAIN_ID, OWNER)));
+        assert!(matches!(
+            Request::set_address_label(CHAIN_ID, OWNER, "Cold".to_string()),
+            Request::SetAddressLabel(CHAIN_ID, OWNER, label) if label == "Cold"
+        ));
+        assert!(matches!(
+            Request::currency_page(CHAIN_ID, "usd".to_string(), 50),
+            Request::CurrencyPage(CurrencyPageParams { chain_id: CHAIN_ID, query, offset: 50 }) if query == "usd"
+        ));
+        assert!(matches!(Request::set_pinned(CHAIN_ID, TOKEN, true), Request::SetPinned(CHAIN_ID, TOKEN, true)));
+    }
+
+    #[tokio::test]
+    async fn the_request_constructors_with_a_client_build_their_variant() {
+        let client = client().await;
+
+        assert!(matches!(Request::init_oracles(client.clone(), ChainId::Arbitrum(42161)), Request::InitOracles(_, ChainId::Arbitrum(42161))));
+        assert!(matches!(Request::check_watches(CHAIN_ID, client.clone()), Request::CheckWatches(CHAIN_ID, _)));
+        assert!(matches!(Request::refresh_stale_metadata(CHAIN_ID, client.clone()), Request::RefreshStaleMetadata(CHAIN_ID, _)));
+
+        let req = Request::erc20_token("id".to_string(), OWNER, TOKEN, CHAIN_ID, client.clone(), TokenMetadataSource::Node, true);
+        assert!(matches!(req, Request::ERC20Token(p) if p.currency_id == "id" && p.owner == OWNER && p.token == TOKEN && p.chain_id == CHAIN_ID && p.force));
+
+        let req = Request::spot_price(token(), ERC20Token::eth_default_output(), CHAIN_ID, client.clone());
+        assert!(matches!(req, Request::SpotPrice(p) if p.token_in == token() && p.token_out == ERC20Token::eth_default_output() && p.chain_id == CHAIN_ID));
+
+        let req = Request::check_contract(TOKEN, CHAIN_ID, client.clone());
+        assert!(matches!(req, Request::CheckContract(p) if p.address == TOKEN && p.chain_id == CHAIN_ID));
+
+        let call = TxCall::new(OWNER, TOKEN, U256::ZERO, Bytes::new());
+        let req = Request::estimate_tx(call.clone(), 1.2, true, client.clone());
+        assert!(matches!(req, Request::EstimateTx(p) if p.call == call && p.gas_multiplier == 1.2 && p.access_list));
+
+        let req = Request::historical_balance(Currency::new_erc20(token()), OWNER, CHAIN_ID, BLOCK, client.clone());
+        assert!(matches!(req, Request::HistoricalBalance(p) if p.currency == Currency::new_erc20(token()) && p.owner == OWNER && p.block == BLOCK));
+
+        let req = Request::eth_balance(OWNER, CHAIN_ID, BLOCK, true, client.clone());
+        assert!(matches!(req, Request::EthBalance(p) if p.owner == OWNER && p.chain_id == CHAIN_ID && p.block == BLOCK && p.force));
+
+        let req = Request::erc20_balance(token(), OWNER, CHAIN_ID, BLOCK, false, client.clone());
+        assert!(matches!(req, Request::ERC20Balance(p) if p.token == token() && p.owner == OWNER && p.block == BLOCK && !p.force));
+
+        let req = Request::erc20_balances(vec![token()], OWNER, CHAIN_ID, BLOCK, client.clone());
+        assert!(matches!(req, Request::ERC20Balances(p) if p.tokens == [token()] && p.owner == OWNER && p.block == BLOCK));
+
+        let params = QuoteParams {
+            currency_in: Currency::new_native(CHAIN_ID),
+            currency_out: Currency::new_erc20(token()),
+            amount_in: U256::from(5),
+            owner: OWNER,
+            slippage: 0.5,
+            chain_id: CHAIN_ID,
+            router: SwapRouter::Uniswap,
+            block: Block::default(),
+            log_quotes: false,
+            client,
+        };
+        assert!(matches!(Request::quote(params), Request::Quote(p) if p.amount_in == U256::from(5) && p.owner == OWNER));
+    }
+
+    #[tokio::test]
+    async fn the_transaction_request_constructors_build_their_variant() {
+        let client = client().await;
+
+        let req = Request::send_crypto(send_params(client.clone()));
+        assert!(matches!(req, Request::SendCrypto(p) if p.amount == U256::from(5) && p.recipient == OWNER));
+
+        let params = BatchSendParams {
+            currency: Currency::new_native(CHAIN_ID),
+            rows: vec![(OWNER, U256::from(1)), (TOKEN, U256::from(2))],
+            wallet: Wallet::new_rng(String::new()),
+            chain_id: CHAIN_ID,
+            next_base_fee: U256::ZERO,
+            priority_fee: U256::ZERO,
+            mev_protect: false,
+            gas_multiplier: 1.0,
+            dry_run: true,
+            abort_on_failure: true,
+            client: client.clone(),
+        };
+        assert!(matches!(Request::batch_send(params), Request::BatchSend(p) if p.rows.len() == 2 && p.abort_on_failure));
+
+        let req = Request::approve(approve_params(client.clone()));
+        assert!(matches!(req, Request::Approve(p) if p.token == token() && p.spender == OWNER));
+
+        let tx = RawTx {
+            chain_id: CHAIN_ID,
+            to: TOKEN,
+            value: U256::ZERO,
+            data: Bytes::new(),
+            gas_limit: None,
+            nonce: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+        };
+        let params = SignRawTxParams {
+            tx: tx.clone(),
+            wallet: Wallet::new_rng(String::new()),
+            next_base_fee: U256::ZERO,
+            priority_fee: U256::ZERO,
+            mev_protect: false,
+            gas_multiplier: 1.0,
+            dry_run: true,
+            overrides: TxOverrides::default(),
+            client: client.clone(),
+        };
+        assert!(matches!(Request::sign_raw_tx(params), Request::SignRawTx(p) if p.tx == tx));
+
+        let signed = SignedRawTx {
+            hash: B256::repeat_byte(1),
+            from: OWNER,
+            to: Some(TOKEN),
+            value: U256::ZERO,
+            nonce: 3,
+            chain_id: Some(CHAIN_ID),
+            data: Bytes::new(),
+            gas_limit: 21_000,
+            raw: Bytes::from_static(&[2]),
+        };
+        let params = BroadcastRawTxParams { tx: signed, chain_id: CHAIN_ID, mev_protect: true, client: client.clone() };
+        assert!(matches!(Request::broadcast_raw_tx(params), Request::BroadcastRawTx(p) if p.tx.nonce == 3 && p.chain_id == CHAIN_ID && p.mev_protect));
+
+        let req = Request::sign_transaction(TxRequest::Transfer(send_params(client.clone())));
+        assert!(matches!(req, Request::SignTransaction(TxRequest::Transfer(p)) if p.amount == U256::from(5)));
+        let req = Request::sign_transaction(TxRequest::Approve(approve_params(client)));
+        assert!(matches!(req, Request::SignTransaction(TxRequest::Approve(p)) if p.token == token()));
+    }
+
+    #[tokio::test]
+    async fn the_response_constructors_build_their_variant() {
+        let client = client().await;
+        let amount = TxAmount::native(CHAIN_ID, U256::from(5));
+
+        assert!(matches!(Response::eth_balance(U256::from(5)), Response::EthBalance(b) if b == U256::from(5)));
+        assert!(matches!(Response::client(Ok(client), ChainId::Ethereum(1)), Response::Client(Ok(_), ChainId::Ethereum(1))));
+        assert!(matches!(
+            Response::client(Err("refused".to_string()), ChainId::Base(8453)),
+            Response::Client(Err(e), ChainId::Base(8453)) if e == "refused"
+        ));
+        assert!(matches!(
+            Response::erc20_token("id".to_string(), OWNER, token(), U256::from(5), CHAIN_ID, true),
+            Response::ERC20Token(res) if res.currency_id == "id" && res.owner == OWNER && res.token == token() && res.balance == U256::from(5) && res.added
+        ));
+        assert!(matches!(Response::latency(Duration::from_millis(40)), Response::Latency(d) if d == Duration::from_millis(40)));
+
+        let dry_run = DryRunResult {
+            tx_hash: B256::repeat_byte(1),
+            raw_tx: Bytes::new(),
+            to: OWNER,
+            value: U256::from(5),
+            amount: amount.clone(),
+            call_data: Bytes::new(),
+            simulation: simulation(),
+        };
+        let res = Response::transaction(TxOutcome::DryRun(Box::new(dry_run)));
+        assert!(matches!(res, Response::Transaction(TxOutcome::DryRun(r)) if r.tx_hash == B256::repeat_byte(1)));
+
+        let call = TxCall::new(OWNER, TOKEN, U256::ZERO, Bytes::new());
+        let estimate = TxEstimate { gas_limit: 50_000, access_list: None };
+        let res = Response::tx_estimate(call.clone(), Ok(estimate));
+        assert!(matches!(res, Response::TxEstimate(c, Ok(e)) if c == call && e.gas_limit == 50_000));
+
+        let signed = SignedTx {
+            tx_hash: B256::repeat_byte(2),
+            raw_tx: Bytes::new(),
+            chain_id: CHAIN_ID,
+            nonce: 4,
+            to: OWNER,
+            value: U256::from(5),
+            amount,
+            simulation: simulation(),
+        };
+        assert!(matches!(Response::signed_transaction(signed), Response::SignedTransaction(tx) if tx.nonce == 4));
+        assert!(matches!(Response::batch_send(2, BatchRowStatus::Skipped), Response::BatchSend(2, BatchRowStatus::Skipped)));
+
+        let cache = CacheRes { erc20_balance: HashMap::new(), eth_balance: HashMap::new(), pinned: HashSet::from([(CHAIN_ID, TOKEN)]) };
+        assert!(matches!(Response::cache_loaded(cache), Response::CacheLoaded(c) if c.pinned.contains(&(CHAIN_ID, TOKEN))));
+        assert!(matches!(Response::watches(vec![watch()]), Response::Watches(w) if w == [watch()]));
+        assert!(matches!(Response::watch_triggered(watch(), 2.6), Response::WatchTriggered(w, price) if w == watch() && price == 2.6));
+
+        let pool = Pool::new(OWNER, token(), ERC20Token::eth_default_output(), PoolVariant::UniswapV2, 3000, CHAIN_ID);
+        let res = Response::spot_price(token(), ERC20Token::eth_default_output(), pool, U256::from(9));
+        assert!(matches!(res, Response::SpotPrice(s) if s.token_in == token() && s.pool.address == OWNER && s.price == U256::from(9)));
+
+        assert!(matches!(Response::token_refreshed(token()), Response::TokenRefreshed(t) if t == token()));
+
+        let quote = QuoteResult { block_number: BLOCK, ..Default::default() };
+        assert!(matches!(Response::quote(quote), Response::Quote(q) if q.block_number == BLOCK));
+
+        let res = Response::historical_balance(Currency::new_erc20(token()), OWNER, CHAIN_ID, BLOCK, U256::from(5));
+        assert!(matches!(res, Response::HistoricalBalance(h) if h.owner == OWNER && h.block == BLOCK && h.balance == U256::from(5)));
+
+        let res = Response::erc20_balance(OWNER, TOKEN, U256::from(5), CHAIN_ID);
+        assert!(matches!(res, Response::ERC20Balance(b) if b.owner == OWNER && b.token == TOKEN && b.balance == U256::from(5)));
+
+        let res = Response::balances_batch(CHAIN_ID, OWNER, vec![(TOKEN, U256::from(5))]);
+        assert!(matches!(res, Response::BalancesBatch { chain_id: CHAIN_ID, owner: OWNER, balances } if balances == [(TOKEN, U256::from(5))]));
+    }
+}
@@ -2,9 +2,15 @@ use alloy::{
     providers::{RootProvider, Provider},
     pubsub::PubSubFrontend,
 };
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    defi_types::pool::dex_supported,
+    evm_types::simulation::get_native_coin,
+    utils::{explorer::explorer_api_url, oracles::block::eth_usd_feed},
+};
+
 
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -13,8 +19,20 @@ pub enum ChainId {
     BinanceSmartChain(u64),
     Base(u64),
     Arbitrum(u64),
+    Sepolia(u64),
+    BaseSepolia(u64),
 }
 
+/// Every chain we support, the main networks first
+const ALL_CHAINS: [ChainId; 6] = [
+    ChainId::Ethereum(1),
+    ChainId::BinanceSmartChain(56),
+    ChainId::Base(8453),
+    ChainId::Arbitrum(42161),
+    ChainId::Sepolia(11155111),
+    ChainId::BaseSepolia(84532),
+];
+
 impl Default for ChainId {
     fn default() -> Self {
         Self::Ethereum(1)
@@ -25,13 +43,17 @@ impl ChainId {
 
     pub async fn new(client: Arc<RootProvider<PubSubFrontend>>) -> Result<Self, anyhow::Error> {
         let chain_id = client.get_chain_id().await?;
-        match chain_id {
-            1 => Ok(Self::Ethereum(1)),
-            56 => Ok(Self::BinanceSmartChain(56)),
-            8453 => Ok(Self::Base(8453)),
-            42161 => Ok(Self::Arbitrum(42161)),
-            _ => Err(anyhow::anyhow!("Unsupported chain id: {}", chain_id)),
-        }
+        Self::from_id(chain_id).ok_or_else(|| anyhow::anyhow!("Unsupported chain id: {}", chain_id))
+    }
+
+    /// Every chain we support, the main networks first and the test networks last
+    pub fn all() -> &'static [ChainId] {
+        &ALL_CHAINS
+    }
+
+    /// The [ChainId] of a chain we support
+    pub fn from_id(chain_id: u64) -> Option<Self> {
+        Self::all().iter().find(|chain| chain.id() == chain_id).cloned()
     }
 
     pub fn name(&self) -> String {
@@ -40,6 +62,8 @@ impl ChainId {
             Self::BinanceSmartChain(_) => "Binance Smart Chain".to_string(),
             Self::Base(_) => "Base".to_string(),
             Self::Arbitrum(_) => "Arbitrum".to_string(),
+            Self::Sepolia(_) => "Sepolia".to_string(),
+            Self::BaseSepolia(_) => "Base Sepolia".to_string(),
         
     }
 }
@@ -50,7 +74,133 @@ impl ChainId {
             Self::BinanceSmartChain(id) => *id,
             Self::Base(id) => *id,
             Self::Arbitrum(id) => *id,
+            Self::Sepolia(id) => *id,
+            Self::BaseSepolia(id) => *id,
         
     }
 }
-}
\ No newline at end of file
+
+    /// Is this a test network, they are only listed when the user enables them
+    pub fn is_testnet(&self) -> bool {
+        matches!(self, Self::Sepolia(_) | Self::BaseSepolia(_))
+    }
+
+    /// Where to get test coins for a test network
+    pub fn faucet_url(&self) -> Option<&'static str> {
+        match self {
+            Self::Sepolia(_) => Some("https://www.alchemy.com/faucets/ethereum-sepolia"),
+            Self::BaseSepolia(_) => Some("https://www.alchemy.com/faucets/base-sepolia"),
+            _ => None,
+        }
+    }
+
+    /// Is `feature` available on this chain, the UI uses this to hide or disable it
+    pub fn supports(&self, feature: ChainFeature) -> bool {
+        feature.supported_on(self.id())
+    }
+}
+
+/// A feature that is only available on some chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainFeature {
+    /// V2 and V3 pools, needed for swaps, watches and the DEX price fallback
+    Dex,
+
+    /// A ChainLink USD feed for the native currency
+    PriceFeed,
+
+    /// A public Blockscout API to fetch token metadata from
+    BlockExplorer,
+
+    /// Private transactions through flashbots
+    MevProtect,
+
+    /// A wrapped version of the native currency
+    WrappedNative,
+}
+
+impl ChainFeature {
+    pub fn supported_on(&self, chain_id: u64) -> bool {
+        match self {
+            Self::Dex => dex_supported(chain_id),
+            Self::PriceFeed => eth_usd_feed(chain_id).is_some(),
+            Self::BlockExplorer => explorer_api_url(chain_id).is_some(),
+            Self::MevProtect => chain_id == 1,
+            Self::WrappedNative => get_native_coin(chain_id).is_ok(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dex => "Swaps",
+            Self::PriceFeed => "The ChainLink price feed",
+            Self::BlockExplorer => "The block explorer API",
+            Self::MevProtect => "MEV protection",
+            Self::WrappedNative => "The wrapped native currency",
+        }
+    }
+}
+
+/// Returned when a feature is used on a chain that doesn't have it
+///
+/// Converts into an [anyhow::Error], use `downcast_ref` to tell it apart from a failed call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainUnsupported {
+    pub chain_id: u64,
+    pub feature: ChainFeature,
+}
+
+impl ChainUnsupported {
+    pub fn new(chain_id: u64, feature: ChainFeature) -> Self {
+        Self { chain_id, feature }
+    }
+}
+
+impl fmt::Display for ChainUnsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match ChainId::from_id(self.chain_id) {
+            Some(chain) => write!(f, "{} is not available on {}", self.feature.name(), chain.name()),
+            None => write!(f, "{} is not available on Chain ID {}", self.feature.name(), self.chain_id),
+        }
+    }
+}
+
+impl std::error::Error for ChainUnsupported {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adding a variant breaks this match, the test below then checks it was added to [ChainId::all]
+    fn variant_index(chain: &ChainId) -> usize {
+        match chain {
+            ChainId::Ethereum(_) => 0,
+            ChainId::BinanceSmartChain(_) => 1,
+            ChainId::Base(_) => 2,
+            ChainId::Arbitrum(_) => 3,
+            ChainId::Sepolia(_) => 4,
+            ChainId::BaseSepolia(_) => 5,
+        }
+    }
+
+    #[test]
+    fn all_lists_every_variant_once() {
+        let mut indexes: Vec<usize> = ChainId::all().iter().map(variant_index).collect();
+        indexes.sort();
+        assert_eq!(indexes, (0..=5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn from_id_finds_every_chain() {
+        for chain in ChainId::all() {
+            assert_eq!(ChainId::from_id(chain.id()).as_ref(), Some(chain));
+        }
+        assert_eq!(ChainId::from_id(10), None);
+    }
+
+    #[test]
+    fn test_networks_come_last() {
+        let first_testnet = ChainId::all().iter().position(ChainId::is_testnet).unwrap();
+        assert!(ChainId::all()[first_testnet..].iter().all(ChainId::is_testnet));
+    }
+}
@@ -1,10 +1,12 @@
 use alloy::{
     primitives::{ Address, Bytes, U256 },
     providers::RootProvider,
+    rpc::types::eth::BlockId,
     sol,
 };
 use alloy::pubsub::PubSubFrontend;
 use alloy::core::sol_types::SolCall;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::str::FromStr;
 use tokio::try_join;
@@ -31,8 +33,39 @@ sol! {
 
 
 
+/// No sane token has more decimals, a bigger value means the contract is broken or malicious
+pub const MAX_DECIMALS: u8 = 36;
+
+/// How much of a token we let a router spend when approving it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApprovalAmount {
+    /// Only the amount of the swap, a new approval is needed for the next one
+    #[default]
+    Exact,
+
+    /// [U256::MAX], the router can spend the whole balance from now on
+    Infinite,
+}
+
+impl ApprovalAmount {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Exact => "Exact amount".to_string(),
+            Self::Infinite => "Unlimited".to_string(),
+        }
+    }
+
+    /// The amount to approve for a swap of `amount_in`
+    pub fn amount(&self, amount_in: U256) -> U256 {
+        match self {
+            Self::Exact => amount_in,
+            Self::Infinite => U256::MAX,
+        }
+    }
+}
+
 /// Struct that holds ERC20 token information
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ERC20Token {
     pub chain_id: u64,
     pub address: Address,
@@ -40,6 +73,9 @@ pub struct ERC20Token {
     pub name: String,
     pub decimals: u8,
     pub total_supply: U256,
+
+    /// Not serialized, logged or exported currencies leave the image out
+    #[serde(skip)]
     pub icon: Option<Vec<u8>>,
 }
 
@@ -69,6 +105,18 @@ impl ERC20Token {
         })
     }
 
+    /// Reject a token that reports more than [MAX_DECIMALS] decimals
+    pub fn check_decimals(&self) -> Result<(), anyhow::Error> {
+        if self.decimals > MAX_DECIMALS {
+            return Err(anyhow::anyhow!(
+                "{} reports {} decimals, the token looks broken",
+                self.symbol,
+                self.decimals
+            ));
+        }
+        Ok(())
+    }
+
 
     async fn symbol(address: Address, client: Arc<RootProvider<PubSubFrontend>>) -> Result<String, anyhow::Error> {
         let contract = ERC20::new(address, client);
@@ -82,7 +130,8 @@ impl ERC20Token {
         Ok(name)
     }
 
-    async fn decimals(address: Address, client: Arc<RootProvider<PubSubFrontend>>) -> Result<u8, anyhow::Error> {
+    /// Read `decimals()` alone, eg. to check that a cached token did not change
+    pub async fn decimals(address: Address, client: Arc<RootProvider<PubSubFrontend>>) -> Result<u8, anyhow::Error> {
         let contract = ERC20::new(address, client);
         let decimals = contract.decimals().call().await?._0;
         Ok(decimals)
@@ -104,6 +153,18 @@ impl ERC20Token {
         Ok(bal.balance)
     }
 
+    /// The balance of `owner` at a past `block`, the node must still have the state of that block
+    pub async fn balance_at(
+        &self,
+        owner: Address,
+        block: u64,
+        client: Arc<RootProvider<PubSubFrontend>>
+    ) -> Result<U256, anyhow::Error> {
+        let contract = ERC20::new(self.address, client);
+        let bal = contract.balanceOf(owner).block(BlockId::number(block)).call().await?;
+        Ok(bal.balance)
+    }
+
     pub async fn allowance(
         &self,
         owner: Address,
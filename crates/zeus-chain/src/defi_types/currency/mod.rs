@@ -1,10 +1,51 @@
 
 
 pub mod erc20;
+pub mod stablecoin;
+use alloy::primitives::{Address, Bytes, U256, U512};
+use bigdecimal::{num_bigint::{BigInt, Sign}, BigDecimal, RoundingMode};
+use serde::{Deserialize, Serialize};
 use self::erc20::ERC20Token;
+use crate::chain_id::{ChainFeature, ChainUnsupported};
+
+/// Typical gas used by a native transfer
+pub const NATIVE_TRANSFER_GAS: u64 = 21_000;
+
+/// Typical gas used by an ERC20 transfer
+pub const ERC20_TRANSFER_GAS: u64 = 65_000;
+
+/// Currencies with more decimals than this are not priced, see [PriceError]
+pub const MAX_PRICE_DECIMALS: u8 = 30;
+
+/// Why a USD value could not be computed, the message is shown to the user as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum PriceError {
+    #[error("Cannot price this token, it reports {0} decimals")]
+    Decimals(u8),
+
+    #[error("Cannot price this token, the amount is too large")]
+    Overflow,
+}
+
+/// One whole unit (`10^decimals`) of a currency that can be priced
+pub fn price_unit(decimals: u8) -> Result<U256, PriceError> {
+    if decimals > MAX_PRICE_DECIMALS {
+        return Err(PriceError::Decimals(decimals));
+    }
+    Ok(U256::from(10).pow(U256::from(decimals)))
+}
+
+/// `a * b / denominator` without overflowing in between, `denominator` must not be zero
+fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256, PriceError> {
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    if result > U512::from(U256::MAX) {
+        return Err(PriceError::Overflow);
+    }
+    Ok(result.to::<U256>())
+}
 
 /// Represents a Currency, this can be a [NativeCurrency] to its chain (eg ETH, BNB) or any [ERC20Token]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Currency {
     Native(NativeCurrency),
     ERC20(ERC20Token),
@@ -30,6 +71,14 @@ impl Currency {
                 Self::Native(
                     NativeCurrency::default_for_chain(&42161)
                 ),
+            11155111 =>
+                Self::Native(
+                    NativeCurrency::default_for_chain(&11155111)
+                ),
+            84532 =>
+                Self::Native(
+                    NativeCurrency::default_for_chain(&84532)
+                ),
             // * This should not happen!
             _ =>
                 Self::Native(
@@ -49,14 +98,15 @@ impl Currency {
     }
 
     /// Created a new Default ERC20 token based on the chain_id
-    pub fn default_erc20(chain_id: u64) -> Self {
+    ///
+    /// This is the wrapped native currency, so chains without one have no default
+    pub fn default_erc20(chain_id: u64) -> Result<Self, ChainUnsupported> {
         match chain_id {
-            1 => Self::ERC20(ERC20Token::eth_default_input()),
-            56 => Self::ERC20(ERC20Token::bsc_default_input()),
-            8453 => Self::ERC20(ERC20Token::base_default_input()),
-            42161 => Self::ERC20(ERC20Token::arbitrum_default_input()),
-            // * This should not happen!
-            _ => Self::ERC20(ERC20Token::eth_default_input()),
+            1 => Ok(Self::ERC20(ERC20Token::eth_default_input())),
+            56 => Ok(Self::ERC20(ERC20Token::bsc_default_input())),
+            8453 => Ok(Self::ERC20(ERC20Token::base_default_input())),
+            42161 => Ok(Self::ERC20(ERC20Token::arbitrum_default_input())),
+            _ => Err(ChainUnsupported::new(chain_id, ChainFeature::WrappedNative)),
         }
     }
 
@@ -65,6 +115,17 @@ impl Currency {
         matches!(self, Self::Native(_))
     }
 
+    /// The token we trade or price this currency with, the wrapped version for a native currency
+    pub fn wrapped(&self) -> ERC20Token {
+        match self {
+            Self::Native(native) => Self::default_erc20(native.chain_id)
+                .ok()
+                .and_then(|currency| currency.erc20().cloned())
+                .unwrap_or_default(),
+            Self::ERC20(erc20) => erc20.clone(),
+        }
+    }
+
     /// Get the ERC20 Token
     pub fn erc20(&self) -> Option<&ERC20Token> {
         match self {
@@ -73,6 +134,14 @@ impl Currency {
         }
     }
 
+    /// The chain of the currency
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Self::Native(native) => native.chain_id,
+            Self::ERC20(erc20) => erc20.chain_id,
+        }
+    }
+
     /// Get currency symbol
     pub fn symbol(&self) -> String {
         match self {
@@ -97,6 +166,57 @@ impl Currency {
         }
     }
 
+    /// The USD value of `amount`, `price` is the USD price of this currency
+    ///
+    /// The result has the same decimals as `price`
+    pub fn usd_value(&self, amount: U256, price: U256) -> Result<U256, PriceError> {
+        mul_div(amount, price, price_unit(self.decimals())?)
+    }
+
+    /// The amount of this currency worth `usd`, the inverse of [Self::usd_value]
+    ///
+    /// Returns zero if `price` is zero
+    pub fn amount_for_usd(&self, usd: U256, price: U256) -> Result<U256, PriceError> {
+        let unit = price_unit(self.decimals())?;
+        if price.is_zero() {
+            return Ok(U256::ZERO);
+        }
+        mul_div(usd, unit, price)
+    }
+
+    /// A `raw` amount in the smallest unit as a decimal, eg. `1500000` of a 6 decimals token is `1.5`
+    pub fn to_big_decimal(&self, raw: U256) -> BigDecimal {
+        let digits = BigInt::from_bytes_be(Sign::Plus, &raw.to_be_bytes::<32>());
+        BigDecimal::new(digits, self.decimals() as i64)
+    }
+
+    /// A decimal `amount` in the smallest unit, the inverse of [Self::to_big_decimal]
+    ///
+    /// Digits beyond the decimals are truncated like [crate::parse_units], negative amounts are rejected
+    pub fn from_big_decimal(&self, amount: &BigDecimal) -> Result<U256, anyhow::Error> {
+        let (digits, _) = amount.with_scale_round(self.decimals() as i64, RoundingMode::Down).into_bigint_and_exponent();
+        let (sign, bytes) = digits.to_bytes_be();
+        if sign == Sign::Minus {
+            return Err(anyhow::anyhow!("Amount can't be negative: {}", amount));
+        }
+        U256::try_from_be_slice(&bytes).ok_or_else(|| anyhow::anyhow!("Amount is too large: {}", amount))
+    }
+
+    /// The `to`, value and call data of a transaction sending `amount` of this currency to `recipient`
+    pub fn transfer_call(&self, recipient: Address, amount: U256) -> (Address, U256, Bytes) {
+        match self {
+            Self::Native(_) => (recipient, amount, Bytes::new()),
+            Self::ERC20(token) => (token.address, U256::ZERO, Bytes::from(token.encode_transfer(recipient, amount))),
+        }
+    }
+
+    /// The gas a transfer of this currency typically uses
+    pub fn transfer_gas(&self) -> u64 {
+        match self {
+            Self::Native(_) => NATIVE_TRANSFER_GAS,
+            Self::ERC20(_) => ERC20_TRANSFER_GAS,
+        }
+    }
 }
 
 impl Default for Currency {
@@ -106,12 +226,15 @@ impl Default for Currency {
 }
 
 /// Represents a Native Currency to its chain
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NativeCurrency {
     pub chain_id: u64,
     pub symbol: String,
     pub name: String,
     pub decimals: u8,
+
+    /// Not serialized, logged or exported currencies leave the image out
+    #[serde(skip)]
     pub icon: Option<Vec<u8>>,
 }
 
@@ -136,6 +259,13 @@ impl NativeCurrency {
         }
     }
 
+    /// The USD value of `amount`, `price` is the USD price of this currency
+    ///
+    /// The result has the same decimals as `price`
+    pub fn usd_value(&self, amount: U256, price: U256) -> Result<U256, PriceError> {
+        mul_div(amount, price, price_unit(self.decimals)?)
+    }
+
     /// A Default Native Currency for a chain id
     pub fn default_for_chain(id: &u64) -> Self {
         match id {
@@ -143,8 +273,82 @@ impl NativeCurrency {
             56 => Self::new(56, "BNB".to_string(), "Binance Coin".to_string(), 18, None),
             8453 => Self::new(8453, "ETH".to_string(), "Ethereum".to_string(), 18, None),
             42161 => Self::new(42161, "ETH".to_string(), "Ethereum".to_string(), 18, None),
+            11155111 => Self::new(11155111, "ETH".to_string(), "Sepolia Ether".to_string(), 18, None),
+            84532 => Self::new(84532, "ETH".to_string(), "Sepolia Ether".to_string(), 18, None),
             // * This should not happen!
             _ => Self::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(decimals: u8) -> Currency {
+        Currency::new_erc20(ERC20Token { decimals, ..Default::default() })
+    }
+
+    #[test]
+    fn big_decimals_round_trip() {
+        let amounts = [U256::ZERO, U256::from(1), U256::from(1_500_000), U256::MAX];
+        for currency in [token(0), token(6), token(18), Currency::new_native(1), token(u8::MAX)] {
+            for raw in amounts {
+                let decimal = currency.to_big_decimal(raw);
+                assert_eq!(currency.from_big_decimal(&decimal).unwrap(), raw);
+            }
+        }
+        assert_eq!(token(6).to_big_decimal(U256::from(1_500_000)).to_string(), "1.500000");
+        assert_eq!(Currency::new_native(1).to_big_decimal(U256::from(10).pow(U256::from(18))), BigDecimal::from(1));
+    }
+
+    #[test]
+    fn big_decimals_parse_like_typed_amounts() {
+        for (typed, decimals) in [("1.5", 6), ("0.1234567", 6), ("42", 0), ("0.000001", 18), ("1.99", 0)] {
+            let amount: BigDecimal = typed.parse().unwrap();
+            assert_eq!(token(decimals).from_big_decimal(&amount).unwrap(), crate::parse_units(typed, decimals).unwrap());
+        }
+        assert!(token(6).from_big_decimal(&"-1".parse().unwrap()).is_err());
+        assert!(token(0).from_big_decimal(&BigDecimal::new(BigInt::from(1), -78)).is_err());
+    }
+
+    #[test]
+    fn usd_value_round_trips() {
+        let usdc = token(6);
+        // $3000 with 8 decimals
+        let price = U256::from(300_000_000_000u64);
+        assert_eq!(usdc.usd_value(U256::from(2_000_000), price), Ok(U256::from(600_000_000_000u64)));
+        assert_eq!(usdc.amount_for_usd(U256::from(600_000_000_000u64), price), Ok(U256::from(2_000_000)));
+        assert_eq!(usdc.amount_for_usd(U256::from(1), U256::ZERO), Ok(U256::ZERO));
+    }
+
+    #[test]
+    fn absurd_decimals_cannot_be_priced() {
+        let price = U256::from(100_000_000);
+        assert!(token(MAX_PRICE_DECIMALS).usd_value(U256::from(1), price).is_ok());
+        assert_eq!(token(31).usd_value(U256::from(1), price), Err(PriceError::Decimals(31)));
+        assert_eq!(token(u8::MAX).amount_for_usd(U256::from(1), price), Err(PriceError::Decimals(u8::MAX)));
+        assert_eq!(PriceError::Decimals(31).to_string(), "Cannot price this token, it reports 31 decimals");
+    }
+
+    #[test]
+    fn extreme_amounts_are_priced_or_rejected_without_panicking() {
+        let mut values = vec![U256::ZERO, U256::from(1), U256::MAX, U256::MAX / U256::from(3)];
+        values.extend((0..78).map(|exp| U256::from(10).pow(U256::from(exp))));
+
+        for decimals in [0, 6, 18, MAX_PRICE_DECIMALS, 31, u8::MAX] {
+            let currency = token(decimals);
+            for &amount in &values {
+                for &price in &values {
+                    match currency.usd_value(amount, price) {
+                        Ok(usd) => assert!(usd <= U256::MAX),
+                        Err(PriceError::Decimals(_)) => assert!(decimals > MAX_PRICE_DECIMALS),
+                        Err(PriceError::Overflow) => assert!(amount > U256::from(1) && price > U256::from(1)),
+                    }
+                    let _ = currency.amount_for_usd(amount, price);
+                }
+            }
+        }
+        assert_eq!(token(0).usd_value(U256::MAX, U256::from(2)), Err(PriceError::Overflow));
+    }
+}
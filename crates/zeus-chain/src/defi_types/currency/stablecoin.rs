@@ -0,0 +1,127 @@
+use alloy::primitives::{address, Address};
+
+use super::Currency;
+
+/// Well known stablecoins for each supported chain
+pub fn default_stablecoins(chain_id: u64) -> Vec<Address> {
+    match chain_id {
+        1 => vec![
+            // USDC
+            address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            // USDT
+            address!("dAC17F958D2ee523a2206206994597C13D831ec7"),
+            // DAI
+            address!("6B175474E89094C44Da98b954EedeAC495271d0F"),
+        ],
+        56 => vec![
+            // USDC
+            address!("8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d"),
+            // USDT
+            address!("55d398326f99059fF775485246999027B3197955"),
+            // DAI
+            address!("1AF3F329e8BE154074D8769D1FFa4eE058B1DBc3"),
+        ],
+        8453 => vec![
+            // USDC
+            address!("833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
+            // USDbC
+            address!("d9aAEc86B65D86f6A7B5B1b0c42FFA531710b6CA"),
+            // DAI
+            address!("50c5725949A6F0c72E6C4a641F24049A917DB0Cb"),
+        ],
+        42161 => vec![
+            // USDC
+            address!("af88d065e77c8cC2239327C5EDb3A432268e5831"),
+            // USDC.e
+            address!("FF970A61A04b1cA14834A43f5dE4533eBDDB5CC8"),
+            // USDT
+            address!("Fd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9"),
+            // DAI
+            address!("DA10009cBd5D07dd0CeCc66161FC93D7c9000da1"),
+        ],
+        _ => vec![],
+    }
+}
+
+/// How volatile a swap pair is, based on how many of its legs are stablecoins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairClass {
+    /// Both legs are stablecoins (eg. USDC/USDT)
+    Stable,
+
+    /// One leg is a stablecoin (eg. WETH/USDC)
+    Mixed,
+
+    /// Neither leg is a stablecoin
+    Volatile,
+}
+
+impl PairClass {
+    /// Classify a pair, `is_stable` decides if a single currency is a stablecoin
+    pub fn classify<F>(a: &Currency, b: &Currency, is_stable: F) -> Self
+    where
+        F: Fn(&Currency) -> bool,
+    {
+        match (is_stable(a), is_stable(b)) {
+            (true, true) => PairClass::Stable,
+            (true, false) | (false, true) => PairClass::Mixed,
+            (false, false) => PairClass::Volatile,
+        }
+    }
+
+    /// The slippage (in %) we use for this class when auto slippage is on
+    pub fn default_slippage(&self) -> f32 {
+        match self {
+            PairClass::Stable => 0.05,
+            PairClass::Mixed => 0.5,
+            PairClass::Volatile => 1.0,
+        }
+    }
+
+    /// Why this class was picked, shown next to the applied slippage
+    pub fn reason(&self) -> &'static str {
+        match self {
+            PairClass::Stable => "both tokens are stablecoins",
+            PairClass::Mixed => "one token is a stablecoin",
+            PairClass::Volatile => "neither token is a stablecoin",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defi_types::currency::erc20::ERC20Token;
+
+    fn token(address: Address) -> Currency {
+        Currency::new_erc20(ERC20Token { chain_id: 1, address, ..Default::default() })
+    }
+
+    #[test]
+    fn pairs_are_classified_by_their_stable_legs() {
+        let usdc = token(address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"));
+        let usdt = token(address!("dAC17F958D2ee523a2206206994597C13D831ec7"));
+        let weth = token(address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"));
+        let eth = Currency::new_native(1);
+        let is_stable = |currency: &Currency| currency.erc20().is_some_and(|t| default_stablecoins(1).contains(&t.address));
+
+        assert_eq!(PairClass::classify(&usdc, &usdt, is_stable), PairClass::Stable);
+        assert_eq!(PairClass::classify(&weth, &usdc, is_stable), PairClass::Mixed);
+        assert_eq!(PairClass::classify(&usdc, &eth, is_stable), PairClass::Mixed);
+        assert_eq!(PairClass::classify(&weth, &eth, is_stable), PairClass::Volatile);
+    }
+
+    #[test]
+    fn the_more_volatile_the_pair_the_more_slippage() {
+        let slippage = [PairClass::Stable, PairClass::Mixed, PairClass::Volatile].map(|class| class.default_slippage());
+        assert!(slippage.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn stablecoins_are_per_chain() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        assert!(default_stablecoins(1).contains(&usdc));
+        assert!(!default_stablecoins(8453).contains(&usdc));
+        assert!(default_stablecoins(10).is_empty());
+    }
+}
@@ -1,13 +1,19 @@
-use alloy::{ primitives::{ Address, U256 }, providers::RootProvider, sol };
+use alloy::{ primitives::{ Address, U256, U512 }, providers::RootProvider, sol };
 use alloy::pubsub::PubSubFrontend;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::str::FromStr;
 use lazy_static::lazy_static;
-use super::currency::erc20::ERC20Token;
+use super::currency::{erc20::ERC20Token, price_unit};
+use crate::utils::rpc_usage::{record_rpc, RpcOrigin};
+use crate::chain_id::{ChainFeature, ChainUnsupported};
 use anyhow::anyhow;
 
 pub const V3_FEES: [u32; 4] = [100, 500, 3000, 10000];
 
+/// V3 fee tiers we try, in order, when we just need a reference price
+pub const REFERENCE_POOL_FEES: [u32; 4] = [500, 3000, 2500, 100];
+
 lazy_static! {
     // Ethereum Mainnet Uniswap Factories
     static ref ETH_UNISWAP_V2_FACTORY: Address = Address::from_str(
@@ -51,9 +57,21 @@ sol! {
     contract UniswapV3Factory {
         function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
     }
+    #[sol(rpc)]
+    contract UniswapV2Pair {
+        function token0() external view returns (address);
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+        function swap(uint amount0Out, uint amount1Out, address to, bytes calldata data) external;
+    }
+    #[sol(rpc)]
+    contract UniswapV3Pool {
+        function slot0() external view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked);
+    }
 }
 
 /// Represents a Pool in any DEX that is a fork of Uniswap
+///
+/// `token0` and `token1` are in the order of the pool contract, see [get_pool_tokens]
 #[derive(Debug, Clone)]
 pub struct Pool {
     pub chain_id: u64,
@@ -83,9 +101,124 @@ impl Pool {
             PoolVariant::UniswapV3 => U256::from(1),
         }
     }
+
+    /// Are we swapping `token0` for `token1`
+    fn zero_for_one(&self, token_in: &ERC20Token) -> bool {
+        token_in.address == self.token0.address
+    }
+
+    /// The reserves of `token_in` and of the other token, in that order
+    fn reserves_for(&self, token_in: &ERC20Token, reserve0: U256, reserve1: U256) -> (U256, U256) {
+        if self.zero_for_one(token_in) {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        }
+    }
+
+    /// The amount of the other token we get for `amount_in` of `token_in` after the fee and the price impact
+    ///
+    /// Only supported for V2 pools, see [get_amount_out]
+    pub fn amount_out(&self, state: &PoolState, token_in: &ERC20Token, amount_in: U256) -> Result<U256, anyhow::Error> {
+        if token_in.address != self.token0.address && token_in.address != self.token1.address {
+            return Err(anyhow!("{} is not in the pool", token_in.symbol));
+        }
+
+        match state {
+            PoolState::UniswapV2 { reserve0, reserve1 } => {
+                let (reserve_in, reserve_out) = self.reserves_for(token_in, *reserve0, *reserve1);
+                Ok(get_amount_out(amount_in, reserve_in, reserve_out, self.fee))
+            }
+            PoolState::UniswapV3 { .. } => Err(anyhow!("Amount out is not supported for V3 pools")),
+        }
+    }
+
+    /// The other token of the pool
+    pub fn token_out(&self, token_in: &ERC20Token) -> &ERC20Token {
+        if token_in.address == self.token0.address {
+            &self.token1
+        } else {
+            &self.token0
+        }
+    }
+
+    /// Read the reserves (V2) or the sqrt price (V3) of the pool
+    pub async fn fetch_state(&self, client: Arc<RootProvider<PubSubFrontend>>) -> Result<PoolState, anyhow::Error> {
+        match self.variant {
+            PoolVariant::UniswapV2 => {
+                let reserves = UniswapV2Pair::new(self.address, client).getReserves().call().await?;
+                Ok(PoolState::UniswapV2 {
+                    reserve0: U256::from(reserves.reserve0),
+                    reserve1: U256::from(reserves.reserve1),
+                })
+            }
+            PoolVariant::UniswapV3 => {
+                let slot0 = UniswapV3Pool::new(self.address, client).slot0().call().await?;
+                Ok(PoolState::UniswapV3 {
+                    sqrt_price_x96: U256::from(slot0.sqrtPriceX96),
+                })
+            }
+        }
+    }
+
+    /// Quote `amount_in` of `token_in` at the spot price of the pool
+    ///
+    /// This ignores the pool fee and the price impact, so its only good as a price reference
+    pub fn quote(&self, state: &PoolState, token_in: &ERC20Token, amount_in: U256) -> Result<U256, anyhow::Error> {
+        if token_in.address != self.token0.address && token_in.address != self.token1.address {
+            return Err(anyhow!("{} is not in the pool", token_in.symbol));
+        }
+
+        let zero_for_one = self.zero_for_one(token_in);
+        let amount_in = U512::from(amount_in);
+
+        let amount_out: U512 = match state {
+            PoolState::UniswapV2 { reserve0, reserve1 } => {
+                let (reserve_in, reserve_out) = self.reserves_for(token_in, *reserve0, *reserve1);
+                if reserve_in.is_zero() {
+                    return Err(anyhow!("Pool has no liquidity"));
+                }
+                amount_in * U512::from(reserve_out) / U512::from(reserve_in)
+            }
+            PoolState::UniswapV3 { sqrt_price_x96 } => {
+                let sqrt_price = U512::from(*sqrt_price_x96);
+                if sqrt_price.is_zero() {
+                    return Err(anyhow!("Pool is not initialized"));
+                }
+
+                // price of token0 in token1 = sqrtPriceX96^2 / 2^192
+                let price_x192 = sqrt_price * sqrt_price;
+                let q192 = U512::from(1) << 192;
+
+                if zero_for_one {
+                    amount_in * price_x192 / q192
+                } else {
+                    amount_in * q192 / price_x192
+                }
+            }
+        };
+
+        if amount_out > U512::from(U256::MAX) {
+            return Err(anyhow!("Quote overflows U256"));
+        }
+        Ok(amount_out.to::<U256>())
+    }
+
+    /// How much of the other token one whole `token_in` is worth, see [Pool::quote]
+    pub fn spot_price(&self, state: &PoolState, token_in: &ERC20Token) -> Result<U256, anyhow::Error> {
+        let one = price_unit(token_in.decimals)?;
+        self.quote(state, token_in, one)
+    }
 }
 
+/// The on-chain state of a [Pool] we need to price it
 #[derive(Debug, Clone)]
+pub enum PoolState {
+    UniswapV2 { reserve0: U256, reserve1: U256 },
+    UniswapV3 { sqrt_price_x96: U256 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PoolVariant {
     UniswapV2,
     UniswapV3,
@@ -114,7 +247,8 @@ pub async fn get_v2_pool(
         return Err(anyhow!("Pool not found"));
     }
 
-    let pool = Pool::new(pool_address, token0, token1, PoolVariant::UniswapV2, 3000, chain_id);
+    let (token0, token1) = get_pool_tokens(pool_address, token0, token1, client).await?;
+    let pool = Pool::new(pool_address, token0, token1, PoolVariant::UniswapV2, get_v2_pool_fee(chain_id), chain_id);
     Ok(pool)
 }
 
@@ -133,11 +267,113 @@ pub async fn get_v3_pool(
         return Err(anyhow!("Pool not found"));
     }
 
+    let (token0, token1) = get_pool_tokens(pool_address, token0, token1, client).await?;
     let pool = Pool::new(pool_address, token0, token1, PoolVariant::UniswapV3, fee, chain_id);
 
     Ok(pool)
 }
 
+/// Put `token_a` and `token_b` in the order of the pool contract by reading its `token0()`
+///
+/// Uniswap sorts the tokens by address but forks are not required to, so we never assume it
+pub async fn get_pool_tokens(
+    pool: Address,
+    token_a: ERC20Token,
+    token_b: ERC20Token,
+    client: Arc<RootProvider<PubSubFrontend>>
+) -> Result<(ERC20Token, ERC20Token), anyhow::Error> {
+    // V2 pairs and V3 pools share the same `token0()`
+    let token0 = UniswapV2Pair::new(pool, client).token0().call().await?._0;
+
+    if token0 == token_a.address {
+        Ok((token_a, token_b))
+    } else if token0 == token_b.address {
+        Ok((token_b, token_a))
+    } else {
+        Err(anyhow!("Pool {} does not hold {}", pool, token_a.symbol))
+    }
+}
+
+/// Quote `amount_in` of `token_in` to `token_out` at the spot price of a Uniswap V3 pool, see [Pool::quote]
+pub async fn get_v3_quote(
+    token_in: ERC20Token,
+    token_out: ERC20Token,
+    amount_in: U256,
+    fee: u32,
+    chain_id: u64,
+    client: Arc<RootProvider<PubSubFrontend>>
+) -> Result<U256, anyhow::Error> {
+    let pool = get_v3_pool(token_in.clone(), token_out, fee, chain_id, client.clone()).await?;
+    let state = pool.fetch_state(client).await?;
+    pool.quote(&state, &token_in, amount_in)
+}
+
+/// [get_v3_quote] from the first pool of the pair we can find, see [REFERENCE_POOL_FEES]
+///
+/// Every attempt is counted against `origin` in the [crate::utils::RPC_USAGE]
+pub async fn get_reference_v3_quote(
+    token_in: ERC20Token,
+    token_out: ERC20Token,
+    amount_in: U256,
+    chain_id: u64,
+    client: Arc<RootProvider<PubSubFrontend>>,
+    origin: RpcOrigin
+) -> Result<U256, anyhow::Error> {
+    let mut last_err = anyhow!("No pool found");
+
+    for fee in REFERENCE_POOL_FEES {
+        // getPool + token0 + slot0
+        record_rpc(origin, 3);
+        match get_v3_quote(token_in.clone(), token_out.clone(), amount_in, fee, chain_id, client.clone()).await {
+            Ok(amount) => return Ok(amount),
+            Err(e) => last_err = e,
+        }
+    }
+
+    Err(last_err)
+}
+
+/// The fee of the V2 pools in hundredths of a bip like [Pool::fee]
+///
+/// PancakeSwap V2 charges 0.25%, Uniswap V2 0.3%
+pub fn get_v2_pool_fee(chain_id: u64) -> u32 {
+    match chain_id {
+        56 => 2500,
+        _ => 3000,
+    }
+}
+
+/// The DEX whose V2 and V3 factories we use on the chain
+pub fn dex_name(chain_id: u64) -> &'static str {
+    match chain_id {
+        56 => "PancakeSwap",
+        _ => "Uniswap",
+    }
+}
+
+/// The Uniswap V2 `getAmountOut` formula
+///
+/// `fee` is in hundredths of a bip like [Pool::fee] (3000 = 0.3%)
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee: u32) -> U256 {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return U256::ZERO;
+    }
+
+    let amount_in_with_fee = U512::from(amount_in) * U512::from(1_000_000u32.saturating_sub(fee));
+    let numerator = amount_in_with_fee * U512::from(reserve_out);
+    let denominator = U512::from(reserve_in) * U512::from(1_000_000) + amount_in_with_fee;
+
+    // always less than `reserve_out` so it fits in a U256
+    (numerator / denominator).to::<U256>()
+}
+
+/// Do we know the V2 and V3 factories of the chain
+///
+/// Swaps, quotes and price watches are only available on these chains
+pub fn dex_supported(chain_id: u64) -> bool {
+    get_v2_pool_factory(chain_id).is_ok() && get_v3_pool_factory(chain_id).is_ok()
+}
+
 /// Gets the v2 pool factory based on the chain id
 /// 
 /// Supports Uniswap V2 and PancakeSwap V2
@@ -147,7 +383,7 @@ pub fn get_v2_pool_factory(chain_id: u64) -> Result<Address, anyhow::Error>{
         56 => Ok(*BSC_PANCAKESWAP_V2_FACTORY), // Pancake V2
         8453 => Ok(*BASE_UNISWAP_V2_FACTORY), // Uni V2
         42161 => Ok(*ARBITRUM_UNISWAP_V2_FACTORY), // Uni V2
-        _ => Err(anyhow!("Unsupported chain id"))
+        _ => Err(ChainUnsupported::new(chain_id, ChainFeature::Dex).into())
     }
 
 }
@@ -161,6 +397,163 @@ pub fn get_v3_pool_factory(chain_id: u64) -> Result<Address, anyhow::Error> {
         56 => Ok(*BSC_PANCAKESWAP_V3_FACTORY), // Pancake V3
         8453 => Ok(*BASE_UNISWAP_V3_FACTORY), // Uni V3
         42161 => Ok(*ARBITRUM_UNISWAP_V3_FACTORY), // Uni V3
-        _ => Err(anyhow!("Unsupported chain id"))
+        _ => Err(ChainUnsupported::new(chain_id, ChainFeature::Dex).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{
+        prelude::*,
+        test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+    };
+
+    /// Fixed so every run checks the same cases
+    const SEED: [u8; 32] = [7; 32];
+
+    /// Reserves of a V2 pair are stored as uint112
+    const MAX_RESERVE: u128 = (1 << 112) - 1;
+
+    fn runner() -> TestRunner {
+        let config = Config {
+            cases: 1024,
+            failure_persistence: None,
+            ..Config::default()
+        };
+        TestRunner::new_with_rng(config, TestRng::from_seed(RngAlgorithm::ChaCha, &SEED))
+    }
+
+    /// The formula as written in the UniswapV2Library contract (997 / 1000)
+    fn reference_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256) -> U256 {
+        let amount_in_with_fee = amount_in * U256::from(997);
+        let numerator = amount_in_with_fee * reserve_out;
+        let denominator = reserve_in * U256::from(1000) + amount_in_with_fee;
+        numerator / denominator
+    }
+
+    fn token(address: [u8; 20]) -> ERC20Token {
+        ERC20Token {
+            address: Address::from(address),
+            decimals: 18,
+            ..ERC20Token::default()
+        }
+    }
+
+    #[test]
+    fn factory_lookups_return_an_address_or_chain_unsupported() {
+        // every ChainId variant plus chains we don't know
+        for chain_id in [1, 56, 8453, 42161, 11155111, 84532, 0, 10] {
+            for factory in [get_v2_pool_factory(chain_id), get_v3_pool_factory(chain_id)] {
+                match factory {
+                    Ok(address) => assert_ne!(address, Address::ZERO),
+                    Err(e) => assert_eq!(
+                        e.downcast_ref::<ChainUnsupported>(),
+                        Some(&ChainUnsupported::new(chain_id, ChainFeature::Dex))
+                    ),
+                }
+            }
+        }
+
+        assert!(dex_supported(56));
+        assert!(!dex_supported(11155111));
+    }
+
+    #[test]
+    fn amount_out_is_monotonic() {
+        let strategy = (1..=MAX_RESERVE, 1..=MAX_RESERVE, 0..=MAX_RESERVE, 0..=MAX_RESERVE, prop_oneof![Just(2500u32), Just(3000u32)]);
+
+        runner()
+            .run(&strategy, |(reserve_in, reserve_out, amount, extra, fee)| {
+                let (reserve_in, reserve_out) = (U256::from(reserve_in), U256::from(reserve_out));
+                let smaller = get_amount_out(U256::from(amount), reserve_in, reserve_out, fee);
+                let bigger = get_amount_out(U256::from(amount) + U256::from(extra), reserve_in, reserve_out, fee);
+                prop_assert!(smaller <= bigger);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn amount_out_never_drains_the_pool() {
+        let strategy = (1..=MAX_RESERVE, 1..=MAX_RESERVE, any::<u128>(), prop_oneof![Just(100u32), Just(2500u32), Just(3000u32), Just(10000u32)]);
+
+        runner()
+            .run(&strategy, |(reserve_in, reserve_out, amount, fee)| {
+                let reserve_out = U256::from(reserve_out);
+                let amount_out = get_amount_out(U256::from(amount), U256::from(reserve_in), reserve_out, fee);
+                prop_assert!(amount_out < reserve_out);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn amount_out_matches_the_contract() {
+        let strategy = (1..=MAX_RESERVE, 1..=MAX_RESERVE, 0..=MAX_RESERVE);
+
+        runner()
+            .run(&strategy, |(reserve_in, reserve_out, amount)| {
+                let (amount, reserve_in, reserve_out) = (U256::from(amount), U256::from(reserve_in), U256::from(reserve_out));
+                prop_assert_eq!(
+                    get_amount_out(amount, reserve_in, reserve_out, 3000),
+                    reference_amount_out(amount, reserve_in, reserve_out)
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn amount_out_follows_pool_order() {
+        let strategy = (any::<[u8; 20]>(), any::<[u8; 20]>(), 1..=MAX_RESERVE, 1..=MAX_RESERVE, 1..=MAX_RESERVE);
+
+        runner()
+            .run(&strategy, |(a, b, reserve0, reserve1, amount)| {
+                prop_assume!(a != b);
+                let (token0, token1) = (token(a), token(b));
+
+                // token0 of the pool is not always the smaller address
+                let pool = Pool::new(Address::ZERO, token0.clone(), token1.clone(), PoolVariant::UniswapV2, 3000, 1);
+                let (reserve0, reserve1) = (U256::from(reserve0), U256::from(reserve1));
+                let state = PoolState::UniswapV2 { reserve0, reserve1 };
+
+                let amount = U256::from(amount);
+                prop_assert_eq!(
+                    pool.amount_out(&state, &token0, amount).unwrap(),
+                    get_amount_out(amount, reserve0, reserve1, 3000)
+                );
+                prop_assert_eq!(
+                    pool.amount_out(&state, &token1, amount).unwrap(),
+                    get_amount_out(amount, reserve1, reserve0, 3000)
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn quote_with_token0_above_token1() {
+        let token0 = token([0xff; 20]);
+        let token1 = token([0x01; 20]);
+        let pool = Pool::new(Address::ZERO, token0.clone(), token1.clone(), PoolVariant::UniswapV2, 3000, 1);
+
+        // 1 token0 = 2 token1
+        let one = U256::from(10).pow(U256::from(18));
+        let state = PoolState::UniswapV2 {
+            reserve0: U256::from(1000) * one,
+            reserve1: U256::from(2000) * one,
+        };
+
+        assert_eq!(pool.spot_price(&state, &token0).unwrap(), U256::from(2) * one);
+        assert_eq!(pool.spot_price(&state, &token1).unwrap(), one / U256::from(2));
+    }
+
+    #[test]
+    fn v2_fee_per_chain() {
+        assert_eq!(get_v2_pool_fee(1), 3000);
+        assert_eq!(get_v2_pool_fee(56), 2500);
+        assert_eq!(get_v2_pool_fee(8453), 3000);
+        assert_eq!(get_v2_pool_fee(42161), 3000);
     }
 }
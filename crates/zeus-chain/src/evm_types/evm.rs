@@ -0,0 +1,105 @@
+use alloy::rpc::types::eth::Block;
+use revm::{
+    primitives::{BlockEnv, U256},
+    Database, Evm,
+};
+
+use super::spec::CHAIN_SPECS;
+
+/// Create a permissive [Evm] that executes on top of `block` with the rules of `chain_id`
+///
+/// The base fee, block gas limit and balance checks are off, so any caller can run any call.
+/// Only use it when the output of a call matters, its gas cost and whether the caller could pay for it
+/// are meaningless. See [new_evm_strict] for quoting
+pub fn new_evm<DB: Database>(db: DB, block: &Block, chain_id: u64) -> Result<Evm<'static, (), DB>, anyhow::Error> {
+    build_evm(db, block, chain_id, false)
+}
+
+/// Create an [Evm] that executes on top of `block` with the rules of `chain_id` and every check on
+///
+/// A transaction has to pay the block's base fee, fit in its gas limit and be affordable by the caller,
+/// like on-chain. Used by [crate::evm_types::simulation::simulate_v2_swap], so the gas of a quote is accurate
+pub fn new_evm_strict<DB: Database>(db: DB, block: &Block, chain_id: u64) -> Result<Evm<'static, (), DB>, anyhow::Error> {
+    build_evm(db, block, chain_id, true)
+}
+
+/// The block env (number, timestamp, coinbase, base fee, prevrandao and blob gas) is copied from `block`,
+/// so block-dependent opcodes return what they would on-chain
+fn build_evm<DB: Database>(db: DB, block: &Block, chain_id: u64, strict: bool) -> Result<Evm<'static, (), DB>, anyhow::Error> {
+    let spec_id = CHAIN_SPECS.read().unwrap().spec_id(chain_id)?;
+    let block_env = block_env(block)?;
+
+    let evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(spec_id)
+        .modify_cfg_env(|cfg| {
+            cfg.chain_id = chain_id;
+            cfg.disable_base_fee = !strict;
+            cfg.disable_block_gas_limit = !strict;
+            cfg.disable_balance_check = !strict;
+        })
+        .modify_block_env(|env| *env = block_env)
+        .build();
+
+    Ok(evm)
+}
+
+/// The revm [BlockEnv] of an rpc [Block]
+pub fn block_env(block: &Block) -> Result<BlockEnv, anyhow::Error> {
+    let header = &block.header;
+    let number = header.number.ok_or_else(|| anyhow::anyhow!("Block number is missing"))?;
+
+    let mut env = BlockEnv {
+        number: U256::from(number),
+        coinbase: header.miner,
+        timestamp: U256::from(header.timestamp),
+        gas_limit: U256::from(header.gas_limit),
+        basefee: U256::from(header.base_fee_per_gas.unwrap_or_default()),
+        difficulty: header.difficulty,
+        prevrandao: header.mix_hash,
+        blob_excess_gas_and_price: None,
+    };
+
+    // only set after Cancun, leaving it empty disables the blob opcodes
+    if let Some(excess_blob_gas) = header.excess_blob_gas {
+        env.set_blob_excess_gas_and_price(excess_blob_gas as u64);
+    }
+
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use revm::{db::EmptyDB, primitives::TransactTo};
+
+    fn block() -> Block {
+        let mut block = Block::default();
+        block.header.number = Some(1);
+        block.header.gas_limit = 30_000_000;
+        block.header.base_fee_per_gas = Some(10);
+        block.header.mix_hash = Some(Default::default());
+        block.header.excess_blob_gas = Some(0);
+        block
+    }
+
+    fn transact(evm: &mut Evm<'static, (), EmptyDB>) -> bool {
+        let tx = evm.tx_mut();
+        tx.caller = Address::repeat_byte(1);
+        tx.transact_to = TransactTo::Call(Address::repeat_byte(2));
+        tx.gas_limit = 100_000;
+        tx.gas_price = U256::from(1);
+        evm.transact().is_ok()
+    }
+
+    #[test]
+    fn only_the_permissive_evm_skips_the_fee_checks() {
+        // the caller has no balance and pays less than the base fee
+        let mut evm = new_evm(EmptyDB::default(), &block(), 1).unwrap();
+        assert!(transact(&mut evm));
+
+        let mut evm = new_evm_strict(EmptyDB::default(), &block(), 1).unwrap();
+        assert!(!transact(&mut evm));
+    }
+}
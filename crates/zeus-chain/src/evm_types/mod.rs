@@ -1,2 +1,4 @@
 pub mod forked_db;
-pub mod dummy_account;
\ No newline at end of file
+pub mod simulation;
+pub mod spec;
+pub mod evm;
@@ -0,0 +1,142 @@
+use alloy::signers::local::LocalSigner;
+use alloy::primitives::{Address, U256};
+use revm::primitives::{Bytecode, AccountInfo, KECCAK_EMPTY};
+use std::str::FromStr;
+
+use super::balance_slot::known_balance_slot;
+use crate::evm_types::forked_db::fork_factory::ForkFactory;
+use crate::chain_id::{ChainFeature, ChainUnsupported};
+
+#[derive(Clone)]
+pub enum AccountType {
+    /// Externally Owned Account
+    EOA,
+
+    /// An Ethereum Smart Contract
+    Contract(Bytecode),
+}
+
+/// Represents a dummy account we want to insert into the fork enviroment
+///
+/// Build one with [DummyAccount::eoa] or [DummyAccount::contract] and the `with_*` methods,
+/// then insert it with [insert_dummy_account]
+#[derive(Clone)]
+pub struct DummyAccount {
+    pub account_type: AccountType,
+
+    /// ETH balance to fund with
+    pub balance: U256,
+
+    /// WETH balance to fund with
+    pub weth_balance: U256,
+
+    pub address: Address,
+
+    /// Storage slots of the account itself, written as `(slot, value)`
+    pub storage: Vec<(U256, U256)>,
+}
+
+impl DummyAccount {
+    pub fn new(account_type: AccountType, balance: U256, weth_balance: U256) -> Self {
+        Self {
+            account_type,
+            balance,
+            weth_balance,
+            address: LocalSigner::random().address(),
+            storage: Vec::new(),
+        }
+    }
+
+    /// An EOA at a random address with no balance
+    pub fn eoa() -> Self {
+        Self::new(AccountType::EOA, U256::ZERO, U256::ZERO)
+    }
+
+    /// A contract running `code` at a random address with no balance
+    pub fn contract(code: Bytecode) -> Self {
+        Self::new(AccountType::Contract(code), U256::ZERO, U256::ZERO)
+    }
+
+    /// Use `address` instead of a random one, eg. to impersonate a real wallet
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    pub fn with_balance(mut self, balance: U256) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    pub fn with_weth_balance(mut self, weth_balance: U256) -> Self {
+        self.weth_balance = weth_balance;
+        self
+    }
+
+    /// Set a storage slot of the account, only useful for contracts
+    pub fn with_storage(mut self, slot: U256, value: U256) -> Self {
+        self.storage.push((slot, value));
+        self
+    }
+
+    /// The revm [AccountInfo] of this account
+    pub fn account_info(&self) -> AccountInfo {
+        let (code, code_hash) = match &self.account_type {
+            AccountType::EOA => (Bytecode::default(), KECCAK_EMPTY),
+            AccountType::Contract(code) => (code.clone(), code.hash_slow()),
+        };
+
+        AccountInfo {
+            balance: self.balance,
+            nonce: 0,
+            code_hash,
+            code: Some(code),
+        }
+    }
+}
+
+
+
+/// Inserts a dummy account to the local fork enviroment
+///
+/// Its WETH balance is written to the wrapped native token of `chain_id`
+pub fn insert_dummy_account(
+    account: &DummyAccount,
+    chain_id: u64,
+    fork_factory: &mut ForkFactory
+) -> Result<(), anyhow::Error> {
+
+    // insert the account info into the fork enviroment
+    fork_factory.insert_account_info(account.address, account.account_info());
+
+    for (slot, value) in &account.storage {
+        if let Err(e) = fork_factory.insert_account_storage(account.address, *slot, *value) {
+            return Err(anyhow::anyhow!("Failed to insert account storage: {}", e));
+        }
+    }
+
+    if account.weth_balance.is_zero() {
+        return Ok(());
+    }
+
+    let native_coin = get_native_coin(chain_id)?;
+    let balance_slot = known_balance_slot(chain_id, native_coin)
+        .ok_or_else(|| anyhow::anyhow!("Unknown balance slot for {}", native_coin))?;
+
+    // insert the erc20 token balance to the dummy account
+    if let Err(e) = fork_factory.insert_account_storage(native_coin, balance_slot.key(account.address), account.weth_balance) {
+        return Err(anyhow::anyhow!("Failed to insert account storage: {}", e));
+    }
+
+    Ok(())
+}
+
+pub fn get_native_coin(chain_id: u64) -> Result<Address, anyhow::Error>{
+    match chain_id {
+        1 => Ok(Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap()),
+        56 => Ok(Address::from_str("0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c").unwrap()),
+        8453 => Ok(Address::from_str("0x4200000000000000000000000000000000000006").unwrap()),
+        42161 => Ok(Address::from_str("0x82aF49447D8a07e3bd95BD0d56f35241523fBab1").unwrap()),
+        _ => Err(ChainUnsupported::new(chain_id, ChainFeature::WrappedNative).into())
+    }
+}
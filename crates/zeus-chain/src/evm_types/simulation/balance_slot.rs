@@ -0,0 +1,93 @@
+use alloy::primitives::{address, Address, U256};
+
+use crate::evm_types::forked_db::keccak256;
+
+/// Where an ERC20 keeps its `balanceOf` mapping
+///
+/// An amazing online tool to see the storage mapping of any contract https://evm.storage/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceSlot {
+    /// Solidity mapping at this slot, the key is `keccak256(owner . slot)`
+    Solidity(U256),
+
+    /// Vyper mapping at this slot, the key is `keccak256(slot . owner)`
+    Vyper(U256),
+}
+
+impl BalanceSlot {
+    /// The storage key that holds the balance of `owner`
+    pub fn key(&self, owner: Address) -> U256 {
+        let owner = pad_left(owner.to_vec(), 32);
+
+        let data = match self {
+            BalanceSlot::Solidity(slot) => [owner, slot.to_be_bytes_vec()].concat(),
+            BalanceSlot::Vyper(slot) => [slot.to_be_bytes_vec(), owner].concat(),
+        };
+
+        U256::from_be_bytes(keccak256(&data))
+    }
+}
+
+/// Tokens whose storage layout we already know as `(chain_id, token, slot)`
+const KNOWN_BALANCE_SLOTS: [(u64, Address, u64); 7] = [
+    // WETH
+    (1, address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"), 3),
+    // USDC
+    (1, address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), 9),
+    // USDT
+    (1, address!("dAC17F958D2ee523a2206206994597C13D831ec7"), 2),
+    // DAI
+    (1, address!("6B175474E89094C44Da98b954EedeAC495271d0F"), 2),
+    // WBNB
+    (56, address!("bb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"), 3),
+    // WETH
+    (8453, address!("4200000000000000000000000000000000000006"), 3),
+    // WETH, an upgradeable token so the mapping comes after the 50 slots gap
+    (42161, address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1"), 51),
+];
+
+/// The balance slot of `token` if its storage layout is known
+pub fn known_balance_slot(chain_id: u64, token: Address) -> Option<BalanceSlot> {
+    KNOWN_BALANCE_SLOTS
+        .iter()
+        .find(|(id, address, _)| *id == chain_id && *address == token)
+        .map(|(_, _, slot)| BalanceSlot::Solidity(U256::from(*slot)))
+}
+
+fn pad_left(vec: Vec<u8>, full_len: usize) -> Vec<u8> {
+    let mut padded = vec![0u8; full_len - vec.len()];
+    padded.extend(vec);
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_tokens_have_their_slot() {
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        assert_eq!(known_balance_slot(1, weth), Some(BalanceSlot::Solidity(U256::from(3))));
+
+        let arbitrum_weth = address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1");
+        assert_eq!(known_balance_slot(42161, arbitrum_weth), Some(BalanceSlot::Solidity(U256::from(51))));
+
+        // the slot is per chain, the same address elsewhere is another contract
+        assert_eq!(known_balance_slot(56, weth), None);
+        assert_eq!(known_balance_slot(1, Address::repeat_byte(1)), None);
+    }
+
+    #[test]
+    fn solidity_and_vyper_hash_the_owner_and_slot_in_opposite_order() {
+        let owner = Address::repeat_byte(0xaa);
+        let slot = U256::from(7);
+        let padded_owner = pad_left(owner.to_vec(), 32);
+
+        let solidity = keccak256(&[padded_owner.clone(), slot.to_be_bytes_vec()].concat());
+        let vyper = keccak256(&[slot.to_be_bytes_vec(), padded_owner].concat());
+
+        assert_eq!(BalanceSlot::Solidity(slot).key(owner), U256::from_be_bytes(solidity));
+        assert_eq!(BalanceSlot::Vyper(slot).key(owner), U256::from_be_bytes(vyper));
+        assert_ne!(BalanceSlot::Solidity(slot).key(owner), BalanceSlot::Vyper(slot).key(owner));
+    }
+}
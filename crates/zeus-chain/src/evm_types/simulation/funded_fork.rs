@@ -0,0 +1,220 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    primitives::{Address, U256},
+    rpc::types::eth::BlockId,
+    sol_types::SolCall,
+};
+use revm::{
+    db::{CacheDB, EmptyDB},
+    primitives::{ExecutionResult, Output, TransactTo},
+    Database, Evm,
+};
+
+use super::{
+    account::{insert_dummy_account, DummyAccount},
+    balance_slot::{known_balance_slot, BalanceSlot},
+};
+use crate::{
+    defi_types::currency::erc20::ERC20,
+    evm_types::{
+        forked_db::{fork_db::ForkDB, fork_factory::ForkFactory},
+        spec::CHAIN_SPECS,
+    },
+    WsClient,
+};
+
+/// How many mapping slots we try when looking for the balance slot of an unknown token
+pub const MAX_PROBED_SLOTS: u64 = 100;
+
+/// A fork of the chain with a caller that already holds the tokens a simulation needs
+///
+/// ```ignore
+/// let mut fork = FundedFork::new(client, Some(block_id), chain_id, parse_ether("10")?)?;
+/// fork.fund_erc20(usdc, U256::from(1_000_000_000))?;
+/// let db = fork.fork_db();
+/// ```
+pub struct FundedFork {
+    pub factory: ForkFactory,
+
+    /// The account holding the funds, use its address as the tx caller
+    pub caller: DummyAccount,
+
+    chain_id: u64,
+
+    /// Balance slots we found so far, by token
+    slots: HashMap<Address, BalanceSlot>,
+}
+
+impl FundedFork {
+    /// Fork the chain at `block` with a random EOA holding `native_balance`
+    pub fn new(
+        client: Arc<WsClient>,
+        block: Option<BlockId>,
+        chain_id: u64,
+        native_balance: U256,
+    ) -> Result<Self, anyhow::Error> {
+        let factory = ForkFactory::new_sandbox_factory(client, CacheDB::new(EmptyDB::default()), block);
+        let caller = DummyAccount::eoa().with_balance(native_balance);
+
+        let mut fork = Self {
+            factory,
+            caller,
+            chain_id,
+            slots: HashMap::new(),
+        };
+        fork.insert_account(&fork.caller.clone())?;
+
+        Ok(fork)
+    }
+
+    /// Insert another account, eg. a contract the caller interacts with
+    pub fn insert_account(&mut self, account: &DummyAccount) -> Result<(), anyhow::Error> {
+        insert_dummy_account(account, self.chain_id, &mut self.factory)
+    }
+
+    /// Give the caller `amount` of `token`
+    ///
+    /// The token's `balanceOf` slot is looked up in [known_balance_slot] or probed on the fork
+    pub fn fund_erc20(&mut self, token: Address, amount: U256) -> Result<(), anyhow::Error> {
+        let slot = self.balance_slot(token)?;
+        let key = slot.key(self.caller.address);
+
+        if let Err(e) = self.factory.insert_account_storage(token, key, amount) {
+            return Err(anyhow::anyhow!("Failed to insert account storage: {}", e));
+        }
+
+        Ok(())
+    }
+
+    /// A new [ForkDB] with all the accounts and balances inserted so far
+    pub fn fork_db(&self) -> ForkDB {
+        self.factory.new_sandbox_fork()
+    }
+
+    /// Find the slot of the `balanceOf` mapping of `token`
+    ///
+    /// We write a marker value to each candidate slot and keep the one `balanceOf` returns
+    pub fn balance_slot(&mut self, token: Address) -> Result<BalanceSlot, anyhow::Error> {
+        if let Some(slot) = self.slots.get(&token) {
+            return Ok(*slot);
+        }
+
+        let known = known_balance_slot(self.chain_id, token);
+        let candidates = known.into_iter().chain((0..MAX_PROBED_SLOTS).flat_map(|slot| {
+            let slot = U256::from(slot);
+            [BalanceSlot::Solidity(slot), BalanceSlot::Vyper(slot)]
+        }));
+
+        let mut db = self.fork_db();
+        // load the token so writing to its storage keeps its code
+        db.basic(token).map_err(|e| anyhow::anyhow!("Failed to load {}: {}", token, e))?;
+
+        let owner = self.caller.address;
+        let marker = U256::from(0x5eed_u64);
+
+        for slot in candidates {
+            db.db.insert_account_storage(token, slot.key(owner), marker)?;
+
+            if balance_of(&mut db, self.chain_id, token, owner)? == marker {
+                self.slots.insert(token, slot);
+                return Ok(slot);
+            }
+        }
+
+        Err(anyhow::anyhow!("Could not find the balance slot of {}", token))
+    }
+}
+
+/// Call `balanceOf(owner)` on `token` without committing anything
+///
+/// Runs on the default block env with a zero gas price, only the returned balance matters
+fn balance_of(db: &mut ForkDB, chain_id: u64, token: Address, owner: Address) -> Result<U256, anyhow::Error> {
+    let spec_id = CHAIN_SPECS.read().unwrap().spec_id(chain_id)?;
+    let call = ERC20::balanceOfCall { owner };
+
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(spec_id)
+        .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+        .modify_tx_env(|tx| {
+            tx.caller = owner;
+            tx.transact_to = TransactTo::Call(token);
+            tx.data = call.abi_encode().into();
+            tx.value = U256::ZERO;
+        })
+        .build();
+
+    let result = evm.transact().map_err(|e| anyhow::anyhow!("balanceOf failed: {}", e))?.result;
+
+    match result {
+        ExecutionResult::Success { output: Output::Call(bytes), .. } => {
+            let balance = ERC20::balanceOfCall::abi_decode_returns(&bytes, true)?;
+            Ok(balance.balance)
+        }
+        // a token that reverts on an unexpected balance is not funded by this slot
+        _ => Ok(U256::ZERO),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evm_types::simulation::account::DummyAccount, test_node::TestNode};
+    use alloy::rpc::types::eth::BlockNumberOrTag;
+    use revm::primitives::Bytecode;
+
+    const SLOT: u8 = 7;
+
+    /// `balanceOf(owner)` reading a mapping at [SLOT], the key is `keccak256(first . second)`
+    fn balance_of_code(owner_first: bool) -> Bytecode {
+        // calldataload(4) is the owner, left padded like in the key
+        let owner_at = |offset: u8| vec![0x60, 0x04, 0x35, 0x60, offset, 0x52];
+        let slot_at = |offset: u8| vec![0x60, SLOT, 0x60, offset, 0x52];
+        let (first, second) = if owner_first {
+            (owner_at(0x00), slot_at(0x20))
+        } else {
+            (slot_at(0x00), owner_at(0x20))
+        };
+        // mstore(0, sload(keccak256(0, 64))), return(0, 32)
+        let load_and_return = [0x60, 0x40, 0x60, 0x00, 0x20, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+
+        Bytecode::new_raw([first, second, load_and_return.to_vec()].concat().into())
+    }
+
+    async fn fork_with_token(code: Bytecode) -> (FundedFork, Address) {
+        let node = TestNode::empty_chain();
+        let block = BlockId::Number(BlockNumberOrTag::Number(1));
+        let mut fork = FundedFork::new(Arc::new(node.client().await), Some(block), 1, U256::ZERO).unwrap();
+
+        let token = DummyAccount::contract(code).with_address(Address::repeat_byte(0x70));
+        fork.insert_account(&token).unwrap();
+        (fork, token.address)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn the_slot_of_a_solidity_and_a_vyper_token_is_probed() {
+        for (owner_first, expected) in [
+            (true, BalanceSlot::Solidity(U256::from(SLOT))),
+            (false, BalanceSlot::Vyper(U256::from(SLOT))),
+        ] {
+            let (mut fork, token) = fork_with_token(balance_of_code(owner_first)).await;
+            assert_eq!(fork.balance_slot(token).unwrap(), expected);
+
+            let amount = U256::from(1_000_000);
+            fork.fund_erc20(token, amount).unwrap();
+            let owner = fork.caller.address;
+            assert_eq!(balance_of(&mut fork.fork_db(), 1, token, owner).unwrap(), amount);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn funding_fails_if_no_slot_holds_the_balance() {
+        // always returns a zero balance, like a token that computes it
+        let zero_balance = Bytecode::new_raw(vec![0x60, 0x20, 0x60, 0x00, 0xf3].into());
+        let (mut fork, token) = fork_with_token(zero_balance).await;
+
+        // the quote falls back to `getAmountOut` on this error
+        assert!(fork.fund_erc20(token, U256::from(1)).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+//! Helpers to set up the state a simulation runs against
+//!
+//! - [DummyAccount] describes an EOA or a contract to insert into a fork, with its balance and storage
+//! - [insert_dummy_account] writes one into a [ForkFactory](super::forked_db::fork_factory::ForkFactory)
+//! - [FundedFork] forks the chain with a caller that already holds native and ERC20 balances
+//! - [simulate_v2_swap] executes a swap on such a fork, so the quote includes any transfer tax
+//!
+//! ERC20 balances are written straight to the token's `balanceOf` mapping, see [BalanceSlot]
+
+pub mod account;
+pub mod balance_slot;
+pub mod funded_fork;
+pub mod swap;
+
+pub use account::{get_native_coin, insert_dummy_account, AccountType, DummyAccount};
+pub use balance_slot::{known_balance_slot, BalanceSlot};
+pub use funded_fork::{FundedFork, MAX_PROBED_SLOTS};
+pub use swap::{simulate_v2_swap, SwapSimulation};
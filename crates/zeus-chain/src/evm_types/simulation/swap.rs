@@ -0,0 +1,126 @@
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    rpc::types::eth::Block,
+    sol_types::SolCall,
+};
+use revm::primitives::{ExecutionResult, Output, TransactTo};
+
+use crate::{
+    defi_types::{
+        currency::erc20::ERC20Token,
+        pool::{get_amount_out, Pool, PoolVariant, UniswapV2Pair},
+    },
+    evm_types::{evm::new_evm_strict, forked_db::fork_db::ForkDB},
+};
+
+/// Gas limit of every call we simulate, the caller must be able to pay for it at the block's base fee
+const CALL_GAS_LIMIT: u64 = 1_000_000;
+
+/// The outcome of a swap executed on a fork
+#[derive(Debug, Clone)]
+pub struct SwapSimulation {
+    /// What the caller actually received
+    pub amount_out: U256,
+
+    /// What the caller would receive from `getAmountOut` if neither token took a transfer tax
+    pub expected_out: U256,
+
+    pub gas_used: u64,
+}
+
+impl SwapSimulation {
+    /// The share (0.0 - 1.0) of the output lost to transfer taxes
+    pub fn tax(&self) -> f64 {
+        if self.expected_out.is_zero() || self.amount_out >= self.expected_out {
+            return 0.0;
+        }
+
+        let lost = self.expected_out - self.amount_out;
+        f64::from(lost) / f64::from(self.expected_out)
+    }
+}
+
+/// Swap `amount_in` of `token_in` on a V2 `pool` like the pair is meant to be used:
+/// transfer the input to the pair, then call `swap` for the output
+///
+/// `caller` must already hold `amount_in` of `token_in` and enough native balance for the gas,
+/// see [super::FundedFork]
+pub fn simulate_v2_swap(
+    db: ForkDB,
+    block: &Block,
+    chain_id: u64,
+    pool: &Pool,
+    caller: Address,
+    token_in: &ERC20Token,
+    amount_in: U256,
+) -> Result<SwapSimulation, anyhow::Error> {
+    if !matches!(pool.variant, PoolVariant::UniswapV2) {
+        return Err(anyhow::anyhow!("Only V2 pools can be simulated"));
+    }
+
+    let token_out = pool.token_out(token_in).clone();
+    let zero_for_one = token_in.address == pool.token0.address;
+
+    // strict, the gas of the swap ends up in the quote
+    let mut evm = new_evm_strict(db, block, chain_id)?;
+    let gas_price = evm.block().basefee;
+
+    let mut call = |to: Address, data: Vec<u8>, commit: bool| -> Result<(Bytes, u64), anyhow::Error> {
+        let tx = evm.tx_mut();
+        tx.caller = caller;
+        tx.transact_to = TransactTo::Call(to);
+        tx.data = data.into();
+        tx.value = U256::ZERO;
+        tx.gas_limit = CALL_GAS_LIMIT;
+        tx.gas_price = gas_price;
+
+        let result = if commit {
+            evm.transact_commit()
+        } else {
+            evm.transact().map(|res| res.result)
+        };
+
+        match result.map_err(|e| anyhow::anyhow!("Simulation failed: {}", e))? {
+            ExecutionResult::Success { output: Output::Call(bytes), gas_used, .. } => Ok((bytes, gas_used)),
+            ExecutionResult::Success { .. } => Err(anyhow::anyhow!("Unexpected create output")),
+            ExecutionResult::Revert { output, .. } => Err(anyhow::anyhow!("Reverted: {}", output)),
+            ExecutionResult::Halt { reason, .. } => Err(anyhow::anyhow!("Halted: {:?}", reason)),
+        }
+    };
+
+    let (reserves, _) = call(pool.address, UniswapV2Pair::getReservesCall {}.abi_encode(), false)?;
+    let reserves = UniswapV2Pair::getReservesCall::abi_decode_returns(&reserves, true)?;
+    let (reserve_in, reserve_out) = if zero_for_one {
+        (U256::from(reserves.reserve0), U256::from(reserves.reserve1))
+    } else {
+        (U256::from(reserves.reserve1), U256::from(reserves.reserve0))
+    };
+
+    let (_, transfer_gas) = call(token_in.address, token_in.encode_transfer(pool.address, amount_in), true)?;
+
+    // a token with a transfer tax delivers less than `amount_in` to the pair
+    let (pair_balance, _) = call(token_in.address, token_in.encode_balance_of(pool.address), false)?;
+    let received = token_in.decode_balance_of(&pair_balance)?.saturating_sub(reserve_in);
+    let pair_out = get_amount_out(received, reserve_in, reserve_out, pool.fee);
+
+    let (balance_before, _) = call(token_out.address, token_out.encode_balance_of(caller), false)?;
+    let balance_before = token_out.decode_balance_of(&balance_before)?;
+
+    let (amount0_out, amount1_out) = if zero_for_one { (U256::ZERO, pair_out) } else { (pair_out, U256::ZERO) };
+    let swap = UniswapV2Pair::swapCall {
+        amount0Out: amount0_out,
+        amount1Out: amount1_out,
+        to: caller,
+        data: Bytes::new(),
+    };
+    let (_, swap_gas) = call(pool.address, swap.abi_encode(), true)?;
+
+    let (balance_after, _) = call(token_out.address, token_out.encode_balance_of(caller), false)?;
+    let balance_after = token_out.decode_balance_of(&balance_after)?;
+
+    Ok(SwapSimulation {
+        amount_out: balance_after.saturating_sub(balance_before),
+        expected_out: get_amount_out(amount_in, reserve_in, reserve_out, pool.fee),
+        gas_used: transfer_gas + swap_gas,
+    })
+}
@@ -0,0 +1,49 @@
+use std::{collections::HashMap, sync::RwLock};
+use lazy_static::lazy_static;
+use revm::primitives::SpecId;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// The [SpecId] used to simulate transactions on each chain, see [ChainSpecs]
+    pub static ref CHAIN_SPECS: RwLock<ChainSpecs> = RwLock::new(ChainSpecs::default());
+}
+
+/// The hardfork the EVM follows on each supported chain
+///
+/// Keep this in sync with the chain upgrades, a wrong spec makes simulations diverge from what happens on-chain
+pub fn default_spec_id(chain_id: u64) -> Option<SpecId> {
+    match chain_id {
+        // Dencun
+        1 => Some(SpecId::CANCUN),
+        // Tycho
+        56 => Some(SpecId::CANCUN),
+        // Ecotone
+        8453 => Some(SpecId::CANCUN),
+        // ArbOS 20 (Atlas)
+        42161 => Some(SpecId::CANCUN),
+        // Sepolia and Base Sepolia follow their mainnets
+        11155111 => Some(SpecId::CANCUN),
+        84532 => Some(SpecId::CANCUN),
+        _ => None,
+    }
+}
+
+/// Per-chain [SpecId] with user overrides loaded from `chains.json`
+///
+/// `chains.json` looks like `{ "overrides": { "1": "CANCUN", "10": "SHANGHAI" } }`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainSpecs {
+    #[serde(default)]
+    pub overrides: HashMap<u64, SpecId>,
+}
+
+impl ChainSpecs {
+    /// The [SpecId] of a chain, the override wins over [default_spec_id]
+    pub fn spec_id(&self, chain_id: u64) -> Result<SpecId, anyhow::Error> {
+        self.overrides
+            .get(&chain_id)
+            .copied()
+            .or_else(|| default_spec_id(chain_id))
+            .ok_or_else(|| anyhow::anyhow!("No EVM spec for Chain ID {}, add one to chains.json", chain_id))
+    }
+}
@@ -4,6 +4,10 @@ pub mod utils;
 pub mod chain_id;
 pub mod rpc;
 pub mod tx;
+pub mod raw_tx;
+
+#[cfg(any(test, feature = "test-node"))]
+pub mod test_node;
 
 
 // * Re-exports
@@ -12,10 +16,12 @@ pub use alloy;
 pub use revm;
 pub use serde_json;
 
-pub use chain_id::ChainId;
+pub use chain_id::{ChainId, ChainFeature, ChainUnsupported};
+pub use tx::{gas_limit_for, DEFAULT_GAS_MULTIPLIER, MIN_TRANSFER_GAS};
 pub use rpc::Rpc;
-pub use utils::{get_client, parse_wei, format_wei, oracles::{OracleAction, block::{BlockInfo, BlockOracle, BLOCK_ORACLE, start_block_oracle}}};
-pub use defi_types::{currency::{Currency, NativeCurrency, erc20::ERC20Token}, pool::*};
+pub use utils::{get_client, parse_units, format_units, format_units_with_precision, format_compact, format_percent_change, TokenMetadataSource, SwapRouter, explorer_address_url, CALLDATA_DECODER, RpcBudget, RpcOrigin, RPC_USAGE, oracles::{OracleAction, block::{BlockInfo, BlockOracle, BLOCK_ORACLE, ETH_USD_FEED_DECIMALS, block_time, lag_threshold, stall_threshold, start_block_oracle}}};
+pub use defi_types::{currency::{Currency, NativeCurrency, PriceError, MAX_PRICE_DECIMALS, erc20::ERC20Token}, pool::*};
+pub use evm_types::{evm::{new_evm, new_evm_strict}, spec::{ChainSpecs, CHAIN_SPECS}, simulation::{DummyAccount, AccountType, FundedFork}};
 
 
 
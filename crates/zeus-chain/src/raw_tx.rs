@@ -0,0 +1,445 @@
+use std::{fmt, str::FromStr};
+
+use alloy::{
+    consensus::{Transaction, TxEnvelope},
+    eips::eip2718::{Decodable2718, Encodable2718},
+    primitives::{Address, Bytes, TxKind, B256, U256},
+};
+use anyhow::anyhow;
+use serde_json::{Map, Value};
+
+/// The fields we accept in a raw transaction json, anything else is rejected
+const KNOWN_FIELDS: [&str; 12] = [
+    "type",
+    "chainId",
+    "from",
+    "to",
+    "value",
+    "data",
+    "input",
+    "gas",
+    "gasLimit",
+    "nonce",
+    "maxFeePerGas",
+    "maxPriorityFeePerGas",
+];
+
+/// An unsigned EIP-1559 transaction produced by another tool
+///
+/// Missing gas, fee and nonce fields are filled from the node before signing
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawTx {
+    pub chain_id: u64,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub gas_limit: Option<u64>,
+    pub nonce: Option<u64>,
+    pub max_fee_per_gas: Option<u128>,
+    pub max_priority_fee_per_gas: Option<u128>,
+}
+
+/// Why a field of a raw transaction was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl RawTx {
+    /// Parse and validate a transaction json
+    ///
+    /// Numbers can be json numbers, decimal strings or `0x` hex strings.
+    /// `chainId` is required and must be `chain_id`, `from` is optional but must be `signer` if present
+    ///
+    /// Returns every invalid field at once so they can be shown next to each other
+    pub fn parse(json: &str, chain_id: u64, signer: Address) -> Result<Self, Vec<FieldError>> {
+        let value: Value = serde_json::from_str(json).map_err(|e| vec![FieldError::new("json", e.to_string())])?;
+        let Value::Object(fields) = value else {
+            return Err(vec![FieldError::new("json", "Expected an object")]);
+        };
+
+        let mut errors = Vec::new();
+
+        for key in fields.keys() {
+            if !KNOWN_FIELDS.contains(&key.as_str()) {
+                errors.push(FieldError::new(key, "Unknown field"));
+            }
+        }
+
+        if let Some(tx_type) = field(&fields, "type", &mut errors, parse_u256) {
+            if tx_type != U256::from(2) {
+                errors.push(FieldError::new("type", "Only EIP-1559 (type 2) transactions are supported"));
+            }
+        }
+
+        match field(&fields, "chainId", &mut errors, parse_u256) {
+            Some(id) if id != U256::from(chain_id) => {
+                errors.push(FieldError::new("chainId", format!("Expected {} (the connected chain), got {}", chain_id, id)));
+            }
+            Some(_) => {}
+            None if !fields.contains_key("chainId") => errors.push(FieldError::new("chainId", "Missing")),
+            None => {}
+        }
+
+        if let Some(from) = field(&fields, "from", &mut errors, parse_address) {
+            if from != signer {
+                errors.push(FieldError::new("from", format!("Does not match the selected wallet {}", signer)));
+            }
+        }
+
+        let to = field(&fields, "to", &mut errors, parse_address);
+        if to.is_none() && !fields.contains_key("to") {
+            errors.push(FieldError::new("to", "Missing, contract deployments are not supported"));
+        }
+
+        if fields.contains_key("data") && fields.contains_key("input") {
+            errors.push(FieldError::new("input", "Use either data or input"));
+        }
+        let data_key = if fields.contains_key("input") { "input" } else { "data" };
+
+        if fields.contains_key("gas") && fields.contains_key("gasLimit") {
+            errors.push(FieldError::new("gasLimit", "Use either gas or gasLimit"));
+        }
+        let gas_key = if fields.contains_key("gasLimit") { "gasLimit" } else { "gas" };
+
+        let value = field(&fields, "value", &mut errors, parse_u256);
+        let data = field(&fields, data_key, &mut errors, parse_bytes);
+        let gas_limit = field(&fields, gas_key, &mut errors, parse_u256).and_then(|gas| fits(gas_key, gas, &mut errors));
+        let nonce = field(&fields, "nonce", &mut errors, parse_u256).and_then(|nonce| fits("nonce", nonce, &mut errors));
+        let max_fee = field(&fields, "maxFeePerGas", &mut errors, parse_u256)
+            .and_then(|fee| fits("maxFeePerGas", fee, &mut errors));
+        let priority_fee = field(&fields, "maxPriorityFeePerGas", &mut errors, parse_u256)
+            .and_then(|fee| fits("maxPriorityFeePerGas", fee, &mut errors));
+
+        if let (Some(max_fee), Some(priority_fee)) = (max_fee, priority_fee) {
+            if priority_fee > max_fee {
+                errors.push(FieldError::new("maxPriorityFeePerGas", "Higher than maxFeePerGas"));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Self {
+            chain_id,
+            to: to.unwrap_or_default(),
+            value: value.unwrap_or_default(),
+            data: data.unwrap_or_default(),
+            gas_limit,
+            nonce,
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority_fee,
+        })
+    }
+}
+
+/// A transaction signed by another tool, decoded from the hex that `eth_sendRawTransaction` takes
+#[derive(Debug, Clone)]
+pub struct SignedRawTx {
+    /// The hash the transaction will have once broadcasted
+    pub hash: B256,
+
+    /// The signer, recovered from the signature
+    pub from: Address,
+
+    /// `None` for a contract deployment
+    pub to: Option<Address>,
+    pub value: U256,
+    pub nonce: u64,
+
+    /// `None` for a legacy transaction signed without EIP-155, it is valid on every chain
+    pub chain_id: Option<u64>,
+    pub data: Bytes,
+    pub gas_limit: u128,
+
+    /// The encoded transaction, sent to the node as is
+    pub raw: Bytes,
+}
+
+impl SignedRawTx {
+    /// Decode a `0x` prefixed, EIP-2718 encoded signed transaction
+    ///
+    /// Blob transactions are rejected since they need their sidecar to be broadcasted
+    pub fn decode(hex: &str) -> Result<Self, anyhow::Error> {
+        let hex = hex.trim();
+        if !hex.starts_with("0x") {
+            return Err(anyhow!("Expected a 0x prefixed hex string"));
+        }
+
+        let raw = Bytes::from_str(hex).map_err(|_| anyhow!("Invalid hex"))?;
+        let envelope = TxEnvelope::decode_2718(&mut raw.as_ref()).map_err(|e| anyhow!("Invalid transaction: {}", e))?;
+        // typed transactions are decoded from a copy of the buffer, so compare the encodings to catch trailing bytes
+        if envelope.encoded_2718() != raw.as_ref() {
+            return Err(anyhow!("Invalid transaction: unexpected trailing bytes"));
+        }
+
+        let from = envelope
+            .recover_signer()
+            .map_err(|e| anyhow!("Invalid signature: {}", e))?;
+
+        let (chain_id, nonce, to, value, data, gas_limit) = match &envelope {
+            TxEnvelope::Legacy(tx) => fields(tx.tx()),
+            TxEnvelope::Eip2930(tx) => fields(tx.tx()),
+            TxEnvelope::Eip1559(tx) => fields(tx.tx()),
+            _ => return Err(anyhow!("Blob transactions are not supported")),
+        };
+
+        Ok(Self {
+            hash: *envelope.tx_hash(),
+            from,
+            to,
+            value,
+            nonce,
+            chain_id,
+            data,
+            gas_limit,
+            raw,
+        })
+    }
+
+    /// Make sure the transaction can only be executed on `chain_id`
+    pub fn check_chain(&self, chain_id: u64) -> Result<(), anyhow::Error> {
+        match self.chain_id {
+            Some(id) if id == chain_id => Ok(()),
+            Some(id) => Err(anyhow!("Signed for chain id {}, but we are connected to {}", id, chain_id)),
+            None => Err(anyhow!("Signed without a chain id, it could be replayed on any chain")),
+        }
+    }
+}
+
+/// chain id, nonce, to, value, data and gas limit of a transaction
+fn fields<T: Transaction>(tx: &T) -> (Option<u64>, u64, Option<Address>, U256, Bytes, u128) {
+    let to = match tx.to() {
+        TxKind::Call(to) => Some(to),
+        TxKind::Create => None,
+    };
+    (tx.chain_id(), tx.nonce(), to, tx.value(), Bytes::copy_from_slice(tx.input()), tx.gas_limit())
+}
+
+/// Parse an optional field, recording the error if it is present but invalid
+fn field<T>(
+    fields: &Map<String, Value>,
+    key: &str,
+    errors: &mut Vec<FieldError>,
+    parse: fn(&Value) -> Result<T, String>,
+) -> Option<T> {
+    match fields.get(key) {
+        None | Some(Value::Null) => None,
+        Some(value) => match parse(value) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                errors.push(FieldError::new(key, e));
+                None
+            }
+        },
+    }
+}
+
+/// Narrow a parsed number to the integer type of its field
+fn fits<T: TryFrom<U256>>(key: &str, value: U256, errors: &mut Vec<FieldError>) -> Option<T> {
+    match T::try_from(value) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            errors.push(FieldError::new(key, "Too large"));
+            None
+        }
+    }
+}
+
+fn parse_u256(value: &Value) -> Result<U256, String> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from).ok_or("Expected a positive integer".to_string()),
+        Value::String(s) => {
+            let s = s.trim();
+            let parsed = match s.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16),
+                None => U256::from_str_radix(s, 10),
+            };
+            parsed.map_err(|_| format!("Invalid number {}", s))
+        }
+        _ => Err("Expected a number or a string".to_string()),
+    }
+}
+
+fn parse_address(value: &Value) -> Result<Address, String> {
+    let s = value.as_str().ok_or("Expected a string")?;
+    Address::from_str(s.trim()).map_err(|_| format!("Invalid address {}", s))
+}
+
+fn parse_bytes(value: &Value) -> Result<Bytes, String> {
+    let s = value.as_str().ok_or("Expected a hex string")?;
+    Bytes::from_str(s.trim()).map_err(|_| "Invalid hex".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        consensus::{SignableTransaction, Signed, TxEip1559, TxEip4844, TxLegacy},
+        network::TxSignerSync,
+        primitives::Signature,
+        signers::local::PrivateKeySigner,
+    };
+
+    const TO: &str = "0x95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5";
+
+    fn errored_fields(result: Result<RawTx, Vec<FieldError>>) -> Vec<String> {
+        result.unwrap_err().into_iter().map(|e| e.field).collect()
+    }
+
+    /// Sign `tx` and encode it like `eth_sendRawTransaction` takes it
+    fn signed_hex<T>(mut tx: T, signer: &PrivateKeySigner) -> String
+    where
+        T: SignableTransaction<Signature>,
+        TxEnvelope: From<Signed<T>>,
+    {
+        let signature = signer.sign_transaction_sync(&mut tx).unwrap();
+        let envelope = TxEnvelope::from(tx.into_signed(signature));
+        Bytes::from(envelope.encoded_2718()).to_string()
+    }
+
+    fn eip1559(chain_id: u64) -> TxEip1559 {
+        TxEip1559 {
+            chain_id,
+            nonce: 4,
+            gas_limit: 21_000,
+            max_fee_per_gas: 20_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(Address::from_str(TO).unwrap()),
+            value: U256::from(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn numbers_can_be_json_numbers_decimal_or_hex() {
+        let signer = Address::repeat_byte(1);
+        let json = format!(
+            r#"{{"chainId": "0x1", "to": "{}", "value": "1000", "nonce": 7, "gas": "0x5208", "input": "0xa9059cbb"}}"#,
+            TO
+        );
+        let tx = RawTx::parse(&json, 1, signer).unwrap();
+
+        assert_eq!(tx.to, Address::from_str(TO).unwrap());
+        assert_eq!(tx.value, U256::from(1000));
+        assert_eq!(tx.nonce, Some(7));
+        assert_eq!(tx.gas_limit, Some(21_000));
+        assert_eq!(tx.data, Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb]));
+        // left for the node to fill
+        assert_eq!(tx.max_fee_per_gas, None);
+    }
+
+    #[test]
+    fn every_bad_field_is_reported_at_once() {
+        let json = format!(
+            r#"{{"chainId": 1, "type": 1, "to": "0x1234", "value": "-1", "data": "0xzz", "nonce": "0x1{}", "gasPrice": 1}}"#,
+            "0".repeat(16)
+        );
+        let mut fields = errored_fields(RawTx::parse(&json, 1, Address::ZERO));
+        fields.sort();
+        assert_eq!(fields, ["data", "gasPrice", "nonce", "to", "type", "value"]);
+
+        let json = format!(r#"{{"chainId": 1, "to": "{}", "maxFeePerGas": 1, "maxPriorityFeePerGas": 2}}"#, TO);
+        assert_eq!(errored_fields(RawTx::parse(&json, 1, Address::ZERO)), ["maxPriorityFeePerGas"]);
+
+        let json = format!(r#"{{"chainId": 1, "to": "{}", "gas": 1, "gasLimit": 1, "data": "0x", "input": "0x"}}"#, TO);
+        assert_eq!(errored_fields(RawTx::parse(&json, 1, Address::ZERO)), ["input", "gasLimit"]);
+    }
+
+    #[test]
+    fn the_chain_id_is_required_and_must_be_the_connected_chain() {
+        let json = format!(r#"{{"chainId": 56, "to": "{}"}}"#, TO);
+        let errors = RawTx::parse(&json, 1, Address::ZERO).unwrap_err();
+        assert_eq!(errors, [FieldError::new("chainId", "Expected 1 (the connected chain), got 56")]);
+
+        let json = format!(r#"{{"to": "{}"}}"#, TO);
+        assert_eq!(errored_fields(RawTx::parse(&json, 1, Address::ZERO)), ["chainId"]);
+    }
+
+    #[test]
+    fn from_must_be_the_signer() {
+        let signer = Address::repeat_byte(1);
+        let json = |from: Address| format!(r#"{{"chainId": 1, "to": "{}", "from": "{}"}}"#, TO, from);
+
+        assert!(RawTx::parse(&json(signer), 1, signer).is_ok());
+        assert_eq!(errored_fields(RawTx::parse(&json(Address::repeat_byte(2)), 1, signer)), ["from"]);
+    }
+
+    #[test]
+    fn only_a_json_object_with_a_recipient_is_a_transaction() {
+        assert_eq!(errored_fields(RawTx::parse("{", 1, Address::ZERO)), ["json"]);
+        assert_eq!(errored_fields(RawTx::parse("[]", 1, Address::ZERO)), ["json"]);
+        assert_eq!(errored_fields(RawTx::parse(r#"{"chainId": 1}"#, 1, Address::ZERO)), ["to"]);
+    }
+
+    #[test]
+    fn a_signed_tx_is_decoded_with_its_signer() {
+        let signer = PrivateKeySigner::random();
+        let tx = SignedRawTx::decode(&signed_hex(eip1559(1), &signer)).unwrap();
+
+        assert_eq!(tx.from, signer.address());
+        assert_eq!(tx.to, Some(Address::from_str(TO).unwrap()));
+        assert_eq!((tx.nonce, tx.value, tx.gas_limit), (4, U256::from(5), 21_000));
+        assert!(tx.check_chain(1).is_ok());
+        assert!(tx.check_chain(56).is_err());
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let hex = signed_hex(eip1559(1), &PrivateKeySigner::random());
+        let e = SignedRawTx::decode(&format!("{}00", hex)).unwrap_err();
+        assert!(e.to_string().contains("trailing bytes"), "{}", e);
+
+        assert!(SignedRawTx::decode(hex.trim_start_matches("0x")).is_err());
+        assert!(SignedRawTx::decode("0xzz").is_err());
+    }
+
+    #[test]
+    fn a_legacy_tx_without_eip155_can_be_replayed_so_it_is_rejected() {
+        let legacy = TxLegacy {
+            chain_id: None,
+            gas_price: 1_000_000_000,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::from_str(TO).unwrap()),
+            ..Default::default()
+        };
+        let tx = SignedRawTx::decode(&signed_hex(legacy.clone(), &PrivateKeySigner::random())).unwrap();
+        assert_eq!(tx.chain_id, None);
+        assert!(tx.check_chain(1).is_err());
+
+        let eip155 = TxLegacy { chain_id: Some(1), ..legacy };
+        let tx = SignedRawTx::decode(&signed_hex(eip155, &PrivateKeySigner::random())).unwrap();
+        assert!(tx.check_chain(1).is_ok());
+    }
+
+    #[test]
+    fn blob_txs_are_rejected() {
+        let blob = TxEip4844 {
+            chain_id: 1,
+            gas_limit: 21_000,
+            max_fee_per_gas: 20_000_000_000,
+            max_fee_per_blob_gas: 1,
+            blob_versioned_hashes: vec![B256::repeat_byte(1)],
+            ..Default::default()
+        };
+        let e = SignedRawTx::decode(&signed_hex(blob, &PrivateKeySigner::random())).unwrap_err();
+        assert_eq!(e.to_string(), "Blob transactions are not supported");
+    }
+}
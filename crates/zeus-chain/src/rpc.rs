@@ -21,6 +21,8 @@ impl Rpc {
             56 => "Binance Smart Chain".to_string(),
             8453 => "Base".to_string(),
             42161 => "Arbitrum".to_string(),
+            11155111 => "Sepolia".to_string(),
+            84532 => "Base Sepolia".to_string(),
             _ => "Unknown Chain ID".to_string(),
         }
     }
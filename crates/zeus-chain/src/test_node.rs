@@ -0,0 +1,87 @@
+//! A node for the tests, it answers every request from a function of the method and records what it was asked
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::value::RawValue;
+
+use crate::{
+    alloy::{
+        providers::RootProvider,
+        pubsub::{ConnectionHandle, PubSubConnect},
+        rpc::{
+            client::RpcClient,
+            json_rpc::{ErrorPayload, Id, PubSubItem, Response, ResponsePayload},
+        },
+        transports::TransportResult,
+    },
+    WsClient,
+};
+
+/// The JSON result of a method, or the message of the error the node returns
+type Answer = dyn Fn(&str) -> Result<String, String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct TestNode {
+    answer: Arc<Answer>,
+    methods: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestNode {
+    pub fn new(answer: impl Fn(&str) -> Result<String, String> + Send + Sync + 'static) -> Self {
+        Self {
+            answer: Arc::new(answer),
+            methods: Arc::default(),
+        }
+    }
+
+    /// A node of an empty chain, every account has no balance, no code and no storage
+    pub fn empty_chain() -> Self {
+        Self::new(|method| match method {
+            "eth_getBalance" | "eth_getTransactionCount" => Ok("\"0x0\"".to_string()),
+            "eth_getCode" => Ok("\"0x\"".to_string()),
+            "eth_getStorageAt" => Ok(format!("\"0x{}\"", "0".repeat(64))),
+            _ => Err(format!("{} is not expected in this test", method)),
+        })
+    }
+
+    /// The methods the node was asked for, in order
+    pub fn methods(&self) -> Vec<String> {
+        self.methods.lock().unwrap().clone()
+    }
+
+    pub async fn client(&self) -> WsClient {
+        RootProvider::new(RpcClient::new(self.clone().into_service().await.unwrap(), true))
+    }
+
+    fn respond(&self, request: &RawValue) -> Response {
+        let request: serde_json::Value = serde_json::from_str(request.get()).unwrap();
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        let id: Id = serde_json::from_value(request["id"].clone()).unwrap();
+
+        let payload = match (self.answer)(&method) {
+            Ok(result) => ResponsePayload::Success(RawValue::from_string(result).unwrap()),
+            Err(message) => ResponsePayload::Failure(ErrorPayload { code: -32000, message, data: None }),
+        };
+        self.methods.lock().unwrap().push(method);
+        Response { id, payload }
+    }
+}
+
+impl PubSubConnect for TestNode {
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    async fn connect(&self) -> TransportResult<ConnectionHandle> {
+        let (handle, mut interface) = ConnectionHandle::new();
+        let node = self.clone();
+        tokio::spawn(async move {
+            while let Some(request) = interface.recv_from_frontend().await {
+                let _ = interface.send_to_frontend(PubSubItem::Response(node.respond(&request)));
+            }
+            // dropping the interface would close the connection and start a reconnect loop
+            std::mem::forget(interface);
+        });
+        Ok(handle)
+    }
+}
@@ -1,10 +1,15 @@
-use crate::defi_types::currency::erc20::ERC20Token;
+use crate::defi_types::currency::{erc20::ERC20Token, Currency};
+use crate::utils::format_units;
+use crate::utils::arbitrum::{estimate_l1_gas, is_arbitrum};
+use crate::chain_id::ChainFeature;
 use std::str::FromStr;
 
 use crate::{
     alloy::{
+        consensus::TxEnvelope,
+        eips::eip2930::AccessList,
         network::{eip2718::Encodable2718, EthereumWallet, TransactionBuilder},
-        primitives::{Address, Bytes, U256},
+        primitives::{Address, Bytes, B256, U256},
         providers::{Provider, ProviderBuilder},
         rpc::types::{TransactionRequest, TransactionReceipt},
         signers::{
@@ -16,7 +21,100 @@ use crate::{
 };
 
 use tracing::trace;
-use anyhow::Context;
+use anyhow::{anyhow, Context};
+
+/// The estimated gas is multiplied by this to get the gas limit, see [gas_limit_for]
+pub const DEFAULT_GAS_MULTIPLIER: f64 = 1.2;
+
+/// The gas of a transfer of the native currency, a transaction without call data never gets a lower limit
+pub const MIN_TRANSFER_GAS: u128 = 21_000;
+
+/// The gas limit of a transaction that used `gas_used` in the estimate
+///
+/// The state can change between the estimate and the block the transaction lands in,
+/// so some room is left with `multiplier`
+pub fn gas_limit_for(gas_used: u128, multiplier: f64, simple_transfer: bool) -> u128 {
+    let limit = (gas_used as f64 * multiplier.max(1.0)).ceil() as u128;
+    if simple_transfer {
+        limit.max(MIN_TRANSFER_GAS)
+    } else {
+        limit
+    }
+}
+
+/// What a transaction does, enough to estimate it before the fees and the nonce are known
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxCall {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+impl TxCall {
+    pub fn new(from: Address, to: Address, value: U256, data: Bytes) -> Self {
+        Self { from, to, value, data }
+    }
+
+    /// The request for `eth_estimateGas` and `eth_createAccessList`
+    pub fn request(&self) -> TransactionRequest {
+        TransactionRequest::default()
+            .with_from(self.from)
+            .with_to(self.to)
+            .with_value(self.value)
+            .with_input(self.data.clone())
+    }
+}
+
+/// What the user set by hand in the advanced settings of a transaction, `None` keeps what we would use
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TxOverrides {
+    /// Also used when the gas estimate fails, eg. for a call whose estimate reverts
+    pub gas_limit: Option<u128>,
+
+    /// To replace a stuck transaction or fill a gap
+    pub nonce: Option<u64>,
+
+    /// EIP-2930 addresses and storage slots the transaction touches, see `eth_createAccessList`
+    pub access_list: Option<AccessList>,
+}
+
+impl TxOverrides {
+    /// A gas limit below [MIN_TRANSFER_GAS] can't pay for any transaction
+    pub fn check(&self) -> Result<(), anyhow::Error> {
+        match self.gas_limit {
+            Some(gas_limit) if gas_limit < MIN_TRANSFER_GAS => {
+                Err(anyhow!("The gas limit must be at least {}", MIN_TRANSFER_GAS))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How much of which currency a transaction moves, shown to the user in its own decimals
+///
+/// For an ERC20 transfer the value of the transaction is zero, the amount is in the call data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxAmount {
+    pub currency: Currency,
+    pub amount: U256,
+}
+
+impl TxAmount {
+    pub fn new(currency: Currency, amount: U256) -> Self {
+        Self { currency, amount }
+    }
+
+    /// The value of the transaction in the native currency of `chain_id`
+    pub fn native(chain_id: u64, value: U256) -> Self {
+        Self::new(Currency::new_native(chain_id), value)
+    }
+
+    /// eg. "1.5 USDC"
+    pub fn text(&self) -> String {
+        format!("{} {}", format_units(self.amount, self.currency.decimals()), self.currency.symbol())
+    }
+}
 
 #[derive(Default, Clone, Debug)]
 pub enum TxVariant {
@@ -43,6 +141,20 @@ pub struct TxData {
     pub gas_used: u128,
     pub chain_id: u64,
     pub mev_protect: bool,
+
+    /// The estimated gas is multiplied by this, see [gas_limit_for]
+    pub gas_multiplier: f64,
+
+    /// Use this gas limit instead of the one from [TxData::gas_used] and [TxData::gas_multiplier]
+    pub gas_limit: Option<u128>,
+
+    /// Use this max fee instead of the next base fee + the priority fee
+    pub max_fee: Option<u128>,
+
+    pub access_list: Option<AccessList>,
+
+    /// What the transaction moves, the native value unless set with [TxData::with_amount]
+    pub amount: TxAmount,
 }
 
 impl TxData {
@@ -71,9 +183,48 @@ impl TxData {
             gas_used,
             chain_id,
             mev_protect,
+            gas_multiplier: DEFAULT_GAS_MULTIPLIER,
+            gas_limit: None,
+            max_fee: None,
+            access_list: None,
+            amount: TxAmount::native(chain_id, value),
         }
     }
 
+    pub fn with_amount(mut self, amount: TxAmount) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn with_gas_multiplier(mut self, gas_multiplier: f64) -> Self {
+        self.gas_multiplier = gas_multiplier;
+        self
+    }
+
+    pub fn with_gas_limit(mut self, gas_limit: u128) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    pub fn with_max_fee(mut self, max_fee: u128) -> Self {
+        self.max_fee = Some(max_fee);
+        self
+    }
+
+    /// Apply what the user set in [TxOverrides], the other fields are kept
+    pub fn with_overrides(mut self, overrides: &TxOverrides) -> Self {
+        if let Some(gas_limit) = overrides.gas_limit {
+            self.gas_limit = Some(gas_limit);
+        }
+        if let Some(nonce) = overrides.nonce {
+            self.nonce = nonce;
+        }
+        if let Some(access_list) = &overrides.access_list {
+            self.access_list = Some(access_list.clone());
+        }
+        self
+    }
+
     pub fn priority_fee_u128(&self) -> Result<u128, anyhow::Error> {
         let p = u128::from_str(&self.priority_fee.to_string().as_str())
             .context("Failed to convert priority fee to u128");
@@ -87,6 +238,9 @@ impl TxData {
     }
 
     pub fn max_fee_per_gas(&self) -> Result<u128, anyhow::Error> {
+        if let Some(max_fee) = self.max_fee {
+            return Ok(max_fee);
+        }
         let priority = self.priority_fee_u128()?;
         let base = self.next_base_fee_u128()?;
         Ok(priority + base)
@@ -94,7 +248,10 @@ impl TxData {
     }
 
     pub fn calc_gas_limit(&self) -> u128 {
-        (self.gas_used * 15) / 10 // +50% gas limit
+        if let Some(gas_limit) = self.gas_limit {
+            return gas_limit;
+        }
+        gas_limit_for(self.gas_used, self.gas_multiplier, self.call_data.is_empty())
     }
 
     pub fn build_transaction(&self) -> Result<TransactionRequest, anyhow::Error> {
@@ -125,37 +282,413 @@ impl TxData {
             .with_max_fee_per_gas(self.max_fee_per_gas()?)
         };
 
+        // on BSC this makes it an EIP-2930 transaction instead of a legacy one
+        let tx = match &self.access_list {
+            Some(access_list) => tx.with_access_list(access_list.clone()),
+            None => tx,
+        };
+
         Ok(tx)
     }
 
-     /// Send a private transaction with flashbots
-     /// This is only used for Ethereum
-     pub async fn send_tx_with_flashbots(&self) -> Result<TransactionReceipt, anyhow::Error> {
-        let flashbots = "https://rpc.flashbots.net/fast".parse()?;
-
-        let provider = ProviderBuilder::new().on_http(flashbots);
-
+    /// Sign the transaction without sending it
+    pub async fn sign(&self) -> Result<TxEnvelope, anyhow::Error> {
         let wallet = EthereumWallet::from(self.signer.clone());
-
         let tx = self.build_transaction()?;
         let tx_envelope = tx.build(&wallet).await?;
-        let tx_encoded = tx_envelope.encoded_2718();
-        let pending = provider.send_raw_transaction(&tx_encoded).await?;
-        trace!("Transaction sent! {}", pending.tx_hash());
+        Ok(tx_envelope)
+    }
 
-        // wait for the receipt
-        let receipt = pending.get_receipt().await?;
-        Ok(receipt)
-     }
+    /// Simulate the transaction against the latest state of the chain
+    pub async fn simulate(&self) -> Result<SimulationResult, anyhow::Error> {
+        let tx = self.build_transaction()?;
 
-     /// Send a transaction without Mev protection
-     pub async fn send_tx(&self) -> Result<TransactionReceipt, anyhow::Error> {
-        let wallet = EthereumWallet::from(self.signer.clone());
+        let result = match self.client.call(&tx).await {
+            Ok(output) => SimulationResult {
+                success: true,
+                output: output.to_string(),
+                gas_used: self.client.estimate_gas(&tx).await.unwrap_or(self.gas_used),
+                l1_gas_used: self.estimate_l1_gas().await,
+            },
+            Err(e) => SimulationResult {
+                success: false,
+                output: e.to_string(),
+                gas_used: 0,
+                l1_gas_used: None,
+            },
+        };
 
-        let tx = self.build_transaction()?;
-        let tx_envelope = tx.build(&wallet).await?;
+        Ok(result)
+    }
+
+    /// The L1 data fee component of the gas on Arbitrum, `None` on other chains or if the node fails
+    async fn estimate_l1_gas(&self) -> Option<u64> {
+        if !is_arbitrum(self.chain_id) {
+            return None;
+        }
+
+        match estimate_l1_gas(self.client.clone(), self.to, self.call_data.clone()).await {
+            Ok(gas) => Some(gas),
+            Err(e) => {
+                trace!("Failed to estimate the L1 gas: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Broadcast a signed transaction and wait for the receipt
+    ///
+    /// If `mev_protect` is on and we are on Ethereum the transaction is sent privately with flashbots
+    pub async fn broadcast(&self, tx_envelope: TxEnvelope) -> Result<TransactionReceipt, anyhow::Error> {
+        broadcast_raw_tx(&self.client, self.chain_id, self.mev_protect, &tx_envelope.encoded_2718()).await
+    }
+
+    /// Run the whole pipeline: build, sign, simulate and broadcast
+    ///
+    /// If `dry_run` is true we stop before broadcasting and return what would have been sent
+    pub async fn execute(&self, dry_run: bool) -> Result<TxOutcome, anyhow::Error> {
+        let simulation = self.simulate().await?;
+        let tx_envelope = self.sign().await?;
+
+        if dry_run {
+            return Ok(TxOutcome::DryRun(Box::new(DryRunResult {
+                tx_hash: *tx_envelope.tx_hash(),
+                raw_tx: tx_envelope.encoded_2718().into(),
+                to: self.to,
+                value: self.value,
+                amount: self.amount.clone(),
+                call_data: self.call_data.clone(),
+                simulation,
+            })));
+        }
+
+        if !simulation.success {
+            return Err(anyhow!("Transaction simulation failed: {}", simulation.output));
+        }
+
+        let receipt = self.broadcast(tx_envelope).await?;
+        Ok(TxOutcome::Sent(Box::new(receipt)))
+    }
+
+    /// Build, simulate and sign the transaction but leave the broadcast to the user
+    ///
+    /// Fails like [Self::execute] if the simulation reverts
+    pub async fn sign_only(&self) -> Result<SignedTx, anyhow::Error> {
+        let simulation = self.simulate().await?;
+        if !simulation.success {
+            return Err(anyhow!("Transaction simulation failed: {}", simulation.output));
+        }
+
+        let tx_envelope = self.sign().await?;
+        Ok(SignedTx {
+            tx_hash: *tx_envelope.tx_hash(),
+            raw_tx: tx_envelope.encoded_2718().into(),
+            chain_id: self.chain_id,
+            nonce: self.nonce,
+            to: self.to,
+            value: self.value,
+            amount: self.amount.clone(),
+            simulation,
+        })
+    }
+}
+
+/// Broadcast an encoded signed transaction and wait for the receipt
+///
+/// If `mev_protect` is on and we are on Ethereum the transaction is sent privately with flashbots
+pub async fn broadcast_raw_tx(
+    client: &WsClient,
+    chain_id: u64,
+    mev_protect: bool,
+    raw_tx: &[u8],
+) -> Result<TransactionReceipt, anyhow::Error> {
+    if mev_protect && ChainFeature::MevProtect.supported_on(chain_id) {
+        return send_tx_with_flashbots(raw_tx).await;
+    }
+
+    let receipt = client.send_raw_transaction(raw_tx).await?.get_receipt().await?;
+    Ok(receipt)
+}
+
+/// Send a private transaction with flashbots
+/// This is only used for Ethereum
+async fn send_tx_with_flashbots(raw_tx: &[u8]) -> Result<TransactionReceipt, anyhow::Error> {
+    let flashbots = "https://rpc.flashbots.net/fast".parse()?;
+
+    let provider = ProviderBuilder::new().on_http(flashbots);
+
+    let pending = provider.send_raw_transaction(raw_tx).await?;
+    trace!("Transaction sent! {}", pending.tx_hash());
+
+    // wait for the receipt
+    let receipt = pending.get_receipt().await?;
+    Ok(receipt)
+}
 
-        let receipt = self.client.send_tx_envelope(tx_envelope).await?.get_receipt().await?;
-        Ok(receipt)
-     }
-}
\ No newline at end of file
+/// The result of simulating a transaction with `eth_call`
+#[derive(Clone, Debug)]
+pub struct SimulationResult {
+    pub success: bool,
+
+    /// The returned data or the revert reason
+    pub output: String,
+
+    /// Estimated gas used
+    pub gas_used: u128,
+
+    /// How much of [SimulationResult::gas_used] pays the L1 data fee, only on Arbitrum
+    pub l1_gas_used: Option<u64>,
+}
+
+/// Everything that would have been broadcasted if dry-run mode was off
+#[derive(Clone, Debug)]
+pub struct DryRunResult {
+    pub tx_hash: B256,
+
+    /// The signed transaction, ready to be broadcasted with `eth_sendRawTransaction`
+    pub raw_tx: Bytes,
+
+    pub to: Address,
+
+    pub value: U256,
+
+    pub amount: TxAmount,
+
+    pub call_data: Bytes,
+
+    pub simulation: SimulationResult,
+}
+
+/// A transaction signed by [TxData::sign_only], nothing was broadcasted
+#[derive(Clone, Debug)]
+pub struct SignedTx {
+    pub tx_hash: B256,
+
+    /// Ready to be broadcasted with `eth_sendRawTransaction`, from here or from another tool
+    pub raw_tx: Bytes,
+
+    pub chain_id: u64,
+
+    pub nonce: u64,
+
+    pub to: Address,
+
+    pub value: U256,
+
+    pub amount: TxAmount,
+
+    pub simulation: SimulationResult,
+}
+
+/// The outcome of [TxData::execute]
+#[derive(Clone, Debug)]
+pub enum TxOutcome {
+    /// Dry-run mode is on, nothing was sent
+    DryRun(Box<DryRunResult>),
+
+    /// The transaction was sent and included in a block
+    Sent(Box<TransactionReceipt>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloy::{
+        eips::{eip2718::Decodable2718, eip2930::AccessListItem},
+        providers::RootProvider,
+        pubsub::{ConnectionHandle, PubSubConnect},
+        rpc::client::RpcClient,
+        signers::local::PrivateKeySigner,
+        transports::TransportResult,
+    };
+    use crate::test_node::TestNode;
+
+    /// Never answers, signing does not need the node
+    struct Offline;
+
+    impl PubSubConnect for Offline {
+        fn is_local(&self) -> bool {
+            true
+        }
+
+        async fn connect(&self) -> TransportResult<ConnectionHandle> {
+            let (handle, interface) = ConnectionHandle::new();
+            // dropping the interface would close the connection and start a reconnect loop
+            std::mem::forget(interface);
+            Ok(handle)
+        }
+    }
+
+    /// Answers the calls of the simulation, `eth_call` reverts if `reverts` is set
+    ///
+    /// Anything else than the simulation is refused
+    fn simulating_node(reverts: bool) -> TestNode {
+        TestNode::new(move |method| match method {
+            "eth_call" if reverts => Err("execution reverted".to_string()),
+            "eth_call" => Ok("\"0x\"".to_string()),
+            "eth_estimateGas" => Ok("\"0xc350\"".to_string()),
+            _ => Err("not expected in this test".to_string()),
+        })
+    }
+
+    async fn tx_data(chain_id: u64) -> TxData {
+        let client = RootProvider::new(RpcClient::new(Offline.into_service().await.unwrap(), true));
+        tx_data_on(client, chain_id)
+    }
+
+    async fn scripted_tx(node: &TestNode) -> TxData {
+        tx_data_on(node.client().await, 1)
+    }
+
+    fn tx_data_on(client: WsClient, chain_id: u64) -> TxData {
+        TxData::new(
+            PrivateKeySigner::random(),
+            client,
+            U256::from(10_000_000_000u64),
+            Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb]),
+            Address::repeat_byte(1),
+            U256::ZERO,
+            3,
+            U256::from(1_000_000_000u64),
+            50_000,
+            chain_id,
+            false,
+        )
+    }
+
+    /// Sign `tx` and decode it back from the bytes we would broadcast
+    async fn signed(tx: &TxData) -> TxEnvelope {
+        let raw = tx.sign().await.unwrap().encoded_2718();
+        TxEnvelope::decode_2718(&mut raw.as_slice()).unwrap()
+    }
+
+    fn access_list() -> AccessList {
+        AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(2),
+            storage_keys: vec![B256::repeat_byte(3)],
+        }])
+    }
+
+    #[tokio::test]
+    async fn overrides_round_trip_into_the_signed_tx() {
+        let tx = tx_data(1).await;
+        let TxEnvelope::Eip1559(estimated) = signed(&tx).await else {
+            panic!("expected an EIP-1559 transaction");
+        };
+        assert_eq!(estimated.tx().nonce, 3);
+        assert_eq!(estimated.tx().gas_limit, gas_limit_for(50_000, DEFAULT_GAS_MULTIPLIER, false));
+        assert!(estimated.tx().access_list.is_empty());
+
+        let overrides = TxOverrides {
+            gas_limit: Some(90_000),
+            nonce: Some(7),
+            access_list: Some(access_list()),
+        };
+        let TxEnvelope::Eip1559(overridden) = signed(&tx.with_overrides(&overrides)).await else {
+            panic!("expected an EIP-1559 transaction");
+        };
+        assert_eq!(overridden.tx().nonce, 7);
+        assert_eq!(overridden.tx().gas_limit, 90_000);
+        assert_eq!(overridden.tx().access_list, access_list());
+    }
+
+    #[tokio::test]
+    async fn an_empty_override_keeps_the_built_tx() {
+        let tx = tx_data(1).await;
+        let overridden = tx.clone().with_overrides(&TxOverrides::default());
+        assert_eq!(signed(&overridden).await.tx_hash(), signed(&tx).await.tx_hash());
+    }
+
+    #[tokio::test]
+    async fn an_access_list_on_bsc_makes_an_eip2930_tx() {
+        let tx = tx_data(56).await;
+        assert!(matches!(signed(&tx).await, TxEnvelope::Legacy(_)));
+
+        let overrides = TxOverrides { access_list: Some(access_list()), ..TxOverrides::default() };
+        let TxEnvelope::Eip2930(with_list) = signed(&tx.with_overrides(&overrides)).await else {
+            panic!("expected an EIP-2930 transaction");
+        };
+        assert_eq!(with_list.tx().access_list, access_list());
+    }
+
+    #[tokio::test]
+    async fn signing_needs_no_node() {
+        let tx = tx_data(1).await;
+        let signed = signed(&tx).await;
+        assert_eq!(signed.recover_signer().unwrap(), tx.signer.address());
+        assert_eq!(signed.tx_hash(), tx.sign().await.unwrap().tx_hash());
+    }
+
+    #[tokio::test]
+    async fn a_dry_run_never_reaches_the_broadcast() {
+        for reverts in [false, true] {
+            let node = simulating_node(reverts);
+            let tx = scripted_tx(&node).await;
+
+            let TxOutcome::DryRun(result) = tx.execute(true).await.unwrap() else {
+                panic!("a dry run must not send the transaction");
+            };
+            assert_eq!(result.simulation.success, !reverts);
+            assert_eq!(result.tx_hash, *tx.sign().await.unwrap().tx_hash());
+            assert!(!node.methods().iter().any(|method| method == "eth_sendRawTransaction"));
+        }
+    }
+
+    #[tokio::test]
+    async fn a_reverting_simulation_is_not_broadcasted() {
+        let node = simulating_node(true);
+        let tx = scripted_tx(&node).await;
+
+        assert!(tx.execute(false).await.is_err());
+        assert!(tx.sign_only().await.is_err());
+        assert_eq!(node.methods(), ["eth_call", "eth_call"]);
+    }
+
+    #[tokio::test]
+    async fn sign_only_simulates_and_never_broadcasts() {
+        let node = simulating_node(false);
+        let tx = scripted_tx(&node).await;
+
+        let signed = tx.sign_only().await.unwrap();
+        assert!(signed.simulation.success);
+        assert_eq!(signed.simulation.gas_used, 50_000);
+        assert_eq!(signed.nonce, 3);
+        assert_eq!(node.methods(), ["eth_call", "eth_estimateGas"]);
+    }
+
+    #[test]
+    fn a_transfer_amount_renders_in_the_token_decimals() {
+        let usdc = ERC20Token { symbol: "USDC".to_string(), decimals: 6, ..Default::default() };
+        let amount = TxAmount::new(Currency::new_erc20(usdc), U256::from(2_500_000u64));
+        assert_eq!(amount.text(), "2.5 USDC");
+
+        let eth = TxAmount::native(1, U256::from(10u64).pow(U256::from(17u64)));
+        assert_eq!(eth.text(), "0.1 ETH");
+    }
+
+    #[test]
+    fn the_gas_limit_override_pays_at_least_a_transfer() {
+        let low = TxOverrides { gas_limit: Some(MIN_TRANSFER_GAS - 1), ..TxOverrides::default() };
+        assert!(low.check().is_err());
+
+        let enough = TxOverrides { gas_limit: Some(MIN_TRANSFER_GAS), ..TxOverrides::default() };
+        assert!(enough.check().is_ok());
+        assert!(TxOverrides::default().check().is_ok());
+    }
+
+    #[test]
+    fn the_gas_limit_leaves_room_over_the_estimate() {
+        assert_eq!(gas_limit_for(100_000, DEFAULT_GAS_MULTIPLIER, false), 120_000);
+        assert_eq!(gas_limit_for(100_001, 1.5, false), 150_002);
+        // never below the estimate
+        assert_eq!(gas_limit_for(100_000, 0.5, false), 100_000);
+    }
+
+    #[test]
+    fn simple_transfers_get_at_least_the_transfer_gas() {
+        assert_eq!(gas_limit_for(0, DEFAULT_GAS_MULTIPLIER, true), MIN_TRANSFER_GAS);
+        assert_eq!(gas_limit_for(10_000, 1.0, true), MIN_TRANSFER_GAS);
+        assert_eq!(gas_limit_for(21_000, DEFAULT_GAS_MULTIPLIER, true), 25_200);
+        assert_eq!(gas_limit_for(10_000, 1.0, false), 10_000);
+    }
+}
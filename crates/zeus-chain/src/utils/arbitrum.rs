@@ -0,0 +1,33 @@
+use alloy::{
+    primitives::{address, Address, Bytes},
+    sol,
+};
+
+use crate::WsClient;
+
+/// Chain id of Arbitrum One
+pub const ARBITRUM_CHAIN_ID: u64 = 42161;
+
+/// The NodeInterface virtual contract, only reachable through `eth_call` and `eth_estimateGas`
+const NODE_INTERFACE: Address = address!("00000000000000000000000000000000000000C8");
+
+sol! {
+    #[sol(rpc)]
+    contract NodeInterface {
+        function gasEstimateL1Component(address to, bool contractCreation, bytes calldata data) external payable returns (uint64 gasEstimateForL1, uint256 baseFee, uint256 l1BaseFeeEstimate);
+    }
+}
+
+/// Is this chain Arbitrum
+pub fn is_arbitrum(chain_id: u64) -> bool {
+    chain_id == ARBITRUM_CHAIN_ID
+}
+
+/// The part of the `eth_estimateGas` result on Arbitrum that pays for posting `data` to L1
+///
+/// Arbitrum charges the L1 data fee as extra L2 gas, so this is already included in the estimate
+pub async fn estimate_l1_gas(client: WsClient, to: Address, data: Bytes) -> Result<u64, anyhow::Error> {
+    let node = NodeInterface::new(NODE_INTERFACE, client);
+    let res = node.gasEstimateL1Component(to, false, data).call().await?;
+    Ok(res.gasEstimateForL1)
+}
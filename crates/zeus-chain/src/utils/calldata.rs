@@ -0,0 +1,245 @@
+use alloy::{
+    hex,
+    primitives::{Address, U256},
+    sol_types::SolCall,
+};
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+
+use crate::defi_types::currency::erc20::ERC20;
+
+lazy_static! {
+    /// A [CalldataDecoder] seeded with all the ABIs we know about
+    pub static ref CALLDATA_DECODER: CalldataDecoder = CalldataDecoder::default();
+}
+
+/// A decoded argument of a function call
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedArg {
+    pub name: String,
+    pub value: String,
+}
+
+impl DecodedArg {
+    pub fn new(name: &str, value: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/// Calldata resolved against a known ABI
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCall {
+    pub selector: [u8; 4],
+
+    /// The function signature (eg. `transfer(address,uint256)`) or the hex selector if unknown
+    pub function: String,
+
+    pub args: Vec<DecodedArg>,
+
+    /// Did the selector match a registered function?
+    pub known: bool,
+}
+
+type DecodeFn = Box<dyn Fn(&[u8]) -> Result<Vec<DecodedArg>, alloy::sol_types::Error> + Send + Sync>;
+
+struct Entry {
+    signature: &'static str,
+    decode: DecodeFn,
+}
+
+/// Registry of known functions by their 4-byte selector
+pub struct CalldataDecoder {
+    functions: HashMap<[u8; 4], Entry>,
+}
+
+impl CalldataDecoder {
+    /// An empty decoder, see [CalldataDecoder::default] for one with the known ABIs
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Register a `sol!` generated call, `args` names and formats its arguments
+    pub fn register<C: SolCall + 'static>(&mut self, args: fn(&C) -> Vec<DecodedArg>) {
+        let decode: DecodeFn = Box::new(move |data| {
+            let call = C::abi_decode(data, true)?;
+            Ok(args(&call))
+        });
+
+        self.functions.insert(C::SELECTOR, Entry {
+            signature: C::SIGNATURE,
+            decode,
+        });
+    }
+
+    /// Decode the given calldata
+    ///
+    /// Returns `None` if there is no calldata (eg. a plain native transfer)
+    ///
+    /// Unknown functions or calldata that fails to decode fall back to the 4-byte selector
+    pub fn decode(&self, data: &[u8]) -> Option<DecodedCall> {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let mut selector = [0u8; 4];
+        selector.copy_from_slice(&data[..4]);
+
+        let decoded = self
+            .functions
+            .get(&selector)
+            .and_then(|entry| (entry.decode)(data).ok().map(|args| (entry.signature, args)));
+
+        let call = match decoded {
+            Some((signature, args)) => DecodedCall {
+                selector,
+                function: signature.to_string(),
+                args,
+                known: true,
+            },
+            None => DecodedCall {
+                selector,
+                function: hex::encode_prefixed(selector),
+                args: Vec::new(),
+                known: false,
+            },
+        };
+
+        Some(call)
+    }
+}
+
+impl Default for CalldataDecoder {
+    fn default() -> Self {
+        let mut decoder = Self::new();
+
+        // ERC20
+        decoder.register(|c: &ERC20::transferCall| {
+            vec![address_arg("recipient", c.recipient), amount_arg("amount", c.amount)]
+        });
+        decoder.register(|c: &ERC20::approveCall| {
+            vec![address_arg("spender", c.spender), amount_arg("amount", c.amount)]
+        });
+        decoder.register(|c: &ERC20::transferFromCall| {
+            vec![
+                address_arg("from", c.from),
+                address_arg("recipient", c.recipient),
+                amount_arg("amount", c.amount),
+            ]
+        });
+
+        // WETH
+        decoder.register(|_: &ERC20::depositCall| Vec::new());
+        decoder.register(|c: &ERC20::withdrawCall| vec![amount_arg("amount", c.amount)]);
+
+        decoder
+    }
+}
+
+fn address_arg(name: &str, address: Address) -> DecodedArg {
+    DecodedArg::new(name, address)
+}
+
+fn amount_arg(name: &str, amount: U256) -> DecodedArg {
+    if amount == U256::MAX {
+        DecodedArg::new(name, "Unlimited")
+    } else {
+        DecodedArg::new(name, amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    const ALICE: Address = address!("1111111111111111111111111111111111111111");
+    const BOB: Address = address!("2222222222222222222222222222222222222222");
+
+    fn decode(data: Vec<u8>) -> DecodedCall {
+        CalldataDecoder::default().decode(&data).unwrap()
+    }
+
+    fn args(call: &DecodedCall) -> Vec<(&str, &str)> {
+        call.args.iter().map(|arg| (arg.name.as_str(), arg.value.as_str())).collect()
+    }
+
+    #[test]
+    fn decodes_transfer() {
+        let call = decode(ERC20::transferCall { recipient: BOB, amount: U256::from(5) }.abi_encode());
+        assert!(call.known);
+        assert_eq!(call.selector, ERC20::transferCall::SELECTOR);
+        assert_eq!(call.function, "transfer(address,uint256)");
+        assert_eq!(args(&call), [("recipient", BOB.to_string().as_str()), ("amount", "5")]);
+    }
+
+    #[test]
+    fn decodes_approve_and_shows_an_unlimited_amount() {
+        let call = decode(ERC20::approveCall { spender: BOB, amount: U256::MAX }.abi_encode());
+        assert!(call.known);
+        assert_eq!(call.function, "approve(address,uint256)");
+        assert_eq!(args(&call), [("spender", BOB.to_string().as_str()), ("amount", "Unlimited")]);
+    }
+
+    #[test]
+    fn decodes_transfer_from() {
+        let call = decode(ERC20::transferFromCall { from: ALICE, recipient: BOB, amount: U256::from(7) }.abi_encode());
+        assert!(call.known);
+        assert_eq!(call.function, "transferFrom(address,address,uint256)");
+        assert_eq!(
+            args(&call),
+            [("from", ALICE.to_string().as_str()), ("recipient", BOB.to_string().as_str()), ("amount", "7")]
+        );
+    }
+
+    #[test]
+    fn decodes_weth_deposit_and_withdraw() {
+        let call = decode(ERC20::depositCall {}.abi_encode());
+        assert!(call.known);
+        assert_eq!(call.function, "deposit()");
+        assert!(call.args.is_empty());
+
+        let call = decode(ERC20::withdrawCall { amount: U256::from(9) }.abi_encode());
+        assert!(call.known);
+        assert_eq!(call.function, "withdraw(uint256)");
+        assert_eq!(args(&call), [("amount", "9")]);
+    }
+
+    #[test]
+    fn every_registered_selector_is_tested() {
+        let mut selectors: Vec<[u8; 4]> = CalldataDecoder::default().functions.into_keys().collect();
+        selectors.sort();
+
+        let mut tested = vec![
+            ERC20::transferCall::SELECTOR,
+            ERC20::approveCall::SELECTOR,
+            ERC20::transferFromCall::SELECTOR,
+            ERC20::depositCall::SELECTOR,
+            ERC20::withdrawCall::SELECTOR,
+        ];
+        tested.sort();
+        assert_eq!(selectors, tested);
+    }
+
+    #[test]
+    fn unknown_or_broken_calldata_falls_back_to_the_selector() {
+        let call = decode(vec![0xde, 0xad, 0xbe, 0xef, 0x00]);
+        assert!(!call.known);
+        assert_eq!(call.function, "0xdeadbeef");
+        assert!(call.args.is_empty());
+
+        // a known selector with its arguments cut off
+        let mut data = ERC20::transferCall { recipient: BOB, amount: U256::from(5) }.abi_encode();
+        data.truncate(20);
+        let call = decode(data);
+        assert!(!call.known);
+        assert_eq!(call.function, hex::encode_prefixed(ERC20::transferCall::SELECTOR));
+
+        // no calldata, eg. a native transfer
+        assert_eq!(CalldataDecoder::default().decode(&[0xa9, 0x05]), None);
+    }
+}
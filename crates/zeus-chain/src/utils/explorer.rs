@@ -0,0 +1,99 @@
+use alloy::{primitives::{Address, U256}, transports::http::reqwest};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use anyhow::anyhow;
+
+use crate::{chain_id::{ChainFeature, ChainUnsupported}, defi_types::currency::erc20::ERC20Token};
+
+
+/// Where we read the metadata (symbol, name, decimals, total supply) of an [ERC20Token] from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenMetadataSource {
+    /// Only use the connected node
+    #[default]
+    Node,
+
+    /// Use the connected node and if that fails fall back to a public block explorer API
+    NodeThenExplorer,
+}
+
+impl TokenMetadataSource {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Node => "Node only".to_string(),
+            Self::NodeThenExplorer => "Node, then block explorer".to_string(),
+        }
+    }
+
+    /// Are we allowed to make requests to an external API?
+    pub fn uses_explorer(&self) -> bool {
+        matches!(self, Self::NodeThenExplorer)
+    }
+}
+
+/// Token info as returned by the Blockscout API
+#[derive(Deserialize)]
+struct BlockscoutToken {
+    symbol: Option<String>,
+    name: Option<String>,
+    decimals: Option<String>,
+    total_supply: Option<String>,
+}
+
+/// The Blockscout instance for the given chain
+///
+/// Returns `None` if there is no public instance for the chain
+pub fn explorer_api_url(chain_id: u64) -> Option<&'static str> {
+    match chain_id {
+        1 => Some("https://eth.blockscout.com"),
+        8453 => Some("https://base.blockscout.com"),
+        42161 => Some("https://arbitrum.blockscout.com"),
+        11155111 => Some("https://eth-sepolia.blockscout.com"),
+        84532 => Some("https://base-sepolia.blockscout.com"),
+        _ => None,
+    }
+}
+
+/// The page of an address on the block explorer users know for the chain
+pub fn explorer_address_url(chain_id: u64, address: Address) -> Option<String> {
+    let base_url = match chain_id {
+        1 => "https://etherscan.io",
+        56 => "https://bscscan.com",
+        8453 => "https://basescan.org",
+        42161 => "https://arbiscan.io",
+        11155111 => "https://sepolia.etherscan.io",
+        84532 => "https://sepolia.basescan.org",
+        _ => return None,
+    };
+    Some(format!("{}/address/{}", base_url, address))
+}
+
+/// Get the metadata of an [ERC20Token] from a public block explorer
+pub async fn erc20_from_explorer(address: Address, chain_id: u64) -> Result<ERC20Token, anyhow::Error> {
+    let base_url = explorer_api_url(chain_id)
+        .ok_or(ChainUnsupported::new(chain_id, ChainFeature::BlockExplorer))?;
+
+    let url = format!("{}/api/v2/tokens/{}", base_url, address);
+    let res = reqwest::get(&url).await?.error_for_status()?.text().await?;
+    let info: BlockscoutToken = serde_json::from_str(&res)?;
+
+    let decimals = info
+        .decimals
+        .ok_or_else(|| anyhow!("Block explorer did not return the token decimals"))?
+        .parse::<u8>()?;
+
+    let total_supply = match info.total_supply {
+        Some(supply) => U256::from_str(&supply)?,
+        None => U256::ZERO,
+    };
+
+    Ok(ERC20Token {
+        chain_id,
+        address,
+        symbol: info.symbol.unwrap_or_default(),
+        name: info.name.unwrap_or_default(),
+        decimals,
+        total_supply,
+        icon: None,
+    })
+}
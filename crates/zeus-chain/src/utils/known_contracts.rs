@@ -0,0 +1,95 @@
+use alloy::primitives::{address, Address};
+
+/// The selectors of `transfer`, `balanceOf` and `totalSupply`, every ERC20 dispatches on them
+const ERC20_SELECTORS: [[u8; 4]; 3] = [[0xa9, 0x05, 0x9c, 0xbb], [0x70, 0xa0, 0x82, 0x31], [0x18, 0x16, 0x0d, 0xdd]];
+
+/// The `PUSH4` opcode, how the dispatcher of a contract loads a selector to compare
+const PUSH4: u8 = 0x63;
+
+/// Deployed at the same address on every chain
+const PERMIT2: Address = address!("000000000022D473030F116dDEE9F6B43aC78BA3");
+
+/// Well known protocol contracts of a chain, shown by name instead of their address
+///
+/// Keep this to contracts users approve or send funds to: routers, Permit2 and bridges
+pub fn known_contracts(chain_id: u64) -> Vec<(Address, &'static str)> {
+    let mut contracts = vec![(PERMIT2, "Permit2")];
+
+    match chain_id {
+        1 => contracts.extend([
+            (address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D"), "Uniswap V2 Router"),
+            (address!("E592427A0AEce92De3Edee1F18E0157C05861564"), "Uniswap V3 Router"),
+            (address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"), "Uniswap V3 Router 2"),
+            (address!("3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD"), "Uniswap Universal Router"),
+            (address!("3154Cf16ccdb4C6d922629664174b904d80F2C35"), "Base Bridge"),
+            (address!("72Ce9c846789fdB6fC1f34aC4AD25Dd9ef7031ef"), "Arbitrum Gateway Router"),
+            (address!("4Dbd4fc535Ac27206064B68FfCf827b0A60BAB3f"), "Arbitrum Delayed Inbox"),
+        ]),
+        56 => contracts.extend([
+            (address!("10ED43C718714eb63d5aA57B78B54704E256024E"), "PancakeSwap V2 Router"),
+            (address!("13f4EA83D0bd40E75C8222255bc855a974568Dd4"), "PancakeSwap Smart Router"),
+        ]),
+        8453 => contracts.extend([
+            (address!("2626664c2603336E57B271c5C0b26F421741e481"), "Uniswap V3 Router 2"),
+            (address!("3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD"), "Uniswap Universal Router"),
+            (address!("4200000000000000000000000000000000000010"), "Base Bridge"),
+        ]),
+        42161 => contracts.extend([
+            (address!("E592427A0AEce92De3Edee1F18E0157C05861564"), "Uniswap V3 Router"),
+            (address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"), "Uniswap V3 Router 2"),
+            (address!("5E325eDA8064b456f4781070C0738d849c824258"), "Uniswap Universal Router"),
+            (address!("5288c571Fd7aD117beA99bF60FE0846C4E84F933"), "Arbitrum Gateway Router"),
+        ]),
+        _ => {}
+    }
+
+    contracts
+}
+
+/// The name of a well known contract, see [known_contracts]
+pub fn known_contract(chain_id: u64, address: Address) -> Option<&'static str> {
+    known_contracts(chain_id)
+        .into_iter()
+        .find(|(contract, _)| *contract == address)
+        .map(|(_, name)| name)
+}
+
+/// Does the runtime `code` of a contract dispatch on the ERC20 selectors
+///
+/// A heuristic, a proxy to a token has none of them in its own code
+pub fn looks_like_erc20(code: &[u8]) -> bool {
+    ERC20_SELECTORS.iter().all(|selector| {
+        code.windows(5)
+            .any(|window| window[0] == PUSH4 && window[1..] == selector[..])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatcher(selectors: &[[u8; 4]]) -> Vec<u8> {
+        // PUSH1 0xe0 CALLDATALOAD SHR, then DUP1 PUSH4 <selector> EQ for each function
+        let mut code = vec![0x60, 0xe0, 0x35, 0x1c];
+        for selector in selectors {
+            code.extend([0x80, PUSH4]);
+            code.extend(selector);
+            code.push(0x14);
+        }
+        code
+    }
+
+    #[test]
+    fn erc20_code_is_recognized_by_its_selectors() {
+        assert!(looks_like_erc20(&dispatcher(&ERC20_SELECTORS)));
+
+        // a contract without `totalSupply`, eg. a router
+        assert!(!looks_like_erc20(&dispatcher(&ERC20_SELECTORS[..2])));
+
+        // the selector bytes as data, not pushed
+        let mut code = Vec::new();
+        ERC20_SELECTORS.iter().for_each(|selector| code.extend(selector));
+        assert!(!looks_like_erc20(&code));
+        assert!(!looks_like_erc20(&[]));
+    }
+}
@@ -5,8 +5,7 @@ use alloy::{
     transports::ws::WsConnect
 };
 use std::sync::Arc;
-use std::str::FromStr;
-use bigdecimal::BigDecimal;
+use anyhow::anyhow;
 
 
 
@@ -18,21 +17,181 @@ pub async fn get_client(url: &str) -> Result<Arc<RootProvider<PubSubFrontend>>,
 }
 
 
-/// Parse from readable units to wei
-pub fn parse_wei(amount: &str, decimals: u8) -> Result<U256, anyhow::Error> {
-    let amount = BigDecimal::from_str(amount)?;
-    let wei_amount = amount * (10_u64).pow(decimals as u32);
-    let wei_str = wei_amount.to_string();
-    let wei_str = wei_str.split('.').next().unwrap_or_default();
-    let wei = U256::from_str(wei_str)?;
-    Ok(wei)
+/// Parse a readable `amount` (eg. `1.5`) to its smallest unit using the given `decimals`
+///
+/// Digits beyond `decimals` are truncated, scientific notation and negative amounts are rejected
+pub fn parse_units(amount: &str, decimals: u8) -> Result<U256, anyhow::Error> {
+    let amount = amount.trim();
+
+    if amount.contains(['e', 'E']) {
+        return Err(anyhow!("Scientific notation is not supported"));
+    }
+
+    let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(anyhow!("Amount is empty"));
+    }
+
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if !is_digits(integer) || !is_digits(fraction) {
+        return Err(anyhow!("Invalid amount: {}", amount));
+    }
+
+    let decimals = decimals as usize;
+    let fraction = &fraction[..fraction.len().min(decimals)];
+    let units = format!("{}{:0<width$}", integer, fraction, width = decimals);
+
+    U256::from_str_radix(&units, 10).map_err(|_| anyhow!("Amount is too large: {}", amount))
+}
+
+/// Format an `amount` from its smallest unit to readable units using the given `decimals`
+///
+/// Trailing zeros of the fractional part are removed (eg. `1500000` with 6 decimals is `1.5`)
+pub fn format_units(amount: U256, decimals: u8) -> String {
+    let (integer, fraction) = split_units(amount, decimals);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        integer
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// Same as [format_units] but always with `precision` decimal places
+///
+/// Extra digits are truncated, not rounded, so we never show more than the actual amount
+pub fn format_units_with_precision(amount: U256, decimals: u8, precision: usize) -> String {
+    let (integer, fraction) = split_units(amount, decimals);
+
+    if precision == 0 {
+        return integer;
+    }
+
+    format!("{}.{:0<width$.width$}", integer, fraction, width = precision)
+}
+
+/// Format a large `amount` compactly with up to 2 decimals (eg. `420.69T`, `1.5M`)
+///
+/// Past trillions it switches to scientific notation (eg. `1.15e77`), so a huge supply never renders as a wall of digits.
+/// Digits are truncated like [format_units_with_precision]
+pub fn format_compact(amount: U256, decimals: u8) -> String {
+    let (integer, fraction) = split_units(amount, decimals);
+    let digits = integer.len();
+    let group = (digits - 1) / 3;
+
+    let (head, tail, suffix) = if group == 0 {
+        (integer.as_str(), fraction.as_str(), String::new())
+    } else if group < COMPACT_SUFFIXES.len() {
+        let (head, tail) = integer.split_at(digits - group * 3);
+        (head, tail, COMPACT_SUFFIXES[group].to_string())
+    } else {
+        let (head, tail) = integer.split_at(1);
+        (head, tail, format!("e{}", digits - 1))
+    };
+
+    let tail = tail.get(..2).unwrap_or(tail).trim_end_matches('0');
+    if tail.is_empty() {
+        format!("{}{}", head, suffix)
+    } else {
+        format!("{}.{}{}", head, tail, suffix)
+    }
+}
+
+/// The suffix of every 3 digits of [format_compact]
+const COMPACT_SUFFIXES: [&str; 5] = ["", "K", "M", "B", "T"];
+
+/// Split an `amount` into its integer and fractional digits
+fn split_units(amount: U256, decimals: u8) -> (String, String) {
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", amount.to_string(), width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    (integer.to_string(), fraction.to_string())
 }
 
-/// Format the amount from wei to readable units
-pub fn format_wei(amount: &str, decimals: u8) -> String {
-    let divisor_str = format!("1{:0>width$}", "", width = decimals as usize);
-    let divisor = BigDecimal::from_str(&divisor_str).unwrap_or_default();
-    let amount = BigDecimal::from_str(&amount).unwrap_or_default();
-    let readable = amount / divisor;
-    readable.to_string()
-}
\ No newline at end of file
+/// Format a percent change with its sign (eg. `+3.25%`, `-0.80%`)
+pub fn format_percent_change(change: f64) -> String {
+    format!("{:+.2}%", change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Powers of ten, their neighbours and the U256 limits
+    fn extreme_amounts() -> Vec<U256> {
+        let mut amounts = vec![U256::ZERO, U256::from(1), U256::MAX, U256::MAX - U256::from(1), U256::MAX / U256::from(2)];
+        for exp in 0..78 {
+            let power = U256::from(10).pow(U256::from(exp));
+            amounts.extend([power, power - U256::from(1), power + U256::from(1)]);
+        }
+        amounts
+    }
+
+    #[test]
+    fn extra_decimals_are_truncated() {
+        assert_eq!(parse_units("1.1234567", 6).unwrap(), U256::from(1_123_456));
+        assert_eq!(parse_units("0.0000009", 6).unwrap(), U256::ZERO);
+        assert_eq!(parse_units("1.9", 0).unwrap(), U256::from(1));
+        assert_eq!(parse_units(".5", 6).unwrap(), U256::from(500_000));
+        assert_eq!(parse_units("5.", 6).unwrap(), U256::from(5_000_000));
+        assert_eq!(parse_units(" 2 ", 2).unwrap(), U256::from(200));
+    }
+
+    #[test]
+    fn scientific_notation_and_other_formats_are_rejected() {
+        for amount in ["1e18", "1E6", "1.5e3", "-1", "1,5", "1.2.3", "0x10", "", ".", "one"] {
+            assert!(parse_units(amount, 18).is_err(), "{:?} parsed", amount);
+        }
+        assert_eq!(
+            parse_units("1e18", 18).unwrap_err().to_string(),
+            "Scientific notation is not supported"
+        );
+    }
+
+    #[test]
+    fn amounts_past_u256_max_overflow() {
+        let max = U256::MAX.to_string();
+        assert_eq!(parse_units(&max, 0).unwrap(), U256::MAX);
+
+        // U256::MAX + 1
+        let over = "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+        assert!(parse_units(over, 0).is_err());
+
+        let (integer, fraction) = max.split_at(max.len() - 18);
+        assert_eq!(parse_units(&format!("{}.{}", integer, fraction), 18).unwrap(), U256::MAX);
+        assert!(parse_units(&format!("{}.584007913129639936", integer), 18).is_err());
+        assert!(parse_units(&format!("{}0", integer), 18).is_err());
+        // truncated digits can't overflow
+        assert_eq!(parse_units(&format!("{}.{}9", integer, fraction), 18).unwrap(), U256::MAX);
+    }
+
+    #[test]
+    fn compact_amounts_get_a_suffix() {
+        let one = U256::from(10).pow(U256::from(18));
+        assert_eq!(format_compact(U256::from(420_690_000_000_000u64) * one, 18), "420.69T");
+        assert_eq!(format_compact(U256::from(1_500_000) * one, 18), "1.5M");
+        assert_eq!(format_compact(U256::from(999) * one, 18), "999");
+        assert_eq!(format_compact(U256::from(1_000) * one, 18), "1K");
+        assert_eq!(format_compact(U256::from(12_345_678), 6), "12.34");
+        assert_eq!(format_compact(U256::ZERO, 18), "0");
+        assert_eq!(format_compact(U256::MAX, 0), "1.15e77");
+        assert_eq!(format_compact(U256::MAX, 18), "1.15e59");
+    }
+
+    #[test]
+    fn extreme_amounts_format_without_panicking() {
+        for amount in extreme_amounts() {
+            for decimals in [0, 1, 6, 18, 30, 31, 77, 78, 100, u8::MAX] {
+                let compact = format_compact(amount, decimals);
+                assert!(compact.len() <= 10, "{} with {} decimals is {}", amount, decimals, compact);
+
+                let exact = format_units(amount, decimals);
+                let with_precision = format_units_with_precision(amount, decimals, 4);
+                assert_eq!(parse_units(&exact, decimals).unwrap(), amount);
+                assert!(with_precision.starts_with(exact.split('.').next().unwrap()));
+            }
+        }
+    }
+}
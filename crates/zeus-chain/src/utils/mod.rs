@@ -1,4 +1,15 @@
 pub mod oracles;
 pub mod misc;
+pub mod explorer;
+pub mod calldata;
+pub mod rpc_usage;
+pub mod arbitrum;
+pub mod known_contracts;
+pub mod routers;
 
-pub use misc::{get_client, parse_wei, format_wei};
\ No newline at end of file
+pub use misc::{get_client, parse_units, format_units, format_units_with_precision, format_compact, format_percent_change};
+pub use explorer::{explorer_address_url, TokenMetadataSource};
+pub use calldata::{CalldataDecoder, DecodedCall, CALLDATA_DECODER};
+pub use rpc_usage::{record_rpc, RpcBudget, RpcOrigin, RpcUsage, RPC_USAGE};
+pub use known_contracts::{known_contract, looks_like_erc20};
+pub use routers::SwapRouter;
@@ -15,12 +15,16 @@ lazy_static! {
     pub static ref BLOCK_ORACLE: Arc<RwLock<BlockOracle>> = BlockOracle::default();
 }
 
-use tracing::{ info, error, trace };
+use tracing::{ info, error, trace, warn };
 use super::OracleAction;
+use crate::utils::{arbitrum::ARBITRUM_CHAIN_ID, rpc_usage::{record_rpc, RpcOrigin}};
+use crate::chain_id::{ChainFeature, ChainUnsupported};
+use crate::defi_types::{currency::erc20::ERC20Token, pool::get_reference_v3_quote};
 
 use std::time::{ Instant, Duration };
 
-//const ETH_USD_FEED_DECIMALS: u8 = 8;
+/// Decimals of the ChainLink USD feeds, the DEX fallback price is scaled to the same
+pub const ETH_USD_FEED_DECIMALS: u8 = 8;
 
 const ETH_USD_FEED: Address = address!("5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
 const BNB_USD_FEED: Address = address!("0567F2323251f0Aab15c8dFb1967E4e8A7D42aeE");
@@ -30,6 +34,36 @@ const ARB_ETH_USD_FEED: Address = address!("639Fe6ab55C921f74e7fac1ee960C0B6293b
 /// Time out for querying the gas price
 const TIME_OUT: u64 = 30;
 
+/// The node is behind when its latest block is older than this many block times
+const LAG_BLOCKS: u32 = 3;
+
+/// The subscription is stuck when no block arrived for this many block times
+const STALL_BLOCKS: u32 = 5;
+
+/// Block timestamps are in seconds, fast chains would warn on every block without these floors
+const MIN_LAG: Duration = Duration::from_secs(5);
+const MIN_STALL: Duration = Duration::from_secs(10);
+
+/// The average time between two blocks of a chain
+pub fn block_time(chain_id: u64) -> Duration {
+    match chain_id {
+        56 => Duration::from_secs(3),
+        8453 | 84532 => Duration::from_secs(2),
+        ARBITRUM_CHAIN_ID => Duration::from_millis(250),
+        _ => Duration::from_secs(12),
+    }
+}
+
+/// How old the latest block can be before we warn that the node is behind
+pub fn lag_threshold(chain_id: u64) -> Duration {
+    (block_time(chain_id) * LAG_BLOCKS).max(MIN_LAG)
+}
+
+/// How long we wait for a new block before we subscribe again
+pub fn stall_threshold(chain_id: u64) -> Duration {
+    (block_time(chain_id) * STALL_BLOCKS).max(MIN_STALL)
+}
+
 sol!(
     #[sol(rpc)]
     contract ChainLinkOracle {
@@ -72,14 +106,17 @@ impl BlockInfo {
         let timestamp = match chain_id {
             1 => block.header.timestamp + 12,
             56 => block.header.timestamp + 3,
-            8453 => block.header.timestamp + 2,
-            42161 => block.header.timestamp, // Arbitrum??????
+            8453 | 84532 => block.header.timestamp + 2,
+            // blocks are produced every ~250ms, the next one most likely has the same timestamp
+            ARBITRUM_CHAIN_ID => block.header.timestamp,
             _ => block.header.timestamp + 12,
         };
 
         let base_fee = match chain_id {
-            1 => calculate_next_block_base_fee(block.clone()),
+            1 | 11155111 => calculate_next_block_base_fee(block.clone()),
             56 => U256::from(3000000000u64), // 3 Gwei
+            // the base fee is set by the sequencer and rarely moves between blocks
+            ARBITRUM_CHAIN_ID => U256::from(block.header.base_fee_per_gas.unwrap_or_default()),
             _ => U256::from(0), // TODO
         };
 
@@ -91,6 +128,21 @@ impl BlockInfo {
         Ok(())
     }
 
+    /// How long ago this block was produced, `now` is a unix timestamp in seconds
+    ///
+    /// Returns `None` until we have a block
+    pub fn age(&self, now: u64) -> Option<Duration> {
+        if self.number == 0 {
+            return None;
+        }
+        Some(Duration::from_secs(now.saturating_sub(self.timestamp)))
+    }
+
+    /// How far behind the node is, `None` if the block is recent enough for `chain_id`
+    pub fn lag(&self, chain_id: u64, now: u64) -> Option<Duration> {
+        self.age(now).filter(|age| *age > lag_threshold(chain_id))
+    }
+
     /// Wei to Gwei conversion
     pub fn gwei(&self) -> U256 {
         self.base_fee * U256::from(10).pow(U256::from(9))
@@ -100,6 +152,19 @@ impl BlockInfo {
     pub fn format_gwei(&self) -> String {
         format!("{:.2} Gwei", self.gwei() / U256::from(10).pow(U256::from(18)))
     }
+
+    /// Percent change of the base fee from this block to `next`
+    ///
+    /// Returns `None` if either base fee is unknown
+    pub fn base_fee_change(&self, next: &BlockInfo) -> Option<f64> {
+        if self.base_fee.is_zero() || next.base_fee.is_zero() {
+            return None;
+        }
+
+        let current = u128::try_from(self.base_fee).ok()? as f64;
+        let next = u128::try_from(next.base_fee).ok()? as f64;
+        Some((next - current) / current * 100.0)
+    }
 }
 
 #[derive(Clone)]
@@ -107,8 +172,15 @@ pub struct BlockOracle {
     pub latest_block: BlockInfo,
     pub next_block: BlockInfo,
     pub chain_id: u64,
-    pub eth_price: U256,
+
+    /// USD price of the native currency with [ETH_USD_FEED_DECIMALS] decimals
+    ///
+    /// `None` if neither the ChainLink feed nor the DEX fallback could provide it
+    pub eth_price: Option<U256>,
     last_eth_price_request: Instant,
+
+    /// The ChainLink feed failed before, so we don't keep logging it
+    feed_failed: bool,
 }
 
 impl BlockOracle {
@@ -120,7 +192,9 @@ impl BlockOracle {
 
         let block_id = BlockId::Number(BlockNumberOrTag::Latest);
         let block = client.get_block(block_id, true.into()).await?;
-        let eth_price = get_eth_price(client.clone(), chain_id).await?;
+        record_rpc(RpcOrigin::Oracle, 1);
+        let mut feed_failed = false;
+        let eth_price = fetch_eth_price(client.clone(), chain_id, &mut feed_failed).await;
 
         let block = block.ok_or_else(|| anyhow!("Block is missing"))?;
 
@@ -145,6 +219,7 @@ impl BlockOracle {
             chain_id,
             eth_price,
             last_eth_price_request: Instant::now(),
+            feed_failed,
         })
     }
 
@@ -154,8 +229,9 @@ impl BlockOracle {
             latest_block: BlockInfo::default(),
             next_block: BlockInfo::default(),
             chain_id: 1,
-            eth_price: U256::ZERO,
+            eth_price: None,
             last_eth_price_request: Instant::now(),
+            feed_failed: false,
         };
 
         Arc::new(RwLock::new(block_oracle))
@@ -186,8 +262,8 @@ impl BlockOracle {
         &self.next_block
     }
 
-    pub fn get_eth_price(&self) -> &U256 {
-        &self.eth_price
+    pub fn get_eth_price(&self) -> Option<U256> {
+        self.eth_price
     }
 }
 
@@ -200,6 +276,7 @@ pub async fn start_block_oracle(
     trace!("Started block oracle for Chain ID: {}", chain_id);
     loop {
         let sub = client.subscribe_blocks().await;
+        record_rpc(RpcOrigin::Oracle, 1);
         let mut stream = match sub {
             Ok(s) => s.into_stream(),
             Err(e) => {
@@ -209,7 +286,22 @@ pub async fn start_block_oracle(
             }
         };
 
-        while let Some(block) = stream.next().await {
+        let stall = stall_threshold(chain_id);
+        loop {
+            let block = match tokio::time::timeout(stall, stream.next()).await {
+                Ok(Some(block)) => block,
+                // the subscription was closed, subscribe again
+                Ok(None) => break,
+                Err(_) => {
+                    if let Ok(OracleAction::KILL) = receiver.try_recv() {
+                        trace!("Received kill signal, block oracle stopped for Chain Id: {:?}", chain_id);
+                        return;
+                    }
+                    warn!("No new block for {:?} on Chain ID: {}, subscribing again", stall, chain_id);
+                    break;
+                }
+            };
+
             match receiver.try_recv() {
                 Ok(OracleAction::KILL) => {
                     trace!(
@@ -231,6 +323,7 @@ pub async fn start_block_oracle(
             trace!("Received new block {} for Chain ID: {}", number, chain_id);
 
             let last_request;
+            let mut feed_failed;
             {
                 let mut lock = oracle.write().unwrap();
 
@@ -239,39 +332,98 @@ pub async fn start_block_oracle(
                     Err(e) => error!("Failed to update block info: {:?}", e),
                 }
                 last_request = lock.last_eth_price_request;
+                feed_failed = lock.feed_failed;
             }
 
             let now = Instant::now();
             let timeout_expired = now.duration_since(last_request) > Duration::from_secs(TIME_OUT);
 
             if timeout_expired {
-                let eth_price = get_eth_price(client.clone(), chain_id).await;
-                match eth_price {
-                    Ok(price) => {
-                        let mut lock = oracle.write().unwrap();
-                        lock.eth_price = price;
-                        lock.last_eth_price_request = Instant::now();
-                    }
-                    Err(e) => error!("Failed to get ETH price: {:?}", e),
+                let eth_price = fetch_eth_price(client.clone(), chain_id, &mut feed_failed).await;
+
+                let mut lock = oracle.write().unwrap();
+                // keep the last known price if both sources fail this time
+                if eth_price.is_some() {
+                    lock.eth_price = eth_price;
                 }
+                lock.feed_failed = feed_failed;
+                lock.last_eth_price_request = Instant::now();
             }
         }
     }
 }
 
-async fn get_eth_price(
+/// Get the USD price of the native currency
+///
+/// Tries the ChainLink feed first and falls back to the spot price of the wrapped native / USDC V3 pool
+///
+/// `feed_failed` makes sure we only warn once about a missing feed
+async fn fetch_eth_price(
+    client: Arc<RootProvider<PubSubFrontend>>,
+    chain_id: u64,
+    feed_failed: &mut bool
+) -> Option<U256> {
+    let feed_err = match get_eth_price(client.clone(), chain_id).await {
+        Ok(price) => return Some(price),
+        Err(e) => e,
+    };
+
+    let dex_price = get_dex_eth_price(client, chain_id).await;
+
+    if !*feed_failed {
+        *feed_failed = true;
+        match &dex_price {
+            Ok(_) => warn!("ChainLink feed failed for Chain ID {}: {}, using the DEX price", chain_id, feed_err),
+            Err(e) => warn!("No USD price for Chain ID {}, ChainLink: {}, DEX: {}", chain_id, feed_err, e),
+        }
+    }
+
+    dex_price.ok()
+}
+
+/// The native currency price from the spot price of its wrapped version against USDC
+async fn get_dex_eth_price(
     client: Arc<RootProvider<PubSubFrontend>>,
     chain_id: u64
 ) -> Result<U256, anyhow::Error> {
-    let feed = match chain_id {
-        1 => ETH_USD_FEED,
-        56 => BNB_USD_FEED,
-        8453 => BASE_ETH_USD_FEED,
-        42161 => ARB_ETH_USD_FEED,
-        _ => ETH_USD_FEED,
+    let (wrapped, usdc) = match chain_id {
+        1 => (ERC20Token::eth_default_input(), ERC20Token::eth_default_output()),
+        56 => (ERC20Token::bsc_default_input(), ERC20Token::bsc_default_output()),
+        8453 => (ERC20Token::base_default_input(), ERC20Token::base_default_output()),
+        42161 => (ERC20Token::arbitrum_default_input(), ERC20Token::arbitrum_default_output()),
+        _ => return Err(ChainUnsupported::new(chain_id, ChainFeature::Dex).into()),
     };
 
+    let one = U256::from(10).pow(U256::from(wrapped.decimals));
+    let amount = get_reference_v3_quote(wrapped, usdc.clone(), one, chain_id, client, RpcOrigin::Oracle).await?;
+
+    let price = amount * U256::from(10).pow(U256::from(ETH_USD_FEED_DECIMALS))
+        / U256::from(10).pow(U256::from(usdc.decimals));
+    Ok(price)
+}
+
+/// The ChainLink USD feed of the native currency
+///
+/// The mainnet feed does not exist on other chains
+pub fn eth_usd_feed(chain_id: u64) -> Option<Address> {
+    match chain_id {
+        1 => Some(ETH_USD_FEED),
+        56 => Some(BNB_USD_FEED),
+        8453 => Some(BASE_ETH_USD_FEED),
+        ARBITRUM_CHAIN_ID => Some(ARB_ETH_USD_FEED),
+        _ => None,
+    }
+}
+
+/// Read the ChainLink USD feed of the chain
+async fn get_eth_price(
+    client: Arc<RootProvider<PubSubFrontend>>,
+    chain_id: u64
+) -> Result<U256, anyhow::Error> {
+    let feed = eth_usd_feed(chain_id).ok_or(ChainUnsupported::new(chain_id, ChainFeature::PriceFeed))?;
+
     let oracle = ChainLinkOracle::new(feed, client.clone());
+    record_rpc(RpcOrigin::Oracle, 1);
     let eth_usd = oracle.latestAnswer().call().await?._0;
 
     // convert i256 to U256
@@ -328,3 +480,46 @@ fn get_usd_value(eth_usd: U256, base_fee: U256) -> Result<f64, anyhow::Error> {
 }
 
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_per_chain() {
+        // (chain, block time, lag, stall)
+        let table = [
+            (1, 12_000, 36, 60),
+            (11155111, 12_000, 36, 60),
+            (56, 3_000, 9, 15),
+            (8453, 2_000, 6, 10),
+            (84532, 2_000, 6, 10),
+            (ARBITRUM_CHAIN_ID, 250, 5, 10),
+        ];
+
+        for (chain_id, block_ms, lag, stall) in table {
+            assert_eq!(block_time(chain_id), Duration::from_millis(block_ms), "block time of {}", chain_id);
+            assert_eq!(lag_threshold(chain_id), Duration::from_secs(lag), "lag of {}", chain_id);
+            assert_eq!(stall_threshold(chain_id), Duration::from_secs(stall), "stall of {}", chain_id);
+        }
+    }
+
+    #[test]
+    fn lag_is_only_reported_past_the_threshold() {
+        let block = BlockInfo::new(None, 100, 1_000, U256::ZERO);
+
+        assert_eq!(block.age(1_012), Some(Duration::from_secs(12)));
+        assert_eq!(block.lag(1, 1_036), None);
+        assert_eq!(block.lag(1, 1_037), Some(Duration::from_secs(37)));
+        assert_eq!(block.lag(8453, 1_007), Some(Duration::from_secs(7)));
+
+        // a clock behind the node is not a lag
+        assert_eq!(block.age(900), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn no_lag_without_a_block() {
+        assert_eq!(BlockInfo::default().age(1_000_000), None);
+        assert_eq!(BlockInfo::default().lag(1, 1_000_000), None);
+    }
+}
@@ -0,0 +1,72 @@
+use alloy::primitives::{address, Address};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use super::known_contracts::known_contract;
+
+/// The router a swap is sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwapRouter {
+    /// Uniswap Swap Router 02
+    Uniswap,
+
+    /// PancakeSwap Smart Router
+    PancakeSwap,
+
+    /// A Zeus router deployed by the user
+    Zeus(Address),
+}
+
+impl SwapRouter {
+    /// The router we use on a chain unless the user picked another one
+    pub fn default_for(chain_id: u64) -> Self {
+        match chain_id {
+            56 => Self::PancakeSwap,
+            _ => Self::Uniswap,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Uniswap => "Uniswap".to_string(),
+            Self::PancakeSwap => "PancakeSwap".to_string(),
+            Self::Zeus(_) => "Zeus Router".to_string(),
+        }
+    }
+
+    /// The address of the router on a chain, `None` if it is not deployed there
+    pub fn address(&self, chain_id: u64) -> Option<Address> {
+        match (self, chain_id) {
+            (Self::Uniswap, 1 | 42161) => Some(address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45")),
+            (Self::Uniswap, 8453) => Some(address!("2626664c2603336E57B271c5C0b26F421741e481")),
+            (Self::Uniswap, 11155111) => Some(address!("3bFA4769FB09eefC5a80d6E87c3B9C650f7Ae48E")),
+            (Self::Uniswap, 84532) => Some(address!("94cC0AaC535CCDB3C01d6787D6413C739ae12bc4")),
+            (Self::PancakeSwap, 56) => Some(address!("13f4EA83D0bd40E75C8222255bc855a974568Dd4")),
+            (Self::Zeus(address), _) => Some(*address),
+            _ => None,
+        }
+    }
+
+    /// Is this a Zeus router, its bytecode has to be deployed on the fork to simulate a swap
+    pub fn is_zeus(&self) -> bool {
+        matches!(self, Self::Zeus(_))
+    }
+
+    /// The address of the router on a chain, or why it can't be used there
+    pub fn validate(&self, chain_id: u64) -> Result<Address, anyhow::Error> {
+        let address = self
+            .address(chain_id)
+            .ok_or(anyhow!("{} is not deployed on chain {}", self.name(), chain_id))?;
+
+        if address.is_zero() {
+            return Err(anyhow!("Enter the address of your {}", self.name()));
+        }
+
+        // a Zeus router is never one of the known contracts, catch a pasted Permit2 or bridge address
+        if let (Self::Zeus(_), Some(name)) = (self, known_contract(chain_id, address)) {
+            return Err(anyhow!("{} is the {}, not a Zeus router", address, name));
+        }
+
+        Ok(address)
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// RPC calls made in this session, by the feature that triggered them
+    pub static ref RPC_USAGE: Arc<RwLock<RpcUsage>> = Arc::new(RwLock::new(RpcUsage::new()));
+}
+
+/// The feature that triggered an rpc call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcOrigin {
+    /// Native and ERC20 balances
+    Balances,
+
+    /// Swap quotes
+    Quotes,
+
+    /// The block oracle and price feeds
+    Oracle,
+
+    /// Looking up the metadata of new tokens
+    Discovery,
+
+    /// Building, simulating and sending transactions
+    Transactions,
+
+    /// Connecting to a node
+    Connection,
+}
+
+impl RpcOrigin {
+    /// All the origins in the order they are displayed
+    pub fn all() -> [RpcOrigin; 6] {
+        [
+            RpcOrigin::Balances,
+            RpcOrigin::Quotes,
+            RpcOrigin::Oracle,
+            RpcOrigin::Discovery,
+            RpcOrigin::Transactions,
+            RpcOrigin::Connection,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RpcOrigin::Balances => "Balances",
+            RpcOrigin::Quotes => "Quotes",
+            RpcOrigin::Oracle => "Oracle",
+            RpcOrigin::Discovery => "Token Discovery",
+            RpcOrigin::Transactions => "Transactions",
+            RpcOrigin::Connection => "Connection",
+        }
+    }
+}
+
+/// Counts the rpc calls per [RpcOrigin] since the session started or the last reset
+#[derive(Debug, Clone)]
+pub struct RpcUsage {
+    counts: HashMap<RpcOrigin, u64>,
+    since: Instant,
+}
+
+impl RpcUsage {
+    pub fn new() -> Self {
+        Self {
+            counts: HashMap::new(),
+            since: Instant::now(),
+        }
+    }
+
+    /// Record `calls` rpc calls made by `origin`
+    pub fn record(&mut self, origin: RpcOrigin, calls: u64) {
+        *self.counts.entry(origin).or_insert(0) += calls;
+    }
+
+    pub fn count(&self, origin: RpcOrigin) -> u64 {
+        self.counts.get(&origin).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// The count of every origin, see [RpcOrigin::all]
+    pub fn breakdown(&self) -> Vec<(RpcOrigin, u64)> {
+        RpcOrigin::all().into_iter().map(|origin| (origin, self.count(origin))).collect()
+    }
+
+    /// How long we have been counting
+    pub fn elapsed(&self) -> Duration {
+        self.since.elapsed()
+    }
+
+    /// Start counting from zero
+    pub fn reset(&mut self) {
+        self.counts.clear();
+        self.since = Instant::now();
+    }
+}
+
+impl Default for RpcUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shorthand to record calls in [RPC_USAGE]
+pub fn record_rpc(origin: RpcOrigin, calls: u64) {
+    RPC_USAGE.write().unwrap().record(origin, calls);
+}
+
+/// Paces background work to at most `per_minute` rpc calls, the budget starts full
+#[derive(Debug, Clone)]
+pub struct RpcBudget {
+    per_minute: u64,
+    available: f64,
+    updated: Instant,
+}
+
+impl RpcBudget {
+    pub fn new(per_minute: u64) -> Self {
+        Self {
+            per_minute,
+            available: per_minute as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take `calls` from the budget at `now`
+    ///
+    /// Returns how long to wait before asking again if there is not enough left, nothing is taken then
+    pub fn take(&mut self, calls: u64, now: Instant) -> Option<Duration> {
+        let per_second = self.per_minute as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * per_second).min(self.per_minute as f64);
+        self.updated = now;
+
+        // more calls than the whole budget go through once it is full
+        let calls = calls.min(self.per_minute) as f64;
+        if self.available >= calls {
+            self.available -= calls;
+            return None;
+        }
+        Some(Duration::from_secs_f64((calls - self.available) / per_second.max(f64::MIN_POSITIVE)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_budget_refills_over_time() {
+        let start = Instant::now();
+        let mut budget = RpcBudget::new(60);
+        budget.updated = start;
+
+        assert_eq!(budget.take(40, start), None);
+        assert_eq!(budget.take(20, start), None);
+        // one call per second
+        assert_eq!(budget.take(4, start), Some(Duration::from_secs(4)));
+        assert_eq!(budget.take(4, start + Duration::from_secs(4)), None);
+
+        // never more than a minute worth of calls
+        assert_eq!(budget.take(60, start + Duration::from_secs(3600)), None);
+        assert!(budget.take(1, start + Duration::from_secs(3600)).is_some());
+    }
+}
@@ -1,11 +1,16 @@
-use password_hash::{ Output, PasswordHasher, SaltString};
+use password_hash::{ PasswordHasher, SaltString};
 use argon2::{ Algorithm, Argon2, Params, Version };
 
 use aes_gcm::{ KeyInit, aead::{ Aead, generic_array::GenericArray } };
-use chacha20poly1305::{ XChaCha20Poly1305, XNonce };
+use chacha20poly1305::{ XChaCha20Poly1305, XNonce, aead::{ AeadCore, OsRng } };
 
 use sha2::{ Sha256, digest::Digest };
+use zeroize::Zeroize;
 use anyhow::anyhow;
+use tracing::warn;
+use std::{fmt, path::Path};
+
+use crate::fs::{atomic_write, backup_path, read_with_backup};
 
 /// The identifier used to find the Argon2 params that was used to encrypt the data
 pub const IDENTIFIER: &[u8] = b"params";
@@ -26,12 +31,107 @@ pub const P_COST: u32 = 8;
 /// Hash Length
 pub const HASH_LENGTH: usize = 64;
 
+/// Length of the XChaCha20Poly1305 key, taken from the start of the Argon2 hash
+const KEY_LENGTH: usize = 32;
+
+/// Length of the XChaCha20Poly1305 nonce, a random one is written in front of every ciphertext
+const NONCE_LENGTH: usize = 24;
+
+/// Hashed together with the key to get [DerivedKey::verifier]
+const VERIFIER_CONSTANT: &[u8] = b"zeus credentials verifier";
 
-/// The credentials needed to encrypt and decrypt an encrypted file
+
+/// The credentials needed to decrypt an encrypted file, typed on unlock
 #[derive(Clone, Default, Debug, PartialEq)]
-pub struct Credentials {
+pub struct UnlockCredentials {
     username: String,
     password: String,
+}
+
+
+impl UnlockCredentials {
+
+    pub fn new(username: String, password: String) -> Self {
+        Self { username, password }
+    }
+
+    /// Clear the credentials, the memory is zeroed first
+    pub fn clear(&mut self) {
+        self.username.zeroize();
+        self.password.zeroize();
+    }
+
+    /// Get a mutable reference to the username
+    pub fn user_mut(&mut self) -> &mut String {
+        &mut self.username
+    }
+
+    /// Get a mutable reference to the password
+    pub fn passwd_mut(&mut self) -> &mut String {
+        &mut self.password
+    }
+
+    /// Salt for Argon2
+    fn generate_saltstring(&self) -> Result<SaltString, anyhow::Error> {
+        let salt_array = Sha256::digest(self.username.as_bytes());
+        let salt = salt_array.to_vec();
+        let salt = salt.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let salt = SaltString::from_b64(&salt).map_err(|e| anyhow!("Failed to generate salt string {:?}", e))?;
+        Ok(salt)
+    }
+
+    /// Derive the encryption key with Argon2
+    ///
+    /// This is the slow part of encrypting or decrypting, the key is kept so the password doesn't have to be
+    pub fn derive_key(&self, params: &EncryptionParams) -> Result<DerivedKey, anyhow::Error> {
+        if self.username.is_empty() || self.password.is_empty() {
+            return Err(anyhow!("Username and Password must be provided"));
+        }
+
+        let salt = self.generate_saltstring()?;
+
+        let argon2_params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(params.hash_length))
+            .map_err(|e| anyhow!("Failed to create Argon2 params {:?}", e))?;
+        let argon2 = Argon2::new(Algorithm::default(), Version::default(), argon2_params);
+
+        let password_hash = argon2
+            .hash_password(self.password.as_bytes(), &salt)
+            .map_err(|e| anyhow!("Failed to hash password {:?}", e))?;
+        let hash = password_hash.hash.ok_or(anyhow!("Failed to get the hash output"))?;
+        if hash.len() < KEY_LENGTH {
+            return Err(anyhow!("The Argon2 hash is too short for a key"));
+        }
+
+        let mut key = [0u8; KEY_LENGTH];
+        key.copy_from_slice(&hash.as_bytes()[..KEY_LENGTH]);
+
+        // files written before the nonce was random used the SHA-256 hash of the username
+        let mut legacy_nonce = [0u8; NONCE_LENGTH];
+        legacy_nonce.copy_from_slice(&Sha256::digest(self.username.as_bytes())[..NONCE_LENGTH]);
+
+        Ok(DerivedKey {
+            verifier: verifier(&key),
+            key,
+            legacy_nonce,
+            params: params.clone(),
+        })
+    }
+
+}
+
+impl Drop for UnlockCredentials {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+
+/// The credentials new data is encrypted with, typed when creating a profile or changing its password
+///
+/// The password has to be typed twice, see [UnlockCredentials] for the ones typed on unlock
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Credentials {
+    unlock: UnlockCredentials,
     confirm_password: String,
 }
 
@@ -40,27 +140,25 @@ impl Credentials {
 
     pub fn new(username: String, password: String, confirm_password: String) -> Self {
         Self {
-            username,
-            password,
+            unlock: UnlockCredentials::new(username, password),
             confirm_password,
         }
     }
 
-    /// Clear the credentials
+    /// Clear the credentials, the memory is zeroed first
     pub fn clear(&mut self) {
-        self.username.clear();
-        self.password.clear();
-        self.confirm_password.clear();
+        self.unlock.clear();
+        self.confirm_password.zeroize();
     }
 
     /// Get a mutable reference to the username
     pub fn user_mut(&mut self) -> &mut String {
-        &mut self.username
+        self.unlock.user_mut()
     }
 
     /// Get a mutable reference to the password
     pub fn passwd_mut(&mut self) -> &mut String {
-        &mut self.password
+        self.unlock.passwd_mut()
     }
 
     /// Get a mutable reference to the confirm password
@@ -68,26 +166,18 @@ impl Credentials {
         &mut self.confirm_password
     }
 
-    /// Copy password to confirm password
-    pub fn copy_passwd_to_confirm(&mut self) {
-        self.confirm_password = self.password.clone();
-    }
-
-    /// Salt for Argon2
-    fn generate_saltstring(&self) -> Result<SaltString, anyhow::Error> {
-        let salt_array = Sha256::digest(self.username.as_bytes());
-        let salt = salt_array.to_vec();
-        let salt = salt.iter().map(|b| format!("{:02x}", b)).collect::<String>();
-        let salt = SaltString::from_b64(&salt).map_err(|e| anyhow!("Failed to generate salt string {:?}", e))?;
-        Ok(salt)
+    /// Derive the encryption key once the password was confirmed, see [UnlockCredentials::derive_key]
+    pub fn derive_key(&self, params: &EncryptionParams) -> Result<DerivedKey, anyhow::Error> {
+        self.is_valid()?;
+        self.unlock.derive_key(params)
     }
 
     fn is_valid(&self) -> Result<(), anyhow::Error> {
-        if self.username.is_empty() || self.password.is_empty() || self.confirm_password.is_empty() {
+        if self.unlock.username.is_empty() || self.unlock.password.is_empty() || self.confirm_password.is_empty() {
             return Err(anyhow!("Username and Password must be provided"));
         }
 
-        if self.password != self.confirm_password {
+        if self.unlock.password != self.confirm_password {
             return Err(anyhow!("Passwords do not match"));
         }
 
@@ -95,6 +185,79 @@ impl Credentials {
     }
 }
 
+impl Drop for Credentials {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+
+/// The key derived from the credentials, see [UnlockCredentials::derive_key]
+///
+/// An unlocked profile keeps this instead of the credentials, it is zeroed on drop
+#[derive(Clone)]
+pub struct DerivedKey {
+    key: [u8; KEY_LENGTH],
+
+    /// The nonce of the files written before it was random, only used to decrypt them
+    legacy_nonce: [u8; NONCE_LENGTH],
+
+    /// A hash of the key, used to confirm the credentials again without decrypting the file
+    verifier: [u8; 32],
+
+    /// The Argon2 params the key was derived with, written next to the encrypted data
+    params: EncryptionParams,
+}
+
+impl DerivedKey {
+    /// Check that `credentials` derive this same key
+    ///
+    /// This runs Argon2 again but never touches the encrypted file
+    pub fn verify(&self, credentials: &UnlockCredentials) -> Result<(), anyhow::Error> {
+        let other = credentials.derive_key(&self.params)?;
+        let diff = self.verifier.iter().zip(other.verifier.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return Err(anyhow!("Invalid credentials"));
+        }
+        Ok(())
+    }
+
+    pub fn params(&self) -> &EncryptionParams {
+        &self.params
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(GenericArray::from_slice(&self.key))
+    }
+}
+
+impl Drop for DerivedKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.legacy_nonce.zeroize();
+        self.verifier.zeroize();
+    }
+}
+
+impl fmt::Debug for DerivedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedKey").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for DerivedKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.verifier == other.verifier
+    }
+}
+
+fn verifier(key: &[u8; KEY_LENGTH]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(VERIFIER_CONSTANT);
+    hasher.update(key);
+    hasher.finalize().into()
+}
+
 
 /// Encrypts the given data using the provided credentials
 /// 
@@ -107,7 +270,16 @@ impl Credentials {
 /// The encrypted data file is written in the same directory as the executable
 pub fn encrypt_data(file: &str, data: Vec<u8>, credentials: Credentials) -> Result<(), anyhow::Error> {
     let encrypted = encrypt(credentials, data)?;
+    write_encrypted(file, encrypted)
+}
 
+/// Same as [encrypt_data] but with an already derived key, Argon2 is not run again
+pub fn encrypt_data_with_key(file: &str, data: Vec<u8>, key: &DerivedKey) -> Result<(), anyhow::Error> {
+    let encrypted = encrypt_with_key(key, data)?;
+    write_encrypted(file, encrypted)
+}
+
+fn write_encrypted(file: &str, encrypted: EncryptionResult) -> Result<(), anyhow::Error> {
     let params_with_identifier = [IDENTIFIER, encrypted.params.to_vec().as_slice()].concat();
 
     let encrypted_data_with_params = [
@@ -115,7 +287,7 @@ pub fn encrypt_data(file: &str, data: Vec<u8>, credentials: Credentials) -> Resu
         params_with_identifier.as_slice(),
     ].concat();
 
-    std::fs::write(file, encrypted_data_with_params)?;
+    atomic_write(file, encrypted_data_with_params)?;
     Ok(())
 }
 
@@ -133,15 +305,27 @@ pub fn encrypt_data(file: &str, data: Vec<u8>, credentials: Credentials) -> Resu
 /// The decrypted data as a `Vec<u8>`
 /// 
 /// The decrypted data stays in memory and is not written to disk
-pub fn decrypt_data(file: &str, credentials: Credentials) -> Result<Vec<u8>, anyhow::Error> {
-    let data = std::fs::read(file)?;
-    let decrypted_data = decrypt(credentials, data)?;
+pub fn decrypt_data(file: &str, credentials: UnlockCredentials) -> Result<Vec<u8>, anyhow::Error> {
+    let (decrypted_data, _) = decrypt_data_or_backup(file, credentials)?;
     Ok(decrypted_data)
 }
 
+/// Same as [decrypt_data] but also returns whether the data was recovered from the `.bak` file
+/// 
+/// The backup is only used if the `file` is missing or cannot be decrypted
+pub fn decrypt_data_or_backup(file: &str, credentials: UnlockCredentials) -> Result<(Vec<u8>, bool), anyhow::Error> {
+    read_with_backup(file, |data| decrypt(credentials.clone(), data))
+}
+
+/// Same as [decrypt_data_or_backup] but also returns the [DerivedKey], so the data can be saved again without the credentials
+pub fn unlock_data_or_backup(file: &str, credentials: &UnlockCredentials) -> Result<(Vec<u8>, DerivedKey, bool), anyhow::Error> {
+    let ((data, key), from_backup) = read_with_backup(file, |data| unlock(credentials, data))?;
+    Ok((data, key, from_backup))
+}
+
 
 pub struct EncryptionResult {
-    /// The encrypted data
+    /// The random nonce followed by the encrypted data
     pub data: Vec<u8>,
 
     /// Argon2 Params used for the encryption
@@ -149,7 +333,7 @@ pub struct EncryptionResult {
 }
 
 /// The parameters used to encrypt the data
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct EncryptionParams {
     pub m_cost: u32,
     pub t_cost: u32,
@@ -157,6 +341,18 @@ pub struct EncryptionParams {
     pub hash_length: usize,
 }
 
+impl Default for EncryptionParams {
+    /// The params new data is encrypted with
+    fn default() -> Self {
+        Self {
+            m_cost: M_COST,
+            t_cost: T_COST,
+            p_cost: P_COST,
+            hash_length: HASH_LENGTH,
+        }
+    }
+}
+
 impl EncryptionParams {
     pub fn new(argon2: Argon2) -> Result<Self, anyhow::Error> {
         let hash_lenght = argon2.params().output_len();
@@ -207,54 +403,35 @@ impl EncryptionParams {
 
 /// Encrypts the given data using the provided credentials
 pub fn encrypt(credentials: Credentials, data: Vec<u8>) -> Result<EncryptionResult, anyhow::Error> {
-    credentials.is_valid()?;
-
-    // generate a salt needed for the password hashing
-    let salt = credentials.generate_saltstring()?;
-
-    // set the argon2 parameters
-    let params = match Params::new(M_COST, T_COST, P_COST, Some(HASH_LENGTH)) {
-        Ok(params) => params,
-        Err(e) => {
-            return Err(anyhow::Error::msg(format!("{:?}", e)));
-        }
-    };
-
-    let argon2 = Argon2::new(Algorithm::default(), Version::default(), params.clone());
-
-    // hash the password
-    let password_hash = match argon2.hash_password(credentials.password.as_bytes(), &salt) {
-        Ok(hash) => hash,
-        Err(e) => {
-            return Err(anyhow!("Failed to hash password {:?}", e));
-        }
-    };
-
-    // get the hash output
-    let key = password_hash.hash.ok_or(anyhow!("Failed to get the hash output"))?;
-
-    // create the cipher using the hashed password as the key
-    let cipher = xchacha20_poly_1305(key);
+    let key = credentials.derive_key(&EncryptionParams::default())?;
+    encrypt_with_key(&key, data)
+}
 
-    // use the SHA-256 hash of the username as the nonce
-    // ! usually this is a random value but since the username is not saved anywhere it should be safe
-    let hash = Sha256::digest(credentials.username.as_bytes());
-    let nonce = XNonce::from_slice(&hash.as_slice()[..24]);
+/// Encrypts the given data with an already derived key
+///
+/// Every call uses a new random nonce, so the same data never gives the same ciphertext
+pub fn encrypt_with_key(key: &DerivedKey, data: Vec<u8>) -> Result<EncryptionResult, anyhow::Error> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
 
-    let encrypted_data = cipher
-        .encrypt(nonce, data.as_ref())
+    let encrypted_data = key
+        .cipher()
+        .encrypt(&nonce, data.as_ref())
         .map_err(|e| anyhow!("Failed to encrypt data {:?}", e))?;
 
     Ok(EncryptionResult {
-        data: encrypted_data,
-        params: EncryptionParams::new(argon2)?,
+        data: [nonce.as_slice(), encrypted_data.as_slice()].concat(),
+        params: key.params.clone(),
     })
 }
 
 /// Decrypts the given data using the provided credentials
-pub fn decrypt(credentials: Credentials, data: Vec<u8>) -> Result<Vec<u8>, anyhow::Error> {
-    credentials.is_valid()?;
+pub fn decrypt(credentials: UnlockCredentials, data: Vec<u8>) -> Result<Vec<u8>, anyhow::Error> {
+    let (decrypted_data, _) = unlock(&credentials, data)?;
+    Ok(decrypted_data)
+}
 
+/// Decrypts the given data and returns the key it was encrypted with
+pub fn unlock(credentials: &UnlockCredentials, data: Vec<u8>) -> Result<(Vec<u8>, DerivedKey), anyhow::Error> {
     // find the argon2 params in the encrypted data
     let identifier_position = find_identifier_position(&data, IDENTIFIER).ok_or(
         anyhow!("Failed to find the identifier in the encrypted data")
@@ -262,53 +439,253 @@ pub fn decrypt(credentials: Credentials, data: Vec<u8>) -> Result<Vec<u8>, anyho
 
     // get the argon2 params from the encrypted data
     let (encrypted_data, identifier_data) = data.split_at(identifier_position);
-    let params = &identifier_data[IDENTIFIER.len()..];
-
-
-    // Parse the encryption params
-    let argon2_params = EncryptionParams::from_u8(params)?;
+    let params = EncryptionParams::from_u8(&identifier_data[IDENTIFIER.len()..])?;
 
+    let key = credentials.derive_key(&params)?;
+    let decrypted_data = decrypt_with_key(&key, encrypted_data)?;
 
-    let params = Params::new(
-        argon2_params.m_cost,
-        argon2_params.t_cost,
-        argon2_params.p_cost,
-        Some(argon2_params.hash_length)
-    ).map_err(|e| anyhow!("Failed to create Argon2 params {:?}", e))?;
-
-    // create the argon2 instance used
-    let argon2 = Argon2::new(Algorithm::default(), Version::default(), params.clone());
+    Ok((decrypted_data, key))
+}
 
-    // generate the salt needed for the password hashing
-    let salt = credentials.generate_saltstring()?;
+/// Decrypts the given data, without the trailing params, with an already derived key
+///
+/// Data written before the nonce was random has no nonce in front, it is decrypted with [DerivedKey::legacy_nonce]
+pub fn decrypt_with_key(key: &DerivedKey, encrypted_data: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    let cipher = key.cipher();
 
-    // hash the password
-    let password_hash = argon2
-        .hash_password(credentials.password.as_bytes(), &salt)
-        .map_err(|e| anyhow!("Failed to hash password {:?}", e))?;
+    if encrypted_data.len() >= NONCE_LENGTH + TAG_LEN {
+        let (nonce, ciphertext) = encrypted_data.split_at(NONCE_LENGTH);
+        if let Ok(data) = cipher.decrypt(XNonce::from_slice(nonce), ciphertext) {
+            return Ok(data);
+        }
+    }
 
-    // get the hash output
-    let key = password_hash.hash.ok_or(anyhow!("Failed to get the hash output"))?;
+    // the tag is checked, a wrong key fails here too
+    cipher
+        .decrypt(XNonce::from_slice(&key.legacy_nonce), encrypted_data)
+        .map_err(|e| anyhow!("Failed to decrypt data {:?}", e))
+}
 
-    // create the cipher using the hashed password as the key
-    let cipher = xchacha20_poly_1305(key);
+/// The size of the Poly1305 tag at the end of the ciphertext
+const TAG_LEN: usize = 16;
 
-    let hash = Sha256::digest(credentials.username.as_bytes());
-    let nonce = XNonce::from_slice(&hash.as_slice()[..24]);
+/// The size of [EncryptionParams::to_vec]
+const PARAMS_LEN: usize = 20;
 
-    let decrypted_data = cipher
-        .decrypt(nonce, encrypted_data)
-        .map_err(|e| anyhow!("Failed to decrypt data {:?}", e))?;
+/// The smallest file [encrypt_data] can write, anything shorter was truncated
+pub const MIN_ENCRYPTED_LEN: usize = NONCE_LENGTH + TAG_LEN + IDENTIFIER.len() + PARAMS_LEN;
 
-    Ok(decrypted_data)
-}
+/// Does `file` or its `.bak` hold something that may be encrypted data
+///
+/// An empty or truncated file, eg. from a crash during a save, can never be unlocked and is ignored
+pub fn encrypted_file_exists<P: AsRef<Path>>(file: P) -> bool {
+    let usable = |path: &Path| std::fs::metadata(path).is_ok_and(|meta| meta.len() >= MIN_ENCRYPTED_LEN as u64);
+    let file = file.as_ref();
 
-fn xchacha20_poly_1305(key: Output) -> XChaCha20Poly1305 {
-    let key = GenericArray::from_slice(&key.as_bytes()[..32]);
-    XChaCha20Poly1305::new(key)
+    if usable(file) {
+        return true;
+    }
+    if file.exists() {
+        warn!("{:?} is empty or truncated, ignoring it", file);
+    }
+    usable(&backup_path(file))
 }
 
 /// Finds the position of the [IDENTIFIER] in the encrypted data
 fn find_identifier_position(data: &[u8], identifier: &[u8]) -> Option<usize> {
     data.windows(identifier.len()).rposition(|window| window == identifier)
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Argon2 with the default params takes seconds in a debug build
+    pub(crate) fn fast_params() -> EncryptionParams {
+        EncryptionParams {
+            m_cost: 8,
+            t_cost: 1,
+            p_cost: 1,
+            hash_length: HASH_LENGTH,
+        }
+    }
+
+    fn credentials(username: &str, password: &str) -> UnlockCredentials {
+        UnlockCredentials::new(username.to_string(), password.to_string())
+    }
+
+    /// The encrypted data followed by the params, like [encrypt_data] writes it
+    fn file_contents(encrypted: EncryptionResult) -> Vec<u8> {
+        [encrypted.data.as_slice(), IDENTIFIER, encrypted.params.to_vec().as_slice()].concat()
+    }
+
+    #[test]
+    fn unlock_returns_the_key_the_data_was_encrypted_with() {
+        let credentials = credentials("user", "password");
+        let key = credentials.derive_key(&fast_params()).unwrap();
+        let encrypted = encrypt_with_key(&key, b"wallets".to_vec()).unwrap();
+
+        let (data, unlocked) = unlock(&credentials, file_contents(encrypted)).unwrap();
+
+        assert_eq!(data, b"wallets");
+        assert_eq!(unlocked, key);
+        assert_eq!(unlocked.params(), &fast_params());
+    }
+
+    #[test]
+    fn unlock_rejects_a_wrong_password() {
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+        let encrypted = encrypt_with_key(&key, b"wallets".to_vec()).unwrap();
+
+        assert!(unlock(&credentials("user", "wrong"), file_contents(encrypted)).is_err());
+    }
+
+    #[test]
+    fn encrypted_data_round_trips() {
+        let credentials = credentials("user", "password");
+        let key = credentials.derive_key(&fast_params()).unwrap();
+
+        for data in [Vec::new(), b"wallets".to_vec(), vec![0xAB; 4096]] {
+            let encrypted = encrypt_with_key(&key, data.clone()).unwrap();
+            assert_eq!(decrypt(credentials.clone(), file_contents(encrypted)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn wrong_credentials_fail_with_an_error() {
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+        let contents = file_contents(encrypt_with_key(&key, b"wallets".to_vec()).unwrap());
+
+        let err = decrypt(credentials("user", "wrong"), contents.clone()).unwrap_err();
+        assert!(err.to_string().contains("Failed to decrypt data"), "{}", err);
+
+        let err = decrypt(credentials("other", "password"), contents).unwrap_err();
+        assert!(err.to_string().contains("Failed to decrypt data"), "{}", err);
+    }
+
+    #[test]
+    fn the_same_data_never_gives_the_same_ciphertext() {
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+
+        let first = encrypt_with_key(&key, b"wallets".to_vec()).unwrap().data;
+        let second = encrypt_with_key(&key, b"wallets".to_vec()).unwrap().data;
+
+        assert_ne!(first[..NONCE_LENGTH], second[..NONCE_LENGTH]);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn data_with_the_username_nonce_still_decrypts() {
+        let credentials = credentials("user", "password");
+        let key = credentials.derive_key(&fast_params()).unwrap();
+
+        // the layout before the nonce was random, no nonce in front of the ciphertext
+        let ciphertext = key
+            .cipher()
+            .encrypt(XNonce::from_slice(&key.legacy_nonce), b"wallets".as_ref())
+            .unwrap();
+        let legacy = EncryptionResult { data: ciphertext, params: fast_params() };
+
+        assert_eq!(decrypt(credentials.clone(), file_contents(legacy)).unwrap(), b"wallets");
+    }
+
+    #[test]
+    fn decrypt_data_round_trips_through_the_file() {
+        let dir = temp_dir("round-trip");
+        let file = dir.join("profile.data");
+        let file = file.to_str().unwrap();
+
+        // encrypt_data with the fast params, decrypt_data reads them from the file
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+        write_encrypted(file, encrypt_with_key(&key, b"wallets".to_vec()).unwrap()).unwrap();
+        assert_eq!(decrypt_data(file, credentials("user", "password")).unwrap(), b"wallets");
+        assert!(decrypt_data(file, credentials("user", "wrong")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn verify_checks_the_credentials_against_the_key() {
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+
+        assert!(key.verify(&credentials("user", "password")).is_ok());
+        assert!(key.verify(&credentials("user", "wrong")).is_err());
+        assert!(key.verify(&credentials("other", "password")).is_err());
+    }
+
+    #[test]
+    fn clear_empties_the_credentials() {
+        let mut credentials = credentials("user", "password");
+        credentials.clear();
+        assert_eq!(credentials, UnlockCredentials::default());
+
+        let mut new = Credentials::new("user".to_string(), "password".to_string(), "password".to_string());
+        new.clear();
+        assert_eq!(new, Credentials::default());
+    }
+
+    #[test]
+    fn new_credentials_need_a_matching_confirmation() {
+        let new = |password: &str, confirm: &str| Credentials::new("user".to_string(), password.to_string(), confirm.to_string());
+
+        let err = new("password", "pasword").derive_key(&fast_params()).unwrap_err();
+        assert!(err.to_string().contains("do not match"), "{}", err);
+        assert!(new("password", "").derive_key(&fast_params()).is_err());
+
+        // the confirmed credentials derive the key the unlock credentials find again
+        let key = new("password", "password").derive_key(&fast_params()).unwrap();
+        assert!(key.verify(&credentials("user", "password")).is_ok());
+    }
+
+    #[test]
+    fn unlock_credentials_need_a_username_and_a_password() {
+        assert!(credentials("user", "").derive_key(&fast_params()).is_err());
+        assert!(credentials("", "password").derive_key(&fast_params()).is_err());
+        assert!(credentials("user", "password").derive_key(&fast_params()).is_ok());
+    }
+
+    /// A fresh directory for the file tests
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("zeus-core-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn empty_or_truncated_file_is_not_an_encrypted_file() {
+        let dir = temp_dir("empty-profile");
+        let file = dir.join("profile.data");
+        assert!(!encrypted_file_exists(&file));
+
+        std::fs::write(&file, b"").unwrap();
+        assert!(!encrypted_file_exists(&file));
+
+        std::fs::write(&file, b"trunc").unwrap();
+        assert!(!encrypted_file_exists(&file));
+
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+        let encrypted = encrypt_with_key(&key, Vec::new()).unwrap();
+        let contents = file_contents(encrypted);
+        assert_eq!(contents.len(), MIN_ENCRYPTED_LEN);
+
+        std::fs::write(&file, &contents).unwrap();
+        assert!(encrypted_file_exists(&file));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn empty_file_with_a_backup_is_an_encrypted_file() {
+        let dir = temp_dir("empty-profile-backup");
+        let file = dir.join("profile.data");
+
+        let key = credentials("user", "password").derive_key(&fast_params()).unwrap();
+        let encrypted = encrypt_with_key(&key, b"wallets".to_vec()).unwrap();
+        std::fs::write(&file, b"").unwrap();
+        std::fs::write(backup_path(&file), file_contents(encrypted)).unwrap();
+
+        assert!(encrypted_file_exists(&file));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use tracing::{info, warn};
+
+/// The rpc endpoints of each chain
+pub const RPC_FILE: &str = "rpc.json";
+
+/// The user preferences
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// The per-chain EVM spec overrides, only edited by hand
+pub const CHAINS_FILE: &str = "chains.json";
+
+/// The files Zeus keeps in its data directory, each one may also have a `.bak`
+pub const DATA_FILES: &[&str] = &["profile.data", RPC_FILE, SETTINGS_FILE, CHAINS_FILE];
+
+/// The directory of the databases, inside the data directory
+pub const DB_DIR: &str = "db";
+
+/// The backup file of `path` (eg. `profile.data.bak`)
+pub fn backup_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// The temp file we write to before renaming it over `path`
+fn temp_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// Write `contents` to `path` without ever leaving a truncated file behind
+///
+/// The data is written to a temp file in the same directory, synced to disk and then renamed over `path`
+///
+/// The previous version of `path` is kept as a `.bak` file
+pub fn atomic_write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<(), anyhow::Error> {
+    let path = path.as_ref();
+    let tmp = write_temp(path, contents.as_ref())?;
+    replace_with_temp(path, &tmp)
+}
+
+/// Write and sync the temp file of `path`, `path` itself is not touched
+fn write_temp(path: &Path, contents: &[u8]) -> Result<PathBuf, anyhow::Error> {
+    let tmp = temp_path(path);
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(tmp)
+}
+
+/// Keep the current `path` as a `.bak` and rename `tmp` over it
+fn replace_with_temp(path: &Path, tmp: &Path) -> Result<(), anyhow::Error> {
+    if path.exists() {
+        std::fs::copy(path, backup_path(path))?;
+    }
+
+    std::fs::rename(tmp, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// Read and parse `path`, if that fails try the `.bak` file instead
+///
+/// Returns the parsed value and whether it was recovered from the backup
+///
+/// If both fail the error of the primary file is returned
+pub fn read_with_backup<P, T, F>(path: P, parse: F) -> Result<(T, bool), anyhow::Error>
+where
+    P: AsRef<Path>,
+    F: Fn(Vec<u8>) -> Result<T, anyhow::Error>,
+{
+    let path = path.as_ref();
+    let primary = std::fs::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(&parse);
+
+    let err = match primary {
+        Ok(value) => return Ok((value, false)),
+        Err(e) => e,
+    };
+
+    let backup = backup_path(path);
+    if !backup.exists() {
+        return Err(err);
+    }
+
+    match std::fs::read(&backup).map_err(anyhow::Error::from).and_then(&parse) {
+        Ok(value) => {
+            warn!("Failed to load {:?}: {}, restored from backup", path, err);
+            Ok((value, true))
+        }
+        Err(_) => Err(err),
+    }
+}
+
+/// Can we create files in `dir`
+///
+/// A read-only filesystem or missing permissions both make this `false`
+pub fn is_writable<P: AsRef<Path>>(dir: P) -> bool {
+    let probe = dir.as_ref().join(".zeus_write_test");
+    match File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Copy the data files and the databases of the current directory to `to` and make it the current directory
+///
+/// `to` is created if needed, it must be writable and must not already hold a profile
+pub fn migrate_data_dir<P: AsRef<Path>>(to: P) -> Result<(), anyhow::Error> {
+    let to = to.as_ref();
+    std::fs::create_dir_all(to)?;
+    if !is_writable(to) {
+        return Err(anyhow!("{:?} is not writable", to));
+    }
+    if to.join("profile.data").exists() {
+        return Err(anyhow!("{:?} already has a profile, it would be overwritten", to));
+    }
+
+    for file in DATA_FILES {
+        for path in [PathBuf::from(file), backup_path(file)] {
+            if path.exists() {
+                std::fs::copy(&path, to.join(&path))?;
+            }
+        }
+    }
+
+    let db = Path::new(DB_DIR);
+    if db.is_dir() {
+        std::fs::create_dir_all(to.join(DB_DIR))?;
+        for entry in std::fs::read_dir(db)? {
+            let path = entry?.path();
+            if path.is_file() {
+                std::fs::copy(&path, to.join(&path))?;
+            }
+        }
+    }
+
+    std::env::set_current_dir(to)?;
+    info!("Data directory moved to {:?}", to);
+    Ok(())
+}
+
+/// Make sure the rename is persisted
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zeus-core-fs-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> Vec<u8> {
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn atomic_write_keeps_the_previous_version_as_backup() {
+        let dir = temp_dir("backup");
+        let path = dir.join("rpc.json");
+
+        atomic_write(&path, b"first").unwrap();
+        assert!(!backup_path(&path).exists());
+
+        atomic_write(&path, b"second").unwrap();
+        assert_eq!(read(&path), b"second");
+        assert_eq!(read(&backup_path(&path)), b"first");
+        assert!(!temp_path(&path).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn write_interrupted_before_rename_leaves_the_file_intact() {
+        let dir = temp_dir("interrupted");
+        let path = dir.join("profile.data");
+        atomic_write(&path, b"saved").unwrap();
+
+        // crash after the temp file was written, before it replaced the file
+        let tmp = write_temp(&path, b"half writ").unwrap();
+        assert!(tmp.exists());
+        assert_eq!(read(&path), b"saved");
+
+        let (data, from_backup) = read_with_backup(&path, Ok).unwrap();
+        assert_eq!(data, b"saved");
+        assert!(!from_backup);
+
+        // the leftover temp file is overwritten by the next save
+        atomic_write(&path, b"saved again").unwrap();
+        assert_eq!(read(&path), b"saved again");
+        assert_eq!(read(&backup_path(&path)), b"saved");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_with_backup_falls_back_when_the_file_is_corrupted() {
+        let dir = temp_dir("corrupted");
+        let path = dir.join("settings.json");
+        atomic_write(&path, b"good").unwrap();
+        atomic_write(&path, b"bad").unwrap();
+
+        let parse = |data: Vec<u8>| if data == b"good" { Ok(data) } else { Err(anyhow::anyhow!("corrupted")) };
+        let (data, from_backup) = read_with_backup(&path, parse).unwrap();
+        assert_eq!(data, b"good");
+        assert!(from_backup);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_truncated_file_is_recovered_from_the_backup() {
+        let dir = temp_dir("truncated");
+        let path = dir.join("settings.json");
+        atomic_write(&path, br#"{"dry_run": false}"#).unwrap();
+        atomic_write(&path, br#"{"dry_run": true}"#).unwrap();
+
+        // what an in-place write leaves behind when it crashes halfway
+        std::fs::write(&path, br#"{"dry_r"#).unwrap();
+
+        let parse = |data: Vec<u8>| Ok(serde_json::from_slice::<serde_json::Value>(&data)?);
+        let (settings, from_backup) = read_with_backup(&path, parse).unwrap();
+        assert_eq!(settings["dry_run"], false);
+        assert!(from_backup);
+
+        // without a backup the parse error of the file is returned
+        std::fs::remove_file(backup_path(&path)).unwrap();
+        let err = read_with_backup(&path, parse).unwrap_err();
+        assert!(err.to_string().contains("EOF"), "{}", err);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::process::Command;
+use anyhow::anyhow;
+
+/// The lock file, relative to the working directory which is the data directory next to `profile.data`
+pub const LOCK_FILE: &str = "zeus.lock";
+
+
+/// Result of trying to acquire the [InstanceLock]
+pub enum LockStatus {
+    /// We are the only running instance
+    Acquired(InstanceLock),
+
+    /// Another live instance holds the lock
+    HeldBy(u32),
+}
+
+/// Prevents two Zeus processes from writing to the same `profile.data` and database files
+///
+/// The lock file holds the PID of the process that owns it
+///
+/// The lock is released when this is dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl InstanceLock {
+    /// Try to acquire the lock at the default [LOCK_FILE]
+    pub fn acquire() -> Result<LockStatus, anyhow::Error> {
+        Self::acquire_at(LOCK_FILE)
+    }
+
+    /// Try to acquire the lock at the given path
+    ///
+    /// If the lock file belongs to a process that is no longer running it is considered stale and it is reclaimed
+    pub fn acquire_at<P: AsRef<Path>>(path: P) -> Result<LockStatus, anyhow::Error> {
+        let path = path.as_ref().to_path_buf();
+        let pid = std::process::id();
+
+        // at most 2 attempts, the second one after removing a stale lock
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(pid.to_string().as_bytes())?;
+                    file.sync_all()?;
+                    return Ok(LockStatus::Acquired(Self { path, pid }));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let holder = std::fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|s| s.trim().parse::<u32>().ok());
+
+                    if let Some(holder) = holder {
+                        if holder != pid && is_process_alive(holder) {
+                            return Ok(LockStatus::HeldBy(holder));
+                        }
+                    }
+
+                    // stale lock (crashed process or unreadable file), reclaim it
+                    std::fs::remove_file(&path)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(anyhow!("Failed to acquire the instance lock at {:?}", path))
+    }
+
+    /// The PID written in the lock file
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // only remove the file if it still belongs to us
+        let owner = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u32>().ok());
+
+        if owner == Some(self.pid) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Check if a process with the given PID is running
+///
+/// Signal 0 only checks the process, `EPERM` means it is running as another user
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    // 0 and negative PIDs signal whole process groups
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid == 0 {
+        return false;
+    }
+
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Check if a process with the given PID is running
+#[cfg(windows)]
+fn is_process_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(false)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn lock_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("zeus-lock-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn a_lock_of_a_dead_process_is_reclaimed() {
+        let path = lock_path("stale");
+        // a child that already exited and was reaped, its pid is no longer running
+        let dead = Command::new("true").spawn().unwrap();
+        let dead_pid = dead.id();
+        dead.wait_with_output().unwrap();
+        std::fs::write(&path, dead_pid.to_string()).unwrap();
+
+        let LockStatus::Acquired(lock) = InstanceLock::acquire_at(&path).unwrap() else {
+            panic!("the stale lock was not reclaimed");
+        };
+        assert_eq!(lock.pid(), std::process::id());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), std::process::id().to_string());
+
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn a_lock_of_a_live_process_is_refused() {
+        let path = lock_path("live");
+        let mut live = Command::new("sleep").arg("30").spawn().unwrap();
+        std::fs::write(&path, live.id().to_string()).unwrap();
+
+        let status = InstanceLock::acquire_at(&path).unwrap();
+        live.kill().unwrap();
+        live.wait().unwrap();
+
+        assert!(matches!(status, LockStatus::HeldBy(pid) if pid == live.id()));
+        // the lock of the other instance is left alone
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), live.id().to_string());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn init_is_alive_even_if_we_may_not_signal_it() {
+        // as another user than root, signalling init fails with EPERM
+        assert!(is_process_alive(1));
+        assert!(!is_process_alive(0));
+        assert!(!is_process_alive(u32::MAX));
+    }
+}
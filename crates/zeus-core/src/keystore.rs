@@ -0,0 +1,455 @@
+//! Web3 Secret Storage (keystore v3) files, the password protected key files most wallets import and export
+//!
+//! New keystores use scrypt and AES-128-CTR, keystores using PBKDF2 can be decrypted too
+
+use aes::Aes128;
+use alloy::primitives::{hex, keccak256, Address};
+use anyhow::anyhow;
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+/// The only version of the format we read and write
+const VERSION: u64 = 3;
+
+/// Length of the key derived from the password, the first half encrypts and the second half goes in the MAC
+const DK_LEN: usize = 32;
+
+/// The most memory (`128 * r * n` bytes) a keystore may ask scrypt to use, a larger one is rejected
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+
+/// The most PBKDF2 rounds a keystore may ask for, 16 times what geth writes
+const MAX_PBKDF2_ROUNDS: u32 = 1 << 22;
+
+/// The scrypt params new keystores are written with, the same as most wallets use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScryptParams {
+    pub n: u32,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        Self { n: 1 << 18, r: 8, p: 1 }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+
+    // some wallets write `Crypto`
+    #[serde(alias = "Crypto")]
+    crypto: CryptoJson,
+
+    id: String,
+    version: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CryptoJson {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: Value,
+    mac: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScryptJson {
+    dklen: usize,
+    n: u32,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Deserialize)]
+struct Pbkdf2Json {
+    c: u32,
+    dklen: usize,
+    prf: String,
+    salt: String,
+}
+
+/// Encrypt `key` with `password` into keystore JSON, `address` is the address of the key
+pub fn encrypt_key(key: &[u8], address: Address, password: &str) -> Result<String, anyhow::Error> {
+    let mut salt = [0u8; 32];
+    let mut iv = [0u8; 16];
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut iv);
+    OsRng.fill_bytes(&mut id);
+
+    encrypt_key_with(key, address, password, ScryptParams::default(), salt, iv, id)
+}
+
+/// [encrypt_key] with the given params and random values
+fn encrypt_key_with(
+    key: &[u8],
+    address: Address,
+    password: &str,
+    params: ScryptParams,
+    salt: [u8; 32],
+    iv: [u8; 16],
+    id: [u8; 16],
+) -> Result<String, anyhow::Error> {
+    if password.is_empty() {
+        return Err(anyhow!("The keystore password must not be empty"));
+    }
+
+    let mut dk = scrypt(password.as_bytes(), &salt, params, DK_LEN)?;
+    let mut ciphertext = key.to_vec();
+    Aes128Ctr::new(dk[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+    let mac = keccak256([&dk[16..32], ciphertext.as_slice()].concat());
+    dk.zeroize();
+
+    let file = KeystoreFile {
+        address: Some(hex::encode(address)),
+        crypto: CryptoJson {
+            cipher: "aes-128-ctr".to_string(),
+            cipherparams: CipherParams { iv: hex::encode(iv) },
+            ciphertext: hex::encode(&ciphertext),
+            kdf: "scrypt".to_string(),
+            kdfparams: serde_json::to_value(ScryptJson {
+                dklen: DK_LEN,
+                n: params.n,
+                r: params.r,
+                p: params.p,
+                salt: hex::encode(salt),
+            })?,
+            mac: hex::encode(mac),
+        },
+        id: uuid_v4(id),
+        version: VERSION,
+    };
+
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// Decrypt the key of keystore `json` with `password`
+///
+/// A wrong password fails on the MAC check, the returned key should be zeroed once used
+pub fn decrypt_key(json: &str, password: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let file: KeystoreFile = serde_json::from_str(json).map_err(|e| anyhow!("Not a keystore file: {}", e))?;
+    if file.version != VERSION {
+        return Err(anyhow!("Unsupported keystore version {}", file.version));
+    }
+
+    let crypto = file.crypto;
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(anyhow!("Unsupported keystore cipher {}", crypto.cipher));
+    }
+
+    let mut dk = match crypto.kdf.as_str() {
+        "scrypt" => {
+            let kdf: ScryptJson = serde_json::from_value(crypto.kdfparams)?;
+            check_dklen(kdf.dklen)?;
+            let params = ScryptParams { n: kdf.n, r: kdf.r, p: kdf.p };
+            scrypt(password.as_bytes(), &hex::decode(kdf.salt)?, params, kdf.dklen)?
+        }
+        "pbkdf2" => {
+            let kdf: Pbkdf2Json = serde_json::from_value(crypto.kdfparams)?;
+            if kdf.prf != "hmac-sha256" {
+                return Err(anyhow!("Unsupported keystore PRF {}", kdf.prf));
+            }
+            check_dklen(kdf.dklen)?;
+            if !(1..=MAX_PBKDF2_ROUNDS).contains(&kdf.c) {
+                return Err(anyhow!("Invalid PBKDF2 iteration count {}", kdf.c));
+            }
+            pbkdf2_sha256(password.as_bytes(), &hex::decode(kdf.salt)?, kdf.c, kdf.dklen)
+        }
+        kdf => return Err(anyhow!("Unsupported keystore KDF {}", kdf)),
+    };
+
+    let mut key = hex::decode(&crypto.ciphertext)?;
+    let mac = keccak256([&dk[16..32], key.as_slice()].concat());
+    if hex::decode(&crypto.mac)? != mac.as_slice() {
+        dk.zeroize();
+        return Err(anyhow!("Wrong keystore password"));
+    }
+
+    let iv: [u8; 16] = hex::decode(&crypto.cipherparams.iv)?
+        .try_into()
+        .map_err(|_| anyhow!("Invalid keystore IV"))?;
+    Aes128Ctr::new(dk[..16].into(), &iv.into()).apply_keystream(&mut key);
+    dk.zeroize();
+
+    Ok(key)
+}
+
+/// Only the first [DK_LEN] bytes are used, a keystore asking for a shorter or a much longer key is broken
+fn check_dklen(dklen: usize) -> Result<(), anyhow::Error> {
+    if !(DK_LEN..=64).contains(&dklen) {
+        return Err(anyhow!("Invalid keystore key length {}", dklen));
+    }
+    Ok(())
+}
+
+/// Format 16 random bytes as a version 4 UUID
+fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// PBKDF2 with HMAC-SHA256 (RFC 8018)
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], rounds: u32, dk_len: usize) -> Vec<u8> {
+    let prf = Hmac::<Sha256>::new_from_slice(password).expect("HMAC takes a key of any size");
+    let mut dk = Vec::with_capacity(dk_len);
+
+    for block in 1..=dk_len.div_ceil(32) as u32 {
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&block.to_be_bytes());
+        let mut u: [u8; 32] = mac.finalize().into_bytes().into();
+        let mut t = u;
+
+        for _ in 1..rounds {
+            let mut mac = prf.clone();
+            mac.update(&u);
+            u = mac.finalize().into_bytes().into();
+            t.iter_mut().zip(u.iter()).for_each(|(t, u)| *t ^= u);
+        }
+
+        let take = (dk_len - dk.len()).min(32);
+        dk.extend_from_slice(&t[..take]);
+    }
+
+    dk
+}
+
+/// scrypt (RFC 7914)
+fn scrypt(password: &[u8], salt: &[u8], params: ScryptParams, dk_len: usize) -> Result<Vec<u8>, anyhow::Error> {
+    let ScryptParams { n, r, p } = params;
+    if n < 2 || !n.is_power_of_two() || r == 0 || p == 0 {
+        return Err(anyhow!("Invalid scrypt params"));
+    }
+    if 128 * r as u64 * n as u64 > MAX_SCRYPT_MEMORY || r as u64 * p as u64 >= 1 << 30 {
+        return Err(anyhow!("The scrypt params need too much memory"));
+    }
+
+    let (n, r, p) = (n as usize, r as usize, p as usize);
+    let block_len = 128 * r;
+    let mut b = pbkdf2_sha256(password, salt, 1, p * block_len);
+
+    let mut x = vec![0u32; 32 * r];
+    let mut v = vec![0u32; 32 * r * n];
+    let mut y = vec![0u32; 32 * r];
+
+    for chunk in b.chunks_mut(block_len) {
+        for (word, bytes) in x.iter_mut().zip(chunk.chunks(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        ro_mix(&mut x, &mut v, &mut y, n, r);
+        for (word, bytes) in x.iter().zip(chunk.chunks_mut(4)) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    v.zeroize();
+    x.zeroize();
+    y.zeroize();
+    let dk = pbkdf2_sha256(password, &b, 1, dk_len);
+    b.zeroize();
+    Ok(dk)
+}
+
+fn ro_mix(x: &mut [u32], v: &mut [u32], y: &mut [u32], n: usize, r: usize) {
+    let len = 32 * r;
+
+    for i in 0..n {
+        v[i * len..(i + 1) * len].copy_from_slice(x);
+        block_mix(x, y, r);
+        x.copy_from_slice(y);
+    }
+
+    for _ in 0..n {
+        let j = x[(2 * r - 1) * 16] as usize & (n - 1);
+        x.iter_mut().zip(&v[j * len..(j + 1) * len]).for_each(|(x, v)| *x ^= v);
+        block_mix(x, y, r);
+        x.copy_from_slice(y);
+    }
+}
+
+/// `output` gets the even blocks of the mix first, then the odd ones
+fn block_mix(input: &[u32], output: &mut [u32], r: usize) {
+    let mut t = [0u32; 16];
+    t.copy_from_slice(&input[(2 * r - 1) * 16..]);
+
+    for i in 0..2 * r {
+        t.iter_mut().zip(&input[i * 16..(i + 1) * 16]).for_each(|(t, b)| *t ^= b);
+        salsa20_8(&mut t);
+        let out = (i / 2 + (i % 2) * r) * 16;
+        output[out..out + 16].copy_from_slice(&t);
+    }
+}
+
+fn salsa20_8(b: &mut [u32; 16]) {
+    let mut x = *b;
+
+    let quarter = |x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize| {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    };
+
+    for _ in 0..4 {
+        // columns
+        quarter(&mut x, 0, 4, 8, 12);
+        quarter(&mut x, 5, 9, 13, 1);
+        quarter(&mut x, 10, 14, 2, 6);
+        quarter(&mut x, 15, 3, 7, 11);
+        // rows
+        quarter(&mut x, 0, 1, 2, 3);
+        quarter(&mut x, 5, 6, 7, 4);
+        quarter(&mut x, 10, 11, 8, 9);
+        quarter(&mut x, 15, 12, 13, 14);
+    }
+
+    b.iter_mut().zip(x.iter()).for_each(|(b, x)| *b = b.wrapping_add(*x));
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // the test vectors of the Web3 Secret Storage definition
+    pub(crate) const PASSWORD: &str = "testpassword";
+    pub(crate) const KEY: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    pub(crate) const PBKDF2_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    const SCRYPT_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "83dbcc02d8ccb40e466191a123791e0e" },
+            "ciphertext": "d172bf743a674da9cdad04534d56926ef8358534d458fffccd4e6ad2fbde479c",
+            "kdf": "scrypt",
+            "kdfparams": {
+                "dklen": 32,
+                "n": 262144,
+                "p": 8,
+                "r": 1,
+                "salt": "ab0c7876052600dd703518d6fc3fe8984592145b591fc8fb5c6d43190334ba19"
+            },
+            "mac": "2103ac29920d71da29f15d75b4a16dbe95cfd7ff8faea1056c33131d846e3097"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    /// Cheap params so the tests don't spend seconds in scrypt
+    const FAST: ScryptParams = ScryptParams { n: 16, r: 8, p: 1 };
+
+    #[test]
+    fn pbkdf2_matches_rfc_7914() {
+        let dk = pbkdf2_sha256(b"passwd", b"salt", 1, 64);
+        assert_eq!(
+            hex::encode(dk),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+             49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+        );
+    }
+
+    #[test]
+    fn scrypt_matches_rfc_7914() {
+        let dk = scrypt(b"", b"", ScryptParams { n: 16, r: 1, p: 1 }, 64).unwrap();
+        assert_eq!(
+            hex::encode(dk),
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+             fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+        );
+
+        let dk = scrypt(b"password", b"NaCl", ScryptParams { n: 1024, r: 8, p: 16 }, 64).unwrap();
+        assert_eq!(
+            hex::encode(dk),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+             2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+        );
+    }
+
+    #[test]
+    fn decrypts_the_pbkdf2_test_vector() {
+        assert_eq!(hex::encode(decrypt_key(PBKDF2_KEYSTORE, PASSWORD).unwrap()), KEY);
+    }
+
+    #[test]
+    fn decrypts_the_scrypt_test_vector() {
+        assert_eq!(hex::encode(decrypt_key(SCRYPT_KEYSTORE, PASSWORD).unwrap()), KEY);
+    }
+
+    #[test]
+    fn a_wrong_password_fails_the_mac_check() {
+        let err = decrypt_key(PBKDF2_KEYSTORE, "wrong").unwrap_err();
+        assert!(err.to_string().contains("Wrong keystore password"), "{}", err);
+    }
+
+    #[test]
+    fn encrypted_keys_round_trip() {
+        let key = hex::decode(KEY).unwrap();
+        let json = encrypt_key_with(&key, Address::ZERO, PASSWORD, FAST, [1; 32], [2; 16], [3; 16]).unwrap();
+
+        assert_eq!(decrypt_key(&json, PASSWORD).unwrap(), key);
+        assert!(decrypt_key(&json, "wrong").is_err());
+
+        let file: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(file["version"], 3);
+        assert_eq!(file["crypto"]["kdf"], "scrypt");
+        assert_eq!(file["crypto"]["kdfparams"]["n"], 16);
+        assert_eq!(file["id"], "03030303-0303-4303-8303-030303030303");
+    }
+
+    #[test]
+    fn an_empty_password_is_rejected() {
+        let key = hex::decode(KEY).unwrap();
+        assert!(encrypt_key_with(&key, Address::ZERO, "", FAST, [1; 32], [2; 16], [3; 16]).is_err());
+    }
+
+    #[test]
+    fn scrypt_params_that_need_too_much_memory_are_rejected() {
+        assert!(scrypt(b"", b"", ScryptParams { n: 1 << 24, r: 8, p: 1 }, 32).is_err());
+        assert!(scrypt(b"", b"", ScryptParams { n: 1000, r: 8, p: 1 }, 32).is_err());
+    }
+
+    #[test]
+    fn pbkdf2_iteration_counts_out_of_bounds_are_rejected() {
+        for c in ["0", "4294967295"] {
+            let json = PBKDF2_KEYSTORE.replace("262144", c);
+            let err = decrypt_key(&json, PASSWORD).unwrap_err();
+            assert!(err.to_string().contains("iteration count"), "{}", err);
+        }
+    }
+}
@@ -1,7 +1,13 @@
 pub mod encryption;
+pub mod fs;
+pub mod instance_lock;
+pub mod keystore;
 pub mod profile;
 
 pub use anyhow;
 pub use lazy_static;
-pub use encryption::{Credentials, encrypt_data, decrypt_data};
-pub use profile::{Profile, Wallet, WalletData};
\ No newline at end of file
+pub use zeroize;
+pub use encryption::{Credentials, UnlockCredentials, DerivedKey, EncryptionParams, encrypt_data, encrypt_data_with_key, decrypt_data, decrypt_data_or_backup, unlock_data_or_backup, encrypted_file_exists};
+pub use fs::{atomic_write, is_writable, migrate_data_dir, read_with_backup, CHAINS_FILE, RPC_FILE, SETTINGS_FILE};
+pub use instance_lock::{InstanceLock, LockStatus};
+pub use profile::{sanitize_wallet_name, Profile, Wallet, WalletBalance, WalletData, MAX_WALLET_NAME_LEN};
@@ -1,9 +1,11 @@
-use super::super::encryption::{Credentials, encrypt_data, decrypt_data};
-use super::{ Wallet, WalletBalance, WalletData};
+use super::super::keystore::encrypt_key;
+use super::super::encryption::{Credentials, DerivedKey, UnlockCredentials, EncryptionParams, encrypt_data_with_key, encrypted_file_exists, unlock_data_or_backup};
+use super::{sanitize_wallet_name, Wallet, WalletData};
 use alloy::core::hex::encode;
 use alloy::primitives::Address;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
 use anyhow::anyhow;
 
 const FILENAME: &str = "profile.data";
@@ -17,8 +19,10 @@ const FILENAME: &str = "profile.data";
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Profile {
 
-    /// Credentials of the profile
-    pub credentials: Credentials,
+    /// The key derived from the credentials when the profile was created or unlocked
+    ///
+    /// The credentials themselves are not kept, `None` while the profile is locked
+    key: Option<Arc<DerivedKey>>,
 
     /// The wallets of the profile
     pub wallets: Vec<Wallet>,
@@ -30,55 +34,111 @@ pub struct Profile {
 
 impl Profile {
 
-    /// Encrypt and save the wallets of the profile
+    /// Is there a profile that can be unlocked, an empty or truncated `profile.data` doesn't count
+    pub fn exists() -> bool {
+        encrypted_file_exists(FILENAME)
+    }
+
+    /// Create a new profile with `credentials` and save it
+    ///
+    /// The caller should clear the credentials afterwards, only the derived key is kept
+    pub fn create(&mut self, credentials: &Credentials) -> Result<(), anyhow::Error> {
+        let key = Arc::new(credentials.derive_key(&EncryptionParams::default())?);
+        let data = self.serialize_to_json()?.as_bytes().to_vec();
+        encrypt_data_with_key(FILENAME, data, &key)?;
+        self.key = Some(key);
+        Ok(())
+    }
+
+    /// Encrypt and save the wallets of the profile with the key derived on unlock
     pub fn encrypt_and_save(&self) -> Result<(), anyhow::Error> {
+        let key = self.key.as_ref().ok_or(anyhow!("The profile is locked"))?;
         let data = self.serialize_to_json()?.as_bytes().to_vec();
-        encrypt_data(FILENAME, data, self.credentials.clone())?;
+        encrypt_data_with_key(FILENAME, data, key)?;
         Ok(())
     }
 
     /// Decrypt and load the profile
-    pub fn decrypt_and_load(&mut self) -> Result<(), anyhow::Error> {
-        let data = decrypt_data(FILENAME, self.credentials.clone())?;
+    ///
+    /// The caller should clear the credentials afterwards, only the derived key is kept
+    /// 
+    /// Returns `true` if `profile.data` was corrupted and the profile was restored from the backup
+    pub fn decrypt_and_load(&mut self, credentials: &UnlockCredentials) -> Result<bool, anyhow::Error> {
+        let (data, key, from_backup) = unlock_data_or_backup(FILENAME, credentials)?;
         
         let wallets = Profile::deserialize_from_json(data)?;
         self.wallets = wallets;
+        self.key = Some(Arc::new(key));
 
         // if there is at least 1 wallet available, set the current wallet to the first one
         if !self.wallets.is_empty() {
             self.current_wallet = Some(self.wallets[0].clone());
         }
 
-        Ok(())
+        Ok(from_backup)
+    }
+
+    /// Forget the wallets and the key
+    ///
+    /// The profile has to be unlocked again with [Profile::decrypt_and_load]
+    pub fn lock(&mut self) {
+        self.wallets.clear();
+        self.current_wallet = None;
+        self.key = None;
+    }
+
+    /// Is the key of the profile in memory
+    pub fn is_unlocked(&self) -> bool {
+        self.key.is_some()
     }
 
     /// Confirm again the credentials and export the givens wallet key
-    pub fn export_wallet(&self, wallet: Wallet, credentials: Credentials) -> Result<String, anyhow::Error> {
-        if let Err(e) = decrypt_data(FILENAME, credentials.clone()) {
-            return Err(anyhow!("Invalid credentials: {}", e));
-        }
+    ///
+    /// The credentials are checked against the key of the profile, the file is not decrypted again
+    pub fn export_wallet(&self, wallet: Wallet, credentials: &UnlockCredentials) -> Result<String, anyhow::Error> {
+        let key = self.key.as_ref().ok_or(anyhow!("The profile is locked"))?;
+        key.verify(credentials)?;
 
         Ok(wallet.get_key())
     }
 
+    /// Confirm again the credentials and export the given wallet as keystore JSON encrypted with `password`
+    ///
+    /// See [crate::keystore], the keystore can be imported into most other wallets
+    pub fn export_keystore(&self, wallet: &Wallet, credentials: &UnlockCredentials, password: &str) -> Result<String, anyhow::Error> {
+        let key = self.key.as_ref().ok_or(anyhow!("The profile is locked"))?;
+        key.verify(credentials)?;
+
+        encrypt_key(wallet.key.to_bytes().as_slice(), wallet.key.address(), password)
+    }
+
     /// Create a new random wallet and add it to the profile
+    ///
+    /// The name is sanitized with [sanitize_wallet_name], if nothing is left the address is used
     pub fn new_wallet(&mut self, name: String) -> Result<(), anyhow::Error> {
-        // do not allow duplicate names
-        if self.wallets.iter().any(|w| w.name == name) {
-            return Err(anyhow!("Wallet with name {} already exists", name));
-        }
-        let wallet = Wallet::new_rng(name);
-        self.wallets.push(wallet);
-        Ok(())
+        let wallet = Wallet::new_rng(sanitize_wallet_name(&name));
+        self.add_wallet(wallet)
     }
 
     /// Import a wallet from a private key
-    pub fn import_wallet(&mut self, name: String, balance: HashMap<u64, WalletBalance>, key: String) -> Result<(), anyhow::Error> {
-        // do not allow duplicate names
-        if self.wallets.iter().any(|w| w.name == name) {
-            return Err(anyhow!("Wallet with name {} already exists", name));
+    ///
+    /// The name is sanitized like in [Profile::new_wallet]
+    pub fn import_wallet(&mut self, name: String, key: String) -> Result<(), anyhow::Error> {
+        let wallet = Wallet::new_from_key(sanitize_wallet_name(&name), HashMap::new(), key)?;
+        self.add_wallet(wallet)
+    }
+
+    /// Add a wallet unless one with the same name or address already exists
+    pub fn add_wallet(&mut self, wallet: Wallet) -> Result<(), anyhow::Error> {
+        if self.wallets.iter().any(|w| w.name == wallet.name) {
+            return Err(anyhow!("Wallet with name {} already exists", wallet.name));
         }
-        let wallet = Wallet::new_from_key(name, balance, key)?;
+
+        let address = wallet.key.address();
+        if let Some(existing) = self.wallets.iter().find(|w| w.key.address() == address) {
+            return Err(anyhow!("This wallet was already imported as {}", existing.name));
+        }
+
         self.wallets.push(wallet);
         Ok(())
     }
@@ -101,7 +161,14 @@ impl Profile {
         }
     }
 
-
+    /// The wallet of this profile with `address`, to sign with it instead of the current wallet
+    pub fn signer(&self, address: Address) -> Result<Wallet, anyhow::Error> {
+        self.wallets
+            .iter()
+            .find(|wallet| wallet.key.address() == address)
+            .cloned()
+            .ok_or(anyhow!("{} is not a wallet of this profile", address))
+    }
 
     /// Convert all the wallets keys with their names to Json string format
     pub fn serialize_to_json(&self) -> Result<String, anyhow::Error> {
@@ -131,4 +198,139 @@ impl Profile {
     }
 
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::WalletBalance;
+    use crate::encryption::tests::fast_params;
+    use alloy::primitives::U256;
+
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn new_wallet_rejects_duplicate_names() {
+        let mut profile = Profile::default();
+        profile.new_wallet("Main".to_string()).unwrap();
+
+        assert!(profile.new_wallet("Main".to_string()).is_err());
+        // the name is compared after it is sanitized
+        assert!(profile.new_wallet("  Main\n".to_string()).is_err());
+        assert_eq!(profile.wallets.len(), 1);
+    }
+
+    #[test]
+    fn new_wallet_without_name_uses_the_address() {
+        let mut profile = Profile::default();
+        profile.new_wallet("   ".to_string()).unwrap();
+
+        let wallet = &profile.wallets[0];
+        assert_eq!(wallet.name, wallet.key.address().to_string());
+    }
+
+    #[test]
+    fn import_wallet_rejects_duplicate_names() {
+        let mut profile = Profile::default();
+        profile.new_wallet("Main".to_string()).unwrap();
+
+        assert!(profile.import_wallet("Main".to_string(), KEY.to_string()).is_err());
+        assert_eq!(profile.wallets.len(), 1);
+    }
+
+    #[test]
+    fn import_wallet_rejects_the_same_key_twice() {
+        let mut profile = Profile::default();
+        profile.import_wallet("First".to_string(), KEY.to_string()).unwrap();
+
+        let err = profile.import_wallet("Second".to_string(), KEY.to_string()).unwrap_err();
+        assert!(err.to_string().contains("First"));
+        assert_eq!(profile.wallets.len(), 1);
+    }
+
+    #[test]
+    fn signer_is_looked_up_by_address() {
+        let mut profile = Profile::default();
+        profile.new_wallet("Main".to_string()).unwrap();
+        profile.import_wallet("Other".to_string(), KEY.to_string()).unwrap();
+        profile.current_wallet = Some(profile.wallets[0].clone());
+
+        let other = profile.wallets[1].key.address();
+        assert_eq!(profile.signer(other).unwrap().name, "Other");
+        // the current wallet is left alone
+        assert_eq!(profile.current_wallet_name(), "Main");
+        assert!(profile.signer(Address::ZERO).is_err());
+    }
+
+    #[test]
+    fn export_wallet_verifies_the_credentials() {
+        let credentials = UnlockCredentials::new("user".to_string(), "password".to_string());
+        let mut profile = Profile {
+            key: Some(Arc::new(credentials.derive_key(&fast_params()).unwrap())),
+            ..Profile::default()
+        };
+        profile.import_wallet("Main".to_string(), KEY.to_string()).unwrap();
+        let wallet = profile.wallets[0].clone();
+
+        assert!(profile.export_wallet(wallet.clone(), &credentials).is_ok());
+
+        let wrong = UnlockCredentials::new("user".to_string(), "wrong".to_string());
+        assert!(profile.export_wallet(wallet, &wrong).is_err());
+    }
+
+    #[test]
+    fn a_locked_profile_cannot_be_saved_or_exported() {
+        let credentials = UnlockCredentials::new("user".to_string(), "password".to_string());
+        let mut profile = Profile {
+            key: Some(Arc::new(credentials.derive_key(&fast_params()).unwrap())),
+            ..Profile::default()
+        };
+        profile.import_wallet("Main".to_string(), KEY.to_string()).unwrap();
+        let wallet = profile.wallets[0].clone();
+
+        profile.lock();
+
+        assert!(!profile.is_unlocked());
+        assert!(profile.encrypt_and_save().is_err());
+        assert!(profile.export_wallet(wallet, &credentials).is_err());
+    }
+
+    #[test]
+    fn a_keystore_wallet_is_added_once() {
+        use crate::keystore::tests::{PASSWORD, PBKDF2_KEYSTORE};
+
+        let mut profile = Profile::default();
+        let wallet = Wallet::from_keystore("Main".to_string(), PBKDF2_KEYSTORE, PASSWORD).unwrap();
+        profile.add_wallet(wallet.clone()).unwrap();
+        assert_eq!(profile.wallets, [wallet]);
+
+        let again = Wallet::from_keystore("Other".to_string(), PBKDF2_KEYSTORE, PASSWORD).unwrap();
+        assert!(profile.add_wallet(again).is_err());
+        assert_eq!(profile.wallets.len(), 1);
+    }
+
+    #[test]
+    fn import_wallet_rejects_an_invalid_key() {
+        let mut profile = Profile::default();
+
+        assert!(profile.import_wallet("Main".to_string(), "not a key".to_string()).is_err());
+        assert!(profile.wallets.is_empty());
+    }
+
+    #[test]
+    fn wallets_survive_serialization() {
+        let mut wallet = Wallet::new_rng_seeded("Main".to_string(), 7);
+        wallet.balance.insert(1, WalletBalance { balance: U256::from(5), block: 10 });
+        let profile = Profile {
+            wallets: vec![wallet.clone(), Wallet::new_rng_seeded(String::new(), 8)],
+            ..Profile::default()
+        };
+
+        let json = profile.serialize_to_json().unwrap();
+        let wallets = Profile::deserialize_from_json(json.into_bytes()).unwrap();
+
+        assert_eq!(wallets, profile.wallets);
+        assert_eq!(wallets[0].key.address().to_string(), "0x47Abcfb094C2fbE86acb2D3Be17fee73C45A5356");
+        assert_eq!(wallets[1].name, wallets[1].key.address().to_string());
+    }
+}
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
+use zeroize::Zeroize;
 
+use crate::keystore::decrypt_key;
 use alloy::{
     primitives::{hex::encode, U256},
     signers::{
@@ -26,6 +28,18 @@ impl Default for WalletBalance {
     }
 }
 
+/// The longest wallet name we accept, in characters
+pub const MAX_WALLET_NAME_LEN: usize = 32;
+
+/// Clean up a wallet name typed by the user
+///
+/// Control characters are removed, surrounding whitespace is trimmed and the name is cut to [MAX_WALLET_NAME_LEN]
+pub fn sanitize_wallet_name(name: &str) -> String {
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name: String = name.trim().chars().take(MAX_WALLET_NAME_LEN).collect();
+    name.trim_end().to_string()
+}
+
 /// Helper struct to serialize wallets that are about to be encrypted in a `profile.data` file
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WalletData {
@@ -60,8 +74,28 @@ impl Wallet {
 
     /// Create a new wallet with a random private key
     pub fn new_rng(name: String) -> Self {
-        let key = PrivateKeySigner::random();
+        Self::from_signer(name, PrivateKeySigner::random())
+    }
+
+    /// Create a wallet whose key only depends on `seed`, so tests can assert exact addresses
+    ///
+    /// The key is the SHA-256 of the seed, never use this outside of tests
+    #[cfg(test)]
+    pub(crate) fn new_rng_seeded(name: String, seed: u64) -> Self {
+        use alloy::primitives::B256;
+        use sha2::{Digest, Sha256};
 
+        // a hash is a valid secp256k1 key with overwhelming probability, rehash until it is
+        let mut bytes = Sha256::digest(seed.to_be_bytes());
+        loop {
+            if let Ok(key) = PrivateKeySigner::from_bytes(&B256::from_slice(&bytes)) {
+                return Self::from_signer(name, key);
+            }
+            bytes = Sha256::digest(bytes);
+        }
+    }
+
+    fn from_signer(name: String, key: LocalSigner<SigningKey>) -> Self {
         let name = if name.is_empty() {
             key.address().to_string()
         } else {
@@ -92,6 +126,29 @@ impl Wallet {
         Ok(Self { name, balance, key })
     }
 
+    /// Create a wallet from keystore JSON encrypted with `password`
+    ///
+    /// The name is sanitized like in [super::Profile::new_wallet], the KDF makes this slow so keep it off the GUI thread
+    pub fn from_keystore(name: String, json: &str, password: &str) -> Result<Self, anyhow::Error> {
+        let mut key = decrypt_key(json, password)?;
+        let mut hex_key = encode(&key);
+        key.zeroize();
+
+        let res = Self::new_from_key(sanitize_wallet_name(&name), HashMap::new(), hex_key.clone());
+        hex_key.zeroize();
+        res
+    }
+
+    /// The Eth balance of the wallet on `chain_id`, zero at block 0 if we never got one
+    pub fn get_balance(&self, chain_id: u64) -> WalletBalance {
+        self.balance.get(&chain_id).cloned().unwrap_or_default()
+    }
+
+    /// Set the Eth balance of the wallet on `chain_id`
+    pub fn set_balance(&mut self, chain_id: u64, block: u64, balance: U256) {
+        self.balance.insert(chain_id, WalletBalance { balance, block });
+    }
+
     /// Truncate the wallet name if its an Ethereum address
     pub fn truncated_name(&self) -> String {
         if self.name.len() == 42 {
@@ -101,3 +158,28 @@ impl Wallet {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_wallets_are_reproducible() {
+        let wallet = Wallet::new_rng_seeded("Main".to_string(), 7);
+
+        assert_eq!(wallet, Wallet::new_rng_seeded("Main".to_string(), 7));
+        assert_ne!(wallet.key.address(), Wallet::new_rng_seeded("Main".to_string(), 8).key.address());
+        assert_eq!(wallet.key.address().to_string(), "0x47Abcfb094C2fbE86acb2D3Be17fee73C45A5356");
+    }
+
+    #[test]
+    fn from_keystore_decrypts_the_key() {
+        use crate::keystore::tests::{KEY, PASSWORD, PBKDF2_KEYSTORE};
+
+        assert!(Wallet::from_keystore("Main".to_string(), PBKDF2_KEYSTORE, "wrong").is_err());
+
+        let wallet = Wallet::from_keystore(" Main ".to_string(), PBKDF2_KEYSTORE, PASSWORD).unwrap();
+        assert_eq!(wallet.name, "Main");
+        assert_eq!(wallet.get_key(), KEY);
+    }
+}
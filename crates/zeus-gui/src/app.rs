@@ -1,52 +1,54 @@
 use eframe::{egui, CreationContext};
-use egui::{Context, Style};
-use std::{
-    collections::HashMap,
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use egui::{Color32, Context, Style};
+use std::{sync::Arc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 
-use crossbeam::channel::{unbounded, Receiver, Sender};
+use crossbeam::channel::{unbounded, Sender};
 
 use crate::{
     fonts::get_fonts,
     gui::{
-        misc::{show_err_msg, show_login, tx_settings_window},
+        components::{request_currency_page, wallet::ViewPrivateKeyUI},
+        misc::{
+            already_running_window, auto_lock_toast, backup_restored_msg, show_login,
+            tx_settings_window,
+        },
         GUI,
     },
     theme::ZeusTheme,
 };
 
 use zeus_backend::{
-    db::ZeusDB,
+    channel::{response_channel, PendingRequests, ResponseReceiver, MAX_RESPONSES_PER_FRAME, MAX_RESPONSE_TIME_PER_FRAME},
     types::*,
     Backend,
 };
 use zeus_chain::{
-    alloy::primitives::{Address, U256},
-    defi_types::currency::Currency,
-    BLOCK_ORACLE,
+    alloy::primitives::U256,
+    defi_types::currency::{erc20::ERC20Token, Currency},
+    evm_types::simulation::get_native_coin,
+    tx::TxOutcome,
+    lag_threshold, BLOCK_ORACLE,
 };
-use zeus_shared_types::{cache::SHARED_CACHE, AppData, SHARED_UI_STATE};
+use zeus_core::{is_writable, InstanceLock, LockStatus};
+use zeus_shared_types::{cache::SHARED_CACHE, AmountInput, AppData, ProfileSave, notify_error, notify_error_text, notify_info, notify_notice, SharedUiState, UiText};
 
 use tracing_subscriber::{
     fmt, layer::SubscriberExt, prelude::*, util::SubscriberInitExt, EnvFilter,
 };
 
-use tracing::{error, info, trace};
+use tracing::{error, info, info_span, trace};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::Registry;
 
-/// The timeout for requesting eth and erc20 balances
-/// This do not apply for Ethereum since it has a block time of 12 secs and cannot cause a lot of rpc calls
-const TIME_OUT: u64 = 3;
-
 /// The width of the window
 pub const WIDTH: f32 = 1280.0;
 
 /// The height of the window
 pub const HEIGHT: f32 = 720.0;
 
+/// How long before the auto-lock we warn the user
+const AUTO_LOCK_WARNING: Duration = Duration::from_secs(30);
+
 /// The main application struct
 pub struct ZeusApp {
     /// The GUI components of the application
@@ -56,7 +58,7 @@ pub struct ZeusApp {
     pub front_sender: Sender<Request>,
 
     /// Receive Data from backend
-    pub back_receiver: Receiver<Response>,
+    pub back_receiver: ResponseReceiver,
 
     /// The app data of the application
     pub data: AppData,
@@ -67,11 +69,23 @@ pub struct ZeusApp {
 
     pub last_quote_request: Instant,
 
+    /// The balance requests waiting for a response, identical ones are not sent again
+    pub pending_requests: PendingRequests,
+
+    /// The last time the user interacted with the app, see [zeus_shared_types::Settings::auto_lock_mins]
+    pub last_interaction: Instant,
+
     pub on_startup: bool,
 
     pub top_panel_h: f32,
 
     pub left_panel_w: f32,
+
+    /// Held for as long as the app is running, released on exit
+    pub instance_lock: Option<InstanceLock>,
+
+    /// PID of another running instance that holds the lock, until the user decides what to do
+    pub lock_holder: Option<u32>,
 }
 
 fn setup_logging() -> (WorkerGuard, WorkerGuard) {
@@ -114,13 +128,37 @@ fn setup_logging() -> (WorkerGuard, WorkerGuard) {
 
 impl ZeusApp {
     pub fn new(cc: &CreationContext) -> Self {
+        let time = Instant::now();
         let _guard = setup_logging();
+        SharedUiState::set_gui_thread();
+
+        // eg. running from a read-only USB stick, there is nowhere to put the lock either
+        let data_dir_writable = is_writable(".");
+        if !data_dir_writable {
+            info!("The data directory is read-only, starting in read-only mode");
+        }
+
+        let (instance_lock, lock_holder) = match InstanceLock::acquire() {
+            _ if !data_dir_writable => (None, None),
+            Ok(LockStatus::Acquired(lock)) => (Some(lock), None),
+            Ok(LockStatus::HeldBy(pid)) => {
+                info!("Another instance of Zeus is running with PID {}", pid);
+                (None, Some(pid))
+            }
+            Err(e) => {
+                error!("Error Acquiring Instance Lock: {}", e);
+                (None, None)
+            }
+        };
+        let read_only = lock_holder.is_some() || !data_dir_writable;
 
         let (front_sender, front_receiver) = unbounded();
-        let (back_sender, back_receiver) = unbounded();
+        let (back_sender, back_receiver) = response_channel();
 
         std::thread::spawn(move || {
-            Backend::new(back_sender, front_receiver).init();
+            Backend::new(back_sender, front_receiver)
+                .read_only(read_only)
+                .init();
         });
 
 
@@ -134,78 +172,70 @@ impl ZeusApp {
             last_eth_request: Instant::now(),
             last_erc20_request: Instant::now(),
             last_quote_request: Instant::now(),
+            pending_requests: PendingRequests::default(),
+            last_interaction: Instant::now(),
             on_startup: true,
             top_panel_h: 0.0,
             left_panel_w: 0.0,
+            instance_lock,
+            lock_holder,
         };
+        app.data.read_only = read_only;
+        app.data.data_dir_read_only = !data_dir_writable;
 
-        let theme = app.config_style(&cc.egui_ctx);
-        app.gui.theme = Arc::new(theme);
-
-        match app.data.load_rpc() {
-            Ok(_) => {}
-            Err(e) => {
-                error!("Error Loading rpc.json: {}", e);
-            }
+        {
+            let _span = info_span!("startup", phase = "config_style").entered();
+            let theme = app.config_style(&cc.egui_ctx);
+            app.gui.theme = Arc::new(theme);
         }
 
-        let currencies: HashMap<u64, Vec<Currency>>;
-        let erc20_balances: HashMap<(u64, Address, Address), U256>;
-        let eth_balances: HashMap<(u64, Address), (u64, U256)>;
-
         {
-            let zeus_db = match ZeusDB::new() {
-                Ok(db) => db,
-                Err(e) => {
-                    // TODO: handle this differently
-                    error!("Error Creating Database: {}", e);
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.err_msg.show(e);
-                    return app;
+            let _span = info_span!("startup", phase = "load_rpc").entered();
+            match app.data.load_rpc() {
+                Ok(from_backup) => {
+                    if from_backup {
+                        backup_restored_msg("rpc.json");
+                    }
                 }
-            };
-
-            match zeus_db.insert_default() {
-                Ok(_) => {}
                 Err(e) => {
-                    error!("Error Inserting Default Tokens: {}", e);
+                    error!("Error Loading rpc.json: {}", e);
                 }
             }
+        }
 
-            let networks = app.data.supported_networks();
-
-            currencies = match zeus_db.load_currencies(networks.clone()) {
-                Ok(currencies) => currencies,
-                Err(e) => {
-                    error!("Error Loading Currencies: {}", e);
-                    HashMap::new()
+        {
+            let _span = info_span!("startup", phase = "load_settings").entered();
+            match app.data.load_settings() {
+                Ok(from_backup) => {
+                    if from_backup {
+                        backup_restored_msg("settings.json");
+                    }
                 }
-            };
-
-            erc20_balances = match zeus_db.load_all_erc20_balances(networks.clone()) {
-                Ok(balances) => balances,
                 Err(e) => {
-                    error!("Error Loading ERC20 Balances: {}", e);
-                    HashMap::new()
+                    error!("Error Loading settings.json: {}", e);
                 }
-            };
+            }
+        }
 
-            eth_balances = match zeus_db.load_all_eth_balances(networks) {
-                Ok(balances) => balances,
-                Err(e) => {
-                    error!("Error Loading ETH Balances: {}", e);
-                    HashMap::new()
-                }
-            };
-            trace!("ERC20 Balances Loaded: {:?}", erc20_balances);
-            trace!("ETH Balances Loaded: {:?}", eth_balances);
+        // first run, walk the user through the setup instead of the bare new profile screen
+        if app.data.new_profile_screen && !app.data.settings.onboarding_done {
+            app.gui.onboarding.open();
+        }
+
+        {
+            let _span = info_span!("startup", phase = "load_chain_specs").entered();
+            if let Err(e) = app.data.load_chain_specs() {
+                error!("Error Loading chains.json: {}", e);
+            }
         }
 
-        let mut shared_cache = SHARED_CACHE.write().unwrap();
-        shared_cache.currencies = currencies;
-        shared_cache.erc20_balance = erc20_balances;
-        shared_cache.eth_balance = eth_balances;
+        // Load the database in the background so the login screen is shown immediately
+        let req = Request::load_cache(app.data.supported_networks());
+        app.send_request(req);
+        app.send_request(Request::load_watches());
+        app.send_request(Request::load_address_labels());
 
+        info!("ZeusApp initialized in {}ms", time.elapsed().as_millis());
 
         app
     }
@@ -221,17 +251,121 @@ impl ZeusApp {
         theme
     }
 
+    /// The data was moved to a writable directory, leave read-only mode if we are the only instance there
+    fn data_dir_moved(&mut self) {
+        match InstanceLock::acquire() {
+            Ok(LockStatus::Acquired(lock)) => {
+                self.instance_lock = Some(lock);
+                self.data.read_only = false;
+                self.data.data_dir_read_only = false;
+                self.send_request(Request::data_dir_changed(self.data.supported_networks()));
+            }
+            Ok(LockStatus::HeldBy(pid)) => {
+                notify_error(format!("Another instance of Zeus (PID {}) uses this directory, staying in read-only mode", pid));
+            }
+            Err(e) => {
+                notify_error(e);
+            }
+        }
+    }
+
     /// Send a request to backend
+    ///
+    /// A polled request is dropped while an identical one is still pending
     fn send_request(&mut self, request: Request) {
+            if !self.pending_requests.admit(&request) {
+                trace!("Dropped a duplicate request: {:?}", request.coalesce_keys());
+                return;
+            }
+
             match self.front_sender.send(request) {
                 Ok(_) => {}
                 Err(e) => {
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.err_msg.show(e);
+                    notify_error(e);
                 }
             }
     }
 
+    /// Repaint once the latest block gets too old, nothing else repaints when the node stops sending blocks
+    ///
+    /// While the node is behind we repaint every second so the banner counts up
+    fn check_node_lag(&self, ctx: &Context) {
+        if !self.data.connected() {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let Some(age) = self.data.latest_block.age(now) else {
+            return;
+        };
+
+        let threshold = lag_threshold(self.data.chain_id.id());
+        let next_check = threshold.saturating_sub(age) + Duration::from_secs(1);
+        ctx.request_repaint_after(next_check);
+    }
+
+    /// Lock the profile once the app was idle for longer than the auto-lock timeout
+    ///
+    /// A warning is shown for the last [AUTO_LOCK_WARNING]
+    fn check_auto_lock(&mut self, ctx: &Context) {
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.last_interaction = Instant::now();
+        }
+
+        let Some(timeout) = self.data.settings.auto_lock_timeout() else {
+            return;
+        };
+        if !self.data.logged_in {
+            return;
+        }
+
+        let idle = self.last_interaction.elapsed();
+        if idle >= timeout {
+            info!("Locking the profile after {} seconds of inactivity", idle.as_secs());
+            self.lock();
+            return;
+        }
+
+        let left = timeout - idle;
+        if left <= AUTO_LOCK_WARNING {
+            auto_lock_toast(ctx, left);
+        }
+
+        // nothing else repaints while the user is idle
+        let next_check = if left > AUTO_LOCK_WARNING { left - AUTO_LOCK_WARNING } else { Duration::from_secs(1) };
+        ctx.request_repaint_after(next_check);
+    }
+
+    /// Send the profile to the backend once it is due, see [AppData::take_profile_save]
+    fn save_profile(&mut self, ctx: &Context, force: bool) {
+        if self.data.read_only || !self.data.logged_in {
+            return;
+        }
+
+        if let Some(profile) = self.data.take_profile_save(force) {
+            self.send_request(Request::save_profile(profile));
+        } else if let Some(left) = self.data.profile_save_in() {
+            ctx.request_repaint_after(left);
+        }
+    }
+
+    /// Lock the profile and go back to the login screen
+    fn lock(&mut self) {
+        // flush pending changes before the keys are cleared
+        if self.data.profile_save_in().is_some() && !self.data.read_only {
+            if let Some(profile) = self.data.take_profile_save(true) {
+                self.send_request(Request::save_profile(profile));
+            }
+        }
+        self.data.lock();
+        self.send_request(Request::cancel_metadata_refresh());
+        self.gui.wallet_ui.view_key_ui = ViewPrivateKeyUI::new(self.front_sender.clone());
+        self.gui.wallet_ui.create_wallet_ui.state.close();
+        self.gui.wallet_ui.import_wallet_ui.state.close();
+        self.gui.wallet_ui.state.close();
+        self.top_panel_h = 0.0;
+        self.left_panel_w = 0.0;
+    }
+
     fn request_eth_balance(&mut self) {
         if self.data.profile.current_wallet.is_none() {
             return;
@@ -254,7 +388,7 @@ impl ZeusApp {
         // check if the timeout has passed
         let now = Instant::now();
         let timeout_expired =
-            now.duration_since(self.last_eth_request) > Duration::from_secs(TIME_OUT);
+            now.duration_since(self.last_eth_request) > self.data.settings.balance_refresh_interval();
 
         // timeout has not expired and chain is not ethereum, skip
         if !timeout_expired && chain != 1 {
@@ -263,7 +397,7 @@ impl ZeusApp {
 
         let client = self.data.client().clone().unwrap();
 
-        let req = Request::eth_balance(owner, chain, latest_block, client);
+        let req = Request::eth_balance(owner, chain, latest_block, false, client);
 
         self.send_request(req);
         self.last_eth_request = now;
@@ -273,10 +407,35 @@ impl ZeusApp {
         trace!("Sent Request For ETH Balance");
     }
 
+    /// Read the eth balance and the tokens of the SwapUI from the node again, skipping the database
+    ///
+    /// The tokens are re-read too (metadata and balance) in case they changed since they were cached
+    fn force_refresh(&mut self) {
+        let Some(client) = self.data.client().clone() else {
+            notify_error_text(UiText::key("error.not_connected"));
+            return;
+        };
+        let chain_id = self.data.chain_id.id();
+        let owner = self.data.wallet_address();
+        let latest_block = self.data.latest_block().number;
+
+        self.send_request(Request::eth_balance(owner, chain_id, latest_block, true, client.clone()));
+
+        let source = self.data.settings.token_metadata_source;
+        let sides = [("input", self.gui.swap_ui.currency_in.clone()), ("output", self.gui.swap_ui.currency_out.clone())];
+        for (id, currency) in sides {
+            if let Some(token) = currency.erc20() {
+                let req = Request::erc20_token(id.to_string(), owner, token.address, chain_id, client.clone(), source, true);
+                self.send_request(req);
+            }
+        }
+        trace!("Sent a forced refresh of the balances");
+    }
+
     /// Request the ERC20 balance of the current wallet for the SwapUI
     ///
     /// For Ethereum we only do requests on every new block
-    /// For other chains their block time can vary a lot so we only do requests every 3 seconds
+    /// For other chains their block time can vary a lot so we only do requests every [zeus_shared_types::Settings::balance_refresh_secs]
     fn request_erc20_balance(&mut self) {
         // no selected wallet, skip
         if self.data.wallet_address().is_zero() {
@@ -291,7 +450,7 @@ impl ZeusApp {
         // check if the timeout has passed
         let now = Instant::now();
         let timeout_expired =
-            now.duration_since(self.last_erc20_request) > Duration::from_secs(TIME_OUT);
+            now.duration_since(self.last_erc20_request) > self.data.settings.balance_refresh_interval();
         let chain = self.data.chain_id.id();
 
         // timeout has not expired and chain is not ethereum, skip
@@ -314,23 +473,14 @@ impl ZeusApp {
         let owner = self.data.wallet_address();
         let chain_id = self.data.chain_id.id();
 
-        if !currency_in.is_native() {
-            // currency is an ERC20 token
-            let token = currency_in.erc20().unwrap();
-
-            let req = Request::erc20_balance(token.clone(), owner, chain_id, latest_block, client.clone());
-
-            self.send_request(req);
-            info!("Request sent for input token: {:?}", token.symbol);
-        }
-
-        if !currency_out.is_native() {
-            let token = currency_out.erc20().unwrap();
-
-            let req = Request::erc20_balance(token.clone(), owner, chain_id, latest_block, client);
-
-            self.send_request(req);
-            info!("Request sent for output token: {:?}", token.symbol);
+        // both tokens are answered together so the cache is updated once
+        let tokens: Vec<ERC20Token> = [currency_in, currency_out]
+            .iter()
+            .filter_map(|currency| currency.erc20().cloned())
+            .collect();
+        if !tokens.is_empty() {
+            info!("Request sent for the balances of {} tokens", tokens.len());
+            self.send_request(Request::erc20_balances(tokens, owner, chain_id, latest_block, client));
         }
 
         // update the last request time
@@ -344,34 +494,47 @@ impl ZeusApp {
         let owner = self.data.wallet_address();
         let chain_id = self.data.chain_id.id();
 
-        // update eth balance in the shared cache
-        let mut shared_cache = SHARED_CACHE.write().unwrap();
-        shared_cache.eth_balance.insert(
-            (chain_id, owner),
-            (self.data.latest_block().number, balance),
-        );
+        // update eth balance in the shared cache and the wallets of the profile
+        self.data.update_balance(chain_id, owner, balance);
     }
 
     fn handle_response(&mut self, res: Response) {
+        self.pending_requests.complete(&res);
         match res {
             Response::EthBalance(balance) => {
                 self.update_eth_balance(balance);
             }
 
-            Response::Client(client, chain_id) => {
+            Response::Client(Ok(client), chain_id) => {
                 trace!("Changed Chain: {:?}", chain_id.name().clone());
 
-                self.data.client = client.clone();
+                self.data.client = Some(client.clone());
+                self.data.connected_chain = Some(chain_id.clone());
+                self.data.latency.clear();
                 self.gui.swap_ui.default_input(chain_id.id());
                 self.gui.swap_ui.default_output(chain_id.id());
                 self.gui.send_screen.default_input(chain_id.id());
 
                 // setup block oracle
-                if client.is_some() {
-                let client = client.unwrap();
                 let req = Request::init_oracles(client.clone(), chain_id.clone());
                 self.send_request(req);
+
+                self.send_request(Request::refresh_stale_metadata(chain_id.id(), client));
             }
+
+            Response::Client(Err(reason), chain_id) => {
+                error!("Failed to connect to {}: {}", chain_id.name(), reason);
+
+                // stay on the chain we are still connected to
+                if let Some(previous) = self.data.connected_chain.clone() {
+                    if previous != chain_id {
+                        self.data.chain_id = previous.clone();
+                        self.gui.swap_ui.default_input(previous.id());
+                        self.gui.swap_ui.default_output(previous.id());
+                    }
+                }
+
+                notify_error(format!("Failed to connect to {}: {}", chain_id.name(), reason));
             }
 
             Response::ERC20Token(res) => {
@@ -379,20 +542,169 @@ impl ZeusApp {
                 self.gui.swap_ui.replace_currency(&res.currency_id, currency.clone());
 
                 let mut shared_cache = SHARED_CACHE.write().unwrap();
-                shared_cache.erc20_balance.insert(
-                        (res.chain_id, res.owner, res.token.address),
-                        res.balance,
-                    );
-                
+                shared_cache.update_erc20_balance(res.chain_id, res.owner, res.token.address, res.balance);
+
                 shared_cache.add_currency(res.chain_id, currency);
+
+                if res.added {
+                    notify_info(format!("Added {} to your tokens", res.token.symbol));
+                }
+            }
+
+            Response::TokenRefreshed(token) => {
+                let currency = Currency::new_erc20(token.clone());
+                let swap_ui = &mut self.gui.swap_ui;
+                for side in [&mut swap_ui.currency_in, &mut swap_ui.currency_out] {
+                    if side.erc20().is_some_and(|cached| cached.chain_id == token.chain_id && cached.address == token.address) {
+                        *side = currency.clone();
+                    }
+                }
+                SHARED_CACHE.write().unwrap().add_currency(token.chain_id, currency);
+            }
+
+            Response::Latency(latency) => {
+                self.data.add_latency(latency);
+            }
+
+            Response::Transaction(outcome) => match outcome {
+                TxOutcome::DryRun(result) => {
+                    self.gui.dry_run_window.open(*result);
+                }
+                TxOutcome::Sent(receipt) => {
+                    info!("Transaction sent: {}", receipt.transaction_hash);
+                    notify_info(format!("Transaction sent: {}", receipt.transaction_hash));
+                }
+            },
+
+            Response::SignedTransaction(tx) => {
+                info!("Transaction signed: {}", tx.tx_hash);
+                self.gui.signed_tx_window.open(*tx);
+            }
+
+            Response::BatchSend(index, status) => {
+                self.gui.send_screen.batch.set_status(index, status);
+            }
+
+            Response::Watches(watches) => {
+                self.gui.watches_ui.watches = watches;
+            }
+
+            Response::WatchTriggered(watch, price) => {
+                // pre-fill the swap panel, the user still has to confirm the swap
+                if watch.chain_id == self.data.chain_id.id() {
+                    let swap_ui = &mut self.gui.swap_ui;
+                    swap_ui.currency_in = Currency::new_erc20(watch.token_in.clone());
+                    swap_ui.currency_out = Currency::new_erc20(watch.token_out.clone());
+                    swap_ui.amount_in = AmountInput::new("1", watch.token_in.decimals);
+                    swap_ui.amount_out = AmountInput::new(price.to_string(), watch.token_out.decimals);
+                    swap_ui.state.open();
+                }
+
+                notify_notice(format!("Price watch triggered: {}, price is now {}", watch.describe(), price));
+            }
+
+            Response::SpotPrice(res) => {
+                // a price against the wrapped native currency also prices the token in USD
+                let chain_id = res.token_in.chain_id;
+                if get_native_coin(chain_id).is_ok_and(|wrapped| wrapped == res.token_out.address) {
+                    SHARED_CACHE.write().unwrap().native_prices.insert((chain_id, res.token_in.address), res.price);
+                }
+                self.gui.swap_ui.set_spot_price(*res);
+            }
+
+            Response::Quote(quote) => {
+                self.gui.swap_ui.set_quote(*quote);
+            }
+
+            Response::HistoricalBalance(res) => {
+                self.gui.historical_balance_ui.set_result(res);
+            }
+
+            Response::TxEstimate(call, estimate) => {
+                self.gui.send_screen.set_tx_estimate(&call, &estimate);
+                self.gui.raw_tx_ui.set_tx_estimate(&call, &estimate);
+            }
+
+            Response::ContractCheck(chain_id, address, is_contract, token) => {
+                let mut shared_cache = SHARED_CACHE.write().unwrap();
+                shared_cache.contracts.insert((chain_id, address), is_contract);
+                if let Some(symbol) = token {
+                    shared_cache.token_contracts.insert((chain_id, address), symbol);
+                }
+            }
+
+            Response::AddressLabels(labels) => {
+                SHARED_CACHE.write().unwrap().address_labels = labels;
+            }
+
+            Response::CurrencyPage(res) => {
+                let mut shared_cache = SHARED_CACHE.write().unwrap();
+                shared_cache.add_currency_page(res.chain_id, &res.query, res.offset, res.currencies, res.more);
+            }
+
+            Response::PinnedTokens(pinned) => {
+                SHARED_CACHE.write().unwrap().pinned_tokens = pinned;
+            }
+
+            Response::CacheLoaded(res) => {
+                SHARED_CACHE.write().unwrap().apply_loaded(res.erc20_balance, res.eth_balance, res.pinned);
+                self.data.sync_wallet_balances();
+            }
+
+            Response::BalanceHistory(chain_id, owner, history) => {
+                self.gui.wallet_ui.balance_history.set_history(chain_id, owner, history);
+            }
+
+            Response::ProfileSaved(res) => {
+                // a change made while saving keeps the profile dirty
+                if self.data.profile_save == ProfileSave::Saving {
+                    self.data.profile_save = if res.is_ok() { ProfileSave::Saved } else { ProfileSave::Failed };
+                }
+                match res {
+                    Ok(_) => notify_info("Profile saved"),
+                    Err(e) => notify_error(e),
+                }
+            }
+
+            Response::KeystoreExported(res) => match res {
+                Ok(path) => notify_info(format!("Keystore saved to {}", path)),
+                Err(e) => notify_error(e),
+            },
+
+            Response::KeystoreImported(res) => match res {
+                // the profile may have been locked while the keystore was decrypted
+                Ok(_) if !self.data.profile.is_unlocked() => {
+                    notify_error("The profile was locked, the keystore was not imported");
+                }
+                Ok(wallet) => {
+                    let name = wallet.name.clone();
+                    match self.data.profile.add_wallet(wallet) {
+                        Ok(_) => {
+                            self.data.mark_profile_dirty();
+                            notify_info(format!("Imported {}", name));
+                        }
+                        Err(e) => notify_error(e),
+                    }
+                }
+                Err(e) => notify_error(e),
+            },
+
+            Response::DefaultTokensRestored(restored) => {
+                let msg = match restored {
+                    0 => "All the default tokens are already in your token list".to_string(),
+                    n => format!("Restored {} default tokens", n),
+                };
+                notify_info(msg);
+            }
+
+            Response::BalancesBatch { chain_id, owner, balances } => {
+                SHARED_CACHE.write().unwrap().update_erc20_balances(chain_id, owner, &balances);
+                trace!("{} ERC20 Balances Updated", balances.len());
             }
 
             Response::ERC20Balance(res) => {
                 let mut shared_cache = SHARED_CACHE.write().unwrap();
-                shared_cache.erc20_balance.insert(
-                    (res.chain_id, res.owner, res.token),
-                    res.balance,
-                );
+                shared_cache.update_erc20_balance(res.chain_id, res.owner, res.token, res.balance);
                 trace!("ERC20 Balance Updated For: {:?}", res.token);
         }
     }
@@ -402,15 +714,31 @@ impl ZeusApp {
 // Main Event Loop Of The Window
 // This is where we draw the UI
 impl eframe::App for ZeusApp {
+    /// Write unsaved profile changes before exiting, the backend may not get to finish them
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.data.read_only || !self.data.logged_in || self.data.profile_save_in().is_none() {
+            return;
+        }
+        if let Err(e) = self.data.profile.encrypt_and_save() {
+            error!("Failed to save the profile on exit: {}", e);
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-         
-            match self.back_receiver.try_recv() {
-                Ok(response) => {
-                    self.handle_response(response);
-                }
-                Err(_) => {}
+        // a burst is spread over a few frames instead of stalling this one
+        let receiver = self.back_receiver.clone();
+        receiver.drain_within(MAX_RESPONSES_PER_FRAME, MAX_RESPONSE_TIME_PER_FRAME, |response| {
+            self.handle_response(response);
+        });
+        if !receiver.is_empty() {
+            ctx.request_repaint();
         }
 
+        self.check_auto_lock(ctx);
+        self.check_node_lag(ctx);
+        self.save_profile(ctx, false);
+        self.gui.poll_notifications();
+
         // this is a temp solution
         if self.data.logged_in {
             self.top_panel_h = 100.0;
@@ -428,17 +756,34 @@ impl eframe::App for ZeusApp {
         }
 
         // update to latest block
-        {
+        let new_block = {
             let oracle = BLOCK_ORACLE.read().unwrap();
-            if self.data.latest_block().number != oracle.latest_block().number {
+            let new_block = self.data.latest_block().number != oracle.latest_block().number;
+            if new_block {
                 self.data.latest_block = oracle.latest_block.clone();
                 self.data.next_block = oracle.next_block.clone();
+                self.data.eth_price = oracle.get_eth_price();
+            }
+            new_block
+        };
+        if new_block {
+            self.pending_requests.prune(self.data.chain_id.id(), self.data.latest_block().number);
+        }
+
+        // sample the spot prices of the watched pairs once per block
+        let chain_id = self.data.chain_id.id();
+        if new_block && self.gui.watches_ui.has_watches(chain_id) {
+            if let Some(client) = self.data.client.clone() {
+                self.send_request(Request::check_watches(chain_id, client));
             }
         }
 
         self.request_eth_balance();
         self.request_erc20_balance();
 
+        // the token list of a chain is loaded the first time we use it
+        request_currency_page(&self.front_sender, self.data.chain_id.id(), "", false);
+
         // Draw the UI that belongs to the Central Panel
         egui::CentralPanel::default().show(ctx, |ui| {
 
@@ -446,11 +791,27 @@ impl eframe::App for ZeusApp {
             let painter = ui.painter();
             painter.add(self.gui.theme.bg_gradient.clone());
 
-            show_login(ui, &mut self.data);
+            if let Some(pid) = self.lock_holder {
+                match already_running_window(ui, pid) {
+                    Some(true) => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+                    Some(false) => self.lock_holder = None,
+                    None => {}
+                }
+                return;
+            }
 
+            self.gui.toasts.show_info_msg(ui);
+
+            // the wizard replaces the new profile screen, but a locked profile is unlocked first
+            let wizard = self.gui.onboarding.state.is_open() && (self.data.logged_in || self.data.new_profile_screen);
+            if wizard {
+                self.gui.show_onboarding(ui, &mut self.data);
+            } else {
+                show_login(ui, &mut self.data);
+            }
 
             // if we are not logged in or we are on the new profile screen, we should not paint the main UI
-            if !self.data.logged_in || self.data.new_profile_screen {
+            if !self.data.logged_in || self.data.new_profile_screen || wizard {
                 return;
             }
 
@@ -470,10 +831,36 @@ impl eframe::App for ZeusApp {
                 let painter = ui.painter();
                 painter.add(self.gui.theme.bg_gradient.clone());
 
-                self.gui.wallet_ui(ui, &mut self.data);
+                if self.data.data_dir_read_only {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(Color32::YELLOW, "Read-only mode: the data directory is not writable, nothing is saved");
+                        if ui.small_button("Choose a data directory").clicked() {
+                            self.gui.data_dir_ui.state.open();
+                        }
+                    });
+                } else if self.data.read_only {
+                    ui.colored_label(Color32::YELLOW, "Read-only mode: another instance of Zeus is running");
+                }
+
+                if self.gui.data_dir_ui.show(ui) {
+                    self.data_dir_moved();
+                }
+
+                if self.data.settings.dry_run {
+                    ui.colored_label(Color32::YELLOW, "Dry-run mode: transactions are simulated but never broadcasted");
+                }
+
+                if let Some(lag) = self.data.node_lag() {
+                    ui.colored_label(Color32::YELLOW, format!("Node is {} seconds behind", lag.as_secs()))
+                        .on_hover_text("Balances and quotes may be stale");
+                }
+
+                if self.gui.wallet_ui(ui, &mut self.data) {
+                    self.force_refresh();
+                }
 
                 ui.horizontal(|ui| {
-                self.gui.settings_menu(ui);
+                self.gui.settings_menu(ui, &mut self.data);
 
                 });
             });
@@ -493,9 +880,16 @@ impl eframe::App for ZeusApp {
 
 
                 // Call Show methods that are not part of the main UI
-                // And they depend on their own `State` or the [SHARED_UI_STATE] to be shown
+                // And they depend on their own `State`, the `SHARED_UI_STATE` or the toasts to be shown
                 self.gui.show_network_settings_ui(ui, &mut self.data);
-                show_err_msg(ui);
+                self.gui.show_settings_ui(ui, &mut self.data);
+                self.gui.show_diagnostics_ui(ui);
+                self.gui.show_historical_balance_ui(ui, &self.data);
+                self.gui.show_raw_tx_ui(ui, &self.data);
+                self.gui.show_broadcast_tx_ui(ui, &self.data);
+                self.gui.show_watches_ui(ui, &self.data);
+                self.gui.show_address_labels_ui(ui, &self.data);
+                self.gui.toasts.show_err_msg(ui);
                 tx_settings_window(ui, &mut self.data);
             });
     }
@@ -0,0 +1,136 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use eframe::egui::{vec2, Color32, Context, Event, Response, RichText, TextEdit, Ui};
+
+use crate::fonts::roboto_regular;
+use zeus_chain::alloy::primitives::Address;
+
+/// A paste that differs from an in-app copy made this long ago is flagged
+pub const PASTE_WINDOW: Duration = Duration::from_secs(120);
+
+/// How many characters of the address are shown large on each side
+const SHOWN_CHARS: usize = 6;
+
+/// How many trailing characters the user has to type back
+const RETYPED_CHARS: usize = 4;
+
+/// The last text copied from within Zeus and when
+static LAST_COPY: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Copy `text` to the clipboard and remember it, so a paste can be checked against it
+pub fn copy_text(ctx: &Context, text: String) {
+    *LAST_COPY.lock().unwrap() = Some((text.clone(), Instant::now()));
+    ctx.copy_text(text);
+}
+
+/// Is `pasted` different from what was copied within Zeus in the last [PASTE_WINDOW]
+///
+/// Clipboard malware swaps a copied address with one that looks alike
+pub fn paste_mismatch(pasted: &str) -> bool {
+    match &*LAST_COPY.lock().unwrap() {
+        Some((copied, at)) if at.elapsed() < PASTE_WINDOW => copied.trim() != pasted.trim(),
+        _ => false,
+    }
+}
+
+/// A single line address field that warns when a paste doesn't match the last in-app copy
+#[derive(Default)]
+pub struct AddressField {
+    hijack_warning: bool,
+}
+
+impl AddressField {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, address: &mut String, width: f32) -> Response {
+        let res = ui.add(
+            TextEdit::singleline(address)
+                .min_size(vec2(width, 25.0))
+                .desired_width(width),
+        );
+
+        if res.has_focus() {
+            let pasted = ui.input(|i| {
+                i.events.iter().find_map(|event| match event {
+                    Event::Paste(text) => Some(text.clone()),
+                    _ => None,
+                })
+            });
+            if let Some(pasted) = pasted {
+                self.hijack_warning = paste_mismatch(&pasted);
+            }
+        }
+
+        if address.is_empty() {
+            self.hijack_warning = false;
+        }
+
+        if self.hijack_warning {
+            ui.label(
+                RichText::new("The pasted address is not the one you copied in Zeus, your clipboard may have been tampered with")
+                    .family(roboto_regular())
+                    .size(13.0)
+                    .color(Color32::RED),
+            );
+        }
+
+        res
+    }
+}
+
+/// Shows the start and end of an address large and asks the user to type back its last characters
+///
+/// Used before sending to a recipient or approving a spender
+#[derive(Default)]
+pub struct AddressCheck {
+    typed: String,
+}
+
+impl AddressCheck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.typed.clear();
+    }
+
+    /// Returns `true` once the user typed the last characters of `address` back
+    pub fn show(&mut self, ui: &mut Ui, address: Address) -> bool {
+        let checksummed = address.to_checksum(None);
+        let (start, end) = address_ends(&checksummed);
+        let expected = &checksummed[checksummed.len() - RETYPED_CHARS..];
+
+        ui.label(
+            RichText::new(format!("0x{}…{}", start, end))
+                .family(roboto_regular())
+                .size(26.0)
+                .color(Color32::WHITE),
+        );
+        ui.add_space(5.0);
+
+        ui.label(
+            RichText::new(format!("Type the last {} characters of the address to continue", RETYPED_CHARS))
+                .family(roboto_regular())
+                .size(13.0),
+        );
+        ui.add(
+            TextEdit::singleline(&mut self.typed)
+                .char_limit(RETYPED_CHARS)
+                .desired_width(60.0),
+        );
+
+        self.typed.trim().eq_ignore_ascii_case(expected)
+    }
+}
+
+/// The first (after `0x`) and last [SHOWN_CHARS] characters of an address
+fn address_ends(address: &str) -> (&str, &str) {
+    let hex = address.strip_prefix("0x").unwrap_or(address);
+    (&hex[..SHOWN_CHARS], &hex[hex.len() - SHOWN_CHARS..])
+}
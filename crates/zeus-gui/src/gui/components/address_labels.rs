@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use crossbeam::channel::Sender;
+use eframe::egui::{vec2, Align2, Button, Color32, Grid, RichText, Sense, TextEdit, Ui, Window};
+
+use super::address_check::AddressField;
+use crate::{fonts::roboto_regular, gui::misc::{rich_text, truncate_address}};
+use zeus_backend::types::Request;
+use zeus_chain::alloy::primitives::Address;
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, UiState, notify_error};
+
+/// The longest label we accept, it is shown in place of the address
+const MAX_LABEL_LEN: usize = 32;
+
+/// Lists the user labels of the current chain and adds new ones
+///
+/// A label is shown instead of the address everywhere we display one
+pub struct AddressLabelsUI {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    pub state: UiState,
+    address: String,
+    address_field: AddressField,
+    label: String,
+}
+
+impl AddressLabelsUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            sender,
+            state: UiState::default(),
+            address: String::new(),
+            address_field: AddressField::new(),
+            label: String::new(),
+        }
+    }
+
+    fn send_request(&self, request: Request) {
+        if let Err(e) = self.sender.send(request) {
+            notify_error(e);
+        }
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let chain_id = data.chain_id.id();
+        let mut labels: Vec<(Address, String)> = SHARED_CACHE
+            .read()
+            .unwrap()
+            .address_labels
+            .iter()
+            .filter(|((chain, _), _)| *chain == chain_id)
+            .map(|((_, address), label)| (*address, label.clone()))
+            .collect();
+        labels.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let address = Address::from_str(self.address.trim()).ok();
+        let label = self.label.trim().to_string();
+
+        let title = RichText::new("Address Labels")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let save = RichText::new("Save").family(roboto_regular()).size(15.0);
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+        let save_button = Button::new(save)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut remove = None;
+        let mut save_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    if labels.is_empty() {
+                        ui.label(rich_text(&format!("No labels on {}", data.chain_id.name()), 13.0));
+                    }
+
+                    Grid::new("address_labels").num_columns(3).show(ui, |ui| {
+                        for (address, label) in &labels {
+                            ui.label(rich_text(label, 13.0));
+                            ui.label(rich_text(&truncate_address(&address.to_string()), 13.0))
+                                .on_hover_text(address.to_string());
+                            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                                remove = Some(*address);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(15.0);
+
+                    ui.label(rich_text("Address", 13.0));
+                    self.address_field.show(ui, &mut self.address, 300.0);
+                    ui.add_space(5.0);
+
+                    ui.label(rich_text("Label", 13.0));
+                    ui.add(
+                        TextEdit::singleline(&mut self.label)
+                            .char_limit(MAX_LABEL_LEN)
+                            .desired_width(200.0),
+                    );
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(address.is_some() && !label.is_empty(), save_button).clicked() {
+                            save_clicked = true;
+                        }
+                        if ui.add(close_button).clicked() {
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if let Some(address) = remove {
+            self.send_request(Request::set_address_label(chain_id, address, String::new()));
+        }
+
+        if let (true, Some(address)) = (save_clicked, address) {
+            self.send_request(Request::set_address_label(chain_id, address, label));
+            self.address.clear();
+            self.label.clear();
+        }
+    }
+}
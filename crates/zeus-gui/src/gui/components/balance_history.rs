@@ -0,0 +1,209 @@
+use crossbeam::channel::Sender;
+use eframe::egui::{vec2, Align2, Button, Color32, Pos2, Rect, Response, RichText, Sense, Shape, Stroke, Ui, Vec2, Window};
+
+use crate::{fonts::roboto_regular, gui::misc::rich_text};
+use zeus_backend::types::Request;
+use zeus_chain::{
+    alloy::primitives::{Address, U256},
+    defi_types::currency::Currency,
+    format_units,
+};
+use zeus_shared_types::{AppData, UiState, notify_error};
+
+/// Size of the sparkline next to the wallet balance
+const SPARKLINE_SIZE: Vec2 = Vec2::new(60.0, 18.0);
+
+/// Size of the chart in the history window
+const CHART_SIZE: Vec2 = Vec2::new(400.0, 150.0);
+
+/// A sparkline of the sampled eth balances of the selected wallet
+///
+/// Clicking it opens a larger chart, the points come from [Request::BalanceHistory]
+pub struct BalanceHistoryUI {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    /// The larger chart window
+    pub state: UiState,
+
+    /// chain, owner and the block of the latest balance we asked the history for
+    requested: Option<(u64, Address, u64)>,
+
+    /// chain and owner of `points`
+    shown: Option<(u64, Address)>,
+
+    /// (block, balance) oldest first
+    points: Vec<(u64, f64)>,
+}
+
+impl BalanceHistoryUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            sender,
+            state: UiState::default(),
+            requested: None,
+            shown: None,
+            points: Vec::new(),
+        }
+    }
+
+    /// Set the history received from the backend, ignored if the wallet or the chain has changed since
+    pub fn set_history(&mut self, chain_id: u64, owner: Address, history: Vec<(u64, U256)>) {
+        if self.requested.is_none_or(|(chain, address, _)| chain != chain_id || address != owner) {
+            return;
+        }
+
+        let decimals = Currency::new_native(chain_id).decimals();
+        self.points = history
+            .into_iter()
+            .map(|(block, balance)| (block, format_units(balance, decimals).parse().unwrap_or_default()))
+            .collect();
+        self.shown = Some((chain_id, owner));
+    }
+
+    /// Ask the backend for the history again once a newer balance was sampled
+    fn request_history(&mut self, data: &AppData) {
+        let chain_id = data.chain_id.id();
+        let owner = data.wallet_address();
+        let (block, _) = data.eth_balance(chain_id, owner);
+
+        if self.requested == Some((chain_id, owner, block)) {
+            return;
+        }
+        self.requested = Some((chain_id, owner, block));
+
+        if let Err(e) = self.sender.send(Request::balance_history(chain_id, owner)) {
+            notify_error(e);
+        }
+    }
+
+    /// The points of the selected wallet and chain, empty until the backend answers
+    fn points(&self, data: &AppData) -> &[(u64, f64)] {
+        if self.shown == Some((data.chain_id.id(), data.wallet_address())) {
+            &self.points
+        } else {
+            &[]
+        }
+    }
+
+    /// Show the sparkline, nothing is shown until there are at least 2 points
+    pub fn sparkline(&mut self, ui: &mut Ui, data: &AppData) {
+        if data.profile.current_wallet.is_none() {
+            return;
+        }
+        self.request_history(data);
+
+        let points = self.points(data);
+        if points.len() < 2 {
+            return;
+        }
+
+        let res = chart(ui, points, SPARKLINE_SIZE).on_hover_text("Balance history, click to expand");
+        if res.clicked() {
+            self.state.open();
+        }
+    }
+
+    /// Show the larger chart
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let points = self.points(data);
+        let symbol = Currency::new_native(data.chain_id.id()).symbol();
+
+        let title = RichText::new("Balance History")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut close_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    if points.len() < 2 {
+                        ui.label(rich_text("Not enough balance samples yet", 13.0));
+                    } else {
+                        let res = chart(ui, points, CHART_SIZE);
+                        if let Some((block, balance)) = hovered_point(&res, points) {
+                            res.on_hover_text(format!("Block {}: {:.4} {}", block, balance, symbol));
+                        }
+
+                        let (min, max) = points
+                            .iter()
+                            .fold((f64::MAX, f64::MIN), |(min, max), (_, b)| (min.min(*b), max.max(*b)));
+                        let (first, last) = (points[0].0, points[points.len() - 1].0);
+                        ui.label(rich_text(&format!("Low {:.4} {} | High {:.4} {}", min, symbol, max, symbol), 13.0));
+                        ui.label(rich_text(&format!("Blocks {} to {}, {} samples", first, last, points.len()), 12.0));
+                    }
+                    ui.add_space(10.0);
+
+                    if ui.add(close_button).clicked() {
+                        close_clicked = true;
+                    }
+                });
+            });
+
+        if close_clicked {
+            self.state.close();
+        }
+    }
+}
+
+/// Draw the balances as a line scaled to `size`, green if the balance went up, red if it went down
+fn chart(ui: &mut Ui, points: &[(u64, f64)], size: Vec2) -> Response {
+    let (rect, res) = ui.allocate_exact_size(size, Sense::click());
+
+    let line: Vec<Pos2> = points
+        .iter()
+        .map(|(block, balance)| to_screen(rect, points, *block, *balance))
+        .collect();
+
+    let first = points[0].1;
+    let last = points[points.len() - 1].1;
+    let color = if last >= first { Color32::GREEN } else { Color32::RED };
+
+    ui.painter().add(Shape::line(line, Stroke::new(1.5, color)));
+    res
+}
+
+/// Blocks on the x axis, balances on the y axis
+fn to_screen(rect: Rect, points: &[(u64, f64)], block: u64, balance: f64) -> Pos2 {
+    let (first, last) = (points[0].0, points[points.len() - 1].0);
+    let (min, max) = points
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), (_, b)| (min.min(*b), max.max(*b)));
+
+    let x = if last > first { (block - first) as f32 / (last - first) as f32 } else { 0.5 };
+    // a flat balance is drawn in the middle
+    let y = if max > min { ((balance - min) / (max - min)) as f32 } else { 0.5 };
+
+    Pos2::new(rect.left() + x * rect.width(), rect.bottom() - y * rect.height())
+}
+
+/// The point under the pointer, by its x position
+fn hovered_point(res: &Response, points: &[(u64, f64)]) -> Option<(u64, f64)> {
+    let pointer = res.hover_pos()?;
+    let rect = res.rect;
+    points
+        .iter()
+        .min_by(|a, b| {
+            let xa = to_screen(rect, points, a.0, a.1).x;
+            let xb = to_screen(rect, points, b.0, b.1).x;
+            (xa - pointer.x).abs().total_cmp(&(xb - pointer.x).abs())
+        })
+        .copied()
+}
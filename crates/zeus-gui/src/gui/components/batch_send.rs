@@ -0,0 +1,469 @@
+use std::str::FromStr;
+
+use crossbeam::channel::Sender;
+use eframe::egui::{vec2, Button, Color32, Grid, RichText, ScrollArea, Sense, TextEdit, Ui};
+
+use super::{
+    address_check::AddressCheck,
+    confirm_tx::{is_large_amount, needs_recipient_check},
+    readiness::batch_readiness,
+};
+use crate::{fonts::roboto_regular, gui::misc::{amount_label, display_address, rich_text}};
+use zeus_backend::types::{BatchRowStatus, BatchSendParams, Request};
+use zeus_chain::{alloy::primitives::{Address, U256}, defi_types::currency::Currency, parse_units};
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, notify_error};
+
+/// The most transfers a single batch can make
+const MAX_BATCH_ROWS: usize = 100;
+
+/// A parsed `address,amount` line, `line` is 1-based so it matches what the user sees
+struct BatchRow {
+    line: usize,
+    row: Result<(Address, U256), String>,
+}
+
+/// Sends the same currency to several recipients, one transaction after the other
+///
+/// The rows are `address,amount` lines, typed, pasted or imported from a CSV file
+pub struct BatchSend {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    rows: String,
+    csv_path: String,
+    abort_on_failure: bool,
+
+    /// The user clicked send and has to confirm the batch
+    confirming: bool,
+
+    /// Recipients are typed back one after the other like in [super::confirm_tx::ConfirmTxWindow]
+    address_check: AddressCheck,
+
+    /// How many of the recipients to check were typed back
+    checked: usize,
+
+    /// The user still wants to send to the token contracts in the batch
+    token_recipients_understood: bool,
+
+    /// The rows of the batch that was sent and their status, empty until a batch is sent
+    sent: Vec<(Address, U256, BatchRowStatus)>,
+}
+
+impl BatchSend {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            sender,
+            rows: String::new(),
+            csv_path: String::new(),
+            abort_on_failure: true,
+            confirming: false,
+            address_check: AddressCheck::new(),
+            checked: 0,
+            token_recipients_understood: false,
+            sent: Vec::new(),
+        }
+    }
+
+    /// Update a row of the batch being sent, see [zeus_backend::types::Response::BatchSend]
+    pub fn set_status(&mut self, index: usize, status: BatchRowStatus) {
+        if let Some((_, _, row_status)) = self.sent.get_mut(index) {
+            *row_status = status;
+        }
+    }
+
+    /// Is a batch still going through
+    pub fn is_sending(&self) -> bool {
+        self.sent.iter().any(|(_, _, status)| !status.is_final())
+    }
+
+    /// Replace the rows with the contents of the CSV file
+    fn import_csv(&mut self) -> Result<(), anyhow::Error> {
+        self.rows = std::fs::read_to_string(self.csv_path.trim())?;
+        Ok(())
+    }
+
+    fn send(&mut self, data: &AppData, currency: &Currency, rows: Vec<(Address, U256)>) {
+        let (Some(wallet), Some(client)) = (data.profile.current_wallet.clone(), data.client.clone()) else {
+            return;
+        };
+
+        let req = Request::batch_send(BatchSendParams {
+            currency: currency.clone(),
+            rows: rows.clone(),
+            wallet,
+            chain_id: data.chain_id.id(),
+            next_base_fee: data.next_block.base_fee,
+            priority_fee: data.tx_settings.parse_gwei(),
+            mev_protect: data.tx_settings.mev_protect,
+            gas_multiplier: data.tx_settings.parse_gas_multiplier(),
+            dry_run: data.settings.dry_run,
+            abort_on_failure: self.abort_on_failure,
+            client,
+        });
+
+        match self.sender.send(req) {
+            Ok(_) => {
+                self.sent = rows
+                    .into_iter()
+                    .map(|(recipient, amount)| (recipient, amount, BatchRowStatus::Pending))
+                    .collect();
+            }
+            Err(e) => {
+                notify_error(e);
+            }
+        }
+    }
+
+    /// Show the batch inside the [super::send_crypto_screen::SendCryptoScreen]
+    ///
+    /// `balance` is the balance of `currency`, `native_balance` pays the fees
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData, currency: &Currency, balance: U256, native_balance: U256) {
+        if !self.sent.is_empty() {
+            self.show_status(ui, data, currency);
+            return;
+        }
+
+        let chain_id = data.chain_id.id();
+        let parsed = parse_rows(&self.rows, currency.decimals());
+        let rows: Vec<_> = parsed.iter().map(|row| row.row.clone()).collect();
+        let readiness = batch_readiness(data, currency, &rows, balance, native_balance);
+
+        ui.label(RichText::new("Recipients").family(roboto_regular()).size(15.0));
+        ui.label(rich_text("One address,amount per line", 12.0).color(Color32::GRAY));
+        ui.add_space(2.0);
+        ui.add_enabled(
+            !self.confirming,
+            TextEdit::multiline(&mut self.rows)
+                .hint_text("0x...,1.5")
+                .desired_rows(5)
+                .desired_width(350.0),
+        );
+
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.csv_path).hint_text("Path to a CSV file").desired_width(250.0));
+            let enabled = !self.confirming && !self.csv_path.trim().is_empty();
+            if ui.add_enabled(enabled, Button::new("Import CSV")).clicked() {
+                if let Err(e) = self.import_csv() {
+                    notify_error(format!("Could not read the CSV file: {}", e));
+                }
+            }
+        });
+        ui.add_space(10.0);
+
+        ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            Grid::new("batch_rows").num_columns(3).striped(true).show(ui, |ui| {
+                for row in &parsed {
+                    ui.label(rich_text(&format!("#{}", row.line), 12.0));
+                    match &row.row {
+                        Ok((recipient, amount)) => {
+                            ui.label(rich_text(&display_address(chain_id, *recipient), 12.0))
+                                .on_hover_text(recipient.to_string());
+                            amount_label(ui, *amount, currency.decimals(), &currency.symbol());
+                        }
+                        Err(e) => {
+                            ui.label(rich_text(e, 12.0).color(Color32::RED));
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        ui.add_space(10.0);
+
+        let total = rows.iter().flatten().fold(U256::ZERO, |total, (_, amount)| total.saturating_add(*amount));
+        let native = data.native_currency();
+        let fee = data.estimate_fee(currency.transfer_gas());
+
+        ui.horizontal(|ui| {
+            ui.label(rich_text("Total:", 13.0));
+            amount_label(ui, total, currency.decimals(), &currency.symbol());
+            ui.label(rich_text(&format!("to {} recipients", rows.len()), 13.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label(rich_text("Fee per transfer:", 13.0));
+            amount_label(ui, fee, native.decimals, &native.symbol);
+            ui.label(rich_text("| All transfers:", 13.0));
+            amount_label(ui, fee * U256::from(rows.len()), native.decimals, &native.symbol);
+        });
+        ui.add_space(5.0);
+
+        ui.checkbox(&mut self.abort_on_failure, "Stop at the first failed transfer")
+            .on_hover_text("Otherwise the remaining rows are still sent");
+        if data.settings.dry_run {
+            ui.label("Dry-run mode: the transfers are simulated and signed but never broadcasted");
+        }
+        ui.add_space(10.0);
+
+        if self.confirming {
+            let valid: Vec<_> = rows.iter().flatten().copied().collect();
+            let reviewed = self.review(ui, data, currency, &valid, total, balance);
+
+            ui.label(rich_text(
+                &format!("Send {} transfers, one after the other?", rows.len()),
+                15.0,
+            ));
+            ui.horizontal(|ui| {
+                if ui.add_enabled(reviewed, button("Confirm")).clicked() {
+                    self.confirming = false;
+                    if readiness.is_ready() {
+                        self.send(data, currency, rows.iter().flatten().copied().collect());
+                    }
+                }
+                if ui.add(button("Back")).clicked() {
+                    self.confirming = false;
+                }
+            });
+        } else {
+            let label = readiness.label(&format!("Send {} transfers", rows.len()));
+            if ui.add_enabled(readiness.is_ready(), button(&label)).clicked() {
+                self.confirming = true;
+                self.checked = 0;
+                self.address_check.reset();
+                self.token_recipients_understood = false;
+            }
+        }
+    }
+
+    /// The checks of [super::confirm_tx::ConfirmTxWindow] over the whole batch
+    ///
+    /// Warns if the total is a large part of the balance or a recipient is a token contract,
+    /// and asks to type back every recipient that gets more than the verification threshold
+    ///
+    /// Returns `true` once everything was reviewed
+    fn review(&mut self, ui: &mut Ui, data: &AppData, currency: &Currency, rows: &[(Address, U256)], total: U256, balance: U256) -> bool {
+        let chain_id = data.chain_id.id();
+
+        if is_large_amount(total, balance, data.settings.large_tx_fraction) {
+            ui.label(
+                rich_text(
+                    &format!(
+                        "Warning: the batch sends more than {:.0}% of your {} balance",
+                        data.settings.large_tx_fraction * 100.0,
+                        currency.symbol()
+                    ),
+                    15.0,
+                )
+                .color(Color32::YELLOW),
+            );
+            ui.add_space(10.0);
+        }
+
+        let token_recipients: Vec<String> = {
+            let cache = SHARED_CACHE.read().unwrap();
+            rows.iter().filter_map(|(recipient, _)| cache.token_contract(chain_id, *recipient)).collect()
+        };
+        if !token_recipients.is_empty() {
+            ui.label(
+                rich_text(
+                    &format!(
+                        "Some recipients are token contracts ({}), funds sent there are usually lost",
+                        token_recipients.join(", ")
+                    ),
+                    15.0,
+                )
+                .color(Color32::RED),
+            );
+            ui.checkbox(&mut self.token_recipients_understood, "Send to the token contracts anyway");
+            ui.add_space(10.0);
+        }
+        let understood = token_recipients.is_empty() || self.token_recipients_understood;
+
+        let to_check = recipients_to_check(rows, |amount| needs_recipient_check(data, currency, amount));
+        let checked = match to_check.get(self.checked) {
+            Some(recipient) => {
+                ui.label(rich_text(&format!("Recipient {} of {} to check", self.checked + 1, to_check.len()), 13.0));
+                if self.address_check.show(ui, *recipient) {
+                    self.checked += 1;
+                    self.address_check.reset();
+                }
+                ui.add_space(10.0);
+                false
+            }
+            None => true,
+        };
+
+        understood && checked
+    }
+
+    /// Show the status of every row of the batch that was sent
+    fn show_status(&mut self, ui: &mut Ui, data: &AppData, currency: &Currency) {
+        let chain_id = data.chain_id.id();
+
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            Grid::new("batch_status").num_columns(3).striped(true).show(ui, |ui| {
+                for (recipient, amount, status) in &self.sent {
+                    ui.label(rich_text(&display_address(chain_id, *recipient), 12.0))
+                        .on_hover_text(recipient.to_string());
+                    amount_label(ui, *amount, currency.decimals(), &currency.symbol());
+
+                    let color = match status {
+                        BatchRowStatus::Confirmed(_) | BatchRowStatus::Simulated => Color32::GREEN,
+                        BatchRowStatus::Failed(_) => Color32::RED,
+                        BatchRowStatus::Pending | BatchRowStatus::Skipped => Color32::GRAY,
+                    };
+                    let res = ui.label(rich_text(&status.label(), 12.0).color(color));
+                    match status {
+                        BatchRowStatus::Confirmed(hash) => {
+                            res.on_hover_text(hash.to_string());
+                        }
+                        BatchRowStatus::Failed(e) => {
+                            res.on_hover_text(e);
+                        }
+                        _ => {}
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        ui.add_space(10.0);
+
+        let done = self.sent.iter().filter(|(_, _, status)| status.is_final()).count();
+        ui.label(rich_text(&format!("{} of {} done", done, self.sent.len()), 13.0));
+
+        if ui.add_enabled(!self.is_sending(), button("New Batch")).clicked() {
+            self.sent.clear();
+        }
+    }
+}
+
+fn button(text: &str) -> Button<'static> {
+    Button::new(RichText::new(text).family(roboto_regular()).size(15.0))
+        .rounding(10.0)
+        .sense(Sense::click())
+        .min_size(vec2(70.0, 25.0))
+}
+
+/// The recipients the user has to type back, once each and in the order of the rows
+///
+/// A recipient is checked if `needs_check` holds for the total it receives in the batch
+fn recipients_to_check(rows: &[(Address, U256)], needs_check: impl Fn(U256) -> bool) -> Vec<Address> {
+    let mut recipients: Vec<(Address, U256)> = Vec::new();
+    for (recipient, amount) in rows {
+        match recipients.iter_mut().find(|(address, _)| address == recipient) {
+            Some((_, total)) => *total = total.saturating_add(*amount),
+            None => recipients.push((*recipient, *amount)),
+        }
+    }
+
+    recipients
+        .into_iter()
+        .filter(|(_, total)| needs_check(*total))
+        .map(|(recipient, _)| recipient)
+        .collect()
+}
+
+/// Parse the `address,amount` lines, `;` and tabs work as separators too
+///
+/// Empty lines, `#` comments and a CSV header are skipped
+fn parse_rows(text: &str, decimals: u8) -> Vec<BatchRow> {
+    let mut rows = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if index == 0 && line.to_lowercase().contains("address") {
+            continue;
+        }
+
+        let row = if rows.len() >= MAX_BATCH_ROWS {
+            Err(format!("A batch can have at most {} rows", MAX_BATCH_ROWS))
+        } else {
+            parse_row(line, decimals)
+        };
+        rows.push(BatchRow { line: index + 1, row });
+    }
+
+    rows
+}
+
+fn parse_row(line: &str, decimals: u8) -> Result<(Address, U256), String> {
+    let fields: Vec<&str> = line.split([',', ';', '\t']).map(str::trim).collect();
+    let [recipient, amount] = fields[..] else {
+        return Err("Expected address,amount".to_string());
+    };
+
+    let recipient = Address::from_str(recipient).map_err(|_| "Invalid address".to_string())?;
+    if recipient.is_zero() {
+        return Err("Cannot send to the zero address".to_string());
+    }
+
+    let amount = parse_units(amount, decimals).map_err(|_| "Invalid amount".to_string())?;
+    if amount.is_zero() {
+        return Err("Amount is zero".to_string());
+    }
+
+    Ok((recipient, amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPIENT: &str = "0x95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5";
+
+    fn recipient() -> Address {
+        Address::from_str(RECIPIENT).unwrap()
+    }
+
+    #[test]
+    fn a_row_takes_any_separator() {
+        for separator in [",", ";", "\t", " , "] {
+            let line = format!("{}{}1.5", RECIPIENT, separator);
+            assert_eq!(parse_row(&line, 6), Ok((recipient(), U256::from(1_500_000u64))));
+        }
+    }
+
+    #[test]
+    fn bad_rows_say_what_is_wrong() {
+        let zero = format!("{},1", Address::ZERO);
+        let cases = [
+            (RECIPIENT.to_string(), "Expected address,amount"),
+            (format!("{},1,2", RECIPIENT), "Expected address,amount"),
+            ("0x1234,1".to_string(), "Invalid address"),
+            (zero, "Cannot send to the zero address"),
+            (format!("{},abc", RECIPIENT), "Invalid amount"),
+            (format!("{},0", RECIPIENT), "Amount is zero"),
+        ];
+        for (line, error) in cases {
+            assert_eq!(parse_row(&line, 18), Err(error.to_string()), "{}", line);
+        }
+    }
+
+    #[test]
+    fn the_csv_header_comments_and_empty_lines_are_skipped() {
+        let text = format!("Address,Amount\n\n# airdrop\n{},1\n  \n{},2\n", RECIPIENT, RECIPIENT);
+        let rows = parse_rows(&text, 0);
+
+        let lines: Vec<usize> = rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, [4, 6]);
+        assert_eq!(rows[1].row, Ok((recipient(), U256::from(2))));
+
+        // only the first line can be a header
+        let rows = parse_rows(&format!("{},1\naddress,1", RECIPIENT), 0);
+        assert_eq!(rows[1].row, Err("Invalid address".to_string()));
+    }
+
+    #[test]
+    fn rows_past_the_limit_are_refused() {
+        let text = vec![format!("{},1", RECIPIENT); MAX_BATCH_ROWS + 2].join("\n");
+        let rows = parse_rows(&text, 0);
+
+        assert_eq!(rows.len(), MAX_BATCH_ROWS + 2);
+        assert!(rows[..MAX_BATCH_ROWS].iter().all(|row| row.row.is_ok()));
+        assert!(rows[MAX_BATCH_ROWS..].iter().all(|row| row.row.is_err()));
+        assert_eq!(rows[MAX_BATCH_ROWS].line, MAX_BATCH_ROWS + 1);
+    }
+
+    #[test]
+    fn recipients_are_checked_on_what_they_get_in_total() {
+        let (a, b, c) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let rows = [(a, U256::from(6)), (b, U256::from(2)), (a, U256::from(6)), (c, U256::from(20))];
+
+        // `a` gets 12 over two rows, `b` stays under the threshold
+        assert_eq!(recipients_to_check(&rows, |total| total >= U256::from(10)), [a, c]);
+        assert!(recipients_to_check(&rows, |_| false).is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use eframe::egui::{CollapsingHeader, Color32, Grid, RichText, ScrollArea, TextEdit, Ui};
+
+use super::address_check::copy_text;
+use crate::{fonts::roboto_regular, gui::misc::rich_text};
+use zeus_chain::{alloy::primitives::Bytes, CALLDATA_DECODER};
+
+/// Show the decoded calldata of a transaction with the raw hex in an expander
+pub fn calldata_view(ui: &mut Ui, call_data: &Bytes) {
+    let call = match CALLDATA_DECODER.decode(call_data) {
+        Some(call) => call,
+        None => {
+            ui.label(rich_text("No call data", 13.0));
+            return;
+        }
+    };
+
+    let function = if call.known {
+        RichText::new(&call.function).color(Color32::WHITE)
+    } else {
+        RichText::new(format!("Unknown function {}", call.function)).color(Color32::YELLOW)
+    };
+    ui.label(function.family(roboto_regular()).size(15.0));
+
+    if !call.args.is_empty() {
+        Grid::new("decoded_calldata").num_columns(2).show(ui, |ui| {
+            for arg in &call.args {
+                ui.label(rich_text(&arg.name, 13.0));
+                ui.label(rich_text(&arg.value, 13.0));
+                ui.end_row();
+            }
+        });
+    }
+
+    CollapsingHeader::new("Raw Call Data").show(ui, |ui| {
+        hex_field(ui, "Call Data", &call_data.to_string());
+    });
+}
+
+/// A read-only hex field with a copy button
+pub fn hex_field(ui: &mut Ui, label: &str, hex: &str) {
+    ui.horizontal(|ui| {
+        ui.label(rich_text(label, 15.0));
+        if ui.button("Copy").clicked() {
+            copy_text(ui.ctx(), hex.to_string());
+        }
+    });
+
+    ScrollArea::vertical()
+        .id_source(label)
+        .max_height(80.0)
+        .show(ui, |ui| {
+            let mut hex = hex.to_string();
+            ui.add(
+                TextEdit::multiline(&mut hex)
+                    .desired_width(420.0)
+                    .interactive(false),
+            );
+        });
+}
@@ -0,0 +1,569 @@
+use eframe::egui::{vec2, Align2, Button, CollapsingHeader, Color32, ComboBox, Grid, RichText, SelectableLabel, Sense, TextEdit, Ui, Window};
+
+use crossbeam::channel::Sender;
+
+use super::{address_check::AddressCheck, calldata::calldata_view};
+use crate::{fonts::roboto_regular, gui::misc::{balance_label, display_address, format_usd_price, rich_text, truncate_address}};
+use zeus_backend::types::{Request, TxEstimate};
+use zeus_chain::{
+    alloy::{eips::eip2930::AccessList, primitives::{Address, U256}},
+    defi_types::currency::{Currency, NativeCurrency},
+    format_units, format_units_with_precision,
+    tx::{TxCall, TxOverrides},
+    utils::known_contract,
+};
+use zeus_core::Wallet;
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, UiState, notify_error};
+
+/// What is about to be signed, shown to the user before anything is sent
+#[derive(Clone)]
+pub struct TxSummary {
+    pub chain: String,
+    pub chain_id: u64,
+    pub to: Address,
+    pub currency: Currency,
+    pub amount: U256,
+
+    /// The currency the fee is paid with
+    pub fee_currency: NativeCurrency,
+
+    /// Estimated fee in [TxSummary::fee_currency]
+    pub gas_fee: U256,
+
+    /// USD value of the fee, `None` if we have no price for the native currency
+    pub gas_fee_usd: Option<U256>,
+
+    /// The amount is more than [zeus_shared_types::Settings::large_tx_fraction] of the balance
+    pub large: bool,
+
+    /// The user has to type back the end of [TxSummary::to] before confirming
+    pub verify_recipient: bool,
+
+    /// What the transaction calls, from the current wallet, needed to estimate it in the advanced settings
+    pub call: Option<TxCall>,
+
+    /// The gas limit the transaction already sets, shown instead of an estimate
+    pub gas_limit: Option<u128>,
+}
+
+impl TxSummary {
+    /// Summarize a transfer of `amount` of `currency` to `to`
+    pub fn transfer(data: &AppData, currency: Currency, amount: U256, to: Address, balance: U256) -> Self {
+        let gas = currency.transfer_gas();
+        let verify_recipient = needs_recipient_check(data, &currency, amount);
+        let (call_to, value, call_data) = currency.transfer_call(to, amount);
+        let call = TxCall::new(data.wallet_address(), call_to, value, call_data);
+
+        let mut summary = Self::new(data, currency, amount, to, balance, gas).with_call(call, None);
+        summary.verify_recipient = verify_recipient;
+        summary
+    }
+
+    /// Let the user estimate the call in the advanced settings, see [TxSummary::call]
+    pub fn with_call(mut self, call: TxCall, gas_limit: Option<u128>) -> Self {
+        self.call = Some(call);
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Summarize a transaction that moves `amount` of `currency` to `to` and uses about `gas`
+    pub fn new(data: &AppData, currency: Currency, amount: U256, to: Address, balance: U256, gas: u64) -> Self {
+        let fee_currency = data.native_currency();
+        let gas_fee = data.estimate_fee(gas);
+        let gas_fee_usd = data.eth_price.and_then(|price| fee_currency.usd_value(gas_fee, price).ok());
+
+        Self {
+            chain: data.chain_id.name(),
+            chain_id: data.chain_id.id(),
+            to,
+            currency,
+            amount,
+            fee_currency,
+            gas_fee,
+            gas_fee_usd,
+            large: is_large_amount(amount, balance, data.settings.large_tx_fraction),
+            verify_recipient: false,
+            call: None,
+            gas_limit: None,
+        }
+    }
+}
+
+/// Is `amount` more than `fraction` of `balance`?
+pub fn is_large_amount(amount: U256, balance: U256, fraction: f32) -> bool {
+    // basis points so we can stay in integers
+    let bps = (fraction.clamp(0.0, 1.0) * 10_000.0) as u64;
+    amount * U256::from(10_000) > balance * U256::from(bps)
+}
+
+/// Does the user have to type back the recipient of `amount` of `currency`
+///
+/// Above [zeus_shared_types::Settings::verify_recipient_usd], or if we can't price the amount
+pub fn needs_recipient_check(data: &AppData, currency: &Currency, amount: U256) -> bool {
+    data.usd_value(currency, amount).is_none_or(|usd| usd >= data.settings.verify_recipient_usd)
+}
+
+/// What we know about the recipient: a known contract, an unverified contract or an EOA
+fn recipient_review(ui: &mut Ui, chain_id: u64, to: Address) {
+    let checked = SHARED_CACHE.read().unwrap().contracts.get(&(chain_id, to)).copied();
+    let (text, color) = match (checked, known_contract(chain_id, to)) {
+        (_, Some(_)) => ("Known contract", Color32::GREEN),
+        (Some(true), None) => ("Unverified contract", Color32::YELLOW),
+        (Some(false), None) => ("Recipient is not a contract", Color32::GRAY),
+        (None, None) => ("Checking the recipient…", Color32::GRAY),
+    };
+    ui.label(RichText::new(text).family(roboto_regular()).size(13.0).color(color));
+}
+
+/// The symbol of the token contract `summary` sends funds to, `None` if it sends nothing to one
+///
+/// Sending the native currency to its wrapped token wraps it, that is not a mistake
+fn token_contract_recipient(summary: &TxSummary) -> Option<String> {
+    let wraps = summary.currency.is_native() && summary.currency.wrapped().address == summary.to;
+    if summary.amount.is_zero() || wraps {
+        return None;
+    }
+    SHARED_CACHE.read().unwrap().token_contract(summary.chain_id, summary.to)
+}
+
+/// Pick between broadcasting the transaction and only signing it, eg. to send it from an offline setup
+pub fn sign_mode_toggle(ui: &mut Ui, sign_only: &mut bool) {
+    ui.horizontal(|ui| {
+        if ui.add(SelectableLabel::new(!*sign_only, "Sign & Send")).clicked() {
+            *sign_only = false;
+        }
+        if ui
+            .add(SelectableLabel::new(*sign_only, "Sign Only"))
+            .on_hover_text("Get the signed transaction to broadcast it yourself, nothing is sent")
+            .clicked()
+        {
+            *sign_only = true;
+        }
+    });
+}
+
+/// The advanced settings of a transaction as typed, turned into [TxOverrides] when it is confirmed
+#[derive(Default)]
+struct AdvancedSettings {
+    gas_limit: String,
+    nonce: String,
+    access_list: Option<AccessList>,
+
+    /// The call we asked the backend to estimate
+    pending: Option<TxCall>,
+
+    /// Why the last estimate failed
+    estimate_error: Option<String>,
+}
+
+impl AdvancedSettings {
+    fn new(summary: &TxSummary) -> Self {
+        Self {
+            gas_limit: summary.gas_limit.map_or(String::new(), |gas_limit| gas_limit.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Empty fields keep what the backend would use
+    fn overrides(&self) -> Result<TxOverrides, anyhow::Error> {
+        let gas_limit = match self.gas_limit.trim() {
+            "" => None,
+            text => Some(text.parse().map_err(|_| anyhow::anyhow!("Invalid gas limit"))?),
+        };
+        let nonce = match self.nonce.trim() {
+            "" => None,
+            text => Some(text.parse().map_err(|_| anyhow::anyhow!("Invalid nonce"))?),
+        };
+        let overrides = TxOverrides { gas_limit, nonce, access_list: self.access_list.clone() };
+        overrides.check()?;
+        Ok(overrides)
+    }
+}
+
+/// Asks the user to confirm a transaction before it is signed
+pub struct ConfirmTxWindow {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    pub state: UiState,
+    pub summary: Option<TxSummary>,
+    address_check: AddressCheck,
+
+    /// The user confirmed sending to a token contract, see [token_contract_recipient]
+    token_recipient_understood: bool,
+
+    /// Show the [sign_mode_toggle]
+    allow_sign_only: bool,
+
+    /// The user only wants the signed transaction, kept between transactions
+    pub sign_only: bool,
+
+    /// Show the [ConfirmTxWindow::signer_picker]
+    allow_signer_choice: bool,
+
+    /// The wallet that signs this transaction, `None` for the current wallet
+    signer: Option<Address>,
+
+    /// Show the advanced settings, see [ConfirmTxWindow::overrides]
+    allow_advanced: bool,
+    advanced: AdvancedSettings,
+}
+
+impl ConfirmTxWindow {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            sender,
+            state: UiState::default(),
+            summary: None,
+            address_check: AddressCheck::new(),
+            token_recipient_understood: false,
+            allow_sign_only: false,
+            sign_only: false,
+            allow_signer_choice: false,
+            signer: None,
+            allow_advanced: false,
+            advanced: AdvancedSettings::default(),
+        }
+    }
+
+    /// Let the user only sign the transaction, see [ConfirmTxWindow::sign_only]
+    pub fn with_sign_only(mut self) -> Self {
+        self.allow_sign_only = true;
+        self
+    }
+
+    /// Let the user sign with another wallet than the current one, see [ConfirmTxWindow::signer]
+    pub fn with_signer_choice(mut self) -> Self {
+        self.allow_signer_choice = true;
+        self
+    }
+
+    /// Let the user set the gas limit, the nonce and an access list, see [ConfirmTxWindow::overrides]
+    pub fn with_advanced(mut self) -> Self {
+        self.allow_advanced = true;
+        self
+    }
+
+    /// What the user set in the advanced settings of the confirmed transaction
+    pub fn overrides(&self) -> Result<TxOverrides, anyhow::Error> {
+        if !self.allow_advanced {
+            return Ok(TxOverrides::default());
+        }
+        self.advanced.overrides()
+    }
+
+    /// Fill the gas limit and the access list with the estimate of the call we asked for
+    pub fn set_estimate(&mut self, call: &TxCall, estimate: &Result<TxEstimate, String>) {
+        if self.advanced.pending.as_ref() != Some(call) {
+            return;
+        }
+        self.advanced.pending = None;
+
+        match estimate {
+            Ok(estimate) => {
+                self.advanced.gas_limit = estimate.gas_limit.to_string();
+                if estimate.access_list.is_some() {
+                    self.advanced.access_list = estimate.access_list.clone();
+                }
+                self.advanced.estimate_error = None;
+            }
+            Err(e) => self.advanced.estimate_error = Some(e.clone()),
+        }
+    }
+
+    /// Ask the backend to estimate the call from the wallet that signs, with an access list if `access_list`
+    fn request_estimate(&mut self, data: &AppData, call: &TxCall, access_list: bool) {
+        let Some(client) = data.client.clone() else {
+            return;
+        };
+        let call = TxCall {
+            from: self.signer.unwrap_or(call.from),
+            ..call.clone()
+        };
+
+        let req = Request::estimate_tx(call.clone(), data.tx_settings.parse_gas_multiplier(), access_list, client);
+        match self.sender.send(req) {
+            Ok(_) => self.advanced.pending = Some(call),
+            Err(e) => notify_error(e),
+        }
+    }
+
+    /// The gas limit, nonce and access list fields
+    ///
+    /// The gas is estimated the first time they are shown, unless the transaction sets it
+    fn advanced_settings(&mut self, ui: &mut Ui, data: &AppData, summary: &TxSummary) {
+        let estimating = self.advanced.pending.is_some();
+        let first_open = self.advanced.gas_limit.is_empty() && self.advanced.estimate_error.is_none();
+        if let (Some(call), true, false) = (&summary.call, first_open, estimating) {
+            self.request_estimate(data, call, false);
+        }
+
+        Grid::new("confirm_tx_advanced").num_columns(2).show(ui, |ui| {
+            ui.label(rich_text("Gas Limit", 13.0));
+            let hint = if estimating { "Estimating…" } else { "Estimated" };
+            ui.add(TextEdit::singleline(&mut self.advanced.gas_limit).hint_text(hint).desired_width(120.0));
+            ui.end_row();
+
+            ui.label(rich_text("Nonce", 13.0));
+            ui.add(TextEdit::singleline(&mut self.advanced.nonce).hint_text("Next nonce").desired_width(120.0))
+                .on_hover_text("Only set it to replace a stuck transaction or fill a gap");
+            ui.end_row();
+        });
+
+        if let Some(e) = &self.advanced.estimate_error {
+            ui.label(rich_text("The node could not estimate the gas, set the limit by hand", 12.0).color(Color32::YELLOW))
+                .on_hover_text(e);
+        }
+        if let Err(e) = self.advanced.overrides() {
+            ui.label(rich_text(&e.to_string(), 12.0).color(Color32::RED));
+        }
+
+        ui.horizontal(|ui| {
+            match &self.advanced.access_list {
+                Some(list) => {
+                    let slots: usize = list.0.iter().map(|item| item.storage_keys.len()).sum();
+                    let details: Vec<String> = list
+                        .0
+                        .iter()
+                        .map(|item| format!("{} ({} slots)", item.address, item.storage_keys.len()))
+                        .collect();
+                    ui.label(rich_text(&format!("Access list: {} addresses, {} slots", list.0.len(), slots), 13.0))
+                        .on_hover_text(details.join("\n"));
+                    if ui.small_button("Remove").clicked() {
+                        self.advanced.access_list = None;
+                    }
+                }
+                None => {
+                    let hint = "Ask the node for the addresses and storage slots the transaction touches (EIP-2930)";
+                    let enabled = summary.call.is_some() && !estimating && data.client.is_some();
+                    let res = ui.add_enabled(enabled, Button::new("Create Access List").small());
+                    if res.on_hover_text(hint).clicked() {
+                        if let Some(call) = &summary.call {
+                            self.request_estimate(data, call, true);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// The wallet that signs the confirmed transaction
+    ///
+    /// The current wallet unless the user picked another one for this transaction
+    pub fn signer(&self, data: &AppData) -> Result<Wallet, anyhow::Error> {
+        match self.signer {
+            Some(address) => data.profile.signer(address),
+            None => data.profile.current_wallet.clone().ok_or(anyhow::anyhow!("No wallet selected")),
+        }
+    }
+
+    /// Pick the wallet that signs, the current wallet stays selected everywhere else
+    fn signer_picker(&mut self, ui: &mut Ui, data: &AppData) {
+        let current = data.wallet_address();
+        let selected = self.signer.unwrap_or(current);
+        let name = data
+            .profile
+            .wallets
+            .iter()
+            .find(|wallet| wallet.key.address() == selected)
+            .map_or(truncate_address(&selected.to_string()), |wallet| wallet.truncated_name());
+
+        ComboBox::from_id_source("confirm_tx_signer")
+            .selected_text(rich_text(&name, 15.0))
+            .show_ui(ui, |ui| {
+                for wallet in &data.profile.wallets {
+                    let address = wallet.key.address();
+                    if ui.selectable_label(address == selected, wallet.truncated_name()).clicked() {
+                        self.signer = (address != current).then_some(address);
+                    }
+                }
+            });
+    }
+
+    /// The balance of a signer that is not the current wallet, its balances may not be loaded
+    fn other_signer_balance(ui: &mut Ui, summary: &TxSummary, signer: Address) {
+        let balance = SHARED_CACHE.read().unwrap().fetched_balance_of(summary.chain_id, signer, &summary.currency);
+        ui.horizontal(|ui| {
+            ui.label(rich_text("Not the selected wallet, its balance:", 13.0).color(Color32::YELLOW));
+            balance_label(ui, balance, summary.currency.decimals(), &summary.currency.symbol());
+        });
+        if balance.is_some_and(|balance| balance < summary.amount) {
+            ui.label(rich_text("This wallet does not have enough to send the amount", 13.0).color(Color32::YELLOW));
+        }
+    }
+
+    /// Open the window for a new transaction
+    ///
+    /// Unless we already know, ask the backend if the recipient is a contract
+    pub fn open(&mut self, data: &AppData, summary: TxSummary) {
+        let checked = SHARED_CACHE.read().unwrap().contracts.contains_key(&(summary.chain_id, summary.to));
+        if let (false, Some(client)) = (checked, data.client.clone()) {
+            let req = Request::check_contract(summary.to, summary.chain_id, client);
+            if let Err(e) = self.sender.send(req) {
+                notify_error(e);
+            }
+        }
+
+        self.advanced = AdvancedSettings::new(&summary);
+        self.summary = Some(summary);
+        self.signer = None;
+        self.token_recipient_understood = false;
+        self.address_check.reset();
+        self.state.open();
+    }
+
+    /// Show this UI
+    ///
+    /// Returns `Some(true)` if the user confirmed, `Some(false)` if cancelled and `None` while waiting
+    ///
+    /// A confirmed transaction is only signed if [ConfirmTxWindow::sign_only] is set
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData) -> Option<bool> {
+        if self.state.is_close() {
+            return None;
+        }
+
+        let summary = self.summary.clone()?;
+        let native = &summary.fee_currency;
+        let token_recipient = token_contract_recipient(&summary);
+
+        let title = RichText::new("Confirm Transaction")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let confirm = RichText::new("Confirm").family(roboto_regular()).size(15.0);
+        let cancel = RichText::new("Cancel").family(roboto_regular()).size(15.0);
+
+        let confirm_button = Button::new(confirm)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let cancel_button = Button::new(cancel)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut choice = None;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    Grid::new("confirm_tx_summary").num_columns(2).show(ui, |ui| {
+                        if self.allow_signer_choice {
+                            ui.label(rich_text("From", 15.0));
+                            self.signer_picker(ui, data);
+                            ui.end_row();
+                        }
+
+                        ui.label(rich_text("Chain", 15.0));
+                        ui.label(rich_text(&summary.chain, 15.0));
+                        ui.end_row();
+
+                        ui.label(rich_text("To", 15.0));
+                        ui.label(rich_text(&display_address(summary.chain_id, summary.to), 15.0))
+                            .on_hover_text(summary.to.to_string());
+                        ui.end_row();
+
+                        ui.label("");
+                        recipient_review(ui, summary.chain_id, summary.to);
+                        ui.end_row();
+
+                        ui.label(rich_text("Amount", 15.0));
+                        ui.label(rich_text(
+                            &format!("{} {}", format_units(summary.amount, summary.currency.decimals()), summary.currency.symbol()),
+                            15.0,
+                        ));
+                        ui.end_row();
+
+                        ui.label(rich_text("Estimated Fee", 15.0));
+                        ui.label(rich_text(
+                            &format!(
+                                "~{} {} ({})",
+                                format_units_with_precision(summary.gas_fee, native.decimals, 6),
+                                native.symbol,
+                                format_usd_price(summary.gas_fee_usd)
+                            ),
+                            15.0,
+                        ));
+                        ui.end_row();
+                    });
+
+                    if let Some(signer) = self.signer {
+                        ui.add_space(10.0);
+                        Self::other_signer_balance(ui, &summary, signer);
+                    }
+
+                    // what the contract is asked to do, a plain native transfer has no call data
+                    if let Some(call) = summary.call.as_ref().filter(|call| !call.data.is_empty()) {
+                        ui.add_space(10.0);
+                        CollapsingHeader::new("Call Data").default_open(true).show(ui, |ui| {
+                            calldata_view(ui, &call.data);
+                        });
+                    }
+
+                    if summary.large {
+                        ui.add_space(10.0);
+                        ui.label(
+                            RichText::new(format!(
+                                "Warning: this is more than {:.0}% of your {} balance",
+                                data.settings.large_tx_fraction * 100.0,
+                                summary.currency.symbol()
+                            ))
+                            .family(roboto_regular())
+                            .size(15.0)
+                            .color(Color32::YELLOW),
+                        );
+                    }
+
+                    if let Some(symbol) = &token_recipient {
+                        ui.add_space(10.0);
+                        ui.label(
+                            rich_text(&format!("This address is the {} token contract, funds sent here are usually lost", symbol), 15.0)
+                                .color(Color32::RED),
+                        );
+                        ui.checkbox(&mut self.token_recipient_understood, "Send to the token contract anyway");
+                    }
+                    ui.add_space(15.0);
+
+                    let verified = !summary.verify_recipient || self.address_check.show(ui, summary.to);
+                    if summary.verify_recipient {
+                        ui.add_space(15.0);
+                    }
+
+                    if self.allow_advanced {
+                        CollapsingHeader::new("Advanced").show(ui, |ui| {
+                            self.advanced_settings(ui, data, &summary);
+                        });
+                        ui.add_space(10.0);
+                    }
+
+                    if self.allow_sign_only {
+                        sign_mode_toggle(ui, &mut self.sign_only);
+                        ui.add_space(10.0);
+                    }
+
+                    ui.horizontal(|ui| {
+                        let valid = !self.allow_advanced || self.advanced.overrides().is_ok();
+                        let understood = token_recipient.is_none() || self.token_recipient_understood;
+                        if ui.add_enabled(verified && valid && understood, confirm_button).clicked() {
+                            choice = Some(true);
+                        }
+                        if ui.add(cancel_button).clicked() {
+                            choice = Some(false);
+                        }
+                    });
+                });
+            });
+
+        if choice.is_some() {
+            self.state.close();
+            self.summary = None;
+        }
+
+        choice
+    }
+}
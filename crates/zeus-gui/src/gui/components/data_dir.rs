@@ -0,0 +1,100 @@
+use eframe::egui::{vec2, Align2, Button, Color32, RichText, Sense, TextEdit, Ui, Window};
+
+use crate::{fonts::roboto_regular, gui::misc::rich_text};
+use zeus_core::migrate_data_dir;
+use zeus_shared_types::{UiState, notify_error};
+
+/// Moves the data of a read-only data directory to a writable one
+///
+/// The profile, the settings and the databases are copied, nothing is removed from the old directory
+#[derive(Default)]
+pub struct DataDirUI {
+    pub state: UiState,
+    path: String,
+}
+
+impl DataDirUI {
+    pub fn new() -> Self {
+        Self {
+            state: UiState::default(),
+            path: String::new(),
+        }
+    }
+
+    /// Show this UI
+    ///
+    /// Returns `true` once the data was moved and the new directory is the current one
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui) -> bool {
+        if self.state.is_close() {
+            return false;
+        }
+
+        let title = RichText::new("Data Directory")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let migrate = RichText::new("Move").family(roboto_regular()).size(15.0);
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+        let migrate_button = Button::new(migrate)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut migrate_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(rich_text("The current data directory is read-only", 13.0));
+                    ui.label(
+                        rich_text("Your profile, settings and cached data are copied to the new one", 12.0)
+                            .color(Color32::GRAY),
+                    );
+                    ui.add_space(10.0);
+
+                    ui.add(
+                        TextEdit::singleline(&mut self.path)
+                            .hint_text("Path to a writable directory")
+                            .desired_width(300.0),
+                    );
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.path.trim().is_empty(), migrate_button).clicked() {
+                            migrate_clicked = true;
+                        }
+                        if ui.add(close_button).clicked() {
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if !migrate_clicked {
+            return false;
+        }
+
+        match migrate_data_dir(self.path.trim()) {
+            Ok(_) => {
+                self.state.close();
+                true
+            }
+            Err(e) => {
+                notify_error(format!("Could not move the data directory: {}", e));
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use eframe::egui::{vec2, Align2, Button, Color32, Grid, ProgressBar, RichText, Sense, Ui, Window};
+
+use crate::{fonts::roboto_regular, gui::misc::rich_text};
+use zeus_chain::RPC_USAGE;
+use zeus_shared_types::UiState;
+
+/// Shows how many rpc calls each feature made in this session
+#[derive(Default)]
+pub struct DiagnosticsUI {
+    pub state: UiState,
+}
+
+impl DiagnosticsUI {
+    pub fn new() -> Self {
+        Self {
+            state: UiState::default(),
+        }
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let usage = RPC_USAGE.read().unwrap().clone();
+        let total = usage.total();
+        let minutes = usage.elapsed().as_secs() / 60;
+
+        let title = RichText::new("Diagnostics")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let reset = RichText::new("Reset").family(roboto_regular()).size(15.0);
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+        let reset_button = Button::new(reset)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.set_min_size(vec2(300.0, 200.0));
+
+                    ui.label(rich_text(&format!("{} RPC calls in the last {} min", total, minutes), 15.0));
+                    ui.add_space(10.0);
+
+                    Grid::new("rpc_usage").num_columns(3).show(ui, |ui| {
+                        for (origin, count) in usage.breakdown() {
+                            let fraction = if total == 0 { 0.0 } else { count as f32 / total as f32 };
+
+                            ui.label(rich_text(origin.name(), 14.0));
+                            ui.add(ProgressBar::new(fraction).desired_width(150.0));
+                            ui.label(rich_text(&count.to_string(), 14.0));
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add(reset_button).clicked() {
+                            RPC_USAGE.write().unwrap().reset();
+                        }
+                        if ui.add(close_button).clicked() {
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+    }
+}
@@ -0,0 +1,105 @@
+use eframe::egui::{vec2, Align2, Button, Color32, RichText, Sense, Ui, Window};
+
+use super::calldata::{calldata_view, hex_field};
+use crate::{fonts::roboto_regular, gui::misc::{rich_text, truncate_address}};
+use zeus_chain::tx::DryRunResult;
+use zeus_shared_types::UiState;
+
+/// Shows what would have been broadcasted when dry-run mode is on
+#[derive(Default)]
+pub struct DryRunWindow {
+    pub state: UiState,
+    pub result: Option<DryRunResult>,
+}
+
+impl DryRunWindow {
+    pub fn new() -> Self {
+        Self {
+            state: UiState::default(),
+            result: None,
+        }
+    }
+
+    /// Open the window with a new result
+    pub fn open(&mut self, result: DryRunResult) {
+        self.result = Some(result);
+        self.state.open();
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let result = match &self.result {
+            Some(result) => result.clone(),
+            None => return,
+        };
+
+        let title = RichText::new("Dry Run")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let (outcome, outcome_color) = if result.simulation.success {
+            ("Simulation succeeded", Color32::GREEN)
+        } else {
+            ("Simulation reverted", Color32::RED)
+        };
+
+        let close = RichText::new("Close")
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.set_max_width(450.0);
+
+                ui.vertical_centered(|ui| {
+                    ui.label(rich_text("Nothing was broadcasted", 15.0));
+                    ui.add_space(10.0);
+
+                    ui.label(
+                        RichText::new(outcome)
+                            .family(roboto_regular())
+                            .size(15.0)
+                            .color(outcome_color),
+                    );
+                    ui.label(rich_text(&format!("Gas used: {}", result.simulation.gas_used), 13.0));
+                    if let Some(l1_gas) = result.simulation.l1_gas_used {
+                        ui.label(rich_text(&format!("Of which L1 data fee: {} gas", l1_gas), 13.0));
+                    }
+                    ui.label(rich_text(&result.simulation.output, 13.0));
+                    ui.add_space(10.0);
+
+                    ui.label(rich_text(&format!("To: {}", truncate_address(&result.to.to_string())), 13.0))
+                        .on_hover_text(result.to.to_string());
+                    ui.label(rich_text(&format!("Amount: {}", result.amount.text()), 13.0));
+                    ui.label(rich_text(&format!("Tx Hash: {}", result.tx_hash), 13.0));
+                    ui.add_space(10.0);
+
+                    calldata_view(ui, &result.call_data);
+                    ui.add_space(10.0);
+                    hex_field(ui, "Raw Transaction", &result.raw_tx.to_string());
+                    ui.add_space(15.0);
+
+                    if ui.add(close_button).clicked() {
+                        self.state.close();
+                        self.result = None;
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,179 @@
+use eframe::egui::{vec2, Align2, Button, Color32, ComboBox, RichText, Sense, TextEdit, Ui, Window};
+use crossbeam::channel::Sender;
+
+use crate::{fonts::roboto_regular, gui::misc::rich_text, theme::THEME};
+use zeus_backend::types::{HistoricalBalanceRes, Request};
+use zeus_chain::{defi_types::currency::Currency, format_units};
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, UiState, notify_error};
+
+/// Look up the balance of the current wallet at a past block
+pub struct HistoricalBalanceUI {
+    pub state: UiState,
+
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    currency: Currency,
+    block: String,
+
+    /// The last balance we got from the backend
+    result: Option<HistoricalBalanceRes>,
+
+    /// Waiting for the backend
+    pending: bool,
+}
+
+impl HistoricalBalanceUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            state: UiState::default(),
+            sender,
+            currency: Currency::default(),
+            block: String::new(),
+            result: None,
+            pending: false,
+        }
+    }
+
+    /// Set the result received from the backend
+    pub fn set_result(&mut self, result: HistoricalBalanceRes) {
+        self.result = Some(result);
+        self.pending = false;
+    }
+
+    /// The backend reports failures (eg. the node pruned that state) through an error notification
+    pub fn on_error(&mut self) {
+        self.pending = false;
+    }
+
+    /// Send the request to the backend
+    fn lookup(&mut self, data: &AppData, block: u64) {
+        let client = match data.client().clone() {
+            Some(client) => client,
+            None => {
+                notify_error("You are not connected to a node");
+                return;
+            }
+        };
+
+        let req = Request::historical_balance(
+            self.currency.clone(),
+            data.wallet_address(),
+            data.chain_id.id(),
+            block,
+            client,
+        );
+
+        match self.sender.send(req) {
+            Ok(_) => {
+                self.result = None;
+                self.pending = true;
+            }
+            Err(e) => {
+                notify_error(e);
+            }
+        }
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let chain_id = data.chain_id.id();
+        let currencies = SHARED_CACHE
+            .read()
+            .unwrap()
+            .currencies
+            .get(&chain_id)
+            .cloned()
+            .unwrap_or_default();
+
+        // the previous chain's currency is of no use here
+        if !currencies.contains(&self.currency) {
+            self.currency = Currency::new_native(chain_id);
+        }
+
+        let title = RichText::new("Balance at Block")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let block = self.block.trim().parse::<u64>().ok();
+
+        let lookup = RichText::new("Lookup").family(roboto_regular()).size(15.0);
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+        let lookup_button = Button::new(lookup)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut lookup_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.set_min_size(vec2(250.0, 150.0));
+
+                    ComboBox::from_id_source("historical_balance_currency")
+                        .selected_text(self.currency.symbol())
+                        .show_ui(ui, |ui| {
+                            for currency in &currencies {
+                                ui.selectable_value(&mut self.currency, currency.clone(), currency.symbol());
+                            }
+                        });
+                    ui.add_space(10.0);
+
+                    ui.add(
+                        TextEdit::singleline(&mut self.block)
+                            .hint_text(format!("Block number (latest {})", data.latest_block().number))
+                            .desired_width(200.0),
+                    );
+                    ui.add_space(10.0);
+
+                    if let Some(res) = &self.result {
+                        ui.horizontal(|ui| {
+                            ui.add(THEME.icons.icon_for_currency(ui.ctx(), &res.currency));
+                            ui.label(rich_text(
+                                &format!(
+                                    "{} {} at block {}",
+                                    format_units(res.balance, res.currency.decimals()),
+                                    res.currency.symbol(),
+                                    res.block
+                                ),
+                                15.0,
+                            ));
+                        });
+                    } else if self.pending {
+                        ui.spinner();
+                    }
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(block.is_some() && !self.pending, lookup_button).clicked() {
+                            lookup_clicked = true;
+                        }
+                        if ui.add(close_button).clicked() {
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if let (true, Some(block)) = (lookup_clicked, block) {
+            self.lookup(data, block);
+        }
+    }
+}
@@ -1,17 +1,56 @@
+pub mod address_check;
+pub mod address_labels;
+pub mod balance_history;
+pub mod batch_send;
+pub mod calldata;
+pub mod confirm_tx;
+pub mod data_dir;
+pub mod diagnostics;
+pub mod dry_run;
+pub mod historical_balance;
+pub mod onboarding;
+pub mod raw_tx;
+pub mod readiness;
+pub mod router;
 pub mod send_crypto_screen;
+pub mod settings;
+pub mod signed_tx;
 pub mod swap_ui;
+pub mod toasts;
 pub mod wallet;
+pub mod watches;
 
-use crate::{fonts::roboto_regular, icons::IconTextures, theme::THEME};
+use crate::{fonts::roboto_regular, gui::misc::{amount_label, truncate_address}, icons::IconTextures, theme::THEME};
 use crossbeam::channel::Sender;
 use eframe::egui::{
-    emath::Vec2b, vec2, Align, Align2, Button, Color32, FontId, Layout, RichText, ScrollArea, Sense, TextEdit, Ui, Window
+    emath::Vec2b, vec2, Align, Align2, Button, CollapsingHeader, Color32, FontId, Layout, RichText, ScrollArea, Sense, TextEdit, Ui, Window
 };
 use std::{str::FromStr, sync::Arc};
 use tracing::trace;
 use zeus_backend::types::*;
-use zeus_chain::{alloy::primitives::Address, defi_types::currency::Currency, utils::format_wei};
-use zeus_shared_types::{cache::SHARED_CACHE, AppData, UiState, SHARED_UI_STATE};
+use zeus_chain::{alloy::primitives::Address, defi_types::currency::Currency};
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, PickSide, UiState, notify_error};
+
+/// Ask the backend for a page of the token list of `chain_id` matching `query`
+///
+/// Only the first page is asked for unless `more` is set, nothing is sent while a page is loading
+pub fn request_currency_page(sender: &Sender<Request>, chain_id: u64, query: &str, more: bool) {
+    let offset = SHARED_CACHE.write().unwrap().next_currency_page(chain_id, query, more);
+    if let Some(offset) = offset {
+        if let Err(e) = sender.send(Request::currency_page(chain_id, query.to_string(), offset)) {
+            notify_error(e);
+        }
+    }
+}
+
+/// Does the symbol, name or address of `currency` contain the lowercase `query`
+fn matches_query(currency: &Currency, query: &str) -> bool {
+    currency.symbol().to_lowercase().contains(query)
+        || currency.name().to_lowercase().contains(query)
+        || currency
+            .erc20()
+            .is_some_and(|token| token.address.to_string().to_lowercase().contains(query))
+}
 
 pub struct TokenSelectionWindow {
     pub state: UiState,
@@ -62,11 +101,11 @@ impl TokenSelectionWindow {
     ///
     /// # Returns
     ///
-    /// The selected currency
+    /// The selected currency, which is also moved to the front of the recent ones of its side
     fn show(
         &mut self,
         ui: &mut Ui,
-        data: &AppData,
+        data: &mut AppData,
         currencies: &Vec<Currency>,
     ) -> Option<Currency> {
         if self.state.is_close() {
@@ -75,6 +114,10 @@ impl TokenSelectionWindow {
 
         let chain_id = data.chain_id.id();
         let owner = data.wallet_address();
+        let query = self.search_query.trim().to_lowercase();
+
+        // searching looks in the whole token list, not only in the pages loaded so far
+        request_currency_page(&self.sender, chain_id, &query, false);
 
         let select = RichText::new("Select a Token")
             .family(roboto_regular())
@@ -83,6 +126,17 @@ impl TokenSelectionWindow {
 
         let mut selected_currency: Option<Currency> = None;
 
+        // pinned tokens go to the top, the rest keep their order
+        let mut listed: Vec<(usize, &Currency, bool)> = {
+            let cache = SHARED_CACHE.read().unwrap();
+            currencies
+                .iter()
+                .enumerate()
+                .map(|(index, currency)| (index, currency, cache.is_pinned(chain_id, currency)))
+                .collect()
+        };
+        listed.sort_by_key(|(_, _, pinned)| !pinned);
+
         Window::new(select)
             .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
             .resizable(false)
@@ -101,127 +155,91 @@ impl TokenSelectionWindow {
                     ui.add_space(5.0);
                 });
 
-                ScrollArea::vertical()
+                if let Some(currency) = self.recent_section(ui, data, currencies) {
+                    selected_currency = Some(currency);
+                    self.state.close();
+                }
+
+                let scroll = ScrollArea::vertical()
                     .auto_shrink(Vec2b::new(false, false))
                     .show(ui, |ui| {
-                        for (index, currency) in currencies.iter().enumerate() {
-                            match currency {
-                                Currency::Native(native) => {
-                                    if native.symbol.to_lowercase().contains(&self.search_query) {
-                                        ui.push_id(index, |ui| {
-                                            let cache = SHARED_CACHE.read().unwrap();
-                                            let (_, balance) =
-                                                cache.get_eth_balance(chain_id, owner);
-                                            let balance = format_wei(
-                                                &balance.to_string(),
-                                                currency.decimals(),
-                                            );
-                                            let formated_balance = format!("{:.4}", balance);
-                                            let balance_text = RichText::new(format!(
-                                                "{} {}",
-                                                formated_balance, native.symbol
-                                            ))
-                                            .size(15.0)
-                                            .family(roboto_regular())
-                                            .color(Color32::WHITE);
-
-                                            let name = RichText::new(native.name.clone())
-                                                .size(15.0)
-                                                .family(roboto_regular())
-                                                .color(Color32::WHITE);
-
-   
-                                            let icon = THEME.icons.currency_icon(chain_id);
-
-                                            let button = Button::image_and_text(icon, name)
-                                                .rounding(10.0)
-                                                .sense(Sense::click())
-                                                .min_size(vec2(70.0, 25.0));
-
-                                           
-                                                ui.horizontal(|ui| {
-                                        
-                                                if ui.add(button).clicked() {
-                                                    selected_currency = Some(currency.clone());
-                                                    self.state.close();
-                                                }
-                                                ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
-                                                ui.label(balance_text);
-                                            });
-                                        });
-                                           
-
-                                            ui.add_space(5.0);
-                                        });
+                        let mut hidden = 0;
+                        for (index, currency, pinned) in listed {
+                            if !matches_query(currency, &query) {
+                                continue;
+                            }
+
+                            // the display settings only apply to the full list, searching still finds every token
+                            let balance = SHARED_CACHE.read().unwrap().balance_of(chain_id, owner, currency);
+                            if self.search_query.is_empty() && data.hide_balance(currency, balance) {
+                                hidden += 1;
+                                continue;
+                            }
+
+                            ui.push_id(index, |ui| {
+                                let name = RichText::new(currency.name())
+                                    .size(15.0)
+                                    .family(roboto_regular())
+                                    .color(Color32::WHITE);
+
+                                // tokens without an icon use the currency icon cause the
+                                // erc20 placeholder is diplayed blurry
+                                let icon = THEME.icons.icon_for_currency(ui.ctx(), currency);
+
+                                let button = Button::image_and_text(icon, name)
+                                    .rounding(10.0)
+                                    .sense(Sense::click())
+                                    .min_size(vec2(70.0, 25.0));
+
+                                ui.horizontal(|ui| {
+                                    if ui.add(button).clicked() {
+                                        selected_currency = Some(currency.clone());
+                                        self.state.close();
                                     }
-                                }
-                                Currency::ERC20(token) => {
-                                    if token.symbol.to_lowercase().contains(&self.search_query) {
-                                        ui.push_id(index, |ui| {
-                                            let cache = SHARED_CACHE.read().unwrap();
-                                            let balance = cache.get_erc20_balance(
-                                                &chain_id,
-                                                &owner,
-                                                &token.address,
-                                            );
-                                            // TODO: use something like numformat
-                                            // to deal with very large numbers
-                                            let balance =
-                                                format_wei(&balance.to_string(), token.decimals);
-                                            let formated_balance = format!("{:.4}", balance);
-                                            let balance_text = RichText::new(format!(
-                                                "{} {}",
-                                                formated_balance, token.symbol
-                                            ))
-                                            .size(15.0)
-                                            .family(roboto_regular())
-                                            .color(Color32::WHITE);
-
-                                            let name = RichText::new(token.name.clone())
-                                                .size(15.0)
+                                    if let Some(token) = currency.erc20() {
+                                        let (star, hint) = if pinned { ("★", "Unpin") } else { ("☆", "Pin to the top") };
+                                        if ui.small_button(star).on_hover_text(hint).clicked() {
+                                            self.send_request(Request::set_pinned(chain_id, token.address, !pinned));
+                                        }
+                                        ui.label(
+                                            RichText::new(truncate_address(&token.address.to_string()))
+                                                .size(11.0)
                                                 .family(roboto_regular())
-                                                .color(Color32::WHITE);
-
-                                            // Use the currency icon cause the
-                                            // erc20 placeholder is diplayed blurry 
-                                            let icon = THEME.icons.currency_icon(chain_id);
-
-                                            let button = Button::image_and_text(icon, name)
-                                                .rounding(10.0)
-                                                .sense(Sense::click())
-                                                .min_size(vec2(70.0, 25.0));
-
-                                            
-                                                ui.horizontal(|ui| {
-                                                if ui.add(button).clicked() {
-                                                    selected_currency = Some(currency.clone());
-                                                    self.state.close();
-                                                }
-                                                ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
-                                                ui.label(balance_text);
-                                            });
-                                        });
-                                           
-                                            ui.add_space(5.0);
-                                        
-                                    });
+                                                .color(Color32::GRAY),
+                                        );
                                     }
-                                }
-                            }
-                        }
+                                    ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                                        // TODO: use something like numformat
+                                        // to deal with very large numbers
+                                        amount_label(ui, balance, currency.decimals(), &currency.symbol());
+                                    });
+                                });
 
-                        let add_token_text = RichText::new("Add Token")
-                            .size(15.0)
-                            .family(roboto_regular())
-                            .color(Color32::WHITE);
+                                ui.add_space(5.0);
+                            });
+                        }
 
-                        let add_token_button = Button::new(add_token_text)
-                            .rounding(10.0)
-                            .sense(Sense::click())
-                            .min_size(vec2(70.0, 25.0));
+                        if hidden > 0 {
+                            ui.label(
+                                RichText::new(format!("{} tokens hidden by the display settings", hidden))
+                                    .size(11.0)
+                                    .family(roboto_regular())
+                                    .color(Color32::GRAY),
+                            );
+                        }
 
                         // if search string is a valid ethereum address
                         if let Ok(address) = Address::from_str(&self.search_query) {
+                            let add_token_text = RichText::new(format!("Add Token {}", truncate_address(&address.to_string())))
+                                .size(15.0)
+                                .family(roboto_regular())
+                                .color(Color32::WHITE);
+
+                            let add_token_button = Button::new(add_token_text)
+                                .rounding(10.0)
+                                .sense(Sense::click())
+                                .min_size(vec2(70.0, 25.0));
+
                             ui.vertical_centered(|ui| {
 
                             if ui.add(add_token_button).clicked() {
@@ -229,15 +247,15 @@ impl TokenSelectionWindow {
                                 let client = match data.client().clone() {
                                     Some(client) => client,
                                     None => {
-                                        let mut state = SHARED_UI_STATE.write().unwrap();
-                                        state.err_msg.show("You are not connected to a node");
+                                        notify_error("You are not connected to a node");
                                         return;
                                     }
                                 };
                                 let owner = data.wallet_address();
                                 let chain_id = data.chain_id.id();
 
-                                let req = Request::erc20_token(self.get_id(), owner, address, chain_id, client );
+                                let source = data.settings.token_metadata_source;
+                                let req = Request::erc20_token(self.get_id(), owner, address, chain_id, client, source, false);
                                 self.send_request(req);
 
                                 self.state.close();
@@ -245,21 +263,87 @@ impl TokenSelectionWindow {
                         });
                         }
                     });
+
+                // load the next page once the user scrolled close to the end of the list
+                let bottom = scroll.state.offset.y + scroll.inner_rect.height();
+                if bottom >= scroll.content_size.y - 100.0 {
+                    request_currency_page(&self.sender, chain_id, &query, true);
+                }
             });
+
+        if let Some(currency) = &selected_currency {
+            data.settings.add_recent_currency(chain_id, PickSide::from_id(&self.currency_id), currency);
+            if !data.read_only {
+                if let Err(e) = data.save_settings() {
+                    notify_error(format!("Error saving settings: {}", e));
+                }
+            }
+        }
+
         selected_currency
     }
+
+    /// The "Recent" section, the currencies recently picked for this side as one-click chips
+    ///
+    /// Recents that are no longer in `currencies` are skipped, nothing is shown if none are left
+    fn recent_section(&self, ui: &mut Ui, data: &AppData, currencies: &[Currency]) -> Option<Currency> {
+        let recents: Vec<&Currency> = data
+            .settings
+            .recents(data.chain_id.id(), PickSide::from_id(&self.currency_id))
+            .iter()
+            .filter_map(|recent| currencies.iter().find(|c| recent.matches(c)))
+            .collect();
+
+        if recents.is_empty() {
+            return None;
+        }
+
+        let mut clicked = None;
+        let title = RichText::new("Recent").size(13.0).family(roboto_regular()).color(Color32::GRAY);
+        CollapsingHeader::new(title)
+            .id_source("recent_currencies")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (index, currency) in recents.into_iter().enumerate() {
+                        ui.push_id(("recent", index), |ui| {
+                            let symbol = RichText::new(currency.symbol())
+                                .size(13.0)
+                                .family(roboto_regular())
+                                .color(Color32::WHITE);
+
+                            let chip = Button::new(symbol)
+                                .rounding(10.0)
+                                .stroke((0.3, Color32::WHITE))
+                                .min_size(vec2(40.0, 20.0));
+
+                            if ui.add(chip).on_hover_text(currency.name()).clicked() {
+                                clicked = Some(currency.clone());
+                            }
+                        });
+                    }
+                });
+            });
+        ui.add_space(5.0);
+
+        clicked
+    }
 }
 
 
 
 pub struct NetworkSettings {
     pub state: UiState,
+
+    /// Confirms a router override, see [router::router_row]
+    router_override: router::RouterOverrideUI,
 }
 
 impl NetworkSettings {
-    pub fn new() -> Self {
+    pub fn new(sender: Sender<Request>) -> Self {
         Self {
             state: UiState::default(),
+            router_override: router::RouterOverrideUI::new(sender),
         }
     }
 
@@ -291,7 +375,7 @@ impl NetworkSettings {
             .min_size(vec2(70.0, 25.0));
 
         let font = FontId::new(15.0, roboto_regular());
-
+        let mut router_action = None;
 
         Window::new(settings)
             .resizable(false)
@@ -303,12 +387,18 @@ impl NetworkSettings {
 
                     ui.add_space(20.0);
 
-                    for network in data.rpc.iter_mut() {
+                    let visible: Vec<u64> = data.visible_chains().iter().map(|chain_id| chain_id.id()).collect();
+                    for index in 0..data.rpc.len() {
+                        let chain_id = data.rpc[index].chain_id;
+                        if !visible.contains(&chain_id) {
+                            continue;
+                        }
+
                         ui.horizontal(|ui| {
                             ui.add_space(60.0);
-                            ui.add(icons.chain_icon(&network.chain_id));
+                            ui.add(icons.chain_icon(&chain_id));
                             ui.add_space(3.0);
-                            let text = RichText::new(network.chain_name())
+                            let text = RichText::new(data.rpc[index].chain_name())
                                 .family(roboto_regular())
                                 .size(15.0)
                                 .color(Color32::WHITE);
@@ -316,11 +406,16 @@ impl NetworkSettings {
                         });
 
                         ui.add_space(10.0);
-                        let text_edit = TextEdit::singleline(&mut network.url)
+                        let text_edit = TextEdit::singleline(&mut data.rpc[index].url)
                             .font(font.clone())
                             .text_color(Color32::WHITE)
                             .desired_width(200.0);
                         ui.add(text_edit);
+                        ui.add_space(5.0);
+
+                        if let Some(action) = router::router_row(ui, data, chain_id) {
+                            router_action = Some(action);
+                        }
                         ui.add_space(10.0);
                     }
 
@@ -331,13 +426,19 @@ impl NetworkSettings {
                                 self.state.close();
                             }
                             Err(e) => {
-                                let mut state = SHARED_UI_STATE.write().unwrap();
-                                state.err_msg.show(&format!("Error saving network settings: {}", e));
+                                notify_error(&format!("Error saving network settings: {}", e));
                                 self.state.close();
                             }
                         }
                     }
                 });
-            });                      
+            });
+
+        match router_action {
+            Some(router::RouterAction::Override(chain_id)) => self.router_override.open(chain_id),
+            Some(router::RouterAction::Reset(chain_id)) => self.router_override.reset(data, chain_id),
+            None => {}
+        }
+        self.router_override.show(ui, data);
+    }
 }
-}
\ No newline at end of file
@@ -0,0 +1,381 @@
+use eframe::egui::{vec2, Align2, Button, Color32, ComboBox, FontId, RichText, ScrollArea, Sense, TextEdit, Ui, Window};
+use crossbeam::channel::Sender;
+use std::str::FromStr;
+use tracing::trace;
+
+use super::wallet::WalletUI;
+use crate::{
+    fonts::roboto_regular,
+    gui::misc::{new_profile_form, rich_text},
+};
+use zeus_backend::types::Request;
+use zeus_chain::alloy::primitives::Address;
+use zeus_shared_types::{AppData, UiState, notify_error, notify_info};
+
+/// The id of the token requests sent by the wizard, the swap panel ignores them
+const IMPORT_ID: &str = "import";
+
+/// The steps of the [OnboardingWizard], in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WizardStep {
+    #[default]
+    Credentials,
+    Wallet,
+    Network,
+    Tokens,
+}
+
+impl WizardStep {
+    pub const ALL: [WizardStep; 4] = [
+        WizardStep::Credentials,
+        WizardStep::Wallet,
+        WizardStep::Network,
+        WizardStep::Tokens,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            WizardStep::Credentials => "Create your profile",
+            WizardStep::Wallet => "Add a wallet",
+            WizardStep::Network => "Connect to a chain",
+            WizardStep::Tokens => "Import tokens (optional)",
+        }
+    }
+
+    /// What the step is about, for users that never used a wallet app before
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            WizardStep::Credentials => {
+                "Your wallets are encrypted with this username and password and only stored on this computer. \
+                 If you forget them, the wallets cannot be recovered."
+            }
+            WizardStep::Wallet => {
+                "A wallet is a private key that owns your funds. Create a new one or import a key you already have."
+            }
+            WizardStep::Network => {
+                "Zeus talks to a chain through an RPC endpoint, a websocket url of a node. \
+                 Keep the default one or paste your own, then test the connection."
+            }
+            WizardStep::Tokens => {
+                "Paste the addresses of the tokens you hold, one per line, so their balances show up. \
+                 You can also add tokens later from the token selection."
+            }
+        }
+    }
+
+    /// 1-based position of the step
+    pub fn number(&self) -> usize {
+        Self::ALL.iter().position(|step| step == self).unwrap_or_default() + 1
+    }
+
+    pub fn next(&self) -> Option<Self> {
+        Self::ALL.get(self.number()).copied()
+    }
+
+    pub fn back(&self) -> Option<Self> {
+        self.number().checked_sub(2).map(|i| Self::ALL[i])
+    }
+}
+
+/// Where the user is in the [OnboardingWizard]
+#[derive(Debug, Clone, Default)]
+pub struct WizardState {
+    pub step: WizardStep,
+}
+
+impl WizardState {
+    /// Is the current step done so the user can move to the next one
+    pub fn can_advance(&self, data: &AppData) -> bool {
+        match self.step {
+            WizardStep::Credentials => data.profile_exists && data.logged_in,
+            WizardStep::Wallet => !data.profile.wallets.is_empty(),
+            WizardStep::Network => data.connected_chain.as_ref() == Some(&data.chain_id),
+            WizardStep::Tokens => true,
+        }
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.step.next().is_none()
+    }
+
+    pub fn next(&mut self) {
+        if let Some(next) = self.step.next() {
+            self.step = next;
+        }
+    }
+
+    pub fn back(&mut self) {
+        if let Some(back) = self.step.back() {
+            self.step = back;
+        }
+    }
+}
+
+/// Walks a new user through creating a profile, adding a wallet, connecting to a chain and importing tokens
+///
+/// Shown on the first run until it is finished, it can be opened again from the settings menu
+pub struct OnboardingWizard {
+    pub state: UiState,
+
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    pub wizard: WizardState,
+
+    /// The token addresses pasted in the last step
+    token_list: String,
+}
+
+impl OnboardingWizard {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            state: UiState::default(),
+            sender,
+            wizard: WizardState::default(),
+            token_list: String::new(),
+        }
+    }
+
+    /// Open the wizard on its first step
+    pub fn open(&mut self) {
+        self.wizard = WizardState::default();
+        self.state.open();
+    }
+
+    fn send_request(&self, request: Request) {
+        if let Err(e) = self.sender.send(request) {
+            notify_error(e);
+        }
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &mut AppData, wallet_ui: &mut WalletUI) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let title = RichText::new("Welcome to Zeus")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let step = self.wizard.step;
+        let can_advance = self.wizard.can_advance(data);
+
+        let mut back_clicked = false;
+        let mut next_clicked = false;
+        let mut close_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.set_min_size(vec2(420.0, 380.0));
+                    ui.set_max_width(420.0);
+
+                    ui.label(
+                        RichText::new(format!("Step {} of {}", step.number(), WizardStep::ALL.len()))
+                            .family(roboto_regular())
+                            .size(13.0)
+                            .color(Color32::GRAY),
+                    );
+                    ui.label(rich_text(step.title(), 18.0));
+                    ui.add_space(5.0);
+                    ui.label(rich_text(step.explanation(), 13.0));
+                    ui.add_space(20.0);
+
+                    match step {
+                        WizardStep::Credentials => self.credentials_step(ui, data),
+                        WizardStep::Wallet => self.wallet_step(ui, data, wallet_ui),
+                        WizardStep::Network => self.network_step(ui, data),
+                        WizardStep::Tokens => self.tokens_step(ui, data),
+                    }
+                    ui.add_space(20.0);
+
+                    let back = RichText::new("Back").family(roboto_regular()).size(15.0);
+                    let next = RichText::new(if self.wizard.is_last() { "Finish" } else { "Next" })
+                        .family(roboto_regular())
+                        .size(15.0);
+                    let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+                    ui.horizontal(|ui| {
+                        let back_button = Button::new(back).rounding(10.0).min_size(vec2(70.0, 25.0));
+                        if ui.add_enabled(step.back().is_some(), back_button).clicked() {
+                            back_clicked = true;
+                        }
+
+                        let next_button = Button::new(next).rounding(10.0).min_size(vec2(70.0, 25.0));
+                        if ui.add_enabled(can_advance, next_button).clicked() {
+                            next_clicked = true;
+                        }
+
+                        // only when opened again from the settings, a new user has to go through it
+                        if data.settings.onboarding_done {
+                            let close_button = Button::new(close).rounding(10.0).min_size(vec2(70.0, 25.0));
+                            if ui.add(close_button).clicked() {
+                                close_clicked = true;
+                            }
+                        }
+                    });
+                });
+            });
+
+        if back_clicked {
+            self.wizard.back();
+        }
+
+        if next_clicked {
+            self.leave_step(data);
+        }
+
+        if close_clicked {
+            self.state.close();
+        }
+    }
+
+    /// Save what the current step changed and move on, the last step finishes the wizard
+    fn leave_step(&mut self, data: &mut AppData) {
+        if self.wizard.step == WizardStep::Network && !data.read_only {
+            if let Err(e) = data.save_rpc() {
+                notify_error(format!("Error saving network settings: {}", e));
+            }
+        }
+
+        if !self.wizard.is_last() {
+            self.wizard.next();
+            return;
+        }
+
+        data.settings.onboarding_done = true;
+        if !data.read_only {
+            if let Err(e) = data.save_settings() {
+                notify_error(format!("Error saving settings: {}", e));
+            }
+        }
+        trace!("Onboarding finished");
+        self.token_list.clear();
+        self.state.close();
+    }
+
+    fn credentials_step(&mut self, ui: &mut Ui, data: &mut AppData) {
+        if data.profile_exists {
+            ui.label(rich_text("Your profile is ready", 15.0));
+        } else {
+            new_profile_form(ui, data);
+        }
+    }
+
+    fn wallet_step(&mut self, ui: &mut Ui, data: &mut AppData, wallet_ui: &mut WalletUI) {
+        // the wallet windows are shown by the top panel, we only open them
+        ui.horizontal(|ui| {
+            ui.add_space(100.0);
+            if ui.button(rich_text("Create New Wallet", 15.0)).clicked() {
+                wallet_ui.create_wallet_ui.state.open();
+            }
+            if ui.button(rich_text("Import Wallet", 15.0)).clicked() {
+                wallet_ui.import_wallet_ui.state.open();
+            }
+        });
+        ui.add_space(10.0);
+
+        if data.profile.current_wallet.is_none() {
+            data.profile.current_wallet = data.profile.wallets.first().cloned();
+        }
+
+        for wallet in &data.profile.wallets {
+            ui.label(rich_text(&format!("{} ({})", wallet.name, wallet.key.address()), 13.0));
+        }
+    }
+
+    fn network_step(&mut self, ui: &mut Ui, data: &mut AppData) {
+        let mut chain_id = data.chain_id.clone();
+        ComboBox::from_id_source("onboarding_chain")
+            .selected_text(chain_id.name())
+            .show_ui(ui, |ui| {
+                for chain in data.visible_chains() {
+                    ui.selectable_value(&mut chain_id, chain.clone(), chain.name());
+                }
+            });
+        ui.add_space(10.0);
+
+        let rpc = data.rpc.iter_mut().find(|rpc| rpc.chain_id == chain_id.id());
+        let Some(rpc) = rpc else {
+            ui.label(RichText::new("No RPC endpoint for this chain").size(13.0).color(Color32::RED));
+            return;
+        };
+
+        ui.add(
+            TextEdit::singleline(&mut rpc.url)
+                .font(FontId::new(15.0, roboto_regular()))
+                .text_color(Color32::WHITE)
+                .desired_width(300.0),
+        );
+        ui.add_space(10.0);
+
+        let connected = data.connected_chain.as_ref() == Some(&chain_id) && data.chain_id == chain_id;
+        let test_button = Button::new(RichText::new("Test").family(roboto_regular()).size(15.0))
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        // connecting makes it the selected chain, on failure we go back to the previous one
+        if ui.add(test_button).clicked() || chain_id != data.chain_id {
+            data.chain_id = chain_id.clone();
+            self.send_request(Request::client(chain_id.clone(), data.rpc.clone()));
+        }
+        ui.add_space(5.0);
+
+        let (status, color) = if connected {
+            (format!("Connected to {}", chain_id.name()), Color32::GREEN)
+        } else {
+            ("Not connected".to_string(), Color32::GRAY)
+        };
+        ui.label(RichText::new(status).size(13.0).color(color));
+    }
+
+    fn tokens_step(&mut self, ui: &mut Ui, data: &mut AppData) {
+        ScrollArea::vertical().id_source("onboarding_tokens").max_height(120.0).show(ui, |ui| {
+            ui.add(
+                TextEdit::multiline(&mut self.token_list)
+                    .hint_text("0x...")
+                    .code_editor()
+                    .desired_width(400.0),
+            );
+        });
+        ui.add_space(5.0);
+
+        let lines: Vec<&str> = self.token_list.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        let invalid: Vec<&str> = lines.iter().copied().filter(|line| Address::from_str(line).is_err()).collect();
+        for line in &invalid {
+            ui.label(RichText::new(format!("Invalid address: {}", line)).size(13.0).color(Color32::RED));
+        }
+
+        let import_button = Button::new(RichText::new("Import").family(roboto_regular()).size(15.0))
+            .rounding(10.0)
+            .min_size(vec2(70.0, 25.0));
+
+        if ui.add_enabled(!lines.is_empty() && invalid.is_empty(), import_button).clicked() {
+            let Some(client) = data.client().clone() else {
+                notify_error("You are not connected to a node");
+                return;
+            };
+
+            let owner = data.wallet_address();
+            let chain_id = data.chain_id.id();
+            let source = data.settings.token_metadata_source;
+            for token in lines.iter().filter_map(|line| Address::from_str(line).ok()) {
+                let req = Request::erc20_token(IMPORT_ID.to_string(), owner, token, chain_id, client.clone(), source, false);
+                self.send_request(req);
+            }
+
+            {
+                notify_info(format!("Importing {} tokens", lines.len()));
+            }
+            self.token_list.clear();
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use eframe::egui::{vec2, Align2, Button, Color32, Grid, RichText, ScrollArea, Sense, TextEdit, Ui, Window};
+use crossbeam::channel::Sender;
+
+use super::{
+    calldata::calldata_view,
+    confirm_tx::{ConfirmTxWindow, TxSummary},
+};
+use crate::{fonts::roboto_regular, gui::misc::{display_address, rich_text, truncate_address}};
+use zeus_backend::types::{BroadcastRawTxParams, Request, SignRawTxParams, TxEstimate};
+use zeus_chain::{
+    defi_types::currency::{Currency, NATIVE_TRANSFER_GAS},
+    format_units,
+    raw_tx::{RawTx, SignedRawTx},
+    tx::{TxCall, TxOverrides},
+};
+use zeus_core::Wallet;
+use zeus_shared_types::{AppData, UiState, notify_error, notify_info, t};
+
+/// Sign and broadcast an unsigned EIP-1559 transaction pasted as json
+pub struct RawTxUI {
+    pub state: UiState,
+
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    json: String,
+
+    /// The json the user is confirming, what is signed even if [RawTxUI::json] is edited meanwhile
+    reviewed_json: String,
+    confirm_window: ConfirmTxWindow,
+}
+
+impl RawTxUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            state: UiState::default(),
+            sender: sender.clone(),
+            json: String::new(),
+            reviewed_json: String::new(),
+            confirm_window: ConfirmTxWindow::new(sender).with_signer_choice().with_advanced(),
+        }
+    }
+
+    /// See [ConfirmTxWindow::set_estimate]
+    pub fn set_tx_estimate(&mut self, call: &TxCall, estimate: &Result<TxEstimate, String>) {
+        self.confirm_window.set_estimate(call, estimate);
+    }
+
+    /// Send the transaction to the backend to be signed by `wallet`, it is simulated first and never broadcasted if `dry_run`
+    fn send(&self, data: &AppData, tx: RawTx, wallet: Option<Wallet>, overrides: TxOverrides, dry_run: bool) {
+        let (Some(wallet), Some(client)) = (wallet, data.client.clone()) else {
+            notify_error("You need a wallet and a connection to a node");
+            return;
+        };
+
+        let req = Request::sign_raw_tx(SignRawTxParams {
+            tx,
+            wallet,
+            next_base_fee: data.next_block.base_fee,
+            priority_fee: data.tx_settings.parse_gwei(),
+            mev_protect: data.tx_settings.mev_protect,
+            gas_multiplier: data.tx_settings.parse_gas_multiplier(),
+            dry_run,
+            overrides,
+            client,
+        });
+
+        if let Err(e) = self.sender.send(req) {
+            notify_error(e);
+        }
+    }
+
+    /// The reviewed transaction parsed again for the wallet picked in the [ConfirmTxWindow], its `from` has to match it
+    fn signed_by_picked_wallet(&self, data: &AppData) -> Result<(RawTx, Wallet), anyhow::Error> {
+        let wallet = self.confirm_window.signer(data)?;
+        let tx = RawTx::parse(&self.reviewed_json, data.chain_id.id(), wallet.key.address()).map_err(|errors| {
+            let errors: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+            anyhow::anyhow!("{}", errors.join(", "))
+        })?;
+        Ok((tx, wallet))
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let chain_id = data.chain_id.id();
+        let parsed = if self.json.trim().is_empty() {
+            None
+        } else {
+            Some(RawTx::parse(&self.json, chain_id, data.wallet_address()))
+        };
+        let tx = parsed.as_ref().and_then(|parsed| parsed.as_ref().ok()).cloned();
+        let confirming = self.confirm_window.state.is_open();
+
+        let title = RichText::new("Sign Raw Transaction")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let simulate = RichText::new("Simulate").family(roboto_regular()).size(15.0);
+        let sign = RichText::new("Sign & Send").family(roboto_regular()).size(15.0);
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+        let simulate_button = Button::new(simulate)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let sign_button = Button::new(sign)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut simulate_clicked = false;
+        let mut sign_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.set_min_size(vec2(450.0, 300.0));
+
+                    ui.label(rich_text(
+                        &format!("Paste an unsigned EIP-1559 transaction for {} (chain id {})", data.chain_id.name(), chain_id),
+                        13.0,
+                    ));
+                    ui.add_space(5.0);
+
+                    ScrollArea::vertical().id_source("raw_tx_json").max_height(150.0).show(ui, |ui| {
+                        ui.add(
+                            TextEdit::multiline(&mut self.json)
+                                .interactive(!confirming)
+                                .hint_text(r#"{ "chainId": 1, "to": "0x...", "value": "0x0", "data": "0x..." }"#)
+                                .code_editor()
+                                .desired_width(430.0),
+                        );
+                    });
+                    ui.add_space(10.0);
+
+                    match &parsed {
+                        Some(Err(errors)) => {
+                            for error in errors {
+                                ui.label(RichText::new(error.to_string()).size(13.0).color(Color32::RED));
+                            }
+                        }
+                        Some(Ok(tx)) => {
+                            let native = data.native_currency();
+                            let from_node = "from the node";
+
+                            Grid::new("raw_tx_fields").num_columns(2).show(ui, |ui| {
+                                ui.label(rich_text("To", 13.0));
+                                ui.label(rich_text(&display_address(data.chain_id.id(), tx.to), 13.0))
+                                    .on_hover_text(tx.to.to_string());
+                                ui.end_row();
+
+                                ui.label(rich_text("Value", 13.0));
+                                ui.label(rich_text(&format!("{} {}", format_units(tx.value, native.decimals), native.symbol), 13.0));
+                                ui.end_row();
+
+                                ui.label(rich_text("Nonce", 13.0));
+                                ui.label(rich_text(&tx.nonce.map_or(from_node.to_string(), |n| n.to_string()), 13.0));
+                                ui.end_row();
+
+                                ui.label(rich_text("Gas Limit", 13.0));
+                                ui.label(rich_text(&tx.gas_limit.map_or(from_node.to_string(), |g| g.to_string()), 13.0));
+                                ui.end_row();
+                            });
+                            ui.add_space(5.0);
+
+                            calldata_view(ui, &tx.data);
+                        }
+                        None => {}
+                    }
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(tx.is_some(), simulate_button).clicked() {
+                            simulate_clicked = true;
+                        }
+                        let res = ui.add_enabled(tx.is_some() && !confirming && !data.read_only, sign_button);
+                        if res.on_disabled_hover_text(t("readiness.read_only")).clicked() {
+                            sign_clicked = true;
+                        }
+                        if ui.add(close_button).clicked() {
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if let (true, Some(tx)) = (simulate_clicked, &tx) {
+            self.send(data, tx.clone(), data.profile.current_wallet.clone(), TxOverrides::default(), true);
+        }
+
+        if let (true, Some(tx)) = (sign_clicked, &tx) {
+            let currency = Currency::new_native(chain_id);
+            let balance = data.eth_balance(chain_id, data.wallet_address()).1;
+            let gas = tx.gas_limit.unwrap_or(NATIVE_TRANSFER_GAS);
+            let call = TxCall::new(data.wallet_address(), tx.to, tx.value, tx.data.clone());
+            let summary = TxSummary::new(data, currency, tx.value, tx.to, balance, gas).with_call(call, tx.gas_limit.map(u128::from));
+            self.reviewed_json = self.json.clone();
+            self.confirm_window.open(data, summary);
+        }
+
+        if let Some(true) = self.confirm_window.show(ui, data) {
+            let confirmed = self.signed_by_picked_wallet(data).and_then(|(tx, wallet)| Ok((tx, wallet, self.confirm_window.overrides()?)));
+            match confirmed {
+                Ok((tx, wallet, overrides)) => self.send(data, tx, Some(wallet), overrides, data.settings.dry_run),
+                Err(e) => notify_error(e),
+            }
+        }
+    }
+}
+
+/// Broadcast a transaction that was signed elsewhere, pasted as the hex `eth_sendRawTransaction` takes
+pub struct BroadcastTxUI {
+    pub state: UiState,
+
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    hex: String,
+
+    /// The hex we last decoded and the result, so we do not recover the signer every frame
+    decoded: Option<(String, Result<SignedRawTx, String>)>,
+
+    /// The user clicked Broadcast and has to confirm it
+    confirming: bool,
+}
+
+impl BroadcastTxUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            state: UiState::default(),
+            sender,
+            hex: String::new(),
+            decoded: None,
+            confirming: false,
+        }
+    }
+
+    fn decode(&mut self) -> Option<Result<SignedRawTx, String>> {
+        let hex = self.hex.trim();
+        if hex.is_empty() {
+            return None;
+        }
+
+        let stale = self.decoded.as_ref().is_none_or(|(decoded, _)| decoded != hex);
+        if stale {
+            let result = SignedRawTx::decode(hex).map_err(|e| e.to_string());
+            self.decoded = Some((hex.to_string(), result));
+            self.confirming = false;
+        }
+
+        self.decoded.as_ref().map(|(_, result)| result.clone())
+    }
+
+    fn broadcast(&self, data: &AppData, tx: SignedRawTx) {
+        let Some(client) = data.client.clone() else {
+            notify_error("You are not connected to a node");
+            return;
+        };
+
+        if data.settings.dry_run {
+            notify_info(format!("Dry-run mode is on, {} was not broadcasted", tx.hash));
+            return;
+        }
+
+        let hash = tx.hash;
+        let req = Request::broadcast_raw_tx(BroadcastRawTxParams {
+            tx,
+            chain_id: data.chain_id.id(),
+            mev_protect: data.tx_settings.mev_protect,
+            client,
+        });
+
+        match self.sender.send(req) {
+            Ok(_) => notify_info(format!("Broadcasting {}, waiting for the receipt", hash)),
+            Err(e) => notify_error(e),
+        }
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let chain_id = data.chain_id.id();
+        let decoded = self.decode();
+        let chain_error = match &decoded {
+            Some(Ok(tx)) => tx.check_chain(chain_id).err(),
+            _ => None,
+        };
+        let tx = decoded.as_ref().and_then(|decoded| decoded.as_ref().ok()).cloned();
+
+        let title = RichText::new("Broadcast Raw Transaction")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let broadcast = RichText::new("Broadcast").family(roboto_regular()).size(15.0);
+        let confirm = RichText::new("Confirm").family(roboto_regular()).size(15.0);
+        let cancel = RichText::new("Cancel").family(roboto_regular()).size(15.0);
+        let close = RichText::new("Close").family(roboto_regular()).size(15.0);
+
+        let mut confirm_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.set_min_size(vec2(450.0, 300.0));
+
+                    ui.label(rich_text(
+                        &format!("Paste a signed transaction for {} (chain id {})", data.chain_id.name(), chain_id),
+                        13.0,
+                    ));
+                    ui.add_space(5.0);
+
+                    ScrollArea::vertical().id_source("broadcast_tx_hex").max_height(150.0).show(ui, |ui| {
+                        ui.add(
+                            TextEdit::multiline(&mut self.hex)
+                                .hint_text("0x02f8...")
+                                .code_editor()
+                                .desired_width(430.0),
+                        );
+                    });
+                    ui.add_space(10.0);
+
+                    match &decoded {
+                        Some(Err(e)) => {
+                            ui.label(RichText::new(e).size(13.0).color(Color32::RED));
+                        }
+                        Some(Ok(tx)) => {
+                            let native = data.native_currency();
+                            let ours = data.profile.wallets.iter().any(|wallet| wallet.key.address() == tx.from);
+                            let to = tx.to.map_or("Contract deployment".to_string(), |to| display_address(data.chain_id.id(), to));
+                            let chain = tx.chain_id.map_or("None".to_string(), |id| id.to_string());
+
+                            Grid::new("broadcast_tx_fields").num_columns(2).show(ui, |ui| {
+                                ui.label(rich_text("Hash", 13.0));
+                                ui.label(rich_text(&truncate_address(&tx.hash.to_string()), 13.0))
+                                    .on_hover_text(tx.hash.to_string());
+                                ui.end_row();
+
+                                ui.label(rich_text("From", 13.0));
+                                ui.label(rich_text(&display_address(data.chain_id.id(), tx.from), 13.0))
+                                    .on_hover_text(tx.from.to_string());
+                                ui.end_row();
+
+                                ui.label(rich_text("To", 13.0));
+                                ui.label(rich_text(&to, 13.0));
+                                ui.end_row();
+
+                                ui.label(rich_text("Value", 13.0));
+                                ui.label(rich_text(&format!("{} {}", format_units(tx.value, native.decimals), native.symbol), 13.0));
+                                ui.end_row();
+
+                                ui.label(rich_text("Nonce", 13.0));
+                                ui.label(rich_text(&tx.nonce.to_string(), 13.0));
+                                ui.end_row();
+
+                                ui.label(rich_text("Chain Id", 13.0));
+                                ui.label(rich_text(&chain, 13.0));
+                                ui.end_row();
+                            });
+                            ui.add_space(5.0);
+
+                            // broadcasting for someone else is fine, but it should not be a surprise
+                            if !ours {
+                                ui.label(
+                                    RichText::new("The sender is not one of your wallets")
+                                        .size(13.0)
+                                        .color(Color32::YELLOW),
+                                );
+                            }
+                            if let Some(e) = &chain_error {
+                                ui.label(RichText::new(e.to_string()).size(13.0).color(Color32::RED));
+                            }
+
+                            calldata_view(ui, &tx.data);
+                        }
+                        None => {}
+                    }
+                    ui.add_space(15.0);
+
+                    let can_broadcast = tx.is_some() && chain_error.is_none() && !data.read_only;
+                    if data.read_only {
+                        ui.label(rich_text(&t("readiness.read_only"), 13.0).color(Color32::YELLOW));
+                    }
+                    if self.confirming {
+                        ui.label(rich_text("A broadcasted transaction cannot be cancelled", 13.0));
+                    }
+
+                    ui.horizontal(|ui| {
+                        if self.confirming {
+                            if ui.add_enabled(can_broadcast, Button::new(confirm).rounding(10.0).min_size(vec2(70.0, 25.0))).clicked() {
+                                confirm_clicked = true;
+                                self.confirming = false;
+                            }
+                            if ui.add(Button::new(cancel).rounding(10.0).min_size(vec2(70.0, 25.0))).clicked() {
+                                self.confirming = false;
+                            }
+                        } else if ui
+                            .add_enabled(can_broadcast, Button::new(broadcast).rounding(10.0).min_size(vec2(70.0, 25.0)))
+                            .clicked()
+                        {
+                            self.confirming = true;
+                        }
+
+                        if ui.add(Button::new(close).rounding(10.0).min_size(vec2(70.0, 25.0))).clicked() {
+                            self.confirming = false;
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if let (true, Some(tx)) = (confirm_clicked, tx) {
+            self.broadcast(data, tx);
+        }
+    }
+}
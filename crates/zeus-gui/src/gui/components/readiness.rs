@@ -0,0 +1,349 @@
+use std::str::FromStr;
+use zeus_chain::{alloy::primitives::{Address, U256}, defi_types::currency::Currency};
+use zeus_shared_types::{AmountInput, AppData, t, tr};
+
+/// Whether a transaction (Swap, Send) can be made and if not, why
+///
+/// This is evaluated on every frame and the button is disabled until it is [TxReadiness::Ready]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxReadiness {
+    Ready,
+
+    /// No wallet is selected
+    NoWallet,
+
+    /// Another instance owns the data, it would send with the same nonces
+    ReadOnly,
+
+    /// We don't have a client for the selected chain
+    NotConnected,
+
+    /// The amount is empty or zero
+    EnterAmount,
+
+    /// The amount could not be parsed
+    InvalidAmount,
+
+    /// The amount is more than the balance of the currency (symbol)
+    InsufficientBalance(String),
+
+    /// Not enough of the native currency (symbol) left to pay the fee
+    InsufficientGas(String),
+
+    /// The recipient is empty
+    EnterRecipient,
+
+    /// The recipient is not a valid address
+    InvalidRecipient,
+
+    /// Some rows of a batch send could not be parsed (count)
+    InvalidRows(usize),
+
+    /// The router can't move this much of the token (symbol) yet
+    NeedsApproval(String),
+}
+
+impl TxReadiness {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready)
+    }
+
+    /// The label of the button, `action` is used when we are ready (eg. "Swap")
+    pub fn label(&self, action: &str) -> String {
+        match self {
+            Self::Ready => action.to_string(),
+            Self::NoWallet => t("readiness.no_wallet"),
+            Self::ReadOnly => t("readiness.read_only"),
+            Self::NotConnected => t("readiness.not_connected"),
+            Self::EnterAmount => t("readiness.enter_amount"),
+            Self::InvalidAmount => t("readiness.invalid_amount"),
+            Self::InsufficientBalance(symbol) => tr("readiness.insufficient_balance", &[("symbol", symbol)]),
+            Self::InsufficientGas(symbol) => tr("readiness.insufficient_gas", &[("symbol", symbol)]),
+            Self::EnterRecipient => t("readiness.enter_recipient"),
+            Self::InvalidRecipient => t("readiness.invalid_recipient"),
+            Self::InvalidRows(count) => tr("readiness.invalid_rows", &[("count", &count.to_string())]),
+            Self::NeedsApproval(symbol) => tr("readiness.needs_approval", &[("symbol", symbol)]),
+        }
+    }
+}
+
+/// Evaluate if a swap of `amount` of `currency` can be made
+pub fn swap_readiness(data: &AppData, currency: &Currency, amount: &AmountInput, balance: U256) -> TxReadiness {
+    if data.profile.current_wallet.is_none() {
+        return TxReadiness::NoWallet;
+    }
+
+    if data.read_only {
+        return TxReadiness::ReadOnly;
+    }
+
+    if !data.connected() {
+        return TxReadiness::NotConnected;
+    }
+
+    amount_readiness(currency, amount, balance)
+}
+
+/// Is the `allowance` of the router enough to swap `amount` of `currency`
+///
+/// `allowance` is `None` for the native currency or if we don't know it yet
+pub fn allowance_readiness(currency: &Currency, amount: &AmountInput, allowance: Option<U256>) -> TxReadiness {
+    match (allowance, amount.amount()) {
+        (Some(allowance), Some(amount)) if amount > allowance => TxReadiness::NeedsApproval(currency.symbol()),
+        _ => TxReadiness::Ready,
+    }
+}
+
+/// Evaluate if `amount` of `currency` can be sent to `recipient`
+///
+/// `native_balance` is the balance of the native currency which pays the fee
+pub fn send_readiness(
+    data: &AppData,
+    currency: &Currency,
+    amount: &AmountInput,
+    balance: U256,
+    native_balance: U256,
+    recipient: &str,
+) -> TxReadiness {
+    let readiness = swap_readiness(data, currency, amount, balance);
+    if !readiness.is_ready() {
+        return readiness;
+    }
+
+    let readiness = gas_readiness(data, currency, amount, native_balance);
+    if !readiness.is_ready() {
+        return readiness;
+    }
+
+    let recipient = recipient.trim();
+    if recipient.is_empty() {
+        return TxReadiness::EnterRecipient;
+    }
+
+    if Address::from_str(recipient).is_err() {
+        return TxReadiness::InvalidRecipient;
+    }
+
+    TxReadiness::Ready
+}
+
+/// Evaluate if every row of a batch send can be sent, `rows` are the parsed (recipient, amount)
+///
+/// The total is checked against the balance and the fee of every transfer against `native_balance`
+pub fn batch_readiness(
+    data: &AppData,
+    currency: &Currency,
+    rows: &[Result<(Address, U256), String>],
+    balance: U256,
+    native_balance: U256,
+) -> TxReadiness {
+    if data.profile.current_wallet.is_none() {
+        return TxReadiness::NoWallet;
+    }
+
+    if data.read_only {
+        return TxReadiness::ReadOnly;
+    }
+
+    if !data.connected() {
+        return TxReadiness::NotConnected;
+    }
+
+    if rows.is_empty() {
+        return TxReadiness::EnterRecipient;
+    }
+
+    let invalid = rows.iter().filter(|row| row.is_err()).count();
+    if invalid > 0 {
+        return TxReadiness::InvalidRows(invalid);
+    }
+
+    let total = rows.iter().flatten().fold(U256::ZERO, |total, (_, amount)| total.saturating_add(*amount));
+    if total > balance {
+        return TxReadiness::InsufficientBalance(currency.symbol());
+    }
+
+    let fees = data.estimate_fee(currency.transfer_gas()) * U256::from(rows.len());
+    let spent = if currency.is_native() { total.saturating_add(fees) } else { fees };
+    if spent > native_balance {
+        return TxReadiness::InsufficientGas(data.native_currency().symbol);
+    }
+
+    TxReadiness::Ready
+}
+
+/// Is there enough of the native currency left to pay the fee of a transfer
+fn gas_readiness(data: &AppData, currency: &Currency, amount: &AmountInput, native_balance: U256) -> TxReadiness {
+    let fee = data.estimate_fee(currency.transfer_gas());
+
+    // a native transfer pays the fee from the same balance
+    let spent = if currency.is_native() {
+        amount.amount_or_zero() + fee
+    } else {
+        fee
+    };
+
+    if spent > native_balance {
+        return TxReadiness::InsufficientGas(data.native_currency().symbol);
+    }
+
+    TxReadiness::Ready
+}
+
+fn amount_readiness(currency: &Currency, amount: &AmountInput, balance: U256) -> TxReadiness {
+    if amount.is_empty() {
+        return TxReadiness::EnterAmount;
+    }
+
+    let Some(amount) = amount.amount() else {
+        return TxReadiness::InvalidAmount;
+    };
+
+    if amount.is_zero() {
+        return TxReadiness::EnterAmount;
+    }
+
+    if amount > balance {
+        return TxReadiness::InsufficientBalance(currency.symbol());
+    }
+
+    TxReadiness::Ready
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use zeus_chain::{alloy::primitives::address, test_node::TestNode, ERC20Token};
+    use zeus_core::Wallet;
+
+    const RECIPIENT: &str = "0x000000000000000000000000000000000000dEaD";
+
+    fn eth() -> Currency {
+        Currency::new_native(1)
+    }
+
+    fn usdc() -> Currency {
+        let token = ERC20Token { chain_id: 1, address: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), symbol: "USDC".to_string(), decimals: 6, ..Default::default() };
+        Currency::new_erc20(token)
+    }
+
+    fn amount(text: &str, currency: &Currency) -> AmountInput {
+        AmountInput::new(text, currency.decimals())
+    }
+
+    /// A wallet is selected and we are connected, the fee of a transfer is not zero
+    async fn ready_data() -> AppData {
+        let mut data = AppData {
+            read_only: false,
+            client: Some(Arc::new(TestNode::empty_chain().client().await)),
+            ..Default::default()
+        };
+        data.profile.current_wallet = Some(Wallet::new_rng(String::new()));
+        data.next_block.base_fee = U256::from(1_000_000_000u64);
+        data
+    }
+
+    fn fee(data: &AppData, currency: &Currency) -> U256 {
+        data.estimate_fee(currency.transfer_gas())
+    }
+
+    #[tokio::test]
+    async fn a_send_needs_a_wallet_and_a_client() {
+        let mut data = ready_data().await;
+        let eth = eth();
+        let send = |data: &AppData| send_readiness(data, &eth, &amount("1", &eth), U256::MAX, U256::MAX, RECIPIENT);
+        assert_eq!(send(&data), TxReadiness::Ready);
+
+        data.client = None;
+        assert_eq!(send(&data), TxReadiness::NotConnected);
+
+        data.profile.current_wallet = None;
+        assert_eq!(send(&data), TxReadiness::NoWallet);
+    }
+
+    #[tokio::test]
+    async fn a_send_checks_the_amount_against_the_balance() {
+        let data = ready_data().await;
+        let usdc = usdc();
+        let balance = U256::from(5_000_000);
+        let send = |text: &str| send_readiness(&data, &usdc, &amount(text, &usdc), balance, U256::MAX, RECIPIENT);
+
+        assert_eq!(send(""), TxReadiness::EnterAmount);
+        assert_eq!(send("0"), TxReadiness::EnterAmount);
+        assert_eq!(send("1e6"), TxReadiness::InvalidAmount);
+        assert_eq!(send("5.000001"), TxReadiness::InsufficientBalance("USDC".to_string()));
+        assert_eq!(send("5"), TxReadiness::Ready);
+    }
+
+    #[tokio::test]
+    async fn a_send_leaves_enough_for_the_fee() {
+        let data = ready_data().await;
+        let (eth, usdc) = (eth(), usdc());
+        let one = U256::from(10).pow(U256::from(18));
+        let gas = TxReadiness::InsufficientGas(data.native_currency().symbol);
+
+        // a native transfer pays the fee from the amount's balance
+        let native_balance = one + fee(&data, &eth);
+        assert_eq!(send_readiness(&data, &eth, &amount("1", &eth), U256::MAX, native_balance, RECIPIENT), TxReadiness::Ready);
+        assert_eq!(send_readiness(&data, &eth, &amount("1", &eth), U256::MAX, native_balance - U256::from(1), RECIPIENT), gas);
+
+        // a token transfer only needs the fee
+        let native_balance = fee(&data, &usdc);
+        assert_eq!(send_readiness(&data, &usdc, &amount("1", &usdc), U256::MAX, native_balance, RECIPIENT), TxReadiness::Ready);
+        assert_eq!(send_readiness(&data, &usdc, &amount("1", &usdc), U256::MAX, native_balance - U256::from(1), RECIPIENT), gas);
+    }
+
+    #[tokio::test]
+    async fn a_send_needs_a_valid_recipient() {
+        let data = ready_data().await;
+        let eth = eth();
+        let send = |recipient: &str| send_readiness(&data, &eth, &amount("1", &eth), U256::MAX, U256::MAX, recipient);
+
+        assert_eq!(send("  "), TxReadiness::EnterRecipient);
+        assert_eq!(send("0xdead"), TxReadiness::InvalidRecipient);
+        assert_eq!(send(&format!(" {} ", RECIPIENT)), TxReadiness::Ready);
+    }
+
+    #[tokio::test]
+    async fn a_batch_is_checked_as_a_whole() {
+        let mut data = ready_data().await;
+        let usdc = usdc();
+        let recipient = Address::from_str(RECIPIENT).unwrap();
+        let rows = vec![Ok((recipient, U256::from(3))), Ok((recipient, U256::from(2)))];
+        let fees = fee(&data, &usdc) * U256::from(2);
+        let batch = |data: &AppData, rows: &[Result<(Address, U256), String>], balance: U256, native_balance: U256| {
+            batch_readiness(data, &usdc, rows, balance, native_balance)
+        };
+
+        assert_eq!(batch(&data, &rows, U256::from(5), fees), TxReadiness::Ready);
+        assert_eq!(batch(&data, &[], U256::from(5), fees), TxReadiness::EnterRecipient);
+        // the total, not each row, has to fit the balance
+        assert_eq!(batch(&data, &rows, U256::from(4), fees), TxReadiness::InsufficientBalance("USDC".to_string()));
+        // every transfer pays its own fee
+        assert_eq!(
+            batch(&data, &rows, U256::from(5), fees - U256::from(1)),
+            TxReadiness::InsufficientGas(data.native_currency().symbol)
+        );
+
+        let invalid = vec![Ok((recipient, U256::from(3))), Err("bad".to_string()), Err("bad".to_string())];
+        assert_eq!(batch(&data, &invalid, U256::from(5), fees), TxReadiness::InvalidRows(2));
+
+        data.client = None;
+        assert_eq!(batch(&data, &rows, U256::from(5), fees), TxReadiness::NotConnected);
+        data.profile.current_wallet = None;
+        assert_eq!(batch(&data, &rows, U256::from(5), fees), TxReadiness::NoWallet);
+    }
+
+    #[test]
+    fn a_swap_waits_for_the_approval() {
+        let usdc = usdc();
+        let needs_approval = TxReadiness::NeedsApproval("USDC".to_string());
+
+        assert_eq!(allowance_readiness(&usdc, &amount("5", &usdc), Some(U256::from(4_999_999))), needs_approval);
+        assert_eq!(allowance_readiness(&usdc, &amount("5", &usdc), Some(U256::from(5_000_000))), TxReadiness::Ready);
+        // the native currency needs no approval and an unknown allowance does not block the swap
+        assert_eq!(allowance_readiness(&eth(), &amount("5", &eth()), None), TxReadiness::Ready);
+        assert_eq!(allowance_readiness(&usdc, &amount("5", &usdc), None), TxReadiness::Ready);
+    }
+}
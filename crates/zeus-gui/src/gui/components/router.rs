@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+use crossbeam::channel::Sender;
+use eframe::egui::{vec2, Align2, Button, Color32, RichText, Sense, Ui, Window};
+
+use super::address_check::AddressField;
+use crate::{fonts::roboto_regular, gui::misc::{rich_text, truncate_address}};
+use zeus_backend::types::Request;
+use zeus_chain::{alloy::primitives::Address, explorer_address_url, ChainId, SwapRouter};
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, UiState, notify_error, notify_info};
+
+/// What the user clicked on the router row of a chain
+pub enum RouterAction {
+    Override(u64),
+    Reset(u64),
+}
+
+/// Show the router swaps and approvals of `chain_id` go to, with a link to it on the block explorer
+///
+/// Only the connected chain can be overridden, the address is checked for code with its client
+pub fn router_row(ui: &mut Ui, data: &AppData, chain_id: u64) -> Option<RouterAction> {
+    let router = data.settings.swap_router(chain_id);
+    let overridden = router != SwapRouter::default_for(chain_id);
+    let connected = data.connected_chain.as_ref().is_some_and(|chain| chain.id() == chain_id);
+    let mut action = None;
+
+    ui.horizontal(|ui| {
+        ui.label(rich_text(&format!("Router: {}", router.name()), 13.0));
+
+        match router.address(chain_id) {
+            Some(address) => {
+                let text = rich_text(&truncate_address(&address.to_string()), 13.0);
+                match explorer_address_url(chain_id, address) {
+                    Some(url) => {
+                        ui.hyperlink_to(text, url).on_hover_text(address.to_string());
+                    }
+                    None => {
+                        ui.label(text).on_hover_text(address.to_string());
+                    }
+                }
+            }
+            None => {
+                ui.label(rich_text("not deployed", 13.0).color(Color32::GRAY));
+            }
+        }
+
+        if overridden {
+            ui.label(rich_text("overridden", 12.0).color(Color32::YELLOW));
+            if ui.small_button("Reset").clicked() {
+                action = Some(RouterAction::Reset(chain_id));
+            }
+        } else {
+            let hint = if connected { "Use your own router contract" } else { "Connect to this chain to override its router" };
+            let res = ui.add_enabled(connected, Button::new("Override").small());
+            if res.on_hover_text(hint).on_disabled_hover_text(hint).clicked() {
+                action = Some(RouterAction::Override(chain_id));
+            }
+        }
+    });
+
+    action
+}
+
+/// The confirmation to send swaps and approvals of a chain to a router the user chose
+///
+/// The override is only saved once the address was found to have code
+pub struct RouterOverrideUI {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    pub state: UiState,
+    chain_id: u64,
+    address: String,
+    address_field: AddressField,
+    understood: bool,
+
+    /// The address we asked the backend to check for code
+    pending: Option<(u64, Address)>,
+}
+
+impl RouterOverrideUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            sender,
+            state: UiState::default(),
+            chain_id: 1,
+            address: String::new(),
+            address_field: AddressField::new(),
+            understood: false,
+            pending: None,
+        }
+    }
+
+    pub fn open(&mut self, chain_id: u64) {
+        self.chain_id = chain_id;
+        self.address.clear();
+        self.understood = false;
+        self.pending = None;
+        self.state.open();
+    }
+
+    /// Go back to the default router of `chain_id`
+    pub fn reset(&self, data: &mut AppData, chain_id: u64) {
+        data.settings.swap_routers.remove(&chain_id);
+        self.save(data);
+    }
+
+    fn save(&self, data: &AppData) {
+        if let Err(e) = data.save_settings() {
+            notify_error(format!("Error saving settings: {}", e));
+        }
+    }
+
+    /// Ask the backend if the typed address has code
+    fn check(&mut self, data: &AppData) -> Result<(), anyhow::Error> {
+        let address = Address::from_str(self.address.trim()).map_err(|_| anyhow::anyhow!("Invalid router address"))?;
+        SwapRouter::Zeus(address).validate(self.chain_id)?;
+
+        let client = data.client.clone().ok_or(anyhow::anyhow!("Not connected"))?;
+        self.sender.send(Request::check_contract(address, self.chain_id, client))?;
+        self.pending = Some((self.chain_id, address));
+        Ok(())
+    }
+
+    /// Save the override once the backend found code at the address
+    fn apply_check(&mut self, data: &mut AppData) {
+        let Some((chain_id, address)) = self.pending else {
+            return;
+        };
+        let Some(is_contract) = SHARED_CACHE.read().unwrap().contracts.get(&(chain_id, address)).copied() else {
+            return;
+        };
+        self.pending = None;
+
+        if !is_contract {
+            notify_error(format!("{} has no code, it can't be a router", address));
+            return;
+        }
+
+        data.settings.swap_routers.insert(chain_id, SwapRouter::Zeus(address));
+        self.save(data);
+        self.state.close();
+
+        notify_info(format!("Swaps and approvals now go to {}", address));
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &mut AppData) {
+        if self.state.is_close() {
+            return;
+        }
+        self.apply_check(data);
+
+        let chain = ChainId::from_id(self.chain_id).map_or(self.chain_id.to_string(), |chain| chain.name());
+        let title = RichText::new(format!("Override the Router on {}", chain))
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let button = |text: &str| {
+            Button::new(RichText::new(text).family(roboto_regular()).size(15.0))
+                .rounding(10.0)
+                .sense(Sense::click())
+                .min_size(vec2(70.0, 25.0))
+        };
+
+        let checking = self.pending.is_some();
+        let mut override_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(rich_text("Danger: every swap and approval on this chain will go to this contract", 14.0).color(Color32::RED));
+                    ui.label(rich_text("A malicious router can take every token you approve to it", 13.0).color(Color32::RED));
+                    ui.label(rich_text("Only use a router you deployed or verified yourself", 13.0));
+                    ui.add_space(10.0);
+
+                    self.address_field.show(ui, &mut self.address, 300.0);
+                    ui.add_space(5.0);
+                    ui.checkbox(&mut self.understood, "I understand the risk");
+                    ui.add_space(10.0);
+
+                    ui.horizontal(|ui| {
+                        let label = if checking { "Checking..." } else { "Override" };
+                        let enabled = self.understood && !checking && !self.address.trim().is_empty();
+                        if ui.add_enabled(enabled, button(label)).clicked() {
+                            override_clicked = true;
+                        }
+                        if ui.add(button("Cancel")).clicked() {
+                            self.pending = None;
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if override_clicked {
+            if let Err(e) = self.check(data) {
+                notify_error(e);
+            }
+        }
+    }
+}
@@ -1,30 +1,73 @@
-use crate::{fonts::roboto_regular, theme::THEME};
-use eframe::egui::{vec2, Align2, Button, Color32, RichText, Sense, TextEdit, Ui, Window};
+use crate::{fonts::roboto_regular, gui::misc::{amount_feedback, display_address, format_usd_price, percent_of}, theme::THEME};
+use eframe::egui::{vec2, Align2, Button, Color32, RichText, SelectableLabel, Sense, TextEdit, Ui, Window};
 
-use super::TokenSelectionWindow;
+use super::{
+    address_check::AddressField,
+    batch_send::BatchSend,
+    confirm_tx::{ConfirmTxWindow, TxSummary},
+    readiness::send_readiness,
+    TokenSelectionWindow,
+};
 use crossbeam::channel::Sender;
-use zeus_backend::types::Request;
-use zeus_chain::{alloy::primitives::{Address, U256}, defi_types::currency::Currency, format_wei};
-use zeus_shared_types::{cache::SHARED_CACHE, AppData, UiState};
+use std::{str::FromStr, time::{Duration, Instant}};
+use zeus_backend::types::{Request, SendCryptoParams, TxEstimate, TxRequest};
+use zeus_chain::{
+    alloy::primitives::{Address, U256},
+    defi_types::currency::Currency,
+    evm_types::simulation::get_native_coin,
+    format_units_with_precision,
+    tx::{TxCall, TxOverrides},
+    ETH_USD_FEED_DECIMALS,
+};
+use zeus_core::Wallet;
+use zeus_shared_types::{cache::SHARED_CACHE, AmountInput, AppData, UiState, notify_error, t};
+
+/// How long the price of a token is used before we ask for it again
+const PRICE_REFRESH: Duration = Duration::from_secs(30);
 
 /// The Send Crypto Screen UI
 
 pub struct SendCryptoScreen {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
     pub state: UiState,
     pub selected_currency: Currency,
     token_selection_window: TokenSelectionWindow,
-    amount: String,
+    confirm_window: ConfirmTxWindow,
+    amount: AmountInput,
+
+    /// The amount is typed in USD and converted to `amount` with the price of the currency
+    usd_mode: bool,
+    usd_amount: AmountInput,
+
+    /// chain, token and time of the last price request
+    price_request: Option<(u64, Address, Instant)>,
+
     recipient: String,
+    recipient_field: AddressField,
+
+    /// Send to several recipients instead of one
+    batch_mode: bool,
+    pub batch: BatchSend,
 }
 
 impl SendCryptoScreen {
     pub fn new(sender: Sender<Request>) -> Self {
         Self {
+            sender: sender.clone(),
             state: UiState::default(),
             selected_currency: Currency::default(),
-            token_selection_window: TokenSelectionWindow::new(sender),
-            amount: String::new(),
+            token_selection_window: TokenSelectionWindow::new(sender.clone()),
+            confirm_window: ConfirmTxWindow::new(sender.clone()).with_sign_only().with_signer_choice().with_advanced(),
+            amount: AmountInput::default(),
+            usd_mode: false,
+            usd_amount: AmountInput::default(),
+            price_request: None,
             recipient: String::new(),
+            recipient_field: AddressField::new(),
+            batch_mode: false,
+            batch: BatchSend::new(sender),
         }
     }
 
@@ -33,19 +76,187 @@ impl SendCryptoScreen {
         self.selected_currency = Currency::new_native(id);
     }
 
+    fn parsed_amount(&self) -> U256 {
+        debug_assert!(
+            self.amount.amount().is_none() || self.amount.decimals() == self.selected_currency.decimals(),
+            "the amount was parsed with the decimals of another currency"
+        );
+        self.amount.amount_or_zero()
+    }
+
+    fn parsed_recipient(&self) -> Address {
+        Address::from_str(self.recipient.trim()).unwrap_or_default()
+    }
+
+    /// Ask the user to confirm the transfer before it is signed
+    ///
+    /// Only called when the [super::readiness::TxReadiness] is ready
+    fn confirm(&mut self, data: &AppData, balance: U256) {
+        let summary = TxSummary::transfer(
+            data,
+            self.selected_currency.clone(),
+            self.parsed_amount(),
+            self.parsed_recipient(),
+            balance,
+        );
+        self.confirm_window.open(data, summary);
+    }
+
+    /// See [ConfirmTxWindow::set_estimate]
+    pub fn set_tx_estimate(&mut self, call: &TxCall, estimate: &Result<TxEstimate, String>) {
+        self.confirm_window.set_estimate(call, estimate);
+    }
+
+    /// Send the transfer to the backend, to be broadcasted or only signed by `wallet`
+    ///
+    /// Only called after the user confirmed it in the [ConfirmTxWindow].
+    /// The transfer is built from the confirmed `summary`, the fields may have been edited since
+    fn send(&self, data: &AppData, summary: TxSummary, wallet: Wallet, overrides: TxOverrides, sign_only: bool) {
+        let Some(client) = data.client.clone() else {
+            return;
+        };
+
+        let params = SendCryptoParams {
+            currency: summary.currency,
+            amount: summary.amount,
+            recipient: summary.to,
+            wallet,
+            chain_id: summary.chain_id,
+            next_base_fee: data.next_block.base_fee,
+            priority_fee: data.tx_settings.parse_gwei(),
+            mev_protect: data.tx_settings.mev_protect,
+            gas_multiplier: data.tx_settings.parse_gas_multiplier(),
+            dry_run: data.settings.dry_run,
+            overrides,
+            client,
+        };
+        let req = if sign_only {
+            Request::sign_transaction(TxRequest::Transfer(params))
+        } else {
+            Request::send_crypto(params)
+        };
+
+        if let Err(e) = self.sender.send(req) {
+            notify_error(e);
+        }
+    }
+
+    /// Ask the backend for the spot price of the selected token against the wrapped native currency
+    ///
+    /// The native currency and stablecoins are priced without it, see [AppData::usd_price]
+    fn request_price(&mut self, data: &AppData) {
+        let chain_id = data.chain_id.id();
+        let Some(token) = self.selected_currency.erc20() else {
+            return;
+        };
+        if data.settings.is_stablecoin(chain_id, &self.selected_currency) {
+            return;
+        }
+        let Ok(wrapped) = get_native_coin(chain_id) else {
+            return;
+        };
+        if token.address == wrapped {
+            return;
+        }
+
+        let recent = matches!(
+            &self.price_request,
+            Some((chain, address, at)) if *chain == chain_id && *address == token.address && at.elapsed() < PRICE_REFRESH
+        );
+        if recent {
+            return;
+        }
+        let Some(client) = data.client.clone() else {
+            return;
+        };
+
+        self.price_request = Some((chain_id, token.address, Instant::now()));
+        let req = Request::spot_price(token.clone(), Currency::new_native(chain_id).wrapped(), chain_id, client);
+        if let Err(e) = self.sender.send(req) {
+            notify_error(e);
+        }
+    }
+
+    /// Switch between typing the amount in the currency and in USD
+    fn toggle_usd_mode(&mut self, price: U256) {
+        self.usd_mode = !self.usd_mode;
+        if self.usd_mode {
+            // start from what was typed so far, rounded to cents
+            let usd = match self.selected_currency.usd_value(self.amount.amount_or_zero(), price) {
+                Ok(usd) => usd,
+                Err(e) => {
+                    self.usd_mode = false;
+                    notify_error(e);
+                    return;
+                }
+            };
+            self.usd_amount = match self.amount.amount() {
+                Some(_) => AmountInput::new(format_units_with_precision(usd, ETH_USD_FEED_DECIMALS, 2), ETH_USD_FEED_DECIMALS),
+                None => AmountInput::default(),
+            };
+        }
+    }
+
+    /// The amount follows the USD amount while it is typed in USD
+    fn convert_usd_amount(&mut self, price: U256) {
+        self.usd_amount.reparse(ETH_USD_FEED_DECIMALS);
+        let decimals = self.selected_currency.decimals();
+
+        self.amount = match self.usd_amount.amount() {
+            Some(usd) => match self.selected_currency.amount_for_usd(usd, price) {
+                Ok(amount) => AmountInput::from_amount(amount, decimals),
+                Err(e) => {
+                    self.usd_mode = false;
+                    notify_error(e);
+                    AmountInput::default()
+                }
+            },
+            // keep the text so the readiness tells an empty amount from an invalid one
+            None => AmountInput::new(self.usd_amount.raw(), decimals),
+        };
+    }
+
+    /// The typed amount converted to the other unit, eg. `≈ $12.50` or `≈ 0.005 ETH`
+    fn converted_amount(&self, price: Option<U256>) -> Option<String> {
+        let price = price?;
+        let amount = self.amount.amount()?;
+
+        if self.usd_mode {
+            let decimals = self.selected_currency.decimals();
+            Some(format!(
+                "≈ {} {}",
+                format_units_with_precision(amount, decimals, 6),
+                self.selected_currency.symbol()
+            ))
+        } else {
+            match self.selected_currency.usd_value(amount, price) {
+                Ok(usd) => Some(format!("≈ {}", format_usd_price(Some(usd)))),
+                Err(e) => Some(e.to_string()),
+            }
+        }
+    }
+
     /// Get balance of the selected currency
     fn get_balance(&self, chain_id: u64, owner: Address) -> U256 {
-        match &self.selected_currency {
-            Currency::Native(_) => {
-                let cache = SHARED_CACHE.read().unwrap();
-                let (_, balance) = cache.get_eth_balance(chain_id, owner);
-                balance
-            }
-            Currency::ERC20(token) => {
-                let cache = SHARED_CACHE.read().unwrap();
-                let balance = cache.get_erc20_balance(&chain_id, &owner, &token.address);
-                balance
-            }
+        SHARED_CACHE.read().unwrap().balance_of(chain_id, owner, &self.selected_currency)
+    }
+
+    /// The most we can send, for the native currency we keep enough to pay the fee
+    fn max_amount(&self, data: &AppData, balance: U256) -> U256 {
+        if self.selected_currency.is_native() {
+            let fee = data.estimate_fee(self.selected_currency.transfer_gas());
+            balance.saturating_sub(fee)
+        } else {
+            balance
+        }
+    }
+
+    /// `percent` of the balance, only the whole balance keeps the fee reserve
+    fn shortcut_amount(&self, data: &AppData, balance: U256, percent: u8) -> U256 {
+        if percent >= 100 {
+            self.max_amount(data, balance)
+        } else {
+            percent_of(balance, percent)
         }
     }
 
@@ -57,6 +268,17 @@ impl SendCryptoScreen {
             return;
         }
 
+        self.amount.reparse(self.selected_currency.decimals());
+
+        self.request_price(data);
+        let price = data.usd_price(&self.selected_currency);
+        match price {
+            Some(price) if self.usd_mode => self.convert_usd_amount(price),
+            // the token lost its price, eg. the chain or the currency changed
+            None => self.usd_mode = false,
+            _ => {}
+        }
+
         let currencies;
         {
             let cache = SHARED_CACHE.read().unwrap();
@@ -71,37 +293,66 @@ impl SendCryptoScreen {
             .family(roboto_regular())
             .size(20.0);
 
-        let send = RichText::new("Send").family(roboto_regular()).size(20.0);
         let cancel = RichText::new("Cancel").family(roboto_regular()).size(20.0);
         let token = RichText::new("Token").family(roboto_regular()).size(15.0);
         let amount = RichText::new("Amount").family(roboto_regular()).size(15.0);
         let recipient = RichText::new("Recipient").family(roboto_regular()).size(15.0);
 
-        let send_button = Button::new(send)
+        let cancel_button = Button::new(cancel)
             .rounding(10.0)
             .sense(Sense::click())
             .min_size(vec2(70.0, 25.0));
 
-        let cancel_button = Button::new(cancel)
+        let chain_id = data.chain_id.id();
+        let owner = data.wallet_address();
+
+        let balance_wei = self.get_balance(chain_id, owner);
+        let native_balance = data.eth_balance(chain_id, owner).1;
+        let readiness = send_readiness(
+            data,
+            &self.selected_currency,
+            &self.amount,
+            balance_wei,
+            native_balance,
+            &self.recipient,
+        );
+        let fetched = SHARED_CACHE.read().unwrap().fetched_balance_of(chain_id, owner, &self.selected_currency);
+        let amount_hint = match fetched {
+            Some(balance) => format!(
+                "{} {} Available",
+                format_units_with_precision(balance, self.selected_currency.decimals(), 4),
+                self.selected_currency.symbol()
+            ),
+            None => "Loading the balance…".to_string(),
+        };
+
+        let send = RichText::new(readiness.label(&t("send.send"))).family(roboto_regular()).size(20.0);
+        let send_button = Button::new(send)
             .rounding(10.0)
             .sense(Sense::click())
             .min_size(vec2(70.0, 25.0));
 
-        let chain_id = data.chain_id.id();
-        let owner = data.wallet_address();
+        let converted = self.converted_amount(price);
+        let usd_label = SelectableLabel::new(self.usd_mode, "USD");
+        let usd_hint = if price.is_some() { "Enter the amount in USD" } else { "No USD price for this token" };
 
-        let balance = self.get_balance(chain_id, owner);
-        let balance = format_wei(&balance.to_string(), self.selected_currency.decimals().clone());
-        let balance = format!("{:.4}", balance);
+        let recipient_preview = Address::from_str(self.recipient.trim()).ok();
 
-        let amount_edit = TextEdit::singleline(&mut self.amount)
-        .hint_text(&format!("{} {} Available", balance, &self.selected_currency.symbol()))
-        .min_size(vec2(150.0, 25.0))
-        .desired_width(150.0);
+        let max_hint = if self.selected_currency.is_native() {
+            format!("Keeps enough {} to pay the fee", data.native_currency().symbol)
+        } else {
+            "Send the whole balance".to_string()
+        };
 
-        let recipient_edit = TextEdit::singleline(&mut self.recipient)
-            .min_size(vec2(150.0, 25.0))
-            .desired_width(150.0);
+        let mut send_clicked = false;
+        let mut shortcut = None;
+        let has_balance = SHARED_CACHE
+            .read()
+            .unwrap()
+            .fetched_balance_of(chain_id, owner, &self.selected_currency)
+            .is_some_and(|balance| !balance.is_zero());
+        let no_balance_hint = "No balance to send yet";
+        let mut usd_clicked = false;
 
         Window::new(send_crypto)
             .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
@@ -118,46 +369,122 @@ impl SendCryptoScreen {
                         .size(14.0)
                         .color(Color32::WHITE);
 
-                    let icon = THEME.icons.currency_icon(chain_id);
+                    let icon = THEME.icons.icon_for_currency(ui.ctx(), &self.selected_currency);
 
                     let currency_button = Button::image_and_text(icon, name)
                         .rounding(10.0)
                         .sense(Sense::click())
                         .min_size(vec2(75.0, 20.0));
 
-                        ui.label(token);
-                        ui.add_space(2.0);
-                        if ui.add(currency_button).clicked() {
-                            self.token_selection_window.state.open();
-                        }
+                        ui.add_enabled_ui(!self.batch.is_sending(), |ui| {
+                            ui.checkbox(&mut self.batch_mode, "Batch send")
+                                .on_hover_text("Send to several recipients, one transaction each");
+                            ui.add_space(5.0);
+
+                            ui.label(token);
+                            ui.add_space(2.0);
+                            if ui.add(currency_button).clicked() {
+                                self.token_selection_window.state.open();
+                            }
+                        });
 
                         ui.add_space(15.0);
 
+                    if self.batch_mode {
+                        self.batch.show(ui, data, &self.selected_currency, balance_wei, native_balance);
+                    } else {
                         ui.label(amount);
                         ui.add_space(2.0);
-                        ui.add(amount_edit);
+                        ui.horizontal(|ui| {
+                            let (input, decimals) = if self.usd_mode {
+                                (&mut self.usd_amount, ETH_USD_FEED_DECIMALS)
+                            } else {
+                                (&mut self.amount, self.selected_currency.decimals())
+                            };
+                            let amount_edit = TextEdit::singleline(input.raw_mut())
+                                .hint_text(&amount_hint)
+                                .min_size(vec2(150.0, 25.0))
+                                .desired_width(150.0);
+                            let res = ui.add(amount_edit);
+                            amount_feedback(ui, &res, input, decimals);
+                            let max = ui.add_enabled(has_balance, Button::new("Max").small());
+                            if max.on_hover_text(&max_hint).on_disabled_hover_text(no_balance_hint).clicked() {
+                                shortcut = Some(100);
+                            }
+                            if ui.add_enabled(price.is_some(), usd_label).on_hover_text(usd_hint).on_disabled_hover_text(usd_hint).clicked() {
+                                usd_clicked = true;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            for percent in [25, 50, 75] {
+                                let res = ui.add_enabled(has_balance, Button::new(format!("{}%", percent)).small());
+                                if res.on_disabled_hover_text(no_balance_hint).clicked() {
+                                    shortcut = Some(percent);
+                                }
+                            }
+                        });
+                        if let Some(converted) = &converted {
+                            ui.label(RichText::new(converted).family(roboto_regular()).size(12.0).color(Color32::GRAY));
+                        }
 
                         ui.add_space(15.0);
                         ui.label(recipient);
                         ui.add_space(2.0);
-                        ui.add(recipient_edit);
+                        self.recipient_field.show(ui, &mut self.recipient, 150.0);
+                        if let Some(address) = recipient_preview {
+                            ui.label(
+                                RichText::new(format!("To {}", display_address(chain_id, address)))
+                                    .family(roboto_regular())
+                                    .size(12.0)
+                                    .color(Color32::GRAY),
+                            );
+                        }
                         ui.add_space(15.0);
                         // TODO: Add Saved Contacts
 
+                        let confirming = self.confirm_window.state.is_open();
+                        if ui.add_enabled(readiness.is_ready() && !confirming, send_button).clicked() {
+                            send_clicked = true;
+                        }
+                    }
+                    ui.add_space(15.0);
+
                     let selected = self.token_selection_window.show(ui, data, &currencies);
                     if let Some(selected) = selected {
                         self.selected_currency = selected;
+                        self.amount.reparse(self.selected_currency.decimals());
                     }
 
-                    if ui.add(send_button).clicked() {
-                        // TODO
-                    }
-                    ui.add_space(15.0);
-
                     if ui.add(cancel_button).clicked() {
                         self.state.close();
                     }
                 });
             });
+
+        if let (true, Some(price)) = (usd_clicked, price) {
+            self.toggle_usd_mode(price);
+        }
+
+        if let Some(percent) = shortcut {
+            let amount = self.shortcut_amount(data, balance_wei, percent);
+            self.amount = AmountInput::from_amount(amount, self.selected_currency.decimals());
+            // the exact amount can't be typed in USD
+            self.usd_mode = false;
+            // the converted value is derived from the amount at the start of the frame
+            ui.ctx().request_repaint();
+        }
+
+        if send_clicked {
+            self.confirm(data, balance_wei);
+        }
+
+        let reviewed = self.confirm_window.summary.clone();
+        if let (Some(true), Some(summary)) = (self.confirm_window.show(ui, data), reviewed) {
+            let confirmed = self.confirm_window.signer(data).and_then(|wallet| Ok((wallet, self.confirm_window.overrides()?)));
+            match confirmed {
+                Ok((wallet, overrides)) => self.send(data, summary, wallet, overrides, self.confirm_window.sign_only),
+                Err(e) => notify_error(e),
+            }
+        }
     }
-}
\ No newline at end of file
+}
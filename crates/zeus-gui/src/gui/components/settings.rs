@@ -0,0 +1,280 @@
+use std::path::Path;
+
+use eframe::egui::{vec2, Align2, Button, Checkbox, Color32, ComboBox, DragValue, RichText, Sense, Slider, TextEdit, Ui, Window};
+use tracing::trace;
+
+use crate::fonts::roboto_regular;
+use zeus_chain::{defi_types::currency::erc20::ApprovalAmount, TokenMetadataSource};
+use zeus_shared_types::{set_language, t, AppData, Language, UiState, UiText, MIN_BALANCE_REFRESH_SECS, notify_error_text, notify_info_text};
+
+/// UI for the general user preferences that are saved in `settings.json`
+///
+/// The swap router of each chain is set in the [super::NetworkSettings]
+#[derive(Default)]
+pub struct SettingsUI {
+    pub state: UiState,
+
+    /// Where the [zeus_shared_types::SettingsBundle] is exported to and imported from
+    bundle_path: String,
+}
+
+impl SettingsUI {
+    pub fn new() -> Self {
+        Self {
+            state: UiState::default(),
+            bundle_path: "zeus-settings.json".to_string(),
+        }
+    }
+
+    /// Export or import all the preferences at once, see [AppData::export_settings]
+    fn bundle_section(&mut self, ui: &mut Ui, data: &mut AppData) {
+        ui.label(t("settings.bundle_hint"));
+        ui.add(TextEdit::singleline(&mut self.bundle_path).hint_text(t("settings.bundle_path")).desired_width(250.0));
+        ui.add_space(5.0);
+
+        let path = self.bundle_path.trim().to_string();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!path.is_empty(), Button::new(t("settings.export"))).clicked() {
+                match data.export_settings(Path::new(&path)) {
+                    Ok(_) => notify_info_text(UiText::key("info.settings_exported").with("path", &path)),
+                    Err(e) => notify_error_text(UiText::key("error.exporting_settings").with("error", e)),
+                }
+            }
+            if ui.add_enabled(!path.is_empty(), Button::new(t("settings.import"))).clicked() {
+                match data.import_settings(Path::new(&path)) {
+                    Ok(_) => notify_info_text(UiText::key("info.settings_imported").with("path", &path)),
+                    Err(e) => notify_error_text(UiText::key("error.importing_settings").with("error", e)),
+                }
+            }
+        });
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui, data: &mut AppData) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let title = RichText::new(t("menu.general_settings"))
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let metadata_text = RichText::new(t("settings.metadata_source"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let dry_run_text = RichText::new(t("settings.dry_run"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let large_tx_text = RichText::new(t("settings.large_tx"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let refresh_text = RichText::new(t("settings.refresh"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let auto_lock_text = RichText::new(t("settings.auto_lock"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let display_text = RichText::new(t("settings.display"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let verify_text = RichText::new(t("settings.recipient_check"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let approval_text = RichText::new(t("settings.approvals"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let language_text = RichText::new(t("settings.language"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let bundle_text = RichText::new(t("settings.bundle"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let save = RichText::new(t("common.save"))
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let save_button = Button::new(save)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.set_min_size(vec2(250.0, 150.0));
+                    ui.add_space(10.0);
+
+                    ui.label(language_text);
+                    ui.add_space(5.0);
+
+                    let language = &mut data.settings.language;
+                    let previous = *language;
+                    ComboBox::from_id_source("language")
+                        .selected_text(language.name())
+                        .show_ui(ui, |ui| {
+                            for option in Language::ALL {
+                                ui.selectable_value(language, option, option.name());
+                            }
+                        });
+                    // applied right away so the user sees the new language before saving
+                    if *language != previous {
+                        set_language(*language);
+                    }
+                    ui.add_space(15.0);
+
+                    ui.label(metadata_text);
+                    ui.add_space(5.0);
+
+                    let source = &mut data.settings.token_metadata_source;
+                    ComboBox::from_id_source("token_metadata_source")
+                        .selected_text(source.name())
+                        .show_ui(ui, |ui| {
+                            for option in [TokenMetadataSource::Node, TokenMetadataSource::NodeThenExplorer] {
+                                ui.selectable_value(source, option, option.name());
+                            }
+                        });
+                    ui.add_space(5.0);
+
+                    if source.uses_explorer() {
+                        ui.label(t("settings.explorer_hint"));
+                    }
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label(dry_run_text);
+                        ui.add_space(5.0);
+                        ui.add(Checkbox::new(&mut data.settings.dry_run, ""));
+                    });
+
+                    if data.settings.dry_run {
+                        ui.label(t("settings.dry_run_hint"));
+                    }
+                    ui.add_space(15.0);
+
+                    ui.label(large_tx_text);
+                    ui.add_space(5.0);
+                    let mut percent = data.settings.large_tx_fraction * 100.0;
+                    if ui.add(Slider::new(&mut percent, 1.0..=100.0).suffix("%")).changed() {
+                        data.settings.large_tx_fraction = percent / 100.0;
+                    }
+                    ui.label(t("settings.of_balance"));
+                    ui.add_space(15.0);
+
+                    ui.label(refresh_text);
+                    ui.add_space(5.0);
+                    ui.add(
+                        Slider::new(&mut data.settings.balance_refresh_secs, MIN_BALANCE_REFRESH_SECS..=60)
+                            .suffix(" sec"),
+                    );
+                    ui.label(t("settings.refresh_hint"));
+                    ui.add_space(15.0);
+
+                    ui.label(auto_lock_text);
+                    ui.add_space(5.0);
+                    ui.add(Slider::new(&mut data.settings.auto_lock_mins, 0..=120).suffix(" min"));
+                    if data.settings.auto_lock_mins == 0 {
+                        ui.label(t("settings.never_lock"));
+                    } else {
+                        ui.label(t("settings.lock_hint"));
+                    }
+                    ui.add_space(15.0);
+
+                    ui.label(display_text);
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut data.settings.hide_small_balances, t("settings.hide_below"));
+                        ui.add_enabled(
+                            data.settings.hide_small_balances,
+                            DragValue::new(&mut data.settings.small_balance_usd)
+                                .range(0.0..=10_000.0)
+                                .speed(0.1)
+                                .prefix("$"),
+                        );
+                    });
+                    ui.checkbox(&mut data.settings.hide_unpriced, t("settings.hide_unpriced"));
+                    ui.checkbox(&mut data.settings.show_testnets, t("settings.show_testnets"));
+                    ui.add_space(15.0);
+
+                    ui.checkbox(&mut data.settings.log_quotes, t("settings.log_quotes"));
+                    if data.settings.log_quotes {
+                        ui.label(t("settings.log_quotes_hint"));
+                    }
+                    ui.add_space(15.0);
+
+                    ui.label(verify_text);
+                    ui.add_space(5.0);
+                    ui.add(
+                        DragValue::new(&mut data.settings.verify_recipient_usd)
+                            .range(0.0..=1_000_000.0)
+                            .speed(10.0)
+                            .prefix("$"),
+                    );
+                    ui.label(t("settings.recipient_hint"));
+                    ui.add_space(15.0);
+
+                    ui.label(approval_text);
+                    ui.add_space(5.0);
+                    let approval = &mut data.settings.approval_amount;
+                    ComboBox::from_id_source("approval_amount")
+                        .selected_text(approval.name())
+                        .show_ui(ui, |ui| {
+                            for option in [ApprovalAmount::Exact, ApprovalAmount::Infinite] {
+                                ui.selectable_value(approval, option, option.name());
+                            }
+                        });
+                    if *approval == ApprovalAmount::Infinite {
+                        ui.label(t("settings.infinite_hint"));
+                    } else {
+                        ui.label(t("settings.exact_hint"));
+                    }
+                    ui.add_space(15.0);
+
+                    ui.label(bundle_text);
+                    ui.add_space(5.0);
+                    self.bundle_section(ui, data);
+                    ui.add_space(15.0);
+
+                    if ui.add(save_button).clicked() {
+                        match data.save_settings() {
+                            Ok(_) => {
+                                trace!("Settings saved");
+                                self.state.close();
+                                notify_info_text(UiText::key("info.settings_saved"));
+                            }
+                            Err(e) => {
+                                notify_error_text(UiText::key("error.saving_settings").with("error", e));
+                                self.state.close();
+                            }
+                        }
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,93 @@
+use eframe::egui::{vec2, Align2, Button, Color32, RichText, Sense, Ui, Window};
+
+use super::calldata::hex_field;
+use crate::{fonts::roboto_regular, gui::misc::{rich_text, truncate_address}};
+use zeus_chain::{tx::SignedTx, ChainId};
+use zeus_shared_types::UiState;
+
+/// Shows a transaction that was signed but not broadcasted, the user sends it from somewhere else
+#[derive(Default)]
+pub struct SignedTxWindow {
+    pub state: UiState,
+    pub tx: Option<SignedTx>,
+}
+
+impl SignedTxWindow {
+    pub fn new() -> Self {
+        Self {
+            state: UiState::default(),
+            tx: None,
+        }
+    }
+
+    /// Open the window with a new signed transaction
+    pub fn open(&mut self, tx: SignedTx) {
+        self.tx = Some(tx);
+        self.state.open();
+    }
+
+    /// Show this UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let tx = match &self.tx {
+            Some(tx) => tx.clone(),
+            None => return,
+        };
+
+        let title = RichText::new("Signed Transaction")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let close = RichText::new("Close")
+            .family(roboto_regular())
+            .size(15.0)
+            .color(Color32::WHITE);
+
+        let close_button = Button::new(close)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let chain = ChainId::from_id(tx.chain_id).map_or(tx.chain_id.to_string(), |chain| chain.name());
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.set_max_width(450.0);
+
+                ui.vertical_centered(|ui| {
+                    ui.label(rich_text("Nothing was broadcasted", 15.0));
+                    ui.label(
+                        rich_text("Anyone with this transaction can broadcast it until the nonce is used", 13.0)
+                            .color(Color32::YELLOW),
+                    );
+                    ui.add_space(10.0);
+
+                    ui.label(rich_text(&format!("Chain: {}", chain), 13.0));
+                    ui.label(rich_text(&format!("Nonce: {}", tx.nonce), 13.0));
+                    ui.label(rich_text(&format!("To: {}", truncate_address(&tx.to.to_string())), 13.0))
+                        .on_hover_text(tx.to.to_string());
+                    ui.label(rich_text(&format!("Amount: {}", tx.amount.text()), 13.0));
+                    ui.label(rich_text(&format!("Estimated gas: {}", tx.simulation.gas_used), 13.0));
+                    ui.label(rich_text(&format!("Tx Hash: {}", tx.tx_hash), 13.0));
+                    ui.add_space(10.0);
+
+                    hex_field(ui, "Raw Transaction", &tx.raw_tx.to_string());
+                    ui.add_space(15.0);
+
+                    if ui.add(close_button).clicked() {
+                        self.state.close();
+                        self.tx = None;
+                    }
+                });
+            });
+    }
+}
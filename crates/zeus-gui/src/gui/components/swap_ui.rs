@@ -1,20 +1,25 @@
 use eframe::egui::{
-    vec2, Align, Button, Color32, FontId, Layout, RichText, TextEdit, Ui,
+    vec2, Align, Button, CollapsingHeader, Color32, FontId, Layout, RichText, SelectableLabel, TextEdit, Ui,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::{Duration, Instant}};
 use tracing::trace;
 
 use crossbeam::channel::Sender;
 
 use crate::{fonts::roboto_regular, icons::IconTextures};
 
-use super::TokenSelectionWindow;
-use zeus_backend::types::Request;
-use zeus_chain::{defi_types::currency::Currency, utils::format_wei};
-use zeus_shared_types::{
-    AppData, cache::SHARED_CACHE, UiState, SHARED_UI_STATE,
+use super::{confirm_tx::sign_mode_toggle, readiness::{allowance_readiness, swap_readiness}, TokenSelectionWindow};
+use crate::gui::misc::{amount_feedback, amount_label, balance_label, format_price, rich_text, truncate_address};
+use zeus_backend::types::{ApproveParams, QuoteParams, Request, SpotPriceRes, TxRequest};
+use zeus_chain::{
+    alloy::primitives::{Address, U256},
+    defi_types::{currency::{erc20::ApprovalAmount, Currency}, pool::PoolVariant},
+    format_compact, format_units, ChainFeature, SwapRouter,
 };
+use zeus_shared_types::{AmountInput, AppData, cache::SHARED_CACHE, QuoteResult, UiState, notify_error, notify_error_text, notify_info_text, t, tr, SharedUiState, UiText};
 
+/// How long a spot price is shown before we ask for it again
+const SPOT_PRICE_REFRESH: Duration = Duration::from_secs(30);
 
 pub struct SwapUI {
     /// Send Request to the backend
@@ -28,12 +33,33 @@ pub struct SwapUI {
     /// Currency to swap to
     pub currency_out: Currency,
 
-    pub amount_in: String,
+    pub amount_in: AmountInput,
 
-    pub amount_out: String,
+    pub amount_out: AmountInput,
 
     /// Latest Block
     pub block: u64,
+
+    /// The spot price of the selected pair from its best pool
+    spot_price: Option<SpotPriceRes>,
+
+    /// The pair (wrapped tokens) and time of the last spot price request
+    spot_price_request: Option<(Address, Address, Instant)>,
+
+    /// The last quote of the selected pair
+    quote: Option<QuoteResult>,
+
+    /// Waiting for a quote from the backend
+    quote_pending: bool,
+
+    /// The approval amount of this swap, [zeus_shared_types::Settings::approval_amount] if `None`
+    approval_override: Option<ApprovalAmount>,
+
+    /// The user clicked approve and has to confirm it
+    confirming_approval: bool,
+
+    /// Only sign the approval, see [super::confirm_tx::sign_mode_toggle]
+    sign_only: bool,
 }
 
 impl SwapUI {
@@ -43,18 +69,126 @@ impl SwapUI {
             sender,
             state: UiState::OPEN,
             currency_in: Currency::new_native(1),
-            currency_out: Currency::default_erc20(1),
-            amount_in: String::new(),
-            amount_out: String::new(),
+            currency_out: Currency::default_erc20(1).unwrap_or_default(),
+            amount_in: AmountInput::default(),
+            amount_out: AmountInput::default(),
             block: 0,
+            spot_price: None,
+            spot_price_request: None,
+            quote: None,
+            quote_pending: false,
+            approval_override: None,
+            confirming_approval: false,
+            sign_only: false,
+        }
+    }
+
+    /// The backend reports failures through an error notification, stop waiting for the quote
+    pub fn on_error(&mut self) {
+        self.quote_pending = false;
+    }
+
+    /// Set the quote received from the backend, ignored if the pair has changed since
+    pub fn set_quote(&mut self, quote: QuoteResult) {
+        self.quote_pending = false;
+        if quote.input_token.currency != self.currency_in || quote.output_token.currency != self.currency_out {
+            return;
+        }
+
+        self.amount_out = quote.output_token.amount.clone();
+        self.quote = Some(quote);
+    }
+
+    /// Ask the backend for a quote of `amount_in`
+    fn request_quote(&mut self, data: &AppData) {
+        let Some(client) = data.client().clone() else {
+            return;
+        };
+        let Some(block) = data.latest_block().full_block else {
+            return;
+        };
+        let Some(amount_in) = self.amount_in.amount() else {
+            return;
+        };
+        debug_assert_eq!(
+            self.amount_in.decimals(),
+            self.currency_in.decimals(),
+            "the amount in was parsed with the decimals of another currency"
+        );
+
+        let slippage = data.swap_slippage(&self.currency_in, &self.currency_out);
+        let req = Request::quote(QuoteParams {
+            currency_in: self.currency_in.clone(),
+            currency_out: self.currency_out.clone(),
+            amount_in,
+            owner: data.wallet_address(),
+            slippage: slippage.value,
+            chain_id: data.chain_id.id(),
+            router: data.settings.swap_router(data.chain_id.id()),
+            block,
+            log_quotes: data.settings.log_quotes,
+            client,
+        });
+
+        match self.sender.send(req) {
+            Ok(_) => {
+                self.quote = None;
+                self.quote_pending = true;
+            }
+            Err(e) => {
+                notify_error(e);
+            }
+        }
+    }
+
+    /// Set the spot price received from the backend, ignored if the pair has changed since
+    pub fn set_spot_price(&mut self, res: SpotPriceRes) {
+        let token_in = self.currency_in.wrapped();
+        let token_out = self.currency_out.wrapped();
+
+        if res.token_in.address == token_in.address && res.token_out.address == token_out.address {
+            self.spot_price = Some(res);
+        }
+    }
+
+    /// Ask the backend for the spot price when the pair changes or the last one is too old
+    fn request_spot_price(&mut self, data: &AppData) {
+        let token_in = self.currency_in.wrapped();
+        let token_out = self.currency_out.wrapped();
+
+        let same_pair = matches!(
+            &self.spot_price_request,
+            Some((a, b, at)) if *a == token_in.address && *b == token_out.address && at.elapsed() < SPOT_PRICE_REFRESH
+        );
+        if same_pair {
+            return;
+        }
+
+        if self.spot_price.as_ref().is_some_and(|res| res.token_in.address != token_in.address || res.token_out.address != token_out.address) {
+            self.spot_price = None;
+        }
+
+        // eg. ETH -> WETH has no pool
+        if token_in.address == token_out.address {
+            return;
+        }
+
+        let Some(client) = data.client().clone() else {
+            return;
+        };
+
+        self.spot_price_request = Some((token_in.address, token_out.address, Instant::now()));
+        let req = Request::spot_price(token_in, token_out, data.chain_id.id(), client);
+        if let Err(e) = self.sender.send(req) {
+            notify_error(e);
         }
     }
 
-    pub fn amount_in(&mut self) -> &mut String {
+    pub fn amount_in(&mut self) -> &mut AmountInput {
         &mut self.amount_in
     }
 
-    pub fn amount_out(&mut self) -> &mut String {
+    pub fn amount_out(&mut self) -> &mut AmountInput {
         &mut self.amount_out
     }
 
@@ -70,16 +204,23 @@ impl SwapUI {
     }
 
     /// Replace the input or output currency by an id
+    ///
+    /// Picking the currency of the other side, or its native/wrapped version, flips the two sides
     pub fn replace_currency(&mut self, id: &str, currency: Currency) {
-        match id {
-            "input" => {
-                self.currency_in = currency;
-            }
-            "output" => {
-                self.currency_out = currency;
-            }
-            _ => {}
+        let (side, other) = match id {
+            "input" => (&mut self.currency_in, &mut self.currency_out),
+            "output" => (&mut self.currency_out, &mut self.currency_in),
+            _ => return,
+        };
+
+        if same_asset(&currency, other) {
+            *other = std::mem::replace(side, currency);
+        } else {
+            *side = currency;
         }
+
+        self.approval_override = None;
+        self.confirming_approval = false;
     }
 
     /// Give a default input currency based on the selected chain id
@@ -89,7 +230,7 @@ impl SwapUI {
 
     /// Give a default output currency based on the selected chain id
     pub fn default_output(&mut self, id: u64) {
-        self.currency_out = Currency::default_erc20(id);
+        self.currency_out = Currency::default_erc20(id).unwrap_or_else(|_| Currency::new_native(id));
     }
 
     /// Show this UI
@@ -106,6 +247,10 @@ impl SwapUI {
             return;
         }
 
+        // the amounts are parsed with the decimals of the selected currencies
+        self.amount_in.reparse(self.currency_in.decimals());
+        self.amount_out.reparse(self.currency_out.decimals());
+
         let currencies;
         {
             let cache = SHARED_CACHE.read().unwrap();
@@ -116,12 +261,12 @@ impl SwapUI {
                 .clone();
         }
 
-        let swap_text = RichText::new("Swap")
+        let swap_text = RichText::new(t("swap.swap"))
             .family(roboto_regular())
             .size(20.0)
             .color(Color32::WHITE);
 
-        let for_text = RichText::new("For")
+        let for_text = RichText::new(t("swap.for"))
             .family(roboto_regular())
             .size(20.0)
             .color(Color32::WHITE);
@@ -134,19 +279,24 @@ impl SwapUI {
                 let res = ui.add(icons.tx_settings_icon());
 
                 if res.clicked() {
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.tx_settings_on = true;
+                    SharedUiState::write().tx_settings_on = true;
                 }
             });
 
             ui.label(swap_text);
 
+            if !data.chain_id.supports(ChainFeature::Dex) {
+                ui.add_space(10.0);
+                ui.label(rich_text(&format!("Swaps are not available on {}", data.chain_id.name()), 15.0));
+                return;
+            }
+
             ui.horizontal(|ui| {
                 ui.add_space(115.0);
                 self.amount_field(ui, "input");
                 ui.add_space(10.0);
                 ui.vertical(|ui| {
-                    self.token_button(ui, "input", token_selection);
+                    self.token_button(ui, data, "input", token_selection, &icons);
                     self.currency_balance(ui, data, "input");
                 });
             });
@@ -159,10 +309,12 @@ impl SwapUI {
                 self.amount_field(ui, "output");
                 ui.add_space(10.0);
                 ui.vertical(|ui| {
-                    self.token_button(ui, "output", token_selection);
+                    self.token_button(ui, data, "output", token_selection, &icons);
                     self.currency_balance(ui, data, "output");
                 });
             });
+            self.route_summary(ui);
+            self.quote_block_label(ui, data);
 
             let selected = token_selection.show(ui, data, &currencies);
             if let Some(currency) = selected {
@@ -171,7 +323,19 @@ impl SwapUI {
     
             }
 
-                self.swap_button(ui, data);
+                self.request_spot_price(data);
+                self.spot_price_label(ui);
+                self.slippage_label(ui, data);
+                self.quote_details(ui, data);
+
+                self.approval_row(ui, data);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(180.0);
+                    self.quote_button(ui, data);
+                    self.approve_button(ui, data);
+                    self.swap_button(ui, data);
+                });
 
         });
     }
@@ -184,52 +348,108 @@ impl SwapUI {
             .size(23.0)
             .family(roboto_regular());
 
-        let amount = match direction {
-            "input" => self.amount_in(),
-            "output" => self.amount_out(),
+        let (amount, decimals) = match direction {
+            "input" => (&mut self.amount_in, self.currency_in.decimals()),
+            "output" => (&mut self.amount_out, self.currency_out.decimals()),
             _ => panic!("Invalid direction, expected 'input' or 'output' but got {}", direction),
         };
 
-        let field = TextEdit::singleline(amount)
+        let field = TextEdit::singleline(amount.raw_mut())
             .font(font)
             .min_size(vec2(100.0, 30.0))
             .text_color(Color32::WHITE)
             .hint_text(hint);
 
-        ui.add(field);
+        let res = ui.add(field);
+        amount_feedback(ui, &res, amount, decimals);
     }
 
     /// Create the token button
     ///
     /// If clicked it will show the [TokenSelectionWindow]
+    ///
+    /// Right click lets the user mark the token as a stablecoin
     fn token_button(
         &mut self,
         ui: &mut Ui,
+        data: &mut AppData,
         currency_id: &str,
-        token_selection: &mut TokenSelectionWindow
+        token_selection: &mut TokenSelectionWindow,
+        icons: &IconTextures,
     ) {
         ui.push_id(currency_id, |ui| {
 
         
-        let symbol = self.get_currency(currency_id).symbol();
-        let symbol_text = RichText::new(symbol)
+        let currency = self.get_currency(currency_id);
+        let symbol_text = RichText::new(currency.symbol())
             .color(Color32::WHITE)
             .size(15.0)
             .family(roboto_regular());
 
-        let button = Button::new(symbol_text)
+        let icon = icons.icon_for_currency(ui.ctx(), currency);
+        let button = Button::image_and_text(icon, symbol_text)
             .min_size(vec2(30.0, 15.0))
             .rounding(10.0)
             .stroke((0.3, Color32::WHITE));
 
-        if ui.add(button).clicked() {
+        let res = ui.add(button);
+        if res.clicked() {
             token_selection.set_id(currency_id.to_string());
             token_selection.state.open();
         }
 
+        if let Some(token) = self.get_currency(currency_id).erc20() {
+            let chain_id = data.chain_id.id();
+            let address = token.address;
+            let symbol = token.symbol.clone();
+            let res = res.on_hover_text(format!(
+                "{}\nSupply: {}\nDecimals: {}",
+                token.name,
+                format_compact(token.total_supply, token.decimals),
+                token.decimals
+            ));
+            res.context_menu(|ui| {
+                let marked = data.settings.is_user_stablecoin(chain_id, address);
+                let label = if marked { t("swap.unmark_stable") } else { t("swap.mark_stable") };
+
+                if ui.button(label).clicked() {
+                    ui.close_menu();
+                    data.settings.set_stablecoin(chain_id, address, !marked);
+                    if let Err(e) = data.save_settings() {
+                        notify_error_text(UiText::key("error.saving_settings").with("error", e));
+                    }
+                }
+
+                let refresh = ui.add_enabled(data.client.is_some(), Button::new(t("swap.refresh_metadata")));
+                if refresh.on_hover_text(t("swap.refresh_metadata_hint")).clicked() {
+                    ui.close_menu();
+                    self.refresh_metadata(data, currency_id, address, &symbol);
+                }
+            });
+        }
+
     });
     }
 
+    /// Read the metadata of a token from the node again, the stored token is replaced
+    fn refresh_metadata(&self, data: &AppData, currency_id: &str, address: Address, symbol: &str) {
+        let Some(client) = data.client.clone() else {
+            return;
+        };
+        let source = data.settings.token_metadata_source;
+        let req = Request::erc20_token(currency_id.to_string(), data.wallet_address(), address, data.chain_id.id(), client, source, true);
+        match self.sender.send(req) {
+            Ok(_) => notify_info_text(UiText::key("info.refreshing_metadata").with("symbol", symbol)),
+            Err(e) => notify_error(e),
+        }
+    }
+
+    /// Get the balance of the input or output currency
+    fn balance(&self, data: &AppData, currency_id: &str) -> U256 {
+        let currency = self.get_currency(currency_id);
+        SHARED_CACHE.read().unwrap().balance_of(data.chain_id.id(), data.wallet_address(), currency)
+    }
+
     /// Show the currency balance
     fn currency_balance(
         &mut self,
@@ -237,42 +457,311 @@ impl SwapUI {
         data: &mut AppData,
         currency_id: &str,
     ) {
-        let balance;
         let currency = self.get_currency(currency_id);
-        {
-            let chain_id = data.chain_id.id();
-            let owner = data.wallet_address();
-            let cache = SHARED_CACHE.read().unwrap();
-            match currency {
-                Currency::Native(_) => {
-                    let (_, bal) = cache.get_eth_balance(chain_id, owner);
-                    balance = bal;
-                }
-                Currency::ERC20(token) => {
-                   balance = cache.get_erc20_balance(&chain_id, &owner, &token.address);
-                }
-            }
-        }
+        let balance = SHARED_CACHE.read().unwrap().fetched_balance_of(data.chain_id.id(), data.wallet_address(), currency);
 
-        let balance_text = RichText::new("Balance:")
+        let balance_text = RichText::new(t("swap.balance"))
         .size(12.0)
         .family(roboto_regular())
         .color(Color32::WHITE);
 
-        let balance = format_wei(&balance.to_string(), currency.decimals());
-        let formated_balance = format!("{:.4}", balance);
-
         ui.horizontal(|ui| {
             ui.label(balance_text);
             ui.add_space(1.0);
-            ui.label(formated_balance);
+            balance_label(ui, balance, currency.decimals(), "");
+        });
+
+    }
+
+    /// Show the spot price of the pair (eg. `1 WETH = 3,210.12 USDC`) and the pool it comes from
+    fn spot_price_label(&self, ui: &mut Ui) {
+        let Some(res) = &self.spot_price else {
+            return;
+        };
+
+        let text = format!(
+            "1 {} = {} {}",
+            self.currency_in.symbol(),
+            format_price(res.price, res.token_out.decimals),
+            self.currency_out.symbol()
+        );
+
+        let pool = match res.pool.variant {
+            PoolVariant::UniswapV2 => "V2".to_string(),
+            PoolVariant::UniswapV3 => format!("V3 {}%", res.pool.fee as f32 / 10_000.0),
+        };
+
+        ui.label(rich_text(&text, 12.0))
+            .on_hover_text(format!("Spot price of the {} pool {}", pool, truncate_address(&res.pool.address.to_string())));
+    }
+
+    /// Show the slippage that applies to the selected pair and why
+    fn slippage_label(&self, ui: &mut Ui, data: &AppData) {
+        let slippage = data.swap_slippage(&self.currency_in, &self.currency_out);
+        ui.label(rich_text(&format!("Slippage: {}% ({})", slippage.value, slippage.reason), 12.0));
+        ui.add_space(5.0);
+    }
+
+    /// Show the DEX and the pools of the last quote on a single line, eg. `Uniswap V3 0.05% · 1 hop`
+    fn route_summary(&self, ui: &mut Ui) {
+        let Some(quote) = &self.quote else {
+            return;
+        };
+        if quote.route.hops.is_empty() {
+            return;
+        }
+
+        let pools: Vec<String> = quote.route.hops.iter().map(|hop| hop.pool.to_string()).collect();
+        ui.label(rich_text(&quote.route.summary(), 12.0).color(Color32::GRAY))
+            .on_hover_text(pools.join("\n"));
+    }
+
+    /// Show the block the last quote was simulated on, a quote that fell behind is shown as stale
+    fn quote_block_label(&self, ui: &mut Ui, data: &AppData) {
+        let Some(quote) = &self.quote else {
+            return;
+        };
+
+        let latest = data.latest_block().number;
+        let text = quote.block_text(latest);
+        if quote.is_stale(latest) {
+            ui.label(rich_text(&tr("swap.stale_quote", &[("block", &text)]), 12.0).color(Color32::YELLOW))
+                .on_hover_text(t("swap.new_quote_hint"));
+        } else {
+            ui.label(rich_text(&text, 12.0).color(Color32::GRAY));
+        }
+    }
+
+    /// Show the minimum received, the pool fee and any transfer tax of the last quote
+    ///
+    /// The header row collapses the details, the choice is saved to the settings
+    fn quote_details(&self, ui: &mut Ui, data: &mut AppData) {
+        let Some(quote) = &self.quote else {
+            return;
+        };
+
+        let open = data.settings.quote_details_open;
+        let res = CollapsingHeader::new(rich_text(&t("swap.quote_details"), 12.0))
+            .id_source("quote_details")
+            .open(Some(open))
+            .show(ui, |ui| Self::quote_details_body(ui, quote, data));
+
+        if res.header_response.clicked() {
+            data.settings.quote_details_open = !open;
+            if let Err(e) = data.save_settings() {
+                notify_error_text(UiText::key("error.saving_settings").with("error", e));
+            }
+        }
+        ui.add_space(5.0);
+    }
+
+    fn quote_details_body(ui: &mut Ui, quote: &QuoteResult, data: &AppData) {
+        let output = &quote.output_token.currency;
+        let mut text = format!("| Pool fee: {}", quote.route.fee_text());
+        if let Some(tax) = quote.token_tax_text() {
+            text.push_str(&format!(" | Transfer tax: {}", tax));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(rich_text(&t("swap.minimum_received"), 12.0));
+            amount_label(ui, quote.minimum_received, output.decimals(), &output.symbol());
+            ui.label(rich_text(&text, 12.0)).on_hover_text(format!(
+                "Quoted {} of {} at block {}",
+                quote.input_token.amount_text(),
+                quote.input_token.balance_text(4),
+                quote.block_number
+            ));
+        });
+        if data.node_lag().is_some() {
+            ui.label(rich_text(&t("swap.node_behind"), 12.0).color(Color32::YELLOW));
+        }
+        // the quote is dropped when the router changes, so this is the contract the swap and approval go to
+        let chain_id = data.chain_id.id();
+        let router = data.settings.swap_router(chain_id);
+        ui.horizontal(|ui| {
+            ui.label(rich_text(&format!("Router: {} {}", router.name(), quote.router), 12.0));
+            if router != SwapRouter::default_for(chain_id) {
+                ui.label(rich_text("overridden", 12.0).color(Color32::YELLOW))
+                    .on_hover_text("Set in the Network Settings");
+            }
         });
+        ui.add_space(5.0);
+
+        if quote.pool_quotes.len() > 1 {
+            CollapsingHeader::new(rich_text(&t("swap.compare_pools"), 12.0))
+                .id_source("compare_pools")
+                .show(ui, |ui| {
+                    for pool in &quote.pool_quotes {
+                        // highlight the pool the quote was taken from
+                        let color = if quote.route.uses_pool(pool.address) { Color32::GREEN } else { Color32::WHITE };
+                        ui.horizontal(|ui| {
+                            ui.label(rich_text(&format!("{}:", pool.label()), 12.0).color(color))
+                                .on_hover_text(pool.address.to_string());
+                            amount_label(ui, pool.amount_out, output.decimals(), &output.symbol());
+                        });
+                    }
+                });
+            ui.add_space(5.0);
+        }
+    }
+
+    /// Creates the Get Quote button
+    fn quote_button(&mut self, ui: &mut Ui, data: &AppData) {
+        // drop a quote of a pair that is no longer selected or of another router
+        let router = data.settings.swap_router(data.chain_id.id()).address(data.chain_id.id());
+        if self.quote.as_ref().is_some_and(|q| {
+            q.input_token.currency != self.currency_in || q.output_token.currency != self.currency_out || Some(q.router) != router
+        }) {
+            self.quote = None;
+        }
+
+        let amount_in = self.amount_in.amount_or_zero();
+        let same_token = same_asset(&self.currency_in, &self.currency_out);
+        let enabled = data.connected() && !amount_in.is_zero() && !same_token && !self.quote_pending;
+
+        let text = RichText::new(if self.quote_pending { t("swap.quoting") } else { t("swap.get_quote") })
+            .size(15.0)
+            .family(roboto_regular())
+            .color(Color32::WHITE);
+
+        let button = Button::new(text)
+            .min_size(vec2(100.0, 30.0))
+            .rounding(10.0);
+
+        let res = ui.add_enabled(enabled, button);
+        if same_token {
+            res.on_disabled_hover_text(t("swap.pick_different"));
+        } else if res.clicked() {
+            self.request_quote(data);
+        }
+    }
+
+    /// The approval amount of this swap
+    fn approval(&self, data: &AppData) -> ApprovalAmount {
+        self.approval_override.unwrap_or(data.settings.approval_amount)
+    }
 
+    /// The token and amount of the approval this swap needs, `None` if the input is the native currency
+    fn approval_params(&self, data: &AppData) -> Option<(Address, U256)> {
+        let router = data.settings.swap_router(data.chain_id.id()).address(data.chain_id.id())?;
+        let amount_in = self.amount_in.amount().filter(|amount| !amount.is_zero())?;
+        self.currency_in.erc20()?;
+        Some((router, self.approval(data).amount(amount_in)))
+    }
+
+    /// Pick the approval amount of this swap, the default comes from the General Settings
+    fn approval_row(&mut self, ui: &mut Ui, data: &AppData) {
+        if self.approval_params(data).is_none() {
+            return;
+        }
+
+        let approval = self.approval(data);
+        ui.horizontal(|ui| {
+            ui.add_space(180.0);
+            ui.label(rich_text(&t("swap.approve_label"), 12.0));
+            for option in [ApprovalAmount::Exact, ApprovalAmount::Infinite] {
+                if ui.add(SelectableLabel::new(approval == option, option.name())).clicked() {
+                    self.approval_override = Some(option);
+                    self.confirming_approval = false;
+                }
+            }
+        });
+        ui.add_space(5.0);
+    }
+
+    /// Creates the approve button, the approval has to be confirmed with its amount
+    fn approve_button(&mut self, ui: &mut Ui, data: &AppData) {
+        let Some((router, amount)) = self.approval_params(data) else {
+            return;
+        };
+        let balance = self.balance(data, "input");
+        let readiness = swap_readiness(data, &self.currency_in, &self.amount_in, balance);
+
+        if !self.confirming_approval {
+            let text = RichText::new(t("swap.approve")).size(15.0).family(roboto_regular()).color(Color32::WHITE);
+            let button = Button::new(text).min_size(vec2(100.0, 30.0)).rounding(10.0);
+            if ui.add_enabled(readiness.is_ready(), button).clicked() {
+                self.confirming_approval = true;
+            }
+            return;
+        }
+
+        let symbol = self.currency_in.symbol();
+        let amount = if amount == U256::MAX {
+            format!("unlimited {}", symbol)
+        } else {
+            format!("{} {}", format_units(amount, self.currency_in.decimals()), symbol)
+        };
+        let router_name = data.settings.swap_router(data.chain_id.id()).name();
+
+        ui.vertical(|ui| {
+            ui.label(rich_text(&tr("swap.approve_confirm", &[("router", &router_name), ("amount", &amount)]), 13.0))
+                .on_hover_text(router.to_string());
+            sign_mode_toggle(ui, &mut self.sign_only);
+            ui.horizontal(|ui| {
+                if ui.button(t("common.confirm")).clicked() {
+                    self.confirming_approval = false;
+                    if readiness.is_ready() {
+                        self.approve(data, router);
+                    }
+                }
+                if ui.button(t("common.back")).clicked() {
+                    self.confirming_approval = false;
+                }
+            });
+        });
+    }
+
+    fn approve(&mut self, data: &AppData, spender: Address) {
+        let (Some(wallet), Some(client)) = (data.profile.current_wallet.clone(), data.client.clone()) else {
+            return;
+        };
+        let (Some(token), Some(amount_in)) = (self.currency_in.erc20(), self.amount_in.amount()) else {
+            return;
+        };
+
+        let params = ApproveParams {
+            token: token.clone(),
+            spender,
+            amount_in,
+            approval: self.approval(data),
+            wallet,
+            chain_id: data.chain_id.id(),
+            next_base_fee: data.next_block.base_fee,
+            priority_fee: data.tx_settings.parse_gwei(),
+            mev_protect: data.tx_settings.mev_protect,
+            gas_multiplier: data.tx_settings.parse_gas_multiplier(),
+            dry_run: data.settings.dry_run,
+            client,
+        };
+        let req = if self.sign_only {
+            Request::sign_transaction(TxRequest::Approve(params))
+        } else {
+            Request::approve(params)
+        };
+
+        match self.sender.send(req) {
+            Ok(_) => self.approval_override = None,
+            Err(e) => {
+                notify_error(e);
+            }
+        }
     }
 
     /// Creates the swap button
+    ///
+    /// The button is disabled and shows the reason until the swap is [super::readiness::TxReadiness::Ready]
+    ///
+    /// The allowance of the router comes with the quote, so a new quote is needed after an approval
     fn swap_button(&mut self, ui: &mut Ui, data: &mut AppData) {
-        let text = RichText::new("Swap")
+        let balance = self.balance(data, "input");
+        let mut readiness = swap_readiness(data, &self.currency_in, &self.amount_in, balance);
+        if readiness.is_ready() {
+            let allowance = self.quote.as_ref().and_then(|quote| quote.allowance);
+            readiness = allowance_readiness(&self.currency_in, &self.amount_in, allowance);
+        }
+
+        let text = RichText::new(readiness.label(&t("swap.swap")))
             .size(15.0)
             .family(roboto_regular())
             .color(Color32::WHITE);
@@ -281,9 +770,67 @@ impl SwapUI {
             .min_size(vec2(100.0, 30.0))
             .rounding(10.0);
 
-        if ui.add(button).clicked() {
+        if ui.add_enabled(readiness.is_ready(), button).clicked() {
             trace!("Swap button clicked, TODO!");
         }
 
     }
 }
+
+/// Are both currencies the same asset, a native currency and its wrapped version count as the same
+fn same_asset(a: &Currency, b: &Currency) -> bool {
+    a.chain_id() == b.chain_id() && a.wrapped().address == b.wrapped().address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam::channel::unbounded;
+    use zeus_chain::defi_types::currency::erc20::ERC20Token;
+
+    fn swap_ui() -> SwapUI {
+        let (sender, _) = unbounded();
+        let mut swap = SwapUI::new(sender);
+        swap.currency_in = Currency::new_erc20(ERC20Token::eth_default_input());
+        swap.currency_out = Currency::new_erc20(ERC20Token::eth_default_output());
+        swap
+    }
+
+    #[test]
+    fn picking_the_other_side_flips_the_pair() {
+        let mut swap = swap_ui();
+        let (weth, usdc) = (swap.currency_in.clone(), swap.currency_out.clone());
+
+        swap.replace_currency("input", usdc.clone());
+        assert_eq!((&swap.currency_in, &swap.currency_out), (&usdc, &weth));
+
+        swap.replace_currency("output", usdc.clone());
+        assert_eq!((&swap.currency_in, &swap.currency_out), (&weth, &usdc));
+    }
+
+    #[test]
+    fn native_and_wrapped_count_as_the_same_currency() {
+        let mut swap = swap_ui();
+        let (weth, usdc) = (swap.currency_in.clone(), swap.currency_out.clone());
+        let eth = Currency::new_native(1);
+
+        swap.replace_currency("output", eth.clone());
+        assert_eq!((&swap.currency_in, &swap.currency_out), (&usdc, &eth));
+
+        swap.replace_currency("input", weth.clone());
+        assert_eq!((&swap.currency_in, &swap.currency_out), (&weth, &usdc));
+    }
+
+    #[test]
+    fn picking_a_different_currency_keeps_the_other_side() {
+        let mut swap = swap_ui();
+        let weth = swap.currency_in.clone();
+        let token = Currency::new_erc20(ERC20Token {
+            address: Address::repeat_byte(1),
+            ..ERC20Token::eth_default_output()
+        });
+
+        swap.replace_currency("output", token.clone());
+        assert_eq!((&swap.currency_in, &swap.currency_out), (&weth, &token));
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Instant;
+
+use eframe::egui::{vec2, Align2, Ui, Window};
+
+use crate::gui::misc::{frame, rich_text};
+use zeus_shared_types::{state::info::INFO_MSG_DURATION, t, take_notifications, ErrorMsg, InfoMsg, UiNotification};
+
+/// Shows the [UiNotification]s sent from anywhere in the app
+///
+/// Owned by the GUI thread, the queue is taken once per frame with [Toasts::poll]
+#[derive(Default)]
+pub struct Toasts {
+    err_msg: ErrorMsg,
+
+    info_msg: InfoMsg,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the queued notifications, a newer message replaces the one on screen
+    ///
+    /// Messages are translated here, in the language of the UI at the time they arrive
+    ///
+    /// Returns `true` if an error came in, UIs waiting on the backend stop waiting on it
+    pub fn poll(&mut self) -> bool {
+        let mut error = false;
+        for notification in take_notifications() {
+            match notification {
+                UiNotification::Error(msg) => {
+                    self.err_msg.show(msg.translate());
+                    error = true;
+                }
+                UiNotification::Info(msg) => self.info_msg = InfoMsg::timed(msg.translate(), INFO_MSG_DURATION),
+                UiNotification::Notice(msg) => self.info_msg = InfoMsg::new(true, msg.translate()),
+            }
+        }
+        error
+    }
+
+    /// Show the error message if needed
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_err_msg(&mut self, ui: &mut Ui) {
+        if self.err_msg.state.is_close() {
+            return;
+        }
+
+        Window::new("Error")
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .title_bar(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    let msg_text = rich_text(&self.err_msg.msg, 16.0);
+                    let close_text = rich_text(&t("common.close"), 16.0);
+
+                    ui.label(msg_text);
+                    ui.add_space(5.0);
+                    if ui.button(close_text).clicked() {
+                        self.err_msg.close();
+                    }
+                });
+            });
+    }
+
+    /// Show the info message if needed
+    ///
+    /// Timed messages, see [zeus_shared_types::notify_info], are closed once they expire
+    pub fn show_info_msg(&mut self, ui: &mut Ui) {
+        if self.info_msg.on && self.info_msg.expired(Instant::now()) {
+            self.info_msg.on = false;
+        }
+        if !self.info_msg.on {
+            return;
+        }
+        // repaint when it expires even if nothing else happens
+        if let Some(expires_at) = self.info_msg.expires_at {
+            ui.ctx().request_repaint_after(expires_at.saturating_duration_since(Instant::now()));
+        }
+
+        ui.vertical_centered_justified(|ui| {
+            frame().show(ui, |ui| {
+                ui.set_max_size(vec2(1000.0, 50.0));
+
+                let msg_text = rich_text(&self.info_msg.msg, 16.0);
+                let close_text = rich_text(&t("common.close"), 16.0);
+
+                ui.label(msg_text);
+                ui.add_space(5.0);
+                if ui.button(close_text).clicked() {
+                    self.info_msg.on = false;
+                }
+            });
+        });
+    }
+}
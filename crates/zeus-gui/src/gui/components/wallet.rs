@@ -2,31 +2,79 @@ use eframe::{
     egui::{Align2, Button, Color32, ComboBox, FontId, RichText, Sense, TextEdit, Ui, Window},
     epaint::vec2,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{str::FromStr, sync::Arc};
 
-use crate::{fonts::roboto_regular, icons::IconTextures};
+use super::{address_check::copy_text, balance_history::BalanceHistoryUI};
+use crate::{fonts::roboto_regular, gui::misc::{balance_label, truncate_address}, icons::IconTextures};
 use crossbeam::channel::Sender;
 use tracing::trace;
-use zeus_backend::types::Request;
-use zeus_chain::alloy::primitives::utils::format_ether;
-use zeus_core::Credentials;
-use zeus_shared_types::{AppData, UiState, SHARED_UI_STATE};
+use zeus_backend::types::{ExportKeystoreParams, Request};
+use zeus_chain::{alloy::primitives::Address, defi_types::currency::Currency};
+use zeus_core::{sanitize_wallet_name, zeroize::Zeroize, UnlockCredentials, MAX_WALLET_NAME_LEN};
+use zeus_shared_types::{cache::SHARED_CACHE, AppData, ProfileSave, UiState, notify_error, notify_info};
 
-/// UI for viewing a private key
+/// UI for viewing a private key or exporting it as a keystore file
 pub struct ViewPrivateKeyUI {
     pub state: UiState,
     pub show_key: UiState,
     pub exported_key: String,
-    pub credentials: Credentials,
+    pub credentials: UnlockCredentials,
+    pub sender: Sender<Request>,
+
+    /// The keystore fields are shown
+    keystore: UiState,
+    keystore_password: String,
+    keystore_confirm: String,
+    keystore_path: String,
 }
 
 impl ViewPrivateKeyUI {
-    pub fn new() -> Self {
+    pub fn new(sender: Sender<Request>) -> Self {
         Self {
             state: UiState::default(),
             show_key: UiState::default(),
             exported_key: String::new(),
-            credentials: Credentials::default(),
+            credentials: UnlockCredentials::default(),
+            sender,
+            keystore: UiState::default(),
+            keystore_password: String::new(),
+            keystore_confirm: String::new(),
+            keystore_path: String::new(),
+        }
+    }
+
+    fn clear_keystore(&mut self) {
+        self.keystore_password.zeroize();
+        self.keystore_confirm.zeroize();
+        self.keystore.close();
+    }
+
+    /// The request to encrypt the current wallet with the keystore password and write it to the keystore path
+    ///
+    /// The backend answers with [zeus_backend::types::Response::KeystoreExported]
+    fn export_keystore(&self, data: &AppData) -> Result<Request, anyhow::Error> {
+        if self.keystore_password != self.keystore_confirm {
+            return Err(anyhow::anyhow!("The keystore passwords do not match"));
+        }
+        let path = self.keystore_path.trim();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Choose where to save the keystore"));
+        }
+
+        let wallet = data.profile.current_wallet.clone().ok_or(anyhow::anyhow!("No wallet selected"))?;
+        Ok(Request::export_keystore(ExportKeystoreParams {
+            profile: data.profile.clone(),
+            wallet,
+            credentials: self.credentials.clone(),
+            password: self.keystore_password.clone(),
+            path: path.to_string(),
+        }))
+    }
+
+    /// Send a request to the backend
+    pub fn send_request(&self, request: Request) {
+        if let Err(e) = self.sender.send(request) {
+            trace!("Error sending request: {}", e);
         }
     }
 
@@ -40,8 +88,7 @@ impl ViewPrivateKeyUI {
 
         if data.profile.current_wallet.is_none() {
             self.state.close();
-            let mut state = SHARED_UI_STATE.write().unwrap();
-            state.err_msg.show("No wallet selected");
+            notify_error("No wallet selected");
             return;
         }
 
@@ -111,16 +158,36 @@ impl ViewPrivateKeyUI {
                         .sense(Sense::click())
                         .min_size(vec2(70.0, 30.0));
 
+                    let keystore_text = RichText::new("Export as keystore")
+                        .family(roboto_regular())
+                        .size(15.0)
+                        .color(Color32::WHITE);
+
+                    let keystore_button = Button::new(keystore_text)
+                        .rounding(10.0)
+                        .sense(Sense::click())
+                        .min_size(vec2(70.0, 30.0));
+
+                    if ui.add(keystore_button).on_hover_text("A password protected file most wallets can import").clicked() {
+                        if self.keystore.is_open() {
+                            self.clear_keystore();
+                        } else {
+                            let address = data.profile.current_wallet.as_ref().map(|wallet| wallet.key.address());
+                            self.keystore_path = address.map_or(String::new(), |address| format!("{}.json", address));
+                            self.keystore.open();
+                        }
+                    }
+                    ui.add_space(10.0);
+                    self.keystore_fields(ui, data, font.clone());
+
                     if ui.add(view_button).clicked() {
                         let wallet = data.profile.current_wallet.clone().unwrap();
-                        self.credentials.copy_passwd_to_confirm();
 
-                        let key = match data.profile.export_wallet(wallet, self.credentials.clone())
+                        let key = match data.profile.export_wallet(wallet, &self.credentials)
                         {
                             Ok(key) => key,
                             Err(e) => {
-                                let mut state = SHARED_UI_STATE.write().unwrap();
-                                state.err_msg.show(e);
+                                notify_error(e);
                                 return;
                             }
                         };
@@ -144,12 +211,46 @@ impl ViewPrivateKeyUI {
                         .min_size(vec2(70.0, 30.0));
 
                     if ui.add(close_button).clicked() {
+                        self.clear_keystore();
                         self.state.close();
                     }
                 });
             });
     }
 
+    /// The keystore password, its confirmation and where to save the file
+    fn keystore_fields(&mut self, ui: &mut Ui, data: &AppData, font: FontId) {
+        if self.keystore.is_close() {
+            return;
+        }
+
+        let label = |text: &str| RichText::new(text).family(roboto_regular()).size(15.0).color(Color32::WHITE);
+
+        ui.label(label("Keystore Password:"));
+        ui.add(keystore_field(&mut self.keystore_password, true, font.clone()));
+        ui.label(label("Confirm Keystore Password:"));
+        ui.add(keystore_field(&mut self.keystore_confirm, true, font.clone()));
+        ui.label(label("Save To:"));
+        ui.add(keystore_field(&mut self.keystore_path, false, font).hint_text("Path of the keystore file"));
+        ui.add_space(5.0);
+
+        let enabled = !self.keystore_password.is_empty() && !self.keystore_confirm.is_empty();
+        if ui.add_enabled(enabled, Button::new(label("Save Keystore")).rounding(10.0)).clicked() {
+            match self.export_keystore(data) {
+                Ok(request) => {
+                    self.send_request(request);
+                    self.credentials.clear();
+                    self.clear_keystore();
+                    notify_info("Saving the keystore...");
+                }
+                Err(e) => {
+                    notify_error(e);
+                }
+            }
+        }
+        ui.add_space(10.0);
+    }
+
     fn show_key(&mut self, ui: &mut Ui) {
         if self.show_key.is_close() {
             return;
@@ -195,12 +296,43 @@ impl ViewPrivateKeyUI {
     }
 }
 
-/// UI for importing a wallet from a private key
+/// Text field for a keystore password or path
+fn keystore_field(text: &mut String, password: bool, font: FontId) -> TextEdit<'_> {
+    TextEdit::singleline(text)
+        .desired_width(250.0)
+        .min_size(vec2(150.0, 25.0))
+        .password(password)
+        .font(font)
+}
+
+/// Text field for a wallet name with a counter of the characters that will be kept
+fn wallet_name_field(ui: &mut Ui, name: &mut String, font: FontId) {
+    let name_field = TextEdit::singleline(name)
+        .desired_width(150.0)
+        .min_size(vec2(150.0, 25.0))
+        .char_limit(MAX_WALLET_NAME_LEN)
+        .font(font);
+    ui.add(name_field);
+
+    let count = sanitize_wallet_name(name).chars().count();
+    let counter = RichText::new(format!("{}/{}", count, MAX_WALLET_NAME_LEN))
+        .family(roboto_regular())
+        .size(12.0)
+        .color(Color32::GRAY);
+    ui.label(counter);
+}
+
+/// UI for importing a wallet from a private key or a keystore file
 pub struct ImportWalletUI {
     pub state: UiState,
     pub wallet_name: String,
     pub private_key: String,
     pub sender: Sender<Request>,
+
+    /// Import from a keystore file instead of a private key
+    from_keystore: bool,
+    keystore_path: String,
+    keystore_password: String,
 }
 
 impl ImportWalletUI {
@@ -210,7 +342,35 @@ impl ImportWalletUI {
             wallet_name: String::new(),
             private_key: String::new(),
             sender,
+            from_keystore: false,
+            keystore_path: String::new(),
+            keystore_password: String::new(),
+        }
+    }
+
+    /// Import the private key, a keystore is decrypted by the backend
+    ///
+    /// See [zeus_backend::types::Response::KeystoreImported]
+    fn import(&self, data: &mut AppData) -> Result<(), anyhow::Error> {
+        if !self.from_keystore {
+            data.profile.import_wallet(self.wallet_name.clone(), self.private_key.clone())?;
+            data.mark_profile_dirty();
+            return Ok(());
         }
+
+        let request = Request::import_keystore(
+            self.wallet_name.clone(),
+            self.keystore_path.clone(),
+            self.keystore_password.clone(),
+        );
+        self.send_request(request);
+        notify_info("Decrypting the keystore...");
+        Ok(())
+    }
+
+    fn clear_secrets(&mut self) {
+        self.private_key.zeroize();
+        self.keystore_password.zeroize();
     }
 
     /// Send a request to the backend
@@ -259,18 +419,27 @@ impl ImportWalletUI {
                         .size(18.0)
                         .color(Color32::WHITE);
 
-                    let name_field = TextEdit::singleline(&mut self.wallet_name)
-                        .desired_width(150.0)
-                        .min_size(vec2(150.0, 25.0))
-                        .font(font);
-
                     ui.label(name_text);
                     ui.add_space(5.0);
-                    ui.add(name_field);
+                    wallet_name_field(ui, &mut self.wallet_name, font.clone());
                     ui.add_space(15.0);
-                    ui.label(private_key);
-                    ui.add_space(5.0);
-                    ui.add(private_key_field);
+                    ui.checkbox(&mut self.from_keystore, "From a keystore file");
+                    ui.add_space(10.0);
+
+                    if self.from_keystore {
+                        let label = |text: &str| RichText::new(text).family(roboto_regular()).size(18.0).color(Color32::WHITE);
+                        ui.label(label("Keystore File:"));
+                        ui.add_space(5.0);
+                        ui.add(keystore_field(&mut self.keystore_path, false, font.clone()).hint_text("Path of the keystore file"));
+                        ui.add_space(10.0);
+                        ui.label(label("Keystore Password:"));
+                        ui.add_space(5.0);
+                        ui.add(keystore_field(&mut self.keystore_password, true, font));
+                    } else {
+                        ui.label(private_key);
+                        ui.add_space(5.0);
+                        ui.add(private_key_field);
+                    }
                     ui.add_space(15.0);
 
                     let import_text = RichText::new("Import Wallet")
@@ -294,29 +463,28 @@ impl ImportWalletUI {
                         .min_size(vec2(70.0, 30.0));
 
                     if ui.add(import_button).clicked() {
-                        match data.profile.import_wallet(
-                            self.wallet_name.clone(),
-                            HashMap::new(),
-                            self.private_key.clone(),
-                        ) {
+                        if data.read_only {
+                            notify_error("Zeus is running in read-only mode");
+                            return;
+                        }
+
+                        match self.import(data) {
                             Ok(_) => {
                                 self.state.close();
                                 self.wallet_name.clear();
-                                self.private_key.clear();
+                                self.keystore_path.clear();
+                                self.clear_secrets();
                             }
                             Err(e) => {
-                                let mut state = SHARED_UI_STATE.write().unwrap();
-                                state.err_msg.show(e);
+                                notify_error(e);
                             }
                         }
-
-                        self.send_request(Request::SaveProfile(data.profile.clone()));
                     }
                     ui.add_space(15.0);
 
                     if ui.add(close_button).clicked() {
                         self.state.close();
-                        self.private_key.clear();
+                        self.clear_secrets();
                     }
                 });
             });
@@ -374,14 +542,9 @@ impl CreateNewWalletUI {
                         .size(18.0)
                         .color(Color32::WHITE);
 
-                    let name_field = TextEdit::singleline(&mut self.wallet_name)
-                        .desired_width(150.0)
-                        .min_size(vec2(150.0, 25.0))
-                        .font(font);
-
                     ui.label(wallet_name);
                     ui.add_space(5.0);
-                    ui.add(name_field);
+                    wallet_name_field(ui, &mut self.wallet_name, font);
                     ui.add_space(25.0);
 
                     let create_text = RichText::new("Create Wallet")
@@ -405,18 +568,21 @@ impl CreateNewWalletUI {
                         .min_size(vec2(70.0, 30.0));
 
                     if ui.add(create_button).clicked() {
+                        if data.read_only {
+                            notify_error("Zeus is running in read-only mode");
+                            return;
+                        }
+
                         match data.profile.new_wallet(self.wallet_name.clone()) {
                             Ok(_) => {
                                 self.state.close();
                                 self.wallet_name.clear();
+                                data.mark_profile_dirty();
                             }
                             Err(e) => {
-                                let mut state = SHARED_UI_STATE.write().unwrap();
-                                state.err_msg.show(e);
+                                notify_error(e);
                             }
                         }
-
-                        self.send_request(Request::SaveProfile(data.profile.clone()));
                     }
                     ui.add_space(15.0);
 
@@ -449,6 +615,7 @@ pub struct WalletUI {
     pub view_key_ui: ViewPrivateKeyUI,
     pub import_wallet_ui: ImportWalletUI,
     pub create_wallet_ui: CreateNewWalletUI,
+    pub balance_history: BalanceHistoryUI,
 }
 
 impl WalletUI {
@@ -456,44 +623,71 @@ impl WalletUI {
         Self {
             state: UiState::default(),
             new_wallet_ui: UiState::default(),
-            view_key_ui: ViewPrivateKeyUI::new(),
+            view_key_ui: ViewPrivateKeyUI::new(sender.clone()),
             import_wallet_ui: ImportWalletUI::new(sender.clone()),
             create_wallet_ui: CreateNewWalletUI::new(sender.clone()),
+            balance_history: BalanceHistoryUI::new(sender),
         }
     }
 
     /// Show this UI
     ///
+    /// Returns `true` if the refresh button was clicked
+    ///
     /// This should be called by the [eframe::App::update] method
-    pub fn show(&mut self, ui: &mut Ui, data: &mut AppData, icons: Arc<IconTextures>) {
+    pub fn show(&mut self, ui: &mut Ui, data: &mut AppData, icons: Arc<IconTextures>) -> bool {
         if self.state.is_close() {
-            return;
+            return false;
         }
+        let mut refresh = false;
 
         ui.vertical_centered(|ui| {
             ui.add_space(10.0);
 
             ui.horizontal(|ui| {
                 self.available_wallets(ui, data);
+                if data.profile.current_wallet.is_some()
+                    && ui.small_button("Copy").on_hover_text("Copy the wallet address").clicked()
+                {
+                    copy_text(ui.ctx(), data.wallet_address().to_string());
+                }
+                save_indicator(ui, data.profile_save);
 
                 // show the balance of the selected wallet
                 let owner = data.wallet_address();
-                let (_, balance) = data.eth_balance(data.chain_id.id(), owner);
-                let formated = format!("{:.4}", format_ether(balance));
-                let balance_text = RichText::new(&formated)
-                    .family(roboto_regular())
-                    .size(15.0)
-                    .color(Color32::WHITE);
+                let native = Currency::new_native(data.chain_id.id());
+                let balance = SHARED_CACHE.read().unwrap().fetched_balance_of(data.chain_id.id(), owner, &native);
 
                 ui.add(icons.currency_icon(data.chain_id.id()));
-                ui.label(balance_text);
+                balance_label(ui, balance, native.decimals(), "");
+                self.balance_history.sparkline(ui, data);
+
+                if data.profile.current_wallet.is_some() {
+                    let res = ui.add_enabled(data.connected(), Button::new("🔄").small());
+                    refresh = res
+                        .on_hover_text("Read the balances from the node again, skipping the cache")
+                        .on_disabled_hover_text("Not connected to a node")
+                        .clicked();
+                }
             });
+
+            if let Some(faucet) = data.chain_id.faucet_url() {
+                let text = RichText::new(format!("Get {} test coins", data.chain_id.name()))
+                    .family(roboto_regular())
+                    .size(13.0);
+                ui.hyperlink_to(text, faucet);
+            }
             // TODO: Portofolio value in USD
         });
+
+        refresh
     }
 
     fn available_wallets(&self, ui: &mut Ui, data: &mut AppData) {
-        let wallet_name = &data.profile.current_wallet_name_truncated();
+        let wallet_name = match &data.profile.current_wallet {
+            Some(wallet) => wallet_display_name(&wallet.name),
+            None => "No Wallet Available".to_string(),
+        };
         let selected_text = RichText::new(wallet_name)
             .family(roboto_regular())
             .size(13.0)
@@ -508,9 +702,32 @@ impl WalletUI {
                     ui.selectable_value(
                         &mut data.profile.current_wallet,
                         Some(wallet.clone()),
-                        wallet.name.clone(),
+                        wallet_display_name(&wallet.name),
                     );
                 }
             });
     }
 }
+
+/// A small "Saving…" / "Saved" label next to the wallet selector
+fn save_indicator(ui: &mut Ui, save: ProfileSave) {
+    let (text, color) = match save {
+        ProfileSave::Saved => ("Saved", Color32::GRAY),
+        ProfileSave::Dirty(_) | ProfileSave::Saving => ("Saving…", Color32::GRAY),
+        ProfileSave::Failed => ("Not saved", Color32::RED),
+    };
+    let text = RichText::new(text).family(roboto_regular()).size(11.0).color(color);
+    let res = ui.label(text);
+    if save == ProfileSave::Failed {
+        res.on_hover_text("The profile could not be saved, use Settings > Save Profile to retry");
+    }
+}
+
+/// Wallets without a name are named after their address, truncate it
+fn wallet_display_name(name: &str) -> String {
+    if Address::from_str(name).is_ok() {
+        truncate_address(name)
+    } else {
+        name.to_string()
+    }
+}
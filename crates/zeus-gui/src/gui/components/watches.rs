@@ -0,0 +1,161 @@
+use eframe::egui::{vec2, Align2, Button, Color32, ComboBox, RichText, Sense, TextEdit, Ui, Window};
+use crossbeam::channel::Sender;
+
+use crate::{fonts::roboto_regular, gui::misc::rich_text};
+use zeus_backend::types::{PriceWatch, Request, WatchCondition};
+use zeus_chain::{defi_types::currency::Currency, ChainFeature};
+use zeus_shared_types::{AppData, UiState, notify_error};
+
+/// The price watches in the side panel and the window to add a new one
+pub struct WatchesUI {
+    /// Send Request to the backend
+    pub sender: Sender<Request>,
+
+    /// State of the New Watch window
+    pub state: UiState,
+
+    /// The active watches as last sent by the backend
+    pub watches: Vec<PriceWatch>,
+
+    condition: WatchCondition,
+    target: String,
+}
+
+impl WatchesUI {
+    pub fn new(sender: Sender<Request>) -> Self {
+        Self {
+            sender,
+            state: UiState::default(),
+            watches: Vec::new(),
+            condition: WatchCondition::Above,
+            target: String::new(),
+        }
+    }
+
+    /// Do we have any watch on this chain
+    pub fn has_watches(&self, chain_id: u64) -> bool {
+        self.watches.iter().any(|w| w.chain_id == chain_id)
+    }
+
+    fn send_request(&self, request: Request) {
+        if let Err(e) = self.sender.send(request) {
+            notify_error(e);
+        }
+    }
+
+    /// Show the watches of the current chain with a cancel button each
+    pub fn show_list(&mut self, ui: &mut Ui, data: &AppData) {
+        let chain_id = data.chain_id.id();
+
+        ui.label(rich_text("Watches", 15.0));
+
+        let mut cancel = None;
+        for watch in self.watches.iter().filter(|w| w.chain_id == chain_id) {
+            ui.horizontal(|ui| {
+                ui.label(rich_text(&watch.describe(), 12.0));
+                if ui.small_button("x").on_hover_text("Cancel").clicked() {
+                    cancel = Some(watch.id);
+                }
+            });
+        }
+
+        if let Some(id) = cancel {
+            self.send_request(Request::cancel_watch(id));
+        }
+
+        let new_watch = ui
+            .add_enabled(data.chain_id.supports(ChainFeature::Dex), Button::new("New Watch").small())
+            .on_disabled_hover_text(format!("No DEX available on {}", data.chain_id.name()));
+        if new_watch.clicked() {
+            self.state.open();
+        }
+    }
+
+    /// Show the New Watch window for the pair selected in the swap panel
+    ///
+    /// Native currencies are watched through their wrapped version
+    pub fn show(&mut self, ui: &mut Ui, data: &AppData, currency_in: &Currency, currency_out: &Currency) {
+        if self.state.is_close() {
+            return;
+        }
+
+        let chain_id = data.chain_id.id();
+        let token_in = currency_in.wrapped();
+        let token_out = currency_out.wrapped();
+        let target = self.target.trim().parse::<f64>().ok().filter(|t| *t > 0.0);
+        let same_token = token_in.address == token_out.address;
+
+        let title = RichText::new("New Price Watch")
+            .family(roboto_regular())
+            .size(20.0)
+            .color(Color32::WHITE);
+
+        let save = RichText::new("Save").family(roboto_regular()).size(15.0);
+        let cancel = RichText::new("Cancel").family(roboto_regular()).size(15.0);
+
+        let save_button = Button::new(save)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let cancel_button = Button::new(cancel)
+            .rounding(10.0)
+            .sense(Sense::click())
+            .min_size(vec2(70.0, 25.0));
+
+        let mut save_clicked = false;
+
+        Window::new(title)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .collapsible(false)
+            .show(ui.ctx(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(rich_text("Notify me when the spot price of", 13.0));
+                    ui.add_space(5.0);
+
+                    ui.horizontal(|ui| {
+                        ui.label(rich_text(&format!("1 {} is", token_in.symbol), 15.0));
+                        ComboBox::from_id_source("watch_condition")
+                            .selected_text(self.condition.name())
+                            .show_ui(ui, |ui| {
+                                for condition in [WatchCondition::Above, WatchCondition::Below] {
+                                    ui.selectable_value(&mut self.condition, condition, condition.name());
+                                }
+                            });
+                        ui.add(TextEdit::singleline(&mut self.target).desired_width(80.0));
+                        ui.label(rich_text(&token_out.symbol, 15.0));
+                    });
+                    ui.add_space(5.0);
+
+                    ui.label(rich_text("Nothing is ever sent automatically", 12.0));
+                    if same_token {
+                        ui.label(rich_text("Select two different tokens in the swap panel", 12.0));
+                    }
+                    ui.add_space(15.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(target.is_some() && !same_token, save_button).clicked() {
+                            save_clicked = true;
+                        }
+                        if ui.add(cancel_button).clicked() {
+                            self.state.close();
+                        }
+                    });
+                });
+            });
+
+        if let (true, Some(target)) = (save_clicked, target) {
+            self.send_request(Request::add_watch(PriceWatch {
+                id: 0,
+                chain_id,
+                token_in,
+                token_out,
+                condition: self.condition,
+                target,
+            }));
+            self.target.clear();
+            self.state.close();
+        }
+    }
+}
@@ -1,15 +1,18 @@
 use eframe::{
     egui::{
-        vec2, widgets::TextEdit, Align2, Button, Checkbox, Color32, FontId, Frame, RichText,
-        Rounding, Sense, Ui, Window,
+        vec2, widgets::TextEdit, Align2, Area, Button, Checkbox, Color32, Context, FontId, Frame, Id, Label, Response,
+        RichText, Rounding, Sense, Stroke, Ui, Window,
     },
     epaint::{Margin, Shadow},
 };
 
+use std::time::Duration;
+
 use crate::fonts::roboto_regular;
 
 
-use zeus_shared_types::{AppData, ErrorMsg, SHARED_UI_STATE, UiState};
+use zeus_chain::{alloy::primitives::{Address, U256}, format_units, format_units_with_precision, ChainFeature, ETH_USD_FEED_DECIMALS};
+use zeus_shared_types::{cache::SHARED_CACHE, AmountInput, AppData, MAX_GAS_MULTIPLIER, SHARED_UI_STATE, notify_error, notify_error_text, notify_info_text, notify_notice, t, SharedUiState, UiText};
 
 use tracing::trace;
 
@@ -31,11 +34,11 @@ pub fn show_login(ui: &mut Ui, data: &mut AppData) {
 /// Paint the login screen
 pub fn login_screen(ui: &mut Ui, data: &mut AppData) {
 
-    let heading = rich_text("Unlock Profile", 16.0);
-    let unlock_txt = rich_text("Unlock", 16.0);
+    let heading = rich_text(&t("login.unlock_profile"), 16.0);
+    let unlock_txt = rich_text(&t("login.unlock"), 16.0);
 
-    let user_text = rich_text("Username", 16.0);
-    let pass_text = rich_text("Password", 16.0);
+    let user_text = rich_text(&t("login.username"), 16.0);
+    let pass_text = rich_text(&t("login.password"), 16.0);
 
     let font = FontId::new(15.0, roboto_regular());
 
@@ -48,7 +51,7 @@ pub fn login_screen(ui: &mut Ui, data: &mut AppData) {
 
 
             {
-                let user_mut = data.profile.credentials.user_mut();
+                let user_mut = data.unlock_credentials.user_mut();
                 let text_edit = TextEdit::singleline(user_mut)
                 .password(false)
                 .font(font.clone())
@@ -63,7 +66,7 @@ pub fn login_screen(ui: &mut Ui, data: &mut AppData) {
             ui.add_space(15.0);
 
             {
-                let pass_mut = data.profile.credentials.passwd_mut();
+                let pass_mut = data.unlock_credentials.passwd_mut();
                 let text_edit = TextEdit::singleline(pass_mut)
                 .password(true)
                 .font(font)
@@ -75,10 +78,6 @@ pub fn login_screen(ui: &mut Ui, data: &mut AppData) {
                 ui.add(text_edit);
                 ui.add_space(15.0);
             }
-            {
-                // set confrim password to the same as password
-                data.profile.credentials.copy_passwd_to_confirm();
-            }
        
 
         let button = Button::new(unlock_txt)
@@ -88,14 +87,17 @@ pub fn login_screen(ui: &mut Ui, data: &mut AppData) {
 
 
         if ui.add(button).clicked() {
-            match data.profile.decrypt_and_load() {
-                Ok(_) => {
+            match data.profile.decrypt_and_load(&data.unlock_credentials) {
+                Ok(from_backup) => {
+                    data.unlock_credentials.clear();
+                    if from_backup {
+                        backup_restored_msg("profile.data");
+                    }
                     trace!("Profile unlocked");
                     data.logged_in = true;
                 }
                 Err(e) => {
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.err_msg.show(e);
+                    notify_error(e);
                 }
             }
         }
@@ -108,77 +110,140 @@ pub fn new_profile_screen(ui: &mut Ui, data: &mut AppData) {
         return;
     }
 
-        let heading = rich_text("Create a Profile", 16.0);
-        let user_text = rich_text("Username", 16.0);
-        let pass_text = rich_text("Password", 16.0);
-        let confirm_text = rich_text("Confirm Password", 16.0);
-        let create_txt = rich_text("Create", 16.0);
+    let heading = rich_text(&t("login.create_profile"), 16.0);
 
+    ui.vertical_centered(|ui| {
+        ui.add_space(150.0);
 
-        ui.vertical_centered(|ui| {
+        ui.label(heading);
+        ui.add_space(30.0);
 
-            ui.add_space(150.0);
+        new_profile_form(ui, data);
+    });
+}
 
-            ui.label(heading);
-            ui.add_space(30.0);
+/// The credentials fields of a new profile and the button to create it
+///
+/// Shared by [new_profile_screen] and the first step of the onboarding wizard
+pub fn new_profile_form(ui: &mut Ui, data: &mut AppData) {
+    let user_text = rich_text(&t("login.username"), 16.0);
+    let pass_text = rich_text(&t("login.password"), 16.0);
+    let confirm_text = rich_text(&t("login.confirm_password"), 16.0);
+    let create_txt = rich_text(&t("login.create"), 16.0);
 
-            {
-                let user_mut = data.profile.credentials.user_mut();
-                let text_edit = TextEdit::singleline(user_mut)
-                    .password(false)
-                    .desired_width(150.0)
-                    .min_size(vec2(50.0, 25.0));
-                ui.label(user_text);
-                ui.add(text_edit);
-            }
+    {
+        let user_mut = data.credentials.user_mut();
+        let text_edit = TextEdit::singleline(user_mut)
+            .password(false)
+            .desired_width(150.0)
+            .min_size(vec2(50.0, 25.0));
+        ui.label(user_text);
+        ui.add(text_edit);
+    }
 
-            ui.add_space(10.0);
+    ui.add_space(10.0);
 
-            {
-                let pass_mut = data.profile.credentials.passwd_mut();
-                let text_edit = TextEdit::singleline(pass_mut)
-                    .password(true)
-                    .desired_width(150.0)
-                    .min_size(vec2(50.0, 25.0));
-                ui.label(pass_text);
-                ui.add(text_edit);
-            }
+    {
+        let pass_mut = data.credentials.passwd_mut();
+        let text_edit = TextEdit::singleline(pass_mut)
+            .password(true)
+            .desired_width(150.0)
+            .min_size(vec2(50.0, 25.0));
+        ui.label(pass_text);
+        ui.add(text_edit);
+    }
 
-            ui.add_space(10.0);
+    ui.add_space(10.0);
 
-            {
-                let pass_mut = data.profile.credentials.confirm_passwd_mut();
-                let text_edit = TextEdit::singleline(pass_mut)
-                    .password(true)
-                    .desired_width(150.0)
-                    .min_size(vec2(50.0, 25.0));
-                ui.label(confirm_text);
-                ui.add(text_edit);
+    {
+        let pass_mut = data.credentials.confirm_passwd_mut();
+        let text_edit = TextEdit::singleline(pass_mut)
+            .password(true)
+            .desired_width(150.0)
+            .min_size(vec2(50.0, 25.0));
+        ui.label(confirm_text);
+        ui.add(text_edit);
+    }
+
+    ui.add_space(15.0);
+
+    let button = Button::new(create_txt)
+        .rounding(10.0)
+        .sense(Sense::click())
+        .min_size(vec2(70.0, 25.0));
+
+    if ui.add(button).clicked() {
+        if data.read_only {
+            notify_error_text(UiText::key("error.read_only"));
+            return;
+        }
+
+        // derive the key and save the profile to disk
+        match data.profile.create(&data.credentials) {
+            Ok(_) => {
+                data.credentials.clear();
+                data.new_profile_screen = false;
+                data.profile_exists = true;
+                data.logged_in = true;
+                notify_info_text(UiText::key("info.profile_created"));
             }
+            Err(e) => {
+                notify_error(e);
+            }
+        }
+    }
+}
 
-            ui.add_space(15.0);
+/// A small notice in the corner that the profile is about to be locked for inactivity
+pub fn auto_lock_toast(ctx: &Context, left: Duration) {
+    Area::new(Id::new("auto_lock_toast"))
+        .anchor(Align2::RIGHT_BOTTOM, vec2(-10.0, -10.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(
+                    RichText::new(format!("Locking in {}s, move the mouse to stay unlocked", left.as_secs() + 1))
+                        .family(roboto_regular())
+                        .size(14.0)
+                        .color(Color32::YELLOW),
+                );
+            });
+        });
+}
 
-            let button = Button::new(create_txt)
-            .rounding(10.0)
-            .sense(Sense::click())
-            .min_size(vec2(70.0, 25.0));
+/// Shown on startup when another instance of Zeus holds the [zeus_core::InstanceLock]
+///
+/// Returns `Some(true)` if the user wants to quit, `Some(false)` to continue in read-only mode
+pub fn already_running_window(ui: &mut Ui, pid: u32) -> Option<bool> {
+    let mut choice = None;
 
-            if ui.add(button).clicked() {
-                // encrypt and save the wallets to disk
-                match data.profile.encrypt_and_save() {
-                    Ok(_) => {
-                        data.new_profile_screen = false;
-                        data.profile_exists = true;
-                        data.logged_in = true;
-                    }
-                    Err(e) => {
-                        let mut state = SHARED_UI_STATE.write().unwrap();
-                        state.err_msg.show(e);
-                    }
+    Window::new("Zeus Is Already Running")
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            ui.vertical_centered(|ui| {
+                let msg = format!(
+                    "Another instance of Zeus (PID {}) is using this profile and database",
+                    pid
+                );
+                ui.label(rich_text(&msg, 16.0));
+                ui.add_space(5.0);
+                ui.label(rich_text("In read-only mode nothing is saved to disk", 14.0));
+                ui.add_space(10.0);
+
+                if ui.add(button(rich_text("Quit", 16.0))).clicked() {
+                    choice = Some(true);
                 }
-            }
+                ui.add_space(5.0);
+
+                if ui.add(button(rich_text("Continue in read-only mode", 16.0))).clicked() {
+                    choice = Some(false);
+                }
+            });
         });
-   
+
+    choice
 }
 
 /// TxSettings popup
@@ -204,7 +269,9 @@ pub fn tx_settings_window(ui: &mut Ui, data: &mut AppData) {
             ui.vertical_centered(|ui| {
                 let priority_fee = rich_text("Priority Fee (Gwei)", 15.0);
                 let slippage_text = rich_text("Slippage", 15.0);
+                let auto_slippage = rich_text("Auto Slippage", 15.0);
                 let mev_protect = rich_text("MEV Protect", 15.0);
+                let gas_multiplier = rich_text("Gas Limit Multiplier", 15.0);
 
                 let fee_field =
                     TextEdit::singleline(&mut data.tx_settings.priority_fee).desired_width(15.0);
@@ -212,8 +279,13 @@ pub fn tx_settings_window(ui: &mut Ui, data: &mut AppData) {
                 let slippage_field =
                     TextEdit::singleline(&mut data.tx_settings.slippage).desired_width(15.0);
 
+                let auto_slippage_check = Checkbox::new(&mut data.tx_settings.auto_slippage, "");
+
                 let mev_protect_check = Checkbox::new(&mut data.tx_settings.mev_protect, "");
 
+                let gas_multiplier_field =
+                    TextEdit::singleline(&mut data.tx_settings.gas_multiplier).desired_width(25.0);
+
                 ui.horizontal(|ui| {
                     ui.label(priority_fee);
                     ui.add_space(5.0);
@@ -221,94 +293,53 @@ pub fn tx_settings_window(ui: &mut Ui, data: &mut AppData) {
                 });
                 ui.add_space(10.0);
 
+                ui.horizontal(|ui| {
+                    ui.label(auto_slippage);
+                    ui.add_space(5.0);
+                    ui.add(auto_slippage_check);
+                });
+                ui.add_space(10.0);
+
+                // the manual value is only used when auto slippage is off
+                let manual = !data.tx_settings.auto_slippage;
                 ui.horizontal(|ui| {
                     ui.label(slippage_text);
                     ui.add_space(5.0);
-                    ui.add(slippage_field);
+                    ui.add_enabled(manual, slippage_field);
                 });
                 ui.add_space(10.0);
 
+                // flashbots only exists on Ethereum, elsewhere the transaction goes to the public mempool
+                let mev_supported = data.chain_id.supports(ChainFeature::MevProtect);
                 ui.horizontal(|ui| {
                     ui.label(mev_protect);
                     ui.add_space(5.0);
-                    ui.add(mev_protect_check);
+                    ui.add_enabled(mev_supported, mev_protect_check)
+                        .on_disabled_hover_text(format!("Not available on {}", data.chain_id.name()));
+                });
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    ui.label(gas_multiplier);
+                    ui.add_space(5.0);
+                    ui.add(gas_multiplier_field).on_hover_text(format!(
+                        "The estimated gas is multiplied by this in case the state changes before the transaction lands, between 1 and {}",
+                        MAX_GAS_MULTIPLIER
+                    ));
                 });
                 ui.add_space(10.0);
 
                 if ui.button("Save").clicked() {
                     // TODO save the settings
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.tx_settings_on = false;
+                    SharedUiState::write().tx_settings_on = false;
                 }
             });
         });
 }
 
-/// Show an error message if needed
-/// 
-/// Depends on [SHARED_UI_STATE]
-/// 
-/// This should be called by the [eframe::App::update] method
-pub fn show_err_msg(ui: &mut Ui) {
-    let err_msg;
-    {
-        let state = SHARED_UI_STATE.read().unwrap();
-        err_msg = state.err_msg.msg.clone();
-        if state.err_msg.state.is_close() {
-            return;
-        }
-    }
-
-    Window::new("Error")
-        .resizable(false)
-        .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
-        .collapsible(false)
-        .title_bar(false)
-        .show(ui.ctx(), |ui| {
-            ui.vertical_centered(|ui| {
-                let msg_text = rich_text(&err_msg, 16.0);
-                let close_text = rich_text("Close", 16.0);
-
-                ui.label(msg_text);
-                ui.add_space(5.0);
-                if ui.button(close_text).clicked() {
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.err_msg.close();
-                }
-            });
-        });
-}
-
-// TODO: Auto close it after a few seconds
-/// Show an info message if needed
-pub fn info_msg(ui: &mut Ui) {
-    {
-        let state = SHARED_UI_STATE.read().unwrap();
-        if !state.info_msg.on {
-            return;
-        }
-    }
-
-    ui.vertical_centered_justified(|ui| {
-        frame().show(ui, |ui| {
-            ui.set_max_size(vec2(1000.0, 50.0));
-
-            let info_msg;
-            {
-                let state = SHARED_UI_STATE.read().unwrap();
-                info_msg = state.info_msg.msg.clone();
-            }
-            let msg_text = rich_text(&info_msg, 16.0);
-            let close_text = rich_text("Close", 16.0);
-
-            ui.label(msg_text);
-            ui.add_space(5.0);
-            if ui.button(close_text).clicked() {
-                let mut state = SHARED_UI_STATE.write().unwrap();
-                state.info_msg.on = false;
-            }
-        });
-    });
+/// Warn the user that `file` was corrupted and the previous version was loaded instead
+pub fn backup_restored_msg(file: &str) {
+    notify_notice(format!("Warning: {} could not be read, the last backup was restored", file));
 }
 
 /// Returns a [Frame] that is commonly used
@@ -344,6 +375,105 @@ pub fn rich_text(text: &str, size: f32) -> RichText {
         .color(Color32::WHITE)
 }
 
+/// Shorten a long string by keeping only its first `head` and last `tail` characters (eg. `0x1234…abcd`)
+///
+/// Strings that are already short enough are returned unchanged
+pub fn truncate_middle(s: &str, head: usize, tail: usize) -> String {
+    let len = s.chars().count();
+    if len <= head + tail + 1 {
+        return s.to_string();
+    }
+
+    let start: String = s.chars().take(head).collect();
+    let end: String = s.chars().skip(len - tail).collect();
+    format!("{}…{}", start, end)
+}
+
+/// Truncate an address to `0x1234…abcd`
+pub fn truncate_address(address: &str) -> String {
+    truncate_middle(address, 6, 4)
+}
+
+/// The label or known contract name of an address, its truncated form if it has none
+pub fn display_address(chain_id: u64, address: Address) -> String {
+    SHARED_CACHE
+        .read()
+        .unwrap()
+        .address_name(chain_id, address)
+        .unwrap_or_else(|| truncate_address(&address.to_string()))
+}
+
+/// Format a USD price from the block oracle (eg. `$3450.12`)
+///
+/// Shows `—` when we have no price, so it is never mistaken for `$0.00`
+pub fn format_usd_price(price: Option<U256>) -> String {
+    match price {
+        Some(price) => format!("${}", format_units_with_precision(price, ETH_USD_FEED_DECIMALS, 2)),
+        None => "—".to_string(),
+    }
+}
+
+/// Format a price with thousands separators (eg. `3,210.12`)
+///
+/// Prices below 1 keep more decimals so small prices don't show as `0.00`
+pub fn format_price(amount: U256, decimals: u8) -> String {
+    let one = U256::from(10).pow(U256::from(decimals));
+    let precision = if amount >= one { 2 } else { 6 };
+
+    let formatted = format_units_with_precision(amount, decimals, precision);
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    if fraction.is_empty() {
+        grouped
+    } else {
+        format!("{}.{}", grouped, fraction)
+    }
+}
+
+/// Show an amount with 4 decimals
+///
+/// Hovering shows the full amount and the raw integer, clicking copies the raw integer
+pub fn amount_label(ui: &mut Ui, value: U256, decimals: u8, symbol: &str) -> Response {
+    let text = format!("{} {}", format_units_with_precision(value, decimals, 4), symbol);
+    let res = ui
+        .add(Label::new(rich_text(text.trim_end(), 13.0)).sense(Sense::click()))
+        .on_hover_text(amount_tooltip(value, decimals, symbol));
+
+    if res.clicked() {
+        // not through address_check::copy_text, an amount is never what an address paste is checked against
+        ui.ctx().copy_text(value.to_string());
+    }
+    res
+}
+
+/// Show a balance with [amount_label], or pulsing dashes while it is still unknown
+///
+/// So a balance we did not receive yet is never mistaken for a zero balance
+pub fn balance_label(ui: &mut Ui, balance: Option<U256>, decimals: u8, symbol: &str) -> Response {
+    if let Some(balance) = balance {
+        return amount_label(ui, balance, decimals, symbol);
+    }
+
+    let pulse = (ui.input(|i| i.time) * 3.0).sin() as f32 * 0.5 + 0.5;
+    let color = Color32::GRAY.gamma_multiply(0.4 + 0.6 * pulse);
+    ui.ctx().request_repaint_after(Duration::from_millis(50));
+    ui.label(rich_text("– – –", 13.0).color(color)).on_hover_text("Loading the balance…")
+}
+
+/// The hover text of [amount_label]
+fn amount_tooltip(value: U256, decimals: u8, symbol: &str) -> String {
+    let amount = format!("{} {}", format_units(value, decimals), symbol);
+    format!("{}\nRaw: {}\nClick to copy the raw value", amount.trim_end(), value)
+}
+
 /// Returns a [TextEdit::singleline] that is commonly used
 pub fn text_edit_s(text: &mut String, width: f32, passwd: bool) -> TextEdit {
     let font = FontId::new(13.0, roboto_regular());
@@ -356,7 +486,92 @@ pub fn text_edit_s(text: &mut String, width: f32, passwd: bool) -> TextEdit {
         
 }
 
+/// Clean an amount field after it was edited and outline it in red while it is invalid
+///
+/// `res` is the response of the [TextEdit] of `input`, see [AmountInput::edited]
+pub fn amount_feedback(ui: &Ui, res: &Response, input: &mut AmountInput, decimals: u8) {
+    if res.changed() {
+        input.edited(decimals);
+    }
+    if input.shows_error() {
+        ui.painter().rect_stroke(res.rect.expand(2.0), Rounding::same(4.0), Stroke::new(1.0, Color32::RED));
+    }
+}
+
 /// Returns a [Button] that is commonly used
 pub fn button(text: RichText) -> Button<'static> {
     Button::new(text).rounding(10.0).sense(Sense::click())
 }
+
+/// `percent` of `balance` rounded down, split so `balance * percent` can't overflow
+pub fn percent_of(balance: U256, percent: u8) -> U256 {
+    let percent = U256::from(percent);
+    let hundred = U256::from(100);
+    balance / hundred * percent + balance % hundred * percent / hundred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amount_tooltip_shows_full_and_raw_value() {
+        let value = U256::from(1_234_567_890_123_456_789u64);
+        assert_eq!(
+            amount_tooltip(value, 18, "ETH"),
+            "1.234567890123456789 ETH\nRaw: 1234567890123456789\nClick to copy the raw value"
+        );
+    }
+
+    #[test]
+    fn percent_of_rounds_down() {
+        let balance = U256::from(1_000_000_000_000_000_001u64);
+        assert_eq!(percent_of(balance, 25), U256::from(250_000_000_000_000_000u64));
+        assert_eq!(percent_of(balance, 50), U256::from(500_000_000_000_000_000u64));
+        assert_eq!(percent_of(balance, 75), U256::from(750_000_000_000_000_000u64));
+        assert_eq!(percent_of(balance, 100), balance);
+        assert_eq!(percent_of(U256::from(3), 50), U256::from(1));
+        assert_eq!(percent_of(U256::ZERO, 75), U256::ZERO);
+    }
+
+    #[test]
+    fn percent_of_does_not_overflow() {
+        assert_eq!(percent_of(U256::MAX, 100), U256::MAX);
+        assert_eq!(percent_of(U256::MAX, 50), U256::MAX / U256::from(2));
+    }
+
+    #[test]
+    fn truncate_middle_keeps_strings_up_to_the_limit() {
+        // the ellipsis takes a character, so a string of head + tail + 1 gains nothing from it
+        assert_eq!(truncate_middle("abcdefghi", 4, 4), "abcdefghi");
+        assert_eq!(truncate_middle("abcdefgh", 4, 4), "abcdefgh");
+        assert_eq!(truncate_middle("abcdefghij", 4, 4), "abcd…ghij");
+        assert_eq!(truncate_middle("", 4, 4), "");
+    }
+
+    #[test]
+    fn truncate_middle_counts_characters_not_bytes() {
+        // 9 characters but 19 bytes, at the limit
+        assert_eq!(truncate_middle("ééééxéééé", 4, 4), "ééééxéééé");
+        assert_eq!(truncate_middle("日本語のトークン名です", 3, 2), "日本語…です");
+        assert_eq!(truncate_middle("🦀🦀🦀🦀🦀🦀", 2, 2), "🦀🦀…🦀🦀");
+    }
+
+    #[test]
+    fn truncate_address_keeps_the_prefix_and_the_last_four() {
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        assert_eq!(truncate_address(address), "0x1234…5678");
+    }
+
+    #[test]
+    fn amount_tooltip_without_symbol() {
+        assert_eq!(
+            amount_tooltip(U256::from(1_500_000u64), 6, ""),
+            "1.5\nRaw: 1500000\nClick to copy the raw value"
+        );
+        assert_eq!(
+            amount_tooltip(U256::ZERO, 18, ""),
+            "0\nRaw: 0\nClick to copy the raw value"
+        );
+    }
+}
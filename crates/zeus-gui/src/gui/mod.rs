@@ -3,10 +3,12 @@ use eframe::egui::{menu, Button, Color32, ComboBox, RichText, Ui, Sense, vec2};
 use crate::{fonts::roboto_regular, theme::ZeusTheme};
 use std::sync::Arc;
 
-use components::{*, send_crypto_screen::SendCryptoScreen, swap_ui::SwapUI, wallet::*};
+use components::{*, address_labels::AddressLabelsUI, data_dir::DataDirUI, diagnostics::DiagnosticsUI, dry_run::DryRunWindow, historical_balance::HistoricalBalanceUI, onboarding::OnboardingWizard, raw_tx::{BroadcastTxUI, RawTxUI}, send_crypto_screen::SendCryptoScreen, settings::SettingsUI, signed_tx::SignedTxWindow, swap_ui::SwapUI, toasts::Toasts, wallet::*, watches::WatchesUI};
 
 use zeus_backend::types::Request;
-use zeus_shared_types::{AppData, SHARED_UI_STATE, SWAP_UI_STATE};
+use zeus_chain::{defi_types::currency::Currency, format_percent_change};
+use misc::format_usd_price;
+use zeus_shared_types::{AppData, notify_error, t};
 
 use crossbeam::channel::Sender;
 
@@ -22,12 +24,36 @@ pub struct GUI {
 
     pub network_settings: NetworkSettings,
 
+    pub settings_ui: SettingsUI,
+
+    pub diagnostics_ui: DiagnosticsUI,
+
+    pub historical_balance_ui: HistoricalBalanceUI,
+
+    pub onboarding: OnboardingWizard,
+
+    pub raw_tx_ui: RawTxUI,
+
+    pub broadcast_tx_ui: BroadcastTxUI,
+
+    pub watches_ui: WatchesUI,
+
+    pub address_labels_ui: AddressLabelsUI,
+
+    pub data_dir_ui: DataDirUI,
+
     pub swap_ui: SwapUI,
 
     pub send_screen: SendCryptoScreen,
 
+    pub dry_run_window: DryRunWindow,
+
+    pub signed_tx_window: SignedTxWindow,
+
     pub wallet_ui: WalletUI,
 
+    pub toasts: Toasts,
+
     pub theme: Arc<ZeusTheme>,
 }
 
@@ -36,21 +62,42 @@ impl GUI {
         Self {
             sender: sender.clone(),
             token_selection_window: TokenSelectionWindow::new(sender.clone()),
-            network_settings: NetworkSettings::new(),
+            network_settings: NetworkSettings::new(sender.clone()),
+            settings_ui: SettingsUI::new(),
+            diagnostics_ui: DiagnosticsUI::new(),
+            historical_balance_ui: HistoricalBalanceUI::new(sender.clone()),
+            onboarding: OnboardingWizard::new(sender.clone()),
+            raw_tx_ui: RawTxUI::new(sender.clone()),
+            broadcast_tx_ui: BroadcastTxUI::new(sender.clone()),
+            watches_ui: WatchesUI::new(sender.clone()),
+            address_labels_ui: AddressLabelsUI::new(sender.clone()),
+            data_dir_ui: DataDirUI::new(),
             swap_ui: SwapUI::new(sender.clone()),
             send_screen: SendCryptoScreen::new(sender.clone()),
+            dry_run_window: DryRunWindow::new(),
+            signed_tx_window: SignedTxWindow::new(),
             wallet_ui: WalletUI::new(sender.clone()),
+            toasts: Toasts::new(),
             theme: Arc::new(ZeusTheme::default()),
         }
     }
 
+    /// Take the notifications sent since the last frame
+    ///
+    /// This should be called once per frame by the [eframe::App::update] method
+    pub fn poll_notifications(&mut self) {
+        if self.toasts.poll() {
+            self.swap_ui.on_error();
+            self.historical_balance_ui.on_error();
+        }
+    }
+
     /// Send a request to the backend
     pub fn send_request(&self, request: Request) {
             match self.sender.send(request) {
                 Ok(_) => {}
                 Err(e) => {
-                    let mut state = SHARED_UI_STATE.write().unwrap();
-                    state.err_msg.show(e);
+                    notify_error(e);
                 }
             }
         
@@ -62,14 +109,60 @@ impl GUI {
     pub fn side_panel_menu(&mut self, ui: &mut Ui, data: &mut AppData) {
         let swap = RichText::new("Swap").family(roboto_regular()).size(20.0);
 
-        let base_fee = RichText::new("Base Fee")
+        let block = RichText::new("Block")
+            .family(roboto_regular())
+            .size(15.0);
+
+        let base_fee = RichText::new("Next Base Fee")
+            .family(roboto_regular())
+            .size(15.0);
+
+        let native = Currency::new_native(data.chain_id.id());
+        let price = RichText::new(format!("{} Price", native.symbol()))
             .family(roboto_regular())
             .size(15.0);
 
         ui.vertical(|ui| {
+            ui.label(block);
+            ui.label(
+                RichText::new(format!("#{}", data.latest_block.number))
+                    .family(roboto_regular())
+                    .size(15.0),
+            );
+            ui.add_space(5.0);
+
             ui.label(base_fee);
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(&data.next_block.format_gwei())
+                        .family(roboto_regular())
+                        .size(15.0),
+                );
+
+                if let Some(change) = data.latest_block.base_fee_change(&data.next_block) {
+                    // a lower base fee is good for the user
+                    let color = if change > 0.0 {
+                        Color32::RED
+                    } else if change < 0.0 {
+                        Color32::GREEN
+                    } else {
+                        Color32::GRAY
+                    };
+
+                    ui.label(
+                        RichText::new(format_percent_change(change))
+                            .family(roboto_regular())
+                            .size(13.0)
+                            .color(color),
+                    )
+                    .on_hover_text("Change from the current block's base fee");
+                }
+            });
+            ui.add_space(5.0);
+
+            ui.label(price);
             ui.label(
-                RichText::new(&data.next_block.format_gwei())
+                RichText::new(format_usd_price(data.eth_price))
                     .family(roboto_regular())
                     .size(15.0),
             );
@@ -77,7 +170,10 @@ impl GUI {
 
             if ui.label(swap).clicked() {
                 self.swap_ui.state.open();
-            }           
+            }
+            ui.add_space(10.0);
+
+            self.watches_ui.show_list(ui, data);
         });
     }
 
@@ -85,15 +181,17 @@ impl GUI {
 
     /// Show the wallet UI
     /// 
+    /// Returns `true` if the user asked to refresh the balances from the node
+    ///
     /// This should be called by the [eframe::App::update] method
-    pub fn wallet_ui(&mut self, ui: &mut Ui, data: &mut AppData) {
+    pub fn wallet_ui(&mut self, ui: &mut Ui, data: &mut AppData) -> bool {
 
         if data.logged_in {
             self.wallet_ui.state.open();
         }
 
         // show the available wallets
-        self.wallet_ui.show(ui, data, self.theme.icons.clone());
+        let refresh = self.wallet_ui.show(ui, data, self.theme.icons.clone());
 
         // show the create new wallet ui
         self.wallet_ui.create_wallet_ui.show(ui, data);
@@ -104,7 +202,10 @@ impl GUI {
         // show the view key ui
         self.wallet_ui.view_key_ui.show(ui, data);
 
+        // show the larger balance history chart
+        self.wallet_ui.balance_history.show(ui, data);
 
+        refresh
     }
 
     /// Show Network Settings UI
@@ -114,11 +215,67 @@ impl GUI {
         self.network_settings.show(ui, data, self.theme.icons.clone());
     }
 
+    /// Show the General Settings UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_settings_ui(&mut self, ui: &mut Ui, data: &mut AppData) {
+        self.settings_ui.show(ui, data);
+    }
+
+    /// Show the Diagnostics UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_diagnostics_ui(&mut self, ui: &mut Ui) {
+        self.diagnostics_ui.show(ui);
+    }
+
+    /// Show the Balance at Block UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_historical_balance_ui(&mut self, ui: &mut Ui, data: &AppData) {
+        self.historical_balance_ui.show(ui, data);
+    }
+
+    /// Show the first-run setup wizard
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_onboarding(&mut self, ui: &mut Ui, data: &mut AppData) {
+        self.onboarding.show(ui, data, &mut self.wallet_ui);
+    }
+
+    /// Show the Sign Raw Transaction UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_raw_tx_ui(&mut self, ui: &mut Ui, data: &AppData) {
+        self.raw_tx_ui.show(ui, data);
+    }
+
+    /// Show the Broadcast Raw Transaction UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_broadcast_tx_ui(&mut self, ui: &mut Ui, data: &AppData) {
+        self.broadcast_tx_ui.show(ui, data);
+    }
+
+    /// Show the Address Labels UI
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_address_labels_ui(&mut self, ui: &mut Ui, data: &AppData) {
+        self.address_labels_ui.show(ui, data);
+    }
+
+    /// Show the New Price Watch UI for the pair selected in the swap panel
+    ///
+    /// This should be called by the [eframe::App::update] method
+    pub fn show_watches_ui(&mut self, ui: &mut Ui, data: &AppData) {
+        self.watches_ui.show(ui, data, &self.swap_ui.currency_in, &self.swap_ui.currency_out);
+    }
+
     /// Chain Selection
     /// 
     /// This should be called by the [eframe::App::update] method
     pub fn select_chain(&mut self, ui: &mut Ui, data: &mut AppData) {
-        let chain_ids = data.chain_ids.clone();
+        let chain_ids = data.visible_chains();
         ui.horizontal(|ui| {
             ui.add(self.theme.icons.chain_icon(&data.chain_id.id()));
 
@@ -134,9 +291,8 @@ impl GUI {
                             let req = Request::client(chain_id.clone(), data.rpc.clone());
                             self.send_request(req);
 
-                            let mut swap_ui_state = SWAP_UI_STATE.write().unwrap();
-                            swap_ui_state.default_input(chain_id.id());
-                            swap_ui_state.default_output(chain_id.id());
+                            self.swap_ui.default_input(chain_id.id());
+                            self.swap_ui.default_output(chain_id.id());
                         }
                     }
                 });
@@ -145,25 +301,77 @@ impl GUI {
                     .icons
                     .connected_icon(data.connected()),
             );
+            self.latency_indicator(ui, data);
         });
     }
 
+    /// A small dot colored by the average rpc latency
+    ///
+    /// Hovering over it shows the latency in ms
+    pub fn latency_indicator(&self, ui: &mut Ui, data: &AppData) {
+        let latency = match data.avg_latency() {
+            Some(latency) if data.connected() => latency,
+            _ => return,
+        };
+
+        let ms = latency.as_millis();
+        let color = match ms {
+            0..=150 => Color32::GREEN,
+            151..=500 => Color32::YELLOW,
+            _ => Color32::RED,
+        };
+
+        let (rect, res) = ui.allocate_exact_size(vec2(10.0, 10.0), Sense::hover());
+        ui.painter().circle_filled(rect.center(), 4.0, color);
+        res.on_hover_text(format!("Latency: {} ms", ms));
+    }
+
     /// Show the Settings Menu
     /// 
     /// This should be called by the [eframe::App::update] method
-    pub fn settings_menu(&mut self, ui: &mut Ui) {
+    pub fn settings_menu(&mut self, ui: &mut Ui, data: &mut AppData) {
+
+        let settings = RichText::new(t("menu.settings"))
+        .family(roboto_regular())
+        .size(14.0)
+        .color(Color32::WHITE);
+
+        let wallet_settings = RichText::new(t("menu.wallet_settings"))
+        .family(roboto_regular())
+        .size(14.0)
+        .color(Color32::WHITE);
+
+        let network_settings = RichText::new(t("menu.network_settings"))
+        .family(roboto_regular())
+        .size(14.0)
+        .color(Color32::WHITE);
 
-        let settings = RichText::new("Settings")
+        let general_settings = RichText::new(t("menu.general_settings"))
         .family(roboto_regular())
         .size(14.0)
         .color(Color32::WHITE);
 
-        let wallet_settings = RichText::new("Wallet Settings")
+        let diagnostics = RichText::new("Diagnostics")
         .family(roboto_regular())
         .size(14.0)
         .color(Color32::WHITE);
 
-        let network_settings = RichText::new("Network Settings")
+        let setup_wizard = RichText::new("Setup Wizard")
+        .family(roboto_regular())
+        .size(14.0)
+        .color(Color32::WHITE);
+
+        let restore_tokens = RichText::new("Restore Default Tokens")
+        .family(roboto_regular())
+        .size(14.0)
+        .color(Color32::WHITE);
+
+        let save_profile = RichText::new("Save Profile")
+        .family(roboto_regular())
+        .size(14.0)
+        .color(Color32::WHITE);
+
+        let advanced = RichText::new("Advanced")
         .family(roboto_regular())
         .size(14.0)
         .color(Color32::WHITE);
@@ -187,6 +395,16 @@ impl GUI {
                         ui.close_menu();
                         self.wallet_ui.view_key_ui.state.open();
                     }
+
+                    if ui.button("Balance at Block").clicked() {
+                        ui.close_menu();
+                        self.historical_balance_ui.state.open();
+                    }
+
+                    if ui.button("Address Labels").clicked() {
+                        ui.close_menu();
+                        self.address_labels_ui.state.open();
+                    }
                     // TODO: Rename and Hide Wallet
                 });
 
@@ -195,6 +413,47 @@ impl GUI {
                     ui.close_menu();
                     self.network_settings.state.open();
                 }
+
+                // General Settings
+                if ui.button(general_settings).clicked() {
+                    ui.close_menu();
+                    self.settings_ui.state.open();
+                }
+
+                // RPC usage per feature
+                if ui.button(diagnostics).clicked() {
+                    ui.close_menu();
+                    self.diagnostics_ui.state.open();
+                }
+
+                if ui.button(setup_wizard).clicked() {
+                    ui.close_menu();
+                    self.onboarding.open();
+                }
+
+                if ui.button(restore_tokens).clicked() {
+                    ui.close_menu();
+                    self.send_request(Request::restore_default_tokens(data.supported_networks()));
+                }
+
+                if ui.add_enabled(!data.read_only, Button::new(save_profile)).clicked() {
+                    ui.close_menu();
+                    if let Some(profile) = data.take_profile_save(true) {
+                        self.send_request(Request::save_profile(profile));
+                    }
+                }
+            });
+
+            ui.menu_button(advanced, |ui| {
+                if ui.button("Sign Raw Transaction").clicked() {
+                    ui.close_menu();
+                    self.raw_tx_ui.state.open();
+                }
+
+                if ui.button("Broadcast Raw Transaction").clicked() {
+                    ui.close_menu();
+                    self.broadcast_tx_ui.state.open();
+                }
             });
         });
     }
@@ -220,6 +479,8 @@ impl GUI {
         }
 
         self.send_screen.show(ui, data);
+        self.dry_run_window.show(ui);
+        self.signed_tx_window.show(ui);
 
     }
 }
@@ -8,7 +8,12 @@ use eframe::egui::{
 };
 
 use image::imageops::FilterType;
+use std::sync::{Arc, Mutex};
+use zeus_chain::defi_types::currency::Currency;
 
+pub use token_icons::{TokenIcons, MAX_TOKEN_ICONS};
+
+mod token_icons;
 
 /// A collection of icons used in the GUI
 #[derive(Clone)]
@@ -42,6 +47,9 @@ pub struct IconTextures {
     // Erc20 placeholder icons
     pub erc20: TextureHandle,
     pub bep20: TextureHandle,
+
+    /// The icons of the tokens, see [IconTextures::icon_for_currency]
+    pub token_icons: Arc<Mutex<TokenIcons>>,
 }
 
 impl IconTextures {
@@ -98,6 +106,7 @@ impl IconTextures {
             receive: ctx.load_texture("receive", receive, texture_options),
             erc20: ctx.load_texture("erc20", erc20, texture_options),
             bep20: ctx.load_texture("bep20", bep20, texture_options),
+            token_icons: Arc::new(Mutex::new(TokenIcons::default())),
         })
     }
 
@@ -184,6 +193,7 @@ impl IconTextures {
             56 => Image::new(&self.bsc),
             8453 => Image::new(&self.base),
             42161 => Image::new(&self.arbitrum),
+            84532 => Image::new(&self.base),
             _ => Image::new(&self.eth),
         }
     }
@@ -199,6 +209,21 @@ impl IconTextures {
         }
     }
 
+    /// Return the icon of any currency
+    ///
+    /// The icon of a token is uploaded the first time it is shown, tokens without one get the
+    /// native currency icon of their chain
+    pub fn icon_for_currency(&self, ctx: &Context, currency: &Currency) -> Image<'static> {
+        let texture = currency
+            .erc20()
+            .and_then(|token| self.token_icons.lock().unwrap().get(ctx, token));
+
+        match texture {
+            Some(texture) => Image::new(&texture),
+            None => self.currency_icon(currency.chain_id()),
+        }
+    }
+
     /// Return the tx settings icon as [ImageButton]
     pub fn tx_settings_icon(&self) -> ImageButton {
         ImageButton::new(&self.tx_settings).rounding(10.0)
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use eframe::egui::{epaint::textures::TextureOptions, Context, TextureHandle};
+use zeus_chain::{alloy::primitives::Address, defi_types::currency::erc20::ERC20Token};
+
+use super::load_image_from_memory;
+
+/// The most token icons we keep as textures, past it the least recently shown one is dropped
+pub const MAX_TOKEN_ICONS: usize = 128;
+
+/// The icons of the ERC20 tokens, uploaded as textures the first time they are shown
+#[derive(Default)]
+pub struct TokenIcons {
+    /// `Key:` (chain_id, token) -> `Value:` the texture (`None` if the icon could not be decoded) and when it was last shown
+    textures: HashMap<(u64, Address), (Option<TextureHandle>, u64)>,
+
+    /// Bumped on every lookup, orders the textures by use
+    clock: u64,
+}
+
+impl TokenIcons {
+    /// The texture of the icon of `token`, `None` if it has no icon or it could not be decoded
+    pub fn get(&mut self, ctx: &Context, token: &ERC20Token) -> Option<TextureHandle> {
+        self.clock += 1;
+        let key = (token.chain_id, token.address);
+
+        if let Some((texture, last_shown)) = self.textures.get_mut(&key) {
+            *last_shown = self.clock;
+            return texture.clone();
+        }
+
+        let icon = token.icon.as_ref()?;
+        let texture = load_image_from_memory(icon, 24, 24).ok().map(|image| {
+            let name = format!("token_{}_{}", token.chain_id, token.address);
+            ctx.load_texture(name, image, TextureOptions::default())
+        });
+
+        self.textures.insert(key, (texture.clone(), self.clock));
+        self.evict();
+        texture
+    }
+
+    /// Drop the least recently shown textures until we are back to [MAX_TOKEN_ICONS]
+    ///
+    /// The GPU memory is freed once the last handle of a texture is dropped
+    fn evict(&mut self) {
+        while self.textures.len() > MAX_TOKEN_ICONS {
+            let oldest = self
+                .textures
+                .iter()
+                .min_by_key(|(_, (_, last_shown))| *last_shown)
+                .map(|(key, _)| *key);
+
+            match oldest {
+                Some(key) => self.textures.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
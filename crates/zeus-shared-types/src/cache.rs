@@ -1,18 +1,31 @@
 use alloy_primitives::{Address, U256};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 
 use tracing::trace;
-use zeus_chain::Currency;
-use zeus_core::lazy_static::lazy_static;
+use zeus_chain::{utils::known_contract, Currency};
+use zeus_core::{lazy_static::lazy_static, Wallet};
 
 lazy_static! {
     pub static ref SHARED_CACHE: Arc<RwLock<SharedCache>> =
         Arc::new(RwLock::new(SharedCache::default()));
 }
 
+/// How much of the token list of a chain matching a query we loaded
+#[derive(Debug, Clone, Default)]
+pub struct CurrencyPages {
+    /// Tokens loaded so far, the offset of the next page
+    pub next_offset: usize,
+
+    /// A page was requested and has not arrived yet
+    pub pending: bool,
+
+    /// The last page arrived
+    pub complete: bool,
+}
+
 /// Cached data that can be safely shared across all tasks
 ///
 /// - `erc20_balance` - A map of all token balances for each chain
@@ -32,8 +45,41 @@ pub struct SharedCache {
     /// `Key:` (chain_id, owner) -> `Value:` (block, balance)
     pub eth_balance: HashMap<(u64, Address), (u64, U256)>,
 
-    /// A Map with all currencies for each chain
+    /// The currencies loaded so far for each chain
     pub currencies: HashMap<u64, Vec<Currency>>,
+
+    /// The pages of the token lists we loaded
+    ///
+    /// `Key:` (chain_id, query) -> `Value:` [CurrencyPages]
+    pub currency_pages: HashMap<(u64, String), CurrencyPages>,
+
+    /// The user labels of addresses
+    ///
+    /// `Key:` (chain_id, address) -> `Value:` label
+    pub address_labels: HashMap<(u64, Address), String>,
+
+    /// The addresses we checked for code during this session
+    ///
+    /// `Key:` (chain_id, address) -> `Value:` is a contract
+    pub contracts: HashMap<(u64, Address), bool>,
+
+    /// The contracts the backend found to be tokens
+    ///
+    /// `Key:` (chain_id, address) -> `Value:` the symbol of the token
+    pub token_contracts: HashMap<(u64, Address), String>,
+
+    /// (chain_id, address) of the tokens the user pinned
+    pub pinned_tokens: HashSet<(u64, Address)>,
+
+    /// The balances we received during this session, the native balance uses the zero address as token
+    ///
+    /// `Key:` (chain_id, owner, token)
+    pub fetched_balances: HashSet<(u64, Address, Address)>,
+
+    /// Spot prices of tokens against the wrapped native currency, with the decimals of the native currency
+    ///
+    /// `Key:` (chain_id, token) -> `Value:` how much of the native currency one whole token is worth
+    pub native_prices: HashMap<(u64, Address), U256>,
 }
 
 impl SharedCache {
@@ -56,10 +102,18 @@ impl SharedCache {
         balance: U256,
     ) {
         self.erc20_balance.insert((chain_id, owner, token), balance);
+        self.fetched_balances.insert((chain_id, owner, token));
 
         trace!("Updated ERC20 Balance: {:?}", self.erc20_balance);
     }
 
+    /// Update the balances of several tokens of `owner`, eg. a batch the backend sent together
+    pub fn update_erc20_balances(&mut self, chain_id: u64, owner: Address, balances: &[(Address, U256)]) {
+        for (token, balance) in balances {
+            self.update_erc20_balance(chain_id, owner, *token, *balance);
+        }
+    }
+
     /// Get eth balance of a wallet for a specific chain
     pub fn get_eth_balance(&self, chain_id: u64, owner: Address) -> (u64, U256) {
         if let Some(balance) = self.eth_balance.get(&(chain_id, owner)) {
@@ -69,18 +123,142 @@ impl SharedCache {
         }
     }
 
+    /// Get the balance of any [Currency], native or ERC20
+    pub fn balance_of(&self, chain_id: u64, owner: Address, currency: &Currency) -> U256 {
+        match currency {
+            Currency::Native(_) => self.get_eth_balance(chain_id, owner).1,
+            Currency::ERC20(token) => self.get_erc20_balance(&chain_id, &owner, &token.address),
+        }
+    }
+
     /// Update eth balance of a wallet for a specific chain
     pub fn update_eth_balance(&mut self, chain_id: u64, owner: Address, block: u64, balance: U256) {
         self.eth_balance
             .insert((chain_id, owner), (block, balance));
+        self.fetched_balances.insert((chain_id, owner, Address::ZERO));
+    }
+
+    /// Copy the cached eth balances of `wallet` into its own balance map
+    ///
+    /// The cache is the source of truth, chains the cache knows nothing about are left as they are
+    pub fn sync_wallet(&self, wallet: &mut Wallet) {
+        let owner = wallet.key.address();
+        for ((chain_id, address), (block, balance)) in &self.eth_balance {
+            if *address == owner {
+                wallet.set_balance(*chain_id, *block, *balance);
+            }
+        }
+    }
+
+    /// The balance of any [Currency] if we received it during this session
+    ///
+    /// `None` while it is unknown, a balance loaded from the database may be outdated
+    pub fn fetched_balance_of(&self, chain_id: u64, owner: Address, currency: &Currency) -> Option<U256> {
+        let token = currency.erc20().map_or(Address::ZERO, |token| token.address);
+        self.fetched_balances
+            .contains(&(chain_id, owner, token))
+            .then(|| self.balance_of(chain_id, owner, currency))
+    }
+
+    /// Replace the cached balances with the ones loaded from the database
+    ///
+    /// Any balance that was already fetched during this session is kept since it is newer.
+    /// The token lists are loaded again page by page and merged by address with the currencies
+    /// we already have, so a token added before the load completed is not lost
+    pub fn apply_loaded(
+        &mut self,
+        erc20_balance: HashMap<(u64, Address, Address), U256>,
+        eth_balance: HashMap<(u64, Address), (u64, U256)>,
+        pinned_tokens: HashSet<(u64, Address)>,
+    ) {
+        self.currency_pages.clear();
+        self.pinned_tokens = pinned_tokens;
+
+        for (key, balance) in erc20_balance {
+            self.erc20_balance.entry(key).or_insert(balance);
+        }
+
+        for (key, balance) in eth_balance {
+            self.eth_balance.entry(key).or_insert(balance);
+        }
+    }
+
+    /// The cached ERC20 currency of `address` on `chain_id`
+    pub fn get_currency(&self, chain_id: u64, address: Address) -> Option<Currency> {
+        self.currencies
+            .get(&chain_id)?
+            .iter()
+            .find(|currency| currency.erc20().is_some_and(|token| token.address == address))
+            .cloned()
+    }
+
+    /// Is the ERC20 `currency` pinned on `chain_id`, the native currency never is
+    pub fn is_pinned(&self, chain_id: u64, currency: &Currency) -> bool {
+        currency
+            .erc20()
+            .is_some_and(|token| self.pinned_tokens.contains(&(chain_id, token.address)))
     }
 
-    /// Add a currency
+    /// The name we show instead of an address, the user label wins over a [known_contract]
+    pub fn address_name(&self, chain_id: u64, address: Address) -> Option<String> {
+        self.address_labels
+            .get(&(chain_id, address))
+            .cloned()
+            .or_else(|| known_contract(chain_id, address).map(str::to_string))
+    }
+
+    /// The symbol of the token deployed at `address`, from the tokens we loaded or the contract checks
+    pub fn token_contract(&self, chain_id: u64, address: Address) -> Option<String> {
+        if let Some(symbol) = self.token_contracts.get(&(chain_id, address)) {
+            return Some(symbol.clone());
+        }
+        self.currencies
+            .get(&chain_id)?
+            .iter()
+            .filter_map(Currency::erc20)
+            .find(|token| token.address == address)
+            .map(|token| token.symbol.clone())
+    }
+
+    /// The offset of the next page of the token list of `chain_id` matching `query`, marks it as pending
+    ///
+    /// `None` while a page is loading or once the list is complete.
+    /// Unless `more` is set only the first page is returned
+    pub fn next_currency_page(&mut self, chain_id: u64, query: &str, more: bool) -> Option<usize> {
+        let pages = self.currency_pages.entry((chain_id, query.to_string())).or_default();
+        let loaded = pages.next_offset > 0 || pages.complete;
+        if pages.pending || pages.complete || (loaded && !more) {
+            return None;
+        }
+        pages.pending = true;
+        Some(pages.next_offset)
+    }
+
+    /// Add a page of the token list, see [SharedCache::next_currency_page]
+    pub fn add_currency_page(&mut self, chain_id: u64, query: &str, offset: usize, currencies: Vec<Currency>, more: bool) {
+        let tokens = currencies.iter().filter(|currency| currency.erc20().is_some()).count();
+        for currency in currencies {
+            self.add_currency(chain_id, currency);
+        }
+
+        let pages = self.currency_pages.entry((chain_id, query.to_string())).or_default();
+        pages.next_offset = offset + tokens;
+        pages.pending = false;
+        pages.complete = !more;
+    }
+
+    /// Add a currency, a currency that is already cached is replaced, eg. when its decimals changed
     pub fn add_currency(&mut self, chain_id: u64, currency: Currency) {
-        if let Some(currencies) = self.currencies.get_mut(&chain_id) {
-            currencies.push(currency);
-        } else {
-            self.currencies.insert(chain_id, vec![currency]);
+        let currencies = self.currencies.entry(chain_id).or_default();
+        let cached = currencies.iter_mut().find(|cached| match (cached.erc20(), currency.erc20()) {
+            (Some(cached), Some(token)) => cached.address == token.address,
+            (None, None) => true,
+            _ => false,
+        });
+
+        match cached {
+            Some(cached) => *cached = currency,
+            None => currencies.push(currency),
         }
     }
 }
@@ -92,6 +270,113 @@ impl Default for SharedCache {
             erc20_balance: HashMap::new(),
             eth_balance: HashMap::new(),
             currencies: HashMap::new(),
+            currency_pages: HashMap::new(),
+            address_labels: HashMap::new(),
+            contracts: HashMap::new(),
+            token_contracts: HashMap::new(),
+            pinned_tokens: HashSet::new(),
+            fetched_balances: HashSet::new(),
+            native_prices: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeus_chain::ERC20Token;
+    use zeus_core::WalletBalance;
+
+    #[test]
+    fn updating_the_cache_reflects_in_the_wallet_balance() {
+        let mut cache = SharedCache::default();
+        let mut wallet = Wallet::new_rng("test".to_string());
+        let owner = wallet.key.address();
+
+        cache.update_eth_balance(1, owner, 10, U256::from(5));
+        cache.sync_wallet(&mut wallet);
+        assert_eq!(wallet.get_balance(1), WalletBalance { balance: U256::from(5), block: 10 });
+
+        cache.update_eth_balance(1, owner, 11, U256::from(7));
+        cache.sync_wallet(&mut wallet);
+        assert_eq!(wallet.get_balance(1), WalletBalance { balance: U256::from(7), block: 11 });
+        assert_eq!(wallet.get_balance(8453), WalletBalance::default());
+    }
+
+    #[test]
+    fn a_balance_batch_writes_every_entry() {
+        let mut cache = SharedCache::default();
+        let owner = Address::repeat_byte(9);
+        let balances: Vec<(Address, U256)> = (1..=3).map(|byte| (Address::repeat_byte(byte), U256::from(byte))).collect();
+
+        cache.update_erc20_balances(1, owner, &balances);
+
+        for (token, balance) in &balances {
+            assert_eq!(cache.get_erc20_balance(&1, &owner, token), *balance);
+            assert!(cache.fetched_balances.contains(&(1, owner, *token)));
         }
+        assert_eq!(cache.erc20_balance.len(), 3);
+    }
+
+    #[test]
+    fn token_contracts_are_found_in_the_loaded_tokens_and_the_checks() {
+        let mut cache = SharedCache::default();
+        let loaded = Address::repeat_byte(1);
+        let checked = Address::repeat_byte(2);
+
+        cache.add_currency(1, Currency::new_native(1));
+        cache.add_currency(1, Currency::new_erc20(ERC20Token { address: loaded, symbol: "USDC".to_string(), ..ERC20Token::default() }));
+        cache.token_contracts.insert((1, checked), "DAI".to_string());
+
+        assert_eq!(cache.token_contract(1, loaded).as_deref(), Some("USDC"));
+        assert_eq!(cache.token_contract(1, checked).as_deref(), Some("DAI"));
+        assert_eq!(cache.token_contract(8453, loaded), None);
+        assert_eq!(cache.token_contract(1, Address::repeat_byte(3)), None);
+    }
+
+    #[test]
+    fn balance_of_reads_the_native_and_the_token_balance() {
+        let mut cache = SharedCache::default();
+        let owner = Address::repeat_byte(9);
+        let token = Currency::new_erc20(ERC20Token { chain_id: 1, address: Address::repeat_byte(1), ..ERC20Token::default() });
+        let eth = Currency::new_native(1);
+
+        cache.update_eth_balance(1, owner, 10, U256::from(5));
+        cache.update_erc20_balance(1, owner, Address::repeat_byte(1), U256::from(7));
+
+        assert_eq!(cache.balance_of(1, owner, &eth), U256::from(5));
+        assert_eq!(cache.balance_of(1, owner, &token), U256::from(7));
+
+        // unknown balances are zero, on another chain or of another owner
+        assert_eq!(cache.balance_of(8453, owner, &eth), U256::ZERO);
+        assert_eq!(cache.balance_of(8453, owner, &token), U256::ZERO);
+        assert_eq!(cache.balance_of(1, Address::repeat_byte(8), &token), U256::ZERO);
+    }
+
+    #[test]
+    fn currencies_added_before_the_load_are_kept() {
+        let mut cache = SharedCache::default();
+        let token = |byte: u8, decimals: u8| Currency::new_erc20(ERC20Token { address: Address::repeat_byte(byte), decimals, ..ERC20Token::default() });
+
+        cache.add_currency(1, token(1, 18));
+        cache.add_currency_page(1, "", 0, vec![token(2, 18)], false);
+        cache.apply_loaded(HashMap::new(), HashMap::new(), HashSet::new());
+        assert_eq!(cache.currencies[&1], [token(1, 18), token(2, 18)]);
+
+        // the token list is loaded again and merged by address
+        assert_eq!(cache.next_currency_page(1, "", false), Some(0));
+        cache.add_currency_page(1, "", 0, vec![token(2, 6), token(3, 18)], false);
+        assert_eq!(cache.currencies[&1], [token(1, 18), token(2, 6), token(3, 18)]);
+    }
+
+    #[test]
+    fn sync_wallet_ignores_other_owners() {
+        let mut cache = SharedCache::default();
+        let mut wallet = Wallet::new_rng("test".to_string());
+        wallet.set_balance(1, 3, U256::from(2));
+
+        cache.update_eth_balance(1, Address::repeat_byte(1), 10, U256::from(5));
+        cache.sync_wallet(&mut wallet);
+        assert_eq!(wallet.get_balance(1), WalletBalance { balance: U256::from(2), block: 3 });
     }
 }
@@ -0,0 +1,190 @@
+//! The text shown to the user in each [Language]
+//!
+//! Every language is a key -> text map embedded from the JSON file next to this module.
+//! A key missing from the current language falls back to English, a key English lacks shows the key itself.
+//! Placeholders are written `{name}` and filled with [tr]
+
+use std::{collections::HashMap, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+use zeus_chain::serde_json;
+use zeus_core::lazy_static::lazy_static;
+
+/// The languages the UI can be shown in, see [crate::Settings::language]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    /// The name of the language in itself, so it can be found without reading the current one
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::Spanish => "Español",
+        }
+    }
+
+    fn catalog_json(&self) -> &'static str {
+        match self {
+            Self::English => include_str!("en.json"),
+            Self::Spanish => include_str!("es.json"),
+        }
+    }
+}
+
+type Catalog = HashMap<String, String>;
+
+lazy_static! {
+    static ref CATALOGS: HashMap<Language, Catalog> = Language::ALL
+        .into_iter()
+        .map(|language| {
+            let catalog = serde_json::from_str(language.catalog_json()).expect("embedded catalogs are valid JSON");
+            (language, catalog)
+        })
+        .collect();
+
+    static ref LANGUAGE: RwLock<Language> = RwLock::new(Language::default());
+}
+
+/// Show the UI in `language` from now on
+pub fn set_language(language: Language) {
+    *LANGUAGE.write().unwrap() = language;
+}
+
+/// The language the UI is shown in
+pub fn language() -> Language {
+    *LANGUAGE.read().unwrap()
+}
+
+/// The text of `key` in the current language
+pub fn t(key: &str) -> String {
+    lookup(language(), key)
+}
+
+/// [t] with every `{name}` placeholder replaced by its value in `params`
+pub fn tr(key: &str, params: &[(&str, &str)]) -> String {
+    fill(lookup(language(), key), params)
+}
+
+fn lookup(language: Language, key: &str) -> String {
+    CATALOGS
+        .get(&language)
+        .and_then(|catalog| catalog.get(key))
+        .or_else(|| CATALOGS.get(&Language::English).and_then(|catalog| catalog.get(key)))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn fill(mut text: String, params: &[(&str, &str)]) -> String {
+    for (name, value) in params {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+/// Text for the user that is only translated when it is shown
+///
+/// The backend sends these with the notifications, so its messages follow the language of the UI
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiText {
+    /// Shown as is, eg. an error we got from the node
+    Raw(String),
+
+    /// A catalog key and the values of its placeholders
+    Key {
+        key: &'static str,
+        params: Vec<(&'static str, String)>,
+    },
+}
+
+impl UiText {
+    pub fn key(key: &'static str) -> Self {
+        Self::Key { key, params: Vec::new() }
+    }
+
+    /// Fill the `{name}` placeholder with `value`
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        if let Self::Key { params, .. } = &mut self {
+            params.push((name, value.to_string()));
+        }
+        self
+    }
+
+    /// The text in the current language
+    pub fn translate(&self) -> String {
+        self.translate_to(language())
+    }
+
+    /// The text in `language`, eg. English for the logs
+    pub fn translate_to(&self, language: Language) -> String {
+        match self {
+            Self::Raw(text) => text.clone(),
+            Self::Key { key, params } => {
+                let params: Vec<(&str, &str)> = params.iter().map(|(name, value)| (*name, value.as_str())).collect();
+                fill(lookup(language, key), &params)
+            }
+        }
+    }
+}
+
+impl From<String> for UiText {
+    fn from(text: String) -> Self {
+        Self::Raw(text)
+    }
+}
+
+impl From<&str> for UiText {
+    fn from(text: &str) -> Self {
+        Self::Raw(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `{name}` placeholders of a text, sorted
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split('{').skip(1).filter_map(|part| part.split_once('}')).map(|(name, _)| name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn every_translation_has_an_english_original_with_the_same_placeholders() {
+        let english = &CATALOGS[&Language::English];
+        for language in Language::ALL {
+            for (key, text) in &CATALOGS[&language] {
+                let original = english.get(key).unwrap_or_else(|| panic!("{:?} has {} but English does not", language, key));
+                assert_eq!(placeholders(text), placeholders(original), "{:?} {}", language, key);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_english_then_to_the_key() {
+        assert_eq!(lookup(Language::Spanish, "common.close"), "Cerrar");
+        assert_eq!(lookup(Language::English, "common.close"), "Close");
+        assert_eq!(lookup(Language::Spanish, "no.such.key"), "no.such.key");
+
+        let english_only = CATALOGS[&Language::English]
+            .keys()
+            .find(|key| !CATALOGS[&Language::Spanish].contains_key(*key));
+        if let Some(key) = english_only {
+            assert_eq!(lookup(Language::Spanish, key), lookup(Language::English, key));
+        }
+    }
+
+    #[test]
+    fn placeholders_are_filled() {
+        let text = UiText::key("error.quote").with("error", "no pool");
+        assert_eq!(text.translate_to(Language::English), "Error getting the quote: no pool");
+        assert_eq!(text.translate_to(Language::Spanish), "Error al obtener la cotización: no pool");
+        assert_eq!(UiText::from("as is").translate_to(Language::Spanish), "as is");
+    }
+}
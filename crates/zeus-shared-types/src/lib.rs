@@ -1,12 +1,18 @@
 pub mod state;
 pub mod cache;
+pub mod i18n;
 
 pub use state::{
-    data::{ AppData, NETWORKS, TxSettings },
-    swap_ui::{ SWAP_UI_STATE, SelectedCurrency, SwapUIState },
+    data::{ AppData, AppliedSlippage, ProfileSave, MAX_GAS_MULTIPLIER, PROFILE_SAVE_DELAY, TxSettings },
+    settings::{PickSide, Settings, MIN_BALANCE_REFRESH_SECS},
+    bundle::{SettingsBundle, SETTINGS_BUNDLE_VERSION},
+    swap_ui::{ AmountInput, PoolQuote, QuoteResult, QuoteRoute, RouteHop, SelectedCurrency, bps_text, fee_text, percent_to_bps, share_to_bps },
     shared_ui::SHARED_UI_STATE,
     SharedUiState,
     error::ErrorMsg,
     info::InfoMsg,
+    notification::{notify, notify_error, notify_error_text, notify_info, notify_info_text, notify_notice, take_notifications, UiNotification},
     UiState
 };
+
+pub use i18n::{set_language, t, tr, Language, UiText};
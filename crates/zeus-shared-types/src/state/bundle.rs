@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use zeus_chain::{serde_json, ChainSpecs, Rpc};
+use zeus_core::anyhow::{self, anyhow};
+
+use super::settings::Settings;
+
+/// The bundle layout we write, bumped when a field changes meaning
+///
+/// Bundles from a newer version are rejected, older ones are read with the defaults for new fields
+pub const SETTINGS_BUNDLE_VERSION: u32 = 1;
+
+/// Every preference file in one JSON, to move them to another install
+///
+/// Holds `settings.json`, `rpc.json` and `chains.json`, never the profile or any key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub version: u32,
+    pub settings: Settings,
+    pub rpc: Vec<Rpc>,
+
+    #[serde(default)]
+    pub chain_specs: ChainSpecs,
+}
+
+impl SettingsBundle {
+    pub fn new(settings: Settings, rpc: Vec<Rpc>, chain_specs: ChainSpecs) -> Self {
+        Self {
+            version: SETTINGS_BUNDLE_VERSION,
+            settings,
+            rpc,
+            chain_specs,
+        }
+    }
+
+    /// Read a bundle and check it is one we understand
+    pub fn parse(json: &str) -> Result<Self, anyhow::Error> {
+        let version = serde_json::from_str::<serde_json::Value>(json)
+            .map_err(|e| anyhow!("Not a settings file: {}", e))?
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or(anyhow!("Not a settings file, it has no version"))?;
+
+        if version == 0 || version > SETTINGS_BUNDLE_VERSION as u64 {
+            return Err(anyhow!(
+                "This settings file is version {}, this Zeus reads up to version {}",
+                version,
+                SETTINGS_BUNDLE_VERSION
+            ));
+        }
+
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid settings file: {}", e))
+    }
+
+    /// The imported preferences win, what only this install knows is kept
+    ///
+    /// - Stablecoins are added to the ones marked here
+    /// - Routers and spec overrides are replaced per chain, the other chains keep theirs
+    /// - The recent picks of this install are kept
+    /// - An empty rpc url does not replace a configured one
+    pub fn merge(self, settings: &Settings, rpc: &[Rpc], chain_specs: &ChainSpecs) -> (Settings, Vec<Rpc>, ChainSpecs) {
+        let mut merged = self.settings;
+        for (chain_id, tokens) in &settings.stablecoins {
+            let merged_tokens = merged.stablecoins.entry(*chain_id).or_default();
+            for token in tokens {
+                if !merged_tokens.contains(token) {
+                    merged_tokens.push(*token);
+                }
+            }
+        }
+        for (chain_id, router) in &settings.swap_routers {
+            merged.swap_routers.entry(*chain_id).or_insert(*router);
+        }
+        merged.recent_picks.extend(settings.recent_picks.clone());
+        merged.onboarding_done |= settings.onboarding_done;
+
+        let mut merged_rpc = rpc.to_vec();
+        for imported in self.rpc.into_iter().filter(|rpc| !rpc.is_url_empty()) {
+            match merged_rpc.iter_mut().find(|rpc| rpc.chain_id == imported.chain_id) {
+                Some(rpc) => rpc.url = imported.url,
+                None => merged_rpc.push(imported),
+            }
+        }
+
+        let mut merged_specs = chain_specs.clone();
+        merged_specs.overrides.extend(self.chain_specs.overrides);
+
+        (merged, merged_rpc, merged_specs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeus_chain::{alloy::primitives::Address, revm::primitives::SpecId, SwapRouter};
+
+    #[test]
+    fn bundles_from_a_newer_version_are_rejected() {
+        let bundle = SettingsBundle::new(Settings::default(), Vec::new(), ChainSpecs::default());
+        let mut json = serde_json::to_value(&bundle).unwrap();
+        assert!(SettingsBundle::parse(&json.to_string()).is_ok());
+
+        json["version"] = (SETTINGS_BUNDLE_VERSION + 1).into();
+        let err = SettingsBundle::parse(&json.to_string()).unwrap_err();
+        assert!(err.to_string().contains("version"));
+
+        assert!(SettingsBundle::parse(r#"{"settings": {}, "rpc": []}"#).is_err());
+        assert!(SettingsBundle::parse("not json").is_err());
+    }
+
+    #[test]
+    fn import_merges_with_what_is_here() {
+        let token = |byte| Address::repeat_byte(byte);
+
+        let mut local = Settings::default();
+        local.set_stablecoin(1, token(1), true);
+        local.swap_routers.insert(8453, SwapRouter::Zeus(token(2)));
+        local.onboarding_done = true;
+        let local_rpc = vec![Rpc::new("http://local-eth".to_string(), 1), Rpc::new("http://local-base".to_string(), 8453)];
+        let mut local_specs = ChainSpecs::default();
+        local_specs.overrides.insert(8453, SpecId::SHANGHAI);
+
+        let mut imported = Settings {
+            dry_run: true,
+            ..Settings::default()
+        };
+        imported.set_stablecoin(1, token(3), true);
+        imported.swap_routers.insert(1, SwapRouter::Zeus(token(4)));
+        let imported_rpc = vec![Rpc::new("http://imported-eth".to_string(), 1), Rpc::new(String::new(), 8453)];
+        let mut imported_specs = ChainSpecs::default();
+        imported_specs.overrides.insert(1, SpecId::SHANGHAI);
+
+        let bundle = SettingsBundle::new(imported, imported_rpc, imported_specs);
+        let (settings, rpc, specs) = bundle.merge(&local, &local_rpc, &local_specs);
+
+        assert!(settings.dry_run);
+        assert!(settings.onboarding_done);
+        assert!(settings.is_user_stablecoin(1, token(1)) && settings.is_user_stablecoin(1, token(3)));
+        assert_eq!(settings.swap_router(1), SwapRouter::Zeus(token(4)));
+        assert_eq!(settings.swap_router(8453), SwapRouter::Zeus(token(2)));
+
+        assert_eq!(rpc[0].url, "http://imported-eth");
+        assert_eq!(rpc[1].url, "http://local-base");
+
+        assert_eq!(specs.overrides.get(&1), Some(&SpecId::SHANGHAI));
+        assert_eq!(specs.overrides.get(&8453), Some(&SpecId::SHANGHAI));
+    }
+}
@@ -1,19 +1,40 @@
 use std::{ path::Path, str::FromStr };
 use std::sync::{Arc, RwLock};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use zeus_core::{anyhow, Profile};
-use zeus_chain::{alloy::primitives::{U256, Address}, ChainId, Rpc, BlockInfo, WsClient, serde_json};
-use crate::cache::{SHARED_CACHE, SharedCache};
+use zeus_core::{anyhow, atomic_write, read_with_backup, Credentials, Profile, UnlockCredentials, CHAINS_FILE, RPC_FILE, SETTINGS_FILE};
+use zeus_chain::{alloy::primitives::{U256, Address}, defi_types::currency::{stablecoin::PairClass, Currency, NativeCurrency}, evm_types::simulation::get_native_coin, format_units, ChainId, ChainSpecs, Rpc, BlockInfo, WsClient, CHAIN_SPECS, DEFAULT_GAS_MULTIPLIER, ETH_USD_FEED_DECIMALS, serde_json};
+use crate::{cache::{SHARED_CACHE, SharedCache}, i18n::set_language};
+use super::{bundle::SettingsBundle, settings::Settings};
 use tracing::trace;
 
-/// Supported networks
-pub const NETWORKS: [ChainId; 4] = [
-    ChainId::Ethereum(1),
-    ChainId::BinanceSmartChain(56),
-    ChainId::Base(8453),
-    ChainId::Arbitrum(42161),
-];
+/// How many latency samples we keep for the rolling average
+pub const LATENCY_SAMPLES: usize = 10;
+
+/// How long after the last change to the profile we save it
+///
+/// Every save re-derives the Argon2 key, so a burst of changes is written once
+pub const PROFILE_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// The highest gas multiplier [TxSettings::parse_gas_multiplier] allows
+pub const MAX_GAS_MULTIPLIER: f64 = 3.0;
+
+/// Where the profile is between a change and the file on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProfileSave {
+    #[default]
+    Saved,
+
+    /// Changed at this time and not saved yet
+    Dirty(Instant),
+
+    /// Sent to the backend, waiting for [ProfileSave::Saved]
+    Saving,
+
+    /// The last save failed, the changes are only in memory
+    Failed,
+}
 
 
 /// Transaction settings
@@ -22,6 +43,21 @@ pub struct TxSettings {
     pub priority_fee: String,
     pub slippage: String,
     pub mev_protect: bool,
+
+    /// Pick the slippage from the [PairClass] of the swap instead of `slippage`
+    pub auto_slippage: bool,
+
+    /// The estimated gas of a transaction is multiplied by this to get its gas limit
+    pub gas_multiplier: String,
+}
+
+/// The slippage used for a swap and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedSlippage {
+    /// In %
+    pub value: f32,
+
+    pub reason: String,
 }
 
 impl TxSettings {
@@ -35,6 +71,29 @@ impl TxSettings {
     pub fn parse_slippage(&self) -> f32 {
         self.slippage.parse().unwrap_or(0.5)
     }
+
+    /// The gas multiplier between 1 and [MAX_GAS_MULTIPLIER], the default if it doesn't parse
+    pub fn parse_gas_multiplier(&self) -> f64 {
+        match self.gas_multiplier.trim().parse::<f64>() {
+            Ok(multiplier) if multiplier.is_finite() => multiplier.clamp(1.0, MAX_GAS_MULTIPLIER),
+            _ => DEFAULT_GAS_MULTIPLIER,
+        }
+    }
+
+    /// The slippage for a swap of `class`, see [TxSettings::auto_slippage]
+    pub fn slippage_for(&self, class: PairClass) -> AppliedSlippage {
+        if self.auto_slippage {
+            AppliedSlippage {
+                value: class.default_slippage(),
+                reason: format!("Auto, {}", class.reason()),
+            }
+        } else {
+            AppliedSlippage {
+                value: self.parse_slippage(),
+                reason: "Set manually".to_string(),
+            }
+        }
+    }
 }
 
 impl Default for TxSettings {
@@ -43,6 +102,8 @@ impl Default for TxSettings {
             priority_fee: String::from("3"),
             slippage: String::from("0.5"),
             mev_protect: true,
+            auto_slippage: true,
+            gas_multiplier: DEFAULT_GAS_MULTIPLIER.to_string(),
         }
     }
 }
@@ -54,39 +115,65 @@ pub struct AppData {
 
     pub next_block: BlockInfo,
 
+    /// USD price of the native currency, see [zeus_chain::BlockOracle::eth_price]
+    pub eth_price: Option<U256>,
+
     /// The current client
     pub client: Option<Arc<WsClient>>,
 
+    /// The chain of [AppData::client], we switch back to it if connecting to another chain fails
+    pub connected_chain: Option<ChainId>,
+
     /// Are we connected to the client?
     pub connected: bool,
 
+    /// The latest round-trip times of the rpc requests
+    pub latency: VecDeque<Duration>,
+
     /// The current selected chain id
     pub chain_id: ChainId,
 
-    /// All supported ChainIds
-    pub chain_ids: Vec<ChainId>,
-
     /// The current saved RPC endpoints
     pub rpc: Vec<Rpc>,
 
     /// The current profile
     pub profile: Profile,
 
+    /// What the user typed on the new profile screen
+    ///
+    /// Cleared as soon as the profile key is derived from them
+    pub credentials: Credentials,
+
+    /// What the user typed on the login screen, cleared once the profile is unlocked
+    pub unlock_credentials: UnlockCredentials,
+
     pub shared_cache: Arc<RwLock<SharedCache>>,
 
     /// Tx settings
     pub tx_settings: TxSettings,
 
+    /// User preferences
+    pub settings: Settings,
+
     /// Are we logged in?
     pub logged_in: bool,
 
     /// New profile screen on/off
     pub new_profile_screen: bool,
 
+    /// Another instance of Zeus is running or the data directory is read-only, nothing is written to disk
+    pub read_only: bool,
+
+    /// The data directory is not writable, see [zeus_core::is_writable]
+    pub data_dir_read_only: bool,
+
     /// Does a profile already exists?
     ///
     /// We lookup for a `profile.data` file in the current directory of the executable
     pub profile_exists: bool,
+
+    /// Save state of [AppData::profile], see [AppData::mark_profile_dirty]
+    pub profile_save: ProfileSave,
 }
 
 impl AppData {
@@ -95,18 +182,79 @@ impl AppData {
         &self.client
     }
 
+    /// The ids of every chain we support, see [ChainId::all]
     pub fn supported_networks(&self) -> Vec<u64> {
-        self.chain_ids
+        ChainId::all().iter().map(|chain_id| chain_id.id()).collect()
+    }
+
+    /// The chains we list in the UI, test networks are left out unless [Settings::show_testnets] is on
+    pub fn visible_chains(&self) -> Vec<ChainId> {
+        ChainId::all()
             .iter()
+            .filter(|chain_id| self.settings.show_testnets || !chain_id.is_testnet())
             .cloned()
-            .map(|chain_id| chain_id.id())
             .collect()
     }
 
+    /// Is `chain_id` one of [AppData::visible_chains]
+    pub fn is_chain_visible(&self, chain_id: u64) -> bool {
+        self.visible_chains().iter().any(|chain| chain.id() == chain_id)
+    }
+
+    /// The profile changed, it is saved [PROFILE_SAVE_DELAY] after the last change
+    pub fn mark_profile_dirty(&mut self) {
+        self.profile_save = ProfileSave::Dirty(Instant::now());
+    }
+
+    /// How long until a dirty profile is due for saving, `None` if there is nothing to save
+    pub fn profile_save_in(&self) -> Option<Duration> {
+        match self.profile_save {
+            ProfileSave::Dirty(at) => Some(PROFILE_SAVE_DELAY.saturating_sub(at.elapsed())),
+            _ => None,
+        }
+    }
+
+    /// The profile to send to the backend if a save is due, the state moves to [ProfileSave::Saving]
+    ///
+    /// `force` saves right away even if nothing changed, used before locking and by "Save Now"
+    pub fn take_profile_save(&mut self, force: bool) -> Option<Profile> {
+        let due = self.profile_save_in().is_some_and(|left| left.is_zero());
+        if !force && !due {
+            return None;
+        }
+        self.profile_save = ProfileSave::Saving;
+        Some(self.profile.clone())
+    }
+
+    /// Lock the profile and clear its keys from memory, the login screen is shown until it is unlocked
+    pub fn lock(&mut self) {
+        self.profile.lock();
+        self.logged_in = false;
+    }
+
     pub fn connected(&self) -> bool {
         self.client.is_some()
     }
 
+    /// Add a new latency sample, the oldest one is dropped after [LATENCY_SAMPLES]
+    pub fn add_latency(&mut self, latency: Duration) {
+        if self.latency.len() == LATENCY_SAMPLES {
+            self.latency.pop_front();
+        }
+        self.latency.push_back(latency);
+    }
+
+    /// The rolling average of the rpc latency
+    ///
+    /// Returns `None` if we have no samples yet
+    pub fn avg_latency(&self) -> Option<Duration> {
+        if self.latency.is_empty() {
+            return None;
+        }
+        let total: Duration = self.latency.iter().sum();
+        Some(total / self.latency.len() as u32)
+    }
+
     /// Return the latest block
     pub fn latest_block(&self) -> BlockInfo {
         self.latest_block.clone()
@@ -117,21 +265,114 @@ impl AppData {
         self.next_block.clone()
     }
 
+    /// How far behind the connected node is, `None` while its latest block is recent
+    pub fn node_lag(&self) -> Option<Duration> {
+        if !self.connected() {
+            return None;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.latest_block.lag(self.chain_id.id(), now)
+    }
+
     pub fn add_rpc(&mut self, rpc: Rpc) {
         self.rpc.push(rpc);
     }
 
     /// Save the rpc endpoints to `rpc.json`
     pub fn save_rpc(&self) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("Zeus is running in read-only mode"));
+        }
         let data = serde_json::to_string(&self.rpc.clone())?;
-        std::fs::write("rpc.json", data)?;
+        atomic_write(RPC_FILE, data)?;
         Ok(())
     }
 
     /// Load the rpc endpoints from file
-    pub fn load_rpc(&mut self) -> Result<(), anyhow::Error> {
-        let data = std::fs::read_to_string("rpc.json")?;
-        self.rpc = serde_json::from_str(&data)?;
+    /// 
+    /// Returns `true` if `rpc.json` was corrupted and the endpoints were restored from the backup
+    ///
+    /// Chains that were added after the file was saved get an empty endpoint
+    pub fn load_rpc(&mut self) -> Result<bool, anyhow::Error> {
+        let (rpc, from_backup) = read_with_backup(RPC_FILE, |data| Ok(serde_json::from_slice(&data)?))?;
+        self.rpc = rpc;
+
+        for chain_id in ChainId::all() {
+            if !self.rpc.iter().any(|rpc| rpc.chain_id == chain_id.id()) {
+                self.rpc.push(Rpc::new(String::new(), chain_id.id()));
+            }
+        }
+        Ok(from_backup)
+    }
+
+    /// Load the per-chain EVM spec overrides from `chains.json` into [zeus_chain::CHAIN_SPECS]
+    ///
+    /// The file is optional, returns `false` if it does not exist
+    pub fn load_chain_specs(&self) -> Result<bool, anyhow::Error> {
+        if !Path::new(CHAINS_FILE).exists() {
+            return Ok(false);
+        }
+
+        let data = std::fs::read(CHAINS_FILE)?;
+        let specs: ChainSpecs = serde_json::from_slice(&data)?;
+        *CHAIN_SPECS.write().unwrap() = specs;
+        Ok(true)
+    }
+
+    /// Save the user preferences to `settings.json`
+    pub fn save_settings(&self) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("Zeus is running in read-only mode"));
+        }
+        let data = serde_json::to_string(&self.settings)?;
+        atomic_write(SETTINGS_FILE, data)?;
+        Ok(())
+    }
+
+    /// Load the user preferences from file
+    /// 
+    /// Returns `true` if `settings.json` was corrupted and the settings were restored from the backup
+    pub fn load_settings(&mut self) -> Result<bool, anyhow::Error> {
+        let (settings, from_backup) = read_with_backup(SETTINGS_FILE, |data| Ok(serde_json::from_slice(&data)?))?;
+        self.settings = settings;
+        set_language(self.settings.language);
+        Ok(from_backup)
+    }
+
+    /// Write the settings, rpc endpoints and chain specs to `path` as one [SettingsBundle]
+    pub fn export_settings(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let chain_specs = CHAIN_SPECS.read().unwrap().clone();
+        let bundle = SettingsBundle::new(self.settings.clone(), self.rpc.clone(), chain_specs);
+        atomic_write(path, serde_json::to_string_pretty(&bundle)?)?;
+        Ok(())
+    }
+
+    /// Merge the [SettingsBundle] at `path` into the current preferences and save them
+    ///
+    /// Nothing is changed if the bundle is invalid
+    pub fn import_settings(&mut self, path: &Path) -> Result<(), anyhow::Error> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("Zeus is running in read-only mode"));
+        }
+        let json = std::fs::read_to_string(path)?;
+        let bundle = SettingsBundle::parse(&json)?;
+
+        // chains.json is optional, it is only written if the bundle overrides a spec
+        let has_specs = !bundle.chain_specs.overrides.is_empty();
+        let chain_specs = CHAIN_SPECS.read().unwrap().clone();
+        let (settings, rpc, chain_specs) = bundle.merge(&self.settings, &self.rpc, &chain_specs);
+
+        if has_specs {
+            atomic_write(CHAINS_FILE, serde_json::to_string(&chain_specs)?)?;
+            *CHAIN_SPECS.write().unwrap() = chain_specs;
+        }
+
+        self.settings = settings;
+        set_language(self.settings.language);
+        self.save_settings()?;
+
+        self.rpc = rpc;
+        self.save_rpc()?;
         Ok(())
     }
 
@@ -140,10 +381,75 @@ impl AppData {
         self.shared_cache.read().unwrap().get_eth_balance(chain_id, owner)
     }
 
+    /// USD price of one whole `currency` on the current chain, with [ETH_USD_FEED_DECIMALS] decimals
+    ///
+    /// Stablecoins are worth $1, other tokens need a spot price against the wrapped native currency,
+    /// see [SharedCache::native_prices]. `None` if we have no price
+    pub fn usd_price(&self, currency: &Currency) -> Option<U256> {
+        let chain_id = self.chain_id.id();
+
+        if self.settings.is_stablecoin(chain_id, currency) {
+            return Some(U256::from(10).pow(U256::from(ETH_USD_FEED_DECIMALS)));
+        }
+
+        let eth_price = self.eth_price?;
+        let token = match currency {
+            Currency::Native(_) => return Some(eth_price),
+            Currency::ERC20(token) => token,
+        };
+
+        if get_native_coin(chain_id).is_ok_and(|wrapped| wrapped == token.address) {
+            return Some(eth_price);
+        }
+
+        let native_price = *self.shared_cache.read().unwrap().native_prices.get(&(chain_id, token.address))?;
+        Currency::new_native(chain_id).usd_value(native_price, eth_price).ok()
+    }
+
+    /// USD value of `amount` of `currency` on the current chain, `None` if it has no price, see [Self::usd_price]
+    pub fn usd_value(&self, currency: &Currency, amount: U256) -> Option<f64> {
+        let value = currency.usd_value(amount, self.usd_price(currency)?).ok()?;
+        format_units(value, ETH_USD_FEED_DECIMALS).parse().ok()
+    }
+
+    /// Should a balance of `currency` be left out of the balance lists, see [Settings::hide_small_balances]
+    pub fn hide_balance(&self, currency: &Currency, balance: U256) -> bool {
+        match self.usd_value(currency, balance) {
+            Some(usd) => self.settings.hide_small_balances && usd < self.settings.small_balance_usd,
+            None => self.settings.hide_unpriced,
+        }
+    }
+
+    /// The native currency of the current chain, this is what we pay the fees with
+    pub fn native_currency(&self) -> NativeCurrency {
+        NativeCurrency::default_for_chain(&self.chain_id.id())
+    }
+
+    /// The gas price we expect to pay in the next block (base fee + priority fee)
+    pub fn gas_price(&self) -> U256 {
+        self.next_block.base_fee + self.tx_settings.parse_gwei()
+    }
+
+    /// Estimated fee in the native currency for a transaction that uses `gas`
+    pub fn estimate_fee(&self, gas: u64) -> U256 {
+        U256::from(gas) * self.gas_price()
+    }
+
     /// Update eth balance of a wallet for a specific chain
     pub fn update_balance(&mut self, chain_id: u64, owner: Address, balance: U256) {
         let block = self.latest_block.number;
         self.shared_cache.write().unwrap().update_eth_balance(chain_id, owner, block, balance);
+        self.sync_wallet_balances();
+    }
+
+    /// Copy the cached eth balances into every wallet of the profile
+    ///
+    /// The selected wallet and its entry in [Profile::wallets] always end up with the same balances
+    pub fn sync_wallet_balances(&mut self) {
+        let cache = self.shared_cache.read().unwrap();
+        for wallet in self.profile.wallets.iter_mut().chain(self.profile.current_wallet.as_mut()) {
+            cache.sync_wallet(wallet);
+        }
     }
 
     /// DEBUG
@@ -157,6 +463,13 @@ impl AppData {
     }
 
 
+    /// The slippage to use for a swap from `currency_in` to `currency_out` on the current chain
+    pub fn swap_slippage(&self, currency_in: &Currency, currency_out: &Currency) -> AppliedSlippage {
+        let chain_id = self.chain_id.id();
+        let class = PairClass::classify(currency_in, currency_out, |c| self.settings.is_stablecoin(chain_id, c));
+        self.tx_settings.slippage_for(class)
+    }
+
     /// Get the current wallet address
     pub fn wallet_address(&self) -> Address {
         if let Some(wallet) = &self.profile.current_wallet {
@@ -169,30 +482,89 @@ impl AppData {
 
 impl Default for AppData {
     fn default() -> Self {
-        let profile_exists = Path::new("profile.data").exists();
+        let profile_exists = Profile::exists();
         let new_profile_screen = !profile_exists;
 
         // Just to init AppData, we load the actual saved data later when we start ZeusApp
         let mut rpc = vec![];
 
-        for chain_id in NETWORKS {
+        for chain_id in ChainId::all() {
             rpc.push(Rpc::new("".to_string(), chain_id.id()));
         }
 
         Self {
             latest_block: BlockInfo::default(),
             next_block: BlockInfo::default(),
+            eth_price: None,
             client: None,
+            connected_chain: None,
             connected: false,
+            latency: VecDeque::with_capacity(LATENCY_SAMPLES),
             chain_id: ChainId::default(),
-            chain_ids: NETWORKS.to_vec(),
             rpc,
             profile: Profile::default(),
+            credentials: Credentials::default(),
+            unlock_credentials: UnlockCredentials::default(),
             shared_cache: SHARED_CACHE.clone(),
             tx_settings: TxSettings::default(),
+            settings: Settings::default(),
             logged_in: false,
             new_profile_screen,
+            read_only: false,
+            data_dir_read_only: false,
             profile_exists,
+            profile_save: ProfileSave::default(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeus_chain::{alloy::primitives::address, ERC20Token};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+
+    fn token(address: Address) -> Currency {
+        Currency::new_erc20(ERC20Token { chain_id: 1, address, ..Default::default() })
+    }
+
+    #[test]
+    fn auto_slippage_follows_how_stable_the_pair_is() {
+        let data = AppData::default();
+        let (usdc, usdt, pepe) = (token(USDC), token(USDT), token(Address::repeat_byte(1)));
+        let eth = Currency::new_native(1);
+
+        let stable = data.swap_slippage(&usdc, &usdt);
+        assert_eq!(stable.value, PairClass::Stable.default_slippage());
+        assert_eq!(stable.reason, "Auto, both tokens are stablecoins");
+
+        assert_eq!(data.swap_slippage(&eth, &usdc).value, PairClass::Mixed.default_slippage());
+        assert_eq!(data.swap_slippage(&pepe, &eth).value, PairClass::Volatile.default_slippage());
+    }
+
+    #[test]
+    fn a_token_the_user_marked_counts_as_a_stablecoin() {
+        let mut data = AppData::default();
+        let (usdc, marked) = (token(USDC), token(Address::repeat_byte(2)));
+        assert_eq!(data.swap_slippage(&usdc, &marked).value, PairClass::Mixed.default_slippage());
+
+        data.settings.set_stablecoin(1, Address::repeat_byte(2), true);
+        assert!(data.settings.is_stablecoin(1, &marked));
+        assert_eq!(data.swap_slippage(&usdc, &marked).value, PairClass::Stable.default_slippage());
+
+        // only on the chain it was marked on
+        assert!(!data.settings.is_stablecoin(56, &marked));
+    }
+
+    #[test]
+    fn manual_slippage_ignores_the_pair() {
+        let mut data = AppData::default();
+        data.tx_settings.auto_slippage = false;
+        data.tx_settings.slippage = "0.8".to_string();
+
+        let applied = data.swap_slippage(&token(USDC), &token(USDT));
+        assert_eq!(applied.value, 0.8);
+        assert_eq!(applied.reason, "Set manually");
+    }
+}
@@ -1,16 +1,58 @@
+use std::time::{Duration, Instant};
+
+/// How long [InfoMsg::timed] messages stay on screen
+pub const INFO_MSG_DURATION: Duration = Duration::from_secs(5);
+
 /// An Info message to show in the UI
 #[derive(Clone, Default)]
 pub struct InfoMsg {
     pub on: bool,
 
     pub msg: String,
+
+    /// When the message goes away by itself, `None` if it stays until it is closed
+    pub expires_at: Option<Instant>,
 }
 
 impl InfoMsg {
+    /// A message that stays until the user closes it
     pub fn new<T>(on: bool, msg: T) -> Self where T: ToString {
         Self {
             on,
             msg: msg.to_string(),
+            expires_at: None,
+        }
+    }
+
+    /// A message that goes away by itself after `duration`
+    pub fn timed<T>(msg: T, duration: Duration) -> Self where T: ToString {
+        Self {
+            on: true,
+            msg: msg.to_string(),
+            expires_at: Some(Instant::now() + duration),
         }
     }
-}
\ No newline at end of file
+
+    /// Is a timed message past its duration at `now`
+    pub fn expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timed_messages_expire() {
+        let msg = InfoMsg::timed("Settings saved", INFO_MSG_DURATION);
+        let now = Instant::now();
+        assert!(msg.on);
+        assert!(!msg.expired(now));
+        assert!(msg.expired(now + INFO_MSG_DURATION));
+
+        // a message without a duration stays until it is closed
+        let msg = InfoMsg::new(true, "Backup restored");
+        assert!(!msg.expired(now + INFO_MSG_DURATION * 100));
+    }
+}
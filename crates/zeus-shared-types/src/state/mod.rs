@@ -2,11 +2,14 @@ pub mod shared_ui;
 pub mod swap_ui;
 pub mod info;
 pub mod error;
+pub mod notification;
 pub mod data;
+pub mod settings;
+pub mod bundle;
 
 
 pub use shared_ui::{SharedUiState, SHARED_UI_STATE};
-pub use swap_ui::{SwapUIState, SWAP_UI_STATE, SelectedCurrency};
+pub use swap_ui::{AmountInput, PoolQuote, QuoteResult, QuoteRoute, RouteHop, SelectedCurrency, bps_text, fee_text, percent_to_bps, share_to_bps};
 
 /// Indicates whether we should show a UI or not
 #[derive(Clone, Default)]
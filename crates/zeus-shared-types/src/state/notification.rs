@@ -0,0 +1,86 @@
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use zeus_core::lazy_static::lazy_static;
+
+use crate::i18n::UiText;
+
+lazy_static! {
+    /// See [UiNotification]
+    ///
+    /// Any thread can send, the GUI takes them once per frame with [take_notifications]
+    static ref NOTIFICATIONS: (Sender<UiNotification>, Receiver<UiNotification>) = unbounded();
+}
+
+/// A message for the user, shown by the GUI the next time it paints
+///
+/// The text is translated by the GUI, see [UiText]
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiNotification {
+    /// Stays until the user closes it
+    Error(UiText),
+
+    /// Confirms that something went through, goes away by itself
+    Info(UiText),
+
+    /// An info message that stays until the user closes it
+    Notice(UiText),
+}
+
+/// Queue a [UiNotification] for the GUI
+pub fn notify(notification: UiNotification) {
+    // the receiver lives as long as the sender, this can't fail
+    let _ = NOTIFICATIONS.0.send(notification);
+}
+
+/// Show an error message as is
+pub fn notify_error<T>(msg: T) where T: ToString {
+    notify(UiNotification::Error(UiText::Raw(msg.to_string())));
+}
+
+/// Show an error message from the translation catalog
+pub fn notify_error_text(text: UiText) {
+    notify(UiNotification::Error(text));
+}
+
+/// Show an info message that goes away by itself
+pub fn notify_info<T>(msg: T) where T: ToString {
+    notify(UiNotification::Info(UiText::Raw(msg.to_string())));
+}
+
+/// Show an info message from the translation catalog that goes away by itself
+pub fn notify_info_text(text: UiText) {
+    notify(UiNotification::Info(text));
+}
+
+/// Show an info message that stays until it is closed
+pub fn notify_notice<T>(msg: T) where T: ToString {
+    notify(UiNotification::Notice(UiText::Raw(msg.to_string())));
+}
+
+/// The notifications queued since the last call, oldest first
+///
+/// Only the GUI should call this, anything taken here is not shown otherwise
+pub fn take_notifications() -> Vec<UiNotification> {
+    NOTIFICATIONS.1.try_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_are_taken_once_in_order() {
+        notify_error("Not connected");
+        notify_info_text(UiText::key("info.settings_saved"));
+        notify_notice("Backup restored");
+
+        assert_eq!(
+            take_notifications(),
+            vec![
+                UiNotification::Error("Not connected".into()),
+                UiNotification::Info(UiText::key("info.settings_saved")),
+                UiNotification::Notice("Backup restored".into()),
+            ]
+        );
+        assert!(take_notifications().is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+use zeus_chain::{
+    alloy::primitives::Address,
+    defi_types::currency::{erc20::ApprovalAmount, stablecoin::default_stablecoins, Currency},
+    SwapRouter, TokenMetadataSource,
+};
+
+use crate::i18n::Language;
+
+
+/// The lowest balance refresh interval we allow, so the RPC is not hammered
+pub const MIN_BALANCE_REFRESH_SECS: u64 = 1;
+
+/// How many recently selected currencies we keep per chain and side
+pub const MAX_RECENT_CURRENCIES: usize = 8;
+
+/// A currency the user selected recently, resolved against the cached currencies when shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecentCurrency {
+    Native,
+    ERC20(Address),
+}
+
+impl RecentCurrency {
+    pub fn new(currency: &Currency) -> Self {
+        match currency.erc20() {
+            Some(token) => RecentCurrency::ERC20(token.address),
+            None => RecentCurrency::Native,
+        }
+    }
+
+    /// Does this refer to `currency`
+    pub fn matches(&self, currency: &Currency) -> bool {
+        *self == RecentCurrency::new(currency)
+    }
+}
+
+/// Which side of a swap a currency was picked for, sends count as the input side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickSide {
+    Input,
+    Output,
+}
+
+impl PickSide {
+    /// The side of a token picker id, `"output"` is the output side and anything else the input one
+    pub fn from_id(id: &str) -> Self {
+        match id {
+            "output" => PickSide::Output,
+            _ => PickSide::Input,
+        }
+    }
+}
+
+/// The recently picked currencies of a chain, most recent first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentPicks {
+    pub input: Vec<RecentCurrency>,
+    pub output: Vec<RecentCurrency>,
+}
+
+impl RecentPicks {
+    pub fn side(&self, side: PickSide) -> &[RecentCurrency] {
+        match side {
+            PickSide::Input => &self.input,
+            PickSide::Output => &self.output,
+        }
+    }
+
+    /// Move `recent` to the front of `side`, dropping the oldest one if the list is full
+    pub fn push(&mut self, side: PickSide, recent: RecentCurrency) {
+        let recents = match side {
+            PickSide::Input => &mut self.input,
+            PickSide::Output => &mut self.output,
+        };
+        recents.retain(|r| *r != recent);
+        recents.insert(0, recent);
+        recents.truncate(MAX_RECENT_CURRENCIES);
+    }
+}
+
+/// User preferences that are saved to `settings.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Where to read the metadata of a token when adding it by address
+    pub token_metadata_source: TokenMetadataSource,
+
+    /// Simulate and sign transactions but never broadcast them
+    pub dry_run: bool,
+
+    /// Warn before sending more than this fraction (0.0 - 1.0) of the balance
+    pub large_tx_fraction: f32,
+
+    /// Tokens the user marked as stablecoins, on top of [default_stablecoins]
+    ///
+    /// `Key:` chain_id -> `Value:` token addresses
+    pub stablecoins: HashMap<u64, Vec<Address>>,
+
+    /// How often we refresh the balances on chains other than Ethereum, in seconds
+    ///
+    /// Ethereum balances are refreshed on every new block
+    pub balance_refresh_secs: u64,
+
+    /// The last selected currencies of each side, most recent first
+    ///
+    /// `Key:` chain_id -> `Value:` at most [MAX_RECENT_CURRENCIES] currencies per side
+    pub recent_picks: HashMap<u64, RecentPicks>,
+
+    /// Lock the profile after this many minutes without any input, 0 to never lock it
+    pub auto_lock_mins: u64,
+
+    /// The user finished the first-run setup wizard, it is not shown on startup again
+    pub onboarding_done: bool,
+
+    /// Hide the balances worth less than [Settings::small_balance_usd]
+    pub hide_small_balances: bool,
+
+    /// The USD threshold of [Settings::hide_small_balances]
+    pub small_balance_usd: f64,
+
+    /// Hide the tokens we can't price in USD
+    pub hide_unpriced: bool,
+
+    /// List Sepolia and Base Sepolia next to the main networks
+    pub show_testnets: bool,
+
+    /// Sends worth at least this much in USD (or that we can't price) ask to type back the end of the recipient
+    pub verify_recipient_usd: f64,
+
+    /// The router swaps are sent to, chains without an entry use [SwapRouter::default_for]
+    ///
+    /// `Key:` chain_id -> `Value:` router
+    pub swap_routers: HashMap<u64, SwapRouter>,
+
+    /// How much a swap approves the router to spend, can be changed for a single swap
+    pub approval_amount: ApprovalAmount,
+
+    /// Show the full details of a quote, the route summary is always shown
+    pub quote_details_open: bool,
+
+    /// Append every quote to `quotes.csv` in the data directory
+    pub log_quotes: bool,
+
+    /// The language of the UI
+    pub language: Language,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            token_metadata_source: TokenMetadataSource::default(),
+            dry_run: false,
+            large_tx_fraction: 0.5,
+            stablecoins: HashMap::new(),
+            balance_refresh_secs: 3,
+            recent_picks: HashMap::new(),
+            auto_lock_mins: 15,
+            onboarding_done: false,
+            hide_small_balances: false,
+            small_balance_usd: 1.0,
+            hide_unpriced: false,
+            show_testnets: false,
+            verify_recipient_usd: 1000.0,
+            swap_routers: HashMap::new(),
+            approval_amount: ApprovalAmount::default(),
+            quote_details_open: true,
+            log_quotes: false,
+            language: Language::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// The balance refresh interval, never lower than [MIN_BALANCE_REFRESH_SECS]
+    pub fn balance_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.balance_refresh_secs.max(MIN_BALANCE_REFRESH_SECS))
+    }
+
+    /// How long the app can stay idle before the profile is locked, `None` if auto-lock is disabled
+    pub fn auto_lock_timeout(&self) -> Option<Duration> {
+        if self.auto_lock_mins == 0 {
+            return None;
+        }
+        Some(Duration::from_secs(self.auto_lock_mins * 60))
+    }
+
+    /// Is this currency a stablecoin, either by default or marked by the user
+    pub fn is_stablecoin(&self, chain_id: u64, currency: &Currency) -> bool {
+        let token = match currency.erc20() {
+            Some(token) => token.address,
+            None => return false,
+        };
+
+        default_stablecoins(chain_id).contains(&token) || self.is_user_stablecoin(chain_id, token)
+    }
+
+    /// Did the user mark this token as a stablecoin
+    pub fn is_user_stablecoin(&self, chain_id: u64, token: Address) -> bool {
+        self.stablecoins
+            .get(&chain_id)
+            .is_some_and(|tokens| tokens.contains(&token))
+    }
+
+    /// Mark or unmark a token as a stablecoin
+    pub fn set_stablecoin(&mut self, chain_id: u64, token: Address, stable: bool) {
+        let tokens = self.stablecoins.entry(chain_id).or_default();
+        tokens.retain(|t| *t != token);
+        if stable {
+            tokens.push(token);
+        }
+    }
+
+    /// The router swaps on a chain are sent to
+    pub fn swap_router(&self, chain_id: u64) -> SwapRouter {
+        self.swap_routers
+            .get(&chain_id)
+            .copied()
+            .unwrap_or_else(|| SwapRouter::default_for(chain_id))
+    }
+
+    /// The currencies recently picked for `side` on a chain, most recent first
+    pub fn recents(&self, chain_id: u64, side: PickSide) -> &[RecentCurrency] {
+        self.recent_picks.get(&chain_id).map(|picks| picks.side(side)).unwrap_or_default()
+    }
+
+    /// Move `currency` to the front of the recents of `side`, dropping the oldest one if the list is full
+    pub fn add_recent_currency(&mut self, chain_id: u64, side: PickSide, currency: &Currency) {
+        self.recent_picks
+            .entry(chain_id)
+            .or_default()
+            .push(side, RecentCurrency::new(currency));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(byte: u8) -> RecentCurrency {
+        RecentCurrency::ERC20(Address::repeat_byte(byte))
+    }
+
+    #[test]
+    fn picking_again_moves_to_the_front_once() {
+        let mut picks = RecentPicks::default();
+        picks.push(PickSide::Input, token(1));
+        picks.push(PickSide::Input, token(2));
+        picks.push(PickSide::Input, token(1));
+
+        assert_eq!(picks.side(PickSide::Input), &[token(1), token(2)]);
+    }
+
+    #[test]
+    fn sides_are_kept_apart() {
+        let mut settings = Settings::default();
+        let native = Currency::new_native(1);
+        settings.add_recent_currency(1, PickSide::Input, &native);
+
+        assert_eq!(settings.recents(1, PickSide::Input), &[RecentCurrency::Native]);
+        assert!(settings.recents(1, PickSide::Output).is_empty());
+        assert!(settings.recents(56, PickSide::Input).is_empty());
+    }
+
+    #[test]
+    fn the_oldest_pick_is_dropped() {
+        let mut picks = RecentPicks::default();
+        for byte in 0..=MAX_RECENT_CURRENCIES as u8 {
+            picks.push(PickSide::Output, token(byte));
+        }
+
+        let recents = picks.side(PickSide::Output);
+        assert_eq!(recents.len(), MAX_RECENT_CURRENCIES);
+        assert_eq!(recents[0], token(MAX_RECENT_CURRENCIES as u8));
+        assert!(!recents.contains(&token(0)));
+    }
+
+    #[test]
+    fn side_of_a_picker_id() {
+        assert_eq!(PickSide::from_id("output"), PickSide::Output);
+        assert_eq!(PickSide::from_id("input"), PickSide::Input);
+        // the send screen picker has no id
+        assert_eq!(PickSide::from_id(""), PickSide::Input);
+    }
+}
@@ -1,18 +1,21 @@
 use zeus_core::lazy_static::lazy_static;
-use std::sync::{Arc, RwLock};
-use super::{error::ErrorMsg, info::InfoMsg};
+use std::{
+    sync::{Arc, OnceLock, RwLock, RwLockWriteGuard},
+    thread::{self, ThreadId},
+};
 
 lazy_static! {
 
     /// See [SharedUiState]
     /// 
-    /// This can be safely shared across all tasks
+    /// This can be safely read from all tasks, only the GUI thread writes to it
     pub static ref SHARED_UI_STATE: Arc<RwLock<SharedUiState>> = Arc::new(
         RwLock::new(SharedUiState::default())
     );
 }
 
-
+/// The thread that runs the GUI, see [SharedUiState::set_gui_thread]
+static GUI_THREAD: OnceLock<ThreadId> = OnceLock::new();
 
 /// Shared State for some GUI components
 /// 
@@ -20,12 +23,13 @@ lazy_static! {
 /// 
 /// For convenience we use a thread-safe [SHARED_UI_STATE] instance to manage the state
 /// 
-/// For example an [ErrorMsg] can be set here using 
-/// ```
-/// let mut state = SHARED_UI_STATE.write().unwrap();
-/// state.err_msg.show(err);
+/// Messages for the user don't go here, see [crate::notify_error] and [crate::notify_info]
+/// 
+/// The state is written with [SharedUiState::write]
+/// ```ignore
+/// SharedUiState::write().tx_settings_on = true;
 /// ```
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct SharedUiState {
 
     /// Network Settings
@@ -33,21 +37,22 @@ pub struct SharedUiState {
 
     /// TxSettings popup on/off
     pub tx_settings_on: bool,
-
-    /// Error message to show in the UI
-    pub err_msg: ErrorMsg,
-
-    /// Info message to show in the UI
-    pub info_msg: InfoMsg,
 }
 
-impl Default for SharedUiState {
-    fn default() -> Self {
-        Self {
-            network_settings: false,
-            tx_settings_on: false,
-            err_msg: ErrorMsg::default(),
-            info_msg: InfoMsg::default(),
-        }
+impl SharedUiState {
+    /// Mark the calling thread as the GUI thread, only the first call counts
+    pub fn set_gui_thread() {
+        let _ = GUI_THREAD.set(thread::current().id());
+    }
+
+    /// Lock [SHARED_UI_STATE] for writing
+    ///
+    /// Debug builds panic if this is not the GUI thread, other threads should send a notification instead
+    pub fn write() -> RwLockWriteGuard<'static, SharedUiState> {
+        debug_assert!(
+            GUI_THREAD.get().is_none_or(|id| *id == thread::current().id()),
+            "SharedUiState is only written from the GUI thread"
+        );
+        SHARED_UI_STATE.write().unwrap()
     }
-}
\ No newline at end of file
+}
@@ -1,57 +1,151 @@
-use std::sync::{ Arc, RwLock };
-
+use serde::{Deserialize, Serialize};
 use zeus_chain::{
-    alloy::primitives::{ Bytes, U256, Address },
-    defi_types::currency::{ Currency, NativeCurrency, erc20::ERC20Token },
+    alloy::primitives::{ Address, Bytes, U256 },
+    defi_types::{ currency::{ Currency, NativeCurrency, erc20::ERC20Token }, pool::PoolVariant },
+    format_units, format_units_with_precision, parse_units,
 };
-use crate::cache::{SHARED_CACHE, SharedCache};
-use zeus_core::lazy_static::lazy_static;
 
-lazy_static! {
+/// `500` -> `0.05%`, `fee` is in hundredths of a bip like [zeus_chain::Pool::fee]
+pub fn fee_text(fee: u32) -> String {
+    format!("{}%", fee as f32 / 10_000.0)
+}
 
-    /// The State Of the Swap UI
-    /// 
-    /// This can be safely shared across all tasks
-    pub static ref SWAP_UI_STATE: Arc<RwLock<SwapUIState>> = Arc::new(
-        RwLock::new(SwapUIState::default())
-    );
+/// A percentage (eg. `0.5` for 0.5%) in basis points, clamped to 0 - 100%
+pub fn percent_to_bps(percent: f32) -> u32 {
+    (percent * 100.0).clamp(0.0, 10_000.0) as u32
+}
+
+/// A share (0.0 - 1.0) in basis points, rounded to the closest one
+pub fn share_to_bps(share: f64) -> u32 {
+    (share * 10_000.0).round().clamp(0.0, 10_000.0) as u32
+}
+
+/// `50` -> `0.5%`
+pub fn bps_text(bps: u32) -> String {
+    format!("{}%", bps as f32 / 100.0)
+}
+
+fn variant_text(variant: &PoolVariant) -> &'static str {
+    match variant {
+        PoolVariant::UniswapV2 => "V2",
+        PoolVariant::UniswapV3 => "V3",
+    }
 }
 
-#[derive(Clone, Default)]
+/// The output of one of the pools that was quoted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolQuote {
+    pub address: Address,
+    pub variant: PoolVariant,
+
+    /// In hundredths of a bip like [zeus_chain::Pool::fee]
+    pub fee: u32,
+
+    pub amount_out: U256,
+}
+
+impl PoolQuote {
+    /// eg. `V3 0.05%`
+    pub fn label(&self) -> String {
+        format!("{} {}", variant_text(&self.variant), fee_text(self.fee))
+    }
+}
+
+/// A pool the swap goes through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteHop {
+    pub pool: Address,
+    pub variant: PoolVariant,
+
+    /// In hundredths of a bip like [zeus_chain::Pool::fee]
+    pub fee: u32,
+}
+
+/// The DEX and the pools a quote goes through, in swap order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuoteRoute {
+    /// eg. `Uniswap`
+    pub dex: String,
+
+    pub hops: Vec<RouteHop>,
+}
+
+impl QuoteRoute {
+    /// eg. `Uniswap V3 0.05% · 1 hop`, the fee tiers of every hop are listed
+    pub fn summary(&self) -> String {
+        let pools: Vec<String> = self
+            .hops
+            .iter()
+            .map(|hop| format!("{} {}", variant_text(&hop.variant), fee_text(hop.fee)))
+            .collect();
+
+        let hops = match self.hops.len() {
+            1 => "1 hop".to_string(),
+            n => format!("{} hops", n),
+        };
+        format!("{} {} · {}", self.dex, pools.join(" > "), hops)
+    }
+
+    /// Does the swap go through `pool`
+    pub fn uses_pool(&self, pool: Address) -> bool {
+        self.hops.iter().any(|hop| hop.pool == pool)
+    }
+
+    /// The fees of every hop, eg. `0.05%` or `0.3% + 0.05%`
+    pub fn fee_text(&self) -> String {
+        let fees: Vec<String> = self.hops.iter().map(|hop| fee_text(hop.fee)).collect();
+        fees.join(" + ")
+    }
+}
+
+/// A quote this many blocks behind the latest block is shown as stale
+pub const STALE_QUOTE_BLOCKS: u64 = 3;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QuoteResult {
-    /// Block Number
+    /// The block the quote was simulated on
     pub block_number: u64,
 
     pub input_token: SelectedCurrency,
 
     pub output_token: SelectedCurrency,
 
-    /// USD worth of the input token
-    pub input_token_usd_worth: String,
+    /// USD worth of the input token, `None` if we can't price it
+    pub input_token_usd_worth: Option<f64>,
 
-    /// USD worth of the output token
-    pub output_token_usd_worth: String,
+    /// USD worth of the output token, `None` if we can't price it
+    pub output_token_usd_worth: Option<f64>,
 
-    /// The price impact of the swap
-    pub price_impact: String,
+    /// The share (0.0 - 1.0) of the value lost to the price impact, `None` if unknown
+    pub price_impact: Option<f64>,
 
-    /// Selected slippage
-    pub slippage: String,
+    /// Selected slippage in basis points
+    pub slippage_bps: u32,
 
     /// The real amount of tokens we will receive, after considering the pool fee and token tax if any
-    pub real_amount: String,
+    pub amount_out: U256,
 
     /// Minimum amount we may receive depending on the slippage
-    pub minimum_received: String,
+    pub minimum_received: U256,
+
+    /// Token Tax in basis points (If any)
+    pub token_tax_bps: Option<u32>,
+
+    /// The pools the swap goes through
+    pub route: QuoteRoute,
+
+    /// Every pool that returned a quote, best first, pools that reverted are left out
+    pub pool_quotes: Vec<PoolQuote>,
 
-    /// Token Tax (If any)
-    pub token_tax: String,
+    /// The router the swap is sent to
+    pub router: Address,
 
-    /// Pool Fee
-    pub pool_fee: String,
+    /// The allowance of the router over the input token, `None` for the native currency or if it could not be read
+    #[serde(default)]
+    pub allowance: Option<U256>,
 
-    /// Gas Cost of the swap in USD
-    pub gas_cost: String,
+    /// Gas used by the swap, 0 if it could not be simulated
+    pub gas_used: u64,
 
     /// Call Data to be used for the transaction
     pub data: Bytes,
@@ -60,179 +154,249 @@ pub struct QuoteResult {
 impl QuoteResult {
     /// Get Output token amount in readable format
     pub fn output_token_amount(&self) -> String {
-        "TODO".to_string()
+        format_units(self.amount_out, self.output_token.decimals())
     }
 
     /// Get Minimum received amount in readable format
     pub fn minimum_received_amount(&self) -> String {
-        "TODO".to_string()
+        format_units(self.minimum_received, self.output_token.decimals())
+    }
+
+    /// eg. `0.5%`
+    pub fn slippage_text(&self) -> String {
+        bps_text(self.slippage_bps)
+    }
+
+    /// eg. `5.12%`, `None` if the token takes no tax
+    pub fn token_tax_text(&self) -> Option<String> {
+        self.token_tax_bps.map(|bps| format!("{:.2}%", bps as f32 / 100.0))
+    }
+
+    /// How many blocks the quote is behind `latest_block`
+    pub fn blocks_old(&self, latest_block: u64) -> u64 {
+        latest_block.saturating_sub(self.block_number)
+    }
+
+    /// Is the quote [STALE_QUOTE_BLOCKS] or more blocks behind `latest_block`
+    pub fn is_stale(&self, latest_block: u64) -> bool {
+        self.blocks_old(latest_block) >= STALE_QUOTE_BLOCKS
+    }
+
+    /// eg. `based on block 20000000 (2 blocks old)`
+    pub fn block_text(&self, latest_block: u64) -> String {
+        let age = match self.blocks_old(latest_block) {
+            0 => "latest".to_string(),
+            1 => "1 block old".to_string(),
+            blocks => format!("{} blocks old", blocks),
+        };
+        format!("based on block {} ({})", self.block_number, age)
     }
 }
 
-/// A currency that its currently selected in a UI
-#[derive(Debug, Clone, PartialEq)]
-pub struct SelectedCurrency {
-    pub currency: Currency,
+/// The characters a pasted number may group its digits with, eg. `1 234 567` or `1'234'567`
+const GROUP_SEPARATORS: [char; 4] = [' ', '_', '\'', '\u{a0}'];
+
+/// Turn typed or pasted text into an amount with at most `decimals` fraction digits
+///
+/// Grouped numbers like `1,234.50` or `1.234,50` become `1234.50` and a lone `,` is a decimal point.
+/// Returns the text and whether something had to be dropped, eg. a letter or a digit past the decimals
+pub fn normalize_amount(text: &str, decimals: u8) -> (String, bool) {
+    let mut rejected = false;
+    let mut kept = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_digit() || c == '.' || c == ',' {
+            kept.push(c);
+        } else if !GROUP_SEPARATORS.contains(&c) {
+            rejected = true;
+        }
+    }
 
-    /// The amount of currency to swap
-    pub amount_to_swap: String,
+    let (integer, fraction) = match decimal_point(&kept) {
+        Some(index) => (&kept[..index], Some(&kept[index + 1..])),
+        None => (&kept[..], None),
+    };
+
+    // the separators left in the integer group its digits, one after the decimal point is a typo
+    let mut normalized: String = integer.chars().filter(char::is_ascii_digit).collect();
+    if let Some(fraction) = fraction {
+        let mut digits: String = fraction.chars().filter(char::is_ascii_digit).collect();
+        rejected |= digits.len() != fraction.len() || digits.len() > decimals as usize || decimals == 0;
+        digits.truncate(decimals as usize);
+        if decimals > 0 {
+            normalized.push('.');
+            normalized.push_str(&digits);
+        }
+    }
+
+    (normalized, rejected)
 }
 
-/// The state of the SwapUI
-pub struct SwapUIState {
-    /// Latest Block Number
-    pub block: u64,
+/// Where the decimal point of a number made of digits, `.` and `,` is, `None` if the separators only group the digits
+fn decimal_point(text: &str) -> Option<usize> {
+    let last = text.rfind(['.', ','])?;
+    let separator = text.as_bytes()[last] as char;
+    let other = if separator == '.' { ',' } else { '.' };
 
-    /// Status of the `Currency In` Window selection list
-    pub currency_in_list_on: bool,
+    // 1,234.5 or 1.234,5
+    if text.contains(other) {
+        return Some(last);
+    }
 
-    /// Status of the `Currency Out` Window selection list
-    pub currency_out_list_on: bool,
+    let groups: Vec<&str> = text.split(separator).collect();
+    let grouped = (1..=3).contains(&groups[0].len())
+        && !groups[0].starts_with('0')
+        && groups[1..].iter().all(|group| group.len() == 3);
 
-    /// Currency to swap from
-    pub currency_in: SelectedCurrency,
+    // a single `.` is always the decimal point, a single `,` only when it does not group thousands
+    if grouped && (groups.len() > 2 || separator == ',') {
+        None
+    } else {
+        text.find(separator)
+    }
+}
 
-    /// Currency to swap to
-    pub currency_out: SelectedCurrency,
+/// An amount typed by the user and the value it parses to
+///
+/// The text is kept as typed, the parsed value depends on the decimals of the currency
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmountInput {
+    raw: String,
 
-    /// The search query for a currency
-    pub search_currency: String,
+    /// `raw` in the smallest unit, `None` if it does not parse
+    amount: Option<U256>,
 
-    pub shared_cache: Arc<RwLock<SharedCache>>,
+    /// The decimals `amount` was parsed with
+    #[serde(default)]
+    decimals: u8,
 
-    pub quote_result: QuoteResult,
+    /// The last edit had characters an amount can't have, they were dropped
+    #[serde(skip)]
+    rejected: bool,
 }
 
-impl SwapUIState {
+impl AmountInput {
+    /// Parse `raw` with the given `decimals`
+    pub fn new(raw: impl Into<String>, decimals: u8) -> Self {
+        let mut input = Self {
+            raw: raw.into(),
+            amount: None,
+            decimals,
+            rejected: false,
+        };
+        input.reparse(decimals);
+        input
+    }
 
-    /// Get the balance of a token for a specific chain_id
-    pub fn get_erc20_balance(&self, chain_id: &u64, owner: &Address, token: &Address) -> U256 {
-        self.shared_cache.read().unwrap().get_erc20_balance(chain_id, owner, token)
+    /// The readable form of an `amount` in its smallest unit, eg. a quote or the max balance
+    pub fn from_amount(amount: U256, decimals: u8) -> Self {
+        Self {
+            raw: format_units(amount, decimals),
+            amount: Some(amount),
+            decimals,
+            rejected: false,
+        }
     }
 
-    /// Update the balance of a token for a specific chain_id
-    pub fn update_erc20_balance(&mut self, chain_id: u64, owner: Address, token: Address, balance: U256) {
-        self.shared_cache.write().unwrap().update_erc20_balance(chain_id, owner, token, balance);
+    /// Clean the text after the user edited it and parse it, see [normalize_amount]
+    pub fn edited(&mut self, decimals: u8) {
+        let (raw, rejected) = normalize_amount(&self.raw, decimals);
+        self.raw = raw;
+        self.rejected = rejected;
+        self.reparse(decimals);
     }
 
-    /// Get the balance of a [SelectedCurrency]
-    pub fn get_balance(&self, chain_id: &u64, owner: &Address, currency: &SelectedCurrency) -> U256 {
-        if currency.currency.is_native() {
-            self.shared_cache.read().unwrap().get_eth_balance(*chain_id, *owner).1
-        } else {
-            let token = currency.get_erc20().unwrap();
-            self.get_erc20_balance(chain_id, owner, &token.address)
-        }
+    /// Parse the text again, after it was edited or the currency has changed
+    pub fn reparse(&mut self, decimals: u8) {
+        self.amount = parse_units(&self.raw, decimals).ok();
+        self.decimals = decimals;
     }
 
-    /// Get the input or output selected currency by an id
-    pub fn get_selected_currency(&self, id: &str) -> SelectedCurrency {
-        match id {
-            "input" => self.currency_in.clone(),
-            "output" => self.currency_out.clone(),
-            // * This should not happen
-            _ => SelectedCurrency::default(),
-        }
+    /// The text as typed
+    pub fn raw(&self) -> &str {
+        &self.raw
     }
 
-    /// Replace the input or output currency by an id
-    pub fn replace_currency(&mut self, id: &str, currency: SelectedCurrency) {
-        match id {
-            "input" => {
-                self.currency_in = currency;
-            }
-            "output" => {
-                self.currency_out = currency;
-            }
-            _ => {}
-        }
+    /// The text to edit, [Self::edited] has to be called after it changed
+    pub fn raw_mut(&mut self) -> &mut String {
+        &mut self.raw
     }
 
+    /// The parsed amount, `None` if the text is empty or invalid
+    pub fn amount(&self) -> Option<U256> {
+        self.amount
+    }
 
-    /// Get which window selection list is on or off by an id
-    ///
-    /// `id` -> "input" or "output" currency
-    pub fn get_currency_list_status(&self, id: &str) -> bool {
-        match id {
-            "input" => self.currency_in_list_on,
-            "output" => self.currency_out_list_on,
-            _ => false,
-        }
+    /// The decimals the text was parsed with, they must be the ones of the currency it is an amount of
+    pub fn decimals(&self) -> u8 {
+        self.decimals
     }
 
-    /// Close or Open a currency selection window by an id
-    ///
-    /// `id` -> "input" or "output" currency
-    ///
-    /// `on` -> true or false
-    pub fn update_token_list_status(&mut self, id: &str, on: bool) {
-        match id {
-            "input" => {
-                self.currency_in_list_on = on;
-            }
-            "output" => {
-                self.currency_out_list_on = on;
-            }
-            _ => {}
-        }
+    /// The parsed amount or zero
+    pub fn amount_or_zero(&self) -> U256 {
+        self.amount.unwrap_or_default()
     }
 
-    /// Give a default input currency based on the selected chain id
-    pub fn default_input(&mut self, id: u64) {
-        self.currency_in = SelectedCurrency::default_input(id);
+    /// Nothing was typed
+    pub fn is_empty(&self) -> bool {
+        self.raw.trim().is_empty()
     }
 
-    /// Give a default output currency based on the selected chain id
-    pub fn default_output(&mut self, id: u64) {
-        self.currency_out = SelectedCurrency::default_output(id);
+    /// The text parses to an amount
+    pub fn is_valid(&self) -> bool {
+        self.amount.is_some()
     }
-}
 
-impl Default for SwapUIState {
-    fn default() -> Self {
-        Self {
-            block: 0,
-            currency_in_list_on: false,
-            currency_out_list_on: false,
-            currency_in: SelectedCurrency::default_input(1),
-            currency_out: SelectedCurrency::default_output(1),
-            search_currency: String::new(),
-            shared_cache: SHARED_CACHE.clone(),
-            quote_result: QuoteResult::default(),
-        }
+    /// The field should be shown as invalid, the text does not parse or the last edit was cut
+    pub fn shows_error(&self) -> bool {
+        self.rejected || (!self.is_empty() && !self.is_valid())
+    }
+
+    pub fn clear(&mut self) {
+        self.raw.clear();
+        self.amount = None;
+        self.rejected = false;
     }
 }
 
+/// A currency that its currently selected in a UI
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SelectedCurrency {
+    pub currency: Currency,
+
+    /// The balance of the wallet in the smallest unit
+    pub balance: U256,
+
+    /// The amount of currency to swap
+    pub amount: AmountInput,
+}
+
 impl SelectedCurrency {
     /// Create a new selected currency from an ERC20Token
     pub fn new_from_erc(token: ERC20Token) -> Self {
-        Self {
-            currency: Currency::new_erc20(token),
-            amount_to_swap: String::new(),
-        }
+        Self::new(Currency::new_erc20(token))
     }
 
     /// Create a new selected currency from a native currency
     pub fn new_from_native(currency: NativeCurrency) -> Self {
-        Self {
-            currency: Currency::new_from_native(currency),
-            amount_to_swap: String::new(),
-        }
+        Self::new(Currency::new_from_native(currency))
     }
 
     /// Create a default input currency based on the chain_id
     pub fn default_input(id: u64) -> Self {
-        Self {
-            currency: Currency::new_native(id),
-            amount_to_swap: String::new(),
-        }
+        Self::new(Currency::new_native(id))
     }
 
     /// Creates a default output currency based on the chain_id
     pub fn default_output(id: u64) -> Self {
+        Self::new(Currency::default_erc20(id).unwrap_or_else(|_| Currency::new_native(id)))
+    }
+
+    fn new(currency: Currency) -> Self {
         Self {
-            currency: Currency::default_erc20(id),
-            amount_to_swap: String::new(),        }
+            currency,
+            balance: U256::ZERO,
+            amount: AmountInput::default(),
+        }
     }
 
     pub fn is_native(&self) -> bool {
@@ -260,15 +424,242 @@ impl SelectedCurrency {
             Currency::Native(currency) => currency.name.clone(),
             Currency::ERC20(erc20) => erc20.name.clone(),
         }
-    
+    }
+
+    /// eg. `1.5000 ETH`
+    pub fn balance_text(&self, precision: usize) -> String {
+        format!(
+            "{} {}",
+            format_units_with_precision(self.balance, self.decimals(), precision),
+            self.currency.symbol()
+        )
+    }
+
+    /// eg. `1.5 ETH`, the text is shown as typed
+    pub fn amount_text(&self) -> String {
+        format!("{} {}", self.amount.raw().trim(), self.currency.symbol())
     }
 }
 
-impl Default for SelectedCurrency {
-    fn default() -> Self {
-        Self {
-            currency: Currency::default(),
-            amount_to_swap: String::new(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_age_is_measured_from_the_latest_block() {
+        let quote = QuoteResult { block_number: 100, ..Default::default() };
+        assert_eq!(quote.block_text(100), "based on block 100 (latest)");
+        assert_eq!(quote.block_text(101), "based on block 100 (1 block old)");
+        assert_eq!(quote.block_text(105), "based on block 100 (5 blocks old)");
+        assert!(!quote.is_stale(100 + STALE_QUOTE_BLOCKS - 1));
+        assert!(quote.is_stale(100 + STALE_QUOTE_BLOCKS));
+        // the oracle can lag behind the block the quote was taken on
+        assert_eq!(quote.blocks_old(99), 0);
+    }
+
+    #[test]
+    fn amount_input_parses_the_text() {
+        let input = AmountInput::new("1.5", 18);
+        assert_eq!(input.raw(), "1.5");
+        assert_eq!(input.amount(), Some(U256::from(1_500_000_000_000_000_000u128)));
+        assert!(input.is_valid());
+
+        let input = AmountInput::new("abc", 18);
+        assert_eq!(input.raw(), "abc");
+        assert_eq!(input.amount(), None);
+        assert!(!input.is_valid() && !input.is_empty());
+
+        let input = AmountInput::new("  ", 6);
+        assert!(input.is_empty() && !input.is_valid());
+        assert_eq!(input.amount_or_zero(), U256::ZERO);
+    }
+
+    #[test]
+    fn amount_input_round_trips() {
+        for (amount, decimals) in [
+            (U256::ZERO, 18),
+            (U256::from(1), 18),
+            (U256::from(1_500_000), 6),
+            (U256::from(123_456_789_000_000_000_000u128), 18),
+            (U256::from(42), 0),
+            (U256::MAX, 18),
+        ] {
+            let formatted = AmountInput::from_amount(amount, decimals);
+            assert_eq!(formatted.amount(), Some(amount));
+
+            let parsed = AmountInput::new(formatted.raw(), decimals);
+            assert_eq!(parsed, formatted, "{} with {} decimals", amount, decimals);
+        }
+
+        for raw in ["1", "0.5", "1234.000001"] {
+            let parsed = AmountInput::new(raw, 6);
+            assert_eq!(AmountInput::from_amount(parsed.amount_or_zero(), 6).raw(), raw);
+        }
+    }
+
+    #[test]
+    fn amount_input_reparses_with_new_decimals() {
+        let mut input = AmountInput::new("2", 6);
+        assert_eq!(input.amount(), Some(U256::from(2_000_000)));
+
+        input.reparse(18);
+        assert_eq!(input.raw(), "2");
+        assert_eq!(input.amount(), Some(U256::from(2_000_000_000_000_000_000u128)));
+
+        input.raw_mut().push('x');
+        input.reparse(18);
+        assert!(!input.is_valid());
+
+        input.clear();
+        assert!(input.is_empty() && input.amount().is_none());
+    }
+
+    #[test]
+    fn amount_input_remembers_its_decimals() {
+        let mut input = AmountInput::new("1.5", 18);
+        assert_eq!(input.decimals(), 18);
+
+        input.reparse(6);
+        assert_eq!((input.decimals(), input.amount()), (6, Some(U256::from(1_500_000))));
+        assert_eq!(AmountInput::from_amount(U256::from(1), 8).decimals(), 8);
+    }
+
+    #[test]
+    fn amounts_are_normalized_as_they_are_typed() {
+        let normalized = |text| normalize_amount(text, 6);
+        assert_eq!(normalized("1.5"), ("1.5".to_string(), false));
+        assert_eq!(normalized("1,5"), ("1.5".to_string(), false));
+        assert_eq!(normalized("0,123"), ("0.123".to_string(), false));
+        assert_eq!(normalized("1."), ("1.".to_string(), false));
+        assert_eq!(normalized(" 1 000 "), ("1000".to_string(), false));
+
+        // pasted with thousands separators
+        assert_eq!(normalized("1,234"), ("1234".to_string(), false));
+        assert_eq!(normalized("1,234,567.25"), ("1234567.25".to_string(), false));
+        assert_eq!(normalized("1.234.567,25"), ("1234567.25".to_string(), false));
+        assert_eq!(normalized("1.234.567"), ("1234567".to_string(), false));
+
+        // dropped characters are reported
+        assert_eq!(normalized("1.2.3"), ("1.23".to_string(), true));
+        assert_eq!(normalized("$12a"), ("12".to_string(), true));
+        assert_eq!(normalized("0.1234567"), ("0.123456".to_string(), true));
+        assert_eq!(normalize_amount("1.5", 0), ("1".to_string(), true));
+    }
+
+    #[test]
+    fn amount_input_shows_an_error_for_rejected_edits() {
+        let mut input = AmountInput::default();
+        assert!(!input.shows_error());
+
+        input.raw_mut().push_str("1,5x");
+        input.edited(18);
+        assert_eq!(input.raw(), "1.5");
+        assert_eq!(input.amount(), Some(U256::from(15u64) * U256::from(10u64).pow(U256::from(17u64))));
+        assert!(input.shows_error());
+
+        input.raw_mut().push('0');
+        input.edited(18);
+        assert!(!input.shows_error());
+
+        input.raw_mut().push_str("0000000000000000000");
+        input.edited(18);
+        assert_eq!(input.raw(), "1.500000000000000000");
+        assert!(input.shows_error());
+
+        input.clear();
+        assert!(!input.shows_error());
+    }
+
+    #[test]
+    fn selected_currency_display() {
+        let mut selected = SelectedCurrency::default_input(1);
+        selected.balance = U256::from(1_500_000_000_000_000_000u128);
+        selected.amount = AmountInput::new(" 0.25 ", selected.decimals());
+
+        assert_eq!(selected.balance_text(2), "1.50 ETH");
+        assert_eq!(selected.amount_text(), "0.25 ETH");
+    }
+
+    #[test]
+    fn six_decimal_tokens_render_in_their_own_decimals() {
+        let usdc = ERC20Token { symbol: "USDC".to_string(), decimals: 6, ..Default::default() };
+        let mut selected = SelectedCurrency::new_from_erc(usdc);
+        selected.balance = U256::from(1_234_567_890u64);
+        selected.amount = AmountInput::new("12.5", selected.decimals());
+
+        // with 18 decimals the balance was 0.0000
+        assert_eq!(selected.balance_text(4), "1234.5678 USDC");
+        assert_eq!(selected.amount_text(), "12.5 USDC");
+        assert_eq!(selected.amount.amount(), Some(U256::from(12_500_000u64)));
+
+        let quote = QuoteResult {
+            output_token: selected,
+            amount_out: U256::from(99_500_000u64),
+            minimum_received: U256::from(99_002_500u64),
+            ..Default::default()
+        };
+        assert_eq!(quote.output_token_amount(), "99.5");
+        assert_eq!(quote.minimum_received_amount(), "99.0025");
+    }
+
+    #[test]
+    fn fees_are_formatted_like_before() {
+        // the old quote showed `format!("{}%", fee as f32 / 10_000.0)`
+        for fee in [100, 500, 2500, 3000, 10000] {
+            assert_eq!(fee_text(fee), format!("{}%", fee as f32 / 10_000.0));
+        }
+        assert_eq!(fee_text(500), "0.05%");
+        assert_eq!(fee_text(2500), "0.25%");
+    }
+
+    #[test]
+    fn slippage_and_tax_in_bps() {
+        assert_eq!(percent_to_bps(0.5), 50);
+        assert_eq!(percent_to_bps(-1.0), 0);
+        assert_eq!(percent_to_bps(150.0), 10_000);
+        assert_eq!(bps_text(50), "0.5%");
+
+        // the old quote showed `format!("{:.2}%", tax * 100.0)`
+        for tax in [0.0512345, 0.1, 0.25, 0.0001] {
+            let quote = QuoteResult { token_tax_bps: Some(share_to_bps(tax)), ..Default::default() };
+            assert_eq!(quote.token_tax_text().unwrap(), format!("{:.2}%", tax * 100.0));
         }
+        assert_eq!(QuoteResult::default().token_tax_text(), None);
+    }
+
+    #[test]
+    fn route_summary() {
+        let hop = |variant, fee| RouteHop { pool: Address::repeat_byte(fee as u8), variant, fee };
+        let mut route = QuoteRoute { dex: "Uniswap".to_string(), hops: vec![hop(PoolVariant::UniswapV3, 500)] };
+        assert_eq!(route.summary(), "Uniswap V3 0.05% · 1 hop");
+        assert_eq!(route.fee_text(), "0.05%");
+
+        route.hops.push(hop(PoolVariant::UniswapV2, 3000));
+        assert_eq!(route.summary(), "Uniswap V3 0.05% > V2 0.3% · 2 hops");
+        assert_eq!(route.fee_text(), "0.05% + 0.3%");
+        assert!(route.uses_pool(Address::repeat_byte(3000u32 as u8)));
+    }
+
+    #[test]
+    fn quotes_survive_serialization() {
+        let quote = QuoteResult {
+            block_number: 20_000_000,
+            input_token: SelectedCurrency::default_input(1),
+            output_token: SelectedCurrency::default_output(1),
+            slippage_bps: 50,
+            amount_out: U256::from(3_000_000_000u64),
+            minimum_received: U256::from(2_985_000_000u64),
+            token_tax_bps: Some(100),
+            gas_used: 120_000,
+            ..Default::default()
+        };
+
+        let json = zeus_chain::serde_json::to_string(&quote).unwrap();
+        let back: QuoteResult = zeus_chain::serde_json::from_str(&json).unwrap();
+        assert_eq!(back.input_token, quote.input_token);
+        assert_eq!(back.output_token, quote.output_token);
+        assert_eq!(back.amount_out, quote.amount_out);
+        assert_eq!(back.minimum_received_amount(), quote.minimum_received_amount());
+        assert_eq!(back.token_tax_text(), quote.token_tax_text());
     }
 }